@@ -1,14 +1,30 @@
 use std::path::PathBuf;
 
 use dh_core::{
-  CoreEngine, ExportFormat, ExportRequest, ExportResult, RecordPage, SearchQuery, SearchResult,
-  RecordMeta, SessionInfo, Task, JsonChildrenPage, JsonPathSegment, JsonNodeSummary,
-  JsonChildrenPageOffset, JsonNodeSummaryOffset,
+  ColumnFilter, CoreEngine, CsvDialect, CsvSchema, CsvWarnings, DedupSpec, DerivedColumn, DiffAlign, DiffPage, ExportFormat, ExportPreset, FileFormat, ExportRequest, ExportResult, GotoRecord, InterruptedExport, NewRecords, ParquetFooterStats, ParquetMetadata, PositionPage, SessionRefresh, RecentFile, RecordCount, RecordPage, RestoredWorkspace, SearchCount, Workspace, DatasetCollection, OpenedCollection, ViewPrefs,
+  SearchQuery, SearchResult, SeekPosition, RecordLabel, RecordMeta, SessionInfo, SessionMetrics, SessionSchema, SortSpec, StorageBackup, StorageLimits, StorageMaintenance, StorageReport, Task, TaskEvent, TaskHistoryEntry, TaskInfo, JsonChildrenPage,
+  JsonChildSort, JsonPathSegment, JsonPathStrings, JsonNodeSummary, JsonChildrenPageOffset, JsonNodeSummaryOffset, JsonNodeStats, JsonTablePage, JsonFindQuery, JsonFindResult,
+  JsonPathResult, JsonValidation, Base64Preview,
+  StatsDiff,
+  StatsReportFormat, StatsResult, StatsSampleStrategy, TextEncoding,
 };
 use serde::{Deserialize, Serialize};
 use std::sync::mpsc;
 use std::path::Path;
 use std::sync::Mutex;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Follow-mode watchers by session id; setting the flag stops the watcher thread.
+pub struct FollowState(pub Mutex<HashMap<String, Arc<AtomicBool>>>);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NewRecordsPayload {
+  pub session_id: String,
+  #[serde(flatten)]
+  pub update: NewRecords,
+}
 
 /// Pending paths opened by the OS (e.g. double-click associated files on macOS).
 ///
@@ -174,6 +190,7 @@ fn scan_dir_inner(
 
 #[tauri::command]
 pub fn scan_folder_tree(
+  engine: tauri::State<'_, CoreEngine>,
   path: String,
   max_depth: Option<u32>,
   max_nodes: Option<u32>,
@@ -199,6 +216,7 @@ pub fn scan_folder_tree(
   // Root is a directory node (counts as 1).
   nodes_used += 1;
   let children = scan_dir_inner(&p, 0, max_depth, max_nodes, &mut nodes_used, &mut truncated);
+  let _ = engine.touch_recent_folder(&path);
 
   Ok(FolderTreeResponse {
     root: FsNode {
@@ -231,7 +249,11 @@ pub async fn open_file(
   engine: tauri::State<'_, CoreEngine>,
   path: String,
   request_id: Option<String>,
+  encoding: Option<TextEncoding>,
+  resume: Option<bool>,
+  decryption_key: Option<String>,
 ) -> Result<OpenFileResponse, String> {
+  let resume = resume.unwrap_or(false);
   let request_id = request_id.unwrap_or_else(|| "default".to_string());
   let engine = engine.inner().clone();
 
@@ -242,8 +264,13 @@ pub async fn open_file(
 
   if !enable_progress {
     let worker = tauri::async_runtime::spawn_blocking(move || {
-      let (session, first_page) = engine.open_file(path).map_err(|e| e.to_string())?;
-      Ok::<_, String>((session, first_page))
+      let (session, first_page) = match &decryption_key {
+        Some(key) => engine.open_file_with_key(path, key, |_| {}),
+        None => engine.open_file(path),
+      }
+      .map_err(|e| e.to_string())?;
+      let (session, first_page) = with_encoding(&engine, session, first_page, encoding)?;
+      with_resume(&engine, session, first_page, resume)
     });
     let (session, first_page) = worker
       .await
@@ -264,21 +291,25 @@ pub async fn open_file(
   let tx2 = tx.clone();
   let worker = tauri::async_runtime::spawn_blocking(move || {
     let mut last_pct: u8 = 255;
-    let (session, first_page) = engine
-      .open_file_with_progress(path2, |pct| {
-        // throttle by pct step
-        if pct == last_pct {
-          return;
-        }
-        last_pct = pct;
-        let _ = tx2.send(OpenFileProgressPayload {
-          request_id: request_id2.clone(),
-          pct_0_100: pct,
-          stage: "载入中".into(),
-        });
-      })
-      .map_err(|e| e.to_string())?;
-    Ok::<_, String>((session, first_page))
+    let on_progress = |pct| {
+      // throttle by pct step
+      if pct == last_pct {
+        return;
+      }
+      last_pct = pct;
+      let _ = tx2.send(OpenFileProgressPayload {
+        request_id: request_id2.clone(),
+        pct_0_100: pct,
+        stage: "载入中".into(),
+      });
+    };
+    let (session, first_page) = match &decryption_key {
+      Some(key) => engine.open_file_with_key(path2, key, on_progress),
+      None => engine.open_file_with_progress(path2, on_progress),
+    }
+    .map_err(|e| e.to_string())?;
+    let (session, first_page) = with_encoding(&engine, session, first_page, encoding)?;
+    with_resume(&engine, session, first_page, resume)
   });
 
   let (session, first_page) = worker
@@ -292,16 +323,318 @@ pub async fn open_file(
   Ok(OpenFileResponse { session, first_page })
 }
 
+/// Applies an `open_file` encoding override, re-reading the first page.
+fn with_encoding(
+  engine: &CoreEngine,
+  mut session: SessionInfo,
+  first_page: RecordPage,
+  encoding: Option<TextEncoding>,
+) -> Result<(SessionInfo, RecordPage), String> {
+  match encoding {
+    Some(encoding) if encoding != session.encoding => {
+      let first_page = engine
+        .set_session_encoding(&session.session_id, encoding)
+        .map_err(|e| e.to_string())?;
+      session.encoding = encoding;
+      Ok((session, first_page))
+    }
+    _ => Ok((session, first_page)),
+  }
+}
+
+/// Applies `open_file`'s `resume`: the first page becomes the one the user left off at.
+fn with_resume(
+  engine: &CoreEngine,
+  session: SessionInfo,
+  first_page: RecordPage,
+  resume: bool,
+) -> Result<(SessionInfo, RecordPage), String> {
+  if !resume || session.read_position.is_none() {
+    return Ok((session, first_page));
+  }
+  let page_size = first_page.records.len().max(1);
+  let page = engine
+    .page_at_read_position(&session.session_id, page_size)
+    .map_err(|e| e.to_string())?;
+  Ok((session, page))
+}
+
+#[tauri::command]
+pub async fn page_at_read_position(
+  engine: tauri::State<'_, CoreEngine>,
+  session_id: String,
+  page_size: usize,
+) -> Result<RecordPage, String> {
+  let engine = engine.inner().clone();
+  tauri::async_runtime::spawn_blocking(move || {
+    engine.page_at_read_position(&session_id, page_size).map_err(|e| e.to_string())
+  })
+  .await
+  .map_err(|e| format!("page_at_read_position task join error: {e}"))?
+}
+
+#[tauri::command]
+pub async fn set_session_encoding(
+  engine: tauri::State<'_, CoreEngine>,
+  session_id: String,
+  encoding: TextEncoding,
+) -> Result<RecordPage, String> {
+  let engine = engine.inner().clone();
+  tauri::async_runtime::spawn_blocking(move || {
+    engine.set_session_encoding(&session_id, encoding).map_err(|e| e.to_string())
+  })
+  .await
+  .map_err(|e| format!("set_session_encoding task join error: {e}"))?
+}
+
+#[tauri::command]
+pub async fn set_csv_dialect(
+  engine: tauri::State<'_, CoreEngine>,
+  session_id: String,
+  dialect: CsvDialect,
+) -> Result<RecordPage, String> {
+  let engine = engine.inner().clone();
+  tauri::async_runtime::spawn_blocking(move || {
+    engine.set_csv_dialect(&session_id, dialect).map_err(|e| e.to_string())
+  })
+  .await
+  .map_err(|e| format!("set_csv_dialect task join error: {e}"))?
+}
+
+#[tauri::command]
+pub async fn set_json_lenient(
+  engine: tauri::State<'_, CoreEngine>,
+  session_id: String,
+  lenient: bool,
+) -> Result<RecordPage, String> {
+  let engine = engine.inner().clone();
+  tauri::async_runtime::spawn_blocking(move || {
+    engine.set_json_lenient(&session_id, lenient).map_err(|e| e.to_string())
+  })
+  .await
+  .map_err(|e| format!("set_json_lenient task join error: {e}"))?
+}
+
+#[tauri::command]
+pub async fn set_derived_columns(
+  engine: tauri::State<'_, CoreEngine>,
+  session_id: String,
+  columns: Vec<DerivedColumn>,
+) -> Result<RecordPage, String> {
+  let engine = engine.inner().clone();
+  tauri::async_runtime::spawn_blocking(move || {
+    engine.set_derived_columns(&session_id, columns).map_err(|e| e.to_string())
+  })
+  .await
+  .map_err(|e| format!("set_derived_columns task join error: {e}"))?
+}
+
+#[tauri::command]
+pub async fn set_session_columns(
+  engine: tauri::State<'_, CoreEngine>,
+  session_id: String,
+  columns: Option<Vec<String>>,
+) -> Result<RecordPage, String> {
+  let engine = engine.inner().clone();
+  tauri::async_runtime::spawn_blocking(move || {
+    engine.set_session_columns(&session_id, columns).map_err(|e| e.to_string())
+  })
+  .await
+  .map_err(|e| format!("set_session_columns task join error: {e}"))?
+}
+
+#[tauri::command]
+pub async fn set_session_filters(
+  engine: tauri::State<'_, CoreEngine>,
+  session_id: String,
+  filters: Option<Vec<ColumnFilter>>,
+) -> Result<RecordPage, String> {
+  let engine = engine.inner().clone();
+  tauri::async_runtime::spawn_blocking(move || {
+    engine.set_session_filters(&session_id, filters).map_err(|e| e.to_string())
+  })
+  .await
+  .map_err(|e| format!("set_session_filters task join error: {e}"))?
+}
+
+#[tauri::command]
+pub async fn open_files(
+  engine: tauri::State<'_, CoreEngine>,
+  paths: Vec<String>,
+) -> Result<OpenFileResponse, String> {
+  let engine = engine.inner().clone();
+  let paths: Vec<PathBuf> = paths.into_iter().map(PathBuf::from).collect();
+  let worker = tauri::async_runtime::spawn_blocking(move || {
+    let (session, first_page) = engine.open_files(&paths).map_err(|e| e.to_string())?;
+    Ok::<_, String>((session, first_page))
+  });
+  let (session, first_page) = worker
+    .await
+    .map_err(|e| format!("open_files task join error: {e}"))??;
+  Ok(OpenFileResponse { session, first_page })
+}
+
+/// "Open from pipe": the app's stdin (`some_tool | datalens -`) as a JSONL / CSV session.
+#[tauri::command]
+pub async fn open_stdin(
+  engine: tauri::State<'_, CoreEngine>,
+  format: FileFormat,
+) -> Result<OpenFileResponse, String> {
+  let engine = engine.inner().clone();
+  let (session, first_page) = tauri::async_runtime::spawn_blocking(move || {
+    engine.open_stream(std::io::stdin(), format).map_err(|e| e.to_string())
+  })
+  .await
+  .map_err(|e| format!("open_stdin task join error: {e}"))??;
+  Ok(OpenFileResponse { session, first_page })
+}
+
+#[tauri::command]
+pub async fn open_search_results(
+  engine: tauri::State<'_, CoreEngine>,
+  session_id: String,
+  task_id: String,
+) -> Result<OpenFileResponse, String> {
+  let engine = engine.inner().clone();
+  let worker = tauri::async_runtime::spawn_blocking(move || {
+    let (session, first_page) = engine
+      .open_search_results(&session_id, &task_id)
+      .map_err(|e| e.to_string())?;
+    Ok::<_, String>((session, first_page))
+  });
+  let (session, first_page) = worker
+    .await
+    .map_err(|e| format!("open_search_results task join error: {e}"))??;
+  Ok(OpenFileResponse { session, first_page })
+}
+
+#[tauri::command]
+pub async fn open_sorted_view(
+  engine: tauri::State<'_, CoreEngine>,
+  session_id: String,
+  sort: SortSpec,
+) -> Result<OpenFileResponse, String> {
+  let engine = engine.inner().clone();
+  let worker = tauri::async_runtime::spawn_blocking(move || {
+    let (session, first_page) = engine
+      .open_sorted_view(&session_id, sort)
+      .map_err(|e| e.to_string())?;
+    Ok::<_, String>((session, first_page))
+  });
+  let (session, first_page) = worker
+    .await
+    .map_err(|e| format!("open_sorted_view task join error: {e}"))??;
+  Ok(OpenFileResponse { session, first_page })
+}
+
+#[tauri::command]
+pub async fn open_dedup_view(
+  engine: tauri::State<'_, CoreEngine>,
+  session_id: String,
+  dedup: DedupSpec,
+) -> Result<OpenFileResponse, String> {
+  let engine = engine.inner().clone();
+  let worker = tauri::async_runtime::spawn_blocking(move || {
+    let (session, first_page) = engine
+      .open_dedup_view(&session_id, dedup)
+      .map_err(|e| e.to_string())?;
+    Ok::<_, String>((session, first_page))
+  });
+  let (session, first_page) = worker
+    .await
+    .map_err(|e| format!("open_dedup_view task join error: {e}"))??;
+  Ok(OpenFileResponse { session, first_page })
+}
+
 #[tauri::command]
 pub fn next_page(
   engine: tauri::State<'_, CoreEngine>,
   session_id: String,
   cursor: Option<String>,
   page_size: Option<u32>,
+  columns: Option<Vec<String>>,
+) -> Result<RecordPage, String> {
+  let page_size = page_size.unwrap_or(0) as usize;
+  engine
+    .next_page_with_columns(&session_id, cursor.as_deref(), page_size, columns.as_deref())
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn page_at(
+  engine: tauri::State<'_, CoreEngine>,
+  session_id: String,
+  record_index: u64,
+  page_size: Option<u32>,
 ) -> Result<RecordPage, String> {
   let page_size = page_size.unwrap_or(0) as usize;
+  let engine = engine.inner().clone();
+  tauri::async_runtime::spawn_blocking(move || {
+    engine
+      .page_at(&session_id, record_index, page_size)
+      .map_err(|e| e.to_string())
+  })
+  .await
+  .map_err(|e| format!("page_at task join error: {e}"))?
+}
+
+#[tauri::command]
+pub async fn goto_record(
+  engine: tauri::State<'_, CoreEngine>,
+  session_id: String,
+  line_no: u64,
+  page_size: Option<u32>,
+) -> Result<GotoRecord, String> {
+  let page_size = page_size.unwrap_or(0) as usize;
+  let engine = engine.inner().clone();
+  tauri::async_runtime::spawn_blocking(move || {
+    engine
+      .goto_record(&session_id, line_no, page_size)
+      .map_err(|e| e.to_string())
+  })
+  .await
+  .map_err(|e| format!("goto_record task join error: {e}"))?
+}
+
+#[tauri::command]
+pub async fn page_at_position(
+  engine: tauri::State<'_, CoreEngine>,
+  session_id: String,
+  position: SeekPosition,
+  page_size: Option<u32>,
+) -> Result<PositionPage, String> {
+  let page_size = page_size.unwrap_or(0) as usize;
+  let engine = engine.inner().clone();
+  tauri::async_runtime::spawn_blocking(move || {
+    engine
+      .page_at_position(&session_id, position, page_size)
+      .map_err(|e| e.to_string())
+  })
+  .await
+  .map_err(|e| format!("page_at_position task join error: {e}"))?
+}
+
+#[tauri::command]
+pub fn set_record_label(
+  engine: tauri::State<'_, CoreEngine>,
+  session_id: String,
+  meta: RecordMeta,
+  tags: Vec<String>,
+  note: Option<String>,
+) -> Result<Option<RecordLabel>, String> {
+  engine
+    .set_record_label(&session_id, meta, tags, note)
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn list_record_labels(
+  engine: tauri::State<'_, CoreEngine>,
+  session_id: String,
+  tag: Option<String>,
+) -> Result<Vec<RecordLabel>, String> {
   engine
-    .next_page(&session_id, cursor.as_deref(), page_size)
+    .list_record_labels(&session_id, tag.as_deref())
     .map_err(|e| e.to_string())
 }
 
@@ -340,6 +673,122 @@ pub fn get_record_raw(
   res
 }
 
+#[tauri::command]
+pub async fn get_cell_raw(
+  engine: tauri::State<'_, CoreEngine>,
+  session_id: String,
+  meta: RecordMeta,
+  column: String,
+) -> Result<String, String> {
+  let engine = engine.inner().clone();
+  tauri::async_runtime::spawn_blocking(move || {
+    engine
+      .get_cell_raw(&session_id, meta, &column)
+      .map_err(|e| e.to_string())
+  })
+  .await
+  .map_err(|e| format!("get_cell_raw task join error: {e}"))?
+}
+
+#[tauri::command]
+pub async fn refresh_session(
+  engine: tauri::State<'_, CoreEngine>,
+  session_id: String,
+) -> Result<SessionRefresh, String> {
+  let engine = engine.inner().clone();
+  tauri::async_runtime::spawn_blocking(move || {
+    engine
+      .refresh_session(&session_id)
+      .map_err(|e| e.to_string())
+  })
+  .await
+  .map_err(|e| format!("refresh_session task join error: {e}"))?
+}
+
+#[tauri::command]
+pub async fn reload_session(
+  engine: tauri::State<'_, CoreEngine>,
+  session_id: String,
+) -> Result<OpenFileResponse, String> {
+  let engine = engine.inner().clone();
+  let (session, first_page) = tauri::async_runtime::spawn_blocking(move || {
+    engine.reload_session(&session_id).map_err(|e| e.to_string())
+  })
+  .await
+  .map_err(|e| format!("reload_session task join error: {e}"))??;
+  Ok(OpenFileResponse { session, first_page })
+}
+
+#[tauri::command]
+pub fn poll_new_records(
+  engine: tauri::State<'_, CoreEngine>,
+  session_id: String,
+  max_records: Option<u32>,
+) -> Result<NewRecords, String> {
+  let max_records = max_records.unwrap_or(0) as usize;
+  engine
+    .poll_new_records(&session_id, max_records)
+    .map_err(|e| e.to_string())
+}
+
+/// Start watching a session's file: appended records are pushed as `new_records` events until
+/// `unfollow_file` (or the session closes).
+#[tauri::command]
+pub fn follow_file(
+  window: tauri::Window,
+  engine: tauri::State<'_, CoreEngine>,
+  follow: tauri::State<'_, FollowState>,
+  session_id: String,
+  interval_ms: Option<u64>,
+) -> Result<(), String> {
+  // Fail fast for sessions / formats without follow support.
+  let first = engine
+    .poll_new_records(&session_id, 0)
+    .map_err(|e| e.to_string())?;
+
+  let stop = Arc::new(AtomicBool::new(false));
+  {
+    let mut guard = follow.0.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(prev) = guard.insert(session_id.clone(), stop.clone()) {
+      prev.store(true, Ordering::SeqCst);
+    }
+  }
+
+  let engine = engine.inner().clone();
+  let interval = std::time::Duration::from_millis(interval_ms.unwrap_or(500).max(50));
+  std::thread::spawn(move || {
+    let mut update = Ok(first);
+    while !stop.load(Ordering::SeqCst) {
+      let Ok(u) = update else {
+        break; // session closed
+      };
+      let more = u.more_available;
+      if !u.records.is_empty() || u.reset {
+        let _ = window.emit(
+          "new_records",
+          NewRecordsPayload {
+            session_id: session_id.clone(),
+            update: u,
+          },
+        );
+      }
+      if !more {
+        std::thread::sleep(interval);
+      }
+      update = engine.poll_new_records(&session_id, 500);
+    }
+  });
+  Ok(())
+}
+
+#[tauri::command]
+pub fn unfollow_file(follow: tauri::State<'_, FollowState>, session_id: String) {
+  let mut guard = follow.0.lock().unwrap_or_else(|e| e.into_inner());
+  if let Some(stop) = guard.remove(&session_id) {
+    stop.store(true, Ordering::SeqCst);
+  }
+}
+
 #[tauri::command]
 pub fn search(
   engine: tauri::State<'_, CoreEngine>,
@@ -349,6 +798,31 @@ pub fn search(
   engine.search(&session_id, query).map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+pub fn list_sessions(engine: tauri::State<'_, CoreEngine>) -> Vec<SessionInfo> {
+  engine.list_sessions()
+}
+
+#[tauri::command]
+pub fn close_session(engine: tauri::State<'_, CoreEngine>, session_id: String) -> Result<(), String> {
+  engine.close_session(&session_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn count_records(engine: tauri::State<'_, CoreEngine>, session_id: String) -> Result<RecordCount, String> {
+  engine.count_records(&session_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn session_metrics(engine: tauri::State<'_, CoreEngine>, session_id: String) -> Result<SessionMetrics, String> {
+  engine.session_metrics(&session_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn csv_warnings(engine: tauri::State<'_, CoreEngine>, session_id: String) -> Result<CsvWarnings, String> {
+  engine.csv_warnings(&session_id).map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub fn get_task(engine: tauri::State<'_, CoreEngine>, task_id: String) -> Result<Task, String> {
   engine.get_task(&task_id).map_err(|e| e.to_string())
@@ -367,12 +841,196 @@ pub fn search_task_hits_page(
     .map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+pub fn search_match_count(engine: tauri::State<'_, CoreEngine>, task_id: String) -> Result<SearchCount, String> {
+  engine.search_match_count(&task_id).map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub fn cancel_task(engine: tauri::State<'_, CoreEngine>, task_id: String) -> Result<(), String> {
   engine.cancel_task(&task_id).map_err(|e| e.to_string())
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[tauri::command]
+pub fn pause_task(engine: tauri::State<'_, CoreEngine>, task_id: String) -> Result<(), String> {
+  engine.pause_task(&task_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn resume_task(engine: tauri::State<'_, CoreEngine>, task_id: String) -> Result<(), String> {
+  engine.resume_task(&task_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn max_concurrent_tasks(engine: tauri::State<'_, CoreEngine>) -> usize {
+  engine.max_concurrent_tasks()
+}
+
+#[tauri::command]
+pub fn set_max_concurrent_tasks(engine: tauri::State<'_, CoreEngine>, max: u32) -> Result<(), String> {
+  engine.set_max_concurrent_tasks(max as usize).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn save_view_prefs(engine: tauri::State<'_, CoreEngine>, session_id: String, prefs: ViewPrefs) -> Result<(), String> {
+  engine.save_view_prefs(&session_id, prefs).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn save_workspace(
+  engine: tauri::State<'_, CoreEngine>,
+  session_ids: Vec<String>,
+  active_session_id: Option<String>,
+) -> Result<Workspace, String> {
+  engine
+    .save_workspace(&session_ids, active_session_id.as_deref())
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn restore_workspace(engine: tauri::State<'_, CoreEngine>) -> Result<RestoredWorkspace, String> {
+  engine.restore_workspace().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn save_collection(
+  engine: tauri::State<'_, CoreEngine>,
+  name: String,
+  paths: Vec<String>,
+) -> Result<DatasetCollection, String> {
+  engine.save_collection(&name, paths).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn list_collections(engine: tauri::State<'_, CoreEngine>) -> Result<Vec<DatasetCollection>, String> {
+  engine.list_collections().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn delete_collection(engine: tauri::State<'_, CoreEngine>, name: String) -> Result<(), String> {
+  engine.delete_collection(&name).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn open_collection(engine: tauri::State<'_, CoreEngine>, name: String) -> Result<OpenedCollection, String> {
+  engine.open_collection(&name).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn list_recent(engine: tauri::State<'_, CoreEngine>, limit: Option<u32>) -> Result<Vec<RecentFile>, String> {
+  let limit = limit.unwrap_or(50) as usize;
+  engine.list_recent(limit).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn pin_recent(engine: tauri::State<'_, CoreEngine>, path: String, pinned: bool) -> Result<(), String> {
+  engine.pin_recent(&path, pinned).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn remove_recent(engine: tauri::State<'_, CoreEngine>, path: String) -> Result<(), String> {
+  engine.remove_recent(&path).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn clear_recent(engine: tauri::State<'_, CoreEngine>) -> Result<(), String> {
+  engine.clear_recent().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn prune_recent(engine: tauri::State<'_, CoreEngine>) -> Result<usize, String> {
+  engine.prune_recent().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn list_recent_folders(
+  engine: tauri::State<'_, CoreEngine>,
+  limit: Option<u32>,
+) -> Result<Vec<RecentFile>, String> {
+  let limit = limit.unwrap_or(50) as usize;
+  engine.list_recent_folders(limit).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn pin_recent_folder(engine: tauri::State<'_, CoreEngine>, path: String, pinned: bool) -> Result<(), String> {
+  engine.pin_recent_folder(&path, pinned).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn remove_recent_folder(engine: tauri::State<'_, CoreEngine>, path: String) -> Result<(), String> {
+  engine.remove_recent_folder(&path).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn list_task_history(
+  engine: tauri::State<'_, CoreEngine>,
+  limit: Option<u32>,
+) -> Result<Vec<TaskHistoryEntry>, String> {
+  let limit = limit.unwrap_or(100) as usize;
+  engine.list_task_history(limit).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn list_export_history(
+  engine: tauri::State<'_, CoreEngine>,
+  limit: Option<u32>,
+) -> Result<Vec<TaskHistoryEntry>, String> {
+  let limit = limit.unwrap_or(100) as usize;
+  engine.list_export_history(limit).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn rerun_export(engine: tauri::State<'_, CoreEngine>, history_id: i64) -> Result<ExportResult, String> {
+  engine.rerun_export(history_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn storage_report(engine: tauri::State<'_, CoreEngine>) -> Result<StorageReport, String> {
+  engine.storage_report().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn maintain_storage(
+  engine: tauri::State<'_, CoreEngine>,
+  limits: Option<StorageLimits>,
+) -> Result<StorageMaintenance, String> {
+  let engine = engine.inner().clone();
+  tauri::async_runtime::spawn_blocking(move || engine.maintain_storage(limits).map_err(|e| e.to_string()))
+    .await
+    .map_err(|e| format!("maintain_storage task join error: {e}"))?
+}
+
+#[tauri::command]
+pub fn backup_storage(engine: tauri::State<'_, CoreEngine>, output_path: String) -> Result<StorageBackup, String> {
+  engine.backup_storage(output_path).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn restore_storage(engine: tauri::State<'_, CoreEngine>, input_path: String) -> Result<StorageBackup, String> {
+  engine.restore_storage(input_path).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn list_task_events(engine: tauri::State<'_, CoreEngine>, task_id: String) -> Result<Vec<TaskEvent>, String> {
+  engine.list_task_events(&task_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn list_interrupted_exports(engine: tauri::State<'_, CoreEngine>) -> Result<Vec<InterruptedExport>, String> {
+  engine.list_interrupted_exports().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn dismiss_interrupted_export(engine: tauri::State<'_, CoreEngine>, id: i64) -> Result<(), String> {
+  engine.dismiss_interrupted_export(id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn clear_task_history(engine: tauri::State<'_, CoreEngine>) -> Result<(), String> {
+  engine.clear_task_history().map_err(|e| e.to_string())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExportArgs {
   pub session_id: String,
   pub request: ExportRequest,
@@ -389,6 +1047,317 @@ pub fn export(engine: tauri::State<'_, CoreEngine>, args: ExportArgs) -> Result<
     .map_err(|e| e.to_string())
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportWithPresetArgs {
+  pub session_id: String,
+  pub request: ExportRequest,
+  pub preset: String,
+  /// output file path; defaults to the preset's destination
+  pub output_path: Option<String>,
+}
+
+#[tauri::command]
+pub fn export_with_preset(
+  engine: tauri::State<'_, CoreEngine>,
+  args: ExportWithPresetArgs,
+) -> Result<ExportResult, String> {
+  engine
+    .export_with_preset(&args.session_id, args.request, &args.preset, args.output_path.as_deref())
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn save_export_preset(engine: tauri::State<'_, CoreEngine>, preset: ExportPreset) -> Result<ExportPreset, String> {
+  engine.save_export_preset(preset).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn list_export_presets(engine: tauri::State<'_, CoreEngine>) -> Result<Vec<ExportPreset>, String> {
+  engine.list_export_presets().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn delete_export_preset(engine: tauri::State<'_, CoreEngine>, name: String) -> Result<(), String> {
+  engine.delete_export_preset(&name).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_schema(
+  engine: tauri::State<'_, CoreEngine>,
+  session_id: String,
+) -> Result<SessionSchema, String> {
+  let engine = engine.inner().clone();
+  tauri::async_runtime::spawn_blocking(move || engine.get_schema(&session_id).map_err(|e| e.to_string()))
+    .await
+    .map_err(|e| format!("get_schema task join error: {e}"))?
+}
+
+#[tauri::command]
+pub async fn csv_schema(engine: tauri::State<'_, CoreEngine>, path: String) -> Result<CsvSchema, String> {
+  let engine = engine.inner().clone();
+  tauri::async_runtime::spawn_blocking(move || engine.csv_schema(&path).map_err(|e| e.to_string()))
+    .await
+    .map_err(|e| format!("csv_schema task join error: {e}"))?
+}
+
+#[tauri::command]
+pub async fn parquet_metadata(
+  engine: tauri::State<'_, CoreEngine>,
+  session_id: String,
+) -> Result<ParquetMetadata, String> {
+  let engine = engine.inner().clone();
+  tauri::async_runtime::spawn_blocking(move || engine.parquet_metadata(&session_id).map_err(|e| e.to_string()))
+    .await
+    .map_err(|e| format!("parquet_metadata task join error: {e}"))?
+}
+
+#[tauri::command]
+pub async fn parquet_footer_stats(
+  engine: tauri::State<'_, CoreEngine>,
+  session_id: String,
+) -> Result<ParquetFooterStats, String> {
+  let engine = engine.inner().clone();
+  tauri::async_runtime::spawn_blocking(move || engine.parquet_footer_stats(&session_id).map_err(|e| e.to_string()))
+    .await
+    .map_err(|e| format!("parquet_footer_stats task join error: {e}"))?
+}
+
+#[tauri::command]
+pub async fn get_stats(
+  engine: tauri::State<'_, CoreEngine>,
+  session_id: String,
+) -> Result<StatsResult, String> {
+  let engine = engine.inner().clone();
+  tauri::async_runtime::spawn_blocking(move || engine.get_stats(&session_id).map_err(|e| e.to_string()))
+    .await
+    .map_err(|e| format!("get_stats task join error: {e}"))?
+}
+
+#[tauri::command]
+pub fn start_stats_task(
+  engine: tauri::State<'_, CoreEngine>,
+  session_id: String,
+  timeout_ms: Option<u64>,
+) -> Result<TaskInfo, String> {
+  engine
+    .start_stats_task_with_timeout(&session_id, timeout_ms)
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn stats_task_result(engine: tauri::State<'_, CoreEngine>, task_id: String) -> Result<StatsResult, String> {
+  engine.stats_task_result(&task_id).map_err(|e| e.to_string())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuickStatsArgs {
+  pub session_id: String,
+  pub strategy: StatsSampleStrategy,
+  /// number of records to sample
+  pub sample_size: u64,
+}
+
+#[tauri::command]
+pub async fn quick_stats(
+  engine: tauri::State<'_, CoreEngine>,
+  args: QuickStatsArgs,
+) -> Result<StatsResult, String> {
+  let engine = engine.inner().clone();
+  tauri::async_runtime::spawn_blocking(move || {
+    engine
+      .quick_stats(&args.session_id, args.strategy, args.sample_size)
+      .map_err(|e| e.to_string())
+  })
+  .await
+  .map_err(|e| format!("quick_stats task join error: {e}"))?
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompareStatsArgs {
+  /// baseline ("before") session
+  pub left_session_id: String,
+  /// candidate ("after") session
+  pub right_session_id: String,
+}
+
+#[tauri::command]
+pub async fn compare_stats(
+  engine: tauri::State<'_, CoreEngine>,
+  args: CompareStatsArgs,
+) -> Result<StatsDiff, String> {
+  let engine = engine.inner().clone();
+  tauri::async_runtime::spawn_blocking(move || {
+    engine
+      .compare_stats(&args.left_session_id, &args.right_session_id)
+      .map_err(|e| e.to_string())
+  })
+  .await
+  .map_err(|e| format!("compare_stats task join error: {e}"))?
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StartDiffTaskArgs {
+  /// baseline ("before") session
+  pub left_session_id: String,
+  /// candidate ("after") session
+  pub right_session_id: String,
+  pub align: DiffAlign,
+}
+
+#[tauri::command]
+pub fn start_diff_task(engine: tauri::State<'_, CoreEngine>, args: StartDiffTaskArgs) -> Result<TaskInfo, String> {
+  engine
+    .start_diff_task(&args.left_session_id, &args.right_session_id, args.align)
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn diff_task_page(
+  engine: tauri::State<'_, CoreEngine>,
+  task_id: String,
+  cursor: Option<String>,
+  page_size: Option<u32>,
+) -> Result<DiffPage, String> {
+  let page_size = page_size.unwrap_or(0) as usize;
+  engine
+    .diff_task_page(&task_id, cursor.as_deref(), page_size)
+    .map_err(|e| e.to_string())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SaveRecordEditArgs {
+  pub session_id: String,
+  pub meta: RecordMeta,
+  /// edited record text (JSON; CSV: the raw object or a CSV row)
+  pub body: String,
+  /// output file path (must not be the session file)
+  pub output_path: String,
+}
+
+#[tauri::command]
+pub async fn save_record_edit(
+  engine: tauri::State<'_, CoreEngine>,
+  args: SaveRecordEditArgs,
+) -> Result<ExportResult, String> {
+  let engine = engine.inner().clone();
+  tauri::async_runtime::spawn_blocking(move || {
+    engine
+      .save_record_edit(&args.session_id, args.meta, &args.body, PathBuf::from(args.output_path))
+      .map_err(|e| e.to_string())
+  })
+  .await
+  .map_err(|e| format!("save_record_edit task join error: {e}"))?
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SaveJsonNodeEditArgs {
+  pub session_id: String,
+  pub meta: RecordMeta,
+  /// node to replace; `path` (under the record) is used when missing
+  #[serde(default)]
+  pub node_offset: Option<u64>,
+  #[serde(default)]
+  pub path: Vec<JsonPathSegment>,
+  /// replacement JSON text
+  pub body: String,
+  /// output file path (must not be the session file)
+  pub output_path: String,
+}
+
+#[tauri::command]
+pub async fn save_json_node_edit(
+  engine: tauri::State<'_, CoreEngine>,
+  args: SaveJsonNodeEditArgs,
+) -> Result<ExportResult, String> {
+  let engine = engine.inner().clone();
+  tauri::async_runtime::spawn_blocking(move || {
+    engine
+      .save_json_node_edit(
+        &args.session_id,
+        args.meta,
+        args.node_offset,
+        &args.path,
+        &args.body,
+        PathBuf::from(args.output_path),
+      )
+      .map_err(|e| e.to_string())
+  })
+  .await
+  .map_err(|e| format!("save_json_node_edit task join error: {e}"))?
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WritePrettyRecordArgs {
+  pub session_id: String,
+  pub meta: RecordMeta,
+}
+
+#[tauri::command]
+pub async fn write_pretty_record(
+  engine: tauri::State<'_, CoreEngine>,
+  args: WritePrettyRecordArgs,
+) -> Result<ExportResult, String> {
+  let engine = engine.inner().clone();
+  tauri::async_runtime::spawn_blocking(move || {
+    engine.write_pretty_record(&args.session_id, args.meta).map_err(|e| e.to_string())
+  })
+  .await
+  .map_err(|e| format!("write_pretty_record task join error: {e}"))?
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SaveParquetBlobArgs {
+  pub session_id: String,
+  pub record_index: u64,
+  /// BLOB column name
+  pub column: String,
+  /// output file path
+  pub output_path: String,
+}
+
+#[tauri::command]
+pub async fn save_parquet_blob(
+  engine: tauri::State<'_, CoreEngine>,
+  args: SaveParquetBlobArgs,
+) -> Result<ExportResult, String> {
+  let engine = engine.inner().clone();
+  tauri::async_runtime::spawn_blocking(move || {
+    engine
+      .save_parquet_blob(&args.session_id, args.record_index, &args.column, PathBuf::from(args.output_path))
+      .map_err(|e| e.to_string())
+  })
+  .await
+  .map_err(|e| format!("save_parquet_blob task join error: {e}"))?
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportStatsReportArgs {
+  pub session_id: String,
+  pub format: StatsReportFormat,
+  /// output file path
+  pub output_path: String,
+}
+
+#[tauri::command]
+pub async fn export_stats_report(
+  engine: tauri::State<'_, CoreEngine>,
+  args: ExportStatsReportArgs,
+) -> Result<ExportResult, String> {
+  let engine = engine.inner().clone();
+  tauri::async_runtime::spawn_blocking(move || {
+    engine
+      .export_stats_report(&args.session_id, args.format, PathBuf::from(args.output_path))
+      .map_err(|e| e.to_string())
+  })
+  .await
+  .map_err(|e| format!("export_stats_report task join error: {e}"))?
+}
+
+#[tauri::command]
+pub fn json_path_strings(engine: tauri::State<'_, CoreEngine>, path: Vec<JsonPathSegment>) -> JsonPathStrings {
+  engine.json_path_strings(&path)
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct JsonListChildrenArgs {
   pub session_id: String,
@@ -396,6 +1365,8 @@ pub struct JsonListChildrenArgs {
   pub path: Vec<JsonPathSegment>,
   pub cursor: Option<u64>,
   pub limit: Option<u32>,
+  #[serde(default)]
+  pub sort: JsonChildSort,
 }
 
 #[tauri::command]
@@ -405,7 +1376,7 @@ pub fn json_list_children(
 ) -> Result<JsonChildrenPage, String> {
   let limit = args.limit.unwrap_or(50) as usize;
   engine
-    .json_list_children(&args.session_id, args.meta, args.path, args.cursor, limit)
+    .json_list_children(&args.session_id, args.meta, args.path, args.cursor, limit, args.sort)
     .map_err(|e| e.to_string())
 }
 
@@ -436,6 +1407,8 @@ pub struct JsonListChildrenAtOffsetArgs {
   pub cursor_offset: Option<u64>,
   pub cursor_index: Option<u64>,
   pub limit: Option<u32>,
+  #[serde(default)]
+  pub sort: JsonChildSort,
 }
 
 #[tauri::command]
@@ -452,6 +1425,39 @@ pub fn json_list_children_at_offset(
       args.cursor_offset,
       args.cursor_index,
       limit,
+      args.sort,
+    )
+    .map_err(|e| e.to_string())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsonArrayTableArgs {
+  pub session_id: String,
+  pub meta: RecordMeta,
+  pub node_offset: u64,
+  /// columns of the previous page, to keep their order
+  #[serde(default)]
+  pub columns: Vec<String>,
+  pub cursor_offset: Option<u64>,
+  pub cursor_index: Option<u64>,
+  pub limit: Option<u32>,
+}
+
+#[tauri::command]
+pub fn json_array_table(
+  engine: tauri::State<'_, CoreEngine>,
+  args: JsonArrayTableArgs,
+) -> Result<JsonTablePage, String> {
+  let limit = args.limit.unwrap_or(50) as usize;
+  engine
+    .json_array_table(
+      &args.session_id,
+      args.meta,
+      args.node_offset,
+      args.columns,
+      args.cursor_offset,
+      args.cursor_index,
+      limit,
     )
     .map_err(|e| e.to_string())
 }
@@ -481,3 +1487,156 @@ pub fn json_node_summary_at_offset(
     .map_err(|e| e.to_string())
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsonFindInRecordArgs {
+  pub session_id: String,
+  pub meta: RecordMeta,
+  pub query: JsonFindQuery,
+}
+
+#[tauri::command]
+pub async fn json_find_in_record(
+  engine: tauri::State<'_, CoreEngine>,
+  args: JsonFindInRecordArgs,
+) -> Result<JsonFindResult, String> {
+  let engine = engine.inner().clone();
+  tauri::async_runtime::spawn_blocking(move || {
+    engine
+      .json_find_in_record(&args.session_id, args.meta, args.query)
+      .map_err(|e| e.to_string())
+  })
+  .await
+  .map_err(|e| format!("json_find_in_record task join error: {e}"))?
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsonEvalPathArgs {
+  pub session_id: String,
+  pub meta: RecordMeta,
+  pub expr: String,
+  pub max_matches: Option<usize>,
+}
+
+#[tauri::command]
+pub async fn json_eval_path(
+  engine: tauri::State<'_, CoreEngine>,
+  args: JsonEvalPathArgs,
+) -> Result<JsonPathResult, String> {
+  let engine = engine.inner().clone();
+  tauri::async_runtime::spawn_blocking(move || {
+    engine
+      .json_eval_path(&args.session_id, args.meta, &args.expr, args.max_matches)
+      .map_err(|e| e.to_string())
+  })
+  .await
+  .map_err(|e| format!("json_eval_path task join error: {e}"))?
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BuildJsonNodeIndexArgs {
+  pub session_id: String,
+  pub meta: RecordMeta,
+}
+
+#[tauri::command]
+pub fn build_json_node_index(
+  engine: tauri::State<'_, CoreEngine>,
+  args: BuildJsonNodeIndexArgs,
+) -> Result<Option<TaskInfo>, String> {
+  engine
+    .build_json_node_index(&args.session_id, args.meta)
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn build_text_index(engine: tauri::State<'_, CoreEngine>, session_id: String) -> Result<Option<TaskInfo>, String> {
+  engine.build_text_index(&session_id).map_err(|e| e.to_string())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsonNodeStatsArgs {
+  pub session_id: String,
+  pub node_offset: u64,
+}
+
+#[tauri::command]
+pub async fn json_node_stats(
+  engine: tauri::State<'_, CoreEngine>,
+  args: JsonNodeStatsArgs,
+) -> Result<JsonNodeStats, String> {
+  let engine = engine.inner().clone();
+  tauri::async_runtime::spawn_blocking(move || {
+    engine
+      .json_node_stats(&args.session_id, args.node_offset)
+      .map_err(|e| e.to_string())
+  })
+  .await
+  .map_err(|e| format!("json_node_stats task join error: {e}"))?
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsonValidateRecordArgs {
+  pub session_id: String,
+  pub meta: RecordMeta,
+}
+
+#[tauri::command]
+pub async fn json_validate_record(
+  engine: tauri::State<'_, CoreEngine>,
+  args: JsonValidateRecordArgs,
+) -> Result<JsonValidation, String> {
+  let engine = engine.inner().clone();
+  tauri::async_runtime::spawn_blocking(move || {
+    engine
+      .json_validate_record(&args.session_id, args.meta)
+      .map_err(|e| e.to_string())
+  })
+  .await
+  .map_err(|e| format!("json_validate_record task join error: {e}"))?
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsonBase64PreviewArgs {
+  pub session_id: String,
+  pub node_offset: u64,
+  pub max_bytes: Option<usize>,
+}
+
+#[tauri::command]
+pub async fn json_base64_preview(
+  engine: tauri::State<'_, CoreEngine>,
+  args: JsonBase64PreviewArgs,
+) -> Result<Option<Base64Preview>, String> {
+  let engine = engine.inner().clone();
+  tauri::async_runtime::spawn_blocking(move || {
+    engine
+      .json_base64_preview(&args.session_id, args.node_offset, args.max_bytes)
+      .map_err(|e| e.to_string())
+  })
+  .await
+  .map_err(|e| format!("json_base64_preview task join error: {e}"))?
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SaveJsonBase64Args {
+  pub session_id: String,
+  pub node_offset: u64,
+  /// output file path
+  pub output_path: String,
+}
+
+#[tauri::command]
+pub async fn save_json_base64(
+  engine: tauri::State<'_, CoreEngine>,
+  args: SaveJsonBase64Args,
+) -> Result<ExportResult, String> {
+  let engine = engine.inner().clone();
+  tauri::async_runtime::spawn_blocking(move || {
+    engine
+      .save_json_base64(&args.session_id, args.node_offset, PathBuf::from(args.output_path))
+      .map_err(|e| e.to_string())
+  })
+  .await
+  .map_err(|e| format!("save_json_base64 task join error: {e}"))?
+}
+
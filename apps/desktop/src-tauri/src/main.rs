@@ -4,15 +4,22 @@ mod commands;
 #[cfg(target_os = "macos")]
 mod macos_open;
 
-use dh_core::{CoreEngine, CoreOptions};
+use dh_core::{CoreEngine, CoreOptions, StorageOptions};
 
 use tauri::Manager;
 
 fn collect_open_paths_from_argv() -> Vec<String> {
   // On macOS, "Open with" / file association often passes the file path(s)
   // as process arguments on cold start.
+  let mut skip_value = false;
   std::env::args_os()
     .skip(1)
+    .filter(|a| {
+      // Storage flags (see `StorageOptions::from_args_and_env`) and the paths they take.
+      let is_flag = std::mem::take(&mut skip_value) || a.to_str().is_some_and(|s| s.starts_with("--"));
+      skip_value = a == "--storage" || a == "--storage-key-file";
+      !is_flag
+    })
     .filter_map(|a| {
       let p = std::path::PathBuf::from(&a);
       if p.exists() {
@@ -25,13 +32,26 @@ fn collect_open_paths_from_argv() -> Vec<String> {
 }
 
 fn main() {
-  let engine = CoreEngine::new(CoreOptions::default()).expect("init CoreEngine");
+  let args: Vec<std::ffi::OsString> = std::env::args_os().skip(1).collect();
+  // Encrypted storage opens with the passphrase from `--storage-key-file` or
+  // `FLUXPEEK_STORAGE_KEY`; there is no prompt, so without either the app stops here.
+  let engine = match CoreEngine::new(CoreOptions {
+    storage: StorageOptions::from_args_and_env(&args),
+    ..CoreOptions::default()
+  }) {
+    Ok(engine) => engine,
+    Err(e) => {
+      eprintln!("init CoreEngine: {e}");
+      std::process::exit(1);
+    }
+  };
 
   let context = tauri::generate_context!();
 
   let app = tauri::Builder::default()
     .manage(engine)
     .manage(commands::PendingOpenState(std::sync::Mutex::new(Vec::new())))
+    .manage(commands::FollowState(std::sync::Mutex::new(Default::default())))
     .setup(|app| {
       #[cfg(target_os = "macos")]
       {
@@ -57,26 +77,120 @@ fn main() {
     })
     .invoke_handler(tauri::generate_handler![
       commands::open_file,
+      commands::page_at_read_position,
+      commands::open_files,
+      commands::open_stdin,
+      commands::set_session_encoding,
+      commands::set_csv_dialect,
+      commands::set_json_lenient,
+      commands::set_derived_columns,
+      commands::set_session_columns,
+      commands::set_session_filters,
+      commands::open_search_results,
+      commands::open_sorted_view,
+      commands::open_dedup_view,
       commands::scan_folder_tree,
       commands::path_kind,
       commands::next_page,
+      commands::page_at,
+      commands::goto_record,
+      commands::page_at_position,
+      commands::set_record_label,
+      commands::list_record_labels,
       commands::get_record_raw,
+      commands::get_cell_raw,
       commands::search,
+      commands::refresh_session,
+      commands::reload_session,
+      commands::poll_new_records,
+      commands::follow_file,
+      commands::unfollow_file,
+      commands::list_sessions,
+      commands::close_session,
+      commands::count_records,
+      commands::session_metrics,
+      commands::csv_warnings,
       commands::get_task,
       commands::search_task_hits_page,
+      commands::search_match_count,
       commands::export,
+      commands::export_with_preset,
+      commands::save_export_preset,
+      commands::list_export_presets,
+      commands::delete_export_preset,
+      commands::save_record_edit,
+      commands::save_json_node_edit,
+      commands::write_pretty_record,
+      commands::save_parquet_blob,
       commands::cancel_task,
+      commands::pause_task,
+      commands::resume_task,
+      commands::max_concurrent_tasks,
+      commands::set_max_concurrent_tasks,
+      commands::save_view_prefs,
+      commands::save_workspace,
+      commands::restore_workspace,
+      commands::save_collection,
+      commands::list_collections,
+      commands::delete_collection,
+      commands::open_collection,
+      commands::list_recent,
+      commands::pin_recent,
+      commands::remove_recent,
+      commands::clear_recent,
+      commands::prune_recent,
+      commands::list_recent_folders,
+      commands::pin_recent_folder,
+      commands::remove_recent_folder,
+      commands::list_task_history,
+      commands::list_export_history,
+      commands::rerun_export,
+      commands::list_interrupted_exports,
+      commands::dismiss_interrupted_export,
+      commands::list_task_events,
+      commands::storage_report,
+      commands::maintain_storage,
+      commands::backup_storage,
+      commands::restore_storage,
+      commands::clear_task_history,
       commands::take_pending_open_paths,
+      commands::json_path_strings,
       commands::json_list_children,
       commands::json_node_summary,
       commands::json_list_children_at_offset,
-      commands::json_node_summary_at_offset
+      commands::json_array_table,
+      commands::json_node_summary_at_offset,
+      commands::json_find_in_record,
+      commands::json_eval_path,
+      commands::json_node_stats,
+      commands::json_validate_record,
+      commands::json_base64_preview,
+      commands::save_json_base64,
+      commands::build_json_node_index,
+      commands::build_text_index,
+      commands::get_schema,
+      commands::csv_schema,
+      commands::parquet_metadata,
+      commands::parquet_footer_stats,
+      commands::get_stats,
+      commands::start_stats_task,
+      commands::stats_task_result,
+      commands::quick_stats,
+      commands::compare_stats,
+      commands::start_diff_task,
+      commands::diff_task_page,
+      commands::export_stats_report
     ])
     .build(context)
     .expect("error while building tauri application");
 
   // Keep the run loop active; additional "open file" events while running are
   // handled differently across platforms and are not wired up here for Tauri v1.
-  app.run(|_app_handle, _event| {});
+  app.run(|app_handle, event| {
+    // Stop tasks and exports before the process goes away, so none leaves half-written files.
+    if let tauri::RunEvent::Exit = event {
+      app_handle.state::<CoreEngine>().shutdown();
+    }
+  });
 }
 
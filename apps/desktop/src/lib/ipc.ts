@@ -7,6 +7,48 @@ export interface SessionInfo {
   path: string;
   format: FileFormat;
   created_at_ms: number;
+  /** Stored by `saveViewPrefs` for this file. */
+  view_prefs?: ViewPrefs;
+  /** Where the user left off in this version of the file (see `pageAtReadPosition`). */
+  read_position?: ReadPosition;
+  /** CSV / Parquet: the only columns pages show (see `setSessionColumns`). */
+  columns?: string[];
+  /** Parquet: only rows meeting every filter are paged (see `setSessionFilters`). */
+  filters?: ColumnFilter[];
+  /** CSV: how rows are read (see `setCsvDialect`). */
+  csv?: CsvDialect;
+  /** JSON / JSONL: comments and trailing commas are skipped (see `setJsonLenient`). */
+  json_lenient?: boolean;
+}
+
+export interface CsvDialect {
+  /** Off: line 0 is data and columns are named `col_0..col_<n-1>`. */
+  has_header: boolean;
+  /** Numeric / boolean / null-looking cells become JSON numbers, booleans and nulls in `raw` and exports. */
+  infer_types?: boolean;
+  /** Cell separator (default `,`), e.g. `;`, `\t`, `|` or several characters like `||`; literal, written between cells by edits and exports. */
+  delimiter?: string;
+  /** Regex splitting cells instead of `delimiter` (which it must match), e.g. `\\s*\\|\\s*`; null: split on `delimiter`. */
+  delimiter_regex?: string | null;
+  /** Quote character (default `"`), e.g. `'`. */
+  quote?: string;
+  /** Makes the next character literal, e.g. `\\`; null: only doubled quotes escape. */
+  escape?: string | null;
+}
+
+export type FilterOp = 'eq' | 'ne' | 'lt' | 'le' | 'gt' | 'ge' | 'contains' | 'starts_with' | 'is_null' | 'not_null';
+
+export interface ColumnFilter {
+  column: string;
+  op: FilterOp;
+  /** Unused by `is_null` / `not_null`. */
+  value?: unknown;
+}
+
+export interface ReadPosition {
+  record_index: number;
+  byte_offset: number | null;
+  updated_at_ms: number;
 }
 
 export interface RecordMeta {
@@ -20,24 +62,55 @@ export interface Record {
   preview: string;
   raw: string | null;
   meta: RecordMeta | null;
+  /** CSV: why the row is malformed (field count or unterminated quote). */
+  warning?: string;
+  /** CSV / Parquet: cell texts, one per column shown (CSV header row: the names), for a grid. */
+  columns?: string[];
+}
+
+export interface RecordWarning {
+  id: number;
+  meta: RecordMeta | null;
+  warning: string;
+}
+
+export interface CsvWarnings {
+  rows: RecordWarning[];
+  truncated: boolean;
 }
 
 export interface RecordPage {
   records: Record[];
   next_cursor: string | null;
   reached_eof: boolean;
+  total_records?: number;
+  /** Ids are estimates (page_at_position without an index); kept by the pages after it. */
+  ids_estimated: boolean;
 }
 
-export type SearchMode = 'current_page' | 'scan_all' | 'indexed';
+export type SearchMode = 'current_page' | 'scan_all' | 'indexed' | 'count_only';
 
 export interface SearchQuery {
   text: string;
   mode: SearchMode;
   case_sensitive: boolean;
   max_hits: number;
+  /** scan_all / indexed: stop after this many ms and keep the hits so far (`Task.timed_out`). */
+  timeout_ms?: number;
+  /** scan_all only: ignore hits cached from the same search on the unchanged file. */
+  force_rescan?: boolean;
 }
 
-export type TaskKind = 'search_scan_all' | 'export';
+export type TaskKind =
+  | 'search_scan_all'
+  | 'search_count'
+  | 'export'
+  | 'stats'
+  | 'count_records'
+  /** Record-offset index of a session, node index of a large JSON record or full-text index. */
+  | 'index_build'
+  | 'diff'
+  | 'spool';
 
 export interface TaskInfo {
   id: string;
@@ -52,16 +125,34 @@ export interface SearchResult {
   truncated: boolean;
 }
 
+/** Background tasks (index builds, stats) start after queued normal ones and wait out paging. */
+export type TaskPriority = 'normal' | 'background';
+
 export interface Task {
   id: string;
   kind: TaskKind;
+  priority?: TaskPriority;
   started_at_ms: number;
   progress_0_100: number;
   cancellable: boolean;
   finished: boolean;
-  error: string | null;
+  error: TaskError | null;
+  timed_out?: boolean;
+  from_cache?: boolean;
+  bytes_processed?: number;
+  records_processed?: number;
+  /** Searches: matches found so far, including those past `max_hits`. */
+  hits_found?: number;
 }
 
+/** Why a task failed; `io` failures are worth a retry, `format` ones are not. */
+export type TaskError =
+  | { type: 'io'; message: string }
+  | { type: 'cancelled' }
+  | { type: 'format'; message: string }
+  | { type: 'limit_exceeded'; message: string }
+  | { type: 'duckdb'; message: string };
+
 export type ExportFormat = 'json' | 'jsonl' | 'csv';
 
 export type ExportRequest =
@@ -72,14 +163,27 @@ export type ExportRequest =
       meta: RecordMeta;
       path: (string | number)[];
       include_root: boolean;
+      /** csv output: the subtree must be an array of flat objects; `children` picks elements. */
       children: (string | number)[];
-    };
+      /** Drop whitespace between tokens (compact output from pretty-printed sources). */
+      minify?: boolean;
+    }
+  | { type: 'filtered' };
 
 export interface ExportResult {
   output_path: string;
   records_written: number;
 }
 
+/** Saved export options; `destination` may use `{name}`, `{format}` and `{timestamp}`. */
+export interface ExportPreset {
+  name: string;
+  format: ExportFormat;
+  columns?: string[] | null;
+  flatten?: boolean;
+  destination?: string | null;
+}
+
 export type JsonNodeKind = 'object' | 'array' | 'string' | 'number' | 'boolean' | 'null' | 'unknown';
 
 export interface JsonChildItem {
@@ -107,6 +211,9 @@ export interface JsonChildItemOffset {
   value_offset: number;
 }
 
+/** Child order: as written, object keys alphabetically, or largest value first. */
+export type JsonChildSort = 'original' | 'key' | 'size';
+
 export interface JsonChildrenPageOffset {
   items: JsonChildItemOffset[];
   next_cursor_offset: number | null;
@@ -114,6 +221,31 @@ export interface JsonChildrenPageOffset {
   reached_end: boolean;
 }
 
+export interface JsonTableCell {
+  kind: JsonNodeKind;
+  preview: string;
+  value_offset: number;
+}
+
+export interface JsonTableRow {
+  index: number;
+  value_offset: number;
+  kind: JsonNodeKind;
+  /** Aligned with `JsonTablePage.columns`; null where the element lacks the key. */
+  cells: (JsonTableCell | null)[];
+  /** Set for elements that aren't objects. */
+  preview: string | null;
+}
+
+export interface JsonTablePage {
+  /** Columns passed in, then keys first seen on this page. */
+  columns: string[];
+  rows: JsonTableRow[];
+  next_cursor_offset: number | null;
+  next_cursor_index: number | null;
+  reached_end: boolean;
+}
+
 export interface JsonNodeSummaryOffset {
   kind: JsonNodeKind;
   child_count: number | null;
@@ -121,6 +253,94 @@ export interface JsonNodeSummaryOffset {
   node_offset: number;
 }
 
+export interface JsonNodeStats {
+  node_offset: number;
+  kind: JsonNodeKind;
+  byte_len: number;
+  /** Nested containers along the deepest branch, the node included (0 for a scalar). */
+  max_depth: number;
+  /** Direct members / elements; null for scalars. */
+  child_count: number | null;
+  /** Array elements anywhere below. */
+  elements: number;
+  /** Object keys anywhere below. */
+  keys: number;
+}
+
+export interface JsonSyntaxError {
+  message: string;
+  /** Absolute byte offset in the file. */
+  byte_offset: number;
+  /** 1-based, within the record. */
+  line: number;
+  /** 1-based byte column within `line`. */
+  column: number;
+  /** Record text just before / from the error. */
+  context_before: string;
+  context_after: string;
+}
+
+export interface JsonValidation {
+  valid: boolean;
+  error: JsonSyntaxError | null;
+}
+
+export type Base64Content = 'text' | 'binary';
+
+export interface Base64Preview {
+  node_offset: number;
+  /** From a `data:` URI, else sniffed from the bytes (images, PDF, gzip, zip). */
+  mime: string | null;
+  url_safe: boolean;
+  decoded_len: number;
+  content: Base64Content;
+  /** Decoded text, or hex pairs separated by spaces. */
+  preview: string;
+  truncated: boolean;
+}
+
+export interface JsonFindQuery {
+  text: string;
+  case_sensitive?: boolean;
+  /** Match object keys (default true). */
+  keys?: boolean;
+  /** Match scalar values (default true). */
+  values?: boolean;
+  /** Default 100. */
+  max_hits?: number;
+}
+
+export interface JsonFindHit {
+  path: (string | number)[];
+  /** Offset of each node along `path`: expand them with `jsonListChildrenAtOffset`. */
+  node_offsets: number[];
+  value_offset: number;
+  kind: JsonNodeKind;
+  key_match: boolean;
+  preview: string;
+}
+
+export interface JsonFindResult {
+  hits: JsonFindHit[];
+  /** `max_hits` was reached before the end of the record. */
+  truncated: boolean;
+}
+
+export interface JsonPathMatch {
+  path: (string | number)[];
+  /** Offset of each node along `path`, as in `JsonFindHit`. */
+  node_offsets: number[];
+  value_offset: number;
+  kind: JsonNodeKind;
+  preview: string;
+}
+
+export interface JsonPathResult {
+  matches: JsonPathMatch[];
+  /** `max_matches` was reached before the end of the record. */
+  truncated: boolean;
+}
+
 export interface OpenFileResponse {
   session: SessionInfo;
   first_page: RecordPage;
@@ -144,11 +364,32 @@ export interface FolderTreeResponse {
 
 export type PathKind = 'file' | 'dir' | 'missing' | 'other';
 
-export async function openFile(path: string, request_id?: string): Promise<OpenFileResponse> {
+/**
+ * `resume`: start at the stored read position instead of record 0.
+ * `decryption_key`: AES key of a parquet file using modular encryption (16 / 24 / 32 bytes, as is or base64).
+ */
+export async function openFile(
+  path: string,
+  request_id?: string,
+  resume?: boolean,
+  decryption_key?: string
+): Promise<OpenFileResponse> {
   return await invokeCompat('open_file', {
     path,
     requestId: request_id ?? null,
-    request_id: request_id ?? null
+    request_id: request_id ?? null,
+    resume: resume ?? null,
+    decryptionKey: decryption_key ?? null,
+    decryption_key: decryption_key ?? null
+  });
+}
+
+export async function pageAtReadPosition(session_id: string, page_size: number): Promise<RecordPage> {
+  return await invokeCompat('page_at_read_position', {
+    sessionId: session_id,
+    session_id,
+    pageSize: page_size,
+    page_size
   });
 }
 
@@ -194,6 +435,84 @@ export async function getRecordRaw(args: { session_id: string; meta: RecordMeta
   });
 }
 
+/** CSV: the whole of a cell cut short in `raw` (its column is listed in `raw.__truncated__`). */
+export async function getCellRaw(session_id: string, meta: RecordMeta, column: string): Promise<string> {
+  return await invokeCompat('get_cell_raw', { sessionId: session_id, session_id, meta, column });
+}
+
+export interface ParquetRowGroup {
+  index: number;
+  row_count: number;
+  total_bytes: number;
+  compressed_bytes: number;
+}
+
+export interface ParquetColumn {
+  path: string;
+  physical_type: string | null;
+  logical_type: string | null;
+  duckdb_type: string | null;
+  repetition: string | null;
+  compression: string | null;
+  compressed_bytes: number;
+  uncompressed_bytes: number;
+}
+
+export interface SchemaField {
+  name: string;
+  kind: JsonNodeKind;
+  data_type?: string | null;
+  nullable: boolean;
+}
+
+/** Quick CSV file summary for the folder tree; no session is opened. */
+export interface CsvSchema {
+  header: string[];
+  /** Sniffed delimiter (`,`, `;`, tab or `|`); pass it as `CsvDialect.delimiter`. */
+  delimiter: string;
+  fields: SchemaField[];
+  sampled_records: number;
+  /** Data rows; extrapolated from the sample unless `exact`. */
+  estimated_records: number;
+  exact: boolean;
+}
+
+export async function csvSchema(path: string): Promise<CsvSchema> {
+  return await invokeCompat('csv_schema', { path });
+}
+
+export interface ParquetMetadata {
+  row_count: number;
+  format_version: number;
+  created_by: string | null;
+  file_size_bytes: number;
+  row_groups: ParquetRowGroup[];
+  columns: ParquetColumn[];
+  key_value: { key: string; value: string }[];
+}
+
+export async function parquetMetadata(session_id: string): Promise<ParquetMetadata> {
+  return await invokeCompat('parquet_metadata', { sessionId: session_id, session_id });
+}
+
+export interface ParquetColumnStats {
+  path: string;
+  min: string | null;
+  max: string | null;
+  null_count: number | null;
+  complete: boolean;
+}
+
+export interface ParquetFooterStats {
+  row_count: number;
+  row_groups: number;
+  columns: ParquetColumnStats[];
+}
+
+export async function parquetFooterStats(session_id: string): Promise<ParquetFooterStats> {
+  return await invokeCompat('parquet_footer_stats', { sessionId: session_id, session_id });
+}
+
 export async function search(args: { session_id: string; query: SearchQuery }): Promise<SearchResult> {
   return await invokeCompat('search', {
     sessionId: args.session_id,
@@ -220,6 +539,16 @@ export async function searchTaskHitsPage(args: {
   });
 }
 
+export interface SearchCount {
+  matches: number;
+  /** False while the task runs, and if it was cancelled or timed out. */
+  complete: boolean;
+}
+
+export async function searchMatchCount(task_id: string): Promise<SearchCount> {
+  return await invokeCompat('search_match_count', { taskId: task_id, task_id });
+}
+
 export async function cancelTask(task_id: string): Promise<void> {
   await invokeCompat('cancel_task', { taskId: task_id, task_id });
 }
@@ -242,12 +571,200 @@ export async function exportToFile(args: {
   });
 }
 
+/** Copy of the file with one JSON node replaced (by `node_offset`, or `path` under the record). */
+export async function saveJsonNodeEdit(args: {
+  session_id: string;
+  meta: RecordMeta;
+  node_offset?: number | null;
+  path?: (string | number)[];
+  body: string;
+  output_path: string;
+}): Promise<ExportResult> {
+  return await invokeCompat('save_json_node_edit', {
+    args: {
+      sessionId: args.session_id,
+      session_id: args.session_id,
+      meta: args.meta,
+      nodeOffset: args.node_offset ?? null,
+      node_offset: args.node_offset ?? null,
+      path: args.path ?? [],
+      body: args.body,
+      outputPath: args.output_path,
+      output_path: args.output_path
+    }
+  });
+}
+
+/** Whole record pretty-printed into a temp file (any size; deleted when the session closes). */
+export async function writePrettyRecord(args: { session_id: string; meta: RecordMeta }): Promise<ExportResult> {
+  return await invokeCompat('write_pretty_record', {
+    args: {
+      sessionId: args.session_id,
+      session_id: args.session_id,
+      meta: args.meta
+    }
+  });
+}
+
+export async function saveParquetBlob(args: {
+  session_id: string;
+  record_index: number;
+  column: string;
+  output_path: string;
+}): Promise<ExportResult> {
+  return await invokeCompat('save_parquet_blob', {
+    args: {
+      sessionId: args.session_id,
+      session_id: args.session_id,
+      recordIndex: args.record_index,
+      record_index: args.record_index,
+      column: args.column,
+      outputPath: args.output_path,
+      output_path: args.output_path
+    }
+  });
+}
+
+export async function exportWithPreset(args: {
+  session_id: string;
+  request: ExportRequest;
+  preset: string;
+  output_path?: string | null;
+}): Promise<ExportResult> {
+  return await invokeCompat('export_with_preset', {
+    args: {
+      sessionId: args.session_id,
+      session_id: args.session_id,
+      request: args.request,
+      preset: args.preset,
+      outputPath: args.output_path ?? null,
+      output_path: args.output_path ?? null
+    }
+  });
+}
+
+export async function saveExportPreset(preset: ExportPreset): Promise<ExportPreset> {
+  return await invokeCompat('save_export_preset', { preset });
+}
+
+export async function listExportPresets(): Promise<ExportPreset[]> {
+  return await invokeCompat('list_export_presets', {});
+}
+
+export async function deleteExportPreset(name: string): Promise<void> {
+  await invokeCompat('delete_export_preset', { name });
+}
+
+/** Size of the storage database and of the tables that grow with use. */
+export interface StorageReport {
+  path: string;
+  size_bytes: number;
+  free_bytes: number;
+  task_history_rows: number;
+  task_events_rows: number;
+  scan_cache_rows: number;
+  scan_cache_bytes: number;
+  encrypted: boolean;
+}
+
+export interface StorageLimits {
+  max_task_history?: number;
+  max_task_events?: number;
+  max_scan_cache_bytes?: number;
+}
+
+export interface StorageMaintenance {
+  before: StorageReport;
+  after: StorageReport;
+  rows_removed: number;
+}
+
+export async function storageReport(): Promise<StorageReport> {
+  return await invokeCompat('storage_report', {});
+}
+
+/** Trims history / event log / scan cache to `limits` (defaults if omitted) and vacuums. */
+export async function maintainStorage(limits?: StorageLimits): Promise<StorageMaintenance> {
+  return await invokeCompat('maintain_storage', { limits: limits ?? null });
+}
+
+/** Rows per table written to / restored from a backup file. */
+export interface StorageBackup {
+  path: string;
+  rows: Record<string, number>;
+}
+
+/** Saves settings, recents, labels, view prefs, collections and export presets to a JSON file. */
+export async function backupStorage(output_path: string): Promise<StorageBackup> {
+  return await invokeCompat('backup_storage', { outputPath: output_path, output_path });
+}
+
+/** Merges a backup file into the storage; entries with the same key are replaced. */
+export async function restoreStorage(input_path: string): Promise<StorageBackup> {
+  return await invokeCompat('restore_storage', { inputPath: input_path, input_path });
+}
+
+/** A finished task or export as kept in the history. */
+export interface TaskHistoryEntry {
+  id: number;
+  task_id: string;
+  kind: TaskKind;
+  paths: string[];
+  params: unknown;
+  started_at_ms: number;
+  finished_at_ms: number;
+  duration_ms: number;
+  cancelled: boolean;
+  summary: string | null;
+  error: TaskError | null;
+}
+
+export async function listExportHistory(limit?: number): Promise<TaskHistoryEntry[]> {
+  return await invokeCompat('list_export_history', { limit: limit ?? null });
+}
+
+/** Repeats the export with the same source, request and output file. */
+export async function rerunExport(history_id: number): Promise<ExportResult> {
+  return await invokeCompat('rerun_export', { historyId: history_id, history_id });
+}
+
+/** An export cut short by a crash; its destination was never written. */
+export interface InterruptedExport {
+  id: number;
+  task_id: string;
+  destination: string;
+  temp_path: string;
+  started_at_ms: number;
+  interrupted_at_ms: number;
+}
+
+export async function listInterruptedExports(): Promise<InterruptedExport[]> {
+  return await invokeCompat('list_interrupted_exports', {});
+}
+
+export async function dismissInterruptedExport(id: number): Promise<void> {
+  await invokeCompat('dismiss_interrupted_export', { id });
+}
+
+export interface JsonPathStrings {
+  /** JSON Pointer, e.g. `/a/b/0`. */
+  pointer: string;
+  /** JavaScript accessor, e.g. `a.b[0]`. */
+  js: string;
+}
+
+/** "Copy path" strings for a tree path (the segments `jsonListChildren` takes). */
+export async function jsonPathStrings(path: (string | number)[]): Promise<JsonPathStrings> {
+  return await invokeCompat('json_path_strings', { path });
+}
+
 export async function jsonListChildren(args: {
   session_id: string;
   meta: RecordMeta;
   path: (string | number)[];
   cursor?: number | null;
   limit?: number | null;
+  sort?: JsonChildSort;
 }): Promise<JsonChildrenPage> {
   return await invokeCompat('json_list_children', {
     args: {
@@ -256,7 +773,8 @@ export async function jsonListChildren(args: {
       meta: args.meta,
       path: args.path,
       cursor: args.cursor ?? null,
-      limit: args.limit ?? null
+      limit: args.limit ?? null,
+      sort: args.sort ?? 'original'
     }
   });
 }
@@ -287,6 +805,8 @@ export async function jsonListChildrenAtOffset(args: {
   cursor_offset?: number | null;
   cursor_index?: number | null;
   limit?: number | null;
+  /** Sorted pages go by `cursor_index` (position in sorted order). */
+  sort?: JsonChildSort;
 }): Promise<JsonChildrenPageOffset> {
   return await invokeCompat('json_list_children_at_offset', {
     args: {
@@ -296,6 +816,31 @@ export async function jsonListChildrenAtOffset(args: {
       node_offset: args.node_offset,
       cursor_offset: args.cursor_offset ?? null,
       cursor_index: args.cursor_index ?? null,
+      limit: args.limit ?? null,
+      sort: args.sort ?? 'original'
+    }
+  });
+}
+
+/** An array of objects as a table; pass the previous page's `columns` to keep their order. */
+export async function jsonArrayTable(args: {
+  session_id: string;
+  meta: RecordMeta;
+  node_offset: number;
+  columns?: string[];
+  cursor_offset?: number | null;
+  cursor_index?: number | null;
+  limit?: number | null;
+}): Promise<JsonTablePage> {
+  return await invokeCompat('json_array_table', {
+    args: {
+      sessionId: args.session_id,
+      session_id: args.session_id,
+      meta: args.meta,
+      node_offset: args.node_offset,
+      columns: args.columns ?? [],
+      cursor_offset: args.cursor_offset ?? null,
+      cursor_index: args.cursor_index ?? null,
       limit: args.limit ?? null
     }
   });
@@ -320,6 +865,251 @@ export async function jsonNodeSummaryAtOffset(args: {
   });
 }
 
+export async function jsonFindInRecord(args: {
+  session_id: string;
+  meta: RecordMeta;
+  query: JsonFindQuery;
+}): Promise<JsonFindResult> {
+  return await invokeCompat('json_find_in_record', {
+    args: {
+      sessionId: args.session_id,
+      session_id: args.session_id,
+      meta: args.meta,
+      query: args.query
+    }
+  });
+}
+
+/** Evaluates a JSONPath (`$`, `.key`, `['key']`, `[n]`, `*`, `..`), e.g. `$.messages[*].role`. */
+export async function jsonEvalPath(args: {
+  session_id: string;
+  meta: RecordMeta;
+  expr: string;
+  max_matches?: number | null;
+}): Promise<JsonPathResult> {
+  return await invokeCompat('json_eval_path', {
+    args: {
+      sessionId: args.session_id,
+      session_id: args.session_id,
+      meta: args.meta,
+      expr: args.expr,
+      max_matches: args.max_matches ?? null
+    }
+  });
+}
+
+/** Byte size, depth and key / element counts of a node (reads the whole node). */
+export async function jsonNodeStats(args: { session_id: string; node_offset: number }): Promise<JsonNodeStats> {
+  return await invokeCompat('json_node_stats', {
+    args: {
+      sessionId: args.session_id,
+      session_id: args.session_id,
+      node_offset: args.node_offset
+    }
+  });
+}
+
+/** Strict JSON parse of a record; the first syntax error and where it is. */
+export async function jsonValidateRecord(args: { session_id: string; meta: RecordMeta }): Promise<JsonValidation> {
+  return await invokeCompat('json_validate_record', {
+    args: {
+      sessionId: args.session_id,
+      session_id: args.session_id,
+      meta: args.meta
+    }
+  });
+}
+
+/** Decoded start of a string node that looks like base64; null for other nodes. */
+export async function jsonBase64Preview(args: {
+  session_id: string;
+  node_offset: number;
+  max_bytes?: number | null;
+}): Promise<Base64Preview | null> {
+  return await invokeCompat('json_base64_preview', {
+    args: {
+      sessionId: args.session_id,
+      session_id: args.session_id,
+      node_offset: args.node_offset,
+      max_bytes: args.max_bytes ?? null
+    }
+  });
+}
+
+/** Decode a base64 string node into a file. */
+export async function saveJsonBase64(args: {
+  session_id: string;
+  node_offset: number;
+  output_path: string;
+}): Promise<ExportResult> {
+  return await invokeCompat('save_json_base64', {
+    args: {
+      sessionId: args.session_id,
+      session_id: args.session_id,
+      node_offset: args.node_offset,
+      outputPath: args.output_path,
+      output_path: args.output_path
+    }
+  });
+}
+
+/**
+ * Index the large containers of a JSON record so deep nodes open without re-reading it from the
+ * start (stored, so it survives restarts). Tree calls start this on their own for huge records;
+ * null if the record is already indexed or being indexed.
+ */
+export async function buildJsonNodeIndex(args: { session_id: string; meta: RecordMeta }): Promise<TaskInfo | null> {
+  return await invokeCompat('build_json_node_index', {
+    args: {
+      sessionId: args.session_id,
+      session_id: args.session_id,
+      meta: args.meta
+    }
+  });
+}
+
+/**
+ * Build the full-text index of a JSONL / CSV file, which `indexed` searches need (stored, so it
+ * survives restarts until the file changes). null if the file is already indexed or being indexed.
+ */
+export async function buildTextIndex(session_id: string): Promise<TaskInfo | null> {
+  return await invokeCompat('build_text_index', { sessionId: session_id, session_id });
+}
+
+export interface ViewPrefs {
+  /** CSV: how rows are read, applied on open (see `setCsvDialect`). */
+  csv?: CsvDialect;
+  encoding?: string;
+  columns?: string[];
+  page_size?: number;
+  json_lenient?: boolean;
+}
+
+export async function saveViewPrefs(session_id: string, prefs: ViewPrefs): Promise<void> {
+  await invokeCompat('save_view_prefs', { sessionId: session_id, session_id, prefs });
+}
+
+export async function setSessionColumns(session_id: string, columns: string[] | null): Promise<RecordPage> {
+  return await invokeCompat('set_session_columns', { sessionId: session_id, session_id, columns });
+}
+
+export async function setSessionFilters(session_id: string, filters: ColumnFilter[] | null): Promise<RecordPage> {
+  return await invokeCompat('set_session_filters', { sessionId: session_id, session_id, filters });
+}
+
+export async function setCsvDialect(session_id: string, dialect: CsvDialect): Promise<RecordPage> {
+  return await invokeCompat('set_csv_dialect', { sessionId: session_id, session_id, dialect });
+}
+
+export async function setJsonLenient(session_id: string, lenient: boolean): Promise<RecordPage> {
+  return await invokeCompat('set_json_lenient', { sessionId: session_id, session_id, lenient });
+}
+
+export async function csvWarnings(session_id: string): Promise<CsvWarnings> {
+  return await invokeCompat('csv_warnings', { sessionId: session_id, session_id });
+}
+
+export interface WorkspaceTab {
+  paths: string[];
+}
+
+export interface Workspace {
+  tabs: WorkspaceTab[];
+  active_tab: number | null;
+}
+
+export interface RestoredWorkspace {
+  tabs: { session: SessionInfo; page: RecordPage }[];
+  active_tab: number | null;
+  /** Tabs whose files are gone or failed to open. */
+  missing: WorkspaceTab[];
+}
+
+export async function saveWorkspace(session_ids: string[], active_session_id?: string | null): Promise<Workspace> {
+  return await invokeCompat('save_workspace', {
+    sessionIds: session_ids,
+    session_ids,
+    activeSessionId: active_session_id ?? null,
+    active_session_id: active_session_id ?? null
+  });
+}
+
+export async function restoreWorkspace(): Promise<RestoredWorkspace> {
+  return await invokeCompat('restore_workspace', {});
+}
+
+export interface DatasetCollection {
+  name: string;
+  paths: string[];
+  created_at_ms: number;
+  updated_at_ms: number;
+}
+
+export interface OpenedCollection {
+  tabs: { session: SessionInfo; page: RecordPage }[];
+  /** Members that are gone or failed to open. */
+  skipped: string[];
+}
+
+export async function saveCollection(name: string, paths: string[]): Promise<DatasetCollection> {
+  return await invokeCompat('save_collection', { name, paths });
+}
+
+export async function listCollections(): Promise<DatasetCollection[]> {
+  return await invokeCompat('list_collections', {});
+}
+
+export async function deleteCollection(name: string): Promise<void> {
+  await invokeCompat('delete_collection', { name });
+}
+
+export async function openCollection(name: string): Promise<OpenedCollection> {
+  return await invokeCompat('open_collection', { name });
+}
+
+export interface RecentFile {
+  path: string;
+  display_name: string;
+  last_opened_at_ms: number;
+  /** Whether the file still existed when last opened. */
+  exists: boolean;
+  pinned: boolean;
+}
+
+export async function listRecent(limit?: number): Promise<RecentFile[]> {
+  return await invokeCompat('list_recent', { limit: limit ?? null });
+}
+
+export async function pinRecent(path: string, pinned: boolean): Promise<void> {
+  await invokeCompat('pin_recent', { path, pinned });
+}
+
+export async function removeRecent(path: string): Promise<void> {
+  await invokeCompat('remove_recent', { path });
+}
+
+export async function clearRecent(): Promise<void> {
+  await invokeCompat('clear_recent', {});
+}
+
+/** Drops unpinned entries whose file is gone; returns how many. */
+export async function pruneRecent(): Promise<number> {
+  return await invokeCompat('prune_recent', {});
+}
+
+/** Folders scanned with `scanFolderTree`, pinned first; `last_opened_at_ms` is the last scan. */
+export async function listRecentFolders(limit?: number): Promise<RecentFile[]> {
+  return await invokeCompat('list_recent_folders', { limit: limit ?? null });
+}
+
+export async function pinRecentFolder(path: string, pinned: boolean): Promise<void> {
+  await invokeCompat('pin_recent_folder', { path, pinned });
+}
+
+export async function removeRecentFolder(path: string): Promise<void> {
+  await invokeCompat('remove_recent_folder', { path });
+}
+
 export async function takePendingOpenPaths(): Promise<string[]> {
   return await invokeCompat('take_pending_open_paths', {});
 }
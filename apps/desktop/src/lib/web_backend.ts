@@ -252,7 +252,7 @@ export async function webInvoke<T>(cmd: string, args: any): Promise<T> {
       const taskId: string = args?.taskId ?? args?.task_id;
       const t = tasks.get(taskId);
       if (t) {
-        t.task = { ...t.task, finished: true, error: 'cancelled', progress_0_100: t.task.progress_0_100 };
+        t.task = { ...t.task, finished: true, error: { type: 'cancelled' }, progress_0_100: t.task.progress_0_100 };
       }
       return undefined as T;
     }
@@ -313,7 +313,22 @@ export async function webInvoke<T>(cmd: string, args: any): Promise<T> {
           const out: ExportResult = { output_path: outputPath, records_written: outValues.length };
           return out as T;
         }
-        throw new Error('Web demo: json_subtree export does not support csv');
+        // csv: an array of flat objects, one row each (the selected elements, if any)
+        const rows: any[] = request.include_root || !(request.children ?? []).length ? subtree : outValues;
+        const isFlat = (v: any) =>
+          v && typeof v === 'object' && !Array.isArray(v) && Object.values(v).every((x) => x === null || typeof x !== 'object');
+        if (!Array.isArray(rows) || !rows.every(isFlat)) {
+          throw new Error('csv export needs an array of flat objects');
+        }
+        const headers = [...new Set(rows.flatMap((r) => Object.keys(r)))];
+        const cell = (v: any) => {
+          const text = v === null || v === undefined ? '' : String(v);
+          return /[",\r\n]/.test(text) ? `"${text.replace(/"/g, '""')}"` : text;
+        };
+        const csv = [headers, ...rows.map((r) => headers.map((h) => r[h]))].map((row) => row.map(cell).join(',')).join('\n');
+        downloadText(outputPath, csv + '\n', 'text/csv;charset=utf-8');
+        const out: ExportResult = { output_path: outputPath, records_written: rows.length };
+        return out as T;
       } else {
         throw new Error('Web demo: invalid export request');
       }
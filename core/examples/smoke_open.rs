@@ -18,7 +18,9 @@ fn main() -> Result<(), String> {
     max_concurrent_tasks: 1,
     storage: StorageOptions {
       sqlite_path: Some(sqlite),
+      ..StorageOptions::default()
     },
+    ..CoreOptions::default()
   })
   .map_err(|e| e.to_string())?;
 
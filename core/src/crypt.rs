@@ -0,0 +1,117 @@
+use std::{fmt, num::NonZeroU32};
+
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use ring::{
+  aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN},
+  hmac, pbkdf2,
+  rand::{SecureRandom, SystemRandom},
+};
+
+/// Start of every sealed value, so plain values (written before encryption was turned on) can
+/// be told apart.
+const SEALED_PREFIX: &str = "enc1:";
+
+const PBKDF2_ROUNDS: u32 = 200_000;
+
+pub(crate) const SALT_LEN: usize = 16;
+
+/// AES-256-GCM over single storage fields, keyed from a passphrase (see
+/// `StorageOptions::passphrase`).
+#[derive(Clone)]
+pub(crate) struct FieldCipher {
+  enc_key: [u8; 32],
+  mac_key: hmac::Key,
+}
+
+impl fmt::Debug for FieldCipher {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str("FieldCipher(..)")
+  }
+}
+
+impl FieldCipher {
+  pub(crate) fn derive(passphrase: &str, salt: &[u8]) -> Self {
+    let mut keys = [0u8; 64];
+    pbkdf2::derive(
+      pbkdf2::PBKDF2_HMAC_SHA256,
+      NonZeroU32::new(PBKDF2_ROUNDS).unwrap_or(NonZeroU32::MIN),
+      salt,
+      passphrase.as_bytes(),
+      &mut keys,
+    );
+    let mut enc_key = [0u8; 32];
+    enc_key.copy_from_slice(&keys[..32]);
+    Self {
+      enc_key,
+      mac_key: hmac::Key::new(hmac::HMAC_SHA256, &keys[32..]),
+    }
+  }
+
+  pub(crate) fn new_salt() -> Result<[u8; SALT_LEN], String> {
+    let mut salt = [0u8; SALT_LEN];
+    SystemRandom::new()
+      .fill(&mut salt)
+      .map_err(|_| "no random source for the storage key".to_string())?;
+    Ok(salt)
+  }
+
+  /// Sealed with a random nonce: the same value seals differently each time.
+  pub(crate) fn seal(&self, plain: &str) -> Result<String, String> {
+    let mut nonce = [0u8; NONCE_LEN];
+    SystemRandom::new()
+      .fill(&mut nonce)
+      .map_err(|_| "no random source for the storage key".to_string())?;
+    self.seal_with(nonce, plain)
+  }
+
+  /// Sealed with a nonce derived from the value, so equal values seal equally and a sealed
+  /// column can still be looked up (`WHERE path=?`). Reveals which rows share a value.
+  pub(crate) fn seal_key(&self, plain: &str) -> Result<String, String> {
+    let tag = hmac::sign(&self.mac_key, plain.as_bytes());
+    let mut nonce = [0u8; NONCE_LEN];
+    nonce.copy_from_slice(&tag.as_ref()[..NONCE_LEN]);
+    self.seal_with(nonce, plain)
+  }
+
+  fn seal_with(&self, nonce: [u8; NONCE_LEN], plain: &str) -> Result<String, String> {
+    let mut data = plain.as_bytes().to_vec();
+    self
+      .key()?
+      .seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::empty(), &mut data)
+      .map_err(|_| "sealing a storage field failed".to_string())?;
+    let mut out = nonce.to_vec();
+    out.extend_from_slice(&data);
+    Ok(format!("{SEALED_PREFIX}{}", STANDARD.encode(out)))
+  }
+
+  /// The plain value of `seal` / `seal_key` output; values that aren't sealed are returned as is.
+  pub(crate) fn open(&self, stored: &str) -> Result<String, String> {
+    let Some(encoded) = stored.strip_prefix(SEALED_PREFIX) else {
+      return Ok(stored.to_string());
+    };
+    let bad = || "storage field can't be decrypted with this passphrase".to_string();
+    let data = STANDARD.decode(encoded).map_err(|_| bad())?;
+    if data.len() < NONCE_LEN {
+      return Err(bad());
+    }
+    let (nonce, sealed) = data.split_at(NONCE_LEN);
+    let nonce = Nonce::try_assume_unique_for_key(nonce).map_err(|_| bad())?;
+    let mut sealed = sealed.to_vec();
+    let plain = self
+      .key()?
+      .open_in_place(nonce, Aad::empty(), &mut sealed)
+      .map_err(|_| bad())?;
+    String::from_utf8(plain.to_vec()).map_err(|_| bad())
+  }
+
+  fn key(&self) -> Result<LessSafeKey, String> {
+    UnboundKey::new(&AES_256_GCM, &self.enc_key)
+      .map(LessSafeKey::new)
+      .map_err(|_| "invalid storage key".to_string())
+  }
+}
+
+/// Whether `stored` was written by `FieldCipher::seal` / `seal_key`.
+pub(crate) fn is_sealed(stored: &str) -> bool {
+  stored.starts_with(SEALED_PREFIX)
+}
@@ -15,7 +15,7 @@ pub(crate) fn encode_cursor(c: Cursor) -> String {
 pub(crate) fn decode_cursor(token: Option<&str>) -> Result<Cursor, crate::engine::CoreError> {
   match token {
     None => Ok(Cursor { offset: 0, line: 0 }),
-    Some(t) if t.is_empty() => Ok(Cursor { offset: 0, line: 0 }),
+    Some("") => Ok(Cursor { offset: 0, line: 0 }),
     Some(t) => {
       let bytes = base64::engine::general_purpose::URL_SAFE_NO_PAD
         .decode(t)
@@ -27,3 +27,54 @@ pub(crate) fn decode_cursor(token: Option<&str>) -> Result<Cursor, crate::engine
   }
 }
 
+/// Marks a cursor whose record ids are estimates (see `page_at_position`); it isn't part of the
+/// base64 alphabet.
+const ESTIMATED_MARK: char = '~';
+
+/// `token` marked as carrying estimated record ids, so pages read from it say so
+/// (`RecordPage::ids_estimated`) and pass the mark on.
+pub(crate) fn mark_estimated(token: String) -> String {
+  format!("{ESTIMATED_MARK}{token}")
+}
+
+/// `token` without its estimate mark, and whether it had one.
+pub(crate) fn strip_estimated(token: Option<&str>) -> (Option<&str>, bool) {
+  match token.and_then(|t| t.strip_prefix(ESTIMATED_MARK)) {
+    Some(inner) => (Some(inner), true),
+    None => (token, false),
+  }
+}
+
+
+/// Paging position in a multi-file session: `inner` is the part-local cursor.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct ShardCursor {
+  pub part: u32,
+  /// Session-wide id of the part's first record.
+  pub base: u64,
+  /// Session-wide id of the next record to return.
+  pub next_id: u64,
+  pub inner: Option<String>,
+}
+
+pub(crate) fn encode_shard_cursor(c: &ShardCursor) -> String {
+  let json = serde_json::to_vec(c).expect("cursor serialize");
+  base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(json)
+}
+
+pub(crate) fn decode_shard_cursor(token: Option<&str>) -> Result<ShardCursor, crate::engine::CoreError> {
+  match token {
+    None | Some("") => Ok(ShardCursor {
+      part: 0,
+      base: 0,
+      next_id: 0,
+      inner: None,
+    }),
+    Some(t) => {
+      let bytes = base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(t)
+        .map_err(|e| crate::engine::CoreError::BadCursor(e.to_string()))?;
+      serde_json::from_slice(&bytes).map_err(|e| crate::engine::CoreError::BadCursor(e.to_string()))
+    }
+  }
+}
@@ -0,0 +1,74 @@
+use std::{
+  collections::{hash_map::DefaultHasher, HashMap, HashSet},
+  hash::{Hash, Hasher},
+  path::Path,
+};
+
+use serde_json::Value;
+
+use crate::{
+  cursor::Cursor,
+  engine::CoreError,
+  formats,
+  models::{CsvDialect, DedupSpec, FileFormat, RecordMeta},
+  sort::lookup,
+};
+
+/// Read every record once and return the metas `spec` keeps, in file order: the first record
+/// of each duplicate group, or (`only_duplicates`) every record of groups with more than one.
+///
+/// Records are grouped by a hash of their content (JSON re-serialized with sorted keys, so key
+/// order and whitespace do not matter) or of `spec.key`'s value. Records missing the key are
+/// never duplicates.
+pub(crate) fn dedup_record_order(
+  path: &Path,
+  format: FileFormat,
+  csv: &CsvDialect,
+  spec: &DedupSpec,
+) -> Result<Vec<RecordMeta>, CoreError> {
+  if spec.key.as_deref().is_some_and(str::is_empty) {
+    return Err(CoreError::InvalidArg("dedup key is empty".into()));
+  }
+  let mut keyed: Vec<(Option<u64>, RecordMeta)> = Vec::new();
+  let mut group_sizes: HashMap<u64, u32> = HashMap::new();
+  formats::for_each_record_with(path, format, csv, Cursor { offset: 0, line: 0 }, |r| {
+    let raw = r.raw.as_deref().unwrap_or(&r.preview);
+    let value = serde_json::from_str::<Value>(raw).ok();
+    let text = match (&spec.key, &value) {
+      (None, Some(v)) => Some(v.to_string()),
+      (None, None) => Some(raw.to_string()),
+      (Some(key), Some(v)) => lookup(v, key).filter(|v| !v.is_null()).map(Value::to_string),
+      (Some(_), None) => None,
+    };
+    let hash = text.map(|t| {
+      let mut hasher = DefaultHasher::new();
+      t.hash(&mut hasher);
+      hasher.finish()
+    });
+    if let Some(h) = hash {
+      *group_sizes.entry(h).or_default() += 1;
+    }
+    // Parquet rows have no byte offsets; the row index is all a view needs.
+    let meta = r.meta.clone().unwrap_or(RecordMeta {
+      line_no: r.id,
+      byte_offset: 0,
+      byte_len: 0,
+      part: None,
+    });
+    keyed.push((hash, meta));
+    true
+  })?;
+
+  let mut seen: HashSet<u64> = HashSet::new();
+  Ok(
+    keyed
+      .into_iter()
+      .filter(|(hash, _)| match hash {
+        None => !spec.only_duplicates,
+        Some(h) if spec.only_duplicates => group_sizes[h] > 1,
+        Some(h) => seen.insert(*h),
+      })
+      .map(|(_, meta)| meta)
+      .collect(),
+  )
+}
@@ -0,0 +1,322 @@
+use std::{iter::Peekable, str::Chars};
+
+use serde_json::{Map, Number, Value};
+
+use crate::{
+  engine::CoreError,
+  formats,
+  models::{CsvDialect, DerivedColumn},
+  sort::lookup,
+};
+
+/// Compiled `DerivedColumn`s of a session, evaluated per record.
+///
+/// Expressions are function calls over fields, `raw`, and literals:
+/// - `name`, `a.b` or `"col name"`: a CSV column / JSON key (dotted paths descend into objects)
+/// - `raw`: the record's full text (the JSON object for CSV rows)
+/// - `'text'` and numbers: literals
+/// - `len(x)`: characters of a string, items of an array or keys of an object
+/// - `lower(x)`, `upper(x)`
+/// - `json_extract(x, '$.path[0].key')`: `x` is a JSON value or a string holding JSON
+///
+/// Anything that does not apply (a missing field, `len` of a number) evaluates to `null`.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct DerivedSet {
+  columns: Vec<(String, Expr)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+  Raw,
+  Field(String),
+  Str(String),
+  Num(f64),
+  Call(Func, Vec<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Func {
+  Len,
+  Lower,
+  Upper,
+  JsonExtract,
+}
+
+impl Func {
+  fn from_name(name: &str) -> Option<Self> {
+    match name {
+      "len" => Some(Func::Len),
+      "lower" => Some(Func::Lower),
+      "upper" => Some(Func::Upper),
+      "json_extract" => Some(Func::JsonExtract),
+      _ => None,
+    }
+  }
+
+  fn arity(self) -> usize {
+    match self {
+      Func::Len | Func::Lower | Func::Upper => 1,
+      Func::JsonExtract => 2,
+    }
+  }
+}
+
+impl DerivedSet {
+  pub(crate) fn compile(columns: &[DerivedColumn]) -> Result<Self, CoreError> {
+    let mut compiled: Vec<(String, Expr)> = Vec::with_capacity(columns.len());
+    for c in columns {
+      let name = c.name.trim();
+      if name.is_empty() {
+        return Err(CoreError::InvalidArg("derived column name is empty".into()));
+      }
+      if compiled.iter().any(|(n, _)| n == name) {
+        return Err(CoreError::InvalidArg(format!("duplicate derived column: {name}")));
+      }
+      let expr = parse(&c.expr).map_err(|e| CoreError::InvalidArg(format!("derived column {name}: {e}")))?;
+      compiled.push((name.to_string(), expr));
+    }
+    Ok(Self { columns: compiled })
+  }
+
+  pub(crate) fn is_empty(&self) -> bool {
+    self.columns.is_empty()
+  }
+
+  pub(crate) fn names(&self) -> impl Iterator<Item = &str> {
+    self.columns.iter().map(|(n, _)| n.as_str())
+  }
+
+  /// Values of every column for a record whose full text is `raw`.
+  pub(crate) fn evaluate(&self, raw: &str) -> Map<String, Value> {
+    let value = serde_json::from_str::<Value>(raw).ok();
+    self.evaluate_value(raw, value.as_ref())
+  }
+
+  /// Same as `evaluate`, with `raw` already parsed (`None` if it is not JSON).
+  pub(crate) fn evaluate_value(&self, raw: &str, value: Option<&Value>) -> Map<String, Value> {
+    self
+      .columns
+      .iter()
+      .map(|(name, expr)| (name.clone(), eval(expr, raw, value)))
+      .collect()
+  }
+}
+
+/// Derived values of raw JSONL / CSV lines, for scan_all search.
+pub(crate) struct LineDeriver {
+  set: DerivedSet,
+  /// CSV sessions: the header the line cells are keyed by.
+  csv_headers: Option<Vec<String>>,
+  /// CSV sessions: whether line 0 is that header and how cells are typed.
+  csv: CsvDialect,
+}
+
+impl LineDeriver {
+  pub(crate) fn new(set: DerivedSet, csv_headers: Option<Vec<String>>, csv: CsvDialect) -> Self {
+    Self { set, csv_headers, csv }
+  }
+
+  /// Search text of the derived values of line `line_no` (none for the CSV header row).
+  pub(crate) fn search_text(&self, line_no: u64, line: &str) -> String {
+    let values = match &self.csv_headers {
+      Some(_) if self.csv.has_header && line_no == 0 => return String::new(),
+      Some(headers) => {
+        let obj = formats::csv_line_to_object(headers, line, &self.csv);
+        self.set.evaluate_value(&obj.to_string(), Some(&obj))
+      }
+      None => self.set.evaluate(line),
+    };
+    values.values().map(value_text).collect::<Vec<_>>().join("\n")
+  }
+}
+
+/// Text a derived value contributes to search / CSV cells (`null` is empty).
+pub(crate) fn value_text(v: &Value) -> String {
+  match v {
+    Value::Null => String::new(),
+    Value::String(s) => s.clone(),
+    other => other.to_string(),
+  }
+}
+
+fn eval(expr: &Expr, raw: &str, value: Option<&Value>) -> Value {
+  match expr {
+    Expr::Raw => Value::String(raw.to_string()),
+    Expr::Field(name) => value.and_then(|v| lookup(v, name)).cloned().unwrap_or(Value::Null),
+    Expr::Str(s) => Value::String(s.clone()),
+    Expr::Num(n) => number(*n),
+    Expr::Call(func, args) => {
+      let arg = |i: usize| eval(&args[i], raw, value);
+      match func {
+        Func::Len => match arg(0) {
+          Value::String(s) => number(s.chars().count() as f64),
+          Value::Array(a) => number(a.len() as f64),
+          Value::Object(o) => number(o.len() as f64),
+          _ => Value::Null,
+        },
+        Func::Lower => match arg(0) {
+          Value::String(s) => Value::String(s.to_lowercase()),
+          _ => Value::Null,
+        },
+        Func::Upper => match arg(0) {
+          Value::String(s) => Value::String(s.to_uppercase()),
+          _ => Value::Null,
+        },
+        Func::JsonExtract => {
+          let doc = match arg(0) {
+            Value::String(s) => serde_json::from_str::<Value>(&s).unwrap_or(Value::Null),
+            other => other,
+          };
+          match arg(1) {
+            Value::String(path) => json_path(&doc, &path).cloned().unwrap_or(Value::Null),
+            _ => Value::Null,
+          }
+        }
+      }
+    }
+  }
+}
+
+fn number(n: f64) -> Value {
+  if n.fract() == 0.0 && n.abs() < i64::MAX as f64 {
+    Value::from(n as i64)
+  } else {
+    Number::from_f64(n).map(Value::Number).unwrap_or(Value::Null)
+  }
+}
+
+/// `$`, `.key`, `['key']` and `[index]` steps; `None` if a step does not match.
+fn json_path<'a>(doc: &'a Value, path: &str) -> Option<&'a Value> {
+  let mut chars = path.trim().chars().peekable();
+  if chars.peek() == Some(&'$') {
+    chars.next();
+  }
+  let mut cur = doc;
+  while let Some(c) = chars.next() {
+    match c {
+      '.' => {
+        let mut key = String::new();
+        while let Some(&c) = chars.peek() {
+          if c == '.' || c == '[' {
+            break;
+          }
+          key.push(c);
+          chars.next();
+        }
+        cur = cur.as_object()?.get(&key)?;
+      }
+      '[' => {
+        let mut inner = String::new();
+        for c in chars.by_ref() {
+          if c == ']' {
+            break;
+          }
+          inner.push(c);
+        }
+        let inner = inner.trim();
+        cur = match inner.strip_prefix('\'').and_then(|s| s.strip_suffix('\'')) {
+          Some(key) => cur.as_object()?.get(key)?,
+          None => cur.as_array()?.get(inner.parse::<usize>().ok()?)?,
+        };
+      }
+      _ => return None,
+    }
+  }
+  Some(cur)
+}
+
+fn parse(text: &str) -> Result<Expr, String> {
+  let mut chars = text.chars().peekable();
+  let expr = parse_expr(&mut chars)?;
+  skip_ws(&mut chars);
+  match chars.next() {
+    None => Ok(expr),
+    Some(c) => Err(format!("unexpected '{c}'")),
+  }
+}
+
+fn parse_expr(chars: &mut Peekable<Chars<'_>>) -> Result<Expr, String> {
+  skip_ws(chars);
+  match chars.peek().copied() {
+    None => Err("expression is empty".into()),
+    Some('\'') => {
+      chars.next();
+      Ok(Expr::Str(read_quoted(chars, '\'')?))
+    }
+    Some('"') => {
+      chars.next();
+      Ok(Expr::Field(read_quoted(chars, '"')?))
+    }
+    Some(c) if c.is_ascii_digit() || c == '-' => {
+      let mut s = String::new();
+      while let Some(&c) = chars.peek() {
+        if !(c.is_ascii_digit() || c == '.' || c == '-' || c == 'e' || c == 'E' || c == '+') {
+          break;
+        }
+        s.push(c);
+        chars.next();
+      }
+      s.parse::<f64>().map(Expr::Num).map_err(|_| format!("bad number: {s}"))
+    }
+    Some(c) if is_ident_char(c) => {
+      let mut name = String::new();
+      while let Some(&c) = chars.peek() {
+        if !is_ident_char(c) {
+          break;
+        }
+        name.push(c);
+        chars.next();
+      }
+      skip_ws(chars);
+      if chars.peek() != Some(&'(') {
+        return Ok(if name == "raw" { Expr::Raw } else { Expr::Field(name) });
+      }
+      chars.next();
+      let func = Func::from_name(&name).ok_or_else(|| format!("unknown function: {name}"))?;
+      let mut args = Vec::new();
+      skip_ws(chars);
+      if chars.peek() == Some(&')') {
+        chars.next();
+      } else {
+        loop {
+          args.push(parse_expr(chars)?);
+          skip_ws(chars);
+          match chars.next() {
+            Some(',') => continue,
+            Some(')') => break,
+            Some(c) => return Err(format!("unexpected '{c}' in {name}()")),
+            None => return Err(format!("missing ')' after {name}(")),
+          }
+        }
+      }
+      if args.len() != func.arity() {
+        return Err(format!("{name}() takes {} argument(s), got {}", func.arity(), args.len()));
+      }
+      Ok(Expr::Call(func, args))
+    }
+    Some(c) => Err(format!("unexpected '{c}'")),
+  }
+}
+
+/// Body of a quoted literal (the opening quote is consumed); `\` escapes the next character.
+fn read_quoted(chars: &mut Peekable<Chars<'_>>, quote: char) -> Result<String, String> {
+  let mut out = String::new();
+  while let Some(c) = chars.next() {
+    match c {
+      '\\' => out.push(chars.next().ok_or("unterminated string")?),
+      c if c == quote => return Ok(out),
+      c => out.push(c),
+    }
+  }
+  Err("unterminated string".into())
+}
+
+fn is_ident_char(c: char) -> bool {
+  c.is_alphanumeric() || matches!(c, '_' | '.' | '$')
+}
+
+fn skip_ws(chars: &mut Peekable<Chars<'_>>) {
+  while chars.peek().is_some_and(|c| c.is_whitespace()) {
+    chars.next();
+  }
+}
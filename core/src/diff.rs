@@ -0,0 +1,245 @@
+use std::{
+  collections::{hash_map::DefaultHasher, HashMap, HashSet},
+  hash::{Hash, Hasher},
+  path::Path,
+};
+
+use serde_json::Value;
+
+use crate::{
+  engine::CoreError,
+  formats,
+  models::{DiffAlign, DiffChange, DiffEntry, DiffSummary, FileFormat, Record, RecordMeta},
+  progress::ScanProgress,
+  sort::lookup,
+};
+
+/// One of the two files being compared.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct DiffSide<'a> {
+  pub path: &'a Path,
+  pub format: &'a FileFormat,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct DiffOutcome {
+  pub entries: Vec<DiffEntry>,
+  pub summary: DiffSummary,
+}
+
+/// A record reduced to what the diff keeps: its page identity and a hash of its content.
+struct Seen {
+  record: Record,
+  hash: u64,
+}
+
+/// Compare `left` (before) with `right` (after) record by record.
+///
+/// Records are equal when they parse to the same JSON value (key order and whitespace don't
+/// matter), or else have the same text. Entries follow the right file's order; removed records
+/// come last, in left file order. By-key diffs skip records without the key (or with a `null` /
+/// empty one) and repeats of a key already seen on their side. Only the left file is held in
+/// memory.
+///
+/// Progress covers the left pass as 0-50 and the right pass as 50-100. Stopping keeps the
+/// entries found so far (`summary.complete: false`).
+pub(crate) fn diff_records(
+  left: DiffSide<'_>,
+  right: DiffSide<'_>,
+  align: &DiffAlign,
+  preview_max_chars: usize,
+  should_stop: impl Fn() -> bool,
+  mut on_progress: impl FnMut(ScanProgress),
+) -> Result<DiffOutcome, CoreError> {
+  let key = match align {
+    DiffAlign::ByLine => None,
+    DiffAlign::ByKey { key } if key.is_empty() => return Err(CoreError::InvalidArg("diff key is empty".into())),
+    DiffAlign::ByKey { key } => Some(key.as_str()),
+  };
+  let mut out = DiffOutcome::default();
+
+  let mut lefts: Vec<(Option<String>, Seen)> = Vec::new();
+  let mut left_by_key: HashMap<String, usize> = HashMap::new();
+  let mut left_done = ScanProgress::default();
+  let on_left = |p: ScanProgress| {
+    left_done = p;
+    on_progress(ScanProgress { pct: p.pct / 2, ..p });
+  };
+  let stopped = scan_side(left, &should_stop, on_left, |r| {
+    let (k, seen) = reduce(r, key, preview_max_chars);
+    match (key, k) {
+      (None, _) => lefts.push((None, seen)),
+      (Some(_), Some(k)) if !left_by_key.contains_key(&k) => {
+        left_by_key.insert(k.clone(), lefts.len());
+        lefts.push((Some(k), seen));
+      }
+      (Some(_), _) => out.summary.skipped += 1,
+    }
+  })?;
+  if stopped {
+    return Ok(out);
+  }
+
+  let mut matched = vec![false; lefts.len()];
+  let mut right_keys: HashSet<String> = HashSet::new();
+  let mut position = 0usize;
+  let on_right = |p: ScanProgress| {
+    on_progress(ScanProgress {
+      pct: 50 + p.pct / 2,
+      bytes: left_done.bytes + p.bytes,
+      records: left_done.records + p.records,
+    })
+  };
+  let stopped = scan_side(right, &should_stop, on_right, |r| {
+    let (k, seen) = reduce(r, key, preview_max_chars);
+    let paired = match (key, k) {
+      (None, _) => {
+        position += 1;
+        Some((position - 1, None, seen))
+      }
+      (Some(_), Some(k)) if right_keys.insert(k.clone()) => Some((
+        left_by_key.get(&k).copied().unwrap_or(usize::MAX),
+        Some(k),
+        seen,
+      )),
+      (Some(_), _) => {
+        out.summary.skipped += 1;
+        None
+      }
+    };
+    let Some((index, k, seen)) = paired else {
+      return;
+    };
+    match lefts.get(index) {
+      Some((_, before)) => {
+        matched[index] = true;
+        if before.hash == seen.hash {
+          out.summary.unchanged += 1;
+        } else {
+          out.summary.changed += 1;
+          out.entries.push(DiffEntry {
+            change: DiffChange::Changed,
+            key: k,
+            left: Some(before.record.clone()),
+            right: Some(seen.record),
+          });
+        }
+      }
+      None => {
+        out.summary.added += 1;
+        out.entries.push(DiffEntry {
+          change: DiffChange::Added,
+          key: k,
+          left: None,
+          right: Some(seen.record),
+        });
+      }
+    }
+  })?;
+  if stopped {
+    return Ok(out);
+  }
+
+  for ((k, before), matched) in lefts.into_iter().zip(matched) {
+    if matched {
+      continue;
+    }
+    out.summary.removed += 1;
+    out.entries.push(DiffEntry {
+      change: DiffChange::Removed,
+      key: k,
+      left: Some(before.record),
+      right: None,
+    });
+  }
+  out.summary.complete = true;
+  Ok(out)
+}
+
+/// Stream every record of `side`; returns whether `should_stop` cut the scan short.
+fn scan_side(
+  side: DiffSide<'_>,
+  should_stop: &impl Fn() -> bool,
+  mut on_progress: impl FnMut(ScanProgress),
+  mut on_record: impl FnMut(&Record),
+) -> Result<bool, CoreError> {
+  // Progress is measured in bytes for text formats and in rows for parquet.
+  let total = match side.format {
+    FileFormat::Parquet => formats::read_parquet_row_count(side.path)?,
+    _ => std::fs::metadata(side.path)?.len(),
+  };
+  let mut stopped = false;
+  let mut records = 0u64;
+  formats::for_each_record(side.path, side.format.clone(), |r| {
+    if should_stop() {
+      stopped = true;
+      return false;
+    }
+    on_record(r);
+    records += 1;
+    let (done, bytes) = match &r.meta {
+      Some(m) => (m.byte_offset + m.byte_len, m.byte_offset + m.byte_len),
+      None => (r.id + 1, 0),
+    };
+    on_progress(ScanProgress::new(done, total, bytes, records));
+    true
+  })?;
+  Ok(stopped)
+}
+
+fn reduce(r: &Record, key: Option<&str>, preview_max_chars: usize) -> (Option<String>, Seen) {
+  let raw = r.raw.as_deref().unwrap_or("");
+  let value = serde_json::from_str::<Value>(raw).ok();
+  let mut hasher = DefaultHasher::new();
+  match &value {
+    // serde_json maps are sorted, so this is independent of the file's key order.
+    Some(v) => v.to_string().hash(&mut hasher),
+    None => raw.hash(&mut hasher),
+  }
+  let k = match (key, &value) {
+    (Some(key), Some(v)) => lookup(v, key).and_then(key_text),
+    _ => None,
+  };
+  // Parquet rows have no byte offsets; the row index is enough for `get_record_raw`.
+  let meta = r.meta.clone().unwrap_or(RecordMeta {
+    line_no: r.id,
+    byte_offset: 0,
+    byte_len: 0,
+    part: None,
+  });
+  let record = Record {
+    id: r.id,
+    preview: truncate_chars(raw, preview_max_chars),
+    raw: None,
+    meta: Some(meta),
+    derived: None,
+    warning: None,
+    columns: None,
+  };
+  (k, Seen { record, hash: hasher.finish() })
+}
+
+fn key_text(v: &Value) -> Option<String> {
+  match v {
+    Value::Null => None,
+    // Empty CSV cells count as missing.
+    Value::String(s) if s.is_empty() => None,
+    Value::String(s) => Some(s.clone()),
+    other => Some(other.to_string()),
+  }
+}
+
+fn truncate_chars(s: &str, max: usize) -> String {
+  if max == 0 {
+    return String::new();
+  }
+  let mut out = String::new();
+  for (i, ch) in s.chars().enumerate() {
+    if i >= max {
+      out.push('…');
+      break;
+    }
+    out.push(ch);
+  }
+  out
+}
@@ -0,0 +1,135 @@
+use std::{borrow::Cow, fs::File, io::Read, path::Path};
+
+use crate::{engine::CoreError, models::TextEncoding};
+
+/// Bytes read from the file start to guess its encoding.
+const DETECT_SAMPLE_BYTES: u64 = 64 * 1024;
+
+/// Guess the text encoding of a JSONL / CSV file from its first bytes.
+pub(crate) fn detect_file_encoding(path: &Path) -> Result<TextEncoding, CoreError> {
+  let mut sample = Vec::new();
+  File::open(path)?.take(DETECT_SAMPLE_BYTES).read_to_end(&mut sample)?;
+  Ok(detect_encoding(&sample))
+}
+
+/// UTF-8 if the bytes are valid UTF-8 (a sequence cut off at the end of the sample is fine),
+/// then GB18030 / Shift-JIS if every non-ASCII byte fits that encoding's byte structure, else
+/// Latin-1 (which accepts any byte).
+pub(crate) fn detect_encoding(sample: &[u8]) -> TextEncoding {
+  match std::str::from_utf8(sample) {
+    Ok(_) => return TextEncoding::Utf8,
+    Err(e) if e.error_len().is_none() => return TextEncoding::Utf8,
+    Err(_) => {}
+  }
+  // "Typical" pairs: the GB2312 hanzi area for GBK, kana / common kanji rows for Shift-JIS.
+  let gb = scan_double_byte(sample, is_gb_lead, is_gb_trail, |_| false, |lead, trail| {
+    (0xB0..=0xF7).contains(&lead) && trail >= 0xA1
+  });
+  let sjis = scan_double_byte(
+    sample,
+    is_sjis_lead,
+    is_sjis_trail,
+    |b| (0xA1..=0xDF).contains(&b),
+    |lead, _| (0x81..=0x9F).contains(&lead),
+  );
+  match (gb, sjis) {
+    (Some(gb), Some(sjis)) => {
+      if sjis > gb {
+        TextEncoding::ShiftJis
+      } else {
+        TextEncoding::Gb18030
+      }
+    }
+    (Some(_), None) => TextEncoding::Gb18030,
+    (None, Some(_)) => TextEncoding::ShiftJis,
+    (None, None) => TextEncoding::Latin1,
+  }
+}
+
+/// Decode record bytes for display / search. Bytes invalid in the encoding become replacement
+/// characters.
+pub(crate) fn decode(bytes: &[u8], encoding: TextEncoding) -> Cow<'_, str> {
+  match encoding {
+    TextEncoding::Latin1 => match std::str::from_utf8(bytes) {
+      // Pure ASCII (the common case for most lines) needs no copy.
+      Ok(s) if s.is_ascii() => Cow::Borrowed(s),
+      _ => Cow::Owned(bytes.iter().map(|&b| b as char).collect()),
+    },
+    TextEncoding::Utf8 => String::from_utf8_lossy(bytes),
+    TextEncoding::Gb18030 => encoding_rs::GB18030.decode_without_bom_handling(bytes).0,
+    TextEncoding::ShiftJis => encoding_rs::SHIFT_JIS.decode_without_bom_handling(bytes).0,
+  }
+}
+
+/// Encode edited text back into a file's encoding. Fails on characters the encoding can't hold
+/// (Latin-1: above U+00FF; Shift-JIS: outside JIS X 0208; GB18030 holds all of Unicode).
+pub(crate) fn encode(text: &str, encoding: TextEncoding) -> Result<Vec<u8>, CoreError> {
+  let codec = match encoding {
+    TextEncoding::Utf8 => return Ok(text.as_bytes().to_vec()),
+    TextEncoding::Latin1 => {
+      return text
+        .chars()
+        .map(|c| u8::try_from(u32::from(c)).ok())
+        .collect::<Option<Vec<u8>>>()
+        .ok_or_else(|| CoreError::InvalidArg("text has characters outside Latin-1".into()))
+    }
+    TextEncoding::Gb18030 => encoding_rs::GB18030,
+    TextEncoding::ShiftJis => encoding_rs::SHIFT_JIS,
+  };
+  let (bytes, _, unmappable) = codec.encode(text);
+  if unmappable {
+    return Err(CoreError::InvalidArg(format!("text has characters outside {encoding:?}")));
+  }
+  Ok(bytes.into_owned())
+}
+
+/// Walk `sample` as a double-byte encoding. Returns the number of `is_typical` pairs, or `None`
+/// on any invalid sequence.
+fn scan_double_byte(
+  sample: &[u8],
+  is_lead: impl Fn(u8) -> bool,
+  is_trail: impl Fn(u8) -> bool,
+  is_single: impl Fn(u8) -> bool,
+  is_typical: impl Fn(u8, u8) -> bool,
+) -> Option<u64> {
+  let mut typical = 0u64;
+  let mut i = 0usize;
+  while i < sample.len() {
+    let b = sample[i];
+    if b < 0x80 || is_single(b) {
+      i += 1;
+      continue;
+    }
+    if !is_lead(b) {
+      return None;
+    }
+    match sample.get(i + 1) {
+      // Cut off at the end of the sample.
+      None => break,
+      Some(&t) if is_trail(t) => {
+        if is_typical(b, t) {
+          typical += 1;
+        }
+        i += 2;
+      }
+      Some(_) => return None,
+    }
+  }
+  Some(typical)
+}
+
+fn is_gb_lead(b: u8) -> bool {
+  (0x81..=0xFE).contains(&b)
+}
+
+fn is_gb_trail(b: u8) -> bool {
+  (0x40..=0x7E).contains(&b) || (0x80..=0xFE).contains(&b) || b.is_ascii_digit()
+}
+
+fn is_sjis_lead(b: u8) -> bool {
+  (0x81..=0x9F).contains(&b) || (0xE0..=0xFC).contains(&b)
+}
+
+fn is_sjis_trail(b: u8) -> bool {
+  (0x40..=0x7E).contains(&b) || (0x80..=0xFC).contains(&b)
+}
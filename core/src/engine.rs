@@ -1,9 +1,13 @@
 use std::{
-  collections::HashMap,
-  io::{Read, Seek, SeekFrom},
+  collections::{BTreeMap, HashMap},
+  io::{Read, Seek, SeekFrom, Write},
   path::{Path, PathBuf},
-  sync::Arc,
-  time::{SystemTime, UNIX_EPOCH},
+  sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+  },
+  thread,
+  time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
 use parking_lot::Mutex;
@@ -11,16 +15,32 @@ use thiserror::Error;
 use uuid::Uuid;
 
 use crate::{
-  cursor::{decode_cursor, encode_cursor},
+  cursor::{
+    decode_cursor, decode_shard_cursor, encode_cursor, encode_shard_cursor, mark_estimated, strip_estimated, Cursor,
+    ShardCursor,
+  },
+  derive::{DerivedSet, LineDeriver},
+  encoding as encoding_impl,
   export as export_impl,
-  formats,
+  formats::{self, ParquetConn},
+  json_index::{IndexedNode, JsonNodeIndex},
+  line_index::{self, LineIndex},
+  remote::{self, Download, RemoteFile},
+  shards::{first_local_id, ShardSet},
   models::{
-    ExportFormat, ExportRequest, ExportResult, FileFormat, RecordMeta, RecordPage, SearchMode,
-    SearchQuery, SearchResult, SessionInfo, StatsResult, Task, TaskInfo, TaskKind, JsonChildrenPage,
-    JsonPathSegment, JsonNodeSummary, JsonChildrenPageOffset, JsonNodeSummaryOffset,
+    DerivedColumn, DiffAlign, DiffPage, ExportFormat, ExportRequest, ExportResult, FileChange, FileFormat, GotoRecord, NewRecords, PositionPage, RecordCount, RecordLabel, RecordMeta, RecordPage,
+    DedupSpec, SearchCount, SearchMode, SearchQuery, SeekPosition, SearchResult, SessionInfo, SessionMetrics, SessionRefresh, SessionSchema, StatsDiff, StatsReportFormat, SortSpec, StatsResult, StatsSampleStrategy,
+    Task, TaskError, TaskEvent, TaskHistoryEntry, TaskInfo, TaskKind, TextEncoding, RestoredTab, RestoredWorkspace, Workspace, WorkspaceTab, DatasetCollection, OpenedCollection, ViewPrefs, ExportPreset, InterruptedExport, ParquetMetadata, ParquetFooterStats, ColumnFilter, CsvDialect, CsvSchema, CsvWarnings, Record, RecordWarning,
+    JsonChildrenPage, JsonChildItem, JsonChildSort, JsonPathSegment, JsonPathStrings, JsonNodeSummary, JsonChildrenPageOffset, JsonNodeSummaryOffset, JsonNodeStats, JsonTablePage, JsonValidation, JsonFindQuery,
+    JsonFindResult, JsonNodeKind, JsonPathResult, Base64Preview,
   },
-  storage::{Storage, StorageOptions},
-  tasks::{TaskManager, TaskManagerOptions},
+  schema as schema_impl,
+  dedup as dedup_impl,
+  sort as sort_impl,
+  stats as stats_impl,
+  storage::{RecentFile, Storage, StorageBackup, StorageLimits, StorageMaintenance, StorageOptions, StorageReport, StoredRecordLabel},
+  tasks::{ScanCacheKey, TaskManager, TaskManagerOptions, TaskOrigin},
+  text_index::TextIndex,
 };
 
 #[derive(Debug, Error)]
@@ -37,16 +57,59 @@ pub enum CoreError {
   InvalidArg(String),
   #[error("storage error: {0}")]
   Storage(String),
+  #[error("http error: {0}")]
+  Http(String),
+  #[error("duckdb error: {0}")]
+  Duckdb(String),
   #[error("task error: {0}")]
   Task(String),
 }
 
+impl From<&CoreError> for TaskError {
+  fn from(e: &CoreError) -> Self {
+    match e {
+      CoreError::Io(e) => e.into(),
+      CoreError::Storage(message) | CoreError::Http(message) => TaskError::Io { message: message.clone() },
+      CoreError::Duckdb(message) => TaskError::Duckdb { message: message.clone() },
+      CoreError::UnsupportedFormat(_)
+      | CoreError::UnknownSession(_)
+      | CoreError::BadCursor(_)
+      | CoreError::InvalidArg(_)
+      | CoreError::Task(_) => TaskError::Format { message: e.to_string() },
+    }
+  }
+}
+
 #[derive(Debug, Clone)]
 pub struct CoreOptions {
   pub default_page_size: usize,
   pub preview_max_chars: usize,
   pub raw_max_chars: usize,
+  /// Background tasks running at once; further ones are queued until a slot frees up.
   pub max_concurrent_tasks: usize,
+  /// Memory a scan_all task may use for its hits; hits past it are kept in a temp file instead.
+  /// 0 keeps every hit in memory.
+  pub task_memory_budget_bytes: usize,
+  /// JSONL / CSV / JSON files at least this large get a background record index built on open.
+  pub line_index_min_bytes: u64,
+  /// JSON records at least this large get a background node index built on their first tree
+  /// call (see `build_json_node_index`).
+  pub json_node_index_min_bytes: u64,
+  /// Sessions untouched for this long are closed automatically (checked on `open_file`,
+  /// `list_sessions` and page reads: `next_page`, `page_at`, `page_at_position`). `None` keeps
+  /// sessions until `close_session`.
+  pub session_idle_timeout_ms: Option<u64>,
+  /// Where `open_stream` spools piped input. `None` uses the system temp directory.
+  pub spool_dir: Option<PathBuf>,
+  /// Where `open_file` caches remote (http/https) files. `None` uses `datalens-http-cache` in
+  /// the system temp directory.
+  pub remote_cache_dir: Option<PathBuf>,
+  /// Minutes east of UTC parquet TIMESTAMPTZ values are shown at (`120`: `+02:00`); 0 shows them
+  /// in UTC (`…Z`). Plain TIMESTAMP values are shown as stored, without a zone.
+  pub parquet_utc_offset_minutes: i32,
+  /// Pages each parquet session keeps in memory, so paging back to a recent page doesn't query
+  /// the file again; 0 turns the cache off. Dropped on `reload_session`.
+  pub parquet_page_cache_pages: usize,
   pub storage: StorageOptions,
 }
 
@@ -57,16 +120,189 @@ impl Default for CoreOptions {
       preview_max_chars: 300,
       raw_max_chars: 40_000,
       max_concurrent_tasks: 2,
+      task_memory_budget_bytes: 64 * 1024 * 1024,
+      line_index_min_bytes: 64 * 1024 * 1024,
+      json_node_index_min_bytes: 64 * 1024 * 1024,
+      session_idle_timeout_ms: None,
+      spool_dir: None,
+      remote_cache_dir: None,
+      parquet_utc_offset_minutes: 0,
+      parquet_page_cache_pages: 8,
       storage: StorageOptions::default(),
     }
   }
 }
 
+/// File size + mtime (ms), used to tell whether a persisted line index is still valid.
+fn file_identity(path: &Path) -> Option<(u64, i64)> {
+  let meta = std::fs::metadata(path).ok()?;
+  let mtime_ms = meta
+    .modified()
+    .ok()?
+    .duration_since(UNIX_EPOCH)
+    .ok()?
+    .as_millis() as i64;
+  Some((meta.len(), mtime_ms))
+}
+
+/// Settings key of the `set_max_concurrent_tasks` limit.
+const MAX_CONCURRENT_TASKS_SETTING: &str = "max_concurrent_tasks";
+/// Setting holding the `Workspace` saved by `save_workspace`.
+const WORKSPACE_SETTING: &str = "workspace";
+
+/// Line-format files up to this size are counted synchronously in `count_records`.
+const COUNT_SYNC_MAX_BYTES: u64 = 32 * 1024 * 1024;
+
+/// How long `open_stream` waits for a full first page before returning what arrived.
+const STREAM_FIRST_PAGE_WAIT: Duration = Duration::from_secs(2);
+
+/// Malformed CSV rows a session remembers for `csv_warnings`.
+const MAX_CSV_WARNINGS: usize = 1000;
+
 #[derive(Debug, Clone)]
 struct SessionState {
   info: SessionInfo,
   format: FileFormat,
   last_page: Option<crate::models::RecordPage>,
+  /// Exact record count once known (see `count_records`).
+  record_count: Option<u64>,
+  /// Background count in flight for this session.
+  count_task_id: Option<String>,
+  /// Background `goto_record` scan in flight for this session.
+  goto_task_id: Option<String>,
+  /// Sparse record offsets for JSONL/CSV, grown by `page_at` or built on open.
+  line_index: Arc<Mutex<LineIndex>>,
+  /// Last API call touching this session (drives idle eviction).
+  last_access_ms: i64,
+  /// Multi-file sessions only (see `open_files`); `info.path` is then the first part.
+  shards: Option<Arc<Mutex<ShardSet>>>,
+  /// Follow mode (JSONL/CSV): where `poll_new_records` resumes. Starts as the file length at
+  /// open; the record id is resolved on the first poll.
+  follow: Option<FollowCursor>,
+  /// File size + mtime as of open / the last `refresh_session`.
+  identity: Option<(u64, i64)>,
+  /// Filtered sessions only (see `open_search_results`): the hit records, in file order.
+  view: Option<Arc<Vec<RecordMeta>>>,
+  /// Compiled `info.derived`.
+  derived: Arc<DerivedSet>,
+  /// Bumped by `reload_session` when the file changed; cursors from earlier epochs are rejected.
+  cursor_epoch: u32,
+  /// Streamed sessions: `info.path` is a spool file, deleted once no session uses it.
+  spooled: bool,
+  /// Files written by `write_pretty_record`, deleted with the session.
+  pretty_files: Vec<PathBuf>,
+  /// Served pages and cache use (see `session_metrics`).
+  metrics: PageMetrics,
+  /// Malformed CSV rows met by served pages (see `csv_warnings`).
+  csv_warnings: WarningLog,
+  /// Single-file Parquet sessions: the DuckDB connection their pages, raw fetches and exports
+  /// reuse (replaced by `reload_session`).
+  parquet: Option<Arc<Mutex<ParquetConn>>>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct PageMetrics {
+  pages: u64,
+  records: u64,
+  bytes: u64,
+  cache_hits: u64,
+  cache_misses: u64,
+  page_time: Duration,
+  slowest_page: Duration,
+}
+
+impl PageMetrics {
+  /// Metrics of a session whose open call read `page` in `elapsed`.
+  fn opened_with(page: &RecordPage, elapsed: Duration) -> Self {
+    let mut metrics = Self::default();
+    metrics.record_page(page, elapsed);
+    metrics
+  }
+
+  fn record_page(&mut self, page: &RecordPage, elapsed: Duration) {
+    self.pages += 1;
+    self.records += page.records.len() as u64;
+    self.bytes += page.records.iter().filter_map(|r| r.meta.as_ref()).map(|m| m.byte_len).sum::<u64>();
+    self.page_time += elapsed;
+    self.slowest_page = self.slowest_page.max(elapsed);
+  }
+
+  fn to_public(&self) -> SessionMetrics {
+    let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+    SessionMetrics {
+      pages_served: self.pages,
+      records_served: self.records,
+      bytes_read: self.bytes,
+      cache_hits: self.cache_hits,
+      cache_misses: self.cache_misses,
+      avg_page_ms: if self.pages == 0 { 0.0 } else { ms(self.page_time) / self.pages as f64 },
+      slowest_page_ms: ms(self.slowest_page),
+    }
+  }
+}
+
+#[derive(Debug, Clone, Default)]
+struct WarningLog {
+  rows: BTreeMap<u64, RecordWarning>,
+  truncated: bool,
+}
+
+impl WarningLog {
+  fn from_records(records: &[Record]) -> Self {
+    let mut log = Self::default();
+    log.note(records);
+    log
+  }
+
+  /// Keeps the warnings of `records`; a record seen again replaces its entry.
+  fn note(&mut self, records: &[Record]) {
+    for r in records {
+      let Some(warning) = &r.warning else { continue };
+      if self.rows.len() >= MAX_CSV_WARNINGS && !self.rows.contains_key(&r.id) {
+        self.truncated = true;
+        continue;
+      }
+      self.rows.insert(
+        r.id,
+        RecordWarning {
+          id: r.id,
+          meta: r.meta.clone(),
+          warning: warning.clone(),
+        },
+      );
+    }
+  }
+
+  fn to_public(&self) -> CsvWarnings {
+    CsvWarnings {
+      rows: self.rows.values().cloned().collect(),
+      truncated: self.truncated,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct FollowCursor {
+  offset: u64,
+  line: Option<u64>,
+}
+
+/// How page records are rendered: size limits, column projection (CSV / Parquet), row filters
+/// (Parquet) and the text encoding of JSONL / CSV bytes.
+#[derive(Debug, Clone, Copy)]
+struct RecordRender<'a> {
+  preview_max_chars: usize,
+  raw_max_chars: usize,
+  columns: Option<&'a [String]>,
+  /// Parquet: only rows meeting these (see `SessionInfo::filters`).
+  filters: Option<&'a [ColumnFilter]>,
+  encoding: TextEncoding,
+  /// CSV: how rows are read (see `SessionInfo::csv`). Unset: the default dialect.
+  csv: Option<&'a CsvDialect>,
+  /// JSON: values are read leniently (see `SessionInfo::json_lenient`).
+  json_lenient: bool,
+  /// Parquet: the session's connection; without it a throwaway one is opened.
+  parquet: Option<&'a Mutex<ParquetConn>>,
 }
 
 #[derive(Clone)]
@@ -75,23 +311,72 @@ pub struct CoreEngine {
   sessions: Arc<Mutex<HashMap<String, SessionState>>>,
   tasks: TaskManager,
   storage: Storage,
+  json_indexes: Arc<Mutex<HashMap<JsonIndexKey, IndexSlot<JsonNodeIndex>>>>,
+  text_indexes: Arc<Mutex<HashMap<TextIndexKey, IndexSlot<TextIndex>>>>,
+}
+
+/// File path, size, mtime and record offset, so a changed file never reuses a JSON node index.
+type JsonIndexKey = (String, u64, i64, u64);
+
+/// File path, size, mtime and the encoding lines were decoded with (see `TextIndex`).
+type TextIndexKey = (String, u64, i64, TextEncoding);
+
+enum IndexSlot<T> {
+  /// The task building it.
+  Building(String),
+  Ready(Arc<T>),
+}
+
+impl<T> Clone for IndexSlot<T> {
+  fn clone(&self) -> Self {
+    match self {
+      Self::Building(id) => Self::Building(id.clone()),
+      Self::Ready(index) => Self::Ready(index.clone()),
+    }
+  }
 }
 
 impl CoreEngine {
   pub fn new(options: CoreOptions) -> Result<Self, CoreError> {
-    let storage = Storage::new(options.storage.clone()).map_err(|e| CoreError::Storage(e))?;
-    let tasks = TaskManager::new(TaskManagerOptions {
-      max_concurrent_tasks: options.max_concurrent_tasks,
-    });
+    let storage = Storage::new(options.storage.clone()).map_err(CoreError::Storage)?;
+    // A limit set with `set_max_concurrent_tasks` outlives the engine.
+    let max_concurrent_tasks = storage
+      .get_setting_json(MAX_CONCURRENT_TASKS_SETTING)
+      .ok()
+      .flatten()
+      .and_then(|v| serde_json::from_str::<usize>(&v).ok())
+      .filter(|&n| n > 0)
+      .unwrap_or(options.max_concurrent_tasks);
+    let tasks = TaskManager::new(
+      TaskManagerOptions {
+        max_concurrent_tasks,
+        task_memory_budget_bytes: options.task_memory_budget_bytes,
+        parquet_utc_offset_minutes: options.parquet_utc_offset_minutes,
+      },
+      storage.clone(),
+    );
+    // Exports still journaled were cut short by a crash: drop what they left behind and keep
+    // them listed until the user has seen them.
+    for export in storage.mark_interrupted_exports().map_err(CoreError::Storage)? {
+      let partial = PathBuf::from(&export.temp_path);
+      let _ = std::fs::remove_file(staging_path(&partial));
+      let _ = std::fs::remove_file(partial);
+    }
     Ok(Self {
       options,
       sessions: Arc::new(Mutex::new(HashMap::new())),
       tasks,
       storage,
+      json_indexes: Arc::new(Mutex::new(HashMap::new())),
+      text_indexes: Arc::new(Mutex::new(HashMap::new())),
     })
   }
 
-  /// IPC API: open_file(path) -> { session, first_page }
+  /// IPC API: open_file(path, decryption_key?) -> { session, first_page }
+  ///
+  /// `path` may also be an http(s) URL, opened from a local cache copy (see `open_remote`).
+  /// Parquet files using modular encryption are rejected with `InvalidArg`; open them with
+  /// `open_file_with_key`.
   pub fn open_file(&self, path: impl AsRef<Path>) -> Result<(SessionInfo, RecordPage), CoreError> {
     self.open_file_with_progress(path, |_| {})
   }
@@ -103,38 +388,112 @@ impl CoreEngine {
   pub fn open_file_with_progress(
     &self,
     path: impl AsRef<Path>,
+    on_progress_pct: impl FnMut(u8),
+  ) -> Result<(SessionInfo, RecordPage), CoreError> {
+    self.open_local(path.as_ref(), None, on_progress_pct)
+  }
+
+  /// Like `open_file_with_progress`, for parquet files with an encrypted footer: `decryption_key`
+  /// (AES, 16 / 24 / 32 bytes as is or base64) is handed to DuckDB's reader and kept with the
+  /// session (in memory only) for its pages, exports, scans and reloads. Other files ignore it.
+  ///
+  /// The footer APIs (`parquet_metadata`, `parquet_footer_stats`) and the whole-file tasks that
+  /// reopen the file (stats, sorted / dedup views) are not available for encrypted files.
+  pub fn open_file_with_key(
+    &self,
+    path: impl AsRef<Path>,
+    decryption_key: &str,
+    on_progress_pct: impl FnMut(u8),
+  ) -> Result<(SessionInfo, RecordPage), CoreError> {
+    self.open_local(path.as_ref(), Some(decryption_key), on_progress_pct)
+  }
+
+  fn open_local(
+    &self,
+    path: &Path,
+    decryption_key: Option<&str>,
     mut on_progress_pct: impl FnMut(u8),
   ) -> Result<(SessionInfo, RecordPage), CoreError> {
-    let path = path.as_ref().to_path_buf();
+    let path = path.to_path_buf();
+    if let Some(url) = path.to_str().filter(|p| remote::is_remote(p)) {
+      return self.open_remote(url, &mut on_progress_pct);
+    }
     let format = formats::detect_format(&path);
     match format {
       FileFormat::Jsonl | FileFormat::Csv | FileFormat::Json | FileFormat::Parquet => {}
       _ => return Err(CoreError::UnsupportedFormat(format)),
     }
 
+    self.evict_idle_sessions();
     on_progress_pct(0);
 
+    let path_key = path.to_string_lossy().to_string();
+    let view_prefs = self.storage.load_view_prefs(&path_key).ok().flatten();
+    let encoding = match (&format, view_prefs.as_ref().and_then(|p| p.encoding)) {
+      (FileFormat::Jsonl | FileFormat::Csv, Some(encoding)) => encoding,
+      (FileFormat::Jsonl | FileFormat::Csv, None) => encoding_impl::detect_file_encoding(&path)?,
+      _ => TextEncoding::Utf8,
+    };
+    let page_size = view_prefs
+      .as_ref()
+      .and_then(|p| p.page_size)
+      .map_or(self.options.default_page_size, |n| n as usize);
+    let json_lenient = matches!(format, FileFormat::Json | FileFormat::Jsonl)
+      && view_prefs.as_ref().and_then(|p| p.json_lenient).unwrap_or(false);
+    // A saved dialect `set_csv_dialect` would refuse now is left out rather than failing the open.
+    let csv = view_prefs
+      .as_ref()
+      .and_then(|p| p.csv.clone())
+      .filter(|csv| format == FileFormat::Csv && check_csv_dialect(csv).is_ok())
+      .unwrap_or_default();
+    let read_position = file_identity(&path)
+      .and_then(|(size, mtime)| self.storage.load_read_position(&path_key, size, mtime).ok().flatten());
     let session_id = Uuid::new_v4().to_string();
     let created_at_ms = now_ms();
-    let info = SessionInfo {
+    let mut info = SessionInfo {
       session_id: session_id.clone(),
-      path: path.to_string_lossy().to_string(),
+      path: path_key,
       format: format.clone(),
       created_at_ms,
+      index_task: None,
+      parts: Vec::new(),
+      filter_task_id: None,
+      sort: None,
+      dedup: None,
+      encoding,
+      derived: Vec::new(),
+      stream_task: None,
+      source_url: None,
+      view_prefs,
+      read_position,
+      columns: None,
+      filters: None,
+      csv,
+      json_lenient,
     };
 
     // Persist recent
     let _ = self.storage.touch_recent(&info.path, None);
 
     // first page from cursor = 0
+    let started = Instant::now();
+    let parquet = parquet_conn(&path, &format, &self.options, decryption_key)?;
+    match (&parquet, info.view_prefs.as_ref().and_then(|p| p.columns.as_ref())) {
+      (Some(conn), Some(saved)) => info.columns = existing_columns(&conn.lock(), saved)?,
+      (None, Some(saved)) if format == FileFormat::Csv => {
+        info.columns = known_columns(&formats::read_csv_header(&path, encoding, &info.csv)?, saved);
+      }
+      _ => {}
+    }
     let first_page = if format == FileFormat::Json {
       // Track progress by bytes for large JSON (best-effort).
       let total = std::fs::metadata(&path).ok().map(|m| m.len()).unwrap_or(0);
       let mut last_pct: u8 = 0;
       let (page, next) = crate::formats::read_json_page_with_progress(
         &path,
+        json_lenient,
         crate::cursor::Cursor { offset: 0, line: 0 },
-        self.options.default_page_size,
+        page_size,
         self.options.preview_max_chars,
         self.options.raw_max_chars,
         Some(&mut |done, total_bytes, _stage| {
@@ -154,310 +513,3939 @@ impl CoreEngine {
         records: page.records,
         next_cursor,
         reached_eof: page.reached_eof,
+        total_records: None,
+        ids_estimated: false,
       }
     } else {
-      self.read_page(&path, format.clone(), None, self.options.default_page_size)?
+      let render = RecordRender {
+        columns: info.columns.as_deref(),
+        csv: Some(&info.csv),
+        json_lenient,
+        parquet: parquet.as_deref(),
+        ..self.render(encoding)
+      };
+      self.read_page_with_limits(&path, format.clone(), Cursor { offset: 0, line: 0 }, page_size, render)?
     };
 
+    // Lenient `.json` indexes fill in as pages are read (they are never stored).
+    let line_index = Arc::new(Mutex::new(LineIndex::for_json(json_lenient)));
+    if !(json_lenient && format == FileFormat::Json) {
+      info.index_task = self.prepare_line_index(&path, &format, &info.csv, &line_index);
+    }
+    let follow = follow_baseline(&path, &format);
+
     let state = SessionState {
       info: info.clone(),
       format,
       last_page: Some(first_page.clone()),
+      record_count: None,
+      count_task_id: None,
+      goto_task_id: None,
+      line_index,
+      last_access_ms: created_at_ms,
+      shards: None,
+      follow,
+      identity: file_identity(&path),
+      view: None,
+      derived: Arc::default(),
+      cursor_epoch: 0,
+      spooled: false,
+      metrics: PageMetrics::opened_with(&first_page, started.elapsed()),
+      pretty_files: Vec::new(),
+      csv_warnings: WarningLog::from_records(&first_page.records),
+      parquet,
     };
     self.sessions.lock().insert(session_id, state);
     on_progress_pct(100);
     Ok((info, first_page))
   }
 
-  /// IPC API: next_page(session_id, cursor, page_size) -> RecordPage
-  pub fn next_page(
-    &self,
-    session_id: &str,
-    cursor: Option<&str>,
-    page_size: usize,
-  ) -> Result<RecordPage, CoreError> {
-    let (path, format) = {
-      let sessions = self.sessions.lock();
-      let s = sessions
-        .get(session_id)
-        .ok_or_else(|| CoreError::UnknownSession(session_id.to_string()))?;
-      (PathBuf::from(&s.info.path), s.format.clone())
+  /// `open_file` of an http(s) URL: the file is downloaded into `remote_cache_dir` and the
+  /// session pages the local copy (`session.source_url` is the URL). JSONL / CSV sessions open
+  /// as soon as the first page arrived and keep growing until the download task
+  /// (`session.stream_task`) is done, as in `open_stream`; JSON / Parquet wait for the whole
+  /// file (reported as progress). A complete copy of the same version (ETag / Last-Modified and
+  /// length) is reused; a partial one is resumed with a range request when the server allows.
+  fn open_remote(&self, url: &str, on_progress_pct: &mut dyn FnMut(u8)) -> Result<(SessionInfo, RecordPage), CoreError> {
+    let dir = match &self.options.remote_cache_dir {
+      Some(dir) => dir.clone(),
+      None => std::env::temp_dir().join("datalens-http-cache"),
     };
-    let page = self.read_page(&path, format, cursor, page_size)?;
-    if let Some(s) = self.sessions.lock().get_mut(session_id) {
-      s.last_page = Some(page.clone());
+    let remote = RemoteFile::head(url, &dir)?;
+    let format = formats::detect_format(&remote.path);
+    match format {
+      FileFormat::Jsonl | FileFormat::Csv | FileFormat::Json | FileFormat::Parquet => {}
+      _ => return Err(CoreError::UnsupportedFormat(format)),
     }
-    Ok(page)
+
+    let (mut info, first_page) = if remote.is_cached() {
+      self.open_file_with_progress(&remote.path, &mut *on_progress_pct)?
+    } else {
+      let Download {
+        mut body,
+        mut out,
+        resumed_from,
+      } = remote.download()?;
+      match format {
+        FileFormat::Jsonl | FileFormat::Csv => {
+          self.evict_idle_sessions();
+          self.open_spooled(body, out, resumed_from, remote.path.clone(), format, false)?
+        }
+        _ => {
+          // JSON / Parquet readers need the whole file: download is the first 90%.
+          let total = remote.version.len.unwrap_or(0);
+          let mut done = resumed_from;
+          let mut buf = vec![0u8; 256 * 1024];
+          loop {
+            let n = body.read(&mut buf)?;
+            if n == 0 {
+              break;
+            }
+            out.write_all(&buf[..n])?;
+            done += n as u64;
+            if let Some(pct) = (done.min(total) * 90).checked_div(total) {
+              on_progress_pct(pct as u8);
+            }
+          }
+          out.flush()?;
+          self.open_file_with_progress(&remote.path, |pct| on_progress_pct(90 + pct / 10))?
+        }
+      }
+    };
+    info.source_url = Some(url.to_string());
+    if let Some(s) = self.sessions.lock().get_mut(&info.session_id) {
+      s.info.source_url = info.source_url.clone();
+    }
+    Ok((info, first_page))
   }
 
-  /// IPC API: search(session_id, query, mode) -> SearchResult
+  /// IPC API: open_stream(input, format) -> { session, first_page }
   ///
-  /// - current_page: runs synchronously over last returned page (open_file/next_page)
-  /// - scan_all: starts a cancellable background task and returns task info
-  pub fn search(&self, session_id: &str, query: SearchQuery) -> Result<SearchResult, CoreError> {
-    let (path, format, last_page) = {
-      let sessions = self.sessions.lock();
-      let s = sessions
-        .get(session_id)
-        .ok_or_else(|| CoreError::UnknownSession(session_id.to_string()))?;
-      (
-        PathBuf::from(&s.info.path),
-        s.format.clone(),
-        s.last_page.clone(),
-      )
+  /// Opens piped input (`some_tool | datalens -`, the desktop "open from pipe" flow) as a JSONL
+  /// or CSV session. A background task (`session.stream_task`, cancellable) spools the input to
+  /// a file under `spool_dir`, and the session pages it like a growing file: `next_page` /
+  /// `page_at` extend the line index as records arrive, `poll_new_records` returns the ones
+  /// after the first page and `count_records` reports the spool task until the input ends. The
+  /// first page waits up to `STREAM_FIRST_PAGE_WAIT` for enough records. The spool file is
+  /// deleted when the last session on it closes.
+  pub fn open_stream(
+    &self,
+    input: impl Read + Send + 'static,
+    format: FileFormat,
+  ) -> Result<(SessionInfo, RecordPage), CoreError> {
+    let ext = match format {
+      FileFormat::Jsonl => "jsonl",
+      FileFormat::Csv => "csv",
+      other => return Err(CoreError::UnsupportedFormat(other)),
     };
+    self.evict_idle_sessions();
 
-    match query.mode {
-      SearchMode::CurrentPage => {
-        let lp = last_page.ok_or_else(|| CoreError::InvalidArg("no page cached".into()))?;
-        Ok(formats::search_current_page(&lp, &query))
+    let dir = self.options.spool_dir.clone().unwrap_or_else(std::env::temp_dir);
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("datalens-stream-{}.{ext}", Uuid::new_v4()));
+    let out = std::fs::File::create(&path)?;
+    self.open_spooled(input, out, 0, path, format, true)
+  }
+
+  /// Session over `path` while a spool task appends `input` to it (`out`, already holding
+  /// `resumed_from` bytes). See `open_stream`.
+  fn open_spooled(
+    &self,
+    input: impl Read + Send + 'static,
+    out: std::fs::File,
+    resumed_from: u64,
+    path: PathBuf,
+    format: FileFormat,
+    spooled: bool,
+  ) -> Result<(SessionInfo, RecordPage), CoreError> {
+    let session_id = Uuid::new_v4().to_string();
+    let line_index = Arc::new(Mutex::new(LineIndex::default()));
+    let lines = Arc::new(AtomicU64::new(0));
+    // End of the last complete line spooled so far; a resumed download may have stopped inside
+    // a line.
+    let complete_bytes = match resumed_from {
+      0 => 0,
+      n => last_newline_end(&path, n)?,
+    };
+    let complete_bytes = Arc::new(AtomicU64::new(complete_bytes));
+    let on_chunk = {
+      let (line_index, lines, complete_bytes) = (line_index.clone(), lines.clone(), complete_bytes.clone());
+      let mut written = resumed_from;
+      move |chunk: &[u8]| {
+        if let Some(last) = chunk.iter().rposition(|b| *b == b'\n') {
+          complete_bytes.store(written + last as u64 + 1, Ordering::SeqCst);
+        }
+        written += chunk.len() as u64;
+        lines.fetch_add(chunk.iter().filter(|b| **b == b'\n').count() as u64, Ordering::SeqCst);
+        line_index.lock().mark_grown();
       }
-      SearchMode::ScanAll => {
-        let task = self
-          .tasks
-          .start_search_scan_all(path, format, query, self.options.preview_max_chars)?;
-        Ok(SearchResult {
-          mode: SearchMode::ScanAll,
-          hits: vec![],
-          task: Some(TaskInfo {
-            id: task.id.clone(),
-            kind: TaskKind::SearchScanAll,
-            cancellable: true,
-          }),
-          truncated: false,
-        })
+    };
+    let task = self.tasks.start_spool(input, out, on_chunk)?;
+
+    // A resumed download already has its first page on disk.
+    let wanted = match resumed_from {
+      0 => self.options.default_page_size as u64 + u64::from(format == FileFormat::Csv),
+      _ => 0,
+    };
+    let deadline = Instant::now() + STREAM_FIRST_PAGE_WAIT;
+    while lines.load(Ordering::SeqCst) < wanted
+      && !self.tasks.is_task_finished(&task.id)
+      && Instant::now() < deadline
+    {
+      thread::sleep(Duration::from_millis(10));
+    }
+    if let Some(e) = self.tasks.get_task(&task.id).map_err(CoreError::Task)?.error {
+      if spooled {
+        let _ = std::fs::remove_file(&path);
       }
-      SearchMode::Indexed => Err(CoreError::InvalidArg(
-        "indexed search not implemented (M4)".into(),
-      )),
+      return Err(CoreError::Task(e.to_string()));
     }
-  }
 
-  /// Poll a background task status.
-  pub fn get_task(&self, task_id: &str) -> Result<Task, CoreError> {
-    self.tasks.get_task(task_id).map_err(CoreError::Task)
-  }
+    let encoding = encoding_impl::detect_file_encoding(&path)?;
+    let started = Instant::now();
+    let first_page = self.read_page(
+      &path,
+      format.clone(),
+      None,
+      self.options.default_page_size,
+      encoding,
+      &CsvDialect::default(),
+      false,
+      None,
+    )?;
+    // Follow from the end of the first page; a trailing line still being written is re-read.
+    let page_end = first_page
+      .records
+      .last()
+      .and_then(|r| r.meta.as_ref())
+      .map(|m| m.byte_offset + m.byte_len)
+      .unwrap_or(0);
+    let follow = FollowCursor {
+      offset: page_end.min(complete_bytes.load(Ordering::SeqCst)),
+      line: None,
+    };
 
-  pub fn cancel_task(&self, task_id: &str) -> Result<(), CoreError> {
-    self.tasks.cancel_task(task_id).map_err(CoreError::Task)
+    let created_at_ms = now_ms();
+    let info = SessionInfo {
+      session_id: session_id.clone(),
+      path: path.to_string_lossy().to_string(),
+      format: format.clone(),
+      created_at_ms,
+      index_task: None,
+      parts: Vec::new(),
+      filter_task_id: None,
+      sort: None,
+      dedup: None,
+      encoding,
+      derived: Vec::new(),
+      stream_task: Some(TaskInfo {
+        id: task.id,
+        kind: TaskKind::Spool,
+        cancellable: true,
+      }),
+      source_url: None,
+      view_prefs: None,
+      read_position: None,
+      columns: None,
+      filters: None,
+      csv: CsvDialect::default(),
+      json_lenient: false,
+    };
+    let state = SessionState {
+      info: info.clone(),
+      format,
+      last_page: Some(first_page.clone()),
+      record_count: None,
+      count_task_id: None,
+      goto_task_id: None,
+      line_index,
+      last_access_ms: created_at_ms,
+      shards: None,
+      follow: Some(follow),
+      identity: file_identity(&path),
+      view: None,
+      derived: Arc::default(),
+      cursor_epoch: 0,
+      spooled,
+      metrics: PageMetrics::opened_with(&first_page, started.elapsed()),
+      pretty_files: Vec::new(),
+      csv_warnings: WarningLog::from_records(&first_page.records),
+      parquet: None,
+    };
+    self.sessions.lock().insert(session_id, state);
+    Ok((info, first_page))
   }
 
-  /// Fetch accumulated hits from a scan_all search task, in pages.
-  pub fn search_task_hits_page(
-    &self,
-    task_id: &str,
-    cursor: Option<&str>,
-    page_size: usize,
-  ) -> Result<crate::models::RecordPage, CoreError> {
-    self.tasks
-      .search_task_hits_page(task_id, cursor, page_size)
-      .map_err(CoreError::Task)
+  /// IPC API: open_files(paths) -> { session, first_page }
+  ///
+  /// One read-only session concatenating several files in the order given: a sharded dataset
+  /// (`part-00000.jsonl … part-00099.jsonl`), split logs, or any folder-tree selection. All parts
+  /// must share one format (JSONL, CSV or Parquet). Record ids run continuously across parts;
+  /// paging, search, export, `get_record_raw` and `count_records` work on the whole set. Every
+  /// record's `meta.part` indexes `session.parts`, the file it came from. CSV parts must share
+  /// the first part's header.
+  pub fn open_files(&self, paths: &[PathBuf]) -> Result<(SessionInfo, RecordPage), CoreError> {
+    let Some(first) = paths.first() else {
+      return Err(CoreError::InvalidArg("open_files: no paths given".into()));
+    };
+    if paths.len() == 1 {
+      return self.open_file(first);
+    }
+    let format = formats::detect_format(first);
+    match format {
+      FileFormat::Jsonl | FileFormat::Csv | FileFormat::Parquet => {}
+      _ => return Err(CoreError::UnsupportedFormat(format)),
+    }
+    for p in paths {
+      let f = formats::detect_format(p);
+      if f != format {
+        return Err(CoreError::InvalidArg(format!(
+          "open_files: {} is {f:?}, expected {format:?} like the first part",
+          p.display()
+        )));
+      }
+      if !p.is_file() {
+        return Err(CoreError::InvalidArg(format!("open_files: not a file: {}", p.display())));
+      }
+    }
+    if format == FileFormat::Csv {
+      let header = formats::read_csv_header(first, TextEncoding::Utf8, &CsvDialect::default())?;
+      for p in &paths[1..] {
+        if formats::read_csv_header(p, TextEncoding::Utf8, &CsvDialect::default())? != header {
+          return Err(CoreError::InvalidArg(format!(
+            "open_files: {} has a different CSV header than the first part",
+            p.display()
+          )));
+        }
+      }
+    }
+    self.evict_idle_sessions();
+
+    let shards = Arc::new(Mutex::new(ShardSet::new(format.clone(), paths.to_vec())));
+    let started = Instant::now();
+    let first_page = self.read_shard_page(&shards, None, self.options.default_page_size)?;
+
+    let session_id = Uuid::new_v4().to_string();
+    let created_at_ms = now_ms();
+    let info = SessionInfo {
+      session_id: session_id.clone(),
+      path: first.to_string_lossy().to_string(),
+      format: format.clone(),
+      created_at_ms,
+      index_task: None,
+      parts: paths.iter().map(|p| p.to_string_lossy().to_string()).collect(),
+      filter_task_id: None,
+      sort: None,
+      dedup: None,
+      // Parts are read as UTF-8.
+      encoding: TextEncoding::Utf8,
+      derived: Vec::new(),
+      stream_task: None,
+      source_url: None,
+      view_prefs: None,
+      read_position: None,
+      columns: None,
+      filters: None,
+      csv: CsvDialect::default(),
+      json_lenient: false,
+    };
+    for p in &info.parts {
+      let _ = self.storage.touch_recent(p, None);
+    }
+    let state = SessionState {
+      info: info.clone(),
+      format,
+      last_page: Some(first_page.clone()),
+      record_count: None,
+      count_task_id: None,
+      goto_task_id: None,
+      line_index: Arc::new(Mutex::new(LineIndex::default())),
+      last_access_ms: created_at_ms,
+      shards: Some(shards),
+      follow: None,
+      identity: None,
+      view: None,
+      derived: Arc::default(),
+      cursor_epoch: 0,
+      spooled: false,
+      metrics: PageMetrics::opened_with(&first_page, started.elapsed()),
+      pretty_files: Vec::new(),
+      csv_warnings: WarningLog::from_records(&first_page.records),
+      parquet: None,
+    };
+    self.sessions.lock().insert(session_id, state);
+    Ok((info, first_page))
   }
 
-  /// IPC API: export(session_id, selection, format, output_path) -> ExportResult
-  pub fn export(
+  /// IPC API: open_search_results(session_id, task_id) -> { session, first_page }
+  ///
+  /// Promotes a finished scan_all task into a filtered session over the same file: paging walks
+  /// only the hit records (each read lazily by its offset) and keeps their original ids, so
+  /// export, `get_record_raw`, the JSON tree APIs and `get_stats` work on the filtered view.
+  /// `page_at` indexes into the hits; whole-file APIs (scan_all, follow / refresh, quick or
+  /// background stats) are not available.
+  pub fn open_search_results(
     &self,
     session_id: &str,
-    request: ExportRequest,
-    format: ExportFormat,
-    output_path: impl AsRef<Path>,
-  ) -> Result<ExportResult, CoreError> {
-    let (path, file_format) = {
-      let sessions = self.sessions.lock();
+    task_id: &str,
+  ) -> Result<(SessionInfo, RecordPage), CoreError> {
+    let (base, derived) = {
+      let mut sessions = self.sessions.lock();
       let s = sessions
-        .get(session_id)
+        .get_mut(session_id)
         .ok_or_else(|| CoreError::UnknownSession(session_id.to_string()))?;
-      (PathBuf::from(&s.info.path), s.format.clone())
+      s.last_access_ms = now_ms();
+      if s.shards.is_some() {
+        return Err(multi_file_unsupported("open_search_results"));
+      }
+      (s.info.clone(), s.derived.clone())
     };
-    export_impl::export(&self.tasks, path, file_format, request, format, output_path.as_ref())
+    let hits = self.tasks.search_task_hit_metas(task_id).map_err(CoreError::Task)?;
+    self.open_view_session(&base, derived, hits, Some(task_id.to_string()), None, None)
   }
 
-  /// IPC API: json_list_children(session_id, meta, path, cursor, limit) -> JsonChildrenPage
+  /// IPC API: open_sorted_view(session_id, sort) -> { session: SessionInfo, first_page: RecordPage }
   ///
-  /// Designed for huge single-record JSON values: list direct children under a selected subtree
-  /// without materializing the full JSON string.
-  pub fn json_list_children(
-    &self,
-    session_id: &str,
-    meta: RecordMeta,
-    path: Vec<JsonPathSegment>,
-    cursor: Option<u64>,
-    limit: usize,
-  ) -> Result<JsonChildrenPage, CoreError> {
-    let (path_buf, format) = {
-      let sessions = self.sessions.lock();
+  /// Reads the file once to order every record by `sort.key` (missing / null values last), then
+  /// opens a new session paging records in that order. Record ids stay those of the source file.
+  pub fn open_sorted_view(&self, session_id: &str, sort: SortSpec) -> Result<(SessionInfo, RecordPage), CoreError> {
+    let (base, derived) = {
+      let mut sessions = self.sessions.lock();
       let s = sessions
-        .get(session_id)
+        .get_mut(session_id)
         .ok_or_else(|| CoreError::UnknownSession(session_id.to_string()))?;
-      (PathBuf::from(&s.info.path), s.format.clone())
+      s.last_access_ms = now_ms();
+      if s.shards.is_some() {
+        return Err(multi_file_unsupported("open_sorted_view"));
+      }
+      if s.view.is_some() || s.info.filters.is_some() {
+        return Err(filtered_unsupported("open_sorted_view"));
+      }
+      (s.info.clone(), s.derived.clone())
     };
-    if format != FileFormat::Json {
-      return Err(CoreError::UnsupportedFormat(format));
-    }
-    let cursor = cursor.unwrap_or(0);
-    let limit = if limit == 0 { 50 } else { limit };
-    crate::formats::list_json_children_page(
-      &path_buf,
-      meta.byte_offset,
-      &path,
-      cursor,
-      limit,
-      self.options.preview_max_chars,
-    )
+    let order = sort_impl::sorted_record_order(Path::new(&base.path), base.format.clone(), &base.csv, &sort)?;
+    self.open_view_session(&base, derived, order, None, Some(sort), None)
   }
 
-  /// IPC API: json_node_summary(session_id, meta, path) -> JsonNodeSummary
+  /// IPC API: open_dedup_view(session_id, dedup) -> { session: SessionInfo, first_page: RecordPage }
   ///
-  /// Returns node kind and (best-effort) child count. Counting may stop early due to caps.
-  pub fn json_node_summary(
-    &self,
-    session_id: &str,
-    meta: RecordMeta,
-    path: Vec<JsonPathSegment>,
-    max_items: Option<u64>,
-    max_scan_bytes: Option<u64>,
-  ) -> Result<JsonNodeSummary, CoreError> {
-    let (path_buf, format) = {
-      let sessions = self.sessions.lock();
+  /// Reads the file once to group identical records (whole content, or `dedup.key`'s value),
+  /// then opens a new session paging the first record of each group in file order, or with
+  /// `only_duplicates` every record that has a duplicate. Record ids stay those of the source
+  /// file.
+  pub fn open_dedup_view(&self, session_id: &str, dedup: DedupSpec) -> Result<(SessionInfo, RecordPage), CoreError> {
+    let (base, derived) = {
+      let mut sessions = self.sessions.lock();
       let s = sessions
-        .get(session_id)
+        .get_mut(session_id)
         .ok_or_else(|| CoreError::UnknownSession(session_id.to_string()))?;
-      (PathBuf::from(&s.info.path), s.format.clone())
+      s.last_access_ms = now_ms();
+      if s.shards.is_some() {
+        return Err(multi_file_unsupported("open_dedup_view"));
+      }
+      if s.view.is_some() || s.info.filters.is_some() {
+        return Err(filtered_unsupported("open_dedup_view"));
+      }
+      (s.info.clone(), s.derived.clone())
+    };
+    let kept = dedup_impl::dedup_record_order(Path::new(&base.path), base.format.clone(), &base.csv, &dedup)?;
+    self.open_view_session(&base, derived, kept, None, None, Some(dedup))
+  }
+
+  /// Register a session that pages `metas` (in order) out of `base`'s file, with its encoding,
+  /// CSV dialect and derived columns.
+  fn open_view_session(
+    &self,
+    base: &SessionInfo,
+    derived: Arc<DerivedSet>,
+    metas: Vec<RecordMeta>,
+    filter_task_id: Option<String>,
+    sort: Option<SortSpec>,
+    dedup: Option<DedupSpec>,
+  ) -> Result<(SessionInfo, RecordPage), CoreError> {
+    let started = Instant::now();
+    let path = PathBuf::from(&base.path);
+    let format = base.format.clone();
+    let view = Arc::new(metas);
+    let mut first_page = self.read_view_page(
+      &path,
+      &format,
+      &view,
+      None,
+      self.options.default_page_size,
+      RecordRender {
+        csv: Some(&base.csv),
+        json_lenient: base.json_lenient,
+        ..self.render(base.encoding)
+      },
+    )?;
+
+    let new_id = Uuid::new_v4().to_string();
+    let created_at_ms = now_ms();
+    let info = SessionInfo {
+      session_id: new_id.clone(),
+      path: base.path.clone(),
+      format: format.clone(),
+      created_at_ms,
+      index_task: None,
+      parts: Vec::new(),
+      filter_task_id,
+      sort,
+      dedup,
+      encoding: base.encoding,
+      derived: base.derived.clone(),
+      stream_task: None,
+      source_url: None,
+      view_prefs: None,
+      read_position: None,
+      columns: None,
+      filters: None,
+      csv: base.csv.clone(),
+      json_lenient: base.json_lenient,
+    };
+    let state = SessionState {
+      info: info.clone(),
+      format,
+      last_page: None,
+      record_count: Some(view.len() as u64),
+      count_task_id: None,
+      goto_task_id: None,
+      line_index: Arc::new(Mutex::new(LineIndex::default())),
+      last_access_ms: created_at_ms,
+      shards: None,
+      follow: None,
+      identity: None,
+      view: Some(view),
+      derived,
+      cursor_epoch: 0,
+      spooled: false,
+      metrics: PageMetrics::default(),
+      pretty_files: Vec::new(),
+      csv_warnings: WarningLog::default(),
+      parquet: None,
+    };
+    self.sessions.lock().insert(new_id.clone(), state);
+    self.finish_page(&new_id, &mut first_page, started)?;
+    Ok((info, first_page))
+  }
+
+  /// IPC API: set_session_encoding(session_id, encoding) -> RecordPage
+  ///
+  /// Overrides the encoding detected at open for a JSONL / CSV session and returns its first page
+  /// decoded again. Byte offsets (cursors, line index) stay valid; views opened later inherit it.
+  pub fn set_session_encoding(&self, session_id: &str, encoding: TextEncoding) -> Result<RecordPage, CoreError> {
+    let started = Instant::now();
+    let (path, format, csv, lenient, columns) = {
+      let mut sessions = self.sessions.lock();
+      let s = sessions
+        .get_mut(session_id)
+        .ok_or_else(|| CoreError::UnknownSession(session_id.to_string()))?;
+      s.last_access_ms = now_ms();
+      if s.shards.is_some() {
+        return Err(multi_file_unsupported("set_session_encoding"));
+      }
+      if s.view.is_some() {
+        return Err(filtered_unsupported("set_session_encoding"));
+      }
+      if !matches!(s.format, FileFormat::Jsonl | FileFormat::Csv) {
+        return Err(CoreError::UnsupportedFormat(s.format.clone()));
+      }
+      (PathBuf::from(&s.info.path), s.format.clone(), s.info.csv.clone(), s.info.json_lenient, s.info.columns.clone())
+    };
+    // Header names can decode differently: keep the selected columns that still exist.
+    let columns = match columns {
+      Some(wanted) if format == FileFormat::Csv => {
+        known_columns(&formats::read_csv_header(&path, encoding, &csv)?, &wanted)
+      }
+      _ => None,
+    };
+    let mut page = self.read_page(
+      &path,
+      format,
+      None,
+      self.options.default_page_size,
+      encoding,
+      &csv,
+      lenient,
+      columns.as_deref(),
+    )?;
+    if let Some(s) = self.sessions.lock().get_mut(session_id) {
+      s.info.encoding = encoding;
+      s.info.columns = columns;
+    }
+    self.finish_page(session_id, &mut page, started)?;
+    Ok(page)
+  }
+
+  /// IPC API: set_csv_dialect(session_id, dialect) -> RecordPage
+  ///
+  /// Changes how a single-file CSV session's rows are read and returns its first page read
+  /// again. Without `has_header`, line 0 is a data row (paged, counted, searched, edited and
+  /// exported like the others) and columns are keyed `col_0..col_<n-1>`. With `infer_types`,
+  /// `raw` and JSON / JSONL exports carry numbers, booleans and nulls instead of strings.
+  /// `delimiter` (one or more characters, e.g. `||`), `quote` and `escape` (ASCII, no line
+  /// breaks, none sharing a character) change how cells are split and quoted, also in edits and
+  /// exports that write cells. `delimiter_regex` splits cells on a regular expression instead
+  /// (e.g. `\s*\|\s*`); it must match `delimiter` (still what edits and exports write) but not
+  /// empty text, a line break, the quote or the escape. Byte offsets stay valid unless they
+  /// change where records end (new delimiter or quoting), which invalidates cursors and replaces
+  /// the record index (loaded from storage or rebuilt in the background like on open, see
+  /// `session.index_task`); the cached count is dropped either way. Views opened later inherit
+  /// it, and stats, quick stats and schema read the file with it.
+  pub fn set_csv_dialect(&self, session_id: &str, dialect: CsvDialect) -> Result<RecordPage, CoreError> {
+    let started = Instant::now();
+    check_csv_dialect(&dialect)?;
+    let (path, encoding, same_records, columns) = {
+      let mut sessions = self.sessions.lock();
+      let s = sessions
+        .get_mut(session_id)
+        .ok_or_else(|| CoreError::UnknownSession(session_id.to_string()))?;
+      s.last_access_ms = now_ms();
+      if s.shards.is_some() {
+        return Err(multi_file_unsupported("set_csv_dialect"));
+      }
+      if s.view.is_some() {
+        return Err(filtered_unsupported("set_csv_dialect"));
+      }
+      if s.format != FileFormat::Csv {
+        return Err(CoreError::UnsupportedFormat(s.format.clone()));
+      }
+      (
+        PathBuf::from(&s.info.path),
+        s.info.encoding,
+        s.info.csv.same_records(&dialect),
+        s.info.columns.clone(),
+      )
+    };
+    // The header can split differently: keep the selected columns that still exist.
+    let columns = match columns {
+      Some(wanted) => known_columns(&formats::read_csv_header(&path, encoding, &dialect)?, &wanted),
+      None => None,
+    };
+    let mut page = self.read_page(
+      &path,
+      FileFormat::Csv,
+      None,
+      self.options.default_page_size,
+      encoding,
+      &dialect,
+      false,
+      columns.as_deref(),
+    )?;
+    let reindex = (!same_records).then(|| {
+      let line_index = Arc::new(Mutex::new(LineIndex::for_csv(&dialect)));
+      let index_task = self.prepare_line_index(&path, &FileFormat::Csv, &dialect, &line_index);
+      (line_index, index_task)
+    });
+    let mut old_index_task = None;
+    if let Some(s) = self.sessions.lock().get_mut(session_id) {
+      if let Some((line_index, index_task)) = reindex {
+        s.cursor_epoch += 1;
+        s.line_index = line_index;
+        old_index_task = std::mem::replace(&mut s.info.index_task, index_task);
+        s.last_page = None;
+      }
+      s.info.csv = dialect;
+      s.info.columns = columns;
+      s.record_count = None;
+      s.count_task_id = None;
+      s.csv_warnings = WarningLog::default();
+    }
+    if let Some(task) = old_index_task.filter(|t| !self.tasks.is_task_finished(&t.id)) {
+      let _ = self.tasks.cancel_task(&task.id);
+    }
+    self.finish_page(session_id, &mut page, started)?;
+    Ok(page)
+  }
+
+  /// IPC API: set_json_lenient(session_id, lenient) -> RecordPage
+  ///
+  /// Reads a single-file JSON / JSONL session leniently (or strictly again) and returns its first
+  /// page read again, for JSON5-ish config files: `//` and `/* */` comments and trailing commas
+  /// before `]` / `}` are read as spaces, so byte offsets stay those of the file. Applies to
+  /// paging, raw records, the JSON tree calls, counts, scan_all search and subtree exports.
+  /// `.json` sessions also drop cursors and the record index (a lenient one is only kept in
+  /// memory); the cached count is dropped either way. Views opened later inherit it. Whole-file
+  /// stats, schema, sort, dedup and record exports still read strict JSON.
+  pub fn set_json_lenient(&self, session_id: &str, lenient: bool) -> Result<RecordPage, CoreError> {
+    let started = Instant::now();
+    let (path, format, unchanged, columns) = {
+      let mut sessions = self.sessions.lock();
+      let s = sessions
+        .get_mut(session_id)
+        .ok_or_else(|| CoreError::UnknownSession(session_id.to_string()))?;
+      s.last_access_ms = now_ms();
+      if s.shards.is_some() {
+        return Err(multi_file_unsupported("set_json_lenient"));
+      }
+      if s.view.is_some() {
+        return Err(filtered_unsupported("set_json_lenient"));
+      }
+      if !matches!(s.format, FileFormat::Json | FileFormat::Jsonl) {
+        return Err(CoreError::UnsupportedFormat(s.format.clone()));
+      }
+      (
+        PathBuf::from(&s.info.path),
+        s.format.clone(),
+        s.info.json_lenient == lenient,
+        s.info.columns.clone(),
+      )
+    };
+    let mut page = self.read_page(
+      &path,
+      format.clone(),
+      None,
+      self.options.default_page_size,
+      TextEncoding::Utf8,
+      &CsvDialect::default(),
+      lenient,
+      columns.as_deref(),
+    )?;
+    // JSONL records are lines either way; `.json` records may end elsewhere.
+    let reindex = (!unchanged && format == FileFormat::Json).then(|| {
+      let line_index = Arc::new(Mutex::new(LineIndex::for_json(lenient)));
+      let index_task = if lenient {
+        None
+      } else {
+        self.prepare_line_index(&path, &format, &CsvDialect::default(), &line_index)
+      };
+      (line_index, index_task)
+    });
+    let mut old_index_task = None;
+    if let Some(s) = self.sessions.lock().get_mut(session_id) {
+      if let Some((line_index, index_task)) = reindex {
+        s.cursor_epoch += 1;
+        s.line_index = line_index;
+        old_index_task = std::mem::replace(&mut s.info.index_task, index_task);
+        s.last_page = None;
+      }
+      s.info.json_lenient = lenient;
+      s.record_count = None;
+      s.count_task_id = None;
+    }
+    if let Some(task) = old_index_task.filter(|t| !self.tasks.is_task_finished(&t.id)) {
+      let _ = self.tasks.cancel_task(&task.id);
+    }
+    self.finish_page(session_id, &mut page, started)?;
+    Ok(page)
+  }
+
+  /// IPC API: set_derived_columns(session_id, columns) -> RecordPage
+  ///
+  /// Replaces the session's computed fields (an empty list removes them) and returns its first
+  /// page with them evaluated. From then on every page record carries their values in
+  /// `derived`, search matches them too (current page; scan_all over JSONL / CSV) and
+  /// single-file JSON / JSONL exports add them as members (CSV exports of CSV sessions as
+  /// columns). Views opened from the session inherit them. See `derive.rs` for the syntax.
+  pub fn set_derived_columns(&self, session_id: &str, columns: Vec<DerivedColumn>) -> Result<RecordPage, CoreError> {
+    let derived = Arc::new(DerivedSet::compile(&columns)?);
+    {
+      let mut sessions = self.sessions.lock();
+      let s = sessions
+        .get_mut(session_id)
+        .ok_or_else(|| CoreError::UnknownSession(session_id.to_string()))?;
+      s.last_access_ms = now_ms();
+      s.info.derived = columns;
+      s.derived = derived;
+    }
+    self.next_page(session_id, None, self.options.default_page_size)
+  }
+
+  /// IPC API: set_session_columns(session_id, columns?) -> RecordPage
+  ///
+  /// CSV / Parquet sessions: the only columns pages show from then on (`session.columns`; `None`
+  /// shows all again), in the given order, and the first page read that way. CSV previews and
+  /// `raw` keep just those cells (the header row just their names); Parquet only reads them.
+  /// Cursors stay valid.
+  pub fn set_session_columns(&self, session_id: &str, columns: Option<Vec<String>>) -> Result<RecordPage, CoreError> {
+    let (path, format, parquet, encoding, csv) = {
+      let mut sessions = self.sessions.lock();
+      let s = sessions
+        .get_mut(session_id)
+        .ok_or_else(|| CoreError::UnknownSession(session_id.to_string()))?;
+      s.last_access_ms = now_ms();
+      if s.shards.is_some() {
+        return Err(multi_file_unsupported("set_session_columns"));
+      }
+      if s.view.is_some() {
+        return Err(filtered_unsupported("set_session_columns"));
+      }
+      (
+        PathBuf::from(&s.info.path),
+        s.format.clone(),
+        s.parquet.clone(),
+        s.info.encoding,
+        s.info.csv.clone(),
+      )
+    };
+    let names = match (parquet, format) {
+      (Some(conn), _) => column_names(&conn.lock())?,
+      (None, FileFormat::Csv) => formats::read_csv_header(&path, encoding, &csv)?,
+      (None, other) => return Err(CoreError::UnsupportedFormat(other)),
+    };
+    if let Some(columns) = &columns {
+      if columns.is_empty() {
+        return Err(CoreError::InvalidArg("columns must not be empty".into()));
+      }
+      if let Some(unknown) = columns.iter().find(|c| !names.contains(c)) {
+        return Err(CoreError::InvalidArg(format!("unknown column: {unknown}")));
+      }
+    }
+    if let Some(s) = self.sessions.lock().get_mut(session_id) {
+      s.info.columns = columns;
+    }
+    self.next_page(session_id, None, self.options.default_page_size)
+  }
+
+  /// IPC API: set_session_filters(session_id, filters?) -> RecordPage
+  ///
+  /// Parquet sessions: only rows meeting every filter are paged, scanned by scan_all and
+  /// exported with `ExportRequest::Filtered` from then on (`session.filters`; `None` shows every
+  /// row again), and the first page read that way. Filters run as a DuckDB `WHERE` clause, with
+  /// their values bound as parameters; filters DuckDB rejects (e.g. text compared with a number
+  /// column) leave the previous ones in place. Records keep their row ids.
+  pub fn set_session_filters(
+    &self,
+    session_id: &str,
+    filters: Option<Vec<ColumnFilter>>,
+  ) -> Result<RecordPage, CoreError> {
+    let (parquet, previous) = {
+      let mut sessions = self.sessions.lock();
+      let s = sessions
+        .get_mut(session_id)
+        .ok_or_else(|| CoreError::UnknownSession(session_id.to_string()))?;
+      s.last_access_ms = now_ms();
+      if s.shards.is_some() {
+        return Err(multi_file_unsupported("set_session_filters"));
+      }
+      if s.view.is_some() {
+        return Err(filtered_unsupported("set_session_filters"));
+      }
+      let parquet = s.parquet.clone().ok_or_else(|| CoreError::UnsupportedFormat(s.format.clone()))?;
+      (parquet, s.info.filters.clone())
+    };
+    let filters = filters.filter(|f| !f.is_empty());
+    if let Some(filters) = &filters {
+      let names = parquet.lock().columns()?;
+      if let Some(unknown) = filters.iter().find(|f| !names.iter().any(|(name, _, _)| *name == f.column)) {
+        return Err(CoreError::InvalidArg(format!("unknown column: {}", unknown.column)));
+      }
+    }
+    let set = |filters: Option<Vec<ColumnFilter>>| {
+      if let Some(s) = self.sessions.lock().get_mut(session_id) {
+        s.info.filters = filters;
+      }
+    };
+    set(filters);
+    self
+      .next_page(session_id, None, self.options.default_page_size)
+      .inspect_err(|_| set(previous))
+  }
+
+  /// IPC API: list_sessions() -> SessionInfo[]
+  ///
+  /// Open sessions, oldest first.
+  pub fn list_sessions(&self) -> Vec<SessionInfo> {
+    self.evict_idle_sessions();
+    let mut out: Vec<SessionInfo> = self.sessions.lock().values().map(|s| s.info.clone()).collect();
+    out.sort_by_key(|s| s.created_at_ms);
+    out
+  }
+
+  /// IPC API: shutdown() -> ()
+  ///
+  /// For app exit: cancels every task, waits for their worker threads to stop and stops exports
+  /// in progress (their partial output is removed). Tasks and exports started afterwards fail.
+  pub fn shutdown(&self) {
+    self.tasks.shutdown();
+  }
+
+  /// IPC API: save_view_prefs(session_id, prefs) -> ()
+  ///
+  /// Remembers how the session's file is viewed; `open_file` of the same path applies them and
+  /// returns them in `session.view_prefs`. Replaces what was stored before.
+  pub fn save_view_prefs(&self, session_id: &str, prefs: ViewPrefs) -> Result<(), CoreError> {
+    let path = {
+      let sessions = self.sessions.lock();
+      let s = sessions
+        .get(session_id)
+        .ok_or_else(|| CoreError::UnknownSession(session_id.to_string()))?;
+      if s.view.is_some() || s.shards.is_some() {
+        return Err(CoreError::InvalidArg("view prefs are kept for single-file sessions only".into()));
+      }
+      s.info.path.clone()
+    };
+    self.storage.save_view_prefs(&path, &prefs).map_err(CoreError::Storage)
+  }
+
+  /// IPC API: save_workspace(session_ids, active_session_id?) -> Workspace
+  ///
+  /// Remembers the files of the given sessions (the UI's tabs, in order) for `restore_workspace`.
+  /// Filtered, sorted and deduplicated views are left out: they are rebuilt from their file.
+  pub fn save_workspace(&self, session_ids: &[String], active_session_id: Option<&str>) -> Result<Workspace, CoreError> {
+    let mut workspace = Workspace::default();
+    {
+      let sessions = self.sessions.lock();
+      for id in session_ids {
+        let s = sessions
+          .get(id)
+          .ok_or_else(|| CoreError::UnknownSession(id.clone()))?;
+        if s.view.is_some() {
+          continue;
+        }
+        if active_session_id == Some(id.as_str()) {
+          workspace.active_tab = Some(workspace.tabs.len());
+        }
+        workspace.tabs.push(WorkspaceTab {
+          paths: history_paths(&s.info),
+        });
+      }
+    }
+    let json = serde_json::to_string(&workspace).map_err(|e| CoreError::Storage(e.to_string()))?;
+    self
+      .storage
+      .set_setting_json(WORKSPACE_SETTING, &json)
+      .map_err(CoreError::Storage)?;
+    Ok(workspace)
+  }
+
+  /// IPC API: restore_workspace() -> RestoredWorkspace
+  ///
+  /// Reopens the tabs of the saved workspace (none if nothing was saved). Tabs whose local files
+  /// are gone, or that fail to open, are returned in `missing` instead.
+  pub fn restore_workspace(&self) -> Result<RestoredWorkspace, CoreError> {
+    let saved: Workspace = self
+      .storage
+      .get_setting_json(WORKSPACE_SETTING)
+      .map_err(CoreError::Storage)?
+      .and_then(|json| serde_json::from_str(&json).ok())
+      .unwrap_or_default();
+    let mut restored = RestoredWorkspace {
+      tabs: Vec::new(),
+      active_tab: None,
+      missing: Vec::new(),
+    };
+    for (i, tab) in saved.tabs.into_iter().enumerate() {
+      match self.reopen(&tab.paths).ok() {
+        Some((session, page)) => {
+          if saved.active_tab == Some(i) {
+            restored.active_tab = Some(restored.tabs.len());
+          }
+          restored.tabs.push(RestoredTab { session, page });
+        }
+        None => restored.missing.push(tab),
+      }
+    }
+    Ok(restored)
+  }
+
+  /// Opens a saved tab, collection member or export source: one file or URL, or the parts of a
+  /// multi-file session.
+  fn reopen(&self, paths: &[String]) -> Result<(SessionInfo, RecordPage), CoreError> {
+    if let Some(gone) = paths.iter().find(|p| !remote::is_remote(p) && !Path::new(p).exists()) {
+      return Err(CoreError::InvalidArg(format!("file not found: {gone}")));
+    }
+    match paths {
+      [] => Err(CoreError::InvalidArg("no files to open".into())),
+      [path] => self.open_file(path),
+      paths => {
+        let paths: Vec<PathBuf> = paths.iter().map(PathBuf::from).collect();
+        self.open_files(&paths)
+      }
+    }
+  }
+
+  /// IPC API: save_collection(name, paths) -> DatasetCollection
+  ///
+  /// Creates the named collection or replaces its members.
+  pub fn save_collection(&self, name: &str, paths: Vec<String>) -> Result<DatasetCollection, CoreError> {
+    let name = name.trim();
+    if name.is_empty() {
+      return Err(CoreError::InvalidArg("collection name is empty".into()));
+    }
+    self.storage.save_collection(name, &paths).map_err(CoreError::Storage)?;
+    self
+      .list_collections()?
+      .into_iter()
+      .find(|c| c.name == name)
+      .ok_or_else(|| CoreError::Storage(format!("collection {name} was not saved")))
+  }
+
+  /// IPC API: list_collections() -> DatasetCollection[]
+  pub fn list_collections(&self) -> Result<Vec<DatasetCollection>, CoreError> {
+    self.storage.list_collections().map_err(CoreError::Storage)
+  }
+
+  /// IPC API: delete_collection(name) -> ()
+  pub fn delete_collection(&self, name: &str) -> Result<(), CoreError> {
+    if !self.storage.delete_collection(name).map_err(CoreError::Storage)? {
+      return Err(CoreError::InvalidArg(format!("unknown collection: {name}")));
+    }
+    Ok(())
+  }
+
+  /// IPC API: open_collection(name) -> OpenedCollection
+  ///
+  /// Opens every member in its own session, in order; members that are gone or fail to open are
+  /// listed in `skipped`.
+  pub fn open_collection(&self, name: &str) -> Result<OpenedCollection, CoreError> {
+    let collection = self
+      .list_collections()?
+      .into_iter()
+      .find(|c| c.name == name)
+      .ok_or_else(|| CoreError::InvalidArg(format!("unknown collection: {name}")))?;
+    let mut opened = OpenedCollection {
+      tabs: Vec::new(),
+      skipped: Vec::new(),
+    };
+    for path in collection.paths {
+      match self.reopen(std::slice::from_ref(&path)).ok() {
+        Some((session, page)) => opened.tabs.push(RestoredTab { session, page }),
+        None => opened.skipped.push(path),
+      }
+    }
+    Ok(opened)
+  }
+
+  /// IPC API: close_session(session_id) -> ()
+  ///
+  /// Drops cached pages / indexes and cancels the session's background count and index tasks.
+  pub fn close_session(&self, session_id: &str) -> Result<(), CoreError> {
+    let state = self
+      .sessions
+      .lock()
+      .remove(session_id)
+      .ok_or_else(|| CoreError::UnknownSession(session_id.to_string()))?;
+    self.cancel_session_tasks(&state);
+    self.remove_unused_spool(&state);
+    remove_pretty_files(&state);
+    Ok(())
+  }
+
+  fn evict_idle_sessions(&self) {
+    let Some(timeout_ms) = self.options.session_idle_timeout_ms else {
+      return;
+    };
+    let cutoff = now_ms().saturating_sub(timeout_ms as i64);
+    let evicted: Vec<SessionState> = {
+      let mut sessions = self.sessions.lock();
+      let idle: Vec<String> = sessions
+        .iter()
+        .filter(|(_, s)| s.last_access_ms < cutoff)
+        .map(|(id, _)| id.clone())
+        .collect();
+      idle.iter().filter_map(|id| sessions.remove(id)).collect()
+    };
+    for state in &evicted {
+      self.cancel_session_tasks(state);
+      self.remove_unused_spool(state);
+      remove_pretty_files(state);
+    }
+  }
+
+  fn cancel_session_tasks(&self, state: &SessionState) {
+    let index_task = state.info.index_task.as_ref().map(|t| t.id.clone());
+    let stream_task = state.info.stream_task.as_ref().map(|t| t.id.clone());
+    for task_id in state
+      .count_task_id
+      .iter()
+      .chain(&state.goto_task_id)
+      .chain(index_task.iter())
+      .chain(stream_task.iter())
+    {
+      if !self.tasks.is_task_finished(task_id) {
+        let _ = self.tasks.cancel_task(task_id);
+      }
+    }
+  }
+
+  /// Deletes a closed streamed session's spool file unless views opened from it still page it.
+  fn remove_unused_spool(&self, state: &SessionState) {
+    if !state.spooled || self.sessions.lock().values().any(|s| s.info.path == state.info.path) {
+      return;
+    }
+    let _ = std::fs::remove_file(&state.info.path);
+  }
+
+  /// Load a persisted record index for `path`, or start building one for large JSONL / CSV /
+  /// JSON files. CSV records are split by `csv`'s quoting, and indexes are persisted per quoting.
+  ///
+  /// Best-effort: storage errors or a full task queue just leave the index to grow lazily.
+  fn prepare_line_index(
+    &self,
+    path: &Path,
+    format: &FileFormat,
+    csv: &CsvDialect,
+    line_index: &Arc<Mutex<LineIndex>>,
+  ) -> Option<TaskInfo> {
+    if !matches!(format, FileFormat::Jsonl | FileFormat::Csv | FileFormat::Json) {
+      return None;
+    }
+    let (file_size, file_mtime_ms) = file_identity(path)?;
+    let key = path.to_string_lossy().to_string();
+    if let Ok(Some(stored)) = self.storage.load_line_index(&key, &line_index::csv_quoting_key(csv), file_size, file_mtime_ms) {
+      if let Some(index) = LineIndex::from_stored(stored, csv) {
+        *line_index.lock() = index;
+        return None;
+      }
+    }
+    if file_size < self.options.line_index_min_bytes {
+      return None;
+    }
+
+    let target = line_index.clone();
+    let storage = self.storage.clone();
+    let on_done = Box::new(move |index: LineIndex| {
+      if let Some(stored) = index.to_stored() {
+        let _ = storage.save_line_index(&key, file_size, file_mtime_ms, &stored);
+      }
+      *target.lock() = index;
+    });
+    let task = self
+      .tasks
+      .start_line_index(path.to_path_buf(), format.clone(), csv.clone(), on_done)
+      .ok()?;
+    Some(TaskInfo {
+      id: task.id,
+      kind: TaskKind::IndexBuild,
+      cancellable: true,
+    })
+  }
+
+  /// The node index of the JSON record at `meta`, from memory or storage. A record without one
+  /// gets a background build (returned as a task) if it has at least
+  /// `json_node_index_min_bytes`, or with `force`.
+  ///
+  /// Best-effort like `prepare_line_index`: without an index, tree calls read from the record
+  /// start. A failed build isn't retried unless forced.
+  fn json_node_index(
+    &self,
+    path: &Path,
+    lenient: bool,
+    meta: &RecordMeta,
+    force: bool,
+  ) -> (Option<Arc<JsonNodeIndex>>, Option<TaskInfo>) {
+    let Some((file_size, file_mtime_ms)) = file_identity(path) else {
+      return (None, None);
+    };
+    let path_key = path.to_string_lossy().to_string();
+    let key = (path_key.clone(), file_size, file_mtime_ms, meta.byte_offset);
+    let slot = self.json_indexes.lock().get(&key).cloned();
+    let tried = match slot {
+      Some(IndexSlot::Ready(index)) => return (Some(index), None),
+      Some(IndexSlot::Building(id)) if !self.tasks.is_task_finished(&id) => return (None, None),
+      Some(IndexSlot::Building(_)) => true,
+      None => false,
+    };
+    if let Ok(Some(index)) = self.storage.load_json_node_index(&path_key, meta.byte_offset, file_size, file_mtime_ms) {
+      let index = Arc::new(index);
+      self.json_indexes.lock().insert(key, IndexSlot::Ready(index.clone()));
+      return (Some(index), None);
+    }
+    if !force && (tried || meta.byte_len < self.options.json_node_index_min_bytes) {
+      return (None, None);
+    }
+
+    let indexes = self.json_indexes.clone();
+    let storage = self.storage.clone();
+    let done_key = key.clone();
+    let on_done = Box::new(move |index: JsonNodeIndex| {
+      let _ = storage.save_json_node_index(&done_key.0, done_key.3, done_key.1, done_key.2, &index);
+      indexes.lock().insert(done_key, IndexSlot::Ready(Arc::new(index)));
+    });
+    let Ok(task) = self
+      .tasks
+      .start_json_node_index(path.to_path_buf(), lenient, meta.byte_offset, meta.byte_len, on_done)
+    else {
+      return (None, None);
+    };
+    self
+      .json_indexes
+      .lock()
+      .entry(key)
+      .and_modify(|slot| {
+        if !matches!(slot, IndexSlot::Ready(_)) {
+          *slot = IndexSlot::Building(task.id.clone());
+        }
+      })
+      .or_insert_with(|| IndexSlot::Building(task.id.clone()));
+    (
+      None,
+      Some(TaskInfo {
+        id: task.id,
+        kind: TaskKind::IndexBuild,
+        cancellable: true,
+      }),
+    )
+  }
+
+  /// The full-text index of `path` decoded with `encoding`, from memory or storage. Without one,
+  /// `force` starts a background build (returned as a task); nothing is built otherwise.
+  fn text_index(&self, path: &Path, encoding: TextEncoding, force: bool) -> (Option<Arc<TextIndex>>, Option<TaskInfo>) {
+    let Some((file_size, file_mtime_ms)) = file_identity(path) else {
+      return (None, None);
+    };
+    let path_key = path.to_string_lossy().to_string();
+    let key = (path_key.clone(), file_size, file_mtime_ms, encoding);
+    match self.text_indexes.lock().get(&key).cloned() {
+      Some(IndexSlot::Ready(index)) => return (Some(index), None),
+      Some(IndexSlot::Building(id)) if !self.tasks.is_task_finished(&id) => return (None, None),
+      _ => {}
+    }
+    if let Ok(Some(index)) = self.storage.load_text_index(&path_key, encoding, file_size, file_mtime_ms) {
+      let index = Arc::new(index);
+      self.text_indexes.lock().insert(key, IndexSlot::Ready(index.clone()));
+      return (Some(index), None);
+    }
+    if !force {
+      return (None, None);
+    }
+
+    let indexes = self.text_indexes.clone();
+    let storage = self.storage.clone();
+    let done_key = key.clone();
+    let on_done = Box::new(move |index: TextIndex| {
+      let _ = storage.save_text_index(&done_key.0, done_key.3, done_key.1, done_key.2, &index);
+      indexes.lock().insert(done_key, IndexSlot::Ready(Arc::new(index)));
+    });
+    let Ok(task) = self.tasks.start_text_index(path.to_path_buf(), encoding, on_done) else {
+      return (None, None);
+    };
+    self
+      .text_indexes
+      .lock()
+      .entry(key)
+      .and_modify(|slot| {
+        if !matches!(slot, IndexSlot::Ready(_)) {
+          *slot = IndexSlot::Building(task.id.clone());
+        }
+      })
+      .or_insert_with(|| IndexSlot::Building(task.id.clone()));
+    (
+      None,
+      Some(TaskInfo {
+        id: task.id,
+        kind: TaskKind::IndexBuild,
+        cancellable: true,
+      }),
+    )
+  }
+
+  /// Where to start reading `path` under the record at `meta`: the deepest node along it in the
+  /// record's node index, and how many segments lead there (the record start and 0 without one).
+  fn json_path_start(
+    &self,
+    file: &Path,
+    lenient: bool,
+    meta: &RecordMeta,
+    path: &[JsonPathSegment],
+  ) -> (u64, usize) {
+    match self.json_node_index(file, lenient, meta, false).0.and_then(|index| index.deepest_on_path(path)) {
+      Some((consumed, node)) => (node.offset, consumed),
+      None => (meta.byte_offset, 0),
+    }
+  }
+
+  /// IPC API: next_page(session_id, cursor, page_size) -> RecordPage
+  pub fn next_page(
+    &self,
+    session_id: &str,
+    cursor: Option<&str>,
+    page_size: usize,
+  ) -> Result<RecordPage, CoreError> {
+    self.next_page_with_columns(session_id, cursor, page_size, None)
+  }
+
+  /// IPC API: next_page(session_id, cursor?, page_size, columns?) -> RecordPage
+  ///
+  /// CSV / Parquet sessions: `columns` limits previews (in the given order) and raw JSON to those
+  /// columns; Parquet only reads the selected columns. Cursors are the same as unprojected.
+  /// Without `columns`, they show `session.columns` (all when unset).
+  pub fn next_page_with_columns(
+    &self,
+    session_id: &str,
+    cursor: Option<&str>,
+    page_size: usize,
+    columns: Option<&[String]>,
+  ) -> Result<RecordPage, CoreError> {
+    let _interactive = self.tasks.interactive();
+    let started = Instant::now();
+    let (path, format, shards, view, encoding, epoch, parquet, session_columns, filters, csv, json_lenient) = {
+      let mut sessions = self.sessions.lock();
+      let s = sessions
+        .get_mut(session_id)
+        .ok_or_else(|| CoreError::UnknownSession(session_id.to_string()))?;
+      s.last_access_ms = now_ms();
+      (
+        PathBuf::from(&s.info.path),
+        s.format.clone(),
+        s.shards.clone(),
+        s.view.clone(),
+        s.info.encoding,
+        s.cursor_epoch,
+        s.parquet.clone(),
+        s.info.columns.clone(),
+        s.info.filters.clone(),
+        s.info.csv.clone(),
+        s.info.json_lenient,
+      )
+    };
+    // This session was just touched: only other idle ones are closed.
+    self.evict_idle_sessions();
+    let cursor = strip_cursor_epoch(cursor, epoch)?;
+    let (cursor, ids_estimated) = strip_estimated(cursor);
+    if let Some(columns) = columns {
+      if !matches!(format, FileFormat::Csv | FileFormat::Parquet) {
+        return Err(CoreError::InvalidArg(
+          "column projection needs a CSV or Parquet session".into(),
+        ));
+      }
+      if columns.is_empty() {
+        return Err(CoreError::InvalidArg("columns must not be empty".into()));
+      }
+      if shards.is_some() {
+        return Err(multi_file_unsupported("column projection"));
+      }
+    }
+    let render = RecordRender {
+      columns: columns.or(session_columns.as_deref()),
+      filters: filters.as_deref(),
+      csv: Some(&csv),
+      json_lenient,
+      parquet: parquet.as_deref(),
+      ..self.render(encoding)
+    };
+    let mut page = match (shards, view) {
+      (Some(shards), _) => self.read_shard_page(&shards, cursor, page_size)?,
+      (None, Some(view)) => self.read_view_page(&path, &format, &view, cursor, page_size, render)?,
+      (None, None) => self.read_page_with_limits(&path, format, decode_cursor(cursor)?, page_size, render)?,
+    };
+    if ids_estimated {
+      mark_ids_estimated(&mut page);
+    }
+    self.finish_page(session_id, &mut page, started)?;
+    Ok(page)
+  }
+
+  /// IPC API: session_metrics(session_id) -> SessionMetrics
+  ///
+  /// Pages served so far with their record bytes and latency, and how often jumps (line index)
+  /// and counts (cached total) were answered without reading the file, to tell why a session
+  /// feels slow.
+  pub fn session_metrics(&self, session_id: &str) -> Result<SessionMetrics, CoreError> {
+    let mut sessions = self.sessions.lock();
+    let s = sessions
+      .get_mut(session_id)
+      .ok_or_else(|| CoreError::UnknownSession(session_id.to_string()))?;
+    s.last_access_ms = now_ms();
+    Ok(s.metrics.to_public())
+  }
+
+  /// IPC API: csv_warnings(session_id) -> CsvWarnings
+  ///
+  /// The malformed rows (see `Record.warning`) among those the session's pages and polls served
+  /// so far, by record id: a session-level summary without scanning the file. Up to 1000 rows
+  /// are kept (`truncated` once more were seen). Empty for other formats.
+  pub fn csv_warnings(&self, session_id: &str) -> Result<CsvWarnings, CoreError> {
+    let mut sessions = self.sessions.lock();
+    let s = sessions
+      .get_mut(session_id)
+      .ok_or_else(|| CoreError::UnknownSession(session_id.to_string()))?;
+    s.last_access_ms = now_ms();
+    Ok(s.csv_warnings.to_public())
+  }
+
+  /// IPC API: count_records(session_id) -> RecordCount
+  ///
+  /// Parquet (metadata) and small files are counted synchronously. Larger files start a
+  /// cancellable background task: poll it with `get_task` and call `count_records` again once it
+  /// finished to get `total`. The count is cached per session.
+  pub fn count_records(&self, session_id: &str) -> Result<RecordCount, CoreError> {
+    let (path, format, cached, task_id, line_index, shards, stream_task, csv, json_lenient) = {
+      let mut sessions = self.sessions.lock();
+      let s = sessions
+        .get_mut(session_id)
+        .ok_or_else(|| CoreError::UnknownSession(session_id.to_string()))?;
+      s.last_access_ms = now_ms();
+      (
+        PathBuf::from(&s.info.path),
+        s.format.clone(),
+        s.record_count,
+        s.count_task_id.clone(),
+        s.line_index.clone(),
+        s.shards.clone(),
+        s.info.stream_task.clone(),
+        s.info.csv.clone(),
+        s.info.json_lenient,
+      )
+    };
+    // Streamed input has no total until it ends.
+    if let Some(task) = stream_task.filter(|t| !self.tasks.is_task_finished(&t.id)) {
+      return Ok(RecordCount { total: None, task: Some(task) });
+    }
+    self.note_cache_use(session_id, cached.is_some());
+    if let Some(total) = cached {
+      return Ok(RecordCount { total: Some(total), task: None });
+    }
+    // Multi-file: parts are counted synchronously (spans already seen while paging are reused).
+    if let Some(shards) = shards {
+      let total = shards.lock().total_records()?;
+      self.set_record_count(session_id, Some(total), None);
+      return Ok(RecordCount { total: Some(total), task: None });
+    }
+    // A line index that already reached EOF knows the answer.
+    if let Some(indexed) = line_index.lock().total_records() {
+      let header = u64::from(format == FileFormat::Csv && csv.has_header);
+      let total = indexed.saturating_sub(header);
+      self.set_record_count(session_id, Some(total), task_id);
+      return Ok(RecordCount { total: Some(total), task: None });
+    }
+    // Counts leave the CSV header out; without one, row 0 is data (if the file has any rows).
+    let file_len = std::fs::metadata(&path)?.len();
+    let first_row = u64::from(format == FileFormat::Csv && !csv.has_header && file_len > 0);
+
+    if let Some(task_id) = task_id {
+      if !self.tasks.is_task_finished(&task_id) {
+        return Ok(RecordCount {
+          total: None,
+          task: Some(TaskInfo {
+            id: task_id,
+            kind: TaskKind::CountRecords,
+            cancellable: true,
+          }),
+        });
+      }
+      // Finished: take the result, or fall through and recount if it was cancelled.
+      let total = self.tasks.count_task_result(&task_id).map_err(CoreError::Task)?.map(|n| n + first_row);
+      self.set_record_count(session_id, total, None);
+      if let Some(total) = total {
+        return Ok(RecordCount { total: Some(total), task: None });
+      }
+    }
+
+    if format == FileFormat::Parquet || file_len <= COUNT_SYNC_MAX_BYTES {
+      let total = formats::count_records_with(&path, format, &csv, json_lenient, || false, |_| {})?.map(|n| n + first_row);
+      self.set_record_count(session_id, total, None);
+      return Ok(RecordCount { total, task: None });
+    }
+
+    let task = self.tasks.start_count_records(path, format, csv, json_lenient)?;
+    self.set_record_count(session_id, None, Some(task.id.clone()));
+    Ok(RecordCount {
+      total: None,
+      task: Some(TaskInfo {
+        id: task.id,
+        kind: TaskKind::CountRecords,
+        cancellable: true,
+      }),
+    })
+  }
+
+  fn note_cache_use(&self, session_id: &str, hit: bool) {
+    if let Some(s) = self.sessions.lock().get_mut(session_id) {
+      if hit {
+        s.metrics.cache_hits += 1;
+      } else {
+        s.metrics.cache_misses += 1;
+      }
+    }
+  }
+
+  fn set_record_count(&self, session_id: &str, total: Option<u64>, task_id: Option<String>) {
+    if let Some(s) = self.sessions.lock().get_mut(session_id) {
+      s.record_count = total;
+      s.count_task_id = task_id;
+    }
+  }
+
+  /// IPC API: page_at(session_id, record_index, page_size) -> RecordPage
+  ///
+  /// Jumps straight to record `record_index` (the record `id` used in pages; for CSV the header
+  /// row is record 0). Parquet seeks via OFFSET; JSONL / CSV / `.json` via the session's sparse
+  /// record index (the first jump deep into a file scans up to it once).
+  pub fn page_at(&self, session_id: &str, record_index: u64, page_size: usize) -> Result<RecordPage, CoreError> {
+    let _interactive = self.tasks.interactive();
+    let started = Instant::now();
+    let (path, format, line_index, view, encoding, parquet, columns, filters, csv, json_lenient) = {
+      let mut sessions = self.sessions.lock();
+      let s = sessions
+        .get_mut(session_id)
+        .ok_or_else(|| CoreError::UnknownSession(session_id.to_string()))?;
+      s.last_access_ms = now_ms();
+      if s.shards.is_some() {
+        return Err(multi_file_unsupported("page_at"));
+      }
+      (
+        PathBuf::from(&s.info.path),
+        s.format.clone(),
+        s.line_index.clone(),
+        s.view.clone(),
+        s.info.encoding,
+        s.parquet.clone(),
+        s.info.columns.clone(),
+        s.info.filters.clone(),
+        s.info.csv.clone(),
+        s.info.json_lenient,
+      )
+    };
+    self.evict_idle_sessions();
+    if let Some(view) = view {
+      if record_index >= view.len() as u64 {
+        return Err(CoreError::InvalidArg(format!("record_index {record_index} is past the last hit")));
+      }
+      let cursor = encode_cursor(Cursor { offset: 0, line: record_index });
+      let render = RecordRender {
+        csv: Some(&csv),
+        json_lenient,
+        ..self.render(encoding)
+      };
+      let mut page = self.read_view_page(&path, &format, &view, Some(&cursor), page_size, render)?;
+      self.finish_page(session_id, &mut page, started)?;
+      return Ok(page);
+    }
+    let past_end = || CoreError::InvalidArg(format!("record_index {record_index} is past the last record"));
+
+    let cursor = match format {
+      FileFormat::Jsonl | FileFormat::Csv | FileFormat::Json => {
+        let covered = line_index.lock().covers(record_index);
+        self.note_cache_use(session_id, covered);
+        let offset = line_index
+          .lock()
+          .offset_of(&path, format.clone(), record_index)?
+          .ok_or_else(past_end)?;
+        Cursor {
+          offset,
+          line: record_index,
+        }
+      }
+      FileFormat::Parquet => Cursor {
+        offset: 0,
+        line: record_index,
+      },
+      other => return Err(CoreError::UnsupportedFormat(other)),
+    };
+    let render = RecordRender {
+      columns: columns.as_deref(),
+      filters: filters.as_deref(),
+      csv: Some(&csv),
+      json_lenient,
+      parquet: parquet.as_deref(),
+      ..self.render(encoding)
+    };
+    let mut page = self.read_page_with_limits(&path, format, cursor, page_size, render)?;
+    if page.records.is_empty() && record_index > 0 {
+      return Err(past_end());
+    }
+    self.finish_page(session_id, &mut page, started)?;
+    Ok(page)
+  }
+
+  /// IPC API: goto_record(session_id, record_index, page_size) -> GotoRecord
+  ///
+  /// `page_at` for "go to line" in the UI. Answered synchronously when the line index already
+  /// covers the record, the file is below `line_index_min_bytes` or Parquet. Otherwise a cancellable background
+  /// task indexes up to the record (or the index build started on open is reused): poll it with
+  /// `get_task` and call `goto_record` again once it finished to get the page.
+  pub fn goto_record(&self, session_id: &str, record_index: u64, page_size: usize) -> Result<GotoRecord, CoreError> {
+    let (path, format, line_index, goto_task, index_task, plain) = {
+      let mut sessions = self.sessions.lock();
+      let s = sessions
+        .get_mut(session_id)
+        .ok_or_else(|| CoreError::UnknownSession(session_id.to_string()))?;
+      s.last_access_ms = now_ms();
+      (
+        PathBuf::from(&s.info.path),
+        s.format.clone(),
+        s.line_index.clone(),
+        s.goto_task_id.clone(),
+        s.info.index_task.clone(),
+        s.shards.is_none() && s.view.is_none(),
+      )
+    };
+    let needs_scan = plain
+      && matches!(format, FileFormat::Jsonl | FileFormat::Csv | FileFormat::Json)
+      && !line_index.lock().covers(record_index)
+      && std::fs::metadata(&path)?.len() >= self.options.line_index_min_bytes;
+    if needs_scan {
+      let running = index_task
+        .into_iter()
+        .chain(goto_task.map(|id| TaskInfo {
+          id,
+          kind: TaskKind::IndexBuild,
+          cancellable: true,
+        }))
+        .find(|t| !self.tasks.is_task_finished(&t.id));
+      if let Some(task) = running {
+        return Ok(GotoRecord { page: None, task: Some(task) });
+      }
+
+      let target = line_index.clone();
+      let on_done = Box::new(move |index: LineIndex| {
+        let mut current = target.lock();
+        if current.total_records().is_none() && index.indexed_records() > current.indexed_records() {
+          *current = index;
+        }
+      });
+      let snapshot = line_index.lock().clone();
+      let task = self
+        .tasks
+        .start_line_index_extend(path, format, snapshot, record_index, on_done)?;
+      if let Some(s) = self.sessions.lock().get_mut(session_id) {
+        s.goto_task_id = Some(task.id.clone());
+      }
+      return Ok(GotoRecord {
+        page: None,
+        task: Some(TaskInfo {
+          id: task.id,
+          kind: TaskKind::IndexBuild,
+          cancellable: true,
+        }),
+      });
+    }
+    Ok(GotoRecord {
+      page: Some(self.page_at(session_id, record_index, page_size)?),
+      task: None,
+    })
+  }
+
+  /// IPC API: page_at_position(session_id, position, page_size) -> PositionPage
+  ///
+  /// Starts paging from an approximate position (byte offset or fraction of the file), snapped
+  /// to the next record boundary, for spot-checking the middle of giant files.
+  ///
+  /// - JSONL/CSV: snaps to the next line start (CSV: a quoted multi-line cell can make this land
+  ///   mid-record unless the line index already covers the position). Ids are exact when the
+  ///   line index covers the position, otherwise estimated from the average record size; such
+  ///   pages and the ones read from their cursors report `ids_estimated`.
+  /// - `.json` root arrays: exact element boundary and id (structural byte scan up to the offset).
+  /// - Parquet: `Fraction` maps to a row; `ByteOffset` is not supported.
+  pub fn page_at_position(
+    &self,
+    session_id: &str,
+    position: SeekPosition,
+    page_size: usize,
+  ) -> Result<PositionPage, CoreError> {
+    let _interactive = self.tasks.interactive();
+    let started = Instant::now();
+    let (path, format, line_index, encoding, parquet, csv, lenient, columns) = {
+      let mut sessions = self.sessions.lock();
+      let s = sessions
+        .get_mut(session_id)
+        .ok_or_else(|| CoreError::UnknownSession(session_id.to_string()))?;
+      s.last_access_ms = now_ms();
+      if s.shards.is_some() {
+        return Err(multi_file_unsupported("page_at_position"));
+      }
+      if s.view.is_some() {
+        return Err(filtered_unsupported("page_at_position"));
+      }
+      (
+        PathBuf::from(&s.info.path),
+        s.format.clone(),
+        s.line_index.clone(),
+        s.info.encoding,
+        s.parquet.clone(),
+        s.info.csv.clone(),
+        s.info.json_lenient,
+        s.info.columns.clone(),
+      )
+    };
+    self.evict_idle_sessions();
+    if let SeekPosition::Fraction { value } = position {
+      if !(0.0..=1.0).contains(&value) {
+        return Err(CoreError::InvalidArg(format!("fraction must be within [0, 1], got {value}")));
+      }
+    }
+
+    if format == FileFormat::Parquet {
+      let SeekPosition::Fraction { value } = position else {
+        return Err(CoreError::InvalidArg("parquet does not support byte offsets; use a fraction".into()));
+      };
+      let rows = match parquet {
+        Some(conn) => conn.lock().row_count()?,
+        None => formats::read_parquet_row_count(&path)?,
+      };
+      let row = ((rows as f64 * value) as u64).min(rows.saturating_sub(1));
+      let page = self.page_at(session_id, row, page_size)?;
+      return Ok(PositionPage {
+        page,
+        byte_offset: None,
+        ids_exact: true,
+      });
+    }
+
+    let file_len = std::fs::metadata(&path)?.len();
+    let target = match position {
+      SeekPosition::ByteOffset { offset } => offset.min(file_len),
+      SeekPosition::Fraction { value } => ((file_len as f64 * value) as u64).min(file_len),
+    };
+
+    let (cursor, ids_exact) = match format {
+      FileFormat::Jsonl | FileFormat::Csv => {
+        let snapped = formats::next_line_start(&path, target)?;
+        match line_index.lock().locate(&path, format.clone(), snapped)? {
+          Some((index, offset)) => (Cursor { offset, line: index }, true),
+          None if snapped == 0 => (Cursor { offset: 0, line: 0 }, true),
+          // Id unknown until the first page tells us the typical record size.
+          None => (Cursor { offset: snapped, line: 1 }, false),
+        }
+      }
+      FileFormat::Json => match formats::root_array_element_at_or_after(&path, lenient, target)? {
+        Some((offset, index)) => (Cursor { offset, line: index }, true),
+        None if target == 0 => (Cursor { offset: 0, line: 0 }, true),
+        None => {
+          return Err(CoreError::InvalidArg(format!(
+            "no root-array element at or after byte {target}"
+          )))
+        }
+      },
+      other => return Err(CoreError::UnsupportedFormat(other)),
+    };
+
+    let mut page = self.read_page_from(&path, format.clone(), cursor, page_size, encoding, &csv, lenient, columns.as_deref())?;
+    if !ids_exact {
+      let metas: Vec<_> = page.records.iter().filter_map(|r| r.meta.as_ref()).collect();
+      let bytes: u64 = metas.iter().map(|m| m.byte_len).sum();
+      if bytes > 0 {
+        let avg = bytes as f64 / metas.len() as f64;
+        let estimate = ((cursor.offset as f64 / avg).round() as u64).max(1);
+        page = self.read_page_from(
+          &path,
+          format,
+          Cursor { offset: cursor.offset, line: estimate },
+          page_size,
+          encoding,
+          &csv,
+          lenient,
+          columns.as_deref(),
+        )?;
+      }
+      mark_ids_estimated(&mut page);
+    }
+    self.finish_page(session_id, &mut page, started)?;
+    Ok(PositionPage {
+      byte_offset: page.records.first().and_then(|r| r.meta.as_ref()).map(|m| m.byte_offset),
+      page,
+      ids_exact,
+    })
+  }
+
+  /// IPC API: poll_new_records(session_id, max_records) -> NewRecords
+  ///
+  /// Follow mode for growing JSONL/CSV files (live logs): returns complete records appended
+  /// since the previous poll (or since open, for the first one). A trailing line without its
+  /// newline yet is left for the next poll. Cached counts and the line index are updated.
+  pub fn poll_new_records(&self, session_id: &str, max_records: usize) -> Result<NewRecords, CoreError> {
+    let (path, format, follow, line_index, encoding, csv, lenient, columns) = {
+      let mut sessions = self.sessions.lock();
+      let s = sessions
+        .get_mut(session_id)
+        .ok_or_else(|| CoreError::UnknownSession(session_id.to_string()))?;
+      s.last_access_ms = now_ms();
+      if s.shards.is_some() {
+        return Err(multi_file_unsupported("poll_new_records"));
+      }
+      if s.view.is_some() {
+        return Err(filtered_unsupported("poll_new_records"));
+      }
+      let follow = s.follow.ok_or_else(|| CoreError::UnsupportedFormat(s.format.clone()))?;
+      (
+        PathBuf::from(&s.info.path),
+        s.format.clone(),
+        follow,
+        s.line_index.clone(),
+        s.info.encoding,
+        s.info.csv.clone(),
+        s.info.json_lenient,
+        s.info.columns.clone(),
+      )
+    };
+    let max_records = if max_records == 0 {
+      self.options.default_page_size
+    } else {
+      max_records
+    };
+
+    let file_len = std::fs::metadata(&path)?.len();
+    if file_len < follow.offset {
+      // Rewritten from scratch: cached counts / offsets no longer apply.
+      let (offset, line) = record_boundary_at_or_after(&path, &format, &csv, file_len)?;
+      if let Some(s) = self.sessions.lock().get_mut(session_id) {
+        s.follow = Some(FollowCursor { offset, line: Some(line) });
+        s.record_count = None;
+        s.line_index = Arc::new(Mutex::new(LineIndex::for_csv(&csv)));
+      }
+      return Ok(NewRecords {
+        records: vec![],
+        more_available: false,
+        reset: true,
+      });
+    }
+    let line = match follow.line {
+      Some(line) => line,
+      None => record_boundary_at_or_after(&path, &format, &csv, follow.offset)?.1,
+    };
+    let mut cursor = FollowCursor { offset: follow.offset, line: Some(line) };
+
+    let mut records = Vec::new();
+    let mut more_available = false;
+    if file_len > follow.offset {
+      let page = self.read_page_from(
+        &path,
+        format.clone(),
+        Cursor { offset: follow.offset, line },
+        max_records + 1,
+        encoding,
+        &csv,
+        lenient,
+        columns.as_deref(),
+      )?;
+      let complete_end = last_newline_end(&path, file_len)?;
+      for r in page.records {
+        let Some(m) = r.meta.as_ref() else { break };
+        let end = m.byte_offset + m.byte_len;
+        if end > complete_end {
+          break;
+        }
+        if records.len() == max_records {
+          more_available = true;
+          break;
+        }
+        cursor = FollowCursor { offset: end, line: Some(r.id + 1) };
+        records.push(r);
+      }
+    }
+
+    if let Some(s) = self.sessions.lock().get_mut(session_id) {
+      s.follow = Some(cursor);
+      s.csv_warnings.note(&records);
+      if !records.is_empty() {
+        // Everything up to the cursor is counted exactly; with more pending, recount later.
+        let header = u64::from(format == FileFormat::Csv && csv.has_header);
+        s.record_count = match (more_available, cursor.line) {
+          (false, Some(line)) => Some(line.saturating_sub(header)),
+          _ => None,
+        };
+      }
+    }
+    if !records.is_empty() {
+      line_index.lock().mark_grown();
+    }
+    self.derive_records(session_id, &mut records)?;
+    Ok(NewRecords {
+      records,
+      more_available,
+      reset: false,
+    })
+  }
+
+  /// IPC API: refresh_session(session_id) -> SessionRefresh
+  ///
+  /// Re-stats the file after another process wrote to it. If JSONL/CSV only grew, cursors stay
+  /// valid and the appended records are counted (from the previous end, not the file start).
+  /// Anything else (shrunk, same size but modified, JSON/Parquet changed) drops cached counts and
+  /// indexes and reports `Rewritten`.
+  pub fn refresh_session(&self, session_id: &str) -> Result<SessionRefresh, CoreError> {
+    let (path, format, identity, csv) = {
+      let mut sessions = self.sessions.lock();
+      let s = sessions
+        .get_mut(session_id)
+        .ok_or_else(|| CoreError::UnknownSession(session_id.to_string()))?;
+      s.last_access_ms = now_ms();
+      if s.shards.is_some() {
+        return Err(multi_file_unsupported("refresh_session"));
+      }
+      if s.view.is_some() {
+        return Err(filtered_unsupported("refresh_session"));
+      }
+      (PathBuf::from(&s.info.path), s.format.clone(), s.identity, s.info.csv.clone())
+    };
+    let (previous_size, previous_mtime) = identity.unwrap_or((0, 0));
+    let current = file_identity(&path).ok_or_else(|| {
+      CoreError::InvalidArg(format!("cannot stat {}", path.display()))
+    })?;
+    let (size, mtime) = current;
+
+    let grew = size > previous_size && matches!(format, FileFormat::Jsonl | FileFormat::Csv);
+    let change = if size == previous_size && mtime == previous_mtime {
+      FileChange::Unchanged
+    } else if grew {
+      FileChange::Grew
+    } else {
+      FileChange::Rewritten
+    };
+    let appended_records = match change {
+      FileChange::Grew => Some(count_appended(&path, &format, previous_size, &csv)?),
+      _ => None,
+    };
+
+    if let Some(s) = self.sessions.lock().get_mut(session_id) {
+      s.identity = Some(current);
+      match change {
+        FileChange::Unchanged => {}
+        FileChange::Grew => {
+          // The cached count may predate or include the append; recount on demand.
+          s.record_count = None;
+          s.line_index.lock().mark_grown();
+          // Existing records did not move, so their labels carry over to the new version.
+          let _ = self
+            .storage
+            .move_record_labels(&s.info.path, (previous_size, previous_mtime), current);
+        }
+        FileChange::Rewritten => {
+          s.record_count = None;
+          s.count_task_id = None;
+          s.line_index = Arc::new(Mutex::new(LineIndex::for_csv(&csv)));
+          s.last_page = None;
+          s.csv_warnings = WarningLog::default();
+          s.follow = follow_baseline(&path, &format);
+        }
+      }
+    }
+    Ok(SessionRefresh {
+      change,
+      previous_size,
+      size,
+      appended_records,
+    })
+  }
+
+  /// IPC API: reload_session(session_id) -> { session, first_page }
+  ///
+  /// Reopens the session's file in place, for when it was replaced or rewritten: the format is
+  /// detected again, cached pages, counts and the line index are dropped (background count /
+  /// index tasks are cancelled) and the new first page is returned. If the file changed since
+  /// open / the last refresh, the encoding (JSONL / CSV) is detected again and cursors issued
+  /// before are rejected with `BadCursor`, so paging restarts from this page. Derived columns
+  /// are kept.
+  pub fn reload_session(&self, session_id: &str) -> Result<(SessionInfo, RecordPage), CoreError> {
+    let started = Instant::now();
+    let (path, old_format, old_encoding, identity, old_parquet, csv, json_lenient) = {
+      let mut sessions = self.sessions.lock();
+      let s = sessions
+        .get_mut(session_id)
+        .ok_or_else(|| CoreError::UnknownSession(session_id.to_string()))?;
+      s.last_access_ms = now_ms();
+      if s.shards.is_some() {
+        return Err(multi_file_unsupported("reload_session"));
+      }
+      if s.view.is_some() {
+        return Err(filtered_unsupported("reload_session"));
+      }
+      (
+        PathBuf::from(&s.info.path),
+        s.format.clone(),
+        s.info.encoding,
+        s.identity,
+        s.parquet.clone(),
+        s.info.csv.clone(),
+        s.info.json_lenient,
+      )
+    };
+    let decryption_key = old_parquet.and_then(|c| c.lock().decryption_key().map(str::to_string));
+    let format = formats::detect_format(&path);
+    match format {
+      FileFormat::Jsonl | FileFormat::Csv | FileFormat::Json | FileFormat::Parquet => {}
+      _ => return Err(CoreError::UnsupportedFormat(format)),
+    }
+    let current = file_identity(&path).ok_or_else(|| {
+      CoreError::InvalidArg(format!("cannot stat {}", path.display()))
+    })?;
+    let changed = identity != Some(current);
+    let encoding = match format {
+      _ if !changed && format == old_format => old_encoding,
+      FileFormat::Jsonl | FileFormat::Csv => encoding_impl::detect_file_encoding(&path)?,
+      _ => TextEncoding::Utf8,
+    };
+
+    let lenient_json = json_lenient && format == FileFormat::Json;
+    let line_index = Arc::new(Mutex::new(match format {
+      FileFormat::Json => LineIndex::for_json(json_lenient),
+      _ => LineIndex::for_csv(&csv),
+    }));
+    let parquet = parquet_conn(&path, &format, &self.options, decryption_key.as_deref())?;
+    let (wanted_columns, wanted_filters) = self
+      .sessions
+      .lock()
+      .get(session_id)
+      .map(|s| (s.info.columns.clone(), s.info.filters.clone()))
+      .unwrap_or_default();
+    let columns = match (&parquet, wanted_columns) {
+      (Some(conn), Some(wanted)) => existing_columns(&conn.lock(), &wanted)?,
+      (None, Some(wanted)) if format == FileFormat::Csv => {
+        known_columns(&formats::read_csv_header(&path, encoding, &csv)?, &wanted)
+      }
+      _ => None,
+    };
+    let filters = match (&parquet, wanted_filters) {
+      (Some(conn), Some(wanted)) => existing_filters(&conn.lock(), &wanted)?,
+      _ => None,
+    };
+    let old_tasks = {
+      let mut sessions = self.sessions.lock();
+      let s = sessions
+        .get_mut(session_id)
+        .ok_or_else(|| CoreError::UnknownSession(session_id.to_string()))?;
+      let index_task = s.info.index_task.take().map(|t| t.id);
+      let tasks: Vec<String> = s
+        .count_task_id
+        .take()
+        .into_iter()
+        .chain(s.goto_task_id.take())
+        .chain(index_task)
+        .collect();
+      if changed {
+        s.cursor_epoch += 1;
+      }
+      s.info.format = format.clone();
+      s.info.encoding = encoding;
+      s.format = format.clone();
+      s.record_count = None;
+      s.line_index = line_index.clone();
+      s.last_page = None;
+      s.csv_warnings = WarningLog::default();
+      s.follow = follow_baseline(&path, &format);
+      s.identity = Some(current);
+      if s.shards.is_none() && s.view.is_none() {
+        s.parquet = parquet.clone();
+        s.info.columns = columns.clone();
+        s.info.filters = filters.clone();
+      }
+      tasks
+    };
+    for task_id in &old_tasks {
+      if !self.tasks.is_task_finished(task_id) {
+        let _ = self.tasks.cancel_task(task_id);
+      }
+    }
+
+    // Lenient `.json` indexes fill in as pages are read (they are never stored).
+    let index_task = if lenient_json {
+      None
+    } else {
+      self.prepare_line_index(&path, &format, &csv, &line_index)
+    };
+    let render = RecordRender {
+      columns: columns.as_deref(),
+      filters: filters.as_deref(),
+      csv: Some(&csv),
+      json_lenient,
+      parquet: parquet.as_deref(),
+      ..self.render(encoding)
+    };
+    let mut page =
+      self.read_page_with_limits(&path, format, Cursor { offset: 0, line: 0 }, self.options.default_page_size, render)?;
+    self.finish_page(session_id, &mut page, started)?;
+    let mut sessions = self.sessions.lock();
+    let s = sessions
+      .get_mut(session_id)
+      .ok_or_else(|| CoreError::UnknownSession(session_id.to_string()))?;
+    s.info.index_task = index_task;
+    Ok((s.info.clone(), page))
+  }
+
+  /// IPC API: search(session_id, query, mode) -> SearchResult
+  ///
+  /// - current_page: runs synchronously over last returned page (open_file/next_page)
+  /// - scan_all: starts a cancellable background task and returns task info; repeating a scan
+  ///   of an unchanged single file returns a task already finished with the cached hits
+  /// - indexed: a scan_all task of a JSONL / CSV file that only reads the lines its full-text
+  ///   index (`build_text_index`) can't rule out; same hits, fails without the index
+  pub fn search(&self, session_id: &str, query: SearchQuery) -> Result<SearchResult, CoreError> {
+    let (path, format, last_page, shards, view, encoding, derived, filters, parquet, paths, csv, json_lenient) = {
+      let mut sessions = self.sessions.lock();
+      let s = sessions
+        .get_mut(session_id)
+        .ok_or_else(|| CoreError::UnknownSession(session_id.to_string()))?;
+      s.last_access_ms = now_ms();
+      (
+        PathBuf::from(&s.info.path),
+        s.format.clone(),
+        s.last_page.clone(),
+        s.shards.clone(),
+        s.view.is_some(),
+        s.info.encoding,
+        s.derived.clone(),
+        s.info.filters.clone(),
+        s.parquet.clone(),
+        history_paths(&s.info),
+        s.info.csv.clone(),
+        s.info.json_lenient,
+      )
+    };
+    let decryption_key = parquet.and_then(|c| c.lock().decryption_key().map(str::to_string));
+
+    match query.mode {
+      SearchMode::CurrentPage => {
+        let _interactive = self.tasks.interactive();
+        let lp = last_page.ok_or_else(|| CoreError::InvalidArg("no page cached".into()))?;
+        Ok(formats::search_current_page(&lp, &query))
+      }
+      SearchMode::ScanAll | SearchMode::CountOnly => {
+        if view {
+          return Err(filtered_unsupported("scan_all search"));
+        }
+        let mode = query.mode.clone();
+        let params = serde_json::json!({ "query": &query });
+        let task = match shards {
+          Some(shards) => {
+            let (parts, spans) = {
+              let shards = shards.lock();
+              (shards.parts().to_vec(), shards.spans())
+            };
+            self
+              .tasks
+              .start_search_scan_all_parts(parts, spans, format, query, self.options.preview_max_chars)?
+          }
+          None => {
+            let deriver = match format {
+              FileFormat::Jsonl | FileFormat::Csv if !derived.is_empty() => {
+                let headers = match format {
+                  FileFormat::Csv => Some(formats::read_csv_header(&path, encoding, &csv)?),
+                  _ => None,
+                };
+                Some(LineDeriver::new((*derived).clone(), headers, csv.clone()))
+              }
+              _ => None,
+            };
+            // Hits depend on derived values too; only plain scans are cached.
+            let cache = (deriver.is_none() && mode == SearchMode::ScanAll)
+              .then(|| file_identity(&path))
+              .flatten()
+              .map(|(file_size, file_mtime_ms)| ScanCacheKey {
+                path: path.to_string_lossy().to_string(),
+                file_size,
+                file_mtime_ms,
+                query_key: serde_json::json!({
+                  "text": &query.text,
+                  "case_sensitive": query.case_sensitive,
+                  "max_hits": query.max_hits,
+                  "encoding": encoding,
+                  "json_lenient": json_lenient,
+                  "preview_max_chars": self.options.preview_max_chars,
+                  "parquet_utc_offset_minutes": self.options.parquet_utc_offset_minutes,
+                  "filters": &filters,
+                })
+                .to_string(),
+              });
+            self.tasks.start_search_scan_all(
+              path,
+              format,
+              encoding,
+              json_lenient,
+              query,
+              self.options.preview_max_chars,
+              deriver,
+              filters,
+              decryption_key,
+              cache,
+            )?
+          }
+        };
+        self.tasks.record_in_history(&task.id, TaskOrigin { paths, params });
+        let kind = match mode {
+          SearchMode::CountOnly => TaskKind::SearchCount,
+          _ => TaskKind::SearchScanAll,
+        };
+        Ok(SearchResult {
+          mode,
+          hits: vec![],
+          task: Some(TaskInfo {
+            id: task.id.clone(),
+            kind,
+            cancellable: true,
+          }),
+          truncated: false,
+        })
+      }
+      SearchMode::Indexed => {
+        if shards.is_some() {
+          return Err(multi_file_unsupported("indexed search"));
+        }
+        if view {
+          return Err(filtered_unsupported("indexed search"));
+        }
+        if format != FileFormat::Jsonl && format != FileFormat::Csv {
+          return Err(CoreError::UnsupportedFormat(format));
+        }
+        if !derived.is_empty() {
+          return Err(CoreError::InvalidArg(
+            "indexed search doesn't cover derived columns; use scan_all".into(),
+          ));
+        }
+        let Some(index) = self.text_index(&path, encoding, false).0 else {
+          return Err(CoreError::InvalidArg(
+            "no full-text index for this file yet; build one with build_text_index".into(),
+          ));
+        };
+        let params = serde_json::json!({ "query": &query });
+        let task = self
+          .tasks
+          .start_search_indexed(path, encoding, query, self.options.preview_max_chars, index)?;
+        self.tasks.record_in_history(&task.id, TaskOrigin { paths, params });
+        Ok(SearchResult {
+          mode: SearchMode::Indexed,
+          hits: vec![],
+          task: Some(TaskInfo {
+            id: task.id,
+            kind: TaskKind::SearchScanAll,
+            cancellable: true,
+          }),
+          truncated: false,
+        })
+      }
+    }
+  }
+
+  /// Poll a background task status.
+  pub fn get_task(&self, task_id: &str) -> Result<Task, CoreError> {
+    self.tasks.get_task(task_id).map_err(CoreError::Task)
+  }
+
+  pub fn cancel_task(&self, task_id: &str) -> Result<(), CoreError> {
+    self.tasks.cancel_task(task_id).map_err(CoreError::Task)
+  }
+
+  /// IPC API: pause_task(task_id) -> ()
+  ///
+  /// Holds a background task (scan, count, stats, diff, index, spool) between two records so it
+  /// stops reading the file; `resume_task` continues from the same place. A paused task keeps
+  /// its `max_concurrent_tasks` slot and can still be cancelled.
+  pub fn pause_task(&self, task_id: &str) -> Result<(), CoreError> {
+    self.tasks.pause_task(task_id).map_err(CoreError::Task)
+  }
+
+  /// IPC API: resume_task(task_id) -> ()
+  pub fn resume_task(&self, task_id: &str) -> Result<(), CoreError> {
+    self.tasks.resume_task(task_id).map_err(CoreError::Task)
+  }
+
+  /// IPC API: max_concurrent_tasks() -> number
+  pub fn max_concurrent_tasks(&self) -> usize {
+    self.tasks.max_concurrent_tasks()
+  }
+
+  /// IPC API: set_max_concurrent_tasks(max) -> ()
+  ///
+  /// Changes how many background tasks run at once, effective immediately: queued tasks start
+  /// if the limit went up; if it went down, running tasks finish and the queue waits. Saved as a
+  /// setting, it replaces `CoreOptions.max_concurrent_tasks` for engines created later.
+  pub fn set_max_concurrent_tasks(&self, max: usize) -> Result<(), CoreError> {
+    if max == 0 {
+      return Err(CoreError::InvalidArg("max_concurrent_tasks must be at least 1".into()));
+    }
+    self
+      .storage
+      .set_setting_json(MAX_CONCURRENT_TASKS_SETTING, &max.to_string())
+      .map_err(CoreError::Storage)?;
+    self.tasks.set_max_concurrent_tasks(max);
+    Ok(())
+  }
+
+  /// IPC API: list_task_history(limit) -> TaskHistoryEntry[]
+  ///
+  /// Finished scan_all searches, stats and diff tasks and exports, newest first, kept in
+  /// SQLite across restarts. `paths` + `params` are what it takes to run one again: reopen the
+  /// files and repeat the call.
+  pub fn list_task_history(&self, limit: usize) -> Result<Vec<TaskHistoryEntry>, CoreError> {
+    self.storage.list_task_history(limit, None).map_err(CoreError::Storage)
+  }
+
+  /// IPC API: list_task_events(task_id) -> TaskEvent[]
+  ///
+  /// The task's event log, oldest first: when it started, each quarter of its progress and how
+  /// it ended, with the records, bytes and hits it had got to. Kept in SQLite across restarts.
+  pub fn list_task_events(&self, task_id: &str) -> Result<Vec<TaskEvent>, CoreError> {
+    self.storage.list_task_events(task_id).map_err(CoreError::Storage)
+  }
+
+  /// IPC API: list_export_history(limit) -> TaskHistoryEntry[]
+  ///
+  /// Like `list_task_history`, exports only: source files (`paths`), `{ request, format,
+  /// output_path, preset? }` (`params`) and the outcome.
+  pub fn list_export_history(&self, limit: usize) -> Result<Vec<TaskHistoryEntry>, CoreError> {
+    self
+      .storage
+      .list_task_history(limit, Some(&TaskKind::Export))
+      .map_err(CoreError::Storage)
+  }
+
+  /// IPC API: rerun_export(history_id) -> ExportResult
+  ///
+  /// Runs an export from the history again: reopens its source, repeats the same request (and
+  /// preset) into the same output file (replacing it) and closes the session. Recorded as a new
+  /// entry. `search_task` exports can't be repeated once their task is gone.
+  pub fn rerun_export(&self, history_id: i64) -> Result<ExportResult, CoreError> {
+    let entry = self
+      .storage
+      .get_task_history(history_id)
+      .map_err(CoreError::Storage)?
+      .filter(|e| e.kind == TaskKind::Export)
+      .ok_or_else(|| CoreError::InvalidArg(format!("no export in the history with id {history_id}")))?;
+    let param = |key: &str| {
+      entry
+        .params
+        .get(key)
+        .cloned()
+        .ok_or_else(|| CoreError::InvalidArg(format!("export history entry has no {key}")))
+    };
+    let request: ExportRequest =
+      serde_json::from_value(param("request")?).map_err(|e| CoreError::InvalidArg(e.to_string()))?;
+    let format: ExportFormat =
+      serde_json::from_value(param("format")?).map_err(|e| CoreError::InvalidArg(e.to_string()))?;
+    let output_path: String =
+      serde_json::from_value(param("output_path")?).map_err(|e| CoreError::InvalidArg(e.to_string()))?;
+
+    let preset: Option<ExportPreset> = match entry.params.get("preset") {
+      Some(p) => Some(serde_json::from_value(p.clone()).map_err(|e| CoreError::InvalidArg(e.to_string()))?),
+      None => None,
+    };
+
+    let (session, _page) = self.reopen(&entry.paths)?;
+    let res = self.export_recorded(&session.session_id, request, format, preset.as_ref(), Path::new(&output_path));
+    let _ = self.close_session(&session.session_id);
+    res
+  }
+
+  /// IPC API: list_interrupted_exports() -> InterruptedExport[]
+  ///
+  /// Exports that were still being written when the app last stopped, newest first. They never
+  /// reached their destination; their temp files were deleted on startup.
+  pub fn list_interrupted_exports(&self) -> Result<Vec<InterruptedExport>, CoreError> {
+    self.storage.list_interrupted_exports().map_err(CoreError::Storage)
+  }
+
+  /// IPC API: dismiss_interrupted_export(id) -> ()
+  pub fn dismiss_interrupted_export(&self, id: i64) -> Result<(), CoreError> {
+    if !self.storage.dismiss_interrupted_export(id).map_err(CoreError::Storage)? {
+      return Err(CoreError::InvalidArg(format!("no interrupted export with id {id}")));
+    }
+    Ok(())
+  }
+
+  /// IPC API: clear_task_history() -> ()
+  ///
+  /// Also clears the task event log.
+  pub fn clear_task_history(&self) -> Result<(), CoreError> {
+    self.storage.clear_task_history().map_err(CoreError::Storage)
+  }
+
+  /// Fetch accumulated hits from a scan_all search task, in pages.
+  pub fn search_task_hits_page(
+    &self,
+    task_id: &str,
+    cursor: Option<&str>,
+    page_size: usize,
+  ) -> Result<crate::models::RecordPage, CoreError> {
+    self.tasks
+      .search_task_hits_page(task_id, cursor, page_size)
+      .map_err(CoreError::Task)
+  }
+
+  /// IPC API: search_match_count(task_id) -> SearchCount
+  ///
+  /// Matches of a `count_only` search; poll it like `get_task` for a live count.
+  pub fn search_match_count(&self, task_id: &str) -> Result<SearchCount, CoreError> {
+    self.tasks.search_match_count(task_id).map_err(CoreError::Task)
+  }
+
+  /// IPC API: export(session_id, selection, format, output_path) -> ExportResult
+  ///
+  /// Every export, finished or failed, is added to the task history (`list_task_history`).
+  pub fn export(
+    &self,
+    session_id: &str,
+    request: ExportRequest,
+    format: ExportFormat,
+    output_path: impl AsRef<Path>,
+  ) -> Result<ExportResult, CoreError> {
+    self.export_recorded(session_id, request, format, None, output_path.as_ref())
+  }
+
+  /// IPC API: save_export_preset(preset) -> ExportPreset
+  ///
+  /// Creates the preset or replaces the one with the same name.
+  pub fn save_export_preset(&self, mut preset: ExportPreset) -> Result<ExportPreset, CoreError> {
+    preset.name = preset.name.trim().to_string();
+    if preset.name.is_empty() {
+      return Err(CoreError::InvalidArg("preset name is empty".into()));
+    }
+    if preset.columns.as_ref().is_some_and(|c| c.is_empty()) {
+      return Err(CoreError::InvalidArg("preset keeps no columns".into()));
+    }
+    preset.destination = preset.destination.filter(|d| !d.trim().is_empty());
+    self.storage.save_export_preset(&preset).map_err(CoreError::Storage)?;
+    Ok(preset)
+  }
+
+  /// IPC API: list_export_presets() -> ExportPreset[]
+  pub fn list_export_presets(&self) -> Result<Vec<ExportPreset>, CoreError> {
+    self.storage.list_export_presets().map_err(CoreError::Storage)
+  }
+
+  /// IPC API: delete_export_preset(name) -> ()
+  pub fn delete_export_preset(&self, name: &str) -> Result<(), CoreError> {
+    if !self.storage.delete_export_preset(name).map_err(CoreError::Storage)? {
+      return Err(CoreError::InvalidArg(format!("unknown export preset: {name}")));
+    }
+    Ok(())
+  }
+
+  /// IPC API: export_with_preset(session_id, selection, preset, output_path?) -> ExportResult
+  ///
+  /// `export` with the named preset's format, columns and flattening. Without `output_path` the
+  /// preset's destination template is used. The history keeps a copy of the preset, so
+  /// `rerun_export` repeats it even after the preset changes.
+  pub fn export_with_preset(
+    &self,
+    session_id: &str,
+    request: ExportRequest,
+    preset_name: &str,
+    output_path: Option<&str>,
+  ) -> Result<ExportResult, CoreError> {
+    let preset = self
+      .storage
+      .get_export_preset(preset_name)
+      .map_err(CoreError::Storage)?
+      .ok_or_else(|| CoreError::InvalidArg(format!("unknown export preset: {preset_name}")))?;
+    let output_path = match output_path {
+      Some(p) => PathBuf::from(p),
+      None => {
+        let template = preset.destination.as_deref().ok_or_else(|| {
+          CoreError::InvalidArg(format!("export preset {preset_name} has no destination; pass output_path"))
+        })?;
+        let source = {
+          let sessions = self.sessions.lock();
+          let s = sessions
+            .get(session_id)
+            .ok_or_else(|| CoreError::UnknownSession(session_id.to_string()))?;
+          PathBuf::from(&s.info.path)
+        };
+        preset_destination(template, &source, &preset.format)
+      }
+    };
+    let format = preset.format.clone();
+    self.export_recorded(session_id, request, format, Some(&preset), &output_path)
+  }
+
+  fn export_recorded(
+    &self,
+    session_id: &str,
+    request: ExportRequest,
+    format: ExportFormat,
+    preset: Option<&ExportPreset>,
+    output_path: &Path,
+  ) -> Result<ExportResult, CoreError> {
+    let paths = {
+      let sessions = self.sessions.lock();
+      let s = sessions
+        .get(session_id)
+        .ok_or_else(|| CoreError::UnknownSession(session_id.to_string()))?;
+      history_paths(&s.info)
+    };
+    let mut params = serde_json::json!({
+      "request": &request,
+      "format": &format,
+      "output_path": output_path.to_string_lossy(),
+    });
+    if let Some(preset) = preset {
+      params["preset"] = serde_json::json!(preset);
+    }
+    let task_id = Uuid::new_v4().to_string();
+    let started_at_ms = now_ms();
+    let started = Instant::now();
+    // Written next to the output and moved over it once complete, so a failed or interrupted
+    // export never leaves a truncated file under the requested name. The journal entry lets the
+    // next start clean up after a crash (see `list_interrupted_exports`).
+    let res = self.tasks.export_started().map_err(CoreError::Task).and_then(|_running| {
+      let partial = partial_output_path(output_path);
+      let journal_id = self
+        .storage
+        .begin_export(&task_id, &output_path.to_string_lossy(), &partial.to_string_lossy())
+        .map_err(CoreError::Storage)?;
+      let written = match preset {
+        Some(preset) if preset.flatten || preset.columns.is_some() => {
+          self.export_reshaped(session_id, request, preset, &partial)
+        }
+        _ => self.export_session(session_id, request, format, &partial),
+      };
+      let res = written.and_then(|mut r| {
+        std::fs::rename(&partial, output_path)?;
+        r.output_path = output_path.to_string_lossy().to_string();
+        Ok(r)
+      });
+      if res.is_err() {
+        let _ = std::fs::remove_file(&partial);
+      }
+      let _ = self.storage.end_export(journal_id);
+      res
+    });
+    let finished_at_ms = now_ms();
+    let _ = self.storage.save_task_history(&TaskHistoryEntry {
+      id: 0,
+      task_id,
+      kind: TaskKind::Export,
+      paths,
+      params,
+      started_at_ms,
+      finished_at_ms,
+      duration_ms: started.elapsed().as_millis() as u64,
+      cancelled: false,
+      summary: res.as_ref().ok().map(|r| format!("{} records written", r.records_written)),
+      error: res.as_ref().err().map(TaskError::from),
+    });
+    res
+  }
+
+  /// Exports as JSONL into a staging file next to `output_path`, then reshapes that per the
+  /// preset.
+  fn export_reshaped(
+    &self,
+    session_id: &str,
+    request: ExportRequest,
+    preset: &ExportPreset,
+    output_path: &Path,
+  ) -> Result<ExportResult, CoreError> {
+    let stage = staging_path(output_path);
+    let res = self
+      .export_session(session_id, request, ExportFormat::Jsonl, &stage)
+      .and_then(|_| {
+        export_impl::reshape_jsonl(
+          &self.tasks,
+          &stage,
+          preset.format.clone(),
+          preset.columns.as_deref(),
+          preset.flatten,
+          output_path,
+        )
+      });
+    let _ = std::fs::remove_file(&stage);
+    res
+  }
+
+  fn export_session(
+    &self,
+    session_id: &str,
+    request: ExportRequest,
+    format: ExportFormat,
+    output_path: &Path,
+  ) -> Result<ExportResult, CoreError> {
+    let (path, file_format, shards, derived, parquet, filters, csv, json_lenient) = {
+      let mut sessions = self.sessions.lock();
+      let s = sessions
+        .get_mut(session_id)
+        .ok_or_else(|| CoreError::UnknownSession(session_id.to_string()))?;
+      s.last_access_ms = now_ms();
+      (
+        PathBuf::from(&s.info.path),
+        s.format.clone(),
+        s.shards.clone(),
+        s.derived.clone(),
+        s.parquet.clone(),
+        s.info.filters.clone(),
+        s.info.csv.clone(),
+        s.info.json_lenient,
+      )
+    };
+    // Held for the whole export: pages of the session wait for it rather than racing it.
+    let parquet = parquet.as_ref().map(|conn| conn.lock());
+    let parquet = parquet.as_deref();
+    if let ExportRequest::Labeled { tag } = &request {
+      let labels = self.list_record_labels(session_id, tag.as_deref())?;
+      return export_impl::export_labeled(
+        &self.tasks,
+        &path,
+        &file_format,
+        &labels,
+        format,
+        output_path,
+        parquet,
+        &csv,
+      );
+    }
+    let request = match (request, parquet) {
+      (ExportRequest::Filtered, Some(conn)) => ExportRequest::Selection {
+        record_ids: conn.matching_rows(filters.as_deref().unwrap_or_default())?,
+      },
+      (ExportRequest::Filtered, None) if shards.is_none() => {
+        return Err(CoreError::UnsupportedFormat(file_format));
+      }
+      (request, _) => request,
+    };
+    if let Some(shards) = shards {
+      return export_impl::export_shards(&self.tasks, &mut shards.lock(), request, format, output_path);
+    }
+    let derived = (!derived.is_empty()).then_some(derived.as_ref());
+    export_impl::export(
+      &self.tasks,
+      path,
+      file_format,
+      request,
+      format,
+      output_path,
+      derived,
+      parquet,
+      &csv,
+      json_lenient,
+    )
+  }
+
+  /// IPC API: set_record_label(session_id, meta, tags, note?) -> RecordLabel?
+  ///
+  /// Replaces the tags / note of the record at `meta` (stored per file version and record
+  /// offset). Empty tags and no note remove the label.
+  pub fn set_record_label(
+    &self,
+    session_id: &str,
+    meta: RecordMeta,
+    tags: Vec<String>,
+    note: Option<String>,
+  ) -> Result<Option<RecordLabel>, CoreError> {
+    let (path, format, (size, mtime)) = self.label_target(session_id, "set_record_label")?;
+    let key = path.to_string_lossy().to_string();
+    let record_offset = label_offset(&format, &meta);
+
+    let mut tags: Vec<String> = tags
+      .into_iter()
+      .map(|t| t.trim().to_string())
+      .filter(|t| !t.is_empty())
+      .collect();
+    tags.sort();
+    tags.dedup();
+    let note = note.filter(|n| !n.trim().is_empty());
+    if tags.is_empty() && note.is_none() {
+      self
+        .storage
+        .delete_record_label(&key, size, mtime, record_offset)
+        .map_err(CoreError::Storage)?;
+      return Ok(None);
+    }
+
+    let stored = StoredRecordLabel {
+      record_offset,
+      line_no: meta.line_no,
+      byte_len: meta.byte_len,
+      tags,
+      note,
+      updated_at_ms: now_ms(),
+    };
+    self
+      .storage
+      .save_record_label(&key, size, mtime, &stored)
+      .map_err(CoreError::Storage)?;
+    Ok(Some(record_label(&format, stored)))
+  }
+
+  /// IPC API: list_record_labels(session_id, tag?) -> RecordLabel[]
+  ///
+  /// Labeled records of the session file in record order; `tag` keeps only records carrying it.
+  /// Labels are bound to the file version, so they are gone once the file is rewritten (appends
+  /// picked up by `refresh_session` keep them).
+  pub fn list_record_labels(&self, session_id: &str, tag: Option<&str>) -> Result<Vec<RecordLabel>, CoreError> {
+    let (path, format, (size, mtime)) = self.label_target(session_id, "list_record_labels")?;
+    let stored = self
+      .storage
+      .list_record_labels(&path.to_string_lossy(), size, mtime)
+      .map_err(CoreError::Storage)?;
+    Ok(
+      stored
+        .into_iter()
+        .filter(|l| tag.is_none_or(|t| l.tags.iter().any(|have| have == t)))
+        .map(|l| record_label(&format, l))
+        .collect(),
+    )
+  }
+
+  fn label_target(&self, session_id: &str, api: &str) -> Result<(PathBuf, FileFormat, (u64, i64)), CoreError> {
+    let (path, format) = {
+      let mut sessions = self.sessions.lock();
+      let s = sessions
+        .get_mut(session_id)
+        .ok_or_else(|| CoreError::UnknownSession(session_id.to_string()))?;
+      s.last_access_ms = now_ms();
+      if s.shards.is_some() {
+        return Err(multi_file_unsupported(api));
+      }
+      (PathBuf::from(&s.info.path), s.format.clone())
+    };
+    let identity = file_identity(&path).ok_or_else(|| {
+      CoreError::InvalidArg(format!("cannot stat {}", path.display()))
+    })?;
+    Ok((path, format, identity))
+  }
+
+  /// IPC API (v2): json_path_strings(path) -> JsonPathStrings
+  ///
+  /// A tree path (the same segments `json_list_children` and subtree exports take) as a JSON
+  /// Pointer and a JavaScript accessor, for "copy path".
+  pub fn json_path_strings(&self, path: &[JsonPathSegment]) -> JsonPathStrings {
+    JsonPathStrings::from_segments(path)
+  }
+
+  /// IPC API: json_list_children(session_id, meta, path, cursor, limit, sort) -> JsonChildrenPage
+  ///
+  /// Designed for huge single-record JSON values: list direct children under a selected subtree
+  /// without materializing the full JSON string. A `sort` other than `original` reads all the
+  /// node's children for every page (`cursor` is then the position in sorted order).
+  pub fn json_list_children(
+    &self,
+    session_id: &str,
+    meta: RecordMeta,
+    path: Vec<JsonPathSegment>,
+    cursor: Option<u64>,
+    limit: usize,
+    sort: JsonChildSort,
+  ) -> Result<JsonChildrenPage, CoreError> {
+    let (path_buf, format, lenient) = {
+      let mut sessions = self.sessions.lock();
+      let s = sessions
+        .get_mut(session_id)
+        .ok_or_else(|| CoreError::UnknownSession(session_id.to_string()))?;
+      s.last_access_ms = now_ms();
+      (PathBuf::from(&s.info.path), s.format.clone(), s.info.json_lenient)
+    };
+    if format != FileFormat::Json {
+      return Err(CoreError::UnsupportedFormat(format));
+    }
+    let cursor = cursor.unwrap_or(0);
+    let limit = if limit == 0 { 50 } else { limit };
+    let (start, consumed) = self.json_path_start(&path_buf, lenient, &meta, &path);
+    if sort != JsonChildSort::Original {
+      let node_offset = crate::formats::json_node_offset(&path_buf, lenient, start, &path[consumed..])?;
+      let children = crate::formats::sorted_json_children_at_offset(
+        &path_buf,
+        lenient,
+        node_offset,
+        sort,
+        self.options.preview_max_chars,
+      )?;
+      let total = children.len();
+      let end = (cursor as usize).saturating_add(limit).min(total);
+      let items = children
+        .into_iter()
+        .skip(cursor as usize)
+        .take(limit)
+        .map(|c| JsonChildItem {
+          seg: c.seg,
+          kind: c.kind,
+          preview: c.preview,
+        })
+        .collect();
+      let reached_end = end >= total;
+      return Ok(JsonChildrenPage {
+        items,
+        next_cursor: (!reached_end).then_some(end as u64),
+        reached_end,
+      });
+    }
+    crate::formats::list_json_children_page(
+      &path_buf,
+      lenient,
+      start,
+      &path[consumed..],
+      cursor,
+      limit,
+      self.options.preview_max_chars,
+    )
+  }
+
+  /// IPC API: json_node_summary(session_id, meta, path) -> JsonNodeSummary
+  ///
+  /// Returns node kind and (best-effort) child count. Counting may stop early due to caps.
+  pub fn json_node_summary(
+    &self,
+    session_id: &str,
+    meta: RecordMeta,
+    path: Vec<JsonPathSegment>,
+    max_items: Option<u64>,
+    max_scan_bytes: Option<u64>,
+  ) -> Result<JsonNodeSummary, CoreError> {
+    let (path_buf, format, lenient) = {
+      let mut sessions = self.sessions.lock();
+      let s = sessions
+        .get_mut(session_id)
+        .ok_or_else(|| CoreError::UnknownSession(session_id.to_string()))?;
+      s.last_access_ms = now_ms();
+      (PathBuf::from(&s.info.path), s.format.clone(), s.info.json_lenient)
     };
     if format != FileFormat::Json {
       return Err(CoreError::UnsupportedFormat(format));
     }
-    let max_items = max_items.unwrap_or(200_000);
-    let max_scan_bytes = max_scan_bytes.unwrap_or(64 * 1024 * 1024);
-    crate::formats::json_node_summary(&path_buf, meta.byte_offset, &path, max_items, max_scan_bytes)
+    let (start, consumed) = self.json_path_start(&path_buf, lenient, &meta, &path);
+    if consumed == path.len() {
+      if let Some(node) = self.json_node_index(&path_buf, lenient, &meta, false).0.and_then(|i| i.at_offset(start)) {
+        return Ok(indexed_summary(&node));
+      }
+    }
+    let max_items = max_items.unwrap_or(200_000);
+    let max_scan_bytes = max_scan_bytes.unwrap_or(64 * 1024 * 1024);
+    crate::formats::json_node_summary(&path_buf, lenient, start, &path[consumed..], max_items, max_scan_bytes)
+  }
+
+  /// IPC API (v2): json_list_children_at_offset(session_id, meta, node_offset, cursor_offset, limit, sort)
+  ///
+  /// This is a faster variant for huge records: the frontend navigates by absolute byte offsets
+  /// returned by the backend, so expanding deep nodes does not rescan the path from record start.
+  /// A `sort` other than `original` reads all the node's children for every page; its pages are
+  /// addressed by `cursor_index` (the position in sorted order) and `cursor_offset` is ignored.
+  #[allow(clippy::too_many_arguments)]
+  pub fn json_list_children_at_offset(
+    &self,
+    session_id: &str,
+    meta: RecordMeta,
+    node_offset: u64,
+    cursor_offset: Option<u64>,
+    cursor_index: Option<u64>,
+    limit: usize,
+    sort: JsonChildSort,
+  ) -> Result<JsonChildrenPageOffset, CoreError> {
+    let (path_buf, format, lenient) = {
+      let mut sessions = self.sessions.lock();
+      let s = sessions
+        .get_mut(session_id)
+        .ok_or_else(|| CoreError::UnknownSession(session_id.to_string()))?;
+      s.last_access_ms = now_ms();
+      (PathBuf::from(&s.info.path), s.format.clone(), s.info.json_lenient)
+    };
+    // Allow JSONL records to reuse the same "parse one JSON value at offset" streaming tree.
+    if format != FileFormat::Json && format != FileFormat::Jsonl {
+      return Err(CoreError::UnsupportedFormat(format));
+    }
+    // Basic safety: node_offset must be >= record_offset (we only support offsets within the record).
+    if node_offset < meta.byte_offset {
+      return Err(CoreError::InvalidArg(format!(
+        "node_offset {} is before record_offset {}",
+        node_offset, meta.byte_offset
+      )));
+    }
+    let limit = if limit == 0 { 50 } else { limit };
+    if sort != JsonChildSort::Original {
+      let children = crate::formats::sorted_json_children_at_offset(
+        &path_buf,
+        lenient,
+        node_offset,
+        sort,
+        self.options.preview_max_chars,
+      )?;
+      let start = cursor_index.unwrap_or(0) as usize;
+      let total = children.len();
+      let end = start.saturating_add(limit).min(total);
+      let reached_end = end >= total;
+      return Ok(JsonChildrenPageOffset {
+        items: children.into_iter().skip(start).take(limit).collect(),
+        // Sorted pages have no byte position; the node's own offset marks that more follow.
+        next_cursor_offset: (!reached_end).then_some(node_offset),
+        next_cursor_index: (!reached_end).then_some(end as u64),
+        reached_end,
+      });
+    }
+    crate::formats::list_json_children_page_at_offset(
+      &path_buf,
+      lenient,
+      node_offset,
+      cursor_offset,
+      cursor_index,
+      limit,
+      self.options.preview_max_chars,
+    )
+  }
+
+  /// IPC API (v2): json_array_table(session_id, meta, node_offset, columns, cursor_offset, cursor_index, limit) -> JsonTablePage
+  ///
+  /// Pages the array at `node_offset` (e.g. a record's `rows`) as a table: a row per element and
+  /// a column per key of the object elements, so arrays of similar objects browse like CSV. Pass
+  /// the `columns` of the previous page back to keep their order; keys first seen on a page are
+  /// appended. Cursors work as in `json_list_children_at_offset`.
+  #[allow(clippy::too_many_arguments)]
+  pub fn json_array_table(
+    &self,
+    session_id: &str,
+    meta: RecordMeta,
+    node_offset: u64,
+    columns: Vec<String>,
+    cursor_offset: Option<u64>,
+    cursor_index: Option<u64>,
+    limit: usize,
+  ) -> Result<JsonTablePage, CoreError> {
+    let (path_buf, format, lenient) = {
+      let mut sessions = self.sessions.lock();
+      let s = sessions
+        .get_mut(session_id)
+        .ok_or_else(|| CoreError::UnknownSession(session_id.to_string()))?;
+      s.last_access_ms = now_ms();
+      (PathBuf::from(&s.info.path), s.format.clone(), s.info.json_lenient)
+    };
+    if format != FileFormat::Json && format != FileFormat::Jsonl {
+      return Err(CoreError::UnsupportedFormat(format));
+    }
+    if node_offset < meta.byte_offset {
+      return Err(CoreError::InvalidArg(format!(
+        "node_offset {} is before record_offset {}",
+        node_offset, meta.byte_offset
+      )));
+    }
+    let limit = if limit == 0 { 50 } else { limit };
+    crate::formats::json_array_table_page(
+      &path_buf,
+      lenient,
+      node_offset,
+      columns,
+      cursor_offset,
+      cursor_index,
+      limit,
+      self.options.preview_max_chars,
+    )
+  }
+
+  /// IPC API (v2): json_find_in_record(session_id, meta, query) -> JsonFindResult
+  ///
+  /// Finds keys and / or scalar values containing `query.text` in the record at `meta`, reading
+  /// it once as a stream. Each hit carries its path and the offsets of the nodes along it, so the
+  /// tree can be expanded down to a field buried deep in a giant record with
+  /// `json_list_children_at_offset`.
+  pub fn json_find_in_record(
+    &self,
+    session_id: &str,
+    meta: RecordMeta,
+    query: JsonFindQuery,
+  ) -> Result<JsonFindResult, CoreError> {
+    let _interactive = self.tasks.interactive();
+    let (path_buf, format, lenient) = {
+      let mut sessions = self.sessions.lock();
+      let s = sessions
+        .get_mut(session_id)
+        .ok_or_else(|| CoreError::UnknownSession(session_id.to_string()))?;
+      s.last_access_ms = now_ms();
+      if s.shards.is_some() {
+        return Err(multi_file_unsupported("json_find_in_record"));
+      }
+      (PathBuf::from(&s.info.path), s.format.clone(), s.info.json_lenient)
+    };
+    if format != FileFormat::Json && format != FileFormat::Jsonl {
+      return Err(CoreError::UnsupportedFormat(format));
+    }
+    if query.text.is_empty() {
+      return Err(CoreError::InvalidArg("text must not be empty".into()));
+    }
+    if !query.keys && !query.values {
+      return Err(CoreError::InvalidArg("match keys, values or both".into()));
+    }
+    crate::formats::find_in_json_record(&path_buf, lenient, meta.byte_offset, &query, self.options.preview_max_chars)
+  }
+
+  /// IPC API (v2): json_eval_path(session_id, meta, expr, max_matches?) -> JsonPathResult
+  ///
+  /// Evaluates the JSONPath `expr` (`$`, `.key`, `['key']`, `[n]`, `*`, `..`) over the record at
+  /// `meta` as a stream, e.g. `$.messages[*].role`. Matches carry node offsets like
+  /// `json_find_in_record` hits; `max_matches` defaults to 1000.
+  pub fn json_eval_path(
+    &self,
+    session_id: &str,
+    meta: RecordMeta,
+    expr: &str,
+    max_matches: Option<usize>,
+  ) -> Result<JsonPathResult, CoreError> {
+    let _interactive = self.tasks.interactive();
+    let (path_buf, format, lenient) = {
+      let mut sessions = self.sessions.lock();
+      let s = sessions
+        .get_mut(session_id)
+        .ok_or_else(|| CoreError::UnknownSession(session_id.to_string()))?;
+      s.last_access_ms = now_ms();
+      if s.shards.is_some() {
+        return Err(multi_file_unsupported("json_eval_path"));
+      }
+      (PathBuf::from(&s.info.path), s.format.clone(), s.info.json_lenient)
+    };
+    if format != FileFormat::Json && format != FileFormat::Jsonl {
+      return Err(CoreError::UnsupportedFormat(format));
+    }
+    let max_matches = max_matches.filter(|&n| n > 0).unwrap_or(1000);
+    crate::formats::eval_json_path_in_record(
+      &path_buf,
+      lenient,
+      meta.byte_offset,
+      expr,
+      max_matches,
+      self.options.preview_max_chars,
+    )
+  }
+
+  /// IPC API (v2): json_node_summary_at_offset(session_id, meta, node_offset)
+  pub fn json_node_summary_at_offset(
+    &self,
+    session_id: &str,
+    meta: RecordMeta,
+    node_offset: u64,
+    max_items: Option<u64>,
+    max_scan_bytes: Option<u64>,
+  ) -> Result<JsonNodeSummaryOffset, CoreError> {
+    let (path_buf, format, lenient) = {
+      let mut sessions = self.sessions.lock();
+      let s = sessions
+        .get_mut(session_id)
+        .ok_or_else(|| CoreError::UnknownSession(session_id.to_string()))?;
+      s.last_access_ms = now_ms();
+      (PathBuf::from(&s.info.path), s.format.clone(), s.info.json_lenient)
+    };
+    // Allow JSONL records to reuse the same "parse one JSON value at offset" streaming tree.
+    if format != FileFormat::Json && format != FileFormat::Jsonl {
+      return Err(CoreError::UnsupportedFormat(format));
+    }
+    if node_offset < meta.byte_offset {
+      return Err(CoreError::InvalidArg(format!(
+        "node_offset {} is before record_offset {}",
+        node_offset, meta.byte_offset
+      )));
+    }
+    let index = self.json_node_index(&path_buf, lenient, &meta, false).0;
+    if let Some(node) = index.and_then(|i| i.at_offset(node_offset)) {
+      let summary = indexed_summary(&node);
+      return Ok(JsonNodeSummaryOffset {
+        kind: summary.kind,
+        child_count: summary.child_count,
+        complete: true,
+        node_offset,
+      });
+    }
+    let max_items = max_items.unwrap_or(200_000);
+    let max_scan_bytes = max_scan_bytes.unwrap_or(64 * 1024 * 1024);
+    crate::formats::json_node_summary_at_offset(&path_buf, lenient, node_offset, max_items, max_scan_bytes)
+  }
+
+  /// IPC API (v2): json_node_stats(session_id, node_offset) -> JsonNodeStats
+  ///
+  /// Byte size, nesting depth and key / element counts of the node at `node_offset` (a
+  /// record's or a tree node's offset), to tell a 2 KB subtree from a 2 GB one before expanding
+  /// or exporting it. Reads the whole node once.
+  pub fn json_node_stats(&self, session_id: &str, node_offset: u64) -> Result<JsonNodeStats, CoreError> {
+    let _interactive = self.tasks.interactive();
+    let (path_buf, format, lenient) = {
+      let mut sessions = self.sessions.lock();
+      let s = sessions
+        .get_mut(session_id)
+        .ok_or_else(|| CoreError::UnknownSession(session_id.to_string()))?;
+      s.last_access_ms = now_ms();
+      if s.shards.is_some() {
+        return Err(multi_file_unsupported("json_node_stats"));
+      }
+      (PathBuf::from(&s.info.path), s.format.clone(), s.info.json_lenient)
+    };
+    if format != FileFormat::Json && format != FileFormat::Jsonl {
+      return Err(CoreError::UnsupportedFormat(format));
+    }
+    crate::formats::json_node_stats_at_offset(&path_buf, lenient, node_offset)
+  }
+
+  /// IPC API (v2): json_validate_record(session_id, meta) -> JsonValidation
+  ///
+  /// Parses the JSON / JSONL record at `meta` as standard JSON (even in lenient sessions, see
+  /// `set_json_lenient`) and reports the first syntax error with its byte offset, line and
+  /// column, to pinpoint why a record breaks downstream tools. Content after the value within
+  /// the record (e.g. a second value on a JSONL line) is an error too. Reads the bytes as
+  /// stored, streaming, so records of any size can be checked.
+  pub fn json_validate_record(&self, session_id: &str, meta: RecordMeta) -> Result<JsonValidation, CoreError> {
+    let _interactive = self.tasks.interactive();
+    let (path_buf, format) = {
+      let mut sessions = self.sessions.lock();
+      let s = sessions
+        .get_mut(session_id)
+        .ok_or_else(|| CoreError::UnknownSession(session_id.to_string()))?;
+      s.last_access_ms = now_ms();
+      if s.shards.is_some() {
+        return Err(multi_file_unsupported("json_validate_record"));
+      }
+      (PathBuf::from(&s.info.path), s.format.clone())
+    };
+    if format != FileFormat::Json && format != FileFormat::Jsonl {
+      return Err(CoreError::UnsupportedFormat(format));
+    }
+    let error = crate::formats::validate_json_value(&path_buf, meta.byte_offset, meta.byte_len)?;
+    Ok(JsonValidation {
+      valid: error.is_none(),
+      error,
+    })
+  }
+
+  /// IPC API (v2): json_base64_preview(session_id, node_offset, max_bytes?) -> Base64Preview?
+  ///
+  /// If the string node at `node_offset` (see `json_node_offset`) holds a base64 payload (plain,
+  /// URL-safe or a `data:...;base64,` URI), decodes its first `max_bytes` (default 1024) as text
+  /// or hex, with the decoded size and a media type from the data URI or the payload's magic
+  /// bytes. `None` for other nodes and for strings that merely could be base64, like ids.
+  pub fn json_base64_preview(
+    &self,
+    session_id: &str,
+    node_offset: u64,
+    max_bytes: Option<usize>,
+  ) -> Result<Option<Base64Preview>, CoreError> {
+    let _interactive = self.tasks.interactive();
+    let (path_buf, format, lenient) = {
+      let mut sessions = self.sessions.lock();
+      let s = sessions
+        .get_mut(session_id)
+        .ok_or_else(|| CoreError::UnknownSession(session_id.to_string()))?;
+      s.last_access_ms = now_ms();
+      if s.shards.is_some() {
+        return Err(multi_file_unsupported("json_base64_preview"));
+      }
+      (PathBuf::from(&s.info.path), s.format.clone(), s.info.json_lenient)
+    };
+    if format != FileFormat::Json && format != FileFormat::Jsonl {
+      return Err(CoreError::UnsupportedFormat(format));
+    }
+    let max_bytes = max_bytes.unwrap_or(1024).clamp(1, 1024 * 1024);
+    crate::formats::json_base64_preview(&path_buf, lenient, node_offset, max_bytes)
   }
 
-  /// IPC API (v2): json_list_children_at_offset(session_id, meta, node_offset, cursor_offset, limit)
+  /// IPC API (v2): save_json_base64(session_id, node_offset, output_path) -> ExportResult
   ///
-  /// This is a faster variant for huge records: the frontend navigates by absolute byte offsets
-  /// returned by the backend, so expanding deep nodes does not rescan the path from record start.
-  pub fn json_list_children_at_offset(
+  /// Decodes the whole base64 string node at `node_offset` (see `json_base64_preview`) into
+  /// `output_path`, streaming, so large embedded files can be saved. `records_written` is 1.
+  pub fn save_json_base64(
     &self,
     session_id: &str,
-    meta: RecordMeta,
     node_offset: u64,
-    cursor_offset: Option<u64>,
-    cursor_index: Option<u64>,
-    limit: usize,
-  ) -> Result<JsonChildrenPageOffset, CoreError> {
-    let (path_buf, format) = {
-      let sessions = self.sessions.lock();
+    output_path: impl AsRef<Path>,
+  ) -> Result<ExportResult, CoreError> {
+    let (path_buf, format, lenient) = {
+      let mut sessions = self.sessions.lock();
       let s = sessions
-        .get(session_id)
+        .get_mut(session_id)
         .ok_or_else(|| CoreError::UnknownSession(session_id.to_string()))?;
-      (PathBuf::from(&s.info.path), s.format.clone())
+      s.last_access_ms = now_ms();
+      if s.shards.is_some() {
+        return Err(multi_file_unsupported("save_json_base64"));
+      }
+      (PathBuf::from(&s.info.path), s.format.clone(), s.info.json_lenient)
     };
-    // Allow JSONL records to reuse the same "parse one JSON value at offset" streaming tree.
     if format != FileFormat::Json && format != FileFormat::Jsonl {
       return Err(CoreError::UnsupportedFormat(format));
     }
-    // Basic safety: node_offset must be >= record_offset (we only support offsets within the record).
-    if node_offset < meta.byte_offset {
-      return Err(CoreError::InvalidArg(format!(
-        "node_offset {} is before record_offset {}",
-        node_offset, meta.byte_offset
-      )));
+    let output_path = output_path.as_ref();
+    let partial = partial_output_path(output_path);
+    let mut writer = std::io::BufWriter::new(std::fs::File::create(&partial)?);
+    let written = crate::formats::decode_json_base64(&path_buf, lenient, node_offset, &mut writer)
+      .and_then(|_| writer.flush().map_err(CoreError::from));
+    if let Err(e) = written {
+      let _ = std::fs::remove_file(&partial);
+      return Err(e);
+    }
+    std::fs::rename(&partial, output_path)?;
+    Ok(ExportResult {
+      output_path: output_path.to_string_lossy().to_string(),
+      records_written: 1,
+    })
+  }
+
+  /// IPC API (v2): build_json_node_index(session_id, meta) -> TaskInfo?
+  ///
+  /// Starts indexing the large containers of the record at `meta`, as tree calls do on their own
+  /// for records of at least `CoreOptions::json_node_index_min_bytes`. The index is stored per
+  /// file version, so listings and summaries of deep nodes start at the nearest indexed node
+  /// instead of the record start, across restarts too. `None` if the record is already indexed
+  /// or being indexed.
+  pub fn build_json_node_index(&self, session_id: &str, meta: RecordMeta) -> Result<Option<TaskInfo>, CoreError> {
+    let (path_buf, format, lenient) = {
+      let mut sessions = self.sessions.lock();
+      let s = sessions
+        .get_mut(session_id)
+        .ok_or_else(|| CoreError::UnknownSession(session_id.to_string()))?;
+      s.last_access_ms = now_ms();
+      if s.shards.is_some() {
+        return Err(multi_file_unsupported("build_json_node_index"));
+      }
+      (PathBuf::from(&s.info.path), s.format.clone(), s.info.json_lenient)
+    };
+    if format != FileFormat::Json && format != FileFormat::Jsonl {
+      return Err(CoreError::UnsupportedFormat(format));
+    }
+    Ok(self.json_node_index(&path_buf, lenient, &meta, true).1)
+  }
+
+  /// IPC API (v2): build_text_index(session_id) -> TaskInfo?
+  ///
+  /// Starts building the full-text index of a JSONL / CSV session's lines (in its current
+  /// encoding), which `indexed` searches need. The index is stored per file version, so it
+  /// serves later sessions and restarts until the file changes. `None` if the file is already
+  /// indexed or being indexed.
+  pub fn build_text_index(&self, session_id: &str) -> Result<Option<TaskInfo>, CoreError> {
+    let (path_buf, format, encoding) = {
+      let mut sessions = self.sessions.lock();
+      let s = sessions
+        .get_mut(session_id)
+        .ok_or_else(|| CoreError::UnknownSession(session_id.to_string()))?;
+      s.last_access_ms = now_ms();
+      if s.shards.is_some() {
+        return Err(multi_file_unsupported("build_text_index"));
+      }
+      (PathBuf::from(&s.info.path), s.format.clone(), s.info.encoding)
+    };
+    if format != FileFormat::Jsonl && format != FileFormat::Csv {
+      return Err(CoreError::UnsupportedFormat(format));
+    }
+    Ok(self.text_index(&path_buf, encoding, true).1)
+  }
+
+  /// IPC API: get_schema(session_id) -> SessionSchema
+  ///
+  /// Column names and types: parquet from the file metadata, CSV (typed cells) and JSONL / JSON
+  /// (nested key paths) inferred from the first records. Multi-file sessions use the first part.
+  pub fn get_schema(&self, session_id: &str) -> Result<SessionSchema, CoreError> {
+    let (path, format, encoding, csv) = {
+      let mut sessions = self.sessions.lock();
+      let s = sessions
+        .get_mut(session_id)
+        .ok_or_else(|| CoreError::UnknownSession(session_id.to_string()))?;
+      s.last_access_ms = now_ms();
+      (PathBuf::from(&s.info.path), s.format.clone(), s.info.encoding, s.info.csv.clone())
+    };
+    schema_impl::read_schema(&path, format, encoding, &csv)
+  }
+
+  /// IPC API: csv_schema(path) -> CsvSchema
+  ///
+  /// Quick summary of a CSV file without opening a session (for the folder tree): header,
+  /// sniffed delimiter, cell types inferred from the first records and a row-count estimate
+  /// from their average size. Reads only the first records (1000 at most).
+  pub fn csv_schema(&self, path: impl AsRef<Path>) -> Result<CsvSchema, CoreError> {
+    let path = path.as_ref();
+    let format = formats::detect_format(path);
+    if format != FileFormat::Csv {
+      return Err(CoreError::UnsupportedFormat(format));
+    }
+    schema_impl::read_csv_schema(path)
+  }
+
+  /// IPC API: parquet_metadata(session_id) -> ParquetMetadata
+  ///
+  /// Row count, row groups, column types / codecs and key-value metadata from the parquet footer;
+  /// no rows are loaded. Multi-file sessions use the first part.
+  pub fn parquet_metadata(&self, session_id: &str) -> Result<ParquetMetadata, CoreError> {
+    let (path, format, parquet) = {
+      let mut sessions = self.sessions.lock();
+      let s = sessions
+        .get_mut(session_id)
+        .ok_or_else(|| CoreError::UnknownSession(session_id.to_string()))?;
+      s.last_access_ms = now_ms();
+      (PathBuf::from(&s.info.path), s.format.clone(), s.parquet.clone())
+    };
+    if format != FileFormat::Parquet {
+      return Err(CoreError::UnsupportedFormat(format));
+    }
+    match parquet {
+      Some(conn) => conn.lock().metadata(),
+      None => formats::read_parquet_metadata(&path),
+    }
+  }
+
+  /// IPC API: parquet_footer_stats(session_id) -> ParquetFooterStats
+  ///
+  /// Min / max / null count per column from the footer's row group statistics, without reading
+  /// any rows; `get_stats` / `quick_stats` profile the data itself. Multi-file sessions use the
+  /// first part.
+  pub fn parquet_footer_stats(&self, session_id: &str) -> Result<ParquetFooterStats, CoreError> {
+    let (path, format, parquet) = {
+      let mut sessions = self.sessions.lock();
+      let s = sessions
+        .get_mut(session_id)
+        .ok_or_else(|| CoreError::UnknownSession(session_id.to_string()))?;
+      s.last_access_ms = now_ms();
+      (PathBuf::from(&s.info.path), s.format.clone(), s.parquet.clone())
+    };
+    if format != FileFormat::Parquet {
+      return Err(CoreError::UnsupportedFormat(format));
+    }
+    match parquet {
+      Some(conn) => stats_impl::parquet_footer_stats(&conn.lock()),
+      None => stats_impl::parquet_footer_stats(&ParquetConn::open(&path)?),
+    }
+  }
+
+  /// IPC API: get_stats(session_id) -> StatsResult
+  ///
+  /// Profiles the whole file in one streaming pass: schema (columns / top-level keys) plus
+  /// per-column kind counts, distinct counts and numeric histograms.
+  pub fn get_stats(&self, session_id: &str) -> Result<StatsResult, CoreError> {
+    let (path, format, view, encoding, csv) = {
+      let mut sessions = self.sessions.lock();
+      let s = sessions
+        .get_mut(session_id)
+        .ok_or_else(|| CoreError::UnknownSession(session_id.to_string()))?;
+      s.last_access_ms = now_ms();
+      if s.shards.is_some() {
+        return Err(multi_file_unsupported("get_stats"));
+      }
+      (
+        PathBuf::from(&s.info.path),
+        s.format.clone(),
+        s.view.clone(),
+        s.info.encoding,
+        s.info.csv.clone(),
+      )
+    };
+    if let Some(view) = view {
+      let render = RecordRender {
+        preview_max_chars: 0,
+        raw_max_chars: formats::FULL_RAW_MAX_CHARS,
+        columns: None,
+        filters: None,
+        encoding,
+        csv: Some(&csv),
+        json_lenient: false,
+        parquet: None,
+      };
+      let mut raws = Vec::with_capacity(view.len());
+      for meta in view.iter() {
+        // The CSV header row is not a data record.
+        if format == FileFormat::Csv && csv.has_header && meta.line_no == 0 {
+          continue;
+        }
+        let cursor = view_cursor(&format, meta);
+        let page = self.read_page_with_limits(&path, format.clone(), cursor, 1, render)?;
+        raws.extend(page.records.into_iter().filter_map(|r| r.raw));
+      }
+      return Ok(stats_impl::compute_stats_for_raws(format, raws));
+    }
+    stats_impl::compute_stats(&path, format, encoding, &csv)
+  }
+
+  /// IPC API: start_stats_task(session_id) -> TaskInfo
+  ///
+  /// Runs `get_stats` as a cancellable background task (poll with `get_task`, stop with
+  /// `cancel_task`, then read the profile with `stats_task_result`).
+  pub fn start_stats_task(&self, session_id: &str) -> Result<TaskInfo, CoreError> {
+    self.start_stats_task_with_timeout(session_id, None)
+  }
+
+  /// `start_stats_task` with a time limit: after `timeout_ms` of running, the task stops with
+  /// `Task.timed_out` set and its result is the partial profile so far.
+  pub fn start_stats_task_with_timeout(
+    &self,
+    session_id: &str,
+    timeout_ms: Option<u64>,
+  ) -> Result<TaskInfo, CoreError> {
+    let (path, format, encoding, csv, paths) = {
+      let mut sessions = self.sessions.lock();
+      let s = sessions
+        .get_mut(session_id)
+        .ok_or_else(|| CoreError::UnknownSession(session_id.to_string()))?;
+      s.last_access_ms = now_ms();
+      if s.shards.is_some() {
+        return Err(multi_file_unsupported("start_stats_task"));
+      }
+      if s.view.is_some() {
+        return Err(filtered_unsupported("start_stats_task"));
+      }
+      (
+        PathBuf::from(&s.info.path),
+        s.format.clone(),
+        s.info.encoding,
+        s.info.csv.clone(),
+        history_paths(&s.info),
+      )
+    };
+    let task = self.tasks.start_stats(path, format, encoding, csv, timeout_ms)?;
+    let params = serde_json::json!({ "timeout_ms": timeout_ms });
+    self.tasks.record_in_history(&task.id, TaskOrigin { paths, params });
+    Ok(TaskInfo {
+      id: task.id,
+      kind: TaskKind::Stats,
+      cancellable: true,
+    })
+  }
+
+  /// IPC API: stats_task_result(task_id) -> StatsResult
+  ///
+  /// Available once the task finished; a cancelled task yields a partial profile
+  /// (`complete: false`).
+  pub fn stats_task_result(&self, task_id: &str) -> Result<StatsResult, CoreError> {
+    self.tasks.stats_task_result(task_id).map_err(CoreError::Task)
+  }
+
+  /// IPC API: quick_stats(session_id, strategy, sample_size) -> StatsResult
+  ///
+  /// Same profile as `get_stats`, computed over a head / tail / random sample of
+  /// `sample_size` records. `sample` and per-column `confidence` describe how far the
+  /// numbers can be trusted. CSV tail / random samples are read through the line index: below
+  /// `line_index_min_bytes` it is completed first, larger files sample the head until the
+  /// index build started on open finished (`sample.strategy` tells which was used).
+  pub fn quick_stats(
+    &self,
+    session_id: &str,
+    strategy: StatsSampleStrategy,
+    sample_size: u64,
+  ) -> Result<StatsResult, CoreError> {
+    let (path, format, encoding, csv, line_index) = {
+      let mut sessions = self.sessions.lock();
+      let s = sessions
+        .get_mut(session_id)
+        .ok_or_else(|| CoreError::UnknownSession(session_id.to_string()))?;
+      s.last_access_ms = now_ms();
+      if s.shards.is_some() {
+        return Err(multi_file_unsupported("quick_stats"));
+      }
+      if s.view.is_some() {
+        return Err(filtered_unsupported("quick_stats"));
+      }
+      (
+        PathBuf::from(&s.info.path),
+        s.format.clone(),
+        s.info.encoding,
+        s.info.csv.clone(),
+        s.line_index.clone(),
+      )
+    };
+    let csv_index = match (&format, strategy) {
+      (FileFormat::Csv, StatsSampleStrategy::Tail | StatsSampleStrategy::Random) => {
+        let mut index = line_index.lock();
+        if index.total_records().is_none() && std::fs::metadata(&path)?.len() < self.options.line_index_min_bytes {
+          index.extend_to(&path, FileFormat::Csv, u64::MAX, || false, |_| {})?;
+        }
+        Some(index.clone())
+      }
+      _ => None,
+    };
+    stats_impl::compute_quick_stats(&path, format, encoding, &csv, csv_index, strategy, sample_size)
+  }
+
+  /// IPC API: compare_stats(left_session_id, right_session_id) -> StatsDiff
+  ///
+  /// Profiles both sessions and diffs them (added/removed columns, count deltas, distribution
+  /// shift), e.g. to validate a regenerated dataset against the previous version.
+  pub fn compare_stats(&self, left_session_id: &str, right_session_id: &str) -> Result<StatsDiff, CoreError> {
+    let left = self.get_stats(left_session_id)?;
+    let right = self.get_stats(right_session_id)?;
+    Ok(stats_impl::diff_stats(&left, &right))
+  }
+
+  /// IPC API: start_diff_task(left_session_id, right_session_id, align) -> TaskInfo
+  ///
+  /// Compares two sessions' files record by record (by position or by a key column / JSON key)
+  /// in the background; page the added / removed / changed records with `diff_task_page`.
+  pub fn start_diff_task(
+    &self,
+    left_session_id: &str,
+    right_session_id: &str,
+    align: DiffAlign,
+  ) -> Result<TaskInfo, CoreError> {
+    let source = |session_id: &str| {
+      let mut sessions = self.sessions.lock();
+      let s = sessions
+        .get_mut(session_id)
+        .ok_or_else(|| CoreError::UnknownSession(session_id.to_string()))?;
+      s.last_access_ms = now_ms();
+      if s.shards.is_some() {
+        return Err(multi_file_unsupported("start_diff_task"));
+      }
+      if s.view.is_some() {
+        return Err(filtered_unsupported("start_diff_task"));
+      }
+      Ok(((PathBuf::from(&s.info.path), s.format.clone()), history_paths(&s.info)))
+    };
+    let (left, mut paths) = source(left_session_id)?;
+    let (right, right_paths) = source(right_session_id)?;
+    paths.extend(right_paths);
+    let params = serde_json::json!({ "align": &align });
+    let task = self.tasks.start_diff(left, right, align, self.options.preview_max_chars)?;
+    self.tasks.record_in_history(&task.id, TaskOrigin { paths, params });
+    Ok(TaskInfo {
+      id: task.id,
+      kind: TaskKind::Diff,
+      cancellable: true,
+    })
+  }
+
+  /// IPC API: diff_task_page(task_id, cursor?, page_size) -> DiffPage
+  ///
+  /// Available once the task finished; `summary` counts every entry, not just the page.
+  pub fn diff_task_page(&self, task_id: &str, cursor: Option<&str>, page_size: usize) -> Result<DiffPage, CoreError> {
+    self.tasks.diff_task_page(task_id, cursor, page_size).map_err(CoreError::Task)
+  }
+
+  /// IPC API: export_stats_report(session_id, format, output_path) -> ExportResult
+  ///
+  /// Computes stats and writes them as a JSON or Markdown report (e.g. for dataset release
+  /// notes). `records_written` is the number of column profiles in the report.
+  pub fn export_stats_report(
+    &self,
+    session_id: &str,
+    format: StatsReportFormat,
+    output_path: impl AsRef<Path>,
+  ) -> Result<ExportResult, CoreError> {
+    let source_path = {
+      let mut sessions = self.sessions.lock();
+      let s = sessions
+        .get_mut(session_id)
+        .ok_or_else(|| CoreError::UnknownSession(session_id.to_string()))?;
+      s.last_access_ms = now_ms();
+      if s.shards.is_some() {
+        return Err(multi_file_unsupported("export_stats_report"));
+      }
+      s.info.path.clone()
+    };
+    let stats = self.get_stats(session_id)?;
+    let report = stats_impl::render_report(&source_path, &stats, format, now_ms())?;
+
+    let output_path = output_path.as_ref();
+    if let Some(parent) = output_path.parent() {
+      std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(output_path, report)?;
+    Ok(ExportResult {
+      output_path: output_path.to_string_lossy().to_string(),
+      records_written: stats.columns.len() as u64,
+    })
+  }
+
+  /// IPC API: list_recent(limit) -> RecentFile[]
+  ///
+  /// Files opened before, pinned first, then most recently opened first.
+  pub fn list_recent(&self, limit: usize) -> Result<Vec<RecentFile>, CoreError> {
+    self.storage.list_recent(limit).map_err(CoreError::Storage)
+  }
+
+  /// IPC API: pin_recent(path, pinned) -> ()
+  ///
+  /// Keeps the order by last-opened time; a path not on the list yet is added.
+  pub fn pin_recent(&self, path: &str, pinned: bool) -> Result<(), CoreError> {
+    if !self.storage.set_recent_pinned(path, pinned).map_err(CoreError::Storage)? {
+      self.storage.touch_recent(path, Some(pinned)).map_err(CoreError::Storage)?;
+    }
+    Ok(())
+  }
+
+  /// IPC API: remove_recent(path) -> ()
+  pub fn remove_recent(&self, path: &str) -> Result<(), CoreError> {
+    self.storage.remove_recent(path).map_err(CoreError::Storage)
+  }
+
+  /// IPC API: clear_recent() -> ()
+  pub fn clear_recent(&self) -> Result<(), CoreError> {
+    self.storage.clear_recent().map_err(CoreError::Storage)
+  }
+
+  /// IPC API: prune_recent() -> number
+  ///
+  /// Refreshes `exists` on every entry and removes unpinned entries whose file is gone; returns
+  /// how many were removed.
+  pub fn prune_recent(&self) -> Result<usize, CoreError> {
+    self.storage.prune_recent().map_err(CoreError::Storage)
+  }
+
+  /// Records a folder scanned for the file picker on the recent folders list.
+  pub fn touch_recent_folder(&self, path: &str) -> Result<(), CoreError> {
+    self.storage.touch_recent_folder(path, None).map_err(CoreError::Storage)
+  }
+
+  /// IPC API: list_recent_folders(limit) -> RecentFile[]
+  ///
+  /// Folders scanned before (`scan_folder_tree`), pinned first, then most recently scanned first.
+  pub fn list_recent_folders(&self, limit: usize) -> Result<Vec<RecentFile>, CoreError> {
+    self.storage.list_recent_folders(limit).map_err(CoreError::Storage)
+  }
+
+  /// IPC API: pin_recent_folder(path, pinned) -> ()
+  ///
+  /// Like `pin_recent`: a folder not on the list yet is added.
+  pub fn pin_recent_folder(&self, path: &str, pinned: bool) -> Result<(), CoreError> {
+    if !self.storage.set_recent_folder_pinned(path, pinned).map_err(CoreError::Storage)? {
+      self.storage.touch_recent_folder(path, Some(pinned)).map_err(CoreError::Storage)?;
+    }
+    Ok(())
+  }
+
+  /// IPC API: remove_recent_folder(path) -> ()
+  pub fn remove_recent_folder(&self, path: &str) -> Result<(), CoreError> {
+    self.storage.remove_recent_folder(path).map_err(CoreError::Storage)
+  }
+
+  /// IPC API: storage_report() -> StorageReport
+  ///
+  /// Size of the storage database and row counts of the history / event / cache tables.
+  pub fn storage_report(&self) -> Result<StorageReport, CoreError> {
+    self.storage.report().map_err(CoreError::Storage)
+  }
+
+  /// IPC API: maintain_storage(limits?) -> StorageMaintenance
+  ///
+  /// Trims the task history, event log and scan cache to `limits` (defaults when omitted) and
+  /// vacuums the database.
+  pub fn maintain_storage(&self, limits: Option<StorageLimits>) -> Result<StorageMaintenance, CoreError> {
+    self
+      .storage
+      .maintain(&limits.unwrap_or_default())
+      .map_err(CoreError::Storage)
+  }
+
+  /// IPC API: backup_storage(output_path) -> StorageBackup
+  ///
+  /// Saves settings, recent files and folders, record labels, view preferences, collections and
+  /// export presets to a JSON file, to carry the setup over to another machine.
+  pub fn backup_storage(&self, output_path: impl AsRef<Path>) -> Result<StorageBackup, CoreError> {
+    self.storage.backup_to(output_path.as_ref()).map_err(CoreError::Storage)
+  }
+
+  /// IPC API: restore_storage(input_path) -> StorageBackup
+  ///
+  /// Merges a `backup_storage` file into the storage; entries with the same key are replaced.
+  /// Labels only show up again on files of the same size and modification time.
+  pub fn restore_storage(&self, input_path: impl AsRef<Path>) -> Result<StorageBackup, CoreError> {
+    self.storage.restore_from(input_path.as_ref()).map_err(CoreError::Storage)
+  }
+
+  pub fn storage(&self) -> &Storage {
+    &self.storage
+  }
+
+  /// Last step of every page read (started at `started`): derived values, the cursor epoch
+  /// stamped on `next_cursor`, the page cached for current-page search and counted in metrics.
+  fn finish_page(&self, session_id: &str, page: &mut RecordPage, started: Instant) -> Result<(), CoreError> {
+    self.derive_records(session_id, &mut page.records)?;
+    let mut read_position = None;
+    if let Some(s) = self.sessions.lock().get_mut(session_id) {
+      s.metrics.record_page(page, started.elapsed());
+      s.csv_warnings.note(&page.records);
+      if s.cursor_epoch > 0 {
+        page.next_cursor = page.next_cursor.take().map(|c| format!("{}.{c}", s.cursor_epoch));
+      }
+      s.last_page = Some(page.clone());
+      // Plain single-file sessions only: record ids of views and multi-file sessions don't
+      // point into one file. Estimated ids would come back as exact ones.
+      if s.shards.is_none() && s.view.is_none() && !s.spooled && !page.ids_estimated {
+        if let (Some(identity), Some(first)) = (s.identity, page.records.first()) {
+          let byte_offset = match s.format {
+            FileFormat::Jsonl | FileFormat::Csv => first.meta.as_ref().map(|m| m.byte_offset),
+            _ => None,
+          };
+          read_position = Some((s.info.path.clone(), identity, first.id, byte_offset));
+        }
+      }
+    }
+    if let Some((path, (size, mtime), record_index, byte_offset)) = read_position {
+      let _ = self
+        .storage
+        .save_read_position(&path, size, mtime, record_index, byte_offset);
+    }
+    Ok(())
+  }
+
+  /// IPC API: page_at_read_position(session_id, page_size) -> RecordPage
+  ///
+  /// The page the user left off at in this file (`session.read_position`, as of `open_file`),
+  /// to resume reading instead of starting at record 0.
+  pub fn page_at_read_position(&self, session_id: &str, page_size: usize) -> Result<RecordPage, CoreError> {
+    let (path, format, encoding, position, csv, lenient, columns) = {
+      let sessions = self.sessions.lock();
+      let s = sessions
+        .get(session_id)
+        .ok_or_else(|| CoreError::UnknownSession(session_id.to_string()))?;
+      (
+        PathBuf::from(&s.info.path),
+        s.format.clone(),
+        s.info.encoding,
+        s.info.read_position.clone(),
+        s.info.csv.clone(),
+        s.info.json_lenient,
+        s.info.columns.clone(),
+      )
+    };
+    let position =
+      position.ok_or_else(|| CoreError::InvalidArg("no read position stored for this file".into()))?;
+    let Some(offset) = position.byte_offset else {
+      return self.page_at(session_id, position.record_index, page_size);
+    };
+    let _interactive = self.tasks.interactive();
+    let started = Instant::now();
+    let cursor = Cursor {
+      offset,
+      line: position.record_index,
+    };
+    let mut page = self.read_page_from(&path, format, cursor, page_size, encoding, &csv, lenient, columns.as_deref())?;
+    self.finish_page(session_id, &mut page, started)?;
+    Ok(page)
+  }
+
+  /// Fills `derived` on page records from the session's derived columns (no-op without any).
+  /// Records whose `raw` was truncated (CSV: has cut cells) are re-read in full first.
+  fn derive_records(&self, session_id: &str, records: &mut [crate::models::Record]) -> Result<(), CoreError> {
+    let (derived, path, format, encoding, csv) = match self.sessions.lock().get(session_id) {
+      Some(s) => (
+        s.derived.clone(),
+        PathBuf::from(&s.info.path),
+        s.format.clone(),
+        s.info.encoding,
+        s.info.csv.clone(),
+      ),
+      None => return Ok(()),
+    };
+    if derived.is_empty() {
+      return Ok(());
+    }
+    let header_row = format == FileFormat::Csv && csv.has_header;
+    let mut headers = None;
+    for r in records {
+      // The CSV header row is not a data record.
+      if header_row && r.meta.as_ref().is_some_and(|m| m.line_no == 0) {
+        continue;
+      }
+      let raw = r.raw.as_deref().unwrap_or("");
+      let values = match (serde_json::from_str::<serde_json::Value>(raw), &r.meta) {
+        (Ok(value), Some(meta)) if format == FileFormat::Csv && value.get("__truncated__").is_some() => {
+          let line = self.get_record_raw(session_id, meta.clone())?;
+          let headers = match &mut headers {
+            Some(headers) => headers,
+            None => headers.insert(formats::read_csv_header(&path, encoding, &csv)?),
+          };
+          let full = formats::csv_line_to_object(headers, &line, &csv);
+          derived.evaluate_value(&full.to_string(), Some(&full))
+        }
+        (Ok(value), _) => derived.evaluate_value(raw, Some(&value)),
+        (Err(_), Some(meta)) if raw.ends_with('…') => {
+          derived.evaluate(&self.get_record_raw(session_id, meta.clone())?)
+        }
+        (Err(_), _) => derived.evaluate(raw),
+      };
+      r.derived = Some(values);
+    }
+    Ok(())
+  }
+
+  #[allow(clippy::too_many_arguments)]
+  fn read_page(
+    &self,
+    path: &Path,
+    format: FileFormat,
+    cursor: Option<&str>,
+    page_size: usize,
+    encoding: TextEncoding,
+    csv: &CsvDialect,
+    json_lenient: bool,
+    columns: Option<&[String]>,
+  ) -> Result<RecordPage, CoreError> {
+    let c = decode_cursor(cursor)?;
+    self.read_page_from(path, format, c, page_size, encoding, csv, json_lenient, columns)
+  }
+
+  /// One page of a multi-file session; continues into the next part when one ends.
+  fn read_shard_page(
+    &self,
+    shards: &Mutex<ShardSet>,
+    cursor: Option<&str>,
+    page_size: usize,
+  ) -> Result<RecordPage, CoreError> {
+    let page_size = if page_size == 0 {
+      self.options.default_page_size
+    } else {
+      page_size
+    };
+    let mut c = decode_shard_cursor(cursor)?;
+    let format = shards.lock().format();
+    let mut records = Vec::new();
+    loop {
+      let Some(path) = shards.lock().part(c.part as usize).map(Path::to_path_buf) else {
+        return Ok(RecordPage {
+          records,
+          next_cursor: None,
+          reached_eof: true,
+          total_records: None,
+          ids_estimated: false,
+        });
+      };
+      let first_local = first_local_id(&format, c.part as usize);
+      let page = self.read_page(
+        &path,
+        format.clone(),
+        c.inner.as_deref(),
+        page_size - records.len(),
+        TextEncoding::Utf8,
+        &CsvDialect::default(),
+        false,
+        None,
+      )?;
+      for mut r in page.records {
+        if r.id < first_local {
+          continue;
+        }
+        // Parquet rows are located by their local row index (`line_no`).
+        let meta = r.meta.get_or_insert(RecordMeta {
+          line_no: r.id,
+          byte_offset: 0,
+          byte_len: 0,
+          part: None,
+        });
+        meta.part = Some(c.part);
+        r.id = c.base + r.id - first_local;
+        c.next_id = r.id + 1;
+        records.push(r);
+      }
+      if !page.reached_eof && page.next_cursor.is_some() {
+        c.inner = page.next_cursor;
+      } else {
+        // Part exhausted: now its span is known.
+        shards.lock().set_span(c.part as usize, c.next_id - c.base);
+        c = ShardCursor {
+          part: c.part + 1,
+          base: c.next_id,
+          next_id: c.next_id,
+          inner: None,
+        };
+        if shards.lock().part(c.part as usize).is_none() {
+          return Ok(RecordPage {
+            records,
+            next_cursor: None,
+            reached_eof: true,
+            total_records: None,
+            ids_estimated: false,
+          });
+        }
+      }
+      if records.len() >= page_size {
+        return Ok(RecordPage {
+          records,
+          next_cursor: Some(encode_shard_cursor(&c)),
+          reached_eof: false,
+          total_records: None,
+          ids_estimated: false,
+        });
+      }
     }
-    let limit = if limit == 0 { 50 } else { limit };
-    crate::formats::list_json_children_page_at_offset(
-      &path_buf,
-      node_offset,
-      cursor_offset,
-      cursor_index,
-      limit,
-      self.options.preview_max_chars,
-    )
   }
 
-  /// IPC API (v2): json_node_summary_at_offset(session_id, meta, node_offset)
-  pub fn json_node_summary_at_offset(
+  /// One page of a filtered session: `cursor.line` is the position in `hits`.
+  fn read_view_page(
     &self,
-    session_id: &str,
-    meta: RecordMeta,
-    node_offset: u64,
-    max_items: Option<u64>,
-    max_scan_bytes: Option<u64>,
-  ) -> Result<JsonNodeSummaryOffset, CoreError> {
-    let (path_buf, format) = {
-      let sessions = self.sessions.lock();
-      let s = sessions
-        .get(session_id)
-        .ok_or_else(|| CoreError::UnknownSession(session_id.to_string()))?;
-      (PathBuf::from(&s.info.path), s.format.clone())
+    path: &Path,
+    format: &FileFormat,
+    hits: &[RecordMeta],
+    cursor: Option<&str>,
+    page_size: usize,
+    render: RecordRender,
+  ) -> Result<RecordPage, CoreError> {
+    let page_size = if page_size == 0 {
+      self.options.default_page_size
+    } else {
+      page_size
     };
-    // Allow JSONL records to reuse the same "parse one JSON value at offset" streaming tree.
-    if format != FileFormat::Json && format != FileFormat::Jsonl {
-      return Err(CoreError::UnsupportedFormat(format));
-    }
-    if node_offset < meta.byte_offset {
-      return Err(CoreError::InvalidArg(format!(
-        "node_offset {} is before record_offset {}",
-        node_offset, meta.byte_offset
-      )));
+    let start = (decode_cursor(cursor)?.line as usize).min(hits.len());
+    let end = (start + page_size).min(hits.len());
+    let mut records = Vec::with_capacity(end - start);
+    for meta in &hits[start..end] {
+      let page = self.read_page_with_limits(path, format.clone(), view_cursor(format, meta), 1, render)?;
+      let record = page.records.into_iter().next().ok_or_else(|| {
+        CoreError::InvalidArg(format!("record {} is no longer in the file", meta.line_no))
+      })?;
+      records.push(record);
     }
-    let max_items = max_items.unwrap_or(200_000);
-    let max_scan_bytes = max_scan_bytes.unwrap_or(64 * 1024 * 1024);
-    crate::formats::json_node_summary_at_offset(&path_buf, node_offset, max_items, max_scan_bytes)
+    let reached_eof = end >= hits.len();
+    Ok(RecordPage {
+      records,
+      next_cursor: (!reached_eof).then(|| encode_cursor(Cursor { offset: 0, line: end as u64 })),
+      reached_eof,
+      total_records: None,
+      ids_estimated: false,
+    })
   }
 
-  /// Reserved for M3.
-  pub fn get_stats(&self, _session_id: &str) -> Result<StatsResult, CoreError> {
-    Ok(StatsResult {
-      message: "not implemented (M3)".into(),
-    })
+  #[allow(clippy::too_many_arguments)]
+  fn read_page_from(
+    &self,
+    path: &Path,
+    format: FileFormat,
+    c: Cursor,
+    page_size: usize,
+    encoding: TextEncoding,
+    csv: &CsvDialect,
+    json_lenient: bool,
+    columns: Option<&[String]>,
+  ) -> Result<RecordPage, CoreError> {
+    let render = RecordRender {
+      columns,
+      csv: Some(csv),
+      json_lenient,
+      ..self.render(encoding)
+    };
+    self.read_page_with_limits(path, format, c, page_size, render)
   }
 
-  pub fn storage(&self) -> &Storage {
-    &self.storage
+  /// Default rendering: the configured preview / raw limits, all columns.
+  fn render(&self, encoding: TextEncoding) -> RecordRender<'static> {
+    RecordRender {
+      preview_max_chars: self.options.preview_max_chars,
+      raw_max_chars: self.options.raw_max_chars,
+      columns: None,
+      filters: None,
+      encoding,
+      csv: None,
+      json_lenient: false,
+      parquet: None,
+    }
   }
 
-  fn read_page(
+  fn read_page_with_limits(
     &self,
     path: &Path,
     format: FileFormat,
-    cursor: Option<&str>,
+    c: Cursor,
     page_size: usize,
+    render: RecordRender,
   ) -> Result<RecordPage, CoreError> {
     let page_size = if page_size == 0 {
       self.options.default_page_size
     } else {
       page_size
     };
-    let c = decode_cursor(cursor)?;
+    let RecordRender {
+      preview_max_chars,
+      raw_max_chars,
+      columns,
+      filters,
+      encoding,
+      csv,
+      json_lenient,
+      parquet,
+    } = render;
+    let mut total_records = None;
     let (page, next) = match format {
       FileFormat::Jsonl => formats::read_lines_page(
         path,
         c,
         page_size,
-        self.options.preview_max_chars,
-        self.options.raw_max_chars,
+        preview_max_chars,
+        raw_max_chars,
+        encoding,
       )?,
       FileFormat::Csv => formats::read_csv_page(
         path,
         c,
         page_size,
-        self.options.preview_max_chars,
-        self.options.raw_max_chars,
+        preview_max_chars,
+        raw_max_chars,
+        columns,
+        encoding,
+        csv.unwrap_or(&CsvDialect::default()),
       )?,
       FileFormat::Json => formats::read_json_page(
         path,
+        json_lenient,
         c,
         page_size,
-        self.options.preview_max_chars,
-        self.options.raw_max_chars,
-      )?,
-      FileFormat::Parquet => formats::read_parquet_page(
-        path,
-        c,
-        page_size,
-        self.options.preview_max_chars,
-        self.options.raw_max_chars,
+        preview_max_chars,
+        raw_max_chars,
       )?,
+      FileFormat::Parquet => {
+        let read = |conn: &ParquetConn| {
+          let page = conn.page(c, page_size, preview_max_chars, raw_max_chars, columns, filters)?;
+          Ok::<_, CoreError>((page, conn.row_count()?))
+        };
+        let (page, total) = match parquet {
+          Some(conn) => read(&conn.lock())?,
+          None => read(&ParquetConn::open(path)?.with_utc_offset(self.options.parquet_utc_offset_minutes))?,
+        };
+        // Filtered sessions don't know how many rows match without counting them.
+        total_records = Some(total).filter(|_| filters.is_none_or(|f| f.is_empty()));
+        page
+      }
       _ => return Err(CoreError::UnsupportedFormat(format)),
     };
     let next_cursor = next.map(encode_cursor);
@@ -465,6 +4453,8 @@ impl CoreEngine {
       records: page.records,
       next_cursor,
       reached_eof: page.reached_eof,
+      total_records,
+      ids_estimated: false,
     })
   }
 
@@ -472,13 +4462,45 @@ impl CoreEngine {
   ///
   /// This is primarily used when `Record.raw` is truncated (for UI performance) but the user
   /// wants to view/parse the full underlying record.
+  ///
+  /// Multi-file sessions: `meta.part` selects the file; without it `meta.line_no` is taken as
+  /// the session-wide record id.
   pub fn get_record_raw(&self, session_id: &str, meta: RecordMeta) -> Result<String, CoreError> {
-    let (path, format) = {
-      let sessions = self.sessions.lock();
+    let _interactive = self.tasks.interactive();
+    let (path, format, shards, encoding, parquet, json_lenient) = {
+      let mut sessions = self.sessions.lock();
       let s = sessions
-        .get(session_id)
+        .get_mut(session_id)
         .ok_or_else(|| CoreError::UnknownSession(session_id.to_string()))?;
-      (PathBuf::from(&s.info.path), s.format.clone())
+      s.last_access_ms = now_ms();
+      (
+        PathBuf::from(&s.info.path),
+        s.format.clone(),
+        s.shards.clone(),
+        s.info.encoding,
+        s.parquet.clone(),
+        s.info.json_lenient,
+      )
+    };
+    let (path, meta) = match shards {
+      Some(shards) => {
+        let mut shards = shards.lock();
+        let (part, meta) = match meta.part {
+          Some(part) => (part as usize, meta),
+          None => {
+            let (part, local) = shards
+              .locate(meta.line_no)?
+              .ok_or_else(|| CoreError::InvalidArg(format!("record {} is past the last part", meta.line_no)))?;
+            (part, RecordMeta { line_no: local, ..meta })
+          }
+        };
+        let path = shards
+          .part(part)
+          .ok_or_else(|| CoreError::InvalidArg(format!("unknown part {part}")))?
+          .to_path_buf();
+        (path, meta)
+      }
+      None => (path, meta),
     };
 
     match format {
@@ -490,13 +4512,16 @@ impl CoreEngine {
     // For `.json` we ignore `meta.byte_len` and rescan to the end of the value to avoid relying
     // on potentially truncated lengths.
     if format == FileFormat::Json {
-      return crate::formats::read_json_value_at_offset(&path, meta.byte_offset, MAX_RECORD_BYTES);
+      return crate::formats::read_json_value_at_offset(&path, json_lenient, meta.byte_offset, MAX_RECORD_BYTES);
     }
     if format == FileFormat::Parquet {
       // For get_record_raw, we want the full content without truncation.
       // Use a very large value to effectively disable per-cell char limits.
       const FULL_RAW_MAX_CHARS: usize = 100_000_000;
-      return crate::formats::read_parquet_row_raw(&path, meta.line_no, FULL_RAW_MAX_CHARS);
+      return match parquet {
+        Some(conn) => conn.lock().row_raw(meta.line_no, FULL_RAW_MAX_CHARS),
+        None => crate::formats::read_parquet_row_raw(&path, meta.line_no, FULL_RAW_MAX_CHARS),
+      };
     }
 
     if meta.byte_len > MAX_RECORD_BYTES {
@@ -532,8 +4557,575 @@ impl CoreEngine {
       buf.pop();
     }
 
-    Ok(String::from_utf8_lossy(&buf).to_string())
+    Ok(encoding_impl::decode(&buf, encoding).into_owned())
+  }
+
+  /// IPC API: get_cell_raw(session_id, meta, column) -> String
+  ///
+  /// CSV sessions: the whole text of one cell of the record at `meta`, for cells cut short in
+  /// `Record.raw` (listed in its `__truncated__`). Streamed from the file, so the rest of a huge
+  /// row is never held in memory. Empty when the row has no such cell.
+  pub fn get_cell_raw(&self, session_id: &str, meta: RecordMeta, column: &str) -> Result<String, CoreError> {
+    let _interactive = self.tasks.interactive();
+    let (path, format, encoding, csv) = {
+      let mut sessions = self.sessions.lock();
+      let s = sessions
+        .get_mut(session_id)
+        .ok_or_else(|| CoreError::UnknownSession(session_id.to_string()))?;
+      s.last_access_ms = now_ms();
+      if s.shards.is_some() {
+        return Err(multi_file_unsupported("get_cell_raw"));
+      }
+      (PathBuf::from(&s.info.path), s.format.clone(), s.info.encoding, s.info.csv.clone())
+    };
+    if format != FileFormat::Csv {
+      return Err(CoreError::UnsupportedFormat(format));
+    }
+    let index = formats::read_csv_header(&path, encoding, &csv)?
+      .iter()
+      .position(|h| h == column)
+      .ok_or_else(|| CoreError::InvalidArg(format!("unknown column: {column}")))?;
+    let file_len = std::fs::metadata(&path)?.len();
+    if meta.byte_offset.saturating_add(meta.byte_len) > file_len {
+      return Err(CoreError::InvalidArg(format!(
+        "range [{}..{}) beyond file len {}",
+        meta.byte_offset,
+        meta.byte_offset.saturating_add(meta.byte_len),
+        file_len
+      )));
+    }
+    formats::read_csv_cell(&path, meta.byte_offset, meta.byte_len, index, encoding, &csv)
+  }
+
+  /// IPC API: save_parquet_blob(session_id, record_index, column, output_path) -> ExportResult
+  ///
+  /// Writes the whole value of a Parquet BLOB cell to `output_path` (pages and raw JSON only show
+  /// its size and first bytes); a null cell writes an empty file. `records_written` is 1.
+  pub fn save_parquet_blob(
+    &self,
+    session_id: &str,
+    record_index: u64,
+    column: &str,
+    output_path: impl AsRef<Path>,
+  ) -> Result<ExportResult, CoreError> {
+    let (path, format, parquet) = {
+      let mut sessions = self.sessions.lock();
+      let s = sessions
+        .get_mut(session_id)
+        .ok_or_else(|| CoreError::UnknownSession(session_id.to_string()))?;
+      s.last_access_ms = now_ms();
+      if s.shards.is_some() {
+        return Err(multi_file_unsupported("save_parquet_blob"));
+      }
+      (PathBuf::from(&s.info.path), s.format.clone(), s.parquet.clone())
+    };
+    if format != FileFormat::Parquet {
+      return Err(CoreError::UnsupportedFormat(format));
+    }
+    let bytes = match parquet {
+      Some(conn) => conn.lock().blob(record_index, column)?,
+      None => ParquetConn::open(&path)?.blob(record_index, column)?,
+    };
+    let output_path = output_path.as_ref();
+    let partial = partial_output_path(output_path);
+    std::fs::write(&partial, &bytes)?;
+    std::fs::rename(&partial, output_path)?;
+    Ok(ExportResult {
+      output_path: output_path.to_string_lossy().to_string(),
+      records_written: 1,
+    })
+  }
+
+  /// IPC API: save_record_edit(session_id, meta, body, output_path) -> ExportResult
+  ///
+  /// Writes a copy of the session's JSONL / CSV file to `output_path` with the record at `meta`
+  /// replaced by `body`; the session file is never modified (`output_path` must be another
+  /// file). JSONL bodies must be valid JSON and are written compact if they span lines. CSV
+  /// bodies are either the `{header: cell}` object shown in `raw` (data rows) or a CSV row as
+  /// text. The body is encoded in the session's encoding. `records_written` is 1.
+  pub fn save_record_edit(
+    &self,
+    session_id: &str,
+    meta: RecordMeta,
+    body: &str,
+    output_path: impl AsRef<Path>,
+  ) -> Result<ExportResult, CoreError> {
+    let (path, format, encoding, csv) = {
+      let mut sessions = self.sessions.lock();
+      let s = sessions
+        .get_mut(session_id)
+        .ok_or_else(|| CoreError::UnknownSession(session_id.to_string()))?;
+      s.last_access_ms = now_ms();
+      if s.shards.is_some() {
+        return Err(multi_file_unsupported("save_record_edit"));
+      }
+      (PathBuf::from(&s.info.path), s.format.clone(), s.info.encoding, s.info.csv.clone())
+    };
+    let output_path = output_path.as_ref();
+    if std::fs::canonicalize(output_path).ok() == Some(std::fs::canonicalize(&path)?) {
+      return Err(CoreError::InvalidArg("output_path must not be the session file".into()));
+    }
+
+    let line = match format {
+      FileFormat::Jsonl => {
+        let value = serde_json::from_str::<serde_json::Value>(body)
+          .map_err(|e| CoreError::InvalidArg(format!("record body is not valid JSON: {e}")))?;
+        if body.contains(['\n', '\r']) {
+          value.to_string()
+        } else {
+          body.to_string()
+        }
+      }
+      FileFormat::Csv => match serde_json::from_str::<serde_json::Value>(body) {
+        Ok(serde_json::Value::Object(obj)) if meta.line_no > 0 || !csv.has_header => {
+          let headers = formats::read_csv_header(&path, encoding, &csv)?;
+          formats::object_to_csv_line(&headers, &obj, &csv)?
+        }
+        _ => body.to_string(),
+      },
+      other => return Err(CoreError::UnsupportedFormat(other)),
+    };
+
+    // The record must still be where the page saw it: a line start, inside the file.
+    let file_len = std::fs::metadata(&path)?.len();
+    if meta.byte_len == 0 || meta.byte_offset.saturating_add(meta.byte_len) > file_len {
+      return Err(CoreError::InvalidArg(format!(
+        "record range [{}..{}) does not fit the file (len {file_len}); was it modified?",
+        meta.byte_offset,
+        meta.byte_offset.saturating_add(meta.byte_len)
+      )));
+    }
+    if meta.byte_offset > 0 {
+      let mut f = std::fs::File::open(&path)?;
+      f.seek(SeekFrom::Start(meta.byte_offset - 1))?;
+      let mut prev = [0u8; 1];
+      f.read_exact(&mut prev)?;
+      if prev[0] != b'\n' {
+        return Err(CoreError::InvalidArg(format!(
+          "byte_offset {} is not a record start; was the file modified?",
+          meta.byte_offset
+        )));
+      }
+    }
+
+    let bytes = encoding_impl::encode(&line, encoding)?;
+    export_impl::write_with_record_replaced(&path, meta.byte_offset, meta.byte_len, &bytes, output_path)?;
+    Ok(ExportResult {
+      output_path: output_path.to_string_lossy().to_string(),
+      records_written: 1,
+    })
+  }
+
+  /// IPC API: save_json_node_edit(session_id, meta, node_offset?, path, body, output_path) -> ExportResult
+  ///
+  /// Writes a copy of the session's JSON / JSONL file to `output_path` with one node of the
+  /// record at `meta` replaced by `body`: the node at `node_offset` (as the offset tree, find and
+  /// JSONPath results give it), or else the one at `path` under the record. Everything around
+  /// the node is copied byte for byte, streaming, so huge records can be fixed surgically; the
+  /// session file is never modified. `body` must be valid JSON (written compact in JSONL if it
+  /// spans lines). `records_written` is 1.
+  pub fn save_json_node_edit(
+    &self,
+    session_id: &str,
+    meta: RecordMeta,
+    node_offset: Option<u64>,
+    path: &[JsonPathSegment],
+    body: &str,
+    output_path: impl AsRef<Path>,
+  ) -> Result<ExportResult, CoreError> {
+    let (source, format, encoding, lenient) = {
+      let mut sessions = self.sessions.lock();
+      let s = sessions
+        .get_mut(session_id)
+        .ok_or_else(|| CoreError::UnknownSession(session_id.to_string()))?;
+      s.last_access_ms = now_ms();
+      if s.shards.is_some() {
+        return Err(multi_file_unsupported("save_json_node_edit"));
+      }
+      (PathBuf::from(&s.info.path), s.format.clone(), s.info.encoding, s.info.json_lenient)
+    };
+    if format != FileFormat::Json && format != FileFormat::Jsonl {
+      return Err(CoreError::UnsupportedFormat(format));
+    }
+    let output_path = output_path.as_ref();
+    if std::fs::canonicalize(output_path).ok() == Some(std::fs::canonicalize(&source)?) {
+      return Err(CoreError::InvalidArg("output_path must not be the session file".into()));
+    }
+    let value = serde_json::from_str::<serde_json::Value>(body)
+      .map_err(|e| CoreError::InvalidArg(format!("node body is not valid JSON: {e}")))?;
+    let body = if format == FileFormat::Jsonl && body.contains(['\n', '\r']) {
+      value.to_string()
+    } else {
+      body.trim().to_string()
+    };
+
+    let node_offset = match node_offset {
+      Some(offset) => offset,
+      None => {
+        let (start, consumed) = self.json_path_start(&source, lenient, &meta, path);
+        formats::json_node_offset(&source, lenient, start, &path[consumed..])?
+      }
+    };
+    let record_end = meta.byte_offset.saturating_add(meta.byte_len);
+    if node_offset < meta.byte_offset || (meta.byte_len > 0 && node_offset >= record_end) {
+      return Err(CoreError::InvalidArg(format!(
+        "node_offset {node_offset} is outside the record [{}..{record_end})",
+        meta.byte_offset
+      )));
+    }
+    let node = formats::json_node_stats_at_offset(&source, lenient, node_offset)?;
+    if meta.byte_len > 0 && node.node_offset + node.byte_len > record_end {
+      return Err(CoreError::InvalidArg(format!(
+        "node at {node_offset} runs past the record end {record_end}; was the file modified?"
+      )));
+    }
+
+    let bytes = encoding_impl::encode(&body, encoding)?;
+    export_impl::write_with_range_replaced(&source, node.node_offset, node.byte_len, &bytes, output_path)?;
+    Ok(ExportResult {
+      output_path: output_path.to_string_lossy().to_string(),
+      records_written: 1,
+    })
+  }
+
+  /// IPC API (v2): write_pretty_record(session_id, meta) -> ExportResult
+  ///
+  /// Pretty-prints the whole JSON / JSONL record at `meta` into a new temp file (under
+  /// `spool_dir`, else the system temp directory) and returns its path, so a viewer can open
+  /// records of any size: there is no `get_record_raw` cap and nothing crosses IPC. Streams
+  /// token by token; strings keep their bytes. The file is deleted when the session closes.
+  /// `records_written` is 1.
+  pub fn write_pretty_record(&self, session_id: &str, meta: RecordMeta) -> Result<ExportResult, CoreError> {
+    let (source, format, lenient) = {
+      let mut sessions = self.sessions.lock();
+      let s = sessions
+        .get_mut(session_id)
+        .ok_or_else(|| CoreError::UnknownSession(session_id.to_string()))?;
+      s.last_access_ms = now_ms();
+      if s.shards.is_some() {
+        return Err(multi_file_unsupported("write_pretty_record"));
+      }
+      (PathBuf::from(&s.info.path), s.format.clone(), s.info.json_lenient)
+    };
+    if format != FileFormat::Json && format != FileFormat::Jsonl {
+      return Err(CoreError::UnsupportedFormat(format));
+    }
+    let dir = self.options.spool_dir.clone().unwrap_or_else(std::env::temp_dir);
+    std::fs::create_dir_all(&dir)?;
+    let output_path = dir.join(format!("datalens-record-{}.json", Uuid::new_v4()));
+    if let Err(e) = export_impl::write_pretty_json_value(&self.tasks, &source, lenient, meta.byte_offset, &output_path) {
+      let _ = std::fs::remove_file(&output_path);
+      return Err(e);
+    }
+    match self.sessions.lock().get_mut(session_id) {
+      Some(s) => s.pretty_files.push(output_path.clone()),
+      None => {
+        // Closed meanwhile: nothing would delete it.
+        let _ = std::fs::remove_file(&output_path);
+        return Err(CoreError::UnknownSession(session_id.to_string()));
+      }
+    }
+    Ok(ExportResult {
+      output_path: output_path.to_string_lossy().to_string(),
+      records_written: 1,
+    })
+  }
+}
+
+/// Deletes the `write_pretty_record` files of a closed session.
+fn remove_pretty_files(state: &SessionState) {
+  for path in &state.pretty_files {
+    let _ = std::fs::remove_file(path);
+  }
+}
+
+/// The summary of a node from its node-index entry (always complete).
+fn indexed_summary(node: &IndexedNode) -> JsonNodeSummary {
+  JsonNodeSummary {
+    kind: if node.object { JsonNodeKind::Object } else { JsonNodeKind::Array },
+    child_count: Some(node.child_count),
+    complete: true,
+  }
+}
+
+/// Follow mode starts at the end of line-format files as opened.
+fn follow_baseline(path: &Path, format: &FileFormat) -> Option<FollowCursor> {
+  if !matches!(format, FileFormat::Jsonl | FileFormat::Csv) {
+    return None;
+  }
+  let offset = std::fs::metadata(path).ok()?.len();
+  Some(FollowCursor { offset, line: None })
+}
+
+/// First record boundary at or after `offset`, as `(offset, record index)` (CSV header = 0).
+fn record_boundary_at_or_after(
+  path: &Path,
+  format: &FileFormat,
+  csv: &CsvDialect,
+  offset: u64,
+) -> Result<(u64, u64), CoreError> {
+  let mut pos = 0u64;
+  let mut index = 0u64;
+  if offset > 0 {
+    formats::walk_record_lengths(path, format.clone(), csv, false, 0, |len| {
+      pos += len;
+      index += 1;
+      pos < offset
+    })?;
+  }
+  Ok((pos, index))
+}
+
+/// Records that start at or after `previous_size` (an unterminated old tail is not recounted).
+fn count_appended(path: &Path, format: &FileFormat, previous_size: u64, csv: &CsvDialect) -> Result<u64, CoreError> {
+  let start = formats::next_line_start(path, previous_size)?;
+  let mut appended = 0u64;
+  formats::walk_record_lengths(path, format.clone(), csv, false, start, |_| {
+    appended += 1;
+    true
+  })?;
+  // A CSV file that was empty gains its header first.
+  if *format == FileFormat::Csv && csv.has_header && previous_size == 0 {
+    appended = appended.saturating_sub(1);
+  }
+  Ok(appended)
+}
+
+/// End of the last newline-terminated record: a writer may still be appending the tail.
+fn last_newline_end(path: &Path, file_len: u64) -> Result<u64, CoreError> {
+  const TAIL_CHUNK: u64 = 64 * 1024;
+  let mut f = std::fs::File::open(path)?;
+  let mut end = file_len;
+  while end > 0 {
+    let start = end.saturating_sub(TAIL_CHUNK);
+    let mut buf = vec![0u8; (end - start) as usize];
+    f.seek(SeekFrom::Start(start))?;
+    f.read_exact(&mut buf)?;
+    if let Some(i) = buf.iter().rposition(|b| *b == b'\n') {
+      return Ok(start + i as u64 + 1);
+    }
+    end = start;
+  }
+  Ok(0)
+}
+
+/// Where a filtered session reads the hit `meta` from.
+fn view_cursor(format: &FileFormat, meta: &RecordMeta) -> Cursor {
+  match format {
+    // Parquet hits carry the row index in `line_no`; there are no byte offsets.
+    FileFormat::Parquet => Cursor { offset: 0, line: meta.line_no },
+    _ => Cursor {
+      offset: meta.byte_offset,
+      line: meta.line_no,
+    },
+  }
+}
+
+/// Label key of a record: its byte offset (Parquet: the row index).
+fn label_offset(format: &FileFormat, meta: &RecordMeta) -> u64 {
+  match format {
+    FileFormat::Parquet => meta.line_no,
+    _ => meta.byte_offset,
+  }
+}
+
+fn record_label(format: &FileFormat, l: StoredRecordLabel) -> RecordLabel {
+  let meta = match format {
+    FileFormat::Parquet => RecordMeta {
+      line_no: l.line_no,
+      byte_offset: 0,
+      byte_len: 0,
+      part: None,
+    },
+    _ => RecordMeta {
+      line_no: l.line_no,
+      byte_offset: l.record_offset,
+      byte_len: l.byte_len,
+      part: None,
+    },
+  };
+  RecordLabel {
+    meta,
+    tags: l.tags,
+    note: l.note,
+    updated_at_ms: l.updated_at_ms,
+  }
+}
+
+fn filtered_unsupported(api: &str) -> CoreError {
+  CoreError::InvalidArg(format!("{api} is not supported for filtered sessions"))
+}
+
+/// The cursor token without its `{epoch}.` prefix (see `finish_page`); tokens from another
+/// epoch are stale.
+fn strip_cursor_epoch(token: Option<&str>, epoch: u32) -> Result<Option<&str>, CoreError> {
+  let (token_epoch, inner) = match token {
+    None | Some("") => return Ok(token),
+    Some(t) => match t.split_once('.') {
+      Some((prefix, inner)) => (
+        prefix.parse::<u32>().map_err(|_| CoreError::BadCursor(t.to_string()))?,
+        inner,
+      ),
+      None => (0, t),
+    },
+  };
+  if token_epoch != epoch {
+    return Err(CoreError::BadCursor(
+      "file was reloaded since this cursor was issued; restart from the first page".into(),
+    ));
+  }
+  Ok(Some(inner))
+}
+
+/// Flags `page`'s ids as estimates and marks its next cursor so the pages after it are too.
+fn mark_ids_estimated(page: &mut RecordPage) {
+  page.ids_estimated = true;
+  page.next_cursor = page.next_cursor.take().map(mark_estimated);
+}
+
+/// `<output>.partial`, where an export writes until it completes.
+fn partial_output_path(output_path: &Path) -> PathBuf {
+  let mut name = output_path.file_name().unwrap_or_default().to_os_string();
+  name.push(".partial");
+  output_path.with_file_name(name)
+}
+
+/// Names of the leaf columns of `conn`'s file, in file order.
+fn column_names(conn: &ParquetConn) -> Result<Vec<String>, CoreError> {
+  Ok(conn.columns()?.into_iter().map(|(name, _, _)| name).collect())
+}
+
+/// `wanted` without the columns `conn`'s file doesn't have (any more); `None` if that leaves none.
+fn existing_columns(conn: &ParquetConn, wanted: &[String]) -> Result<Option<Vec<String>>, CoreError> {
+  Ok(known_columns(&column_names(conn)?, wanted))
+}
+
+/// `wanted` without the columns missing from `names`; `None` if that leaves none.
+fn known_columns(names: &[String], wanted: &[String]) -> Option<Vec<String>> {
+  let kept: Vec<String> = wanted.iter().filter(|c| names.contains(c)).cloned().collect();
+  (!kept.is_empty()).then_some(kept)
+}
+
+/// `wanted` without the filters on columns `conn`'s file doesn't have (any more); `None` if that
+/// leaves none.
+fn existing_filters(conn: &ParquetConn, wanted: &[ColumnFilter]) -> Result<Option<Vec<ColumnFilter>>, CoreError> {
+  let names = conn.columns()?;
+  let kept: Vec<ColumnFilter> = wanted
+    .iter()
+    .filter(|f| names.iter().any(|(name, _, _)| *name == f.column))
+    .cloned()
+    .collect();
+  Ok((!kept.is_empty()).then_some(kept))
+}
+
+/// A DuckDB connection for a single-file Parquet session to keep (see `SessionState::parquet`).
+///
+/// Files with an encrypted footer need `decryption_key`.
+fn parquet_conn(
+  path: &Path,
+  format: &FileFormat,
+  options: &CoreOptions,
+  decryption_key: Option<&str>,
+) -> Result<Option<Arc<Mutex<ParquetConn>>>, CoreError> {
+  if *format != FileFormat::Parquet {
+    return Ok(None);
+  }
+  let mut conn = ParquetConn::open(path)?
+    .with_utc_offset(options.parquet_utc_offset_minutes)
+    .with_page_cache(options.parquet_page_cache_pages);
+  match decryption_key {
+    Some(key) => conn = conn.with_decryption_key(key)?,
+    None if formats::is_encrypted_parquet(path) => {
+      return Err(CoreError::InvalidArg(format!(
+        "{} is an encrypted parquet file; open it with its decryption key",
+        path.display()
+      )));
+    }
+    None => {}
+  }
+  Ok(Some(Arc::new(Mutex::new(conn))))
+}
+
+/// JSONL file a reshaped export (`export_reshaped`) is staged in before writing `output_path`.
+fn staging_path(output_path: &Path) -> PathBuf {
+  output_path.with_extension("stage.jsonl")
+}
+
+/// Output path from an export preset's `destination` template (see `ExportPreset`).
+fn preset_destination(template: &str, source: &Path, format: &ExportFormat) -> PathBuf {
+  let name = source.file_stem().unwrap_or_default().to_string_lossy();
+  let ext = match format {
+    ExportFormat::Json => "json",
+    ExportFormat::Jsonl => "jsonl",
+    ExportFormat::Csv => "csv",
+  };
+  let timestamp = (now_ms() / 1000).to_string();
+  let path = PathBuf::from(
+    template
+      .replace("{name}", &name)
+      .replace("{format}", ext)
+      .replace("{timestamp}", &timestamp),
+  );
+  match source.parent() {
+    Some(dir) if path.is_relative() => dir.join(path),
+    _ => path,
+  }
+}
+
+/// Files to reopen to run a session's task again (`TaskHistoryEntry::paths`).
+fn history_paths(info: &SessionInfo) -> Vec<String> {
+  if let Some(url) = &info.source_url {
+    vec![url.clone()]
+  } else if !info.parts.is_empty() {
+    info.parts.clone()
+  } else {
+    vec![info.path.clone()]
+  }
+}
+
+/// The checks `set_csv_dialect` documents: an ASCII delimiter, quote and escape without line
+/// breaks or shared characters, and a `delimiter_regex` that compiles, matches `delimiter` and
+/// can't end a cell on nothing, a line break, the quote or the escape.
+fn check_csv_dialect(dialect: &CsvDialect) -> Result<(), CoreError> {
+  let marks = [Some(dialect.quote), dialect.escape];
+  let usable = |c: char| c.is_ascii() && !matches!(c, '\n' | '\r');
+  if dialect.delimiter.is_empty()
+    || dialect.escape == Some(dialect.quote)
+    || !dialect.delimiter.chars().chain(marks.into_iter().flatten()).all(usable)
+    || marks.into_iter().flatten().any(|c| dialect.delimiter.contains(c))
+  {
+    return Err(CoreError::InvalidArg(
+      "CSV delimiter (one or more characters), quote and escape must be ASCII other than line breaks and \
+       share no character"
+        .into(),
+    ));
   }
+  let Some(pattern) = &dialect.delimiter_regex else {
+    return Ok(());
+  };
+  let regex = formats::DelimiterRegex::new(pattern)
+    .map_err(|e| CoreError::InvalidArg(format!("CSV delimiter regex {pattern:?}: {e}")))?;
+  if regex.match_len(dialect.delimiter.as_bytes()) != Some(dialect.delimiter.len()) {
+    return Err(CoreError::InvalidArg(format!(
+      "CSV delimiter regex {pattern:?} must match the delimiter {:?} written between cells",
+      dialect.delimiter
+    )));
+  }
+  let stray = ["", "\n", "\r"]
+    .into_iter()
+    .map(String::from)
+    .chain(marks.into_iter().flatten().map(String::from))
+    .find(|s| regex.is_match(s));
+  if let Some(stray) = stray {
+    return Err(CoreError::InvalidArg(format!(
+      "CSV delimiter regex {pattern:?} must not match {stray:?} (empty text, a line break, the quote or the escape)"
+    )));
+  }
+  Ok(())
+}
+
+fn multi_file_unsupported(api: &str) -> CoreError {
+  CoreError::InvalidArg(format!("{api} is not supported for multi-file sessions"))
 }
 
 fn now_ms() -> i64 {
@@ -3,17 +3,58 @@ use std::{
   fs::File,
   io::{BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write},
   path::{Path, PathBuf},
+  sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+  },
 };
 
 use serde_json::{Map, Value};
 
 use crate::{
+  cursor::Cursor,
+  derive::{self, DerivedSet},
   engine::CoreError,
-  models::{ExportFormat, ExportRequest, FileFormat},
+  formats::{csv_line_to_object, quote_csv_field, read_csv_header, read_csv_record_bytes, ParquetConn},
+  models::{CsvDialect, ExportFormat, ExportRequest, FileFormat, RecordLabel, TextEncoding},
   models::ExportResult,
+  shards::ShardSet,
   tasks::TaskManager,
 };
 
+/// Export output file; writes fail once the engine shuts down (see `CoreEngine::shutdown`), so
+/// an export in progress stops at its next buffer flush.
+pub(crate) struct ExportFile {
+  file: File,
+  shutting_down: Arc<AtomicBool>,
+}
+
+impl ExportFile {
+  fn create(path: &Path, tasks: &TaskManager) -> Result<Self, CoreError> {
+    Ok(Self {
+      file: File::create(path)?,
+      shutting_down: tasks.shutdown_flag(),
+    })
+  }
+}
+
+impl Write for ExportFile {
+  fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+    if self.shutting_down.load(Ordering::SeqCst) {
+      return Err(std::io::Error::other("export stopped: shutting down"));
+    }
+    self.file.write(buf)
+  }
+
+  fn flush(&mut self) -> std::io::Result<()> {
+    self.file.flush()
+  }
+}
+
+/// `parquet`: the session's DuckDB connection for Parquet sources; without it one is opened.
+/// `csv`: how CSV sources are read (without a header, row 0 is exported like any other).
+/// `json_lenient`: JSON subtrees are read leniently (see `SessionInfo::json_lenient`).
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn export(
   tasks: &TaskManager,
   session_path: PathBuf,
@@ -21,12 +62,15 @@ pub(crate) fn export(
   request: ExportRequest,
   out_format: ExportFormat,
   output_path: &Path,
+  derived: Option<&DerivedSet>,
+  parquet: Option<&ParquetConn>,
+  csv: &CsvDialect,
+  json_lenient: bool,
 ) -> Result<ExportResult, CoreError> {
   if let Some(parent) = output_path.parent() {
     std::fs::create_dir_all(parent)?;
   }
-  let out_file = File::create(output_path)?;
-  let mut writer = BufWriter::new(out_file);
+  let mut writer = BufWriter::new(ExportFile::create(output_path, tasks)?);
 
   // Special: export a subtree (or its children) from the current JSON record.
   if let ExportRequest::JsonSubtree {
@@ -34,25 +78,22 @@ pub(crate) fn export(
     path,
     include_root,
     children,
+    minify,
   } = request
   {
     if session_format != FileFormat::Json {
       return Err(CoreError::UnsupportedFormat(session_format));
     }
-    if matches!(out_format, ExportFormat::Csv) {
-      return Err(CoreError::InvalidArg(
-        "json_subtree export only supports json/jsonl output".into(),
-      ));
-    }
-
     // Stream export for huge records (no full JSON parse in memory).
     let written = crate::formats::export_json_subtree_stream(
       &session_path,
+      json_lenient,
       meta.byte_offset,
       &path,
       include_root,
       &children,
       out_format,
+      minify,
       &mut writer,
     )?;
     writer.flush()?;
@@ -69,6 +110,12 @@ pub(crate) fn export(
       .get_search_task_hit_ids(&task_id)
       .map_err(CoreError::Task)?,
     ExportRequest::JsonSubtree { .. } => unreachable!("handled above"),
+    ExportRequest::Labeled { .. } => {
+      return Err(CoreError::InvalidArg("labeled export needs the session's labels (see export_labeled)".into()))
+    }
+    ExportRequest::Filtered => {
+      return Err(CoreError::InvalidArg("filtered export needs the session's filters".into()))
+    }
   };
 
   let ids = normalize_ids(ids);
@@ -79,20 +126,51 @@ pub(crate) fn export(
     });
   }
 
+  if let Some(derived) = derived {
+    let written = match out_format {
+      ExportFormat::Json => {
+        writer.write_all(b"[")?;
+        let mut array = JsonLinesToArray::new(&mut writer);
+        let written = export_with_derived_as_jsonl(&session_path, session_format, csv, &ids, derived, &mut array)?;
+        let wrote_any = array.wrote_any;
+        writer.write_all(if wrote_any { b"\n]" } else { b"]" })?;
+        written
+      }
+      ExportFormat::Jsonl => {
+        export_with_derived_as_jsonl(&session_path, session_format, csv, &ids, derived, &mut writer)?
+      }
+      ExportFormat::Csv if session_format == FileFormat::Csv => {
+        export_csv_with_derived(&session_path, csv, &ids, derived, &mut writer)?
+      }
+      ExportFormat::Csv => {
+        return Err(CoreError::InvalidArg(
+          "derived columns are only exported to CSV from CSV sessions".into(),
+        ))
+      }
+    };
+    writer.flush()?;
+    return Ok(ExportResult {
+      output_path: output_path.to_string_lossy().to_string(),
+      records_written: written,
+    });
+  }
+
   let written = match (session_format, out_format) {
     // Raw line export (backward compatible behavior):
     (FileFormat::Jsonl, ExportFormat::Jsonl) => export_lines_passthrough(&session_path, &ids, &mut writer)?,
     (FileFormat::Jsonl, ExportFormat::Csv) => export_lines_passthrough(&session_path, &ids, &mut writer)?,
-    (FileFormat::Csv, ExportFormat::Csv) => export_csv_passthrough(&session_path, &ids, &mut writer)?,
+    (FileFormat::Csv, ExportFormat::Csv) => export_csv_passthrough(&session_path, csv, &ids, &mut writer)?,
 
     // Conversions:
     (FileFormat::Jsonl, ExportFormat::Json) => export_jsonl_to_json_array(&session_path, &ids, &mut writer)?,
-    (FileFormat::Csv, ExportFormat::Jsonl) => export_csv_to_jsonl(&session_path, &ids, &mut writer)?,
-    (FileFormat::Csv, ExportFormat::Json) => export_csv_to_json(&session_path, &ids, &mut writer)?,
+    (FileFormat::Csv, ExportFormat::Jsonl) => export_csv_to_jsonl(&session_path, csv, &ids, &mut writer)?,
+    (FileFormat::Csv, ExportFormat::Json) => export_csv_to_json(&session_path, csv, &ids, &mut writer)?,
     (FileFormat::Json, ExportFormat::Jsonl) => export_json_to_jsonl(&session_path, &ids, &mut writer)?,
     (FileFormat::Json, ExportFormat::Json) => export_json_to_json(&session_path, &ids, &mut writer)?,
-    (FileFormat::Parquet, ExportFormat::Jsonl) => export_parquet_to_jsonl(&session_path, &ids, &mut writer)?,
-    (FileFormat::Parquet, ExportFormat::Json) => export_parquet_to_json(&session_path, &ids, &mut writer)?,
+    (FileFormat::Parquet, out_format @ (ExportFormat::Jsonl | ExportFormat::Json)) => match parquet {
+      Some(conn) => export_parquet(conn, &ids, out_format, &mut writer)?,
+      None => export_parquet(&ParquetConn::open(&session_path)?, &ids, out_format, &mut writer)?,
+    },
 
     (fmt, _) => return Err(CoreError::UnsupportedFormat(fmt)),
   };
@@ -104,6 +182,490 @@ pub(crate) fn export(
   })
 }
 
+/// Selection / search-task export over a multi-file session (ids are session-wide).
+///
+/// Parts are exported one after another into the same output; JSON output stitches their
+/// records into a single array.
+pub(crate) fn export_shards(
+  tasks: &TaskManager,
+  shards: &mut ShardSet,
+  request: ExportRequest,
+  out_format: ExportFormat,
+  output_path: &Path,
+) -> Result<ExportResult, CoreError> {
+  let format = shards.format();
+  let ids: Vec<u64> = match request {
+    ExportRequest::Selection { record_ids } => record_ids,
+    ExportRequest::SearchTask { task_id } => tasks
+      .get_search_task_hit_ids(&task_id)
+      .map_err(CoreError::Task)?,
+    ExportRequest::JsonSubtree { .. } => return Err(CoreError::UnsupportedFormat(format)),
+    ExportRequest::Labeled { .. } => {
+      return Err(CoreError::InvalidArg("labeled export is not supported for multi-file sessions".into()))
+    }
+    ExportRequest::Filtered => {
+      return Err(CoreError::InvalidArg("filtered export is not supported for multi-file sessions".into()))
+    }
+  };
+  if format == FileFormat::Parquet && matches!(out_format, ExportFormat::Csv) {
+    return Err(CoreError::UnsupportedFormat(format));
+  }
+
+  // Ids are sorted, so parts come out in order.
+  let mut groups: Vec<(usize, Vec<u64>)> = Vec::new();
+  for id in normalize_ids(ids) {
+    let Some((part, local)) = shards.locate(id)? else {
+      break;
+    };
+    match groups.last_mut() {
+      Some((p, local_ids)) if *p == part => local_ids.push(local),
+      _ => groups.push((part, vec![local])),
+    }
+  }
+
+  let parts = shards.parts();
+  if let Some(parent) = output_path.parent() {
+    std::fs::create_dir_all(parent)?;
+  }
+  let mut writer = BufWriter::new(ExportFile::create(output_path, tasks)?);
+  let mut written = 0u64;
+  match out_format {
+    ExportFormat::Json => {
+      writer.write_all(b"[")?;
+      let mut array = JsonLinesToArray::new(&mut writer);
+      for (part, local_ids) in &groups {
+        written += export_part_as_jsonl(&parts[*part], &format, local_ids, &mut array)?;
+      }
+      let wrote_any = array.wrote_any;
+      writer.write_all(if wrote_any { b"\n]" } else { b"]" })?;
+    }
+    ExportFormat::Jsonl => {
+      for (part, local_ids) in &groups {
+        written += export_part_as_jsonl(&parts[*part], &format, local_ids, &mut writer)?;
+      }
+    }
+    ExportFormat::Csv => {
+      for (part, local_ids) in &groups {
+        let path = &parts[*part];
+        written += match format {
+          FileFormat::Csv => export_csv_passthrough(path, &CsvDialect::default(), local_ids, &mut writer)?,
+          _ => export_lines_passthrough(path, local_ids, &mut writer)?,
+        };
+      }
+    }
+  }
+
+  writer.flush()?;
+  Ok(ExportResult {
+    output_path: output_path.to_string_lossy().to_string(),
+    records_written: written,
+  })
+}
+
+/// Labeled-record export: each record gets a `_labels` member (`{"tags": [...], "note": ...}`);
+/// records that are not JSON objects are wrapped as `{"record": ..., "_labels": ...}`.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn export_labeled(
+  tasks: &TaskManager,
+  path: &Path,
+  format: &FileFormat,
+  labels: &[RecordLabel],
+  out_format: ExportFormat,
+  output_path: &Path,
+  parquet: Option<&ParquetConn>,
+  csv: &CsvDialect,
+) -> Result<ExportResult, CoreError> {
+  if let Some(parent) = output_path.parent() {
+    std::fs::create_dir_all(parent)?;
+  }
+  let mut writer = BufWriter::new(ExportFile::create(output_path, tasks)?);
+  match out_format {
+    ExportFormat::Json => {
+      writer.write_all(b"[")?;
+      let mut array = JsonLinesToArray::new(&mut writer);
+      write_labeled_jsonl(path, format, csv, labels, parquet, &mut array)?;
+      let wrote_any = array.wrote_any;
+      writer.write_all(if wrote_any { b"\n]" } else { b"]" })?;
+    }
+    ExportFormat::Jsonl => write_labeled_jsonl(path, format, csv, labels, parquet, &mut writer)?,
+    ExportFormat::Csv => {
+      return Err(CoreError::InvalidArg("labeled export only supports json/jsonl output".into()));
+    }
+  }
+  writer.flush()?;
+  Ok(ExportResult {
+    output_path: output_path.to_string_lossy().to_string(),
+    records_written: labels.len() as u64,
+  })
+}
+
+fn write_labeled_jsonl(
+  path: &Path,
+  format: &FileFormat,
+  csv: &CsvDialect,
+  labels: &[RecordLabel],
+  parquet: Option<&ParquetConn>,
+  writer: &mut impl Write,
+) -> Result<(), CoreError> {
+  let headers = match format {
+    FileFormat::Csv => read_csv_header(path, TextEncoding::Utf8, csv)?,
+    _ => Vec::new(),
+  };
+  for label in labels {
+    let record = match format {
+      FileFormat::Parquet => {
+        let raw = match parquet {
+          Some(conn) => conn.row_raw(label.meta.line_no, usize::MAX)?,
+          None => crate::formats::read_parquet_row_raw(path, label.meta.line_no, usize::MAX)?,
+        };
+        serde_json::from_str(&raw).unwrap_or(Value::String(raw))
+      }
+      FileFormat::Csv => {
+        csv_line_to_object(&headers, &read_record_text(path, label.meta.byte_offset, label.meta.byte_len)?, csv)
+      }
+      _ => {
+        let text = read_record_text(path, label.meta.byte_offset, label.meta.byte_len)?;
+        serde_json::from_str(&text).unwrap_or(Value::String(text))
+      }
+    };
+    let mut label_obj = Map::new();
+    label_obj.insert("tags".into(), Value::from(label.tags.clone()));
+    if let Some(note) = &label.note {
+      label_obj.insert("note".into(), Value::String(note.clone()));
+    }
+    let mut obj = match record {
+      Value::Object(obj) => obj,
+      other => Map::from_iter([("record".to_string(), other)]),
+    };
+    obj.insert("_labels".into(), Value::Object(label_obj));
+    let line = serde_json::to_string(&Value::Object(obj)).map_err(|e| CoreError::InvalidArg(e.to_string()))?;
+    writer.write_all(line.as_bytes())?;
+    writer.write_all(b"\n")?;
+  }
+  Ok(())
+}
+
+/// Write the JSON value at `offset` of `path` to `output_path`, pretty-printed (see
+/// `formats::pretty_print_json_value`). Streams; stops like exports when the engine shuts down.
+pub(crate) fn write_pretty_json_value(
+  tasks: &TaskManager,
+  path: &Path,
+  json_lenient: bool,
+  offset: u64,
+  output_path: &Path,
+) -> Result<(), CoreError> {
+  let mut writer = BufWriter::new(ExportFile::create(output_path, tasks)?);
+  crate::formats::pretty_print_json_value(path, json_lenient, offset, &mut writer)?;
+  writer.flush()?;
+  Ok(())
+}
+
+/// Copy `path` to `output_path` with the `len` bytes at `offset` (one record, line terminator
+/// included) replaced by `body`. The record's terminator is kept, so line endings stay as they
+/// were. Streams the file; nothing is modified in place.
+pub(crate) fn write_with_record_replaced(
+  path: &Path,
+  offset: u64,
+  len: u64,
+  body: &[u8],
+  output_path: &Path,
+) -> Result<(), CoreError> {
+  let tail_len = len.min(2);
+  let mut tail = vec![0u8; tail_len as usize];
+  let mut f = File::open(path)?;
+  f.seek(SeekFrom::Start(offset + len - tail_len))?;
+  f.read_exact(&mut tail)?;
+  let terminator: &[u8] = if tail.ends_with(b"\r\n") {
+    b"\r\n"
+  } else if tail.ends_with(b"\n") {
+    b"\n"
+  } else {
+    b""
+  };
+  write_with_range_replaced(path, offset, len, &[body, terminator].concat(), output_path)
+}
+
+/// Copy `path` to `output_path` with the `len` bytes at `offset` replaced by `body`, byte for
+/// byte around them. Streams the file; nothing is modified in place.
+pub(crate) fn write_with_range_replaced(
+  path: &Path,
+  offset: u64,
+  len: u64,
+  body: &[u8],
+  output_path: &Path,
+) -> Result<(), CoreError> {
+  if let Some(parent) = output_path.parent() {
+    std::fs::create_dir_all(parent)?;
+  }
+  let mut reader = BufReader::new(File::open(path)?);
+  let mut writer = BufWriter::new(File::create(output_path)?);
+  std::io::copy(&mut reader.by_ref().take(offset), &mut writer)?;
+  writer.write_all(body)?;
+  reader.seek_relative(len as i64)?;
+  std::io::copy(&mut reader, &mut writer)?;
+  writer.flush()?;
+  Ok(())
+}
+
+/// Selected records as JSON lines with the derived values added as members (non-object
+/// records are wrapped as `{"record": value}`).
+fn export_with_derived_as_jsonl(
+  path: &Path,
+  format: FileFormat,
+  csv: &CsvDialect,
+  ids: &[u64],
+  derived: &DerivedSet,
+  writer: &mut impl Write,
+) -> Result<u64, CoreError> {
+  let mut wanted_idx = 0usize;
+  let mut written = 0u64;
+  let mut result = Ok(());
+  crate::formats::for_each_record_with(path, format, csv, Cursor { offset: 0, line: 0 }, |r| {
+    while wanted_idx < ids.len() && ids[wanted_idx] < r.id {
+      wanted_idx += 1;
+    }
+    if wanted_idx >= ids.len() {
+      return false;
+    }
+    if ids[wanted_idx] != r.id {
+      return true;
+    }
+    let raw = r.raw.as_deref().unwrap_or("");
+    let value = serde_json::from_str::<Value>(raw).unwrap_or_else(|_| Value::String(raw.to_string()));
+    let values = derived.evaluate_value(raw, Some(&value));
+    let mut obj = match value {
+      Value::Object(obj) => obj,
+      other => Map::from_iter([("record".to_string(), other)]),
+    };
+    obj.extend(values);
+    result = serde_json::to_string(&Value::Object(obj))
+      .map_err(|e| CoreError::InvalidArg(e.to_string()))
+      .and_then(|line| {
+        writer.write_all(line.as_bytes())?;
+        writer.write_all(b"\n")?;
+        Ok(())
+      });
+    written += 1;
+    wanted_idx += 1;
+    result.is_ok()
+  })?;
+  result?;
+  Ok(written)
+}
+
+/// CSV passthrough with the derived columns appended (their names to the header row, id 0).
+fn export_csv_with_derived(
+  path: &Path,
+  csv: &CsvDialect,
+  ids: &[u64],
+  derived: &DerivedSet,
+  writer: &mut impl Write,
+) -> Result<u64, CoreError> {
+  let headers = read_csv_header(path, TextEncoding::Utf8, csv)?;
+  let mut wanted_idx = 0usize;
+  let mut written = 0u64;
+
+  let in_file = File::open(path)?;
+  let mut reader = BufReader::new(in_file);
+
+  let mut record_no = 0u64;
+  loop {
+    if wanted_idx >= ids.len() {
+      break;
+    }
+    let mut buf = Vec::new();
+    let (n, _terminated_by_newline) = read_csv_record_bytes(&mut reader, &mut buf, csv)?;
+    if n == 0 {
+      break;
+    }
+
+    if ids[wanted_idx] == record_no {
+      trim_record_terminator(&mut buf);
+      let line = String::from_utf8_lossy(&buf).to_string();
+      let extra: Vec<String> = if csv.has_header && record_no == 0 {
+        derived.names().map(|n| quote_csv_field(n, csv)).collect()
+      } else {
+        let obj = csv_line_to_object(&headers, &line, csv);
+        let values = derived.evaluate_value(&obj.to_string(), Some(&obj));
+        values.values().map(|v| quote_csv_field(&derive::value_text(v), csv)).collect()
+      };
+      let delimiter = &csv.delimiter;
+      writer.write_all(line.as_bytes())?;
+      writer.write_all(format!("{delimiter}{}\n", extra.join(delimiter)).as_bytes())?;
+      written += 1;
+      wanted_idx += 1;
+    }
+    record_no += 1;
+  }
+  Ok(written)
+}
+
+/// Rewrites a JSONL export (`input`) as `out_format`, for export presets: nested objects become
+/// dotted keys if `flatten`, and `columns` keeps only those fields (missing ones are `null`).
+/// Records that aren't objects are taken as `{"value": ...}`. CSV gets a header row of
+/// `columns`, or else of the first record's keys.
+pub(crate) fn reshape_jsonl(
+  tasks: &TaskManager,
+  input: &Path,
+  out_format: ExportFormat,
+  columns: Option<&[String]>,
+  flatten: bool,
+  output_path: &Path,
+) -> Result<ExportResult, CoreError> {
+  let reader = BufReader::new(File::open(input)?);
+  let mut writer = BufWriter::new(ExportFile::create(output_path, tasks)?);
+  let mut header: Option<Vec<String>> = None;
+  let mut written = 0u64;
+
+  if out_format == ExportFormat::Json {
+    writer.write_all(b"[")?;
+  }
+  for line in reader.lines() {
+    let line = line?;
+    if line.trim().is_empty() {
+      continue;
+    }
+    let value: Value =
+      serde_json::from_str(&line).map_err(|e| CoreError::InvalidArg(format!("invalid JSON record: {e}")))?;
+    let mut record = match value {
+      Value::Object(map) => map,
+      other => Map::from_iter([("value".to_string(), other)]),
+    };
+    if flatten {
+      let mut flat = Map::new();
+      flatten_into(&mut flat, "", record);
+      record = flat;
+    }
+    if let Some(columns) = columns {
+      record = columns
+        .iter()
+        .map(|c| (c.clone(), record.remove(c).unwrap_or(Value::Null)))
+        .collect();
+    }
+
+    match out_format {
+      ExportFormat::Jsonl => {
+        writer.write_all(Value::Object(record).to_string().as_bytes())?;
+        writer.write_all(b"\n")?;
+      }
+      ExportFormat::Json => {
+        writer.write_all(if written == 0 { b"\n" } else { b",\n" })?;
+        writer.write_all(Value::Object(record).to_string().as_bytes())?;
+      }
+      ExportFormat::Csv => {
+        let header = match &header {
+          Some(header) => header,
+          None => {
+            let names: Vec<String> = match columns {
+              Some(columns) => columns.to_vec(),
+              None => record.keys().cloned().collect(),
+            };
+            let row: Vec<String> = names.iter().map(|n| quote_csv_field(n, &CsvDialect::default())).collect();
+            writer.write_all(format!("{}\n", row.join(",")).as_bytes())?;
+            header.insert(names)
+          }
+        };
+        let row: Vec<String> = header
+          .iter()
+          .map(|n| quote_csv_field(&record.get(n).map(derive::value_text).unwrap_or_default(), &CsvDialect::default()))
+          .collect();
+        writer.write_all(format!("{}\n", row.join(",")).as_bytes())?;
+      }
+    }
+    written += 1;
+  }
+  if out_format == ExportFormat::Json {
+    writer.write_all(b"\n]\n")?;
+  }
+
+  writer.flush()?;
+  Ok(ExportResult {
+    output_path: output_path.to_string_lossy().to_string(),
+    records_written: written,
+  })
+}
+
+/// `{"a":{"b":1}}` -> `{"a.b":1}`; arrays and empty objects are kept as values.
+fn flatten_into(out: &mut Map<String, Value>, prefix: &str, obj: Map<String, Value>) {
+  for (key, value) in obj {
+    let key = if prefix.is_empty() { key } else { format!("{prefix}.{key}") };
+    match value {
+      Value::Object(inner) if !inner.is_empty() => flatten_into(out, &key, inner),
+      other => {
+        out.insert(key, other);
+      }
+    }
+  }
+}
+
+/// Text of the record at `offset` (`len` bytes), without its line terminator.
+fn read_record_text(path: &Path, offset: u64, len: u64) -> Result<String, CoreError> {
+  let mut file = File::open(path)?;
+  file.seek(SeekFrom::Start(offset))?;
+  let mut buf = Vec::with_capacity(len as usize);
+  file.take(len).read_to_end(&mut buf)?;
+  trim_record_terminator(&mut buf);
+  Ok(String::from_utf8_lossy(&buf).to_string())
+}
+
+fn export_part_as_jsonl(
+  path: &Path,
+  format: &FileFormat,
+  ids: &[u64],
+  writer: &mut impl Write,
+) -> Result<u64, CoreError> {
+  match format {
+    FileFormat::Jsonl => export_lines_passthrough(path, ids, writer),
+    FileFormat::Csv => export_csv_to_jsonl(path, &CsvDialect::default(), ids, writer),
+    FileFormat::Parquet => export_parquet(&ParquetConn::open(path)?, ids, ExportFormat::Jsonl, writer),
+    other => Err(CoreError::UnsupportedFormat(other.clone())),
+  }
+}
+
+/// Rewrites JSONL written through it as JSON array elements; the caller writes `[` and `]`.
+struct JsonLinesToArray<W: Write> {
+  inner: W,
+  at_line_start: bool,
+  wrote_any: bool,
+}
+
+impl<W: Write> JsonLinesToArray<W> {
+  fn new(inner: W) -> Self {
+    Self {
+      inner,
+      at_line_start: true,
+      wrote_any: false,
+    }
+  }
+}
+
+impl<W: Write> Write for JsonLinesToArray<W> {
+  fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+    for chunk in buf.split_inclusive(|b| *b == b'\n') {
+      let (body, ends_line) = match chunk.strip_suffix(b"\n") {
+        Some(body) => (body, true),
+        None => (chunk, false),
+      };
+      if !body.is_empty() {
+        if self.at_line_start {
+          self.inner.write_all(if self.wrote_any { b",\n" } else { b"\n" })?;
+          self.wrote_any = true;
+          self.at_line_start = false;
+        }
+        self.inner.write_all(body)?;
+      }
+      if ends_line {
+        self.at_line_start = true;
+      }
+    }
+    Ok(buf.len())
+  }
+
+  fn flush(&mut self) -> std::io::Result<()> {
+    self.inner.flush()
+  }
+}
+
 fn normalize_ids(ids: Vec<u64>) -> Vec<u64> {
   let mut set = BTreeSet::new();
   for id in ids {
@@ -115,7 +677,7 @@ fn normalize_ids(ids: Vec<u64>) -> Vec<u64> {
 fn export_lines_passthrough(
   path: &Path,
   ids: &[u64],
-  writer: &mut BufWriter<File>,
+  writer: &mut impl Write,
 ) -> Result<u64, CoreError> {
   let mut wanted_idx = 0usize;
   let mut written = 0u64;
@@ -147,8 +709,9 @@ fn export_lines_passthrough(
 
 fn export_csv_passthrough(
   path: &Path,
+  csv: &CsvDialect,
   ids: &[u64],
-  writer: &mut BufWriter<File>,
+  writer: &mut impl Write,
 ) -> Result<u64, CoreError> {
   let mut wanted_idx = 0usize;
   let mut written = 0u64;
@@ -162,7 +725,7 @@ fn export_csv_passthrough(
       break;
     }
     let mut buf = Vec::new();
-    let (n, _terminated_by_newline) = read_csv_record_bytes(&mut reader, &mut buf)?;
+    let (n, _terminated_by_newline) = read_csv_record_bytes(&mut reader, &mut buf, csv)?;
     if n == 0 {
       break;
     }
@@ -205,7 +768,7 @@ fn normalize_record_line_ending(buf: &mut Vec<u8>) {
 fn export_jsonl_to_json_array(
   path: &Path,
   ids: &[u64],
-  writer: &mut BufWriter<File>,
+  writer: &mut BufWriter<ExportFile>,
 ) -> Result<u64, CoreError> {
   let in_file = File::open(path)?;
   let mut reader = BufReader::new(in_file);
@@ -256,8 +819,8 @@ fn export_jsonl_to_json_array(
 
 // --- CSV -> JSON/JSONL ---
 
-fn export_csv_to_jsonl(path: &Path, ids: &[u64], writer: &mut BufWriter<File>) -> Result<u64, CoreError> {
-  let headers = read_csv_header(path).unwrap_or_default();
+fn export_csv_to_jsonl(path: &Path, csv: &CsvDialect, ids: &[u64], writer: &mut impl Write) -> Result<u64, CoreError> {
+  let headers = read_csv_header(path, TextEncoding::Utf8, csv).unwrap_or_default();
   let in_file = File::open(path)?;
   let mut reader = BufReader::new(in_file);
 
@@ -270,7 +833,7 @@ fn export_csv_to_jsonl(path: &Path, ids: &[u64], writer: &mut BufWriter<File>) -
       break;
     }
     let mut buf = Vec::new();
-    let (n, _terminated_by_newline) = read_csv_record_bytes(&mut reader, &mut buf)?;
+    let (n, _terminated_by_newline) = read_csv_record_bytes(&mut reader, &mut buf, csv)?;
     if n == 0 {
       break;
     }
@@ -280,7 +843,7 @@ fn export_csv_to_jsonl(path: &Path, ids: &[u64], writer: &mut BufWriter<File>) -
     }
 
     // For csv->jsonl: skip header row (line 0) even if selected.
-    if record_no == 0 {
+    if csv.has_header && record_no == 0 {
       wanted_idx += 1;
       record_no += 1;
       continue;
@@ -288,7 +851,7 @@ fn export_csv_to_jsonl(path: &Path, ids: &[u64], writer: &mut BufWriter<File>) -
 
     trim_record_terminator(&mut buf);
     let line = String::from_utf8_lossy(&buf).to_string();
-    let obj = csv_line_to_object(&headers, &line);
+    let obj = csv_line_to_object(&headers, &line, csv);
     let s = serde_json::to_string(&obj)
       .map_err(|e| CoreError::InvalidArg(format!("CSV 转 JSON 失败：{e}")))?;
     writer.write_all(s.as_bytes())?;
@@ -302,8 +865,13 @@ fn export_csv_to_jsonl(path: &Path, ids: &[u64], writer: &mut BufWriter<File>) -
   Ok(written)
 }
 
-fn export_csv_to_json(path: &Path, ids: &[u64], writer: &mut BufWriter<File>) -> Result<u64, CoreError> {
-  let headers = read_csv_header(path).unwrap_or_default();
+fn export_csv_to_json(
+  path: &Path,
+  csv: &CsvDialect,
+  ids: &[u64],
+  writer: &mut BufWriter<ExportFile>,
+) -> Result<u64, CoreError> {
+  let headers = read_csv_header(path, TextEncoding::Utf8, csv).unwrap_or_default();
   let in_file = File::open(path)?;
   let mut reader = BufReader::new(in_file);
 
@@ -319,7 +887,7 @@ fn export_csv_to_json(path: &Path, ids: &[u64], writer: &mut BufWriter<File>) ->
       break;
     }
     let mut buf = Vec::new();
-    let (n, _terminated_by_newline) = read_csv_record_bytes(&mut reader, &mut buf)?;
+    let (n, _terminated_by_newline) = read_csv_record_bytes(&mut reader, &mut buf, csv)?;
     if n == 0 {
       break;
     }
@@ -328,7 +896,7 @@ fn export_csv_to_json(path: &Path, ids: &[u64], writer: &mut BufWriter<File>) ->
       continue;
     }
 
-    if record_no == 0 {
+    if csv.has_header && record_no == 0 {
       wanted_idx += 1;
       record_no += 1;
       continue;
@@ -336,7 +904,7 @@ fn export_csv_to_json(path: &Path, ids: &[u64], writer: &mut BufWriter<File>) ->
 
     trim_record_terminator(&mut buf);
     let line = String::from_utf8_lossy(&buf).to_string();
-    let obj = csv_line_to_object(&headers, &line);
+    let obj = csv_line_to_object(&headers, &line, csv);
     let s = serde_json::to_string(&obj)
       .map_err(|e| CoreError::InvalidArg(format!("CSV 转 JSON 失败：{e}")))?;
 
@@ -361,108 +929,6 @@ fn export_csv_to_json(path: &Path, ids: &[u64], writer: &mut BufWriter<File>) ->
   Ok(written)
 }
 
-fn read_csv_header(path: &Path) -> Result<Vec<String>, CoreError> {
-  let file = File::open(path)?;
-  let mut reader = BufReader::new(file);
-  let mut buf = Vec::new();
-  let (n, _terminated_by_newline) = read_csv_record_bytes(&mut reader, &mut buf)?;
-  if n == 0 {
-    return Ok(vec![]);
-  }
-  trim_record_terminator(&mut buf);
-  let mut line = String::from_utf8_lossy(&buf).to_string();
-  // Strip UTF-8 BOM if present
-  if line.starts_with('\u{feff}') {
-    line = line.trim_start_matches('\u{feff}').to_string();
-  }
-  let mut headers = parse_csv_line(&line);
-  for (i, h) in headers.iter_mut().enumerate() {
-    if h.trim().is_empty() {
-      *h = format!("col_{i}");
-    }
-  }
-  if headers.is_empty() {
-    headers.push("col_0".to_string());
-  }
-  Ok(headers)
-}
-
-/// Read a single CSV record into `out`, allowing embedded newlines inside quoted fields.
-///
-/// Returns:
-/// - bytes consumed from reader (including the record terminator if present)
-/// - whether the record ended due to a newline terminator (as opposed to EOF)
-fn read_csv_record_bytes<R: BufRead>(reader: &mut R, out: &mut Vec<u8>) -> Result<(usize, bool), CoreError> {
-  out.clear();
-
-  let mut in_quotes = false;
-  let mut at_field_start = true;
-  let mut consumed = 0usize;
-  let mut terminated_by_newline = false;
-
-  loop {
-    let mut chunk = Vec::new();
-    let n = reader.read_until(b'\n', &mut chunk)?;
-    if n == 0 {
-      break;
-    }
-    consumed += n;
-
-    let scan_slice = if chunk.ends_with(b"\n") {
-      &chunk[..chunk.len().saturating_sub(1)]
-    } else {
-      chunk.as_slice()
-    };
-    update_csv_quote_state(&mut in_quotes, &mut at_field_start, scan_slice);
-
-    out.extend_from_slice(&chunk);
-
-    if chunk.ends_with(b"\n") && !in_quotes {
-      terminated_by_newline = true;
-      break;
-    }
-    if !chunk.ends_with(b"\n") {
-      break;
-    }
-  }
-
-  Ok((consumed, terminated_by_newline))
-}
-
-fn update_csv_quote_state(in_quotes: &mut bool, at_field_start: &mut bool, bytes: &[u8]) {
-  let mut i = 0usize;
-  while i < bytes.len() {
-    let b = bytes[i];
-
-    if *in_quotes {
-      if b == b'"' {
-        if i + 1 < bytes.len() && bytes[i + 1] == b'"' {
-          i += 2;
-          continue;
-        }
-        *in_quotes = false;
-      }
-      i += 1;
-      continue;
-    }
-
-    match b {
-      b',' => {
-        *at_field_start = true;
-      }
-      b' ' | b'\t' if *at_field_start => {}
-      b'"' if *at_field_start => {
-        *in_quotes = true;
-        *at_field_start = false;
-      }
-      _ => {
-        *at_field_start = false;
-      }
-    }
-    i += 1;
-  }
-}
-
 fn trim_record_terminator(buf: &mut Vec<u8>) {
   if buf.ends_with(b"\n") {
     buf.pop();
@@ -472,56 +938,13 @@ fn trim_record_terminator(buf: &mut Vec<u8>) {
   }
 }
 
-fn parse_csv_line(line: &str) -> Vec<String> {
-  let mut out: Vec<String> = Vec::new();
-  let mut cur = String::new();
-  let mut in_quotes = false;
-  let mut chars = line.chars().peekable();
-
-  while let Some(ch) = chars.next() {
-    match ch {
-      '"' => {
-        if in_quotes && matches!(chars.peek(), Some('"')) {
-          cur.push('"');
-          let _ = chars.next();
-        } else {
-          in_quotes = !in_quotes;
-        }
-      }
-      ',' if !in_quotes => {
-        out.push(cur);
-        cur = String::new();
-      }
-      _ => cur.push(ch),
-    }
-  }
-  out.push(cur);
-  out
-}
-
-fn csv_line_to_object(headers: &[String], line: &str) -> Value {
-  let fields = parse_csv_line(line);
-  let mut obj = Map::new();
-  for (i, h) in headers.iter().enumerate() {
-    let v = fields.get(i).cloned().unwrap_or_default();
-    obj.insert(h.clone(), Value::String(v));
-  }
-  if fields.len() > headers.len() {
-    obj.insert(
-      "__extra__".to_string(),
-      Value::Array(fields[headers.len()..].iter().cloned().map(Value::String).collect()),
-    );
-  }
-  Value::Object(obj)
-}
-
 // --- JSON (.json) -> JSON/JSONL ---
 
-fn export_json_to_jsonl(path: &Path, ids: &[u64], writer: &mut BufWriter<File>) -> Result<u64, CoreError> {
+fn export_json_to_jsonl(path: &Path, ids: &[u64], writer: &mut BufWriter<ExportFile>) -> Result<u64, CoreError> {
   export_json_stream(path, ids, ExportFormat::Jsonl, writer)
 }
 
-fn export_json_to_json(path: &Path, ids: &[u64], writer: &mut BufWriter<File>) -> Result<u64, CoreError> {
+fn export_json_to_json(path: &Path, ids: &[u64], writer: &mut BufWriter<ExportFile>) -> Result<u64, CoreError> {
   export_json_stream(path, ids, ExportFormat::Json, writer)
 }
 
@@ -529,7 +952,7 @@ fn export_json_stream(
   path: &Path,
   ids: &[u64],
   out_format: ExportFormat,
-  writer: &mut BufWriter<File>,
+  writer: &mut BufWriter<ExportFile>,
 ) -> Result<u64, CoreError> {
   let mut f = File::open(path)?;
   f.seek(SeekFrom::Start(0))?;
@@ -633,7 +1056,7 @@ fn export_json_stream(
 
 fn scan_one_json_value(
   reader: &mut BufReader<File>,
-  mut out: Option<&mut BufWriter<File>>,
+  mut out: Option<&mut BufWriter<ExportFile>>,
 ) -> Result<(), CoreError> {
   let mut in_string = false;
   let mut escape = false;
@@ -681,10 +1104,8 @@ fn scan_one_json_value(
     match b {
       b'"' => in_string = true,
       b'{' | b'[' => depth += 1,
-      b'}' | b']' => {
-        if depth > 0 {
-          depth -= 1;
-        }
+      b'}' | b']' if depth > 0 => {
+        depth -= 1;
       }
       _ => {}
     }
@@ -704,32 +1125,12 @@ fn scan_one_json_value(
   Ok(())
 }
 
-fn export_parquet_to_jsonl(path: &Path, ids: &[u64], writer: &mut BufWriter<File>) -> Result<u64, CoreError> {
-  export_parquet(path, ids, ExportFormat::Jsonl, writer)
-}
-
-fn export_parquet_to_json(path: &Path, ids: &[u64], writer: &mut BufWriter<File>) -> Result<u64, CoreError> {
-  export_parquet(path, ids, ExportFormat::Json, writer)
-}
-
 fn export_parquet(
-  path: &Path,
+  conn: &ParquetConn,
   ids: &[u64],
   out_format: ExportFormat,
-  writer: &mut BufWriter<File>,
+  writer: &mut impl Write,
 ) -> Result<u64, CoreError> {
-  let path_str = path
-    .to_str()
-    .ok_or_else(|| CoreError::InvalidArg("invalid path encoding".into()))?;
-
-  let conn = duckdb::Connection::open_in_memory()
-    .map_err(|e| CoreError::InvalidArg(format!("DuckDB 初始化失败：{e}")))?;
-  let _ = conn.execute_batch("LOAD parquet;");
-
-  let mut stmt = conn
-    .prepare("SELECT * FROM read_parquet(?) LIMIT 1 OFFSET ?")
-    .map_err(|e| CoreError::InvalidArg(format!("DuckDB 准备语句失败：{e}")))?;
-
   if matches!(out_format, ExportFormat::Json) {
     writer.write_all(b"[")?;
   }
@@ -737,36 +1138,17 @@ fn export_parquet(
   let mut written = 0u64;
 
   for row_idx in ids {
-    let offset_i64 = i64::try_from(*row_idx)
-      .map_err(|_| CoreError::InvalidArg(format!("invalid row index for parquet: {row_idx}")))?;
-
-    let mut rows = stmt
-      .query(duckdb::params![path_str, offset_i64])
-      .map_err(|e| CoreError::InvalidArg(format!("Parquet 读取失败：{e}")))?;
-    let Some(row) = rows
-      .next()
-      .map_err(|e| CoreError::InvalidArg(format!("Parquet 读取失败：{e}")))?
-    else {
+    let Some(values) = conn.row_values(*row_idx)? else {
       // out of range -> skip
       continue;
     };
-
-    let col_count = row.as_ref().column_count();
-    let mut obj = Map::with_capacity(col_count);
-    for i in 0..col_count {
-      let key = row
-        .as_ref()
-        .column_name(i)
-        .map(|s| s.to_string())
-        .unwrap_or_else(|_| format!("col_{i}"));
-      let v: duckdb::types::Value = row
-        .get(i)
-        .map_err(|e| CoreError::InvalidArg(format!("Parquet 读取失败：{e}")))?;
-      obj.insert(key, duckdb_value_to_json(&v));
-    }
+    let obj: Map<String, Value> = values
+      .into_iter()
+      .map(|(key, v)| (key, duckdb_value_to_json(&v)))
+      .collect();
     let value = Value::Object(obj);
     let line = serde_json::to_string(&value)
-      .map_err(|e| CoreError::InvalidArg(format!("Parquet 行序列化失败：{e}")))?;
+      .map_err(|e| CoreError::Duckdb(format!("Parquet 行序列化失败：{e}")))?;
 
     match out_format {
       ExportFormat::Jsonl => {
@@ -820,7 +1202,34 @@ fn duckdb_value_to_json(v: &duckdb::types::Value) -> Value {
       .map(Value::Number)
       .unwrap_or_else(|| Value::String(x.to_string())),
 
-    V::Text(s) => Value::String(s.clone()),
+    V::Text(s) | V::Enum(s) => Value::String(s.clone()),
+
+    // Exact text rather than numbers JSON readers would round to f64.
+    V::HugeInt(x) => Value::String(x.to_string()),
+    V::Decimal(d) => Value::String(d.to_string()),
+
+    V::List(xs) | V::Array(xs) => Value::Array(xs.iter().map(duckdb_value_to_json).collect()),
+    V::Struct(members) => Value::Object(members.iter().map(|(k, v)| (k.clone(), duckdb_value_to_json(v))).collect()),
+    V::Map(entries) => Value::Object(
+      entries
+        .iter()
+        .map(|(k, v)| {
+          let key = match k {
+            V::Text(s) => s.clone(),
+            other => match duckdb_value_to_json(other) {
+              Value::String(s) => s,
+              json => json.to_string(),
+            },
+          };
+          (key, duckdb_value_to_json(v))
+        })
+        .collect(),
+    ),
+    V::Union(v) => duckdb_value_to_json(v),
+
+    V::Date32(days) => Value::String(crate::formats::iso_date(*days)),
+    V::Time64(unit, v) => Value::String(crate::formats::iso_time(*unit, *v)),
+    V::Timestamp(unit, v) => Value::String(crate::formats::iso_timestamp(*unit, *v, None)),
 
     other => Value::String(format!("{other:?}")),
   }
@@ -1,29 +1,58 @@
 use std::{
+  collections::HashMap,
   fs::File,
-  io::{BufRead, BufReader, Seek, SeekFrom},
+  io::{BufRead, BufReader, Read, Seek, SeekFrom},
   path::Path,
+  sync::OnceLock,
 };
 
+use parking_lot::Mutex;
+use regex::{bytes, Regex};
 use serde_json::{Map, Value};
 
 use crate::{
   cursor::Cursor,
+  encoding,
   engine::CoreError,
   formats::LinesPageInternal,
-  models::{Record, RecordMeta},
+  models::{CsvDialect, Record, RecordMeta, TextEncoding},
+  progress::ScanProgress,
 };
 
 /// CSV paging implementation:
 /// - Record-based streaming (supports multi-line quoted cells).
 /// - Additionally provides `Record.raw` as a JSON string, whose keys are the header row fields.
+/// - `columns` (header names) restricts preview (in that order) and raw to those columns.
+/// - Without `dialect.has_header`, line 0 is a data row like the others.
+/// - Rows whose field count differs from the header's, or that end inside quotes, carry a
+///   `Record.warning` (extra cells still go to `__extra__`).
+/// - Cells longer than `raw_max_chars` are cut (ending in `…`) in `raw`, which then names their
+///   columns in `__truncated__` (see `read_csv_cell` for the whole cell).
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn read_csv_page(
   path: &Path,
   cursor: Cursor,
   page_size: usize,
   preview_max_chars: usize,
-  _raw_max_chars: usize, // unused: CSV always shows full content in detail view
+  raw_max_chars: usize,
+  columns: Option<&[String]>,
+  encoding: TextEncoding,
+  dialect: &CsvDialect,
 ) -> Result<(LinesPageInternal, Option<Cursor>), CoreError> {
-  let headers = read_csv_header(path).unwrap_or_default();
+  let headers = read_csv_header(path, encoding, dialect).unwrap_or_default();
+  let projection = columns
+    .map(|cols| {
+      cols
+        .iter()
+        .map(|c| {
+          headers
+            .iter()
+            .position(|h| h == c)
+            .ok_or_else(|| CoreError::InvalidArg(format!("unknown column: {c}")))
+        })
+        .collect::<Result<Vec<usize>, CoreError>>()
+    })
+    .transpose()?;
 
   let mut file = File::open(path)?;
   let file_len = file.metadata().ok().map(|m| m.len()).unwrap_or(0);
@@ -38,45 +67,97 @@ pub(crate) fn read_csv_page(
 
   let mut records = Vec::with_capacity(page_size);
   let mut offset = cursor.offset;
-  let mut line_no = cursor.line;
 
-  for _ in 0..page_size {
+  for line_no in (cursor.line..).take(page_size) {
     let start_offset = offset;
     let mut buf = Vec::new();
-    let (n, _terminated_by_newline) = read_csv_record_bytes(&mut reader, &mut buf)?;
+    let (n, terminated_by_newline) = read_csv_record_bytes(&mut reader, &mut buf, dialect)?;
     if n == 0 {
       break;
     }
     offset += n as u64;
+    let unterminated = !terminated_by_newline && ends_in_quotes(&buf, dialect);
 
     // Trim the *record terminator* (CRLF/LF) only.
     trim_record_terminator(&mut buf);
 
-    let line = String::from_utf8_lossy(&buf).to_string();
+    let mut line = encoding::decode(&buf, encoding).into_owned();
+    let is_header = dialect.has_header && line_no == 0;
+    if !is_header && start_offset == 0 {
+      // A UTF-8 BOM is not part of the first data cell.
+      line = line.trim_start_matches('\u{feff}').to_string();
+    }
+    let mut fields = (!is_header).then(|| parse_csv_line(&line, dialect));
+    let warning = match fields.as_ref().map(Vec::len) {
+      _ if unterminated => Some("unterminated quoted field: the record runs to the end of the file".to_string()),
+      Some(found) if found != headers.len() => Some(format!("expected {} fields, found {found}", headers.len())),
+      _ => None,
+    };
+    if let Some(projection) = projection.as_deref() {
+      // The header row shows the (normalized) selected header names.
+      let picked: Vec<String> = match &fields {
+        Some(all) => projection.iter().map(|&i| all.get(i).cloned().unwrap_or_default()).collect(),
+        None => projection.iter().map(|&i| headers[i].clone()).collect(),
+      };
+      line = picked
+        .iter()
+        .map(|f| quote_csv_field(f, dialect))
+        .collect::<Vec<_>>()
+        .join(&dialect.delimiter);
+      fields = fields.map(|_| picked);
+    }
     let preview = truncate_chars(&line, preview_max_chars);
 
     // Provide a JSON-like raw for details:
     // - header line keeps original raw text (backward compatible with existing behavior/tests)
     // - data line becomes {"colA":"...", "colB":"..."} with keys from header row
-    // For CSV, always show full content in detail view (no truncation).
-    // These files typically have reasonable line/cell lengths.
-    let raw = if line_no == 0 {
+    // Cells are shown in full up to `raw_max_chars`; longer ones are cut and listed.
+    // `columns` carries the same cells (the header row: the names) as text for a grid.
+    let mut columns = Vec::new();
+    let raw = if is_header {
+      columns = match projection.as_deref() {
+        Some(projection) => projection.iter().map(|&i| headers[i].clone()).collect(),
+        None => headers.clone(),
+      };
       Some(line.clone())
     } else {
-      let fields = parse_csv_line(&line);
+      let fields = fields.unwrap_or_default();
       let mut obj = Map::new();
-      for (i, h) in headers.iter().enumerate() {
-        let v = fields.get(i).cloned().unwrap_or_default();
-        obj.insert(h.clone(), Value::String(v));
+      let mut truncated = Vec::new();
+      let mut cell = |h: &String, v: &str| {
+        let value = if v.len() > raw_max_chars && v.chars().count() > raw_max_chars {
+          truncated.push(Value::String(h.clone()));
+          let cut = truncate_chars(v, raw_max_chars);
+          columns.push(cut.clone());
+          Value::String(cut)
+        } else {
+          columns.push(v.to_string());
+          csv_cell_value(v, dialect)
+        };
+        obj.insert(h.clone(), value);
+      };
+      match projection.as_deref() {
+        Some(projection) => {
+          for (&i, v) in projection.iter().zip(fields.iter()) {
+            cell(&headers[i], v);
+          }
+        }
+        None => {
+          for (i, h) in headers.iter().enumerate() {
+            cell(h, fields.get(i).map(String::as_str).unwrap_or_default());
+          }
+        }
+      }
+      if !truncated.is_empty() {
+        obj.insert("__truncated__".to_string(), Value::Array(truncated));
       }
-      if fields.len() > headers.len() {
+      if projection.is_none() && fields.len() > headers.len() {
         obj.insert(
           "__extra__".to_string(),
           Value::Array(
             fields[headers.len()..]
               .iter()
-              .cloned()
-              .map(Value::String)
+              .map(|v| csv_cell_value(v, dialect))
               .collect(),
           ),
         );
@@ -94,9 +175,12 @@ pub(crate) fn read_csv_page(
         line_no,
         byte_offset: start_offset,
         byte_len: n as u64,
+        part: None,
       }),
+      derived: None,
+      warning,
+      columns: Some(columns),
     });
-    line_no += 1;
   }
 
   let reached_eof = records.is_empty() || offset >= file_len;
@@ -118,24 +202,121 @@ pub(crate) fn read_csv_page(
   ))
 }
 
-fn read_csv_header(path: &Path) -> Result<Vec<String>, CoreError> {
+/// Count CSV data records (header row excluded), honoring multi-line quoted cells.
+///
+/// Returns `None` if `should_stop` fired before EOF.
+pub(crate) fn count_csv_records(
+  path: &Path,
+  dialect: &CsvDialect,
+  should_stop: impl Fn() -> bool,
+  mut on_progress: impl FnMut(ScanProgress),
+) -> Result<Option<u64>, CoreError> {
+  let file = File::open(path)?;
+  let file_len = file.metadata().ok().map(|m| m.len()).unwrap_or(0);
+  let mut reader = BufReader::with_capacity(1024 * 1024, file);
+  let mut buf = Vec::new();
+  let mut records = 0u64;
+  let mut read = 0u64;
+  loop {
+    // Polling per record is cheap next to the read itself.
+    if should_stop() {
+      return Ok(None);
+    }
+    let (n, _terminated_by_newline) = read_csv_record_bytes(&mut reader, &mut buf, dialect)?;
+    if n == 0 {
+      break;
+    }
+    records += 1;
+    read += n as u64;
+    if records.is_multiple_of(4096) {
+      on_progress(ScanProgress::new(read, file_len, read, records.saturating_sub(1)));
+    }
+  }
+  Ok(Some(records.saturating_sub(1)))
+}
+
+/// Walk CSV records (header included) starting at `offset` (a record start): `on_record(byte_len)`
+/// is called per record until it returns `false` or EOF.
+pub(crate) fn walk_csv_records(
+  path: &Path,
+  dialect: &CsvDialect,
+  offset: u64,
+  mut on_record: impl FnMut(u64) -> bool,
+) -> Result<(), CoreError> {
+  let mut file = File::open(path)?;
+  file.seek(SeekFrom::Start(offset))?;
+  let mut reader = BufReader::with_capacity(1024 * 1024, file);
+  let mut buf = Vec::new();
+  loop {
+    let (n, _terminated_by_newline) = read_csv_record_bytes(&mut reader, &mut buf, dialect)?;
+    if n == 0 || !on_record(n as u64) {
+      return Ok(());
+    }
+  }
+}
+
+/// Delimiters `sniff_csv_delimiter` chooses from; ties go to the earlier one.
+const DELIMITER_CANDIDATES: [char; 4] = [',', ';', '\t', '|'];
+/// Records `sniff_csv_delimiter` compares.
+const SNIFF_RECORDS: usize = 20;
+
+/// The candidate delimiter that splits the first records of `path` most consistently: the most
+/// records with as many cells as the first one (at least 2), then the most cells. `,` if none
+/// splits them.
+pub(crate) fn sniff_csv_delimiter(path: &Path) -> Result<char, CoreError> {
+  let mut best = (',', 0usize, 0usize);
+  for delimiter in DELIMITER_CANDIDATES {
+    let dialect = CsvDialect {
+      delimiter: delimiter.to_string(),
+      ..CsvDialect::default()
+    };
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut buf = Vec::new();
+    let mut cells = Vec::new();
+    while cells.len() < SNIFF_RECORDS {
+      let (n, _terminated_by_newline) = read_csv_record_bytes(&mut reader, &mut buf, &dialect)?;
+      if n == 0 {
+        break;
+      }
+      trim_record_terminator(&mut buf);
+      if !buf.is_empty() {
+        cells.push(parse_csv_line(&String::from_utf8_lossy(&buf), &dialect).len());
+      }
+    }
+    let Some(&first) = cells.first() else {
+      break;
+    };
+    let agreeing = cells.iter().filter(|&&c| c == first).count();
+    if first > 1 && (agreeing, first) > (best.1, best.2) {
+      best = (delimiter, agreeing, first);
+    }
+  }
+  Ok(best.0)
+}
+
+/// Without `dialect.has_header`: `col_<i>` for each field of the first row.
+pub(crate) fn read_csv_header(
+  path: &Path,
+  encoding: TextEncoding,
+  dialect: &CsvDialect,
+) -> Result<Vec<String>, CoreError> {
   let file = File::open(path)?;
   let mut reader = BufReader::new(file);
   let mut buf = Vec::new();
-  let (n, _terminated_by_newline) = read_csv_record_bytes(&mut reader, &mut buf)?;
+  let (n, _terminated_by_newline) = read_csv_record_bytes(&mut reader, &mut buf, dialect)?;
   if n == 0 {
     return Ok(vec![]);
   }
   trim_record_terminator(&mut buf);
-  let mut line = String::from_utf8_lossy(&buf).to_string();
+  let mut line = encoding::decode(&buf, encoding).into_owned();
   // Strip UTF-8 BOM if present
   if line.starts_with('\u{feff}') {
     line = line.trim_start_matches('\u{feff}').to_string();
   }
-  let mut headers = parse_csv_line(&line);
+  let mut headers = parse_csv_line(&line, dialect);
   // Normalize empty headers to generic names.
   for (i, h) in headers.iter_mut().enumerate() {
-    if h.trim().is_empty() {
+    if !dialect.has_header || h.trim().is_empty() {
       *h = format!("col_{i}");
     }
   }
@@ -148,17 +329,21 @@ fn read_csv_header(path: &Path) -> Result<Vec<String>, CoreError> {
 
 /// Read a single CSV *record* into `out`, streaming from `reader`.
 ///
-/// Unlike `read_until('\n')`, this treats newlines inside quoted fields as part of the record,
-/// and only ends the record when it sees a line break while **not** inside quotes.
+/// Unlike `read_until('\n')`, this treats newlines inside quoted fields (or after
+/// `dialect.escape`) as part of the record, and only ends the record when it sees a line break
+/// while **not** inside quotes.
 ///
 /// Returns:
 /// - bytes consumed from reader (including the record terminator if present)
 /// - whether the record ended due to a newline terminator (as opposed to EOF)
-fn read_csv_record_bytes<R: BufRead>(reader: &mut R, out: &mut Vec<u8>) -> Result<(usize, bool), CoreError> {
+pub(crate) fn read_csv_record_bytes<R: BufRead>(
+  reader: &mut R,
+  out: &mut Vec<u8>,
+  dialect: &CsvDialect,
+) -> Result<(usize, bool), CoreError> {
   out.clear();
 
-  let mut in_quotes = false;
-  let mut at_field_start = true;
+  let mut state = QuoteState::default();
   let mut consumed = 0usize;
   let mut terminated_by_newline = false;
 
@@ -176,11 +361,16 @@ fn read_csv_record_bytes<R: BufRead>(reader: &mut R, out: &mut Vec<u8>) -> Resul
     } else {
       chunk.as_slice()
     };
-    update_csv_quote_state(&mut in_quotes, &mut at_field_start, scan_slice);
+    update_csv_quote_state(&mut state, scan_slice, dialect);
 
     out.extend_from_slice(&chunk);
 
-    if chunk.ends_with(b"\n") && !in_quotes {
+    if chunk.ends_with(b"\n") && state.escaped {
+      // An escaped line break is cell content.
+      update_csv_quote_state(&mut state, b"\n", dialect);
+      continue;
+    }
+    if chunk.ends_with(b"\n") && !state.in_quotes {
       terminated_by_newline = true;
       break;
     }
@@ -194,42 +384,194 @@ fn read_csv_record_bytes<R: BufRead>(reader: &mut R, out: &mut Vec<u8>) -> Resul
   Ok((consumed, terminated_by_newline))
 }
 
-fn update_csv_quote_state(in_quotes: &mut bool, at_field_start: &mut bool, bytes: &[u8]) {
+/// The whole of cell `index` of the record at `byte_offset` (`byte_len` bytes, as in its
+/// `RecordMeta`), split like `parse_csv_line` does but streamed byte by byte: only that cell is
+/// kept in memory, not its record. Empty if the record has fewer cells.
+pub(crate) fn read_csv_cell(
+  path: &Path,
+  byte_offset: u64,
+  byte_len: u64,
+  index: usize,
+  encoding: TextEncoding,
+  dialect: &CsvDialect,
+) -> Result<String, CoreError> {
+  let mut file = File::open(path)?;
+  file.seek(SeekFrom::Start(byte_offset))?;
+  let mut reader = BufReader::new(file.take(byte_len));
+  if dialect.delimiter_regex.is_some() {
+    // A pattern can't be matched a byte at a time: split the whole record instead.
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf)?;
+    trim_record_terminator(&mut buf);
+    let line = encoding::decode(&buf, encoding);
+    let line = if byte_offset == 0 { line.trim_start_matches('\u{feff}') } else { &line };
+    return Ok(parse_csv_line(line, dialect).into_iter().nth(index).unwrap_or_default());
+  }
+  let delimiter = dialect.delimiter.as_bytes();
+  let quote = dialect.quote as u8;
+  let escape = dialect.escape.map(|c| c as u8);
+
+  let mut out = Vec::new();
+  let mut field = 0usize;
+  let mut in_quotes = false;
+  let mut escaped = false;
+  // A quote inside quotes: doubled it stands for itself, else it closed them.
+  let mut quote_seen = false;
+  // Bytes read so far of what may be a (multi-byte) delimiter.
+  let mut partial = Vec::with_capacity(delimiter.len());
+  let mut terminated = false;
+  for b in reader.bytes() {
+    let b = b?;
+    let mut push = |b: u8, field: usize| {
+      if field == index {
+        out.push(b);
+      }
+    };
+    if escaped {
+      escaped = false;
+      push(b, field);
+      continue;
+    }
+    if quote_seen {
+      quote_seen = false;
+      if b == quote {
+        push(b, field);
+        continue;
+      }
+      in_quotes = false;
+    }
+    if Some(b) == escape {
+      partial.drain(..).for_each(|p| push(p, field));
+      escaped = true;
+      continue;
+    }
+    if b == quote {
+      partial.drain(..).for_each(|p| push(p, field));
+      if in_quotes {
+        quote_seen = true;
+      } else {
+        in_quotes = true;
+      }
+      continue;
+    }
+    if in_quotes {
+      push(b, field);
+      continue;
+    }
+    if b == b'\n' {
+      terminated = true;
+      break;
+    }
+    partial.push(b);
+    while !delimiter.starts_with(&partial) {
+      push(partial.remove(0), field);
+    }
+    if partial == delimiter {
+      partial.clear();
+      field += 1;
+      if field > index {
+        break;
+      }
+    }
+  }
+  if escaped && field == index {
+    out.extend(escape);
+  }
+  if field == index {
+    out.extend(partial);
+  }
+  if terminated && field == index && out.ends_with(b"\r") {
+    // CRLF terminator.
+    out.pop();
+  }
+  if index == 0 && byte_offset == 0 && out.starts_with(b"\xef\xbb\xbf") {
+    // A UTF-8 BOM is not part of the first cell.
+    out.drain(..3);
+  }
+  Ok(encoding::decode(&out, encoding).into_owned())
+}
+
+/// How far `update_csv_quote_state` got into a record.
+struct QuoteState {
+  in_quotes: bool,
+  at_field_start: bool,
+  /// The last byte was `CsvDialect::escape`, so the next one is taken literally.
+  escaped: bool,
+}
+
+impl Default for QuoteState {
+  fn default() -> Self {
+    Self {
+      in_quotes: false,
+      at_field_start: true,
+      escaped: false,
+    }
+  }
+}
+
+/// `dialect.delimiter` / `quote` / `escape` are ASCII without line breaks (see
+/// `CoreEngine::set_csv_dialect`), so bytes compare against them directly and a delimiter never
+/// spans two of the lines `bytes` come in.
+fn update_csv_quote_state(state: &mut QuoteState, bytes: &[u8], dialect: &CsvDialect) {
+  let delimiter = CellDelimiter::of(dialect);
+  let quote = dialect.quote as u8;
+  let escape = dialect.escape.map(|c| c as u8);
   let mut i = 0usize;
   while i < bytes.len() {
     let b = bytes[i];
 
-    if *in_quotes {
-      if b == b'"' {
+    if state.escaped {
+      state.escaped = false;
+      state.at_field_start = false;
+      i += 1;
+      continue;
+    }
+    if Some(b) == escape {
+      state.escaped = true;
+      i += 1;
+      continue;
+    }
+
+    if state.in_quotes {
+      if b == quote {
         // Escaped quote inside quoted field: ""
-        if i + 1 < bytes.len() && bytes[i + 1] == b'"' {
+        if i + 1 < bytes.len() && bytes[i + 1] == quote {
           i += 2;
           continue;
         }
-        *in_quotes = false;
+        state.in_quotes = false;
       }
       i += 1;
       continue;
     }
 
+    if let Some(len) = delimiter.starts(&bytes[i..]) {
+      state.at_field_start = true;
+      i += len;
+      continue;
+    }
     match b {
-      b',' => {
-        *at_field_start = true;
-      }
       // Allow leading spaces/tabs before an opening quote.
-      b' ' | b'\t' if *at_field_start => {}
-      b'"' if *at_field_start => {
-        *in_quotes = true;
-        *at_field_start = false;
+      b' ' | b'\t' if state.at_field_start => {}
+      _ if b == quote && state.at_field_start => {
+        state.in_quotes = true;
+        state.at_field_start = false;
       }
       _ => {
-        *at_field_start = false;
+        state.at_field_start = false;
       }
     }
     i += 1;
   }
 }
 
+/// Whether a record read by `read_csv_record_bytes` stopped inside a quoted field (only at EOF).
+fn ends_in_quotes(record: &[u8], dialect: &CsvDialect) -> bool {
+  let mut state = QuoteState::default();
+  update_csv_quote_state(&mut state, record, dialect);
+  state.in_quotes
+}
+
 fn trim_record_terminator(buf: &mut Vec<u8>) {
   // Trim LF
   if buf.ends_with(b"\n") {
@@ -241,30 +583,110 @@ fn trim_record_terminator(buf: &mut Vec<u8>) {
   }
 }
 
+/// A data row as the JSON object paging shows in `raw` (cells keyed by header).
+pub(crate) fn csv_line_to_object(headers: &[String], line: &str, dialect: &CsvDialect) -> Value {
+  let fields = parse_csv_line(line, dialect);
+  let mut obj = Map::new();
+  for (i, h) in headers.iter().enumerate() {
+    obj.insert(h.clone(), csv_cell_value(fields.get(i).map(String::as_str).unwrap_or_default(), dialect));
+  }
+  if fields.len() > headers.len() {
+    obj.insert(
+      "__extra__".to_string(),
+      Value::Array(fields[headers.len()..].iter().map(|v| csv_cell_value(v, dialect)).collect()),
+    );
+  }
+  Value::Object(obj)
+}
+
+/// A cell as a JSON value: a string, or with `dialect.infer_types` the `null` / boolean / number
+/// it reads as. Numbers must be written as in JSON (no `+`, leading zeros or spaces, so IDs
+/// like `007` stay strings), and integers too long for 64 bits stay strings rather than lose
+/// digits.
+pub(crate) fn csv_cell_value(cell: &str, dialect: &CsvDialect) -> Value {
+  if !dialect.infer_types {
+    return Value::String(cell.to_string());
+  }
+  if cell.is_empty() || cell.eq_ignore_ascii_case("null") {
+    return Value::Null;
+  }
+  if cell.eq_ignore_ascii_case("true") || cell.eq_ignore_ascii_case("false") {
+    return Value::Bool(cell.eq_ignore_ascii_case("true"));
+  }
+  if cell.trim() != cell {
+    return Value::String(cell.to_string());
+  }
+  match serde_json::from_str::<serde_json::Number>(cell) {
+    Ok(n) if n.is_f64() && !cell.contains(['.', 'e', 'E']) => Value::String(cell.to_string()),
+    Ok(n) => Value::Number(n),
+    Err(_) => Value::String(cell.to_string()),
+  }
+}
+
+/// The inverse of `csv_line_to_object`: cells in header order (missing ones empty), then any
+/// `__extra__` cells. Keys that are not headers are rejected so edits can't silently drop them.
+pub(crate) fn object_to_csv_line(
+  headers: &[String],
+  obj: &Map<String, Value>,
+  dialect: &CsvDialect,
+) -> Result<String, CoreError> {
+  if let Some(key) = obj.keys().find(|k| *k != "__extra__" && !headers.contains(k)) {
+    return Err(CoreError::InvalidArg(format!("unknown CSV column: {key}")));
+  }
+  let cell = |v: &Value| match v {
+    Value::Null => String::new(),
+    Value::String(s) => s.clone(),
+    other => other.to_string(),
+  };
+  let mut cells: Vec<String> = headers.iter().map(|h| obj.get(h).map(cell).unwrap_or_default()).collect();
+  match obj.get("__extra__") {
+    Some(Value::Array(extra)) => cells.extend(extra.iter().map(cell)),
+    Some(_) => return Err(CoreError::InvalidArg("__extra__ must be an array".into())),
+    None => {}
+  }
+  Ok(
+    cells
+      .iter()
+      .map(|c| quote_csv_field(c, dialect))
+      .collect::<Vec<_>>()
+      .join(&dialect.delimiter),
+  )
+}
+
 /// Best-effort single-line CSV parser:
-/// - Supports quotes and escaped quotes ("")
+/// - Supports quotes and escaped quotes (doubled `dialect.quote`)
+/// - `dialect.escape` makes the next character literal, in or out of quotes
 /// - Works fine with multi-line records as long as the record text is provided in full
-fn parse_csv_line(line: &str) -> Vec<String> {
+fn parse_csv_line(line: &str, dialect: &CsvDialect) -> Vec<String> {
   let mut out: Vec<String> = Vec::new();
   let mut cur = String::new();
   let mut in_quotes = false;
-  let mut chars = line.chars().peekable();
+  let delimiter = CellDelimiter::of(dialect);
+  let mut rest = line;
 
-  while let Some(ch) = chars.next() {
+  while let Some(ch) = rest.chars().next() {
+    let at_delimiter = (!in_quotes).then(|| delimiter.starts(rest.as_bytes())).flatten();
+    if let Some(len) = at_delimiter.filter(|&len| rest.is_char_boundary(len)) {
+      out.push(std::mem::take(&mut cur));
+      rest = &rest[len..];
+      continue;
+    }
+    rest = &rest[ch.len_utf8()..];
     match ch {
-      '"' => {
-        if in_quotes && matches!(chars.peek(), Some('"')) {
+      _ if Some(ch) == dialect.escape => {
+        let next = rest.chars().next();
+        rest = &rest[next.map_or(0, char::len_utf8)..];
+        cur.push(next.unwrap_or(ch));
+      }
+      _ if ch == dialect.quote => {
+        if in_quotes && rest.starts_with(dialect.quote) {
           // Escaped quote
-          cur.push('"');
-          let _ = chars.next();
+          cur.push(ch);
+          rest = &rest[ch.len_utf8()..];
         } else {
           in_quotes = !in_quotes;
         }
       }
-      ',' if !in_quotes => {
-        out.push(cur);
-        cur = String::new();
-      }
       _ => cur.push(ch),
     }
   }
@@ -273,6 +695,26 @@ fn parse_csv_line(line: &str) -> Vec<String> {
   out
 }
 
+/// Quote a cell for a CSV line if it contains a delimiter (or a `delimiter_regex` match), quote,
+/// escape or line break. Quotes (and escapes) inside are written with `dialect.escape` if there
+/// is one, else doubled.
+pub(crate) fn quote_csv_field(s: &str, dialect: &CsvDialect) -> String {
+  let special = |c: char| matches!(c, '\n' | '\r') || c == dialect.quote || Some(c) == dialect.escape;
+  if !s.contains(special) && !s.contains(dialect.delimiter.as_str()) && !CellDelimiter::of(dialect).occurs_in(s) {
+    return s.to_string();
+  }
+  let mut out = String::with_capacity(s.len() + 2);
+  out.push(dialect.quote);
+  for c in s.chars() {
+    if c == dialect.quote || Some(c) == dialect.escape {
+      out.push(dialect.escape.unwrap_or(dialect.quote));
+    }
+    out.push(c);
+  }
+  out.push(dialect.quote);
+  out
+}
+
 fn truncate_chars(s: &str, max: usize) -> String {
   if max == 0 {
     return String::new();
@@ -280,7 +722,7 @@ fn truncate_chars(s: &str, max: usize) -> String {
   let mut out = String::new();
   for (i, ch) in s.chars().enumerate() {
     if i >= max {
-      out.push_str("…");
+      out.push('…');
       break;
     }
     out.push(ch);
@@ -293,3 +735,70 @@ fn sanitize_json_string(s: &str) -> String {
   s.replace('\\', "\\\\").replace('"', "\\\"")
 }
 
+
+/// `CsvDialect::delimiter_regex` compiled: anchored to find a delimiter where a cell may end,
+/// and unanchored to tell whether a written cell needs quotes.
+#[derive(Clone)]
+pub(crate) struct DelimiterRegex {
+  at_start: bytes::Regex,
+  anywhere: Regex,
+}
+
+impl DelimiterRegex {
+  pub(crate) fn new(pattern: &str) -> Result<Self, regex::Error> {
+    Ok(Self {
+      at_start: bytes::Regex::new(&format!("^(?:{pattern})"))?,
+      anywhere: Regex::new(pattern)?,
+    })
+  }
+
+  /// Length of the match `bytes` start with, if any (empty matches don't count).
+  pub(crate) fn match_len(&self, bytes: &[u8]) -> Option<usize> {
+    self.at_start.find(bytes).map(|m| m.end()).filter(|&len| len > 0)
+  }
+
+  pub(crate) fn is_match(&self, s: &str) -> bool {
+    self.anywhere.is_match(s)
+  }
+
+  /// Compiled once per pattern (records are split one at a time); `None` if it doesn't compile
+  /// (`set_csv_dialect` refuses those).
+  fn cached(pattern: &str) -> Option<Self> {
+    static COMPILED: OnceLock<Mutex<HashMap<String, Option<DelimiterRegex>>>> = OnceLock::new();
+    COMPILED
+      .get_or_init(Default::default)
+      .lock()
+      .entry(pattern.to_string())
+      .or_insert_with(|| Self::new(pattern).ok())
+      .clone()
+  }
+}
+
+/// Where a dialect's cells end: `delimiter`, or matches of `delimiter_regex` if it has one.
+struct CellDelimiter<'a> {
+  literal: &'a [u8],
+  regex: Option<DelimiterRegex>,
+}
+
+impl<'a> CellDelimiter<'a> {
+  fn of(dialect: &'a CsvDialect) -> Self {
+    Self {
+      literal: dialect.delimiter.as_bytes(),
+      regex: dialect.delimiter_regex.as_deref().and_then(DelimiterRegex::cached),
+    }
+  }
+
+  /// Length of the delimiter `bytes` start with, if they do.
+  fn starts(&self, bytes: &[u8]) -> Option<usize> {
+    match &self.regex {
+      Some(regex) => regex.match_len(bytes),
+      None => (!self.literal.is_empty() && bytes.starts_with(self.literal)).then_some(self.literal.len()),
+    }
+  }
+
+  /// Whether `s` holds a delimiter `delimiter_regex` would split (the literal one is checked by
+  /// the caller).
+  fn occurs_in(&self, s: &str) -> bool {
+    self.regex.as_ref().is_some_and(|regex| regex.is_match(s))
+  }
+}
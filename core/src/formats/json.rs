@@ -7,10 +7,14 @@ use std::{
 use crate::{
   cursor::Cursor,
   engine::CoreError,
-  formats::LinesPageInternal,
+  formats::{quote_csv_field, LinesPageInternal},
+  json_index::{child_path_hash, IndexedNode, ROOT_PATH_HASH},
+  progress::ScanProgress,
   models::{
-    ExportFormat, JsonChildItem, JsonChildrenPage, JsonNodeKind, JsonNodeSummary, JsonPathSegment,
+    Base64Content, Base64Preview, CsvDialect, ExportFormat, JsonChildItem, JsonChildrenPage, JsonNodeKind, JsonNodeSummary, JsonPathSegment,
     Record, RecordMeta, JsonChildItemOffset, JsonChildrenPageOffset, JsonNodeSummaryOffset,
+    JsonFindHit, JsonFindQuery, JsonFindResult, JsonPathMatch, JsonPathResult,
+    JsonChildSort, JsonNodeStats, JsonSyntaxError, JsonTableCell, JsonTablePage, JsonTableRow,
   },
 };
 
@@ -19,6 +23,7 @@ use crate::{
 /// This is used for the UI "详情" view when `Record.raw` was truncated.
 pub(crate) fn read_json_value_at_offset(
   path: &Path,
+  lenient: bool,
   offset: u64,
   max_bytes: u64,
 ) -> Result<String, CoreError> {
@@ -31,7 +36,7 @@ pub(crate) fn read_json_value_at_offset(
     )));
   }
   f.seek(SeekFrom::Start(offset))?;
-  let mut reader = BufReader::with_capacity(1024 * 1024, f);
+  let mut reader = BufReader::with_capacity(1024 * 1024, JsonSource::new(f, lenient)?);
 
   let mut abs = offset;
   let total = file_len;
@@ -92,18 +97,14 @@ pub(crate) fn read_json_value_at_offset(
     match b {
       b'"' => in_string = true,
       b'{' | b'[' => depth += 1,
-      b'}' | b']' => {
-        if depth > 0 {
-          depth -= 1;
-        }
+      b'}' | b']' if depth > 0 => {
+        depth -= 1;
       }
-      b',' => {
-        if depth == 0 {
-          // Comma delim ends the current value; push back and do NOT include comma.
-          unread_one(&mut reader)?;
-          out.pop();
-          break;
-        }
+      b',' if depth == 0 => {
+        // Comma delim ends the current value; push back and do NOT include comma.
+        unread_one(&mut reader)?;
+        out.pop();
+        break;
       }
       _ => {}
     }
@@ -134,16 +135,18 @@ pub(crate) fn read_json_value_at_offset(
 /// - Cursor uses `offset` as the next element's byte offset (fast seek), and `line` as the record id.
 pub(crate) fn read_json_page(
   path: &Path,
+  lenient: bool,
   cursor: Cursor,
   page_size: usize,
   preview_max_chars: usize,
   raw_max_chars: usize,
 ) -> Result<(LinesPageInternal, Option<Cursor>), CoreError> {
-  read_json_page_with_progress(path, cursor, page_size, preview_max_chars, raw_max_chars, None)
+  read_json_page_with_progress(path, lenient, cursor, page_size, preview_max_chars, raw_max_chars, None)
 }
 
 pub(crate) fn read_json_page_with_progress(
   path: &Path,
+  lenient: bool,
   cursor: Cursor,
   page_size: usize,
   preview_max_chars: usize,
@@ -159,7 +162,7 @@ pub(crate) fn read_json_page_with_progress(
     )));
   }
   file.seek(SeekFrom::Start(cursor.offset))?;
-  let mut reader = BufReader::with_capacity(1024 * 1024, file);
+  let mut reader = BufReader::with_capacity(1024 * 1024, JsonSource::new(file, lenient)?);
 
   let mut abs = cursor.offset;
   let mut next_id = cursor.line;
@@ -238,7 +241,7 @@ pub(crate) fn read_json_page_with_progress(
     }
 
     // Capture up to raw_max_chars chars (approx by bytes) for preview/raw.
-    let capture_max_bytes = (raw_max_chars.max(preview_max_chars) * 4).max(1024) as usize;
+    let capture_max_bytes = (raw_max_chars.max(preview_max_chars) * 4).max(1024);
     let scanned = scan_one_json_value(
       &mut reader,
       &mut abs,
@@ -265,7 +268,11 @@ pub(crate) fn read_json_page_with_progress(
         line_no: next_id,
         byte_offset: start_offset,
         byte_len: scanned.total_len_bytes,
+        part: None,
       }),
+      derived: None,
+      warning: None,
+      columns: None,
     });
     next_id += 1;
 
@@ -325,7 +332,7 @@ struct ScannedValue {
 /// - Tracks JSON nesting depth and string escaping to find the end of the value.
 /// - `capture_max_bytes`: capture up to N bytes for preview/raw. If None, capture nothing.
 fn scan_one_json_value(
-  reader: &mut BufReader<File>,
+  reader: &mut BufReader<JsonSource>,
   abs: &mut u64,
   total: u64,
   capture_max_bytes: Option<usize>,
@@ -385,19 +392,15 @@ fn scan_one_json_value(
     match b {
       b'"' => in_string = true,
       b'{' | b'[' => depth += 1,
-      b'}' | b']' => {
-        if depth > 0 {
-          depth -= 1;
-        }
-      }
-      b',' => {
-        if depth == 0 {
-          // Comma delim ends the current value; push back.
-          unread_one(reader)?;
-          *abs -= 1;
-          total_len -= 1;
-          break;
-        }
+      b'}' | b']' if depth > 0 => {
+        depth -= 1;
+      }
+      b',' if depth == 0 => {
+        // Comma delim ends the current value; push back.
+        unread_one(reader)?;
+        *abs -= 1;
+        total_len -= 1;
+        break;
       }
       _ => {}
     }
@@ -421,6 +424,156 @@ fn scan_one_json_value(
   }))
 }
 
+/// Bytes a lenient `JsonSource` lexes per refill (more when the chunk ends on an undecided `/`
+/// or `,`).
+const LENIENT_CHUNK_BYTES: u64 = 256 * 1024;
+
+/// A JSON file as the scanners read it. Lenient sources (JSON5-ish config files, see
+/// `SessionInfo::json_lenient`) read `//` and `/* */` comments and trailing commas (before `]` or
+/// `}`) as spaces, so every scanner skips them while offsets stay those of the file.
+///
+/// What to blank is decided by a lexer run from where reading starts, which must be outside
+/// strings and comments (a record, node or member offset, like every reader here uses).
+pub(crate) struct JsonSource {
+  file: File,
+  lenient: bool,
+  /// Lenient only: blanked bytes from `chunk_start`, the read position, and the lexer state at
+  /// the chunk end.
+  chunk: Vec<u8>,
+  chunk_start: u64,
+  pos: u64,
+  lex: LenientLex,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LenientLex {
+  Value,
+  Str,
+  StrEscape,
+  LineComment,
+  BlockComment,
+  BlockCommentStar,
+}
+
+impl JsonSource {
+  /// Reads `file` from its current position.
+  pub(crate) fn new(mut file: File, lenient: bool) -> std::io::Result<Self> {
+    let pos = if lenient { file.stream_position()? } else { 0 };
+    Ok(Self {
+      file,
+      lenient,
+      chunk: Vec::new(),
+      chunk_start: pos,
+      pos,
+      lex: LenientLex::Value,
+    })
+  }
+
+  /// Blank the chunk starting at `pos`, continuing the lexer if that is where the last one ended.
+  fn next_chunk(&mut self) -> std::io::Result<()> {
+    if self.pos != self.chunk_start + self.chunk.len() as u64 {
+      self.lex = LenientLex::Value;
+    }
+    self.file.seek(SeekFrom::Start(self.pos))?;
+    self.chunk_start = self.pos;
+    self.chunk.clear();
+    // A `/` that may open a comment, and a `,` that may turn out trailing.
+    let mut slash: Option<usize> = None;
+    let mut comma: Option<usize> = None;
+    loop {
+      let lexed = self.chunk.len();
+      let read = (&mut self.file).take(LENIENT_CHUNK_BYTES).read_to_end(&mut self.chunk)?;
+      for i in lexed..self.chunk.len() {
+        let b = self.chunk[i];
+        let blank = |chunk: &mut Vec<u8>| {
+          if b != b'\n' {
+            chunk[i] = b' ';
+          }
+        };
+        match self.lex {
+          LenientLex::Str if b == b'\\' => self.lex = LenientLex::StrEscape,
+          LenientLex::Str if b == b'"' => self.lex = LenientLex::Value,
+          LenientLex::Str => {}
+          LenientLex::StrEscape => self.lex = LenientLex::Str,
+          LenientLex::LineComment if b == b'\n' => self.lex = LenientLex::Value,
+          LenientLex::LineComment => blank(&mut self.chunk),
+          LenientLex::BlockComment | LenientLex::BlockCommentStar => {
+            blank(&mut self.chunk);
+            self.lex = match (self.lex, b) {
+              (LenientLex::BlockCommentStar, b'/') => LenientLex::Value,
+              (_, b'*') => LenientLex::BlockCommentStar,
+              _ => LenientLex::BlockComment,
+            };
+          }
+          LenientLex::Value => {
+            if let Some(s) = slash.take() {
+              if b == b'/' || b == b'*' {
+                self.chunk[s] = b' ';
+                self.chunk[i] = b' ';
+                self.lex = if b == b'/' { LenientLex::LineComment } else { LenientLex::BlockComment };
+                continue;
+              }
+              // Not a comment: the `/` is (invalid) data.
+              comma = None;
+            }
+            match b {
+              b'/' => slash = Some(i),
+              b',' => comma = Some(i),
+              b']' | b'}' => {
+                if let Some(c) = comma.take() {
+                  self.chunk[c] = b' ';
+                }
+              }
+              b'"' => {
+                comma = None;
+                self.lex = LenientLex::Str;
+              }
+              b if is_ignorable_head_byte(b) => {}
+              _ => comma = None,
+            }
+          }
+        }
+      }
+      if (read as u64) < LENIENT_CHUNK_BYTES || (slash.is_none() && comma.is_none()) {
+        return Ok(());
+      }
+    }
+  }
+}
+
+impl Read for JsonSource {
+  fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+    if !self.lenient {
+      return self.file.read(buf);
+    }
+    if self.pos < self.chunk_start || self.pos >= self.chunk_start + self.chunk.len() as u64 {
+      self.next_chunk()?;
+    }
+    let at = (self.pos - self.chunk_start) as usize;
+    let n = buf.len().min(self.chunk.len() - at);
+    buf[..n].copy_from_slice(&self.chunk[at..at + n]);
+    self.pos += n as u64;
+    Ok(n)
+  }
+}
+
+impl Seek for JsonSource {
+  fn seek(&mut self, to: SeekFrom) -> std::io::Result<u64> {
+    if !self.lenient {
+      return self.file.seek(to);
+    }
+    self.pos = match to {
+      SeekFrom::Start(n) => n,
+      SeekFrom::Current(d) => self
+        .pos
+        .checked_add_signed(d)
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, "seek before file start"))?,
+      SeekFrom::End(_) => self.file.seek(to)?,
+    };
+    Ok(self.pos)
+  }
+}
+
 fn maybe_emit_progress(
   done: u64,
   total: u64,
@@ -434,7 +587,7 @@ fn maybe_emit_progress(
 }
 
 fn skip_bom_and_ws(
-  reader: &mut BufReader<File>,
+  reader: &mut BufReader<JsonSource>,
   abs: &mut u64,
   total: u64,
   on_progress: &mut Option<&mut dyn FnMut(u64, u64, &'static str)>,
@@ -451,7 +604,7 @@ fn skip_bom_and_ws(
 }
 
 fn skip_ws_and_nul(
-  reader: &mut BufReader<File>,
+  reader: &mut BufReader<JsonSource>,
   abs: &mut u64,
   total: u64,
   on_progress: &mut Option<&mut dyn FnMut(u64, u64, &'static str)>,
@@ -468,7 +621,7 @@ fn skip_ws_and_nul(
 }
 
 fn consume_byte(
-  reader: &mut BufReader<File>,
+  reader: &mut BufReader<JsonSource>,
   abs: &mut u64,
   total: u64,
   on_progress: &mut Option<&mut dyn FnMut(u64, u64, &'static str)>,
@@ -479,7 +632,7 @@ fn consume_byte(
   Ok(b)
 }
 
-fn read_one(reader: &mut BufReader<File>) -> Result<Option<u8>, CoreError> {
+fn read_one(reader: &mut BufReader<JsonSource>) -> Result<Option<u8>, CoreError> {
   let mut buf = [0u8; 1];
   match reader.read(&mut buf)? {
     0 => Ok(None),
@@ -487,7 +640,7 @@ fn read_one(reader: &mut BufReader<File>) -> Result<Option<u8>, CoreError> {
   }
 }
 
-fn unread_one(reader: &mut BufReader<File>) -> Result<(), CoreError> {
+fn unread_one(reader: &mut BufReader<JsonSource>) -> Result<(), CoreError> {
   // BufReader provides `fill_buf`/`consume`, but not unconsume. We can use `Seek` to step back by 1
   // on the underlying file, then clear the buffer by re-creating the reader would be expensive.
   // Instead, leverage `std::io::Seek` on BufReader itself.
@@ -495,7 +648,7 @@ fn unread_one(reader: &mut BufReader<File>) -> Result<(), CoreError> {
   Ok(())
 }
 
-fn peek_byte(reader: &mut BufReader<File>) -> Result<Option<u8>, CoreError> {
+fn peek_byte(reader: &mut BufReader<JsonSource>) -> Result<Option<u8>, CoreError> {
   let buf = reader.fill_buf()?;
   if buf.is_empty() {
     Ok(None)
@@ -504,7 +657,7 @@ fn peek_byte(reader: &mut BufReader<File>) -> Result<Option<u8>, CoreError> {
   }
 }
 
-fn peek_n(reader: &mut BufReader<File>, n: usize) -> Result<Vec<u8>, CoreError> {
+fn peek_n(reader: &mut BufReader<JsonSource>, n: usize) -> Result<Vec<u8>, CoreError> {
   let buf = reader.fill_buf()?;
   Ok(buf.iter().take(n).copied().collect())
 }
@@ -521,7 +674,7 @@ fn truncate_chars(s: &str, max: usize) -> String {
   let mut out = String::new();
   for (i, ch) in s.chars().enumerate() {
     if i >= max {
-      out.push_str("…");
+      out.push('…');
       break;
     }
     out.push(ch);
@@ -529,6 +682,135 @@ fn truncate_chars(s: &str, max: usize) -> String {
   out
 }
 
+/// Walk the records of a `.json` file (root array elements, or the root value itself) from
+/// `offset`: 0, or the first byte of an element as found by an earlier walk. `on_record(len)`
+/// gets the distance from the record's position to the next element's first byte (for the
+/// last one, to its end), so positions add up like `walk_lines` line lengths do; record 0's
+/// position is 0. `on_record` returns `false` to stop.
+pub(crate) fn walk_json_records(path: &Path, lenient: bool, offset: u64, mut on_record: impl FnMut(u64) -> bool) -> Result<(), CoreError> {
+  let mut file = File::open(path)?;
+  let total = file.metadata().ok().map(|m| m.len()).unwrap_or(0);
+  file.seek(SeekFrom::Start(offset))?;
+  let mut reader = BufReader::with_capacity(1024 * 1024, JsonSource::new(file, lenient)?);
+  let mut abs = offset;
+  let mut no_progress = None;
+  if offset == 0 {
+    skip_bom_and_ws(&mut reader, &mut abs, total, &mut no_progress)?;
+    if peek_byte(&mut reader)? == Some(b'[') {
+      consume_byte(&mut reader, &mut abs, total, &mut no_progress)?;
+    }
+  }
+
+  // Position of the record being walked; its length is known once the next one starts.
+  let mut current: Option<u64> = None;
+  let mut end = offset;
+  loop {
+    skip_ws_and_nul(&mut reader, &mut abs, total, &mut no_progress)?;
+    match peek_byte(&mut reader)? {
+      None | Some(b']') => break,
+      Some(b',') => {
+        consume_byte(&mut reader, &mut abs, total, &mut no_progress)?;
+        continue;
+      }
+      _ => {}
+    }
+    let position = match current {
+      None => offset,
+      Some(prev) => {
+        if !on_record(abs - prev) {
+          return Ok(());
+        }
+        abs
+      }
+    };
+    if scan_one_json_value(&mut reader, &mut abs, total, None, 0, &mut no_progress)?.is_none() {
+      break;
+    }
+    current = Some(position);
+    end = abs;
+  }
+  if let Some(prev) = current {
+    on_record(end - prev);
+  }
+  Ok(())
+}
+
+/// Locate the first root-array element starting at or after byte `offset`.
+///
+/// Returns `(element_offset, element_index)`, or `None` if no element starts there (past the last
+/// element, or the root is not an array). This is a structural byte scan from the file start
+/// (strings/escapes/nesting only, no parsing), so it is exact but linear in `offset`.
+pub(crate) fn root_array_element_at_or_after(path: &Path, lenient: bool, offset: u64) -> Result<Option<(u64, u64)>, CoreError> {
+  let file = File::open(path)?;
+  let mut reader = BufReader::with_capacity(1024 * 1024, JsonSource::new(file, lenient)?);
+
+  let mut abs = 0u64;
+  let mut depth = 0u32;
+  let mut in_string = false;
+  let mut escaped = false;
+  let mut seen_root = false;
+  // True right after the root `[` or a depth-1 `,`: the next non-ws byte starts an element.
+  let mut expect_element = false;
+  let mut index = 0u64;
+
+  loop {
+    let buf = reader.fill_buf()?;
+    if buf.is_empty() {
+      return Ok(None);
+    }
+    let n = buf.len();
+    for &b in buf {
+      let at = abs;
+      abs += 1;
+      if in_string {
+        if escaped {
+          escaped = false;
+        } else if b == b'\\' {
+          escaped = true;
+        } else if b == b'"' {
+          in_string = false;
+        }
+        continue;
+      }
+      if !seen_root {
+        if is_ignorable_head_byte(b) || b == 0xEF || b == 0xBB || b == 0xBF {
+          continue;
+        }
+        if b != b'[' {
+          return Ok(None);
+        }
+        seen_root = true;
+        depth = 1;
+        expect_element = true;
+        continue;
+      }
+      if expect_element && !is_ignorable_head_byte(b) {
+        if b == b']' && depth == 1 {
+          return Ok(None);
+        }
+        expect_element = false;
+        if at >= offset {
+          return Ok(Some((at, index)));
+        }
+        index += 1;
+      }
+      match b {
+        b'"' => in_string = true,
+        b'[' | b'{' => depth += 1,
+        b']' | b'}' => {
+          depth = depth.saturating_sub(1);
+          if depth == 0 {
+            return Ok(None);
+          }
+        }
+        b',' if depth == 1 => expect_element = true,
+        _ => {}
+      }
+    }
+    reader.consume(n);
+  }
+}
+
 // --- Lazy JSON tree helpers (streaming) ---
 
 /// List direct children under a JSON node selected by `path_segments`, starting from `cursor`.
@@ -537,6 +819,7 @@ fn truncate_chars(s: &str, max: usize) -> String {
 /// in the frontend. It scans the underlying bytes and only materializes small previews.
 pub(crate) fn list_json_children_page(
   path: &Path,
+  lenient: bool,
   record_offset: u64,
   path_segments: &[JsonPathSegment],
   cursor: u64,
@@ -552,7 +835,7 @@ pub(crate) fn list_json_children_page(
     )));
   }
   f.seek(SeekFrom::Start(record_offset))?;
-  let mut reader = BufReader::with_capacity(1024 * 1024, f);
+  let mut reader = BufReader::with_capacity(1024 * 1024, JsonSource::new(f, lenient)?);
   let mut abs = record_offset;
   let total = file_len;
   let mut on_progress: Option<&mut dyn FnMut(u64, u64, &'static str)> = None;
@@ -594,7 +877,7 @@ fn kind_from_first_byte(b: u8) -> JsonNodeKind {
 }
 
 fn list_object_children(
-  reader: &mut BufReader<File>,
+  reader: &mut BufReader<JsonSource>,
   abs: &mut u64,
   total: u64,
   on_progress: &mut Option<&mut dyn FnMut(u64, u64, &'static str)>,
@@ -632,7 +915,7 @@ fn list_object_children(
     expect_byte(reader, abs, total, on_progress, b':')?;
     skip_ws_and_nul(reader, abs, total, on_progress)?;
     // value
-    scan_one_json_value_with_stops(reader, abs, total, None, &[b',', b'}'], on_progress)?;
+    scan_one_json_value_with_stops(reader, abs, total, None, b",}", on_progress)?;
     skip_ws_and_nul(reader, abs, total, on_progress)?;
     match peek_byte(reader)? {
       Some(b',') => {
@@ -689,8 +972,8 @@ fn list_object_children(
       reader,
       abs,
       total,
-      Some((preview_max_chars.max(64) * 4) as usize),
-      &[b',', b'}'],
+      Some(preview_max_chars.max(64) * 4),
+      b",}",
       on_progress,
     )?;
     let (preview, truncated) = preview_from_scan(scanned.captured, scanned.total_len_bytes, preview_max_chars);
@@ -738,7 +1021,7 @@ fn list_object_children(
 }
 
 fn list_array_children(
-  reader: &mut BufReader<File>,
+  reader: &mut BufReader<JsonSource>,
   abs: &mut u64,
   total: u64,
   on_progress: &mut Option<&mut dyn FnMut(u64, u64, &'static str)>,
@@ -770,7 +1053,7 @@ fn list_array_children(
         reached_end: true,
       });
     }
-    scan_one_json_value_with_stops(reader, abs, total, None, &[b',', b']'], on_progress)?;
+    scan_one_json_value_with_stops(reader, abs, total, None, b",]", on_progress)?;
     skip_ws_and_nul(reader, abs, total, on_progress)?;
     match peek_byte(reader)? {
       Some(b',') => {
@@ -797,9 +1080,7 @@ fn list_array_children(
 
   let mut out: Vec<JsonChildItem> = Vec::with_capacity(limit);
   let mut reached_end = false;
-  let mut cur_idx = cursor;
-
-  for _ in 0..limit {
+  for cur_idx in (cursor..).take(limit) {
     skip_ws_and_nul(reader, abs, total, on_progress)?;
     match peek_byte(reader)? {
       Some(b']') => {
@@ -820,8 +1101,8 @@ fn list_array_children(
       reader,
       abs,
       total,
-      Some((preview_max_chars.max(64) * 4) as usize),
-      &[b',', b']'],
+      Some(preview_max_chars.max(64) * 4),
+      b",]",
       on_progress,
     )?;
     let (preview, truncated) = preview_from_scan(scanned.captured, scanned.total_len_bytes, preview_max_chars);
@@ -836,7 +1117,6 @@ fn list_array_children(
       kind,
       preview,
     });
-    cur_idx += 1;
 
     skip_ws_and_nul(reader, abs, total, on_progress)?;
     match peek_byte(reader)? {
@@ -872,7 +1152,8 @@ fn list_array_children(
 fn preview_from_scan(captured: Vec<u8>, total_len_bytes: u64, preview_max_chars: usize) -> (String, bool) {
   let mut s = String::from_utf8_lossy(&captured).to_string();
   // Trim trailing NUL/whitespace for a cleaner preview.
-  while s.ends_with('\0') || s.ends_with('\n') || s.ends_with('\r') {
+  // (Blanked lenient trailing commas leave spaces before a closing bracket.)
+  while s.ends_with(['\0', '\n', '\r', ' ', '\t']) {
     s.pop();
   }
   let truncated = (captured.len() as u64) < total_len_bytes;
@@ -881,7 +1162,7 @@ fn preview_from_scan(captured: Vec<u8>, total_len_bytes: u64, preview_max_chars:
 }
 
 fn expect_byte(
-  reader: &mut BufReader<File>,
+  reader: &mut BufReader<JsonSource>,
   abs: &mut u64,
   total: u64,
   on_progress: &mut Option<&mut dyn FnMut(u64, u64, &'static str)>,
@@ -898,7 +1179,7 @@ fn expect_byte(
 }
 
 fn read_json_string(
-  reader: &mut BufReader<File>,
+  reader: &mut BufReader<JsonSource>,
   abs: &mut u64,
   total: u64,
   on_progress: &mut Option<&mut dyn FnMut(u64, u64, &'static str)>,
@@ -934,19 +1215,32 @@ fn read_json_string(
 /// value into memory.
 ///
 /// This replaces the previous `serde_json::from_str` based approach, and works for huge records.
+/// With `minify`, whitespace between tokens is dropped (values and the `.json` wrapper alike), so
+/// pretty-printed sources come out compact and JSONL lines stay one line each. CSV output needs
+/// an array of flat objects (see `export_json_array_csv`).
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn export_json_subtree_stream(
   session_path: &Path,
+  lenient: bool,
   record_offset: u64,
   path: &[JsonPathSegment],
   include_root: bool,
   children: &[JsonPathSegment],
   out_format: ExportFormat,
+  minify: bool,
   writer: &mut dyn Write,
 ) -> Result<u64, CoreError> {
   if matches!(out_format, ExportFormat::Csv) {
-    return Err(CoreError::InvalidArg(
-      "json_subtree export does not support csv output".into(),
-    ));
+    // Without a selection the whole array is exported; else the selected elements.
+    let indices: std::collections::BTreeSet<u64> = children
+      .iter()
+      .filter_map(|seg| match seg {
+        JsonPathSegment::Index(i) => Some(*i),
+        JsonPathSegment::Key(_) => None,
+      })
+      .collect();
+    let indices = (!include_root && !children.is_empty()).then_some(&indices);
+    return export_json_array_csv(session_path, lenient, record_offset, path, indices, writer);
   }
 
   let mut f = File::open(session_path)?;
@@ -958,7 +1252,7 @@ pub(crate) fn export_json_subtree_stream(
     )));
   }
   f.seek(SeekFrom::Start(record_offset))?;
-  let mut reader = BufReader::with_capacity(1024 * 1024, f);
+  let mut reader = BufReader::with_capacity(1024 * 1024, JsonSource::new(f, lenient)?);
   let mut abs = record_offset;
   let total = file_len;
   let mut on_progress: Option<&mut dyn FnMut(u64, u64, &'static str)> = None;
@@ -975,9 +1269,10 @@ pub(crate) fn export_json_subtree_stream(
       &mut reader,
       &mut abs,
       total,
-      &[b',', b']', b'}'],
+      b",]}",
       &mut on_progress,
       Some(writer),
+      minify,
     )?;
     return Ok(1);
   }
@@ -994,7 +1289,7 @@ pub(crate) fn export_json_subtree_stream(
   };
   let end_out = |w: &mut dyn Write, wrote_any: bool| -> Result<(), CoreError> {
     if matches!(out_format, ExportFormat::Json) {
-      if wrote_any {
+      if wrote_any && !minify {
         w.write_all(b"\n]")?;
       } else {
         w.write_all(b"]")?;
@@ -1006,10 +1301,11 @@ pub(crate) fn export_json_subtree_stream(
     match out_format {
       ExportFormat::Jsonl => Ok(()),
       ExportFormat::Json => {
-        if wrote_any {
-          w.write_all(b",\n")?;
-        } else {
-          w.write_all(b"\n")?;
+        match (wrote_any, minify) {
+          (true, true) => w.write_all(b",")?,
+          (true, false) => w.write_all(b",\n")?,
+          (false, true) => {}
+          (false, false) => w.write_all(b"\n")?,
         }
         Ok(())
       }
@@ -1027,7 +1323,7 @@ pub(crate) fn export_json_subtree_stream(
 
   if include_root {
     begin_item(writer, wrote_any)?;
-    scan_one_json_value_to_writer(&mut reader, &mut abs, total, &[b',', b']', b'}'], &mut on_progress, Some(writer))?;
+    scan_one_json_value_to_writer(&mut reader, &mut abs, total, b",]}", &mut on_progress, Some(writer), minify)?;
     end_item(writer)?;
     wrote_any = true;
     written += 1;
@@ -1069,12 +1365,12 @@ pub(crate) fn export_json_subtree_stream(
 
           if want_keys.contains(&key) {
             begin_item(writer, wrote_any)?;
-            scan_one_json_value_to_writer(&mut reader, &mut abs, total, &[b',', b'}'], &mut on_progress, Some(writer))?;
+            scan_one_json_value_to_writer(&mut reader, &mut abs, total, b",}", &mut on_progress, Some(writer), minify)?;
             end_item(writer)?;
             wrote_any = true;
             written += 1;
           } else {
-            scan_one_json_value_to_writer(&mut reader, &mut abs, total, &[b',', b'}'], &mut on_progress, None)?;
+            scan_one_json_value_to_writer(&mut reader, &mut abs, total, b",}", &mut on_progress, None, false)?;
           }
 
           skip_ws_and_nul(&mut reader, &mut abs, total, &mut on_progress)?;
@@ -1114,12 +1410,12 @@ pub(crate) fn export_json_subtree_stream(
 
           if want_indices.contains(&idx) {
             begin_item(writer, wrote_any)?;
-            scan_one_json_value_to_writer(&mut reader, &mut abs, total, &[b',', b']'], &mut on_progress, Some(writer))?;
+            scan_one_json_value_to_writer(&mut reader, &mut abs, total, b",]", &mut on_progress, Some(writer), minify)?;
             end_item(writer)?;
             wrote_any = true;
             written += 1;
           } else {
-            scan_one_json_value_to_writer(&mut reader, &mut abs, total, &[b',', b']'], &mut on_progress, None)?;
+            scan_one_json_value_to_writer(&mut reader, &mut abs, total, b",]", &mut on_progress, None, false)?;
           }
           idx += 1;
 
@@ -1142,7 +1438,7 @@ pub(crate) fn export_json_subtree_stream(
     _ => {
       // Leaf: export as root.
       begin_item(writer, wrote_any)?;
-      scan_one_json_value_to_writer(&mut reader, &mut abs, total, &[b',', b']', b'}'], &mut on_progress, Some(writer))?;
+      scan_one_json_value_to_writer(&mut reader, &mut abs, total, b",]}", &mut on_progress, Some(writer), minify)?;
       end_item(writer)?;
       wrote_any = true;
       written += 1;
@@ -1153,16 +1449,58 @@ pub(crate) fn export_json_subtree_stream(
   Ok(written)
 }
 
-/// Best-effort summary (kind + child count) for the selected subtree.
-///
-/// Counting can be expensive; we support caps to keep UI responsive.
-pub(crate) fn json_node_summary(
+/// CSV export of the array node at `path`, whose elements must be objects with scalar values:
+/// a header of every key in first-seen order, then one row per element (only `indices`, if
+/// set), empty where a key is missing or null. Two streaming passes over the array, one for the
+/// header and one for the rows, so only one element is held at a time. Returns the rows written.
+fn export_json_array_csv(
   session_path: &Path,
+  lenient: bool,
   record_offset: u64,
-  path_segments: &[JsonPathSegment],
-  max_items: u64,
-  max_scan_bytes: u64,
-) -> Result<JsonNodeSummary, CoreError> {
+  path: &[JsonPathSegment],
+  indices: Option<&std::collections::BTreeSet<u64>>,
+  writer: &mut dyn Write,
+) -> Result<u64, CoreError> {
+  let mut columns: Vec<String> = Vec::new();
+  let mut column_of: std::collections::HashMap<String, usize> = std::collections::HashMap::new();
+  for_each_flat_object(session_path, lenient, record_offset, path, indices, &mut |members| {
+    for (key, _) in members {
+      if !column_of.contains_key(&key) {
+        column_of.insert(key.clone(), columns.len());
+        columns.push(key);
+      }
+    }
+    Ok(())
+  })?;
+
+  let dialect = CsvDialect::default();
+  let header: Vec<String> = columns.iter().map(|c| quote_csv_field(c, &dialect)).collect();
+  writer.write_all(format!("{}\n", header.join(",")).as_bytes())?;
+  let mut written = 0u64;
+  for_each_flat_object(session_path, lenient, record_offset, path, indices, &mut |members| {
+    let mut row = vec![String::new(); columns.len()];
+    for (key, value) in members {
+      if let Some(&col) = column_of.get(&key) {
+        row[col] = quote_csv_field(&crate::derive::value_text(&value), &dialect);
+      }
+    }
+    writer.write_all(format!("{}\n", row.join(",")).as_bytes())?;
+    written += 1;
+    Ok(())
+  })?;
+  Ok(written)
+}
+
+/// Call `on_object` with the members of each element (only `indices`, if set) of the array node
+/// at `path`, in order. Errors on elements that aren't objects or have nested values.
+fn for_each_flat_object(
+  session_path: &Path,
+  lenient: bool,
+  record_offset: u64,
+  path: &[JsonPathSegment],
+  indices: Option<&std::collections::BTreeSet<u64>>,
+  on_object: &mut dyn FnMut(Vec<(String, serde_json::Value)>) -> Result<(), CoreError>,
+) -> Result<(), CoreError> {
   let mut f = File::open(session_path)?;
   let file_len = f.metadata().ok().map(|m| m.len()).unwrap_or(0);
   if record_offset > file_len {
@@ -1172,115 +1510,363 @@ pub(crate) fn json_node_summary(
     )));
   }
   f.seek(SeekFrom::Start(record_offset))?;
-  let mut reader = BufReader::with_capacity(1024 * 1024, f);
+  let mut reader = BufReader::with_capacity(1024 * 1024, JsonSource::new(f, lenient)?);
   let mut abs = record_offset;
   let total = file_len;
   let mut on_progress: Option<&mut dyn FnMut(u64, u64, &'static str)> = None;
 
-  seek_to_subtree(&mut reader, &mut abs, total, &mut on_progress, path_segments)?;
+  seek_to_subtree(&mut reader, &mut abs, total, &mut on_progress, path)?;
   skip_ws_and_nul(&mut reader, &mut abs, total, &mut on_progress)?;
-  let first = peek_byte(&mut reader)?.unwrap_or(b'?');
-  let kind = kind_from_first_byte(first);
-  if kind != JsonNodeKind::Object && kind != JsonNodeKind::Array {
-    return Ok(JsonNodeSummary {
-      kind,
-      child_count: None,
-      complete: true,
-    });
+  if peek_byte(&mut reader)? != Some(b'[') {
+    return Err(CoreError::InvalidArg("csv export needs an array of objects".into()));
   }
+  consume_byte(&mut reader, &mut abs, total, &mut on_progress)?;
+  let mut idx: u64 = 0;
+  loop {
+    skip_ws_and_nul(&mut reader, &mut abs, total, &mut on_progress)?;
+    match peek_byte(&mut reader)? {
+      Some(b']') | None => break,
+      _ => {}
+    }
 
-  let start_abs = abs;
-  let mut count: u64 = 0;
-  let mut complete = true;
-
-  if kind == JsonNodeKind::Object {
-    consume_byte(&mut reader, &mut abs, total, &mut on_progress)?; // '{'
-    loop {
-      if abs.saturating_sub(start_abs) > max_scan_bytes || count >= max_items {
-        complete = false;
-        break;
-      }
-      skip_ws_and_nul(&mut reader, &mut abs, total, &mut on_progress)?;
-      match peek_byte(&mut reader)? {
-        Some(b'}') => {
-          consume_byte(&mut reader, &mut abs, total, &mut on_progress)?;
-          break;
-        }
-        None => break,
-        _ => {}
+    if indices.is_some_and(|want| !want.contains(&idx)) {
+      scan_one_json_value_to_writer(&mut reader, &mut abs, total, b",]", &mut on_progress, None, false)?;
+    } else {
+      if peek_byte(&mut reader)? != Some(b'{') {
+        return Err(CoreError::InvalidArg(format!(
+          "csv export needs an array of objects: element {idx} is not an object"
+        )));
       }
-      // key
-      skip_json_string_literal(&mut reader, &mut abs, total, &mut on_progress)?;
-      skip_ws_and_nul(&mut reader, &mut abs, total, &mut on_progress)?;
-      expect_byte(&mut reader, &mut abs, total, &mut on_progress, b':')?;
-      skip_ws_and_nul(&mut reader, &mut abs, total, &mut on_progress)?;
-      // value
-      let _ = scan_one_json_value_with_stops(&mut reader, &mut abs, total, None, &[b',', b'}'], &mut on_progress)?;
-      count += 1;
+      consume_byte(&mut reader, &mut abs, total, &mut on_progress)?;
       skip_ws_and_nul(&mut reader, &mut abs, total, &mut on_progress)?;
-      match peek_byte(&mut reader)? {
-        Some(b',') => {
-          consume_byte(&mut reader, &mut abs, total, &mut on_progress)?;
-          continue;
-        }
-        Some(b'}') => {
-          consume_byte(&mut reader, &mut abs, total, &mut on_progress)?;
-          break;
+      let mut members = Vec::new();
+      if peek_byte(&mut reader)? == Some(b'}') {
+        consume_byte(&mut reader, &mut abs, total, &mut on_progress)?;
+      } else {
+        loop {
+          let key = read_json_string(&mut reader, &mut abs, total, &mut on_progress)?;
+          skip_ws_and_nul(&mut reader, &mut abs, total, &mut on_progress)?;
+          expect_byte(&mut reader, &mut abs, total, &mut on_progress, b':')?;
+          skip_ws_and_nul(&mut reader, &mut abs, total, &mut on_progress)?;
+          if matches!(peek_byte(&mut reader)?, Some(b'{' | b'[')) {
+            return Err(CoreError::InvalidArg(format!(
+              "csv export needs flat objects: element {idx} has a nested value at {key:?}"
+            )));
+          }
+          let mut raw = Vec::new();
+          scan_one_json_value_to_writer(&mut reader, &mut abs, total, b",}", &mut on_progress, Some(&mut raw), false)?;
+          let value = serde_json::from_slice(&raw)
+            .map_err(|e| CoreError::InvalidArg(format!("invalid json value in element {idx}: {e}")))?;
+          members.push((key, value));
+
+          skip_ws_and_nul(&mut reader, &mut abs, total, &mut on_progress)?;
+          match peek_byte(&mut reader)? {
+            Some(b',') => {
+              consume_byte(&mut reader, &mut abs, total, &mut on_progress)?;
+              skip_ws_and_nul(&mut reader, &mut abs, total, &mut on_progress)?;
+            }
+            Some(b'}') => {
+              consume_byte(&mut reader, &mut abs, total, &mut on_progress)?;
+              break;
+            }
+            _ => {
+              return Err(CoreError::InvalidArg(format!(
+                "invalid json in element {idx}: expected ',' or '}}'"
+              )))
+            }
+          }
         }
-        None => break,
-        _ => break,
       }
+      on_object(members)?;
     }
-  } else {
-    consume_byte(&mut reader, &mut abs, total, &mut on_progress)?; // '['
-    loop {
-      if abs.saturating_sub(start_abs) > max_scan_bytes || count >= max_items {
-        complete = false;
-        break;
-      }
-      skip_ws_and_nul(&mut reader, &mut abs, total, &mut on_progress)?;
-      match peek_byte(&mut reader)? {
-        Some(b']') => {
-          consume_byte(&mut reader, &mut abs, total, &mut on_progress)?;
-          break;
-        }
-        None => break,
-        _ => {}
+    idx += 1;
+
+    skip_ws_and_nul(&mut reader, &mut abs, total, &mut on_progress)?;
+    match peek_byte(&mut reader)? {
+      Some(b',') => {
+        consume_byte(&mut reader, &mut abs, total, &mut on_progress)?;
       }
-      let _ = scan_one_json_value_with_stops(&mut reader, &mut abs, total, None, &[b',', b']'], &mut on_progress)?;
-      count += 1;
-      skip_ws_and_nul(&mut reader, &mut abs, total, &mut on_progress)?;
-      match peek_byte(&mut reader)? {
-        Some(b',') => {
-          consume_byte(&mut reader, &mut abs, total, &mut on_progress)?;
-          continue;
-        }
-        Some(b']') => {
-          consume_byte(&mut reader, &mut abs, total, &mut on_progress)?;
-          break;
-        }
-        None => break,
-        _ => break,
+      Some(b']') => break,
+      _ => {
+        return Err(CoreError::InvalidArg(format!(
+          "invalid json after element {}: expected ',' or ']'",
+          idx - 1
+        )))
       }
     }
   }
-
-  Ok(JsonNodeSummary {
-    kind,
-    child_count: Some(count),
-    complete,
-  })
+  Ok(())
 }
 
-/// Best-effort summary (kind + child count) for the node at `node_offset`.
-pub(crate) fn json_node_summary_at_offset(
+/// Stream the JSON value at (or after) `offset` to `writer`, pretty-printed with two-space
+/// indentation like `serde_json::to_string_pretty`, plus a final newline. Tokens are copied as
+/// they are (strings byte for byte); only whitespace between them changes, so records of any
+/// size are laid out without being parsed into memory.
+pub(crate) fn pretty_print_json_value(
   session_path: &Path,
-  node_offset: u64,
-  max_items: u64,
-  max_scan_bytes: u64,
-) -> Result<JsonNodeSummaryOffset, CoreError> {
-  let mut f = File::open(session_path)?;
-  let file_len = f.metadata().ok().map(|m| m.len()).unwrap_or(0);
+  lenient: bool,
+  offset: u64,
+  writer: &mut dyn Write,
+) -> Result<(), CoreError> {
+  let mut f = File::open(session_path)?;
+  let file_len = f.metadata().ok().map(|m| m.len()).unwrap_or(0);
+  if offset > file_len {
+    return Err(CoreError::InvalidArg(format!(
+      "offset {} beyond file len {}",
+      offset, file_len
+    )));
+  }
+  f.seek(SeekFrom::Start(offset))?;
+  let mut reader = BufReader::with_capacity(1024 * 1024, JsonSource::new(f, lenient)?);
+
+  let newline = |w: &mut dyn Write, depth: usize| -> Result<(), CoreError> {
+    w.write_all(b"\n")?;
+    for _ in 0..depth {
+      w.write_all(b"  ")?;
+    }
+    Ok(())
+  };
+  let mut depth: usize = 0;
+  let mut in_string = false;
+  let mut escape = false;
+  let mut started = false;
+  // Just wrote `{` / `[`: the line break waits for a member, so empty containers stay `{}`.
+  let mut opened = false;
+
+  loop {
+    let Some(b) = read_one(&mut reader)? else {
+      if !started {
+        return Err(CoreError::InvalidArg("unexpected EOF at offset".into()));
+      }
+      if in_string || depth > 0 {
+        return Err(CoreError::InvalidArg("unexpected EOF inside json value".into()));
+      }
+      break;
+    };
+
+    if in_string {
+      writer.write_all(&[b])?;
+      if escape {
+        escape = false;
+      } else if b == b'\\' {
+        escape = true;
+      } else if b == b'"' {
+        in_string = false;
+        if depth == 0 {
+          break;
+        }
+      }
+      continue;
+    }
+
+    if is_ignorable_head_byte(b) {
+      // Whitespace ends a top-level scalar.
+      if started && depth == 0 {
+        break;
+      }
+      continue;
+    }
+    // A delimiter after a top-level scalar belongs to the enclosing array / stream.
+    if started && depth == 0 && matches!(b, b',' | b']' | b'}') {
+      break;
+    }
+    started = true;
+
+    match b {
+      b'}' | b']' => {
+        depth = depth.saturating_sub(1);
+        if !opened {
+          newline(writer, depth)?;
+        }
+        opened = false;
+        writer.write_all(&[b])?;
+        if depth == 0 {
+          break;
+        }
+      }
+      b',' => {
+        writer.write_all(b",")?;
+        newline(writer, depth)?;
+      }
+      b':' => writer.write_all(b": ")?,
+      _ => {
+        if opened {
+          newline(writer, depth)?;
+          opened = false;
+        }
+        writer.write_all(&[b])?;
+        match b {
+          b'{' | b'[' => {
+            depth += 1;
+            opened = true;
+          }
+          b'"' => in_string = true,
+          _ => {}
+        }
+      }
+    }
+  }
+  writer.write_all(b"\n")?;
+  Ok(())
+}
+
+/// Bytes of file text on each side of a validation error.
+const VALIDATE_CONTEXT_BYTES: u64 = 40;
+
+/// Strictly parse the JSON value at `offset` (standard JSON: no comments or trailing commas) and
+/// report where it first fails, or `None` if it is valid. With `len` > 0 only that many bytes are
+/// read, and anything but whitespace after the value within them is an error too. Streams the
+/// value without building it.
+pub(crate) fn validate_json_value(path: &Path, offset: u64, len: u64) -> Result<Option<JsonSyntaxError>, CoreError> {
+  let mut f = File::open(path)?;
+  f.seek(SeekFrom::Start(offset))?;
+  let limit = if len > 0 { len } else { u64::MAX };
+  let mut de = serde_json::Deserializer::from_reader(BufReader::new(f).take(limit));
+  let parsed = <serde::de::IgnoredAny as serde::Deserialize>::deserialize(&mut de)
+    .and_then(|_| if len > 0 { de.end() } else { Ok(()) });
+  let err = match parsed {
+    Ok(()) => return Ok(None),
+    Err(e) if e.is_io() => return Err(std::io::Error::from(e).into()),
+    Err(e) => e,
+  };
+  let (line, column) = (err.line() as u64, err.column() as u64);
+  let position = format!(" at line {line} column {column}");
+  let message = err.to_string();
+  let message = message.strip_suffix(&position).unwrap_or(&message).to_string();
+
+  // The parser only counts lines; find where the error's line starts.
+  let mut reader = BufReader::with_capacity(1024 * 1024, File::open(path)?);
+  reader.seek(SeekFrom::Start(offset))?;
+  let mut line_start = offset;
+  let mut newlines = 0u64;
+  while newlines + 1 < line {
+    let buf = reader.fill_buf()?;
+    if buf.is_empty() {
+      break;
+    }
+    let mut used = buf.len();
+    for (i, &b) in buf.iter().enumerate() {
+      if b == b'\n' {
+        newlines += 1;
+        if newlines + 1 == line {
+          used = i + 1;
+          break;
+        }
+      }
+    }
+    line_start += used as u64;
+    reader.consume(used);
+  }
+  let byte_offset = line_start + column.saturating_sub(1);
+
+  let before_start = byte_offset.saturating_sub(VALIDATE_CONTEXT_BYTES).max(offset);
+  let after_end = (byte_offset + VALIDATE_CONTEXT_BYTES).min(offset.saturating_add(limit));
+  let mut context = Vec::new();
+  reader.seek(SeekFrom::Start(before_start))?;
+  (&mut reader)
+    .take(after_end.saturating_sub(before_start))
+    .read_to_end(&mut context)?;
+  let split = ((byte_offset - before_start) as usize).min(context.len());
+  Ok(Some(JsonSyntaxError {
+    message,
+    byte_offset,
+    line,
+    column,
+    context_before: String::from_utf8_lossy(&context[..split]).to_string(),
+    context_after: String::from_utf8_lossy(&context[split..]).to_string(),
+  }))
+}
+
+/// Strings shorter than this (not counting a `data:` prefix) aren't taken for base64.
+const BASE64_MIN_CHARS: u64 = 16;
+/// Letters-only strings need this many characters to pass for base64 rather than words.
+const BASE64_LETTERS_MIN_CHARS: u64 = 64;
+/// Longest `data:<mime>;base64,` prefix recognised.
+const DATA_URI_MAX_PREFIX: usize = 256;
+
+/// Content bytes of the JSON string whose opening quote has been read, escapes resolved. `\u`
+/// escapes outside ASCII become 0xFF, which no base64 alphabet contains.
+struct JsonStringBytes {
+  reader: BufReader<JsonSource>,
+  done: bool,
+}
+
+impl JsonStringBytes {
+  fn next_byte(&mut self) -> Result<Option<u8>, CoreError> {
+    if self.done {
+      return Ok(None);
+    }
+    let eof = || CoreError::InvalidArg("unexpected EOF in json string".into());
+    let b = read_one(&mut self.reader)?.ok_or_else(eof)?;
+    match b {
+      b'"' => {
+        self.done = true;
+        Ok(None)
+      }
+      b'\\' => {
+        let e = read_one(&mut self.reader)?.ok_or_else(eof)?;
+        Ok(Some(match e {
+          b'n' => b'\n',
+          b'r' => b'\r',
+          b't' => b'\t',
+          b'b' => 0x08,
+          b'f' => 0x0c,
+          b'u' => {
+            let mut hex = [0u8; 4];
+            self.reader.read_exact(&mut hex)?;
+            match std::str::from_utf8(&hex).ok().and_then(|h| u32::from_str_radix(h, 16).ok()) {
+              Some(c) if c < 0x80 => c as u8,
+              _ => 0xff,
+            }
+          }
+          other => other,
+        }))
+      }
+      b => Ok(Some(b)),
+    }
+  }
+}
+
+/// The base64 characters of a string node (line breaks dropped, a `data:` prefix skipped), as a
+/// reader for `base64::read::DecoderReader`.
+struct Base64Chars {
+  content: JsonStringBytes,
+  head: Vec<u8>,
+  head_pos: usize,
+}
+
+impl Read for Base64Chars {
+  fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+    let mut n = 0;
+    while n < buf.len() {
+      let b = if self.head_pos < self.head.len() {
+        self.head_pos += 1;
+        self.head[self.head_pos - 1]
+      } else {
+        match self.content.next_byte().map_err(std::io::Error::other)? {
+          Some(b) => b,
+          None => break,
+        }
+      };
+      if b != b'\n' && b != b'\r' {
+        buf[n] = b;
+        n += 1;
+      }
+    }
+    Ok(n)
+  }
+}
+
+/// Open the string node at `node_offset` for reading its base64 characters, with the media type
+/// of a `data:...;base64,` prefix. `None` if the node isn't a string, or has a `data:` prefix
+/// that isn't base64.
+fn open_base64_chars(
+  path: &Path,
+  lenient: bool,
+  node_offset: u64,
+) -> Result<Option<(Base64Chars, Option<String>)>, CoreError> {
+  let mut f = File::open(path)?;
+  let file_len = f.metadata().ok().map(|m| m.len()).unwrap_or(0);
   if node_offset > file_len {
     return Err(CoreError::InvalidArg(format!(
       "offset {} beyond file len {}",
@@ -1288,7 +1874,320 @@ pub(crate) fn json_node_summary_at_offset(
     )));
   }
   f.seek(SeekFrom::Start(node_offset))?;
-  let mut reader = BufReader::with_capacity(1024 * 1024, f);
+  let mut reader = BufReader::with_capacity(1024 * 1024, JsonSource::new(f, lenient)?);
+  let mut abs = node_offset;
+  let mut on_progress: Option<&mut dyn FnMut(u64, u64, &'static str)> = None;
+  skip_ws_and_nul(&mut reader, &mut abs, file_len, &mut on_progress)?;
+  if read_one(&mut reader)? != Some(b'"') {
+    return Ok(None);
+  }
+  let mut content = JsonStringBytes { reader, done: false };
+  let mut head = Vec::new();
+  while head.len() < 5 {
+    match content.next_byte()? {
+      Some(b) => head.push(b),
+      None => break,
+    }
+  }
+  let mut mime = None;
+  if head.eq_ignore_ascii_case(b"data:") {
+    let mut prefix = Vec::new();
+    loop {
+      match content.next_byte()? {
+        Some(b',') => break,
+        Some(b) if prefix.len() < DATA_URI_MAX_PREFIX => prefix.push(b),
+        _ => return Ok(None),
+      }
+    }
+    let Some(media) = prefix.strip_suffix(b";base64") else {
+      return Ok(None);
+    };
+    mime = Some(String::from_utf8_lossy(media).to_string()).filter(|m| !m.is_empty());
+    head.clear();
+  }
+  Ok(Some((Base64Chars { content, head, head_pos: 0 }, mime)))
+}
+
+/// How a string node reads as base64 (see `scan_base64_string`).
+struct Base64Shape {
+  mime: Option<String>,
+  url_safe: bool,
+  decoded_len: u64,
+}
+
+/// Whether the string node at `node_offset` looks like a base64 payload: only characters of one
+/// base64 alphabet (line breaks allowed), padding only at the end, a decodable length, at least
+/// `BASE64_MIN_CHARS`, and mixed-case with digits or symbols (or long), so ids and words don't
+/// pass. A `data:...;base64,` prefix is enough on its own. Reads the whole string once.
+fn scan_base64_string(path: &Path, lenient: bool, node_offset: u64) -> Result<Option<Base64Shape>, CoreError> {
+  let Some((mut chars, mime)) = open_base64_chars(path, lenient, node_offset)? else {
+    return Ok(None);
+  };
+  let (mut count, mut pad) = (0u64, 0u64);
+  let (mut standard, mut url_safe, mut digit, mut upper, mut lower) = (false, false, false, false, false);
+  let mut buf = vec![0u8; 64 * 1024];
+  loop {
+    let n = chars.read(&mut buf)?;
+    if n == 0 {
+      break;
+    }
+    for &b in &buf[..n] {
+      if b == b'=' {
+        pad += 1;
+        if pad > 2 {
+          return Ok(None);
+        }
+        continue;
+      }
+      if pad > 0 {
+        return Ok(None);
+      }
+      count += 1;
+      match b {
+        b'A'..=b'Z' => upper = true,
+        b'a'..=b'z' => lower = true,
+        b'0'..=b'9' => digit = true,
+        b'+' | b'/' => standard = true,
+        b'-' | b'_' => url_safe = true,
+        _ => return Ok(None),
+      }
+    }
+  }
+  let decodable = !(standard && url_safe) && count % 4 != 1 && (pad == 0 || (count + pad) % 4 == 0);
+  let encoded_like = mime.is_some()
+    || (count >= BASE64_MIN_CHARS
+      && upper
+      && lower
+      && (digit || standard || url_safe || count >= BASE64_LETTERS_MIN_CHARS));
+  if !(count > 0 && decodable && encoded_like) {
+    return Ok(None);
+  }
+  Ok(Some(Base64Shape { mime, url_safe, decoded_len: count * 3 / 4 }))
+}
+
+fn base64_engine(url_safe: bool) -> base64::engine::GeneralPurpose {
+  use base64::engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig};
+  let config = GeneralPurposeConfig::new()
+    .with_decode_padding_mode(DecodePaddingMode::Indifferent)
+    .with_decode_allow_trailing_bits(true);
+  let alphabet = if url_safe { &base64::alphabet::URL_SAFE } else { &base64::alphabet::STANDARD };
+  GeneralPurpose::new(alphabet, config)
+}
+
+/// Media type from the first bytes of common binary formats.
+fn sniff_mime(bytes: &[u8]) -> Option<&'static str> {
+  let magic: [(&[u8], &str); 7] = [
+    (b"\x89PNG\r\n\x1a\n", "image/png"),
+    (b"\xff\xd8\xff", "image/jpeg"),
+    (b"GIF8", "image/gif"),
+    (b"%PDF-", "application/pdf"),
+    (b"\x1f\x8b", "application/gzip"),
+    (b"PK\x03\x04", "application/zip"),
+    (b"RIFF", "image/webp"),
+  ];
+  magic
+    .iter()
+    .find(|(m, mime)| bytes.starts_with(m) && (*mime != "image/webp" || bytes.get(8..12) == Some(b"WEBP")))
+    .map(|(_, mime)| *mime)
+}
+
+/// Decode up to `max_bytes` of the string node at `node_offset` if it looks like base64 (see
+/// `scan_base64_string`), else `None`. Text that is valid UTF-8 without control characters is
+/// previewed as is, anything else as hex bytes.
+pub(crate) fn json_base64_preview(
+  path: &Path,
+  lenient: bool,
+  node_offset: u64,
+  max_bytes: usize,
+) -> Result<Option<Base64Preview>, CoreError> {
+  let Some(shape) = scan_base64_string(path, lenient, node_offset)? else {
+    return Ok(None);
+  };
+  let Some((chars, _)) = open_base64_chars(path, lenient, node_offset)? else {
+    return Ok(None);
+  };
+  let engine = base64_engine(shape.url_safe);
+  let mut bytes = Vec::new();
+  base64::read::DecoderReader::new(chars, &engine)
+    .take(max_bytes as u64)
+    .read_to_end(&mut bytes)?;
+  let text = match std::str::from_utf8(&bytes) {
+    Ok(s) => Some(s),
+    // A character cut off by `max_bytes`.
+    Err(e) if e.error_len().is_none() => std::str::from_utf8(&bytes[..e.valid_up_to()]).ok(),
+    Err(_) => None,
+  }
+  .filter(|s| !s.chars().any(|c| c.is_control() && !matches!(c, '\t' | '\n' | '\r')));
+  let (content, preview) = match text {
+    Some(s) => (Base64Content::Text, s.to_string()),
+    None => (
+      Base64Content::Binary,
+      bytes.iter().map(|x| format!("{x:02x}")).collect::<Vec<_>>().join(" "),
+    ),
+  };
+  Ok(Some(Base64Preview {
+    node_offset,
+    mime: shape.mime.or_else(|| sniff_mime(&bytes).map(str::to_string)),
+    url_safe: shape.url_safe,
+    decoded_len: shape.decoded_len,
+    content,
+    preview,
+    truncated: (bytes.len() as u64) < shape.decoded_len,
+  }))
+}
+
+/// Decode the base64 string node at `node_offset` into `writer`; returns the bytes written.
+pub(crate) fn decode_json_base64(
+  path: &Path,
+  lenient: bool,
+  node_offset: u64,
+  writer: &mut dyn Write,
+) -> Result<u64, CoreError> {
+  let not_base64 = || CoreError::InvalidArg(format!("node at {node_offset} is not a base64 string"));
+  let shape = scan_base64_string(path, lenient, node_offset)?.ok_or_else(not_base64)?;
+  let (chars, _) = open_base64_chars(path, lenient, node_offset)?.ok_or_else(not_base64)?;
+  let engine = base64_engine(shape.url_safe);
+  Ok(std::io::copy(&mut base64::read::DecoderReader::new(chars, &engine), writer)?)
+}
+
+/// Best-effort summary (kind + child count) for the selected subtree.
+///
+/// Counting can be expensive; we support caps to keep UI responsive.
+pub(crate) fn json_node_summary(
+  session_path: &Path,
+  lenient: bool,
+  record_offset: u64,
+  path_segments: &[JsonPathSegment],
+  max_items: u64,
+  max_scan_bytes: u64,
+) -> Result<JsonNodeSummary, CoreError> {
+  let mut f = File::open(session_path)?;
+  let file_len = f.metadata().ok().map(|m| m.len()).unwrap_or(0);
+  if record_offset > file_len {
+    return Err(CoreError::InvalidArg(format!(
+      "offset {} beyond file len {}",
+      record_offset, file_len
+    )));
+  }
+  f.seek(SeekFrom::Start(record_offset))?;
+  let mut reader = BufReader::with_capacity(1024 * 1024, JsonSource::new(f, lenient)?);
+  let mut abs = record_offset;
+  let total = file_len;
+  let mut on_progress: Option<&mut dyn FnMut(u64, u64, &'static str)> = None;
+
+  seek_to_subtree(&mut reader, &mut abs, total, &mut on_progress, path_segments)?;
+  skip_ws_and_nul(&mut reader, &mut abs, total, &mut on_progress)?;
+  let first = peek_byte(&mut reader)?.unwrap_or(b'?');
+  let kind = kind_from_first_byte(first);
+  if kind != JsonNodeKind::Object && kind != JsonNodeKind::Array {
+    return Ok(JsonNodeSummary {
+      kind,
+      child_count: None,
+      complete: true,
+    });
+  }
+
+  let start_abs = abs;
+  let mut count: u64 = 0;
+  let mut complete = true;
+
+  if kind == JsonNodeKind::Object {
+    consume_byte(&mut reader, &mut abs, total, &mut on_progress)?; // '{'
+    loop {
+      if abs.saturating_sub(start_abs) > max_scan_bytes || count >= max_items {
+        complete = false;
+        break;
+      }
+      skip_ws_and_nul(&mut reader, &mut abs, total, &mut on_progress)?;
+      match peek_byte(&mut reader)? {
+        Some(b'}') => {
+          consume_byte(&mut reader, &mut abs, total, &mut on_progress)?;
+          break;
+        }
+        None => break,
+        _ => {}
+      }
+      // key
+      skip_json_string_literal(&mut reader, &mut abs, total, &mut on_progress)?;
+      skip_ws_and_nul(&mut reader, &mut abs, total, &mut on_progress)?;
+      expect_byte(&mut reader, &mut abs, total, &mut on_progress, b':')?;
+      skip_ws_and_nul(&mut reader, &mut abs, total, &mut on_progress)?;
+      // value
+      let _ = scan_one_json_value_with_stops(&mut reader, &mut abs, total, None, b",}", &mut on_progress)?;
+      count += 1;
+      skip_ws_and_nul(&mut reader, &mut abs, total, &mut on_progress)?;
+      match peek_byte(&mut reader)? {
+        Some(b',') => {
+          consume_byte(&mut reader, &mut abs, total, &mut on_progress)?;
+          continue;
+        }
+        Some(b'}') => {
+          consume_byte(&mut reader, &mut abs, total, &mut on_progress)?;
+          break;
+        }
+        None => break,
+        _ => break,
+      }
+    }
+  } else {
+    consume_byte(&mut reader, &mut abs, total, &mut on_progress)?; // '['
+    loop {
+      if abs.saturating_sub(start_abs) > max_scan_bytes || count >= max_items {
+        complete = false;
+        break;
+      }
+      skip_ws_and_nul(&mut reader, &mut abs, total, &mut on_progress)?;
+      match peek_byte(&mut reader)? {
+        Some(b']') => {
+          consume_byte(&mut reader, &mut abs, total, &mut on_progress)?;
+          break;
+        }
+        None => break,
+        _ => {}
+      }
+      let _ = scan_one_json_value_with_stops(&mut reader, &mut abs, total, None, b",]", &mut on_progress)?;
+      count += 1;
+      skip_ws_and_nul(&mut reader, &mut abs, total, &mut on_progress)?;
+      match peek_byte(&mut reader)? {
+        Some(b',') => {
+          consume_byte(&mut reader, &mut abs, total, &mut on_progress)?;
+          continue;
+        }
+        Some(b']') => {
+          consume_byte(&mut reader, &mut abs, total, &mut on_progress)?;
+          break;
+        }
+        None => break,
+        _ => break,
+      }
+    }
+  }
+
+  Ok(JsonNodeSummary {
+    kind,
+    child_count: Some(count),
+    complete,
+  })
+}
+
+/// Best-effort summary (kind + child count) for the node at `node_offset`.
+pub(crate) fn json_node_summary_at_offset(
+  session_path: &Path,
+  lenient: bool,
+  node_offset: u64,
+  max_items: u64,
+  max_scan_bytes: u64,
+) -> Result<JsonNodeSummaryOffset, CoreError> {
+  let mut f = File::open(session_path)?;
+  let file_len = f.metadata().ok().map(|m| m.len()).unwrap_or(0);
+  if node_offset > file_len {
+    return Err(CoreError::InvalidArg(format!(
+      "offset {} beyond file len {}",
+      node_offset, file_len
+    )));
+  }
+  f.seek(SeekFrom::Start(node_offset))?;
+  let mut reader = BufReader::with_capacity(1024 * 1024, JsonSource::new(f, lenient)?);
   let mut abs = node_offset;
   let total = file_len;
   let mut on_progress: Option<&mut dyn FnMut(u64, u64, &'static str)> = None;
@@ -1331,7 +2230,7 @@ pub(crate) fn json_node_summary_at_offset(
       expect_byte(&mut reader, &mut abs, total, &mut on_progress, b':')?;
       skip_ws_and_nul(&mut reader, &mut abs, total, &mut on_progress)?;
       // value
-      let _ = scan_one_json_value_with_stops(&mut reader, &mut abs, total, None, &[b',', b'}'], &mut on_progress)?;
+      let _ = scan_one_json_value_with_stops(&mut reader, &mut abs, total, None, b",}", &mut on_progress)?;
       count += 1;
       skip_ws_and_nul(&mut reader, &mut abs, total, &mut on_progress)?;
       match peek_byte(&mut reader)? {
@@ -1363,7 +2262,7 @@ pub(crate) fn json_node_summary_at_offset(
         None => break,
         _ => {}
       }
-      let _ = scan_one_json_value_with_stops(&mut reader, &mut abs, total, None, &[b',', b']'], &mut on_progress)?;
+      let _ = scan_one_json_value_with_stops(&mut reader, &mut abs, total, None, b",]", &mut on_progress)?;
       count += 1;
       skip_ws_and_nul(&mut reader, &mut abs, total, &mut on_progress)?;
       match peek_byte(&mut reader)? {
@@ -1381,19 +2280,264 @@ pub(crate) fn json_node_summary_at_offset(
     }
   }
 
-  Ok(JsonNodeSummaryOffset {
-    kind,
-    child_count: Some(count),
-    complete,
-    node_offset,
-  })
+  Ok(JsonNodeSummaryOffset {
+    kind,
+    child_count: Some(count),
+    complete,
+    node_offset,
+  })
+}
+
+/// Size and shape of the JSON value at `node_offset`, read to its end in one streaming pass.
+pub(crate) fn json_node_stats_at_offset(session_path: &Path, lenient: bool, node_offset: u64) -> Result<JsonNodeStats, CoreError> {
+  let mut f = File::open(session_path)?;
+  let file_len = f.metadata().ok().map(|m| m.len()).unwrap_or(0);
+  if node_offset > file_len {
+    return Err(CoreError::InvalidArg(format!(
+      "offset {} beyond file len {}",
+      node_offset, file_len
+    )));
+  }
+  f.seek(SeekFrom::Start(node_offset))?;
+  let mut reader = BufReader::with_capacity(1024 * 1024, JsonSource::new(f, lenient)?);
+  let mut abs = node_offset;
+  let total = file_len;
+  let mut on_progress: Option<&mut dyn FnMut(u64, u64, &'static str)> = None;
+
+  skip_ws_and_nul(&mut reader, &mut abs, total, &mut on_progress)?;
+  let start_abs = abs;
+  let first = peek_byte(&mut reader)?.ok_or_else(|| CoreError::InvalidArg("unexpected EOF".into()))?;
+  let mut stats = JsonNodeStats {
+    node_offset: start_abs,
+    kind: kind_from_first_byte(first),
+    byte_len: 0,
+    max_depth: 0,
+    child_count: None,
+    elements: 0,
+    keys: 0,
+  };
+
+  /// What the next token starts, if anything is expected.
+  #[derive(PartialEq)]
+  enum Next {
+    Key,
+    Value,
+    Other,
+  }
+  // Open containers (`{` / `[`), innermost last.
+  let mut stack: Vec<u8> = Vec::new();
+  let mut next = Next::Value;
+  let mut in_string = false;
+  let mut escape = false;
+  let mut children: u64 = 0;
+  while let Some(b) = peek_byte(&mut reader)? {
+    if !in_string && stack.is_empty() && next == Next::Other && (b"{[]},:\"".contains(&b) || is_ignorable_head_byte(b)) {
+      // A scalar root ends here.
+      break;
+    }
+    consume_byte(&mut reader, &mut abs, total, &mut on_progress)?;
+    if in_string {
+      if escape {
+        escape = false;
+      } else if b == b'\\' {
+        escape = true;
+      } else if b == b'"' {
+        in_string = false;
+        if stack.is_empty() {
+          break;
+        }
+      }
+      continue;
+    }
+    let in_array = stack.last() == Some(&b'[');
+    let starts_value = next == Next::Value && !matches!(b, b']' | b'}' | b',' | b':') && !is_ignorable_head_byte(b);
+    if starts_value && in_array {
+      stats.elements += 1;
+      if stack.len() == 1 {
+        children += 1;
+      }
+    }
+    if next == Next::Key && b == b'"' {
+      stats.keys += 1;
+      if stack.len() == 1 {
+        children += 1;
+      }
+    }
+    match b {
+      b'{' | b'[' => {
+        stack.push(b);
+        stats.max_depth = stats.max_depth.max(stack.len() as u64);
+        next = if b == b'{' { Next::Key } else { Next::Value };
+      }
+      b'}' | b']' => {
+        stack.pop();
+        next = Next::Other;
+        if stack.is_empty() {
+          break;
+        }
+      }
+      b',' => next = if stack.last() == Some(&b'{') { Next::Key } else { Next::Value },
+      b':' => next = Next::Value,
+      b'"' => {
+        in_string = true;
+        next = Next::Other;
+      }
+      _ if is_ignorable_head_byte(b) => {}
+      _ => next = Next::Other,
+    }
+  }
+  if in_string || !stack.is_empty() {
+    return Err(CoreError::InvalidArg(format!("JSON value at offset {start_abs} is not closed")));
+  }
+  stats.byte_len = abs - start_abs;
+  if matches!(stats.kind, JsonNodeKind::Object | JsonNodeKind::Array) {
+    stats.child_count = Some(children);
+  }
+  Ok(stats)
+}
+
+/// Offset-based JSON lazy tree (v2): list direct children at `node_offset`.
+///
+/// See `dh_core::models::JsonChildrenPageOffset` for cursor semantics.
+/// The containers of the record at `record_offset` (`record_len` bytes) worth indexing: the root
+/// and every object / array of at least `min_node_bytes`, read in one streaming pass. `None` if
+/// `should_stop` cut the walk short.
+pub(crate) fn index_json_record(
+  path: &Path,
+  lenient: bool,
+  record_offset: u64,
+  record_len: u64,
+  min_node_bytes: u64,
+  should_stop: impl Fn() -> bool,
+  mut on_progress: impl FnMut(ScanProgress),
+) -> Result<Option<Vec<IndexedNode>>, CoreError> {
+  let mut f = File::open(path)?;
+  let file_len = f.metadata().ok().map(|m| m.len()).unwrap_or(0);
+  if record_offset > file_len {
+    return Err(CoreError::InvalidArg(format!(
+      "offset {} beyond file len {}",
+      record_offset, file_len
+    )));
+  }
+  f.seek(SeekFrom::Start(record_offset))?;
+  let mut reader = BufReader::with_capacity(1024 * 1024, JsonSource::new(f, lenient)?);
+  let mut abs = record_offset;
+  let total = file_len;
+  let mut last_report = record_offset;
+  let mut report = |done: u64, _total: u64, _stage: &'static str| {
+    if done >= last_report + 1024 * 1024 {
+      last_report = done;
+      let read = done - record_offset;
+      on_progress(ScanProgress::new(read, record_len, read, 0));
+    }
+  };
+  let mut on_progress: Option<&mut dyn FnMut(u64, u64, &'static str)> = Some(&mut report);
+
+  /// An open container.
+  struct Frame {
+    path_hash: u64,
+    offset: u64,
+    children: u64,
+    object: bool,
+  }
+  let mut stack: Vec<Frame> = Vec::new();
+  let mut nodes: Vec<IndexedNode> = Vec::new();
+  let mut path_hash = ROOT_PATH_HASH;
+
+  skip_bom_and_ws(&mut reader, &mut abs, total, &mut on_progress)?;
+  'values: loop {
+    if should_stop() {
+      return Ok(None);
+    }
+    // A value starts here; `path_hash` is its path's.
+    skip_ws_and_nul(&mut reader, &mut abs, total, &mut on_progress)?;
+    let value_offset = abs;
+    let opened = match peek_byte(&mut reader)? {
+      Some(b @ (b'{' | b'[')) => {
+        consume_byte(&mut reader, &mut abs, total, &mut on_progress)?;
+        stack.push(Frame {
+          path_hash,
+          offset: value_offset,
+          children: 0,
+          object: b == b'{',
+        });
+        true
+      }
+      Some(_) => {
+        scan_one_json_value_with_stops(&mut reader, &mut abs, total, None, b",]}", &mut on_progress)?;
+        false
+      }
+      None => return Err(CoreError::InvalidArg("unexpected EOF".into())),
+    };
+
+    // Move on to the next value: close finished containers, then read a key / index.
+    let mut finished = !opened;
+    loop {
+      let Some(top) = stack.last_mut() else {
+        break 'values;
+      };
+      skip_ws_and_nul(&mut reader, &mut abs, total, &mut on_progress)?;
+      let close = if top.object { b'}' } else { b']' };
+      if peek_byte(&mut reader)? == Some(close) {
+        consume_byte(&mut reader, &mut abs, total, &mut on_progress)?;
+        let Some(done) = stack.pop() else {
+          break 'values;
+        };
+        let byte_len = abs - done.offset;
+        if byte_len >= min_node_bytes || stack.is_empty() {
+          nodes.push(IndexedNode {
+            path_hash: done.path_hash,
+            offset: done.offset,
+            byte_len,
+            child_count: done.children,
+            object: done.object,
+          });
+        }
+        finished = true;
+        continue;
+      }
+      if finished {
+        expect_byte(&mut reader, &mut abs, total, &mut on_progress, b',')?;
+        skip_ws_and_nul(&mut reader, &mut abs, total, &mut on_progress)?;
+      }
+      let seg = if top.object {
+        let key = read_json_string(&mut reader, &mut abs, total, &mut on_progress)?;
+        skip_ws_and_nul(&mut reader, &mut abs, total, &mut on_progress)?;
+        expect_byte(&mut reader, &mut abs, total, &mut on_progress, b':')?;
+        JsonPathSegment::Key(key)
+      } else {
+        JsonPathSegment::Index(top.children)
+      };
+      top.children += 1;
+      path_hash = child_path_hash(top.path_hash, &seg);
+      continue 'values;
+    }
+  }
+  Ok(Some(nodes))
+}
+
+/// Absolute offset of the node at `path_segments` under the record at `record_offset`.
+pub(crate) fn json_node_offset(session_path: &Path, lenient: bool, record_offset: u64, path_segments: &[JsonPathSegment]) -> Result<u64, CoreError> {
+  let mut f = File::open(session_path)?;
+  let file_len = f.metadata().ok().map(|m| m.len()).unwrap_or(0);
+  if record_offset > file_len {
+    return Err(CoreError::InvalidArg(format!(
+      "offset {} beyond file len {}",
+      record_offset, file_len
+    )));
+  }
+  f.seek(SeekFrom::Start(record_offset))?;
+  let mut reader = BufReader::with_capacity(1024 * 1024, JsonSource::new(f, lenient)?);
+  let mut abs = record_offset;
+  let mut on_progress: Option<&mut dyn FnMut(u64, u64, &'static str)> = None;
+  seek_to_subtree(&mut reader, &mut abs, file_len, &mut on_progress, path_segments)?;
+  skip_ws_and_nul(&mut reader, &mut abs, file_len, &mut on_progress)?;
+  Ok(abs)
 }
 
-/// Offset-based JSON lazy tree (v2): list direct children at `node_offset`.
-///
-/// See `dh_core::models::JsonChildrenPageOffset` for cursor semantics.
 pub(crate) fn list_json_children_page_at_offset(
   path: &Path,
+  lenient: bool,
   node_offset: u64,
   cursor_offset: Option<u64>,
   cursor_index: Option<u64>,
@@ -1409,7 +2553,7 @@ pub(crate) fn list_json_children_page_at_offset(
     )));
   }
   f.seek(SeekFrom::Start(node_offset))?;
-  let mut reader = BufReader::with_capacity(1024 * 1024, f);
+  let mut reader = BufReader::with_capacity(1024 * 1024, JsonSource::new(f, lenient)?);
   let mut abs = node_offset;
   let total = file_len;
   let mut on_progress: Option<&mut dyn FnMut(u64, u64, &'static str)> = None;
@@ -1426,9 +2570,10 @@ pub(crate) fn list_json_children_page_at_offset(
   };
 
   match first {
-    b'{' => list_object_children_at_offset(path, node_offset, cursor_offset, limit, preview_max_chars),
+    b'{' => list_object_children_at_offset(path, lenient, node_offset, cursor_offset, limit, preview_max_chars),
     b'[' => list_array_children_at_offset(
       path,
+      lenient,
       node_offset,
       cursor_offset,
       cursor_index,
@@ -1444,8 +2589,197 @@ pub(crate) fn list_json_children_page_at_offset(
   }
 }
 
+/// Children read at most to list a node in sorted order.
+const SORTED_MAX_CHILDREN: usize = 200_000;
+
+/// All direct children of the object / array at `node_offset`, in `sort` order (read in one
+/// pass; sorting needs them all, so pages of a sorted listing each read the whole node).
+pub(crate) fn sorted_json_children_at_offset(
+  path: &Path,
+  lenient: bool,
+  node_offset: u64,
+  sort: JsonChildSort,
+  preview_max_chars: usize,
+) -> Result<Vec<JsonChildItemOffset>, CoreError> {
+  let mut f = File::open(path)?;
+  let file_len = f.metadata().ok().map(|m| m.len()).unwrap_or(0);
+  if node_offset > file_len {
+    return Err(CoreError::InvalidArg(format!(
+      "offset {} beyond file len {}",
+      node_offset, file_len
+    )));
+  }
+  f.seek(SeekFrom::Start(node_offset))?;
+  let mut reader = BufReader::with_capacity(1024 * 1024, JsonSource::new(f, lenient)?);
+  let mut abs = node_offset;
+  let total = file_len;
+  let mut on_progress: Option<&mut dyn FnMut(u64, u64, &'static str)> = None;
+
+  skip_ws_and_nul(&mut reader, &mut abs, total, &mut on_progress)?;
+  let close = match peek_byte(&mut reader)? {
+    Some(b'{') => b'}',
+    Some(b'[') => b']',
+    _ => return Ok(Vec::new()),
+  };
+  consume_byte(&mut reader, &mut abs, total, &mut on_progress)?;
+
+  // (child, value length in bytes)
+  let mut children: Vec<(JsonChildItemOffset, u64)> = Vec::new();
+  loop {
+    skip_ws_and_nul(&mut reader, &mut abs, total, &mut on_progress)?;
+    match peek_byte(&mut reader)? {
+      Some(b) if b == close => break,
+      None => break,
+      Some(b',') if !children.is_empty() => {
+        consume_byte(&mut reader, &mut abs, total, &mut on_progress)?;
+        skip_ws_and_nul(&mut reader, &mut abs, total, &mut on_progress)?;
+      }
+      _ => {}
+    }
+    if children.len() >= SORTED_MAX_CHILDREN {
+      return Err(CoreError::InvalidArg(format!(
+        "node has over {SORTED_MAX_CHILDREN} children; list it in original order"
+      )));
+    }
+    let seg = if close == b'}' {
+      let key = read_json_string(&mut reader, &mut abs, total, &mut on_progress)?;
+      skip_ws_and_nul(&mut reader, &mut abs, total, &mut on_progress)?;
+      expect_byte(&mut reader, &mut abs, total, &mut on_progress, b':')?;
+      skip_ws_and_nul(&mut reader, &mut abs, total, &mut on_progress)?;
+      JsonPathSegment::Key(key)
+    } else {
+      JsonPathSegment::Index(children.len() as u64)
+    };
+    let value_offset = abs;
+    let kind = kind_from_first_byte(peek_byte(&mut reader)?.unwrap_or(b'?'));
+    let scanned = scan_one_json_value_with_stops(
+      &mut reader,
+      &mut abs,
+      total,
+      Some(preview_max_chars.max(64) * 4),
+      &[b',', close],
+      &mut on_progress,
+    )?;
+    let (preview, truncated) = preview_from_scan(scanned.captured, scanned.total_len_bytes, preview_max_chars);
+    let preview = if truncated && !preview.ends_with('…') {
+      format!("{preview}…")
+    } else {
+      preview
+    };
+    children.push((
+      JsonChildItemOffset {
+        seg,
+        kind,
+        preview,
+        value_offset,
+      },
+      scanned.total_len_bytes,
+    ));
+  }
+
+  match sort {
+    JsonChildSort::Original => {}
+    JsonChildSort::Key => children.sort_by_cached_key(|(child, _)| match &child.seg {
+      JsonPathSegment::Key(key) => (key.to_lowercase(), key.clone()),
+      JsonPathSegment::Index(_) => Default::default(),
+    }),
+    JsonChildSort::Size => children.sort_by(|(_, a), (_, b)| b.cmp(a)),
+  }
+  Ok(children.into_iter().map(|(child, _)| child).collect())
+}
+
+/// Members read per object element in `json_array_table_page`.
+const TABLE_MAX_MEMBERS: usize = 10_000;
+
+/// A page of the array at `node_offset` as table rows: one per element, with a column per object
+/// key (`known_columns` first, so column order holds across pages). Pages like
+/// `list_array_children_at_offset`.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn json_array_table_page(
+  path: &Path,
+  lenient: bool,
+  node_offset: u64,
+  known_columns: Vec<String>,
+  cursor_offset: Option<u64>,
+  cursor_index: Option<u64>,
+  limit: usize,
+  preview_max_chars: usize,
+) -> Result<JsonTablePage, CoreError> {
+  let mut f = File::open(path)?;
+  let file_len = f.metadata().ok().map(|m| m.len()).unwrap_or(0);
+  if node_offset > file_len {
+    return Err(CoreError::InvalidArg(format!(
+      "offset {} beyond file len {}",
+      node_offset, file_len
+    )));
+  }
+  f.seek(SeekFrom::Start(node_offset))?;
+  let mut reader = BufReader::new(JsonSource::new(f, lenient)?);
+  let mut abs = node_offset;
+  skip_ws_and_nul(&mut reader, &mut abs, file_len, &mut None)?;
+  if peek_byte(&mut reader)? != Some(b'[') {
+    return Err(CoreError::InvalidArg(format!("node at {node_offset} is not an array")));
+  }
+
+  let elements =
+    list_array_children_at_offset(path, lenient, node_offset, cursor_offset, cursor_index, limit, preview_max_chars)?;
+  let mut columns = known_columns;
+  let mut rows = Vec::with_capacity(elements.items.len());
+  for element in elements.items {
+    let JsonPathSegment::Index(index) = element.seg else {
+      continue;
+    };
+    let mut row = JsonTableRow {
+      index,
+      value_offset: element.value_offset,
+      kind: element.kind.clone(),
+      cells: Vec::new(),
+      preview: None,
+    };
+    if element.kind == JsonNodeKind::Object {
+      let members =
+        list_object_children_at_offset(path, lenient, element.value_offset, None, TABLE_MAX_MEMBERS, preview_max_chars)?;
+      for member in members.items {
+        let JsonPathSegment::Key(key) = member.seg else {
+          continue;
+        };
+        let col = match columns.iter().position(|c| *c == key) {
+          Some(col) => col,
+          None => {
+            columns.push(key);
+            columns.len() - 1
+          }
+        };
+        if row.cells.len() <= col {
+          row.cells.resize(col + 1, None);
+        }
+        row.cells[col] = Some(JsonTableCell {
+          kind: member.kind,
+          preview: member.preview,
+          value_offset: member.value_offset,
+        });
+      }
+    } else {
+      row.preview = Some(element.preview);
+    }
+    rows.push(row);
+  }
+  for row in &mut rows {
+    row.cells.resize(columns.len(), None);
+  }
+
+  Ok(JsonTablePage {
+    columns,
+    rows,
+    next_cursor_offset: elements.next_cursor_offset,
+    next_cursor_index: elements.next_cursor_index,
+    reached_end: elements.reached_end,
+  })
+}
+
 fn list_object_children_at_offset(
   path: &Path,
+  lenient: bool,
   node_offset: u64,
   cursor_offset: Option<u64>,
   limit: usize,
@@ -1460,7 +2794,7 @@ fn list_object_children_at_offset(
     )));
   }
   f.seek(SeekFrom::Start(node_offset))?;
-  let mut reader = BufReader::with_capacity(1024 * 1024, f);
+  let mut reader = BufReader::with_capacity(1024 * 1024, JsonSource::new(f, lenient)?);
   let mut abs = node_offset;
   let total = file_len;
   let mut on_progress: Option<&mut dyn FnMut(u64, u64, &'static str)> = None;
@@ -1490,7 +2824,7 @@ fn list_object_children_at_offset(
   // Seek to cursor.
   let mut f2 = File::open(path)?;
   f2.seek(SeekFrom::Start(want))?;
-  let mut reader = BufReader::with_capacity(1024 * 1024, f2);
+  let mut reader = BufReader::with_capacity(1024 * 1024, JsonSource::new(f2, lenient)?);
   let mut abs = want;
   let total = file_len;
   let mut on_progress: Option<&mut dyn FnMut(u64, u64, &'static str)> = None;
@@ -1534,8 +2868,8 @@ fn list_object_children_at_offset(
       &mut reader,
       &mut abs,
       total,
-      Some((preview_max_chars.max(64) * 4) as usize),
-      &[b',', b'}'],
+      Some(preview_max_chars.max(64) * 4),
+      b",}",
       &mut on_progress,
     )?;
     let (preview, truncated) = preview_from_scan(scanned.captured, scanned.total_len_bytes, preview_max_chars);
@@ -1591,6 +2925,7 @@ fn list_object_children_at_offset(
 
 fn list_array_children_at_offset(
   path: &Path,
+  lenient: bool,
   node_offset: u64,
   cursor_offset: Option<u64>,
   cursor_index: Option<u64>,
@@ -1606,7 +2941,7 @@ fn list_array_children_at_offset(
     )));
   }
   f.seek(SeekFrom::Start(node_offset))?;
-  let mut reader = BufReader::with_capacity(1024 * 1024, f);
+  let mut reader = BufReader::with_capacity(1024 * 1024, JsonSource::new(f, lenient)?);
   let mut abs = node_offset;
   let total = file_len;
   let mut on_progress: Option<&mut dyn FnMut(u64, u64, &'static str)> = None;
@@ -1636,7 +2971,7 @@ fn list_array_children_at_offset(
 
   let mut f2 = File::open(path)?;
   f2.seek(SeekFrom::Start(want_off))?;
-  let mut reader = BufReader::with_capacity(1024 * 1024, f2);
+  let mut reader = BufReader::with_capacity(1024 * 1024, JsonSource::new(f2, lenient)?);
   let mut abs = want_off;
   let total = file_len;
   let mut on_progress: Option<&mut dyn FnMut(u64, u64, &'static str)> = None;
@@ -1678,8 +3013,8 @@ fn list_array_children_at_offset(
       &mut reader,
       &mut abs,
       total,
-      Some((preview_max_chars.max(64) * 4) as usize),
-      &[b',', b']'],
+      Some(preview_max_chars.max(64) * 4),
+      b",]",
       &mut on_progress,
     )?;
     let (preview, truncated) = preview_from_scan(scanned.captured, scanned.total_len_bytes, preview_max_chars);
@@ -1739,8 +3074,411 @@ fn list_array_children_at_offset(
   })
 }
 
+/// Nodes of the JSON value at `record_offset` whose key or scalar value contains
+/// `query.text`, in document order, found in one streaming pass: only the current path and
+/// single strings are held in memory, so giant records are fine.
+pub(crate) fn find_in_json_record(
+  path: &Path,
+  lenient: bool,
+  record_offset: u64,
+  query: &JsonFindQuery,
+  preview_max_chars: usize,
+) -> Result<JsonFindResult, CoreError> {
+  let mut f = File::open(path)?;
+  let file_len = f.metadata().ok().map(|m| m.len()).unwrap_or(0);
+  if record_offset > file_len {
+    return Err(CoreError::InvalidArg(format!(
+      "offset {} beyond file len {}",
+      record_offset, file_len
+    )));
+  }
+  f.seek(SeekFrom::Start(record_offset))?;
+  let mut reader = BufReader::with_capacity(1024 * 1024, JsonSource::new(f, lenient)?);
+  let mut abs = record_offset;
+  let total = file_len;
+  let mut on_progress: Option<&mut dyn FnMut(u64, u64, &'static str)> = None;
+
+  let needle = if query.case_sensitive {
+    query.text.clone()
+  } else {
+    query.text.to_lowercase()
+  };
+  let matches = |text: &str| {
+    if query.case_sensitive {
+      text.contains(&needle)
+    } else {
+      text.to_lowercase().contains(&needle)
+    }
+  };
+  let max_hits = if query.max_hits == 0 { 100 } else { query.max_hits };
+  let mut hits: Vec<JsonFindHit> = Vec::new();
+
+  /// Open containers, innermost last: objects, or arrays with their next element index.
+  enum Open {
+    Object,
+    Array(u64),
+  }
+  let mut stack: Vec<Open> = Vec::new();
+  let mut segs: Vec<JsonPathSegment> = Vec::new();
+  let mut offsets: Vec<u64> = Vec::new();
+  // Whether the member just read matched by its key (reported with its value's offset).
+  let mut key_matched = false;
+
+  skip_bom_and_ws(&mut reader, &mut abs, total, &mut on_progress)?;
+  'values: loop {
+    // A value starts here; `segs` / `offsets` already lead to it.
+    skip_ws_and_nul(&mut reader, &mut abs, total, &mut on_progress)?;
+    let value_offset = abs;
+    let first = peek_byte(&mut reader)?.ok_or_else(|| CoreError::InvalidArg("unexpected EOF".into()))?;
+    let kind = kind_from_first_byte(first);
+    let hit = |key_match: bool, preview: &str| JsonFindHit {
+      path: segs.clone(),
+      node_offsets: offsets.clone(),
+      value_offset,
+      kind: kind.clone(),
+      key_match,
+      preview: truncate_chars(preview, preview_max_chars),
+    };
+    let opened = match first {
+      b'{' | b'[' => {
+        if std::mem::take(&mut key_matched) {
+          hits.push(hit(true, if first == b'{' { "{…}" } else { "[…]" }));
+        }
+        consume_byte(&mut reader, &mut abs, total, &mut on_progress)?;
+        stack.push(if first == b'{' { Open::Object } else { Open::Array(0) });
+        true
+      }
+      _ => {
+        let text = if first == b'"' {
+          read_json_string(&mut reader, &mut abs, total, &mut on_progress)?
+        } else {
+          let mut literal = Vec::new();
+          while let Some(b) = peek_byte(&mut reader)? {
+            if matches!(b, b',' | b']' | b'}') || is_ignorable_head_byte(b) {
+              break;
+            }
+            literal.push(consume_byte(&mut reader, &mut abs, total, &mut on_progress)?);
+          }
+          String::from_utf8_lossy(&literal).into_owned()
+        };
+        if std::mem::take(&mut key_matched) {
+          hits.push(hit(true, &text));
+        } else if query.values && matches(&text) {
+          hits.push(hit(false, &text));
+        }
+        false
+      }
+    };
+    if hits.len() >= max_hits {
+      return Ok(JsonFindResult { hits, truncated: true });
+    }
+
+    // Move on to the next value: close finished containers, then read a key / index.
+    let mut finished = !opened;
+    loop {
+      if finished {
+        if stack.is_empty() {
+          break 'values;
+        }
+        segs.pop();
+        offsets.pop();
+      }
+      let Some(top) = stack.last_mut() else {
+        break 'values;
+      };
+      skip_ws_and_nul(&mut reader, &mut abs, total, &mut on_progress)?;
+      let close = if matches!(top, Open::Object) { b'}' } else { b']' };
+      if peek_byte(&mut reader)? == Some(close) {
+        consume_byte(&mut reader, &mut abs, total, &mut on_progress)?;
+        stack.pop();
+        finished = true;
+        continue;
+      }
+      if finished {
+        expect_byte(&mut reader, &mut abs, total, &mut on_progress, b',')?;
+      }
+      match top {
+        Open::Object => {
+          let key = read_json_string(&mut reader, &mut abs, total, &mut on_progress)?;
+          skip_ws_and_nul(&mut reader, &mut abs, total, &mut on_progress)?;
+          expect_byte(&mut reader, &mut abs, total, &mut on_progress, b':')?;
+          key_matched = query.keys && matches(&key);
+          segs.push(JsonPathSegment::Key(key));
+        }
+        Open::Array(next) => {
+          segs.push(JsonPathSegment::Index(*next));
+          *next += 1;
+        }
+      }
+      skip_ws_and_nul(&mut reader, &mut abs, total, &mut on_progress)?;
+      offsets.push(abs);
+      continue 'values;
+    }
+  }
+  Ok(JsonFindResult { hits, truncated: false })
+}
+
+/// One step of a JSONPath expression (see `parse_json_path`).
+#[derive(Debug, Clone, PartialEq)]
+enum JsonPathStep {
+  Key(String),
+  Index(u64),
+  Wildcard,
+  /// `..`: the next step may match at any depth below.
+  Descend,
+}
+
+/// Parses the JSONPath subset `eval_json_path_in_record` understands: `$` followed by
+/// `.key`, `['key']` / `["key"]`, `[n]`, `.*` / `[*]` and `..` (recursive descent) steps.
+fn parse_json_path(expr: &str) -> Result<Vec<JsonPathStep>, CoreError> {
+  let bad = |why: &str| CoreError::InvalidArg(format!("invalid JSONPath {expr:?}: {why}"));
+  let rest = expr.trim();
+  let Some(rest) = rest.strip_prefix('$') else {
+    return Err(bad("must start with `$`"));
+  };
+  let chars: Vec<char> = rest.chars().collect();
+  let mut steps = Vec::new();
+  let mut i = 0;
+  while i < chars.len() {
+    match chars[i] {
+      '.' => {
+        i += 1;
+        if chars.get(i) == Some(&'.') {
+          steps.push(JsonPathStep::Descend);
+          i += 1;
+          if chars.get(i) == Some(&'[') {
+            continue;
+          }
+        }
+        let start = i;
+        while i < chars.len() && chars[i] != '.' && chars[i] != '[' {
+          i += 1;
+        }
+        let name: String = chars[start..i].iter().collect();
+        match name.as_str() {
+          "" => return Err(bad("empty member name")),
+          "*" => steps.push(JsonPathStep::Wildcard),
+          _ => steps.push(JsonPathStep::Key(name)),
+        }
+      }
+      '[' => {
+        i += 1;
+        match chars.get(i) {
+          Some(&q) if q == '\'' || q == '"' => {
+            i += 1;
+            let mut name = String::new();
+            loop {
+              match chars.get(i) {
+                None => return Err(bad("unterminated quoted name")),
+                Some('\\') => {
+                  name.extend(chars.get(i + 1));
+                  i += 2;
+                }
+                Some(&c) if c == q => {
+                  i += 1;
+                  break;
+                }
+                Some(&c) => {
+                  name.push(c);
+                  i += 1;
+                }
+              }
+            }
+            steps.push(JsonPathStep::Key(name));
+          }
+          _ => {
+            let start = i;
+            while i < chars.len() && chars[i] != ']' {
+              i += 1;
+            }
+            let inner: String = chars[start..i].iter().collect();
+            let inner = inner.trim();
+            if inner == "*" {
+              steps.push(JsonPathStep::Wildcard);
+            } else {
+              let index = inner
+                .parse::<u64>()
+                .map_err(|_| bad("brackets take `*`, an index or a quoted name"))?;
+              steps.push(JsonPathStep::Index(index));
+            }
+          }
+        }
+        if chars.get(i) != Some(&']') {
+          return Err(bad("missing `]`"));
+        }
+        i += 1;
+      }
+      c => return Err(bad(&format!("unexpected {c:?}"))),
+    }
+  }
+  if steps.last() == Some(&JsonPathStep::Descend) {
+    return Err(bad("`..` must be followed by a step"));
+  }
+  Ok(steps)
+}
+
+/// The steps reached by a child under `seg` of a node that reached `states` (indexes into
+/// `steps`; `steps.len()` means the whole path matched). A `..` step stays reachable at every
+/// depth below the node that reached it.
+fn json_path_child_states(steps: &[JsonPathStep], states: &[usize], seg: &JsonPathSegment) -> Vec<usize> {
+  let mut out: Vec<usize> = Vec::new();
+  for &s in states {
+    let mut step = s;
+    if steps.get(s) == Some(&JsonPathStep::Descend) {
+      if !out.contains(&s) {
+        out.push(s);
+      }
+      step += 1;
+    }
+    let matched = match (steps.get(step), seg) {
+      (Some(JsonPathStep::Wildcard), _) => true,
+      (Some(JsonPathStep::Key(k)), JsonPathSegment::Key(key)) => k == key,
+      (Some(JsonPathStep::Index(n)), JsonPathSegment::Index(index)) => n == index,
+      _ => false,
+    };
+    if matched && !out.contains(&(step + 1)) {
+      out.push(step + 1);
+    }
+  }
+  out
+}
+
+/// Nodes of the JSON value at `record_offset` selected by the JSONPath `expr`, in document
+/// order, found in one streaming pass like `find_in_json_record`; subtrees the path can't reach
+/// are skipped without being parsed.
+pub(crate) fn eval_json_path_in_record(
+  path: &Path,
+  lenient: bool,
+  record_offset: u64,
+  expr: &str,
+  max_matches: usize,
+  preview_max_chars: usize,
+) -> Result<JsonPathResult, CoreError> {
+  let steps = parse_json_path(expr)?;
+  let mut f = File::open(path)?;
+  let file_len = f.metadata().ok().map(|m| m.len()).unwrap_or(0);
+  if record_offset > file_len {
+    return Err(CoreError::InvalidArg(format!(
+      "offset {} beyond file len {}",
+      record_offset, file_len
+    )));
+  }
+  f.seek(SeekFrom::Start(record_offset))?;
+  let mut reader = BufReader::with_capacity(1024 * 1024, JsonSource::new(f, lenient)?);
+  let mut abs = record_offset;
+  let total = file_len;
+  let mut on_progress: Option<&mut dyn FnMut(u64, u64, &'static str)> = None;
+  let mut matches: Vec<JsonPathMatch> = Vec::new();
+
+  /// Open containers, innermost last, with the path steps the container itself reached.
+  enum Open {
+    Object(Vec<usize>),
+    Array(Vec<usize>, u64),
+  }
+  let mut stack: Vec<Open> = Vec::new();
+  let mut segs: Vec<JsonPathSegment> = Vec::new();
+  let mut offsets: Vec<u64> = Vec::new();
+  let mut states: Vec<usize> = vec![0];
+
+  skip_bom_and_ws(&mut reader, &mut abs, total, &mut on_progress)?;
+  'values: loop {
+    // A value starts here; `segs` / `offsets` already lead to it and `states` is what it reached.
+    skip_ws_and_nul(&mut reader, &mut abs, total, &mut on_progress)?;
+    let value_offset = abs;
+    let first = peek_byte(&mut reader)?.ok_or_else(|| CoreError::InvalidArg("unexpected EOF".into()))?;
+    let matched = states.contains(&steps.len());
+    let deeper = states.iter().any(|&s| s < steps.len());
+    let opened = match first {
+      b'{' | b'[' if deeper => {
+        if matched {
+          matches.push(JsonPathMatch {
+            path: segs.clone(),
+            node_offsets: offsets.clone(),
+            value_offset,
+            kind: kind_from_first_byte(first),
+            preview: if first == b'{' { "{…}" } else { "[…]" }.to_string(),
+          });
+        }
+        consume_byte(&mut reader, &mut abs, total, &mut on_progress)?;
+        let states = std::mem::take(&mut states);
+        stack.push(if first == b'{' { Open::Object(states) } else { Open::Array(states, 0) });
+        true
+      }
+      _ => {
+        let capture = matched.then(|| preview_max_chars.max(64) * 4);
+        let scanned = scan_one_json_value_with_stops(&mut reader, &mut abs, total, capture, b",]}", &mut on_progress)?;
+        if matched {
+          let (preview, truncated) = preview_from_scan(scanned.captured, scanned.total_len_bytes, preview_max_chars);
+          matches.push(JsonPathMatch {
+            path: segs.clone(),
+            node_offsets: offsets.clone(),
+            value_offset,
+            kind: kind_from_first_byte(first),
+            preview: if truncated && !preview.ends_with('…') { format!("{preview}…") } else { preview },
+          });
+        }
+        false
+      }
+    };
+    if matches.len() >= max_matches {
+      return Ok(JsonPathResult { matches, truncated: true });
+    }
+
+    // Move on to the next value the path can still reach, skipping the others.
+    let mut finished = !opened;
+    loop {
+      if finished {
+        if stack.is_empty() {
+          break 'values;
+        }
+        segs.pop();
+        offsets.pop();
+      }
+      let Some(top) = stack.last_mut() else {
+        break 'values;
+      };
+      skip_ws_and_nul(&mut reader, &mut abs, total, &mut on_progress)?;
+      let close = if matches!(top, Open::Object(_)) { b'}' } else { b']' };
+      if peek_byte(&mut reader)? == Some(close) {
+        consume_byte(&mut reader, &mut abs, total, &mut on_progress)?;
+        stack.pop();
+        finished = true;
+        continue;
+      }
+      if finished {
+        expect_byte(&mut reader, &mut abs, total, &mut on_progress, b',')?;
+      }
+      let (parent, seg) = match top {
+        Open::Object(parent) => {
+          let key = read_json_string(&mut reader, &mut abs, total, &mut on_progress)?;
+          skip_ws_and_nul(&mut reader, &mut abs, total, &mut on_progress)?;
+          expect_byte(&mut reader, &mut abs, total, &mut on_progress, b':')?;
+          (parent, JsonPathSegment::Key(key))
+        }
+        Open::Array(parent, next) => {
+          let index = *next;
+          *next += 1;
+          (parent, JsonPathSegment::Index(index))
+        }
+      };
+      states = json_path_child_states(&steps, parent, &seg);
+      skip_ws_and_nul(&mut reader, &mut abs, total, &mut on_progress)?;
+      segs.push(seg);
+      offsets.push(abs);
+      if states.is_empty() {
+        // Nothing below can match: skip the value unparsed.
+        scan_one_json_value_with_stops(&mut reader, &mut abs, total, None, b",]}", &mut on_progress)?;
+        finished = true;
+        continue;
+      }
+      continue 'values;
+    }
+  }
+  Ok(JsonPathResult { matches, truncated: false })
+}
+
 fn skip_json_string_literal(
-  reader: &mut BufReader<File>,
+  reader: &mut BufReader<JsonSource>,
   abs: &mut u64,
   total: u64,
   on_progress: &mut Option<&mut dyn FnMut(u64, u64, &'static str)>,
@@ -1769,12 +3507,13 @@ fn skip_json_string_literal(
 }
 
 fn scan_one_json_value_to_writer(
-  reader: &mut BufReader<File>,
+  reader: &mut BufReader<JsonSource>,
   abs: &mut u64,
   total: u64,
   stop_bytes: &[u8],
   on_progress: &mut Option<&mut dyn FnMut(u64, u64, &'static str)>,
   mut out: Option<&mut dyn Write>,
+  minify: bool,
 ) -> Result<(), CoreError> {
   let mut in_string = false;
   let mut escape = false;
@@ -1804,7 +3543,9 @@ fn scan_one_json_value_to_writer(
     }
 
     if let Some(w) = out.as_deref_mut() {
-      w.write_all(&[b])?;
+      if !(minify && !in_string && is_ignorable_head_byte(b)) {
+        w.write_all(&[b])?;
+      }
     }
 
     if in_string {
@@ -1825,10 +3566,8 @@ fn scan_one_json_value_to_writer(
     match b {
       b'"' => in_string = true,
       b'{' | b'[' => depth += 1,
-      b'}' | b']' => {
-        if depth > 0 {
-          depth -= 1;
-        }
+      b'}' | b']' if depth > 0 => {
+        depth -= 1;
       }
       _ => {}
     }
@@ -1849,7 +3588,7 @@ fn scan_one_json_value_to_writer(
 }
 
 fn seek_to_subtree(
-  reader: &mut BufReader<File>,
+  reader: &mut BufReader<JsonSource>,
   abs: &mut u64,
   total: u64,
   on_progress: &mut Option<&mut dyn FnMut(u64, u64, &'static str)>,
@@ -1877,7 +3616,7 @@ fn seek_to_subtree(
             break;
           } else {
             // skip value
-            scan_one_json_value_with_stops(reader, abs, total, None, &[b',', b'}'], on_progress)?;
+            scan_one_json_value_with_stops(reader, abs, total, None, b",}", on_progress)?;
             skip_ws_and_nul(reader, abs, total, on_progress)?;
             match peek_byte(reader)? {
               Some(b',') => {
@@ -1908,7 +3647,7 @@ fn seek_to_subtree(
             // positioned at element start for next segment
             break;
           }
-          scan_one_json_value_with_stops(reader, abs, total, None, &[b',', b']'], on_progress)?;
+          scan_one_json_value_with_stops(reader, abs, total, None, b",]", on_progress)?;
           skip_ws_and_nul(reader, abs, total, on_progress)?;
           match peek_byte(reader)? {
             Some(b',') => {
@@ -1936,7 +3675,7 @@ struct ScannedAny {
 }
 
 fn scan_one_json_value_with_stops(
-  reader: &mut BufReader<File>,
+  reader: &mut BufReader<JsonSource>,
   abs: &mut u64,
   total: u64,
   capture_max_bytes: Option<usize>,
@@ -1970,7 +3709,7 @@ fn scan_one_json_value_with_stops(
       if is_ignorable_head_byte(b) {
         // ignore head bytes before the actual value
         if let Some(max) = capture_max_bytes {
-          if captured.len() > 0 {
+          if !captured.is_empty() {
             // keep behavior simple; allow captured whitespace at start if any
           } else if max > 0 {
             // no-op
@@ -1999,6 +3738,9 @@ fn scan_one_json_value_with_stops(
     // If we hit a top-level delimiter, do NOT include it.
     if depth == 0 && stop_bytes.contains(&b) {
       unread_one(reader)?;
+      if captured.len() as u64 == total_len {
+        captured.pop();
+      }
       *abs -= 1;
       total_len -= 1;
       break;
@@ -2007,10 +3749,8 @@ fn scan_one_json_value_with_stops(
     match b {
       b'"' => in_string = true,
       b'{' | b'[' => depth += 1,
-      b'}' | b']' => {
-        if depth > 0 {
-          depth -= 1;
-        }
+      b'}' | b']' if depth > 0 => {
+        depth -= 1;
       }
       _ => {}
     }
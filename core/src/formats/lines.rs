@@ -1,14 +1,16 @@
 use std::{
   fs::File,
-  io::{BufRead, BufReader, Seek, SeekFrom},
+  io::{BufRead, BufReader, Read, Seek, SeekFrom},
   path::Path,
 };
 
 use crate::{
   cursor::Cursor,
+  encoding,
   engine::CoreError,
   formats::LinesPageInternal,
-  models::{Record, RecordMeta},
+  models::{Record, RecordMeta, TextEncoding},
+  progress::ScanProgress,
 };
 
 pub(crate) fn read_lines_page(
@@ -17,6 +19,7 @@ pub(crate) fn read_lines_page(
   page_size: usize,
   preview_max_chars: usize,
   raw_max_chars: usize,
+  encoding: TextEncoding,
 ) -> Result<(LinesPageInternal, Option<Cursor>), CoreError> {
   let mut file = File::open(path)?;
   let file_len = file.metadata().ok().map(|m| m.len()).unwrap_or(0);
@@ -31,7 +34,6 @@ pub(crate) fn read_lines_page(
 
   let mut records = Vec::with_capacity(page_size);
   let mut offset = cursor.offset;
-  let mut line_no = cursor.line;
   // To avoid huge allocations for extremely long lines, only collect a limited prefix of each line.
   // We still scan/consume the full line to compute the next cursor offset correctly.
   let max_chars_needed = preview_max_chars.max(raw_max_chars).max(1);
@@ -41,7 +43,7 @@ pub(crate) fn read_lines_page(
     .saturating_mul(4)
     .saturating_add(64);
 
-  for _ in 0..page_size {
+  for line_no in (cursor.line..).take(page_size) {
     let start_offset = offset;
     let (mut prefix, n_total_bytes, truncated) = read_line_prefix_bytes(&mut reader, collect_limit_bytes)?;
     if n_total_bytes == 0 {
@@ -55,7 +57,7 @@ pub(crate) fn read_lines_page(
       prefix.pop();
     }
 
-    let line_prefix = String::from_utf8_lossy(&prefix).to_string();
+    let line_prefix = encoding::decode(&prefix, encoding).into_owned();
 
     let preview = truncate_chars_force_ellipsis(&line_prefix, preview_max_chars, truncated);
     // For very large JSONL records, we MUST NOT ship full raw via IPC (can exceed caps / freeze UI).
@@ -74,9 +76,12 @@ pub(crate) fn read_lines_page(
         line_no,
         byte_offset: start_offset,
         byte_len: n_total_bytes,
+        part: None,
       }),
+      derived: None,
+      warning: None,
+      columns: None,
     });
-    line_no += 1;
   }
 
   let reached_eof = records.is_empty() || offset >= file_len;
@@ -105,7 +110,7 @@ fn truncate_chars_force_ellipsis(s: &str, max: usize, force_ellipsis: bool) -> S
   let mut out = String::new();
   for (i, ch) in s.chars().enumerate() {
     if i >= max {
-      out.push_str("…");
+      out.push('…');
       break;
     }
     out.push(ch);
@@ -165,3 +170,148 @@ fn read_line_prefix_bytes(
   Ok((out, total, truncated))
 }
 
+
+/// Byte offset where the last `n` lines of the file start.
+///
+/// Reads backwards in chunks, so this is cheap even for huge files. A trailing newline at EOF
+/// does not count as an extra (empty) line. Raw line breaks only: not for CSV, whose quoted
+/// cells may hold them.
+pub(crate) fn tail_start_offset(path: &Path, n: u64) -> Result<u64, CoreError> {
+  let mut file = File::open(path)?;
+  let file_len = file.metadata().ok().map(|m| m.len()).unwrap_or(0);
+  if file_len == 0 || n == 0 {
+    return Ok(file_len);
+  }
+
+  let mut end = file_len;
+  let mut last = [0u8; 1];
+  file.seek(SeekFrom::Start(file_len - 1))?;
+  file.read_exact(&mut last)?;
+  if last[0] == b'\n' {
+    end -= 1;
+  }
+
+  let mut seen = 0u64;
+  let mut pos = end;
+  let mut buf = vec![0u8; 64 * 1024];
+  while pos > 0 {
+    let start = pos.saturating_sub(buf.len() as u64);
+    let chunk = &mut buf[..(pos - start) as usize];
+    file.seek(SeekFrom::Start(start))?;
+    file.read_exact(chunk)?;
+    for i in (0..chunk.len()).rev() {
+      if chunk[i] == b'\n' {
+        seen += 1;
+        if seen == n {
+          return Ok(start + i as u64 + 1);
+        }
+      }
+    }
+    pos = start;
+  }
+  Ok(0)
+}
+
+/// Offset of the first line start at or after `offset` (file length if there is none).
+pub(crate) fn next_line_start(path: &Path, offset: u64) -> Result<u64, CoreError> {
+  if offset == 0 {
+    return Ok(0);
+  }
+  let mut file = File::open(path)?;
+  let file_len = file.metadata().ok().map(|m| m.len()).unwrap_or(0);
+  if offset >= file_len {
+    return Ok(file_len);
+  }
+  // Start one byte early so an offset that already sits at a line start is kept as-is.
+  file.seek(SeekFrom::Start(offset - 1))?;
+  let mut reader = BufReader::new(file);
+  let mut skipped = Vec::new();
+  let n = reader.read_until(b'\n', &mut skipped)?;
+  Ok((offset - 1 + n as u64).min(file_len))
+}
+
+/// Count lines (= JSONL records) with a chunked newline scan.
+///
+/// An unterminated last line counts; a trailing newline does not add an extra record.
+/// Returns `None` if `should_stop` fired before EOF.
+pub(crate) fn count_lines(
+  path: &Path,
+  should_stop: impl Fn() -> bool,
+  mut on_progress: impl FnMut(ScanProgress),
+) -> Result<Option<u64>, CoreError> {
+  let mut file = File::open(path)?;
+  let file_len = file.metadata().ok().map(|m| m.len()).unwrap_or(0);
+  let mut buf = vec![0u8; 1024 * 1024];
+  let mut lines = 0u64;
+  let mut read = 0u64;
+  let mut last = b'\n';
+  loop {
+    if should_stop() {
+      return Ok(None);
+    }
+    let n = file.read(&mut buf)?;
+    if n == 0 {
+      break;
+    }
+    lines += buf[..n].iter().filter(|b| **b == b'\n').count() as u64;
+    last = buf[n - 1];
+    read += n as u64;
+    on_progress(ScanProgress::new(read, file_len, read, lines));
+  }
+  if last != b'\n' {
+    lines += 1;
+  }
+  Ok(Some(lines))
+}
+
+/// The next line of `reader` decoded with `encoding`, without its `\n` / `\r\n`, and the bytes
+/// it took (terminator included); `None` at EOF. Lines as scan_all searches them.
+pub(crate) fn read_search_line(
+  reader: &mut impl BufRead,
+  encoding: TextEncoding,
+) -> Result<Option<(u64, String)>, CoreError> {
+  let mut buf = Vec::new();
+  let n = reader.read_until(b'\n', &mut buf)?;
+  if n == 0 {
+    return Ok(None);
+  }
+  if buf.ends_with(b"\n") {
+    buf.pop();
+    if buf.ends_with(b"\r") {
+      buf.pop();
+    }
+  }
+  Ok(Some((n as u64, encoding::decode(&buf, encoding).into_owned())))
+}
+
+/// Walk lines starting at `offset` (a line start): `on_line(byte_len)` is called per line
+/// (terminator included) until it returns `false` or EOF.
+pub(crate) fn walk_lines(path: &Path, offset: u64, mut on_line: impl FnMut(u64) -> bool) -> Result<(), CoreError> {
+  let mut file = File::open(path)?;
+  file.seek(SeekFrom::Start(offset))?;
+  let mut reader = BufReader::with_capacity(1024 * 1024, file);
+  let mut len = 0u64;
+  loop {
+    let buf = reader.fill_buf()?;
+    if buf.is_empty() {
+      if len > 0 {
+        on_line(len);
+      }
+      return Ok(());
+    }
+    let n = buf.len();
+    let mut line_start = 0usize;
+    for (i, b) in buf.iter().enumerate() {
+      if *b == b'\n' {
+        len += (i + 1 - line_start) as u64;
+        line_start = i + 1;
+        if !on_line(len) {
+          return Ok(());
+        }
+        len = 0;
+      }
+    }
+    len += (n - line_start) as u64;
+    reader.consume(n);
+  }
+}
@@ -3,7 +3,8 @@ use std::path::Path;
 use crate::{
   cursor::Cursor,
   engine::CoreError,
-  models::{FileFormat, Record, RecordPage, SearchQuery, SearchResult},
+  models::{CsvDialect, FileFormat, Record, RecordPage, SearchQuery, SearchResult, TextEncoding},
+  progress::ScanProgress,
   search_match::PreparedSearch,
 };
 
@@ -34,32 +35,93 @@ pub(crate) fn read_lines_page(
   page_size: usize,
   preview_max_chars: usize,
   raw_max_chars: usize,
+  encoding: TextEncoding,
 ) -> Result<(LinesPageInternal, Option<Cursor>), CoreError> {
-  crate::formats::lines::read_lines_page(path, cursor, page_size, preview_max_chars, raw_max_chars)
+  crate::formats::lines::read_lines_page(path, cursor, page_size, preview_max_chars, raw_max_chars, encoding)
 }
 
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn read_csv_page(
   path: &Path,
   cursor: Cursor,
   page_size: usize,
   preview_max_chars: usize,
   raw_max_chars: usize,
+  columns: Option<&[String]>,
+  encoding: TextEncoding,
+  dialect: &CsvDialect,
 ) -> Result<(LinesPageInternal, Option<Cursor>), CoreError> {
-  crate::formats::csv::read_csv_page(path, cursor, page_size, preview_max_chars, raw_max_chars)
+  crate::formats::csv::read_csv_page(path, cursor, page_size, preview_max_chars, raw_max_chars, columns, encoding, dialect)
+}
+
+/// Column names of a CSV file (empty names become `col_<i>`; all of them without a header).
+pub(crate) fn read_csv_header(
+  path: &Path,
+  encoding: TextEncoding,
+  dialect: &CsvDialect,
+) -> Result<Vec<String>, CoreError> {
+  crate::formats::csv::read_csv_header(path, encoding, dialect)
+}
+
+/// A CSV data row as the JSON object paging shows in `raw`.
+pub(crate) fn csv_line_to_object(headers: &[String], line: &str, dialect: &CsvDialect) -> serde_json::Value {
+  crate::formats::csv::csv_line_to_object(headers, line, dialect)
+}
+
+/// See `csv::object_to_csv_line`.
+pub(crate) fn object_to_csv_line(
+  headers: &[String],
+  obj: &serde_json::Map<String, serde_json::Value>,
+  dialect: &CsvDialect,
+) -> Result<String, CoreError> {
+  crate::formats::csv::object_to_csv_line(headers, obj, dialect)
+}
+
+/// See `csv::read_csv_cell`.
+pub(crate) fn read_csv_cell(
+  path: &Path,
+  byte_offset: u64,
+  byte_len: u64,
+  index: usize,
+  encoding: TextEncoding,
+  dialect: &CsvDialect,
+) -> Result<String, CoreError> {
+  crate::formats::csv::read_csv_cell(path, byte_offset, byte_len, index, encoding, dialect)
+}
+
+/// See `csv::sniff_csv_delimiter`.
+pub(crate) fn sniff_csv_delimiter(path: &Path) -> Result<char, CoreError> {
+  crate::formats::csv::sniff_csv_delimiter(path)
+}
+
+/// See `csv::read_csv_record_bytes`.
+pub(crate) fn read_csv_record_bytes<R: std::io::BufRead>(
+  reader: &mut R,
+  out: &mut Vec<u8>,
+  dialect: &CsvDialect,
+) -> Result<(usize, bool), CoreError> {
+  crate::formats::csv::read_csv_record_bytes(reader, out, dialect)
+}
+
+/// See `csv::quote_csv_field`.
+pub(crate) fn quote_csv_field(s: &str, dialect: &CsvDialect) -> String {
+  crate::formats::csv::quote_csv_field(s, dialect)
 }
 
 pub(crate) fn read_json_page(
   path: &Path,
+  lenient: bool,
   cursor: Cursor,
   page_size: usize,
   preview_max_chars: usize,
   raw_max_chars: usize,
 ) -> Result<(LinesPageInternal, Option<Cursor>), CoreError> {
-  crate::formats::json::read_json_page(path, cursor, page_size, preview_max_chars, raw_max_chars)
+  crate::formats::json::read_json_page(path, lenient, cursor, page_size, preview_max_chars, raw_max_chars)
 }
 
 pub(crate) fn read_json_page_with_progress(
   path: &Path,
+  lenient: bool,
   cursor: Cursor,
   page_size: usize,
   preview_max_chars: usize,
@@ -68,6 +130,7 @@ pub(crate) fn read_json_page_with_progress(
 ) -> Result<(LinesPageInternal, Option<Cursor>), CoreError> {
   crate::formats::json::read_json_page_with_progress(
     path,
+    lenient,
     cursor,
     page_size,
     preview_max_chars,
@@ -79,36 +142,82 @@ pub(crate) fn read_json_page_with_progress(
 /// Read a single JSON value starting at (or after) `offset` and return its full text.
 ///
 /// Used by the UI when a record's `raw` was truncated for performance.
+pub(crate) fn root_array_element_at_or_after(path: &Path, lenient: bool, offset: u64) -> Result<Option<(u64, u64)>, CoreError> {
+  crate::formats::json::root_array_element_at_or_after(path, lenient, offset)
+}
+
 pub(crate) fn read_json_value_at_offset(
   path: &Path,
+  lenient: bool,
   offset: u64,
   max_bytes: u64,
 ) -> Result<String, CoreError> {
-  crate::formats::json::read_json_value_at_offset(path, offset, max_bytes)
+  crate::formats::json::read_json_value_at_offset(path, lenient, offset, max_bytes)
 }
 
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn export_json_subtree_stream(
   session_path: &Path,
+  lenient: bool,
   record_offset: u64,
   path_segments: &[crate::models::JsonPathSegment],
   include_root: bool,
   children: &[crate::models::JsonPathSegment],
   out_format: crate::models::ExportFormat,
+  minify: bool,
   writer: &mut dyn std::io::Write,
 ) -> Result<u64, CoreError> {
   crate::formats::json::export_json_subtree_stream(
     session_path,
+    lenient,
     record_offset,
     path_segments,
     include_root,
     children,
     out_format,
+    minify,
     writer,
   )
 }
 
+pub(crate) fn pretty_print_json_value(
+  session_path: &Path,
+  lenient: bool,
+  offset: u64,
+  writer: &mut dyn std::io::Write,
+) -> Result<(), CoreError> {
+  crate::formats::json::pretty_print_json_value(session_path, lenient, offset, writer)
+}
+
+pub(crate) fn validate_json_value(
+  path: &Path,
+  offset: u64,
+  len: u64,
+) -> Result<Option<crate::models::JsonSyntaxError>, CoreError> {
+  crate::formats::json::validate_json_value(path, offset, len)
+}
+
+pub(crate) fn json_base64_preview(
+  path: &Path,
+  lenient: bool,
+  node_offset: u64,
+  max_bytes: usize,
+) -> Result<Option<crate::models::Base64Preview>, CoreError> {
+  crate::formats::json::json_base64_preview(path, lenient, node_offset, max_bytes)
+}
+
+pub(crate) fn decode_json_base64(
+  path: &Path,
+  lenient: bool,
+  node_offset: u64,
+  writer: &mut dyn std::io::Write,
+) -> Result<u64, CoreError> {
+  crate::formats::json::decode_json_base64(path, lenient, node_offset, writer)
+}
+
 pub(crate) fn json_node_summary(
   session_path: &Path,
+  lenient: bool,
   record_offset: u64,
   path_segments: &[crate::models::JsonPathSegment],
   max_items: u64,
@@ -116,6 +225,7 @@ pub(crate) fn json_node_summary(
 ) -> Result<crate::models::JsonNodeSummary, CoreError> {
   crate::formats::json::json_node_summary(
     session_path,
+    lenient,
     record_offset,
     path_segments,
     max_items,
@@ -125,15 +235,84 @@ pub(crate) fn json_node_summary(
 
 pub(crate) fn json_node_summary_at_offset(
   session_path: &Path,
+  lenient: bool,
   node_offset: u64,
   max_items: u64,
   max_scan_bytes: u64,
 ) -> Result<crate::models::JsonNodeSummaryOffset, CoreError> {
-  crate::formats::json::json_node_summary_at_offset(session_path, node_offset, max_items, max_scan_bytes)
+  crate::formats::json::json_node_summary_at_offset(session_path, lenient, node_offset, max_items, max_scan_bytes)
+}
+
+/// See `json::sorted_json_children_at_offset`.
+pub(crate) fn sorted_json_children_at_offset(
+  path: &Path,
+  lenient: bool,
+  node_offset: u64,
+  sort: crate::models::JsonChildSort,
+  preview_max_chars: usize,
+) -> Result<Vec<crate::models::JsonChildItemOffset>, CoreError> {
+  crate::formats::json::sorted_json_children_at_offset(path, lenient, node_offset, sort, preview_max_chars)
+}
+
+/// See `json::json_array_table_page`.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn json_array_table_page(
+  path: &Path,
+  lenient: bool,
+  node_offset: u64,
+  known_columns: Vec<String>,
+  cursor_offset: Option<u64>,
+  cursor_index: Option<u64>,
+  limit: usize,
+  preview_max_chars: usize,
+) -> Result<crate::models::JsonTablePage, CoreError> {
+  crate::formats::json::json_array_table_page(
+    path,
+    lenient,
+    node_offset,
+    known_columns,
+    cursor_offset,
+    cursor_index,
+    limit,
+    preview_max_chars,
+  )
+}
+
+/// See `json::index_json_record`.
+pub(crate) fn index_json_record(
+  path: &Path,
+  lenient: bool,
+  record_offset: u64,
+  record_len: u64,
+  min_node_bytes: u64,
+  should_stop: impl Fn() -> bool,
+  on_progress: impl FnMut(crate::progress::ScanProgress),
+) -> Result<Option<Vec<crate::json_index::IndexedNode>>, CoreError> {
+  crate::formats::json::index_json_record(path, lenient, record_offset, record_len, min_node_bytes, should_stop, on_progress)
+}
+
+/// See `json::json_node_offset`.
+pub(crate) fn json_node_offset(
+  session_path: &Path,
+  lenient: bool,
+  record_offset: u64,
+  path_segments: &[crate::models::JsonPathSegment],
+) -> Result<u64, CoreError> {
+  crate::formats::json::json_node_offset(session_path, lenient, record_offset, path_segments)
+}
+
+/// See `json::json_node_stats_at_offset`.
+pub(crate) fn json_node_stats_at_offset(
+  session_path: &Path,
+  lenient: bool,
+  node_offset: u64,
+) -> Result<crate::models::JsonNodeStats, CoreError> {
+  crate::formats::json::json_node_stats_at_offset(session_path, lenient, node_offset)
 }
 
 pub(crate) fn list_json_children_page(
   path: &Path,
+  lenient: bool,
   record_offset: u64,
   path_segments: &[crate::models::JsonPathSegment],
   cursor: u64,
@@ -142,6 +321,7 @@ pub(crate) fn list_json_children_page(
 ) -> Result<crate::models::JsonChildrenPage, CoreError> {
   crate::formats::json::list_json_children_page(
     path,
+    lenient,
     record_offset,
     path_segments,
     cursor,
@@ -150,8 +330,32 @@ pub(crate) fn list_json_children_page(
   )
 }
 
+/// See `json::find_in_json_record`.
+pub(crate) fn find_in_json_record(
+  path: &Path,
+  lenient: bool,
+  record_offset: u64,
+  query: &crate::models::JsonFindQuery,
+  preview_max_chars: usize,
+) -> Result<crate::models::JsonFindResult, CoreError> {
+  crate::formats::json::find_in_json_record(path, lenient, record_offset, query, preview_max_chars)
+}
+
+/// See `json::eval_json_path_in_record`.
+pub(crate) fn eval_json_path_in_record(
+  path: &Path,
+  lenient: bool,
+  record_offset: u64,
+  expr: &str,
+  max_matches: usize,
+  preview_max_chars: usize,
+) -> Result<crate::models::JsonPathResult, CoreError> {
+  crate::formats::json::eval_json_path_in_record(path, lenient, record_offset, expr, max_matches, preview_max_chars)
+}
+
 pub(crate) fn list_json_children_page_at_offset(
   path: &Path,
+  lenient: bool,
   node_offset: u64,
   cursor_offset: Option<u64>,
   cursor_index: Option<u64>,
@@ -160,6 +364,7 @@ pub(crate) fn list_json_children_page_at_offset(
 ) -> Result<crate::models::JsonChildrenPageOffset, CoreError> {
   crate::formats::json::list_json_children_page_at_offset(
     path,
+    lenient,
     node_offset,
     cursor_offset,
     cursor_index,
@@ -174,8 +379,9 @@ pub(crate) fn read_parquet_page(
   page_size: usize,
   preview_max_chars: usize,
   raw_max_chars: usize,
+  columns: Option<&[String]>,
 ) -> Result<(LinesPageInternal, Option<Cursor>), CoreError> {
-  crate::formats::parquet::read_parquet_page(path, cursor, page_size, preview_max_chars, raw_max_chars)
+  crate::formats::parquet::read_parquet_page(path, cursor, page_size, preview_max_chars, raw_max_chars, columns)
 }
 
 /// Read a single row from a parquet file (by 0-based row index) and return a JSON string.
@@ -189,6 +395,185 @@ pub(crate) fn read_parquet_row_raw(
   crate::formats::parquet::read_parquet_row_raw(path, row_idx, raw_max_chars)
 }
 
+/// Raw char cap used when streaming records for whole-file processing (stats etc.).
+///
+/// Large enough to keep normal records intact; pathological records are still bounded.
+pub(crate) const FULL_RAW_MAX_CHARS: usize = 16 * 1024 * 1024;
+
+/// Stream every record of the file through `on_record` (file order), one page at a time.
+///
+/// Records carry their full (untruncated) raw text:
+/// - CSV: the header row (id 0) is skipped; `raw` is the `{header: cell}` JSON object.
+/// - JSONL / JSON / Parquet: `raw` is the record's JSON text.
+///
+/// `on_record` returns `false` to stop early.
+pub(crate) fn for_each_record(
+  path: &Path,
+  format: FileFormat,
+  on_record: impl FnMut(&Record) -> bool,
+) -> Result<(), CoreError> {
+  for_each_record_from(path, format, Cursor { offset: 0, line: 0 }, on_record)
+}
+
+/// Same as `for_each_record`, but starts at `cursor` (a record boundary) instead of the file start.
+pub(crate) fn for_each_record_from(
+  path: &Path,
+  format: FileFormat,
+  cursor: Cursor,
+  on_record: impl FnMut(&Record) -> bool,
+) -> Result<(), CoreError> {
+  for_each_record_with(path, format, &CsvDialect::default(), cursor, on_record)
+}
+
+/// Same as `for_each_record_from`, with CSV rows read as `dialect` says (without a header, id 0
+/// is a record too).
+pub(crate) fn for_each_record_with(
+  path: &Path,
+  format: FileFormat,
+  dialect: &CsvDialect,
+  cursor: Cursor,
+  on_record: impl FnMut(&Record) -> bool,
+) -> Result<(), CoreError> {
+  for_each_record_decoded(path, format, TextEncoding::Utf8, dialect, cursor, on_record)
+}
+
+/// Same as `for_each_record_with`, with JSONL / CSV text decoded from `encoding` (a session's
+/// `SessionInfo.encoding`).
+pub(crate) fn for_each_record_decoded(
+  path: &Path,
+  format: FileFormat,
+  encoding: TextEncoding,
+  dialect: &CsvDialect,
+  mut cursor: Cursor,
+  mut on_record: impl FnMut(&Record) -> bool,
+) -> Result<(), CoreError> {
+  const PAGE_SIZE: usize = 512;
+  loop {
+    let (page, next) = match format {
+      FileFormat::Jsonl => read_lines_page(path, cursor, PAGE_SIZE, 0, FULL_RAW_MAX_CHARS, encoding)?,
+      FileFormat::Csv => read_csv_page(path, cursor, PAGE_SIZE, 0, FULL_RAW_MAX_CHARS, None, encoding, dialect)?,
+      FileFormat::Json => read_json_page(path, false, cursor, PAGE_SIZE, 0, FULL_RAW_MAX_CHARS)?,
+      FileFormat::Parquet => read_parquet_page(path, cursor, PAGE_SIZE, 0, FULL_RAW_MAX_CHARS, None)?,
+      other => return Err(CoreError::UnsupportedFormat(other)),
+    };
+    for r in &page.records {
+      if format == FileFormat::Csv && dialect.has_header && r.id == 0 {
+        continue;
+      }
+      if !on_record(r) {
+        return Ok(());
+      }
+    }
+    match next {
+      Some(c) if !page.reached_eof => cursor = c,
+      _ => break,
+    }
+  }
+  Ok(())
+}
+
+/// Exact record count (CSV header excluded). Returns `None` if `should_stop` fired first.
+///
+/// Parquet reads row-group metadata; JSONL counts newlines; CSV scans records (quote-aware);
+/// `.json` has to walk every top-level value.
+pub(crate) fn count_records(
+  path: &Path,
+  format: FileFormat,
+  should_stop: impl Fn() -> bool,
+  on_progress: impl FnMut(ScanProgress),
+) -> Result<Option<u64>, CoreError> {
+  count_records_with(path, format, &CsvDialect::default(), false, should_stop, on_progress)
+}
+
+/// `count_records` with CSV record boundaries following `csv`'s quoting, and `.json` values
+/// read leniently with `json_lenient` (see `json::JsonSource`).
+pub(crate) fn count_records_with(
+  path: &Path,
+  format: FileFormat,
+  csv: &CsvDialect,
+  json_lenient: bool,
+  should_stop: impl Fn() -> bool,
+  mut on_progress: impl FnMut(ScanProgress),
+) -> Result<Option<u64>, CoreError> {
+  match format {
+    FileFormat::Parquet => read_parquet_row_count(path).map(Some),
+    FileFormat::Jsonl => crate::formats::lines::count_lines(path, should_stop, on_progress),
+    FileFormat::Csv => crate::formats::csv::count_csv_records(path, csv, should_stop, on_progress),
+    FileFormat::Json => {
+      let total = std::fs::metadata(path)?.len();
+      let mut count = 0u64;
+      let mut done = 0u64;
+      let mut stopped = false;
+      crate::formats::json::walk_json_records(path, json_lenient, 0, |len| {
+        if should_stop() {
+          stopped = true;
+          return false;
+        }
+        count += 1;
+        done += len;
+        on_progress(ScanProgress::new(done, total, done, count));
+        true
+      })?;
+      Ok(if stopped { None } else { Some(count) })
+    }
+    other => Err(CoreError::UnsupportedFormat(other)),
+  }
+}
+
+/// Walk record byte lengths of JSONL lines, CSV records (header included) or `.json` root
+/// values (see `json::walk_json_records`) starting at the record boundary `offset`; CSV records
+/// end where `csv`'s quoting says, `.json` values are read leniently with `json_lenient`.
+/// `on_record` returns `false` to stop.
+pub(crate) fn walk_record_lengths(
+  path: &Path,
+  format: FileFormat,
+  csv: &CsvDialect,
+  json_lenient: bool,
+  offset: u64,
+  on_record: impl FnMut(u64) -> bool,
+) -> Result<(), CoreError> {
+  match format {
+    FileFormat::Jsonl => crate::formats::lines::walk_lines(path, offset, on_record),
+    FileFormat::Csv => crate::formats::csv::walk_csv_records(path, csv, offset, on_record),
+    FileFormat::Json => crate::formats::json::walk_json_records(path, json_lenient, offset, on_record),
+    other => Err(CoreError::UnsupportedFormat(other)),
+  }
+}
+
+pub(crate) fn read_parquet_columns(path: &Path) -> Result<Vec<(String, String, bool)>, CoreError> {
+  crate::formats::parquet::read_parquet_columns(path)
+}
+
+pub(crate) fn read_parquet_metadata(path: &Path) -> Result<crate::models::ParquetMetadata, CoreError> {
+  crate::formats::parquet::read_parquet_metadata(path)
+}
+
+pub(crate) fn read_parquet_row_count(path: &Path) -> Result<u64, CoreError> {
+  crate::formats::parquet::read_parquet_row_count(path)
+}
+
+pub(crate) fn read_parquet_sample_raw(path: &Path, n: u64) -> Result<Vec<String>, CoreError> {
+  crate::formats::parquet::read_parquet_sample_raw(path, n)
+}
+
+/// See `lines::tail_start_offset`.
+pub(crate) fn tail_start_offset(path: &Path, n: u64) -> Result<u64, CoreError> {
+  crate::formats::lines::tail_start_offset(path, n)
+}
+
+/// See `lines::read_search_line`.
+pub(crate) fn read_search_line(
+  reader: &mut impl std::io::BufRead,
+  encoding: TextEncoding,
+) -> Result<Option<(u64, String)>, CoreError> {
+  crate::formats::lines::read_search_line(reader, encoding)
+}
+
+/// See `lines::next_line_start`.
+pub(crate) fn next_line_start(path: &Path, offset: u64) -> Result<u64, CoreError> {
+  crate::formats::lines::next_line_start(path, offset)
+}
+
 pub(crate) fn search_current_page(page: &RecordPage, query: &SearchQuery) -> SearchResult {
   let prepared = match PreparedSearch::new(query) {
     Some(p) => p,
@@ -205,11 +590,15 @@ pub(crate) fn search_current_page(page: &RecordPage, query: &SearchQuery) -> Sea
   let mut hits = Vec::new();
   for r in &page.records {
     // Match the same "display content" the UI uses: preview + raw (if present).
-    let text = if let Some(raw) = &r.raw {
+    let mut text = if let Some(raw) = &r.raw {
       format!("{}\n{}", r.preview, raw)
     } else {
       r.preview.clone()
     };
+    for v in r.derived.iter().flat_map(|d| d.values()) {
+      text.push('\n');
+      text.push_str(&crate::derive::value_text(v));
+    }
     let hay = if query.case_sensitive { text } else { text.to_lowercase() };
     if prepared.matches_in_hay(&hay) {
       hits.push(r.clone());
@@ -229,4 +618,7 @@ mod csv;
 mod json;
 mod parquet;
 // parquet reader implemented with embedded DuckDB (no external CLI dependency)
+pub(crate) use csv::DelimiterRegex;
+pub(crate) use json::JsonSource;
+pub(crate) use parquet::{is_encrypted_parquet, iso_date, iso_time, iso_timestamp, ParquetConn};
 
@@ -1,176 +1,1292 @@
-use std::path::Path;
+use std::{
+  cell::{OnceCell, RefCell},
+  collections::VecDeque,
+  fmt,
+  io::{Read, Seek, SeekFrom},
+  path::Path,
+};
 
-use base64::Engine as _;
 use serde_json::{Map, Value};
 
+use duckdb::OptionalExt as _;
+
+use duckdb::arrow::{
+  array::{Array, AsArray},
+  datatypes::{
+    DataType, Date32Type, Decimal128Type, Decimal256Type, Float32Type, Float64Type, Int16Type, Int32Type, Int64Type, Int8Type,
+    Time64MicrosecondType, Time64NanosecondType, TimeUnit as ArrowTimeUnit, TimestampMicrosecondType,
+    TimestampMillisecondType, TimestampNanosecondType, TimestampSecondType, UInt16Type, UInt32Type, UInt64Type,
+    UInt8Type,
+  },
+  util::display::{ArrayFormatter, FormatOptions},
+};
+
 use crate::{
   cursor::Cursor,
   engine::CoreError,
   formats::LinesPageInternal,
-  models::{Record, RecordMeta},
+  models::{
+    ColumnFilter, FilterOp, ParquetColumn, ParquetKeyValue, ParquetMetadata, ParquetRowGroup, Record, RecordMeta,
+  },
 };
 
-/// Parquet paging implementation via embedded DuckDB (no external CLI dependency).
+use duckdb::types::Value as V;
+
+/// An in-memory DuckDB connection reading one parquet file.
 ///
-/// Cursor semantics:
-/// - `cursor.line` is used as row offset (0-based).
-/// - `cursor.offset` is ignored.
+/// Sessions keep theirs (see `SessionState::parquet`) so pages, raw fetches and exports skip
+/// DuckDB start-up and reuse prepared statements and the parsed footer; the free functions below
+/// open a throwaway one.
+pub(crate) struct ParquetConn {
+  conn: duckdb::Connection,
+  path: String,
+  /// Footer row count, read once (a reload opens a new connection).
+  row_count: OnceCell<u64>,
+  /// First row of each row group, from the footer; read once like `row_count`.
+  row_group_starts: OnceCell<Vec<u64>>,
+  /// `numbered_source`'s source and row number column, built once from the columns.
+  numbered: OnceCell<(String, String)>,
+  /// Offset TIMESTAMPTZ values are shown at (see `CoreOptions::parquet_utc_offset_minutes`).
+  utc_offset_minutes: i32,
+  /// Set for files with an encrypted footer (see `with_decryption_key`); rows are then read with
+  /// it registered under `FOOTER_KEY_NAME`.
+  decryption_key: Option<String>,
+  /// Recently read pages, least recently used first (see `with_page_cache`).
+  pages: RefCell<VecDeque<(PageKey, PageResult)>>,
+  page_cache_len: usize,
+}
+
+type PageResult = (LinesPageInternal, Option<Cursor>);
+
+/// What a page read depends on besides the file (see `ParquetConn::page`).
+#[derive(PartialEq)]
+struct PageKey {
+  line: u64,
+  page_size: usize,
+  preview_max_chars: usize,
+  raw_max_chars: usize,
+  columns: Option<Vec<String>>,
+  filters: Option<Vec<ColumnFilter>>,
+}
+
+/// Name the decryption key is registered under in a connection's DuckDB catalog.
+const FOOTER_KEY_NAME: &str = "datalens_footer_key";
+
+/// Preferred name of the row number column queries add (see `ParquetConn::numbered_source`).
+const ROW_NUMBER_COLUMN: &str = "__datalens_row";
+
+/// Footer statistics of one column chunk (one column in one row group).
+#[derive(Debug, Clone)]
+pub(crate) struct ParquetChunkStats {
+  /// Rows of the chunk's row group.
+  pub row_count: u64,
+  /// Dotted leaf path.
+  pub path: String,
+  pub physical_type: String,
+  pub min: Option<String>,
+  pub max: Option<String>,
+  pub null_count: Option<u64>,
+}
+
+impl fmt::Debug for ParquetConn {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_tuple("ParquetConn").field(&self.path).finish()
+  }
+}
+
+impl ParquetConn {
+  pub(crate) fn open(path: &Path) -> Result<Self, CoreError> {
+    let path = path
+      .to_str()
+      .ok_or_else(|| CoreError::InvalidArg("invalid path encoding".into()))?
+      .to_string();
+    let conn = duckdb::Connection::open_in_memory()
+      .map_err(|e| CoreError::Duckdb(format!("DuckDB 初始化失败：{e}")))?;
+
+    // Some builds require explicitly loading the parquet extension even when compiled with it.
+    // Ignore errors to be tolerant across versions/builds.
+    let _ = conn.execute_batch("LOAD parquet;");
+    // Parse the footer once per connection rather than once per query.
+    let _ = conn.execute_batch("SET parquet_metadata_cache = true;");
+    Ok(Self {
+      conn,
+      path,
+      row_count: OnceCell::new(),
+      row_group_starts: OnceCell::new(),
+      numbered: OnceCell::new(),
+      utc_offset_minutes: 0,
+      decryption_key: None,
+      pages: RefCell::default(),
+      page_cache_len: 0,
+    })
+  }
+
+  /// Keeps the last `pages` pages read in memory, so paging back and forth between adjacent
+  /// pages doesn't query the file again. Like the footer caches it lasts as long as the
+  /// connection; 0 (the default) caches nothing.
+  pub(crate) fn with_page_cache(mut self, pages: usize) -> Self {
+    self.page_cache_len = pages;
+    self
+  }
+
+  /// Reads the file with `key` (AES, 16 / 24 / 32 bytes as is or base64) if it uses parquet
+  /// modular encryption; plain files ignore it.
+  ///
+  /// DuckDB's footer functions can't read encrypted files: `metadata` and `chunk_stats` fail for
+  /// them.
+  pub(crate) fn with_decryption_key(mut self, key: &str) -> Result<Self, CoreError> {
+    if !is_encrypted_parquet(Path::new(&self.path)) {
+      return Ok(self);
+    }
+    // PRAGMA arguments can't be bound parameters.
+    self
+      .conn
+      .execute_batch(&format!(
+        "PRAGMA add_parquet_key('{FOOTER_KEY_NAME}', '{}');",
+        key.replace('\'', "''")
+      ))
+      .map_err(|e| CoreError::InvalidArg(format!("invalid parquet decryption key: {e}")))?;
+    self.decryption_key = Some(key.to_string());
+    Ok(self)
+  }
+
+  pub(crate) fn decryption_key(&self) -> Option<&str> {
+    self.decryption_key.as_deref()
+  }
+
+  /// `read_parquet` over the file (bound as the first parameter), with `file_row_number` if
+  /// `row_numbers`, decrypting it if needed.
+  fn source(&self, row_numbers: bool) -> String {
+    let mut options = String::new();
+    if row_numbers {
+      options.push_str(", file_row_number = true");
+    }
+    if self.decryption_key.is_some() {
+      options.push_str(&format!(", encryption_config = {{footer_key: '{FOOTER_KEY_NAME}'}}"));
+    }
+    format!("read_parquet(?{options})")
+  }
+
+  /// `source` with each row's number (0-based) as an extra last column, and that column's quoted
+  /// name: `__datalens_row`, with `_` appended while the file has a column of that name, so the
+  /// file's own columns keep theirs.
+  ///
+  /// Rows are numbered by `file_row_number`, which DuckDB refuses on files with a column of that
+  /// name; those are numbered by position (`WITH ORDINALITY`) instead, which reads the rows
+  /// before the ones asked for rather than skipping their row groups.
+  fn numbered_source(&self) -> Result<(&str, &str), CoreError> {
+    if let Some((src, row)) = self.numbered.get() {
+      return Ok((src, row));
+    }
+    let names = self.columns()?.into_iter().map(|(name, _, _)| name).collect::<Vec<_>>();
+    let taken = |name: &str| names.iter().any(|n| n.eq_ignore_ascii_case(name));
+    let mut row = ROW_NUMBER_COLUMN.to_string();
+    while taken(&row) {
+      row.push('_');
+    }
+    let row = quote_ident(&row);
+    let aliases = names
+      .iter()
+      .map(|n| quote_ident(n))
+      .chain([row.clone()])
+      .collect::<Vec<_>>()
+      .join(", ");
+    let src = if taken("file_row_number") {
+      format!(
+        "(SELECT * REPLACE ({row} - 1 AS {row}) FROM {} WITH ORDINALITY AS t({aliases}))",
+        self.source(false)
+      )
+    } else {
+      format!("{} AS t({aliases})", self.source(true))
+    };
+    let (src, row) = self.numbered.get_or_init(|| (src, row));
+    Ok((src, row))
+  }
+
+  /// Error for footer APIs DuckDB can't serve on encrypted files.
+  fn footer_unreadable(&self, api: &str) -> Result<(), CoreError> {
+    match self.decryption_key {
+      Some(_) => Err(CoreError::InvalidArg(format!("{api} is not available for encrypted parquet files"))),
+      None => Ok(()),
+    }
+  }
+
+  /// Shows TIMESTAMPTZ values at `minutes` east of UTC instead of in UTC.
+  pub(crate) fn with_utc_offset(mut self, minutes: i32) -> Self {
+    self.utc_offset_minutes = minutes;
+    self
+  }
+
+  fn prepare(&self, sql: &str) -> Result<duckdb::CachedStatement<'_>, CoreError> {
+    self
+      .conn
+      .prepare_cached(sql)
+      .map_err(|e| CoreError::Duckdb(format!("DuckDB 准备语句失败：{e}")))
+  }
+
+  /// One page of rows.
+  ///
+  /// Cursor semantics:
+  /// - `cursor.line` is the row (0-based, file-wide).
+  /// - `cursor.offset` is ignored.
+  ///
+  /// `columns` restricts the scan (and so preview / raw) to those columns; previews keep their
+  /// order.
+  ///
+  /// `filters` keeps only the rows meeting all of them, from `cursor.line` on; record ids stay
+  /// the rows' own, and the next cursor points at the next matching row.
+  ///
+  /// `reached_eof` is exact (see `row_count`), also when the page ends on the last row.
+  ///
+  /// Rows are selected by their row number (see `numbered_source`), which DuckDB compares with
+  /// each row group's row range from the footer: the groups outside the page are skipped rather
+  /// than read up to it as `OFFSET` would, so paging to the end of a file costs the same as
+  /// paging its start. Rows come back as Arrow record batches. Pages read recently are answered
+  /// from memory (see `with_page_cache`).
+  pub(crate) fn page(
+    &self,
+    cursor: Cursor,
+    page_size: usize,
+    preview_max_chars: usize,
+    raw_max_chars: usize,
+    columns: Option<&[String]>,
+    filters: Option<&[ColumnFilter]>,
+  ) -> Result<PageResult, CoreError> {
+    if self.page_cache_len == 0 {
+      return self.read_page(cursor, page_size, preview_max_chars, raw_max_chars, columns, filters);
+    }
+    let key = PageKey {
+      line: cursor.line,
+      page_size,
+      preview_max_chars,
+      raw_max_chars,
+      columns: columns.map(<[String]>::to_vec),
+      filters: filters.map(<[ColumnFilter]>::to_vec),
+    };
+    {
+      let mut pages = self.pages.borrow_mut();
+      if let Some(entry) = pages.iter().position(|(k, _)| *k == key).and_then(|i| pages.remove(i)) {
+        let page = entry.1.clone();
+        pages.push_back(entry);
+        return Ok(page);
+      }
+    }
+    let page = self.read_page(cursor, page_size, preview_max_chars, raw_max_chars, columns, filters)?;
+    let mut pages = self.pages.borrow_mut();
+    if pages.len() == self.page_cache_len {
+      pages.pop_front();
+    }
+    pages.push_back((key, page.clone()));
+    Ok(page)
+  }
+
+  fn read_page(
+    &self,
+    cursor: Cursor,
+    page_size: usize,
+    preview_max_chars: usize,
+    raw_max_chars: usize,
+    columns: Option<&[String]>,
+    filters: Option<&[ColumnFilter]>,
+  ) -> Result<PageResult, CoreError> {
+    let offset = cursor.line;
+    let offset_i64 = i64::try_from(offset).map_err(|_| {
+      CoreError::InvalidArg(format!("invalid cursor offset for parquet: {offset}"))
+    })?;
+    let end_i64 = i64::try_from(page_size)
+      .ok()
+      .and_then(|n| offset_i64.checked_add(n))
+      .ok_or_else(|| CoreError::InvalidArg(format!("invalid page_size: {page_size}")))?;
+
+    let mut records = Vec::with_capacity(page_size);
+
+    let (src, row) = self.numbered_source()?;
+    let select = match columns {
+      Some(cols) => cols.iter().map(|c| quote_ident(c)).collect::<Vec<_>>().join(", "),
+      None => format!("* EXCLUDE ({row})"),
+    };
+    let filter = filters.filter(|f| !f.is_empty()).map(compile_filters).transpose()?;
+    // Filtered pages can't tell their last row from the page size: they read one row more, whose
+    // id is the next cursor.
+    let (sql, params) = match &filter {
+      None => (
+        format!("SELECT {row}, {select} FROM {src} WHERE {row} >= ? AND {row} < ? ORDER BY {row}"),
+        vec![V::Text(self.path.clone()), V::BigInt(offset_i64), V::BigInt(end_i64)],
+      ),
+      Some(filter) => {
+        let mut params = vec![V::Text(self.path.clone()), V::BigInt(offset_i64)];
+        params.extend(filter.params.iter().cloned());
+        params.push(V::BigInt(page_size as i64 + 1));
+        let sql = format!(
+          "SELECT {row}, {select} FROM {src} WHERE {row} >= ? AND {} ORDER BY {row} LIMIT ?",
+          filter.sql
+        );
+        (sql, params)
+      }
+    };
+    let mut stmt = self.prepare(&sql)?;
+    let batches = stmt
+      .query_arrow(duckdb::params_from_iter(params))
+      .map_err(|e| CoreError::Duckdb(format!("Parquet 读取失败：{e}")))?;
+
+    // For parquet detail view, show full content without truncation.
+    // The `raw_max_chars` param is ignored for parquet to ensure complete field display.
+    let _ = raw_max_chars;
+
+    let mut next_match = None;
+    let format_options = FormatOptions::default();
+    'batches: for batch in batches {
+      let schema = batch.schema();
+      let ids = batch.column(0).as_primitive::<Int64Type>();
+      // Only the fallback text needs a formatter; types Arrow can't display (e.g. named time
+      // zones) are rendered by `arrow_text` themselves.
+      let formatters = batch
+        .columns()
+        .iter()
+        .map(|col| ArrayFormatter::try_new(col.as_ref(), &format_options).ok())
+        .collect::<Vec<_>>();
+
+      for row in 0..batch.num_rows() {
+        let id = ids.value(row).max(0) as u64;
+        if records.len() == page_size {
+          next_match = Some(id);
+          break 'batches;
+        }
+        let mut cols = Vec::with_capacity(batch.num_columns() - 1);
+        let mut obj = Map::with_capacity(batch.num_columns() - 1);
+        for (i, col) in batch.columns().iter().enumerate().skip(1) {
+          let formatter = formatters[i].as_ref();
+          cols.push(sanitize_cell(&arrow_text(col.as_ref(), row, formatter, self.utc_offset_minutes)));
+          obj.insert(
+            schema.field(i).name().clone(),
+            arrow_json(col.as_ref(), row, formatter, self.utc_offset_minutes),
+          );
+        }
+
+        let line = cols.join("\t");
+        let preview = truncate_chars(&line, preview_max_chars);
+
+        // Provide JSON-like raw for the detail view (keys are parquet column names).
+        // Keep JSON valid (do NOT truncate the entire JSON string).
+        let json_raw = serde_json::to_string(&Value::Object(obj))
+          .unwrap_or_else(|_| format!(r#"{{"__raw__":"{}"}}"#, sanitize_json_string(&line)));
+        let raw = Some(json_raw);
+
+        records.push(Record {
+          id,
+          preview,
+          raw,
+          // No byte offsets inside parquet; the row index locates the row (see `row_raw`).
+          meta: Some(RecordMeta {
+            line_no: id,
+            byte_offset: 0,
+            byte_len: 0,
+            part: None,
+          }),
+          derived: None,
+          warning: None,
+          columns: Some(cols),
+        });
+      }
+    }
+
+    let next_line = match &filter {
+      // The footer row count says exactly where the file ends, so a page that ends on the last
+      // row already reports eof.
+      None => {
+        let line = offset + records.len() as u64;
+        (line < self.row_count()?).then_some(line)
+      }
+      Some(_) => next_match,
+    };
+    let next = next_line.map(|line| Cursor { offset: 0, line });
+
+    Ok((
+      LinesPageInternal {
+        records,
+        reached_eof: next.is_none(),
+      },
+      next,
+    ))
+  }
+
+  /// Row `row_idx` (0-based) as `(column, value)` pairs; `None` past the last row.
+  ///
+  /// Read through Arrow like `page`, so TIMESTAMPTZ values (which DuckDB rows don't tell apart
+  /// from plain timestamps) render the same.
+  pub(crate) fn row_values(&self, row_idx: u64) -> Result<Option<Vec<(String, duckdb::types::Value)>>, CoreError> {
+    let row = i64::try_from(row_idx).map_err(|_| {
+      CoreError::InvalidArg(format!("invalid row index for parquet: {row_idx}"))
+    })?;
+
+    let (src, row_number) = self.numbered_source()?;
+    let mut stmt = self.prepare(&format!("SELECT * EXCLUDE ({row_number}) FROM {src} WHERE {row_number} = ?"))?;
+    let batches = stmt
+      .query_arrow(duckdb::params![self.path, row])
+      .map_err(|e| CoreError::Duckdb(format!("Parquet 读取失败：{e}")))?;
+
+    for batch in batches {
+      if batch.num_rows() == 0 {
+        continue;
+      }
+      let schema = batch.schema();
+      let out = batch
+        .columns()
+        .iter()
+        .enumerate()
+        .map(|(i, col)| {
+          let v = arrow_cell(col.as_ref(), 0, None, self.utc_offset_minutes);
+          (schema.field(i).name().clone(), v)
+        })
+        .collect();
+      return Ok(Some(out));
+    }
+    Ok(None)
+  }
+
+  /// The whole value of BLOB column `column` in row `row_idx` (0-based); previews only show its
+  /// start (see `blob_preview`).
+  pub(crate) fn blob(&self, row_idx: u64, column: &str) -> Result<Vec<u8>, CoreError> {
+    let row = i64::try_from(row_idx)
+      .map_err(|_| CoreError::InvalidArg(format!("invalid row index for parquet: {row_idx}")))?;
+    let (src, row_number) = self.numbered_source()?;
+    let sql = format!("SELECT {} FROM {src} WHERE {row_number} = ?", quote_ident(column));
+    let value: Option<duckdb::types::Value> = self
+      .prepare(&sql)?
+      .query_row(duckdb::params![self.path, row], |r| r.get(0))
+      .optional()
+      .map_err(|e| CoreError::Duckdb(format!("Parquet 读取失败：{e}")))?;
+    match value {
+      None => Err(CoreError::InvalidArg(format!("parquet row out of range: {row_idx}"))),
+      Some(duckdb::types::Value::Blob(bytes)) => Ok(bytes),
+      Some(duckdb::types::Value::Null) => Ok(Vec::new()),
+      Some(_) => Err(CoreError::InvalidArg(format!("column {column} is not a BLOB column"))),
+    }
+  }
+
+  /// A single row (0-based) as a JSON string.
+  ///
+  /// This is used by `get_record_raw` for scan_all hits (which only carry `RecordMeta`).
+  pub(crate) fn row_raw(&self, row_idx: u64, raw_max_chars: usize) -> Result<String, CoreError> {
+    let values = self
+      .row_values(row_idx)?
+      .ok_or_else(|| CoreError::InvalidArg(format!("parquet row out of range: {row_idx}")))?;
+    // Use raw_max_chars directly for full content retrieval.
+    let obj: Map<String, Value> = values
+      .into_iter()
+      .map(|(key, v)| (key, duckdb_value_to_json(&v, raw_max_chars)))
+      .collect();
+    serde_json::to_string(&Value::Object(obj))
+      .map_err(|e| CoreError::Duckdb(format!("Parquet 行序列化失败：{e}")))
+  }
+
+  /// Column `(name, type, nullable)` triples from the parquet footer (no rows are read).
+  pub(crate) fn columns(&self) -> Result<Vec<(String, String, bool)>, CoreError> {
+    let mut stmt = self.prepare(&format!("DESCRIBE SELECT * FROM {}", self.source(false)))?;
+    let rows = stmt
+      .query_map(duckdb::params![self.path], |r| {
+        let name: String = r.get(0)?;
+        let data_type: String = r.get(1)?;
+        let null: Option<String> = r.get(2)?;
+        Ok((name, data_type, null.as_deref() != Some("NO")))
+      })
+      .map_err(|e| CoreError::Duckdb(format!("Parquet 读取失败：{e}")))?;
+    let mut out = Vec::new();
+    for r in rows {
+      out.push(r.map_err(|e| CoreError::Duckdb(format!("Parquet 读取失败：{e}")))?);
+    }
+    Ok(out)
+  }
+
+  /// File, row group and column details from the footer (no rows are read).
+  pub(crate) fn metadata(&self) -> Result<ParquetMetadata, CoreError> {
+    self.footer_unreadable("parquet_metadata")?;
+    let err = |e: duckdb::Error| CoreError::Duckdb(format!("Parquet 元数据读取失败：{e}"));
+    let text = |v: duckdb::types::Value| match v {
+      duckdb::types::Value::Null => None,
+      v => Some(value_to_string(&v)),
+    };
+
+    let (row_count, format_version, created_by, file_size_bytes) = self
+      .prepare("SELECT num_rows, format_version, created_by, file_size_bytes FROM parquet_file_metadata(?)")?
+      .query_row(duckdb::params![self.path], |r| {
+        Ok((
+          r.get::<_, Option<i64>>(0)?.unwrap_or(0).max(0) as u64,
+          r.get::<_, Option<i64>>(1)?.unwrap_or(0),
+          r.get::<_, Option<String>>(2)?,
+          r.get::<_, Option<u64>>(3)?.unwrap_or(0),
+        ))
+      })
+      .map_err(err)?;
+
+    // The schema is flattened depth-first: the root, then each field followed by its children.
+    // Walk it with a stack of open groups to get each leaf's dotted path.
+    let mut columns = Vec::new();
+    {
+      let mut stmt = self.prepare(
+        "SELECT name, type, repetition_type, num_children, converted_type, logical_type, duckdb_type \
+         FROM parquet_schema(?)",
+      )?;
+      let mut rows = stmt.query(duckdb::params![self.path]).map_err(err)?;
+      let mut groups: Vec<(String, i64)> = Vec::new();
+      let mut root = true;
+      while let Some(r) = rows.next().map_err(err)? {
+        let name: String = r.get(0).map_err(err)?;
+        let children = r.get::<_, Option<i64>>(3).map_err(err)?.unwrap_or(0);
+        if std::mem::take(&mut root) {
+          continue;
+        }
+        if let Some(parent) = groups.last_mut() {
+          parent.1 -= 1;
+        }
+        if children > 0 {
+          groups.push((name, children));
+          continue;
+        }
+        let path = groups
+          .iter()
+          .map(|(g, _)| g.as_str())
+          .chain(std::iter::once(name.as_str()))
+          .collect::<Vec<_>>()
+          .join(".");
+        let logical = text(r.get(5).map_err(err)?);
+        let converted = r.get::<_, Option<String>>(4).map_err(err)?;
+        columns.push(ParquetColumn {
+          path,
+          physical_type: r.get(1).map_err(err)?,
+          logical_type: logical.or(converted),
+          duckdb_type: r.get(6).map_err(err)?,
+          repetition: r.get(2).map_err(err)?,
+          compression: None,
+          compressed_bytes: 0,
+          uncompressed_bytes: 0,
+        });
+        while groups.last().is_some_and(|(_, left)| *left <= 0) {
+          groups.pop();
+        }
+      }
+    }
+
+    // One row per column chunk; chunk `column_id` is the leaf's index.
+    let mut row_groups: Vec<ParquetRowGroup> = Vec::new();
+    {
+      let mut stmt = self.prepare(
+        "SELECT row_group_id, row_group_num_rows, row_group_bytes, column_id, compression, \
+         total_compressed_size, total_uncompressed_size \
+         FROM parquet_metadata(?) ORDER BY row_group_id, column_id",
+      )?;
+      let mut rows = stmt.query(duckdb::params![self.path]).map_err(err)?;
+      while let Some(r) = rows.next().map_err(err)? {
+        let index = r.get::<_, i64>(0).map_err(err)?.max(0) as u64;
+        let compressed = r.get::<_, Option<i64>>(5).map_err(err)?.unwrap_or(0).max(0) as u64;
+        let uncompressed = r.get::<_, Option<i64>>(6).map_err(err)?.unwrap_or(0).max(0) as u64;
+        if row_groups.last().map(|g| g.index) != Some(index) {
+          row_groups.push(ParquetRowGroup {
+            index,
+            row_count: r.get::<_, Option<i64>>(1).map_err(err)?.unwrap_or(0).max(0) as u64,
+            total_bytes: r.get::<_, Option<i64>>(2).map_err(err)?.unwrap_or(0).max(0) as u64,
+            compressed_bytes: 0,
+          });
+        }
+        if let Some(group) = row_groups.last_mut() {
+          group.compressed_bytes += compressed;
+        }
+        let column_id = r.get::<_, Option<i64>>(3).map_err(err)?;
+        let Some(column) = column_id.and_then(|i| usize::try_from(i).ok()).and_then(|i| columns.get_mut(i)) else {
+          continue;
+        };
+        column.compressed_bytes += compressed;
+        column.uncompressed_bytes += uncompressed;
+        if let Some(codec) = r.get::<_, Option<String>>(4).map_err(err)? {
+          match &mut column.compression {
+            None => column.compression = Some(codec),
+            Some(seen) if !seen.split(", ").any(|c| c == codec) => {
+              seen.push_str(", ");
+              seen.push_str(&codec);
+            }
+            Some(_) => {}
+          }
+        }
+      }
+    }
+
+    let mut key_value = Vec::new();
+    {
+      let mut stmt = self.prepare("SELECT key, value FROM parquet_kv_metadata(?)")?;
+      let rows = stmt
+        .query_map(duckdb::params![self.path], |r| {
+          let key: Vec<u8> = r.get(0)?;
+          let value: Option<Vec<u8>> = r.get(1)?;
+          Ok(ParquetKeyValue {
+            key: String::from_utf8_lossy(&key).into_owned(),
+            value: String::from_utf8_lossy(value.as_deref().unwrap_or_default()).into_owned(),
+          })
+        })
+        .map_err(err)?;
+      for kv in rows {
+        key_value.push(kv.map_err(err)?);
+      }
+    }
+
+    Ok(ParquetMetadata {
+      row_count,
+      format_version,
+      created_by,
+      file_size_bytes,
+      row_groups,
+      columns,
+      key_value,
+    })
+  }
+
+  /// Total row count (DuckDB answers `count(*)` on parquet from the footer metadata), cached
+  /// for the life of the connection.
+  pub(crate) fn row_count(&self) -> Result<u64, CoreError> {
+    if let Some(&n) = self.row_count.get() {
+      return Ok(n);
+    }
+    let n: i64 = self
+      .prepare(&format!("SELECT count(*) FROM {}", self.source(false)))?
+      .query_row(duckdb::params![self.path], |r| r.get(0))
+      .map_err(|e| CoreError::Duckdb(format!("Parquet 读取失败：{e}")))?;
+    Ok(*self.row_count.get_or_init(|| n.max(0) as u64))
+  }
+
+  /// First row (file-wide, 0-based) of each row group, in file order. Encrypted files count as
+  /// one group: their footer is only readable through `read_parquet`.
+  pub(crate) fn row_group_starts(&self) -> Result<&[u64], CoreError> {
+    if let Some(starts) = self.row_group_starts.get() {
+      return Ok(starts);
+    }
+    if self.decryption_key.is_some() {
+      return Ok(self.row_group_starts.get_or_init(|| vec![0]));
+    }
+    let mut stmt = self.prepare(
+      "SELECT DISTINCT row_group_id, row_group_num_rows FROM parquet_metadata(?) ORDER BY row_group_id",
+    )?;
+    let rows = stmt
+      .query_map(duckdb::params![self.path], |r| r.get::<_, i64>(1))
+      .map_err(|e| CoreError::Duckdb(format!("Parquet 元数据读取失败：{e}")))?;
+    let mut starts = Vec::new();
+    let mut next = 0u64;
+    for n in rows {
+      let n = n.map_err(|e| CoreError::Duckdb(format!("Parquet 元数据读取失败：{e}")))?;
+      starts.push(next);
+      next += n.max(0) as u64;
+    }
+    Ok(self.row_group_starts.get_or_init(|| starts))
+  }
+
+  /// Min / max / null count of every column chunk from the footer, by row group then column;
+  /// `stats::parquet_footer_stats` merges them per column.
+  pub(crate) fn chunk_stats(&self) -> Result<Vec<ParquetChunkStats>, CoreError> {
+    self.footer_unreadable("parquet_footer_stats")?;
+    let err = |e: duckdb::Error| CoreError::Duckdb(format!("Parquet 元数据读取失败：{e}"));
+    let mut stmt = self.prepare(
+      "SELECT row_group_num_rows, path_in_schema, type, stats_min_value, stats_max_value, stats_null_count \
+       FROM parquet_metadata(?) ORDER BY row_group_id, column_id",
+    )?;
+    let rows = stmt
+      .query_map(duckdb::params![self.path], |r| {
+        Ok(ParquetChunkStats {
+          row_count: r.get::<_, Option<i64>>(0)?.unwrap_or(0).max(0) as u64,
+          path: r.get::<_, String>(1)?.replace(", ", "."),
+          physical_type: r.get(2)?,
+          min: r.get(3)?,
+          max: r.get(4)?,
+          null_count: r.get::<_, Option<i64>>(5)?.map(|n| n.max(0) as u64),
+        })
+      })
+      .map_err(err)?;
+    rows.collect::<Result<Vec<_>, _>>().map_err(err)
+  }
+
+  /// Reservoir-sample up to `n` rows (repeatable) and return each as a JSON string.
+  pub(crate) fn sample_raw(&self, n: u64) -> Result<Vec<String>, CoreError> {
+    // The sample size cannot be a bound parameter; `n` is an integer so formatting is safe.
+    let sql = format!("SELECT * FROM {} USING SAMPLE reservoir({n} ROWS) REPEATABLE (42)", self.source(false));
+    let mut stmt = self.prepare(&sql)?;
+    let batches = stmt
+      .query_arrow(duckdb::params![self.path])
+      .map_err(|e| CoreError::Duckdb(format!("Parquet 读取失败：{e}")))?;
+
+    let mut out = Vec::new();
+    for batch in batches {
+      let schema = batch.schema();
+      for row in 0..batch.num_rows() {
+        let obj: Map<String, Value> = batch
+          .columns()
+          .iter()
+          .enumerate()
+          .map(|(i, col)| {
+            let v = arrow_json(col.as_ref(), row, None, self.utc_offset_minutes);
+            (schema.field(i).name().clone(), v)
+          })
+          .collect();
+        out.push(
+          serde_json::to_string(&Value::Object(obj))
+            .map_err(|e| CoreError::Duckdb(format!("Parquet 行序列化失败：{e}")))?,
+        );
+      }
+    }
+    Ok(out)
+  }
+
+  /// Every row from `from` (0-based) on that meets `filters` (all rows without), as its id and
+  /// the tab-separated line its preview shows, in order, until `f` returns false. Streamed like
+  /// `page`, for scan_all.
+  pub(crate) fn for_each_line(
+    &self,
+    from: u64,
+    filters: Option<&[ColumnFilter]>,
+    mut f: impl FnMut(u64, String) -> bool,
+  ) -> Result<(), CoreError> {
+    let from_i64 = i64::try_from(from)
+      .map_err(|_| CoreError::InvalidArg(format!("invalid row index for parquet: {from}")))?;
+    let filter = filters.filter(|f| !f.is_empty()).map(compile_filters).transpose()?;
+    let mut params = vec![V::Text(self.path.clone()), V::BigInt(from_i64)];
+    let condition = match &filter {
+      Some(filter) => {
+        params.extend(filter.params.iter().cloned());
+        format!(" AND {}", filter.sql)
+      }
+      None => String::new(),
+    };
+    let (src, row) = self.numbered_source()?;
+    let mut stmt = self.prepare(&format!(
+      "SELECT {row}, * EXCLUDE ({row}) FROM {src} WHERE {row} >= ?{condition} ORDER BY {row}"
+    ))?;
+    let batches = stmt
+      .query_arrow(duckdb::params_from_iter(params))
+      .map_err(|e| CoreError::Duckdb(format!("Parquet 读取失败：{e}")))?;
+
+    let format_options = FormatOptions::default();
+    for batch in batches {
+      let ids = batch.column(0).as_primitive::<Int64Type>();
+      let formatters = batch
+        .columns()
+        .iter()
+        .map(|col| ArrayFormatter::try_new(col.as_ref(), &format_options).ok())
+        .collect::<Vec<_>>();
+      for row in 0..batch.num_rows() {
+        let line = batch
+          .columns()
+          .iter()
+          .enumerate()
+          .skip(1)
+          .map(|(i, col)| {
+            sanitize_cell(&arrow_text(col.as_ref(), row, formatters[i].as_ref(), self.utc_offset_minutes))
+          })
+          .collect::<Vec<_>>()
+          .join("\t");
+        if !f(ids.value(row).max(0) as u64, line) {
+          return Ok(());
+        }
+      }
+    }
+    Ok(())
+  }
+
+  /// Ids of the rows meeting every filter, in file order.
+  pub(crate) fn matching_rows(&self, filters: &[ColumnFilter]) -> Result<Vec<u64>, CoreError> {
+    if filters.is_empty() {
+      return Ok((0..self.row_count()?).collect());
+    }
+    let filter = compile_filters(filters)?;
+    let mut params = vec![V::Text(self.path.clone())];
+    params.extend(filter.params);
+    let (src, row) = self.numbered_source()?;
+    let mut stmt = self.prepare(&format!("SELECT {row} FROM {src} WHERE {} ORDER BY {row}", filter.sql))?;
+    let rows = stmt
+      .query_map(duckdb::params_from_iter(params), |r| r.get::<_, i64>(0))
+      .map_err(|e| CoreError::Duckdb(format!("Parquet 读取失败：{e}")))?;
+    let mut out = Vec::new();
+    for r in rows {
+      out.push(r.map_err(|e| CoreError::Duckdb(format!("Parquet 读取失败：{e}")))?.max(0) as u64);
+    }
+    Ok(out)
+  }
+}
+
+/// Whether `path` ends in the magic of a parquet file with an encrypted footer (`PARE`); such
+/// files only open with their key (see `ParquetConn::with_decryption_key`).
+pub(crate) fn is_encrypted_parquet(path: &Path) -> bool {
+  let mut magic = [0u8; 4];
+  std::fs::File::open(path)
+    .and_then(|mut f| {
+      f.seek(SeekFrom::End(-4))?;
+      f.read_exact(&mut magic)
+    })
+    .is_ok_and(|_| &magic == b"PARE")
+}
+
 pub(crate) fn read_parquet_page(
   path: &Path,
   cursor: Cursor,
   page_size: usize,
   preview_max_chars: usize,
   raw_max_chars: usize,
+  columns: Option<&[String]>,
 ) -> Result<(LinesPageInternal, Option<Cursor>), CoreError> {
-  let offset = cursor.line;
-  let path_str = path
-    .to_str()
-    .ok_or_else(|| CoreError::InvalidArg("invalid path encoding".into()))?;
-
-  let offset_i64 = i64::try_from(offset).map_err(|_| {
-    CoreError::InvalidArg(format!("invalid cursor offset for parquet: {offset}"))
-  })?;
-  let limit_i64 = i64::try_from(page_size)
-    .map_err(|_| CoreError::InvalidArg(format!("invalid page_size: {page_size}")))?;
-
-  let mut records = Vec::with_capacity(page_size);
-  let mut row_idx = offset;
-
-  let conn = duckdb::Connection::open_in_memory()
-    .map_err(|e| CoreError::InvalidArg(format!("DuckDB 初始化失败：{e}")))?;
-
-  // Some builds require explicitly loading the parquet extension even when compiled with it.
-  // Ignore errors to be tolerant across versions/builds.
-  let _ = conn.execute_batch("LOAD parquet;");
-
-  let mut stmt = conn
-    .prepare("SELECT * FROM read_parquet(?) LIMIT ? OFFSET ?")
-    .map_err(|e| CoreError::InvalidArg(format!("DuckDB 准备语句失败：{e}")))?;
-
-  let mut rows = stmt
-    .query(duckdb::params![path_str, limit_i64, offset_i64])
-    .map_err(|e| CoreError::InvalidArg(format!("Parquet 读取失败：{e}")))?;
-
-  // For parquet detail view, show full content without truncation.
-  // The `raw_max_chars` param is ignored for parquet to ensure complete field display.
-  let _ = raw_max_chars;
-  let cell_max = usize::MAX;
-
-  while let Some(row) = rows
-    .next()
-    .map_err(|e| CoreError::InvalidArg(format!("Parquet 读取失败：{e}")))?
-  {
-    let col_count = row.as_ref().column_count();
-    let mut cols = Vec::with_capacity(col_count);
-    let mut obj = Map::with_capacity(col_count);
-    for i in 0..col_count {
-      let key = row
-        .as_ref()
-        .column_name(i)
-        .map(|s| s.to_string())
-        .unwrap_or_else(|_| format!("col_{i}"));
-      let v: duckdb::types::Value = row
-        .get(i)
-        .map_err(|e| CoreError::InvalidArg(format!("Parquet 读取失败：{e}")))?;
-
-      cols.push(sanitize_cell(&value_to_string(&v)));
-      obj.insert(key, duckdb_value_to_json(&v, cell_max));
-    }
-
-    let line = cols.join("\t");
-    let preview = truncate_chars(&line, preview_max_chars);
-
-    // Provide JSON-like raw for the detail view (keys are parquet column names).
-    // Keep JSON valid (do NOT truncate the entire JSON string).
-    let json_raw = serde_json::to_string(&Value::Object(obj))
-      .unwrap_or_else(|_| format!(r#"{{"__raw__":"{}"}}"#, sanitize_json_string(&line)));
-    let raw = Some(json_raw);
-
-    records.push(Record {
-      id: row_idx,
-      preview,
-      raw,
-      // We don't have stable offsets without internal parquet indexing; omit meta.
-      meta: None::<RecordMeta>,
-    });
-    row_idx += 1;
-  }
-
-  // Heuristic: if we got fewer rows than requested, assume eof.
-  let reached_eof = records.len() < page_size;
-  let next = if reached_eof {
-    None
-  } else {
-    Some(Cursor {
-      offset: 0,
-      line: offset + records.len() as u64,
-    })
-  };
+  ParquetConn::open(path)?.page(cursor, page_size, preview_max_chars, raw_max_chars, columns, None)
+}
+
+pub(crate) fn read_parquet_row_raw(path: &Path, row_idx: u64, raw_max_chars: usize) -> Result<String, CoreError> {
+  ParquetConn::open(path)?.row_raw(row_idx, raw_max_chars)
+}
+
+pub(crate) fn read_parquet_columns(path: &Path) -> Result<Vec<(String, String, bool)>, CoreError> {
+  ParquetConn::open(path)?.columns()
+}
+
+pub(crate) fn read_parquet_metadata(path: &Path) -> Result<ParquetMetadata, CoreError> {
+  ParquetConn::open(path)?.metadata()
+}
+
+pub(crate) fn read_parquet_row_count(path: &Path) -> Result<u64, CoreError> {
+  ParquetConn::open(path)?.row_count()
+}
+
+pub(crate) fn read_parquet_sample_raw(path: &Path, n: u64) -> Result<Vec<String>, CoreError> {
+  ParquetConn::open(path)?.sample_raw(n)
+}
+
+/// `ColumnFilter`s as a DuckDB condition: `sql` holds one `?` per `params` entry, in order, so
+/// values are bound rather than spliced into the query.
+struct CompiledFilter {
+  sql: String,
+  params: Vec<duckdb::types::Value>,
+}
 
-  Ok((
-    LinesPageInternal {
-      records,
-      reached_eof,
+fn compile_filters(filters: &[ColumnFilter]) -> Result<CompiledFilter, CoreError> {
+  let mut conditions = Vec::with_capacity(filters.len());
+  let mut params = Vec::new();
+  for filter in filters {
+    let column = quote_ident(&filter.column);
+    let condition = match filter.op {
+      FilterOp::IsNull => format!("{column} IS NULL"),
+      FilterOp::NotNull => format!("{column} IS NOT NULL"),
+      FilterOp::Contains => {
+        params.push(V::Text(filter_text(filter)?));
+        format!("contains(lower(CAST({column} AS VARCHAR)), lower(?))")
+      }
+      FilterOp::StartsWith => {
+        params.push(V::Text(filter_text(filter)?));
+        format!("starts_with(CAST({column} AS VARCHAR), ?)")
+      }
+      op => {
+        params.push(filter_value(filter)?);
+        let op = match op {
+          FilterOp::Eq => "=",
+          FilterOp::Ne => "<>",
+          FilterOp::Lt => "<",
+          FilterOp::Le => "<=",
+          FilterOp::Gt => ">",
+          _ => ">=",
+        };
+        format!("{column} {op} ?")
+      }
+    };
+    conditions.push(format!("({condition})"));
+  }
+  Ok(CompiledFilter {
+    sql: conditions.join(" AND "),
+    params,
+  })
+}
+
+/// A filter's value as DuckDB binds it; strings are cast to the column's type by DuckDB (e.g.
+/// dates and timestamps).
+fn filter_value(filter: &ColumnFilter) -> Result<duckdb::types::Value, CoreError> {
+  match &filter.value {
+    Value::String(s) => Ok(V::Text(s.clone())),
+    Value::Bool(b) => Ok(V::Boolean(*b)),
+    Value::Number(n) => Ok(match (n.as_i64(), n.as_u64()) {
+      (Some(i), _) => V::BigInt(i),
+      (None, Some(u)) => V::UBigInt(u),
+      _ => V::Double(n.as_f64().unwrap_or(f64::NAN)),
+    }),
+    _ => Err(CoreError::InvalidArg(format!(
+      "filter on {} needs a string, number or boolean value",
+      filter.column
+    ))),
+  }
+}
+
+fn filter_text(filter: &ColumnFilter) -> Result<String, CoreError> {
+  match &filter.value {
+    Value::String(s) => Ok(s.clone()),
+    Value::Number(_) | Value::Bool(_) => Ok(filter.value.to_string()),
+    _ => Err(CoreError::InvalidArg(format!("filter on {} needs a text value", filter.column))),
+  }
+}
+
+fn quote_ident(name: &str) -> String {
+  format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// Cell `row` of an Arrow column as a DuckDB value, for `row_values` (exports). Pages don't go
+/// through it: `arrow_text` / `arrow_json` read cells straight into the text and JSON that
+/// `value_to_string` / `duckdb_value_to_json` give for its values. Scalars, dates, times and nested
+/// values (lists, structs, maps) map one to one; decimals become their exact text; other types
+/// become text as Arrow displays them, with `formatter` if the caller has one for `col`.
+///
+/// TIMESTAMPTZ values, which have no `Value` of their own, become ISO-8601 text at
+/// `utc_offset_minutes` (see `iso_timestamp`).
+fn arrow_cell(
+  col: &dyn Array,
+  row: usize,
+  formatter: Option<&ArrayFormatter>,
+  utc_offset_minutes: i32,
+) -> duckdb::types::Value {
+  use duckdb::types::OrderedMap;
+  let cell = |values: &dyn Array, i: usize| arrow_cell(values, i, None, utc_offset_minutes);
+  let items = |values: &dyn Array| (0..values.len()).map(|i| cell(values, i)).collect::<Vec<_>>();
+  if col.is_null(row) {
+    return V::Null;
+  }
+  match col.data_type() {
+    DataType::Boolean => V::Boolean(col.as_boolean().value(row)),
+    DataType::Int8 => V::TinyInt(col.as_primitive::<Int8Type>().value(row)),
+    DataType::Int16 => V::SmallInt(col.as_primitive::<Int16Type>().value(row)),
+    DataType::Int32 => V::Int(col.as_primitive::<Int32Type>().value(row)),
+    DataType::Int64 => V::BigInt(col.as_primitive::<Int64Type>().value(row)),
+    DataType::UInt8 => V::UTinyInt(col.as_primitive::<UInt8Type>().value(row)),
+    DataType::UInt16 => V::USmallInt(col.as_primitive::<UInt16Type>().value(row)),
+    DataType::UInt32 => V::UInt(col.as_primitive::<UInt32Type>().value(row)),
+    DataType::UInt64 => V::UBigInt(col.as_primitive::<UInt64Type>().value(row)),
+    DataType::Float32 => V::Float(col.as_primitive::<Float32Type>().value(row)),
+    DataType::Float64 => V::Double(col.as_primitive::<Float64Type>().value(row)),
+    DataType::Utf8 => V::Text(col.as_string::<i32>().value(row).to_string()),
+    DataType::LargeUtf8 => V::Text(col.as_string::<i64>().value(row).to_string()),
+    DataType::Utf8View => V::Text(col.as_string_view().value(row).to_string()),
+    DataType::Binary => V::Blob(col.as_binary::<i32>().value(row).to_vec()),
+    DataType::LargeBinary => V::Blob(col.as_binary::<i64>().value(row).to_vec()),
+    DataType::BinaryView => V::Blob(col.as_binary_view().value(row).to_vec()),
+    DataType::Date32 => V::Date32(col.as_primitive::<Date32Type>().value(row)),
+    // Written out from the unscaled integer: Arrow's display stops at the declared precision,
+    // which HUGEINT values (exported as DECIMAL(38, 0)) can exceed.
+    DataType::Decimal128(_, scale) => V::Text(decimal_text(
+      col.as_primitive::<Decimal128Type>().value(row).to_string(),
+      *scale,
+    )),
+    DataType::Decimal256(_, scale) => V::Text(decimal_text(
+      col.as_primitive::<Decimal256Type>().value(row).to_string(),
+      *scale,
+    )),
+    DataType::Timestamp(unit, tz) => {
+      let v = match unit {
+        ArrowTimeUnit::Second => col.as_primitive::<TimestampSecondType>().value(row),
+        ArrowTimeUnit::Millisecond => col.as_primitive::<TimestampMillisecondType>().value(row),
+        ArrowTimeUnit::Microsecond => col.as_primitive::<TimestampMicrosecondType>().value(row),
+        ArrowTimeUnit::Nanosecond => col.as_primitive::<TimestampNanosecondType>().value(row),
+      };
+      match tz {
+        Some(_) => V::Text(iso_timestamp(time_unit(unit), v, Some(utc_offset_minutes))),
+        None => V::Timestamp(time_unit(unit), v),
+      }
+    }
+    DataType::Time64(unit) => {
+      let v = match unit {
+        ArrowTimeUnit::Nanosecond => col.as_primitive::<Time64NanosecondType>().value(row),
+        _ => col.as_primitive::<Time64MicrosecondType>().value(row),
+      };
+      V::Time64(time_unit(unit), v)
+    }
+    DataType::List(_) => V::List(items(col.as_list::<i32>().value(row).as_ref())),
+    DataType::LargeList(_) => V::List(items(col.as_list::<i64>().value(row).as_ref())),
+    DataType::FixedSizeList(..) => V::Array(items(col.as_fixed_size_list().value(row).as_ref())),
+    DataType::Struct(_) => {
+      let fields = col.as_struct();
+      let members = fields
+        .column_names()
+        .into_iter()
+        .zip(fields.columns())
+        .map(|(name, values)| (name.to_string(), cell(values.as_ref(), row)))
+        .collect::<Vec<_>>();
+      V::Struct(OrderedMap::from(members))
+    }
+    DataType::Map(..) => {
+      let entries = col.as_map().value(row);
+      let (keys, values) = (entries.column(0), entries.column(1));
+      let pairs = (0..entries.len())
+        .map(|i| (cell(keys.as_ref(), i), cell(values.as_ref(), i)))
+        .collect::<Vec<_>>();
+      V::Map(OrderedMap::from(pairs))
+    }
+    _ => V::Text(match formatter {
+      Some(formatter) => formatter.value(row).to_string(),
+      None => ArrayFormatter::try_new(col, &FormatOptions::default())
+        .map(|f| f.value(row).to_string())
+        .unwrap_or_default(),
+    }),
+  }
+}
+
+/// Cell `row` of an Arrow column as `value_to_string` shows its `arrow_cell` value, without
+/// building that value.
+fn arrow_text(col: &dyn Array, row: usize, formatter: Option<&ArrayFormatter>, utc_offset_minutes: i32) -> String {
+  let text = |values: &dyn Array, i: usize| arrow_text(values, i, None, utc_offset_minutes);
+  let items = |values: &dyn Array| {
+    let inner = (0..values.len()).map(|i| text(values, i)).collect::<Vec<_>>().join(", ");
+    format!("[{inner}]")
+  };
+  if col.is_null(row) {
+    return "null".into();
+  }
+  match col.data_type() {
+    DataType::Boolean => col.as_boolean().value(row).to_string(),
+    DataType::Int8 => col.as_primitive::<Int8Type>().value(row).to_string(),
+    DataType::Int16 => col.as_primitive::<Int16Type>().value(row).to_string(),
+    DataType::Int32 => col.as_primitive::<Int32Type>().value(row).to_string(),
+    DataType::Int64 => col.as_primitive::<Int64Type>().value(row).to_string(),
+    DataType::UInt8 => col.as_primitive::<UInt8Type>().value(row).to_string(),
+    DataType::UInt16 => col.as_primitive::<UInt16Type>().value(row).to_string(),
+    DataType::UInt32 => col.as_primitive::<UInt32Type>().value(row).to_string(),
+    DataType::UInt64 => col.as_primitive::<UInt64Type>().value(row).to_string(),
+    DataType::Float32 => col.as_primitive::<Float32Type>().value(row).to_string(),
+    DataType::Float64 => col.as_primitive::<Float64Type>().value(row).to_string(),
+    DataType::Utf8 => col.as_string::<i32>().value(row).to_string(),
+    DataType::LargeUtf8 => col.as_string::<i64>().value(row).to_string(),
+    DataType::Utf8View => col.as_string_view().value(row).to_string(),
+    DataType::Binary => blob_preview(col.as_binary::<i32>().value(row)),
+    DataType::LargeBinary => blob_preview(col.as_binary::<i64>().value(row)),
+    DataType::BinaryView => blob_preview(col.as_binary_view().value(row)),
+    DataType::Date32 => iso_date(col.as_primitive::<Date32Type>().value(row)),
+    DataType::Decimal128(_, scale) => {
+      decimal_text(col.as_primitive::<Decimal128Type>().value(row).to_string(), *scale)
+    }
+    DataType::Decimal256(_, scale) => {
+      decimal_text(col.as_primitive::<Decimal256Type>().value(row).to_string(), *scale)
+    }
+    DataType::Timestamp(unit, tz) => {
+      let v = match unit {
+        ArrowTimeUnit::Second => col.as_primitive::<TimestampSecondType>().value(row),
+        ArrowTimeUnit::Millisecond => col.as_primitive::<TimestampMillisecondType>().value(row),
+        ArrowTimeUnit::Microsecond => col.as_primitive::<TimestampMicrosecondType>().value(row),
+        ArrowTimeUnit::Nanosecond => col.as_primitive::<TimestampNanosecondType>().value(row),
+      };
+      iso_timestamp(time_unit(unit), v, tz.as_ref().map(|_| utc_offset_minutes))
+    }
+    DataType::Time64(unit) => {
+      let v = match unit {
+        ArrowTimeUnit::Nanosecond => col.as_primitive::<Time64NanosecondType>().value(row),
+        _ => col.as_primitive::<Time64MicrosecondType>().value(row),
+      };
+      iso_time(time_unit(unit), v)
+    }
+    DataType::List(_) => items(col.as_list::<i32>().value(row).as_ref()),
+    DataType::LargeList(_) => items(col.as_list::<i64>().value(row).as_ref()),
+    DataType::FixedSizeList(..) => items(col.as_fixed_size_list().value(row).as_ref()),
+    DataType::Struct(_) => {
+      let fields = col.as_struct();
+      let inner = fields
+        .column_names()
+        .into_iter()
+        .zip(fields.columns())
+        .map(|(name, values)| format!("{name}: {}", text(values.as_ref(), row)))
+        .collect::<Vec<_>>()
+        .join(", ");
+      format!("{{{inner}}}")
+    }
+    DataType::Map(..) => {
+      let entries = col.as_map().value(row);
+      let (keys, values) = (entries.column(0), entries.column(1));
+      let inner = (0..entries.len())
+        .map(|i| format!("{}: {}", text(keys.as_ref(), i), text(values.as_ref(), i)))
+        .collect::<Vec<_>>()
+        .join(", ");
+      format!("{{{inner}}}")
+    }
+    _ => match formatter {
+      Some(formatter) => formatter.value(row).to_string(),
+      None => ArrayFormatter::try_new(col, &FormatOptions::default())
+        .map(|f| f.value(row).to_string())
+        .unwrap_or_default(),
     },
-    next,
-  ))
+  }
+}
+
+/// Cell `row` of an Arrow column as the JSON `duckdb_value_to_json` gives for its `arrow_cell`
+/// value (untruncated), without building that value: booleans, integers, finite floats and
+/// nested values keep their JSON types, everything else is its `arrow_text`.
+fn arrow_json(col: &dyn Array, row: usize, formatter: Option<&ArrayFormatter>, utc_offset_minutes: i32) -> Value {
+  let json = |values: &dyn Array, i: usize| arrow_json(values, i, None, utc_offset_minutes);
+  let items = |values: &dyn Array| Value::Array((0..values.len()).map(|i| json(values, i)).collect());
+  let float = |x: f64| serde_json::Number::from_f64(x).map(Value::Number);
+  if col.is_null(row) {
+    return Value::Null;
+  }
+  let value = match col.data_type() {
+    DataType::Boolean => Some(Value::Bool(col.as_boolean().value(row))),
+    DataType::Int8 => Some(col.as_primitive::<Int8Type>().value(row).into()),
+    DataType::Int16 => Some(col.as_primitive::<Int16Type>().value(row).into()),
+    DataType::Int32 => Some(col.as_primitive::<Int32Type>().value(row).into()),
+    DataType::Int64 => Some(col.as_primitive::<Int64Type>().value(row).into()),
+    DataType::UInt8 => Some(col.as_primitive::<UInt8Type>().value(row).into()),
+    DataType::UInt16 => Some(col.as_primitive::<UInt16Type>().value(row).into()),
+    DataType::UInt32 => Some(col.as_primitive::<UInt32Type>().value(row).into()),
+    DataType::UInt64 => Some(col.as_primitive::<UInt64Type>().value(row).into()),
+    DataType::Float32 => float(f64::from(col.as_primitive::<Float32Type>().value(row))),
+    DataType::Float64 => float(col.as_primitive::<Float64Type>().value(row)),
+    DataType::List(_) => Some(items(col.as_list::<i32>().value(row).as_ref())),
+    DataType::LargeList(_) => Some(items(col.as_list::<i64>().value(row).as_ref())),
+    DataType::FixedSizeList(..) => Some(items(col.as_fixed_size_list().value(row).as_ref())),
+    DataType::Struct(_) => {
+      let fields = col.as_struct();
+      let members = fields
+        .column_names()
+        .into_iter()
+        .zip(fields.columns())
+        .map(|(name, values)| (name.to_string(), json(values.as_ref(), row)))
+        .collect();
+      Some(Value::Object(members))
+    }
+    // JSON keys are strings: map keys are written as `arrow_text` shows them.
+    DataType::Map(..) => {
+      let entries = col.as_map().value(row);
+      let (keys, values) = (entries.column(0), entries.column(1));
+      let members = (0..entries.len())
+        .map(|i| {
+          let key = arrow_text(keys.as_ref(), i, None, utc_offset_minutes);
+          (key, json(values.as_ref(), i))
+        })
+        .collect();
+      Some(Value::Object(members))
+    }
+    _ => None,
+  };
+  value.unwrap_or_else(|| Value::String(arrow_text(col, row, formatter, utc_offset_minutes)))
+}
+
+/// Bytes of a BLOB cell shown in previews and raw JSON; the whole value is saved with
+/// `save_parquet_blob`.
+const BLOB_PREVIEW_BYTES: usize = 16;
+
+/// `blob(<len> bytes, hex:<first bytes>…)`: BLOB cells keep their true size without inlining the
+/// data.
+pub(crate) fn blob_preview(b: &[u8]) -> String {
+  let hex: String = b.iter().take(BLOB_PREVIEW_BYTES).map(|x| format!("{x:02x}")).collect();
+  let more = if b.len() > BLOB_PREVIEW_BYTES { "…" } else { "" };
+  format!("blob({} bytes, hex:{hex}{more})", b.len())
+}
+
+/// `unscaled` (an integer's digits, maybe signed) with the decimal point `scale` digits from
+/// the right, e.g. `("-5", 2)` -> `-0.05`.
+fn decimal_text(unscaled: String, scale: i8) -> String {
+  let (sign, digits) = match unscaled.strip_prefix('-') {
+    Some(digits) => ("-", digits),
+    None => ("", unscaled.as_str()),
+  };
+  if scale <= 0 {
+    let zeros = if digits == "0" { 0 } else { scale.unsigned_abs() as usize };
+    return format!("{sign}{digits}{}", "0".repeat(zeros));
+  }
+  let scale = scale as usize;
+  let digits = format!("{digits:0>width$}", width = scale + 1);
+  let (int, frac) = digits.split_at(digits.len() - scale);
+  format!("{sign}{int}.{frac}")
+}
+
+/// `YYYY-MM-DD` of a DATE (days since 1970-01-01).
+pub(crate) fn iso_date(days: i32) -> String {
+  let (y, m, d) = civil_from_days(i64::from(days));
+  format!("{}-{m:02}-{d:02}", iso_year(y))
+}
+
+/// `HH:MM:SS[.fraction]` of a TIME (`v` units since midnight).
+pub(crate) fn iso_time(unit: duckdb::types::TimeUnit, v: i64) -> String {
+  let (secs, frac) = split_seconds(unit, v);
+  format!("{}{}", hms(secs.rem_euclid(86_400)), fraction(unit, frac))
 }
 
-/// Read a single parquet row (0-based) and return it as a JSON string.
+/// `YYYY-MM-DDTHH:MM:SS[.fraction]` of a TIMESTAMP (`v` units since the epoch).
 ///
-/// This is used by `get_record_raw` for scan_all hits (which only carry `RecordMeta`).
-pub(crate) fn read_parquet_row_raw(
-  path: &Path,
-  row_idx: u64,
-  raw_max_chars: usize,
-) -> Result<String, CoreError> {
-  let path_str = path
-    .to_str()
-    .ok_or_else(|| CoreError::InvalidArg("invalid path encoding".into()))?;
-
-  let offset_i64 = i64::try_from(row_idx).map_err(|_| {
-    CoreError::InvalidArg(format!("invalid row index for parquet: {row_idx}"))
-  })?;
-
-  let conn = duckdb::Connection::open_in_memory()
-    .map_err(|e| CoreError::InvalidArg(format!("DuckDB 初始化失败：{e}")))?;
-  let _ = conn.execute_batch("LOAD parquet;");
-
-  let mut stmt = conn
-    .prepare("SELECT * FROM read_parquet(?) LIMIT 1 OFFSET ?")
-    .map_err(|e| CoreError::InvalidArg(format!("DuckDB 准备语句失败：{e}")))?;
-
-  let mut rows = stmt
-    .query(duckdb::params![path_str, offset_i64])
-    .map_err(|e| CoreError::InvalidArg(format!("Parquet 读取失败：{e}")))?;
-
-  let Some(row) = rows
-    .next()
-    .map_err(|e| CoreError::InvalidArg(format!("Parquet 读取失败：{e}")))?
-  else {
-    return Err(CoreError::InvalidArg(format!(
-      "parquet row out of range: {row_idx}"
-    )));
+/// Without an offset the wall time is shown as stored, with no zone. With one, `v` is taken as a
+/// UTC instant and shown at that many minutes east of UTC, suffixed `Z` or `+HH:MM`.
+pub(crate) fn iso_timestamp(unit: duckdb::types::TimeUnit, v: i64, utc_offset_minutes: Option<i32>) -> String {
+  let (mut secs, frac) = split_seconds(unit, v);
+  if let Some(offset) = utc_offset_minutes {
+    secs = secs.saturating_add(i64::from(offset) * 60);
+  }
+  let (y, m, d) = civil_from_days(secs.div_euclid(86_400));
+  let zone = match utc_offset_minutes {
+    None => String::new(),
+    Some(0) => "Z".to_string(),
+    Some(offset) => {
+      let sign = if offset < 0 { '-' } else { '+' };
+      let abs = offset.unsigned_abs();
+      format!("{sign}{:02}:{:02}", abs / 60, abs % 60)
+    }
   };
+  format!(
+    "{}-{m:02}-{d:02}T{}{}{zone}",
+    iso_year(y),
+    hms(secs.rem_euclid(86_400)),
+    fraction(unit, frac)
+  )
+}
+
+/// Whole seconds and the sub-second remainder (in `unit`, always non-negative) of `v`.
+fn split_seconds(unit: duckdb::types::TimeUnit, v: i64) -> (i64, i64) {
+  let per = units_per_second(unit);
+  (v.div_euclid(per), v.rem_euclid(per))
+}
+
+fn units_per_second(unit: duckdb::types::TimeUnit) -> i64 {
+  use duckdb::types::TimeUnit;
+  match unit {
+    TimeUnit::Second => 1,
+    TimeUnit::Millisecond => 1_000,
+    TimeUnit::Microsecond => 1_000_000,
+    TimeUnit::Nanosecond => 1_000_000_000,
+  }
+}
+
+fn hms(secs_of_day: i64) -> String {
+  format!("{:02}:{:02}:{:02}", secs_of_day / 3600, secs_of_day / 60 % 60, secs_of_day % 60)
+}
+
+/// `.123` for a sub-second remainder (trailing zeros dropped); empty on a whole second.
+fn fraction(unit: duckdb::types::TimeUnit, frac: i64) -> String {
+  if frac == 0 {
+    return String::new();
+  }
+  let width = units_per_second(unit).ilog10() as usize;
+  let digits = format!("{frac:0width$}");
+  format!(".{}", digits.trim_end_matches('0'))
+}
 
-  let col_count = row.as_ref().column_count();
-  let mut obj = Map::with_capacity(col_count);
-  // Use raw_max_chars directly for full content retrieval.
-  let cell_max = raw_max_chars;
-  for i in 0..col_count {
-    let key = row
-      .as_ref()
-      .column_name(i)
-      .map(|s| s.to_string())
-      .unwrap_or_else(|_| format!("col_{i}"));
-    let v: duckdb::types::Value = row
-      .get(i)
-      .map_err(|e| CoreError::InvalidArg(format!("Parquet 读取失败：{e}")))?;
-    obj.insert(key, duckdb_value_to_json(&v, cell_max));
+fn iso_year(y: i64) -> String {
+  if y < 0 {
+    format!("-{:04}", -y)
+  } else {
+    format!("{y:04}")
   }
+}
 
-  serde_json::to_string(&Value::Object(obj))
-    .map_err(|e| CoreError::InvalidArg(format!("Parquet 行序列化失败：{e}")))
+/// Proleptic Gregorian `(year, month, day)` of `days` since 1970-01-01 (H. Hinnant's
+/// `civil_from_days`).
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+  let z = days + 719_468;
+  let era = z.div_euclid(146_097);
+  let doe = z.rem_euclid(146_097);
+  let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+  let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+  let mp = (5 * doy + 2) / 153;
+  let d = (doy - (153 * mp + 2) / 5 + 1) as u32;
+  let m = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+  (yoe + era * 400 + i64::from(m <= 2), m, d)
+}
+
+fn time_unit(unit: &ArrowTimeUnit) -> duckdb::types::TimeUnit {
+  use duckdb::types::TimeUnit;
+  match unit {
+    ArrowTimeUnit::Second => TimeUnit::Second,
+    ArrowTimeUnit::Millisecond => TimeUnit::Millisecond,
+    ArrowTimeUnit::Microsecond => TimeUnit::Microsecond,
+    ArrowTimeUnit::Nanosecond => TimeUnit::Nanosecond,
+  }
 }
 
 fn sanitize_cell(s: &str) -> String {
@@ -190,7 +1306,7 @@ fn truncate_chars(s: &str, max: usize) -> String {
   let mut out = String::new();
   for (i, ch) in s.chars().enumerate() {
     if i >= max {
-      out.push_str("…");
+      out.push('…');
       break;
     }
     out.push(ch);
@@ -215,14 +1331,11 @@ fn value_to_string(v: &duckdb::types::Value) -> String {
     Value::Float(x) => x.to_string(),
     Value::Double(x) => x.to_string(),
     Value::Decimal(d) => d.to_string(),
-    Value::Timestamp(unit, v) => format!("timestamp({unit:?},{v})"),
+    Value::Timestamp(unit, v) => iso_timestamp(*unit, *v, None),
     Value::Text(s) => s.clone(),
-    Value::Blob(b) => {
-      let encoded = base64::engine::general_purpose::STANDARD.encode(b);
-      format!("blob(base64:{encoded})")
-    }
-    Value::Date32(days) => format!("date32({days})"),
-    Value::Time64(unit, v) => format!("time64({unit:?},{v})"),
+    Value::Blob(b) => blob_preview(b),
+    Value::Date32(days) => iso_date(*days),
+    Value::Time64(unit, v) => iso_time(*unit, *v),
     Value::Interval { months, days, nanos } => format!("interval({months}m,{days}d,{nanos}n)"),
     Value::List(xs) | Value::Array(xs) => {
       let inner = xs.iter().map(value_to_string).collect::<Vec<_>>().join(", ");
@@ -272,7 +1385,35 @@ fn duckdb_value_to_json(v: &duckdb::types::Value, cell_max: usize) -> Value {
       .map(Value::Number)
       .unwrap_or_else(|| Value::String(truncate_chars(&x.to_string(), cell_max))),
 
-    V::Text(s) => Value::String(truncate_chars(s, cell_max)),
+    V::Text(s) | V::Enum(s) => Value::String(truncate_chars(s, cell_max)),
+
+    // Exact text rather than numbers JSON readers would round to f64.
+    V::HugeInt(x) => Value::String(x.to_string()),
+    V::Decimal(d) => Value::String(d.to_string()),
+
+    // Nested values become real JSON, so the JSON tree, subtree export and key:value search see
+    // their members.
+    V::List(xs) | V::Array(xs) => Value::Array(xs.iter().map(|x| duckdb_value_to_json(x, cell_max)).collect()),
+    V::Struct(members) => Value::Object(
+      members
+        .iter()
+        .map(|(k, v)| (k.clone(), duckdb_value_to_json(v, cell_max)))
+        .collect(),
+    ),
+    // JSON keys are strings: non-text map keys are written as `value_to_string` shows them.
+    V::Map(entries) => Value::Object(
+      entries
+        .iter()
+        .map(|(k, v)| {
+          let key = match k {
+            V::Text(s) => s.clone(),
+            other => value_to_string(other),
+          };
+          (key, duckdb_value_to_json(v, cell_max))
+        })
+        .collect(),
+    ),
+    V::Union(v) => duckdb_value_to_json(v, cell_max),
 
     // Keep other types stable by stringifying (still readable in JsonTree).
     other => Value::String(truncate_chars(&value_to_string(other), cell_max)),
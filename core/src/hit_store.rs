@@ -0,0 +1,141 @@
+use std::{
+  fs::{File, OpenOptions},
+  io::{BufRead, BufReader, BufWriter, Seek, SeekFrom, Write},
+  path::PathBuf,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::models::TaskError;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct SearchHit {
+  /// Record id shown to the user (differs from `line_no` only in multi-file sessions).
+  pub id: u64,
+  pub line_no: u64,
+  pub part: Option<u32>,
+  pub byte_offset: u64,
+  pub byte_len: u64,
+  pub preview: String,
+}
+
+impl SearchHit {
+  /// Rough heap + inline size, counted against the task's memory budget.
+  fn mem_size(&self) -> usize {
+    std::mem::size_of::<Self>() + self.preview.len()
+  }
+}
+
+/// Hits of a scan_all task, in the order found.
+///
+/// Hits are kept in memory until they take `budget_bytes`; later ones are appended to a temp
+/// file (one JSON line each) and read back from it when paged. Only their offsets stay in memory.
+/// The file is removed when the store is dropped.
+#[derive(Debug, Default)]
+pub(crate) struct HitStore {
+  /// 0: no budget, everything stays in memory.
+  budget_bytes: usize,
+  mem: Vec<SearchHit>,
+  mem_bytes: usize,
+  spill: Option<Spill>,
+}
+
+#[derive(Debug)]
+struct Spill {
+  writer: BufWriter<File>,
+  /// Start of each spilled hit's line.
+  offsets: Vec<u64>,
+  end: u64,
+  /// Last, so the file is closed by the time it is removed.
+  path: SpillPath,
+}
+
+#[derive(Debug)]
+struct SpillPath(PathBuf);
+
+impl HitStore {
+  pub(crate) fn new(budget_bytes: usize) -> Self {
+    Self {
+      budget_bytes,
+      ..Self::default()
+    }
+  }
+
+  pub(crate) fn len(&self) -> usize {
+    self.mem.len() + self.spill.as_ref().map_or(0, |s| s.offsets.len())
+  }
+
+  pub(crate) fn push(&mut self, hit: SearchHit) -> Result<(), TaskError> {
+    let size = hit.mem_size();
+    if self.spill.is_none() && (self.budget_bytes == 0 || self.mem_bytes + size <= self.budget_bytes) {
+      self.mem_bytes += size;
+      self.mem.push(hit);
+      return Ok(());
+    }
+    let spill = match &mut self.spill {
+      Some(spill) => spill,
+      None => self.spill.insert(Spill::create()?),
+    };
+    let mut line = serde_json::to_vec(&hit).map_err(|e| TaskError::Io { message: e.to_string() })?;
+    line.push(b'\n');
+    spill.writer.write_all(&line)?;
+    spill.offsets.push(spill.end);
+    spill.end += line.len() as u64;
+    Ok(())
+  }
+
+  /// Up to `take` hits from index `skip`.
+  pub(crate) fn page(&mut self, skip: usize, take: usize) -> Result<Vec<SearchHit>, TaskError> {
+    let mut out: Vec<SearchHit> = self.mem.iter().skip(skip).take(take).cloned().collect();
+    if out.len() < take {
+      if let Some(spill) = &mut self.spill {
+        let from = skip.saturating_sub(self.mem.len());
+        spill.read(from, take - out.len(), |hit| out.push(hit))?;
+      }
+    }
+    Ok(out)
+  }
+
+  /// Every hit, in order.
+  pub(crate) fn for_each(&mut self, mut f: impl FnMut(&SearchHit)) -> Result<(), TaskError> {
+    self.mem.iter().for_each(&mut f);
+    if let Some(spill) = &mut self.spill {
+      spill.read(0, usize::MAX, |hit| f(&hit))?;
+    }
+    Ok(())
+  }
+}
+
+impl Spill {
+  fn create() -> Result<Self, TaskError> {
+    let path = std::env::temp_dir().join(format!("datalens-hits-{}.jsonl", uuid::Uuid::new_v4()));
+    let file = OpenOptions::new().create_new(true).append(true).open(&path)?;
+    Ok(Self {
+      writer: BufWriter::new(file),
+      offsets: Vec::new(),
+      end: 0,
+      path: SpillPath(path),
+    })
+  }
+
+  fn read(&mut self, skip: usize, take: usize, mut f: impl FnMut(SearchHit)) -> Result<(), TaskError> {
+    let Some(&start) = self.offsets.get(skip) else {
+      return Ok(());
+    };
+    self.writer.flush()?;
+    let mut file = File::open(&self.path.0)?;
+    file.seek(SeekFrom::Start(start))?;
+    let reader = BufReader::new(file);
+    for line in reader.lines().take(take.min(self.offsets.len() - skip)) {
+      let hit = serde_json::from_str(&line?).map_err(|e| TaskError::Io { message: e.to_string() })?;
+      f(hit);
+    }
+    Ok(())
+  }
+}
+
+impl Drop for SpillPath {
+  fn drop(&mut self) {
+    let _ = std::fs::remove_file(&self.0);
+  }
+}
@@ -0,0 +1,114 @@
+use std::collections::HashMap;
+
+use crate::models::JsonPathSegment;
+
+/// Containers smaller than this aren't indexed: reading them is cheap, and leaving them out
+/// keeps an index to a few entries per 64 KiB of record.
+pub(crate) const JSON_INDEX_MIN_NODE_BYTES: u64 = 64 * 1024;
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// Bytes of one entry in `JsonNodeIndex::to_blob`.
+const ENTRY_BYTES: usize = 8 * 4 + 1;
+
+/// Hash of the record root's (empty) path.
+pub(crate) const ROOT_PATH_HASH: u64 = FNV_OFFSET;
+
+fn fnv(mut hash: u64, bytes: &[u8]) -> u64 {
+  for &b in bytes {
+    hash ^= b as u64;
+    hash = hash.wrapping_mul(FNV_PRIME);
+  }
+  hash
+}
+
+/// Hash of the path `parent`'s path + `seg` (FNV-1a, so it is stable across runs and builds).
+pub(crate) fn child_path_hash(parent: u64, seg: &JsonPathSegment) -> u64 {
+  match seg {
+    JsonPathSegment::Key(key) => {
+      let hash = fnv(parent, b"k");
+      fnv(fnv(hash, &(key.len() as u64).to_le_bytes()), key.as_bytes())
+    }
+    JsonPathSegment::Index(index) => fnv(fnv(parent, b"i"), &index.to_le_bytes()),
+  }
+}
+
+/// One indexed container of a record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct IndexedNode {
+  /// `child_path_hash` chain from the record root.
+  pub path_hash: u64,
+  /// Absolute byte offset of the node's `{` / `[`.
+  pub offset: u64,
+  pub byte_len: u64,
+  /// Direct members / elements.
+  pub child_count: u64,
+  pub object: bool,
+}
+
+/// Offsets of the large containers of one JSON record, by path hash and by offset, so tree calls
+/// can start at the deepest indexed node of a path instead of the record start. Built by
+/// `formats::index_json_record`; persisted per file version (see `Storage::save_json_node_index`).
+#[derive(Debug, Clone, Default)]
+pub(crate) struct JsonNodeIndex {
+  by_hash: HashMap<u64, IndexedNode>,
+  by_offset: HashMap<u64, u64>,
+}
+
+impl JsonNodeIndex {
+  pub(crate) fn from_nodes(nodes: impl IntoIterator<Item = IndexedNode>) -> Self {
+    let mut index = Self::default();
+    for node in nodes {
+      index.by_offset.insert(node.offset, node.path_hash);
+      index.by_hash.insert(node.path_hash, node);
+    }
+    index
+  }
+
+  /// The deepest indexed node along `path` (the root included), with how many segments of
+  /// `path` lead to it.
+  pub(crate) fn deepest_on_path(&self, path: &[JsonPathSegment]) -> Option<(usize, IndexedNode)> {
+    let mut hash = ROOT_PATH_HASH;
+    let mut found = self.by_hash.get(&hash).map(|n| (0, *n));
+    for (i, seg) in path.iter().enumerate() {
+      hash = child_path_hash(hash, seg);
+      if let Some(node) = self.by_hash.get(&hash) {
+        found = Some((i + 1, *node));
+      }
+    }
+    found
+  }
+
+  pub(crate) fn at_offset(&self, offset: u64) -> Option<IndexedNode> {
+    self.by_offset.get(&offset).and_then(|h| self.by_hash.get(h)).copied()
+  }
+
+  pub(crate) fn to_blob(&self) -> Vec<u8> {
+    let mut nodes: Vec<&IndexedNode> = self.by_hash.values().collect();
+    nodes.sort_by_key(|n| n.offset);
+    let mut blob = Vec::with_capacity(nodes.len() * ENTRY_BYTES);
+    for n in nodes {
+      for v in [n.path_hash, n.offset, n.byte_len, n.child_count] {
+        blob.extend_from_slice(&v.to_le_bytes());
+      }
+      blob.push(n.object as u8);
+    }
+    blob
+  }
+
+  /// `None` if `blob` isn't a whole number of entries.
+  pub(crate) fn from_blob(blob: &[u8]) -> Option<Self> {
+    if !blob.len().is_multiple_of(ENTRY_BYTES) {
+      return None;
+    }
+    let u64_at = |entry: &[u8], i: usize| u64::from_le_bytes(entry[i * 8..i * 8 + 8].try_into().unwrap_or_default());
+    Some(Self::from_nodes(blob.chunks_exact(ENTRY_BYTES).map(|e| IndexedNode {
+      path_hash: u64_at(e, 0),
+      offset: u64_at(e, 1),
+      byte_len: u64_at(e, 2),
+      child_count: u64_at(e, 3),
+      object: e[32] != 0,
+    })))
+  }
+}
@@ -1,19 +1,45 @@
+mod crypt;
 mod cursor;
+mod dedup;
+mod derive;
+mod diff;
+mod encoding;
 mod engine;
 mod export;
 mod formats;
+mod hit_store;
+mod json_index;
+mod line_index;
 mod models;
+mod progress;
+mod remote;
+mod schema;
 mod search_match;
+mod shards;
+mod sort;
+mod stats;
 mod storage;
 mod tasks;
+mod text_index;
 
 pub use crate::engine::{CoreEngine, CoreOptions};
 pub use crate::models::{
-  ExportFormat, ExportRequest, ExportResult, FileFormat, JsonPathSegment, Record, RecordMeta,
+  ExportFormat, ExportRequest, ExportResult, FileFormat, JsonPathSegment, JsonPathStrings, Record, RecordMeta,
   RecordPage, SearchMode, SearchQuery, SearchResult, SessionInfo, StatsResult, Task, TaskInfo,
-  TaskKind, JsonNodeKind, JsonChildItem, JsonChildrenPage, JsonNodeSummary, JsonChildItemOffset,
-  JsonChildrenPageOffset, JsonNodeSummaryOffset,
+  TaskKind, JsonNodeKind, JsonChildSort, JsonChildItem, JsonChildrenPage, JsonNodeSummary, JsonChildItemOffset,
+  JsonChildrenPageOffset, JsonNodeSummaryOffset, JsonNodeStats, JsonTableCell, JsonTableRow,
+  JsonTablePage, JsonFindQuery, JsonFindHit, JsonFindResult, JsonPathMatch,
+  JsonPathResult, JsonSyntaxError, JsonValidation, Base64Content, Base64Preview, ColumnStats,
+  KindCount, NumericStats, HistogramBin, StatsReportFormat, StatsSampleStrategy, StatsSampleInfo, ColumnConfidence,
+  StatsDiff, ColumnStatsDiff, TextStats, RecordCount, SeekPosition, PositionPage,
+  NewRecords, FileChange, SessionRefresh, SortSpec, RecordLabel, GotoRecord, SessionSchema, SchemaField, CsvSchema,
+  TextEncoding, DiffAlign, DiffChange, DiffEntry, DiffSummary, DiffPage, DerivedColumn,
+  DedupSpec, SessionMetrics, TaskHistoryEntry, TaskError, SearchCount, TaskPriority, TaskEvent,
+  TaskEventKind, Workspace, WorkspaceTab, RestoredTab, RestoredWorkspace, DatasetCollection, ExportPreset,
+  OpenedCollection, ViewPrefs, ReadPosition, InterruptedExport, ParquetMetadata, ParquetRowGroup, ParquetColumn,
+  ParquetKeyValue, ParquetFooterStats, ParquetColumnStats,
+  ColumnFilter, FilterOp, CsvDialect, CsvWarnings, RecordWarning,
 };
-pub use crate::storage::{Storage, StorageOptions};
+pub use crate::storage::{RecentFile, Storage, StorageBackup, StorageLimits, StorageMaintenance, StorageOptions, StorageReport};
 
 pub use crate::engine::CoreError;
@@ -0,0 +1,237 @@
+use std::path::Path;
+
+use crate::{
+  engine::CoreError,
+  formats,
+  models::{CsvDialect, FileFormat},
+  progress::ScanProgress,
+  storage::StoredLineIndex,
+};
+
+/// Distance (in records) between two indexed offsets.
+pub(crate) const LINE_INDEX_STRIDE: u64 = 1024;
+
+/// Sparse record-offset index for JSONL / CSV lines and the elements of `.json` root arrays (a
+/// structural scan, no parsing).
+///
+/// Stores the byte offset of every `LINE_INDEX_STRIDE`th record, so seeking to record N costs at
+/// most `LINE_INDEX_STRIDE` record reads once the file has been indexed up to N. The index grows
+/// lazily: each lookup past the indexed range scans forward from where the last scan stopped.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct LineIndex {
+  /// `checkpoints[i]` = byte offset of record `i * LINE_INDEX_STRIDE`.
+  checkpoints: Vec<u64>,
+  /// Records indexed so far.
+  scanned_records: u64,
+  /// Byte offset right after the last indexed record.
+  scanned_offset: u64,
+  /// True once a scan reached EOF (`scanned_records` is then the total).
+  complete: bool,
+  /// Quoting CSV records are split by (other formats ignore it).
+  csv: CsvDialect,
+  /// `.json` values are read leniently (see `SessionInfo::json_lenient`; other formats ignore it).
+  json_lenient: bool,
+}
+
+impl LineIndex {
+  /// An empty index of CSV records split by `csv`'s quoting.
+  pub(crate) fn for_csv(csv: &CsvDialect) -> Self {
+    Self {
+      csv: csv.clone(),
+      ..Self::default()
+    }
+  }
+
+  /// An empty index of `.json` records, read leniently with `lenient`.
+  pub(crate) fn for_json(lenient: bool) -> Self {
+    Self {
+      json_lenient: lenient,
+      ..Self::default()
+    }
+  }
+
+  /// Byte offset of record `index` (0-based, CSV header = record 0), or `None` if the file has
+  /// fewer records.
+  pub(crate) fn offset_of(&mut self, path: &Path, format: FileFormat, index: u64) -> Result<Option<u64>, CoreError> {
+    if index >= self.scanned_records && !self.complete {
+      self.extend_to(path, format.clone(), index, || false, |_| {})?;
+    }
+    if index >= self.scanned_records {
+      return Ok(None);
+    }
+
+    let cp = index / LINE_INDEX_STRIDE;
+    let mut offset = self.checkpoints[cp as usize];
+    let mut remaining = index - cp * LINE_INDEX_STRIDE;
+    if remaining > 0 {
+      formats::walk_record_lengths(path, format, &self.csv, self.json_lenient, offset, |len| {
+        offset += len;
+        remaining -= 1;
+        remaining > 0
+      })?;
+    }
+    Ok(Some(offset))
+  }
+
+  /// First record starting at or after `byte_offset`, as `(index, offset)`, if that part of the
+  /// file is already indexed (no scan from the file start needed).
+  pub(crate) fn locate(&self, path: &Path, format: FileFormat, byte_offset: u64) -> Result<Option<(u64, u64)>, CoreError> {
+    if byte_offset >= self.scanned_offset || self.checkpoints.is_empty() {
+      return Ok(None);
+    }
+    let cp = self.checkpoints.partition_point(|o| *o <= byte_offset).saturating_sub(1);
+    let mut index = cp as u64 * LINE_INDEX_STRIDE;
+    let mut offset = self.checkpoints[cp];
+    if offset < byte_offset {
+      formats::walk_record_lengths(path, format, &self.csv, self.json_lenient, offset, |len| {
+        offset += len;
+        index += 1;
+        offset < byte_offset
+      })?;
+    }
+    Ok(Some((index, offset)))
+  }
+
+  /// Index the whole file (background task body). Returns `None` if `should_stop` fired first.
+  pub(crate) fn build(
+    path: &Path,
+    format: FileFormat,
+    csv: &CsvDialect,
+    should_stop: impl Fn() -> bool,
+    mut on_progress: impl FnMut(ScanProgress),
+  ) -> Result<Option<Self>, CoreError> {
+    let file_len = std::fs::metadata(path)?.len();
+    let mut index = Self::for_csv(csv);
+    let mut stopped = false;
+    let mut records = 0u64;
+    let mut offset = 0u64;
+    formats::walk_record_lengths(path, format, csv, false, 0, |len| {
+      if records.is_multiple_of(LINE_INDEX_STRIDE) {
+        if should_stop() {
+          stopped = true;
+          return false;
+        }
+        index.checkpoints.push(offset);
+        on_progress(ScanProgress::new(offset, file_len, offset, records));
+      }
+      offset += len;
+      records += 1;
+      true
+    })?;
+    if stopped {
+      return Ok(None);
+    }
+    index.scanned_records = records;
+    index.scanned_offset = offset;
+    index.complete = true;
+    Ok(Some(index))
+  }
+
+  /// Restore a persisted index (as produced by `to_stored`, loaded for `csv`'s quoting).
+  pub(crate) fn from_stored(stored: StoredLineIndex, csv: &CsvDialect) -> Option<Self> {
+    if stored.stride != LINE_INDEX_STRIDE {
+      return None;
+    }
+    Some(Self {
+      checkpoints: stored.checkpoints,
+      scanned_records: stored.total_records,
+      scanned_offset: stored.end_offset,
+      complete: true,
+      csv: csv.clone(),
+      json_lenient: false,
+    })
+  }
+
+  /// Snapshot for persistence; only complete indexes are worth storing. Lenient `.json`
+  /// indexes stay in memory (stored ones are shared by every session of the file).
+  pub(crate) fn to_stored(&self) -> Option<StoredLineIndex> {
+    (self.complete && !self.json_lenient).then(|| StoredLineIndex {
+      stride: LINE_INDEX_STRIDE,
+      total_records: self.scanned_records,
+      end_offset: self.scanned_offset,
+      checkpoints: self.checkpoints.clone(),
+      csv_quoting: csv_quoting_key(&self.csv),
+    })
+  }
+
+  /// The file had records appended: a complete index becomes resumable again.
+  pub(crate) fn mark_grown(&mut self) {
+    self.complete = false;
+  }
+
+  /// True if `offset_of(index)` needs no scan past the indexed range.
+  pub(crate) fn covers(&self, index: u64) -> bool {
+    self.complete || index < self.scanned_records
+  }
+
+  /// Records indexed so far.
+  pub(crate) fn indexed_records(&self) -> u64 {
+    self.scanned_records
+  }
+
+  /// Index up to record `target` (or EOF) in the background. Returns `false` if `should_stop`
+  /// fired first; what was scanned so far is kept either way.
+  pub(crate) fn extend_to(
+    &mut self,
+    path: &Path,
+    format: FileFormat,
+    target: u64,
+    should_stop: impl Fn() -> bool,
+    mut on_progress: impl FnMut(ScanProgress),
+  ) -> Result<bool, CoreError> {
+    if self.covers(target) {
+      return Ok(true);
+    }
+    let start = self.scanned_records;
+    let checkpoints = &mut self.checkpoints;
+    let mut records = self.scanned_records;
+    let start_offset = self.scanned_offset;
+    let mut offset = start_offset;
+    let mut stopped = false;
+    formats::walk_record_lengths(path, format, &self.csv, self.json_lenient, offset, |len| {
+      if records.is_multiple_of(LINE_INDEX_STRIDE) {
+        if should_stop() {
+          stopped = true;
+          return false;
+        }
+        checkpoints.push(offset);
+        on_progress(ScanProgress::new(
+          records - start,
+          target.saturating_add(1) - start,
+          offset - start_offset,
+          records - start,
+        ));
+      }
+      offset += len;
+      records += 1;
+      records <= target
+    })?;
+    // A stop lands on a stride boundary before its checkpoint is pushed, so resuming is exact.
+    if !stopped {
+      self.complete = records <= target;
+    }
+    self.scanned_records = records;
+    self.scanned_offset = offset;
+    Ok(!stopped)
+  }
+
+  /// Total records, if a scan already reached EOF.
+  pub(crate) fn total_records(&self) -> Option<u64> {
+    self.complete.then_some(self.scanned_records)
+  }
+}
+
+/// How `csv` splits records, as persisted with an index: empty for standard CSV, else the
+/// delimiter, quote and escape character (if any), then the delimiter regex if there is one, one
+/// per line (none holds a line break).
+pub(crate) fn csv_quoting_key(csv: &CsvDialect) -> String {
+  if csv.same_records(&CsvDialect::default()) {
+    return String::new();
+  }
+  let mut key = format!("{}\n{}\n{}", csv.delimiter, csv.quote, csv.escape.map(String::from).unwrap_or_default());
+  if let Some(regex) = &csv.delimiter_regex {
+    key.push('\n');
+    key.push_str(regex);
+  }
+  key
+}
@@ -10,19 +10,265 @@ pub enum FileFormat {
   Unknown,
 }
 
+/// Text encoding of a JSONL / CSV session (detected on open, see `set_session_encoding`).
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TextEncoding {
+  #[default]
+  Utf8,
+  /// ISO-8859-1.
+  Latin1,
+  /// GBK / GB18030.
+  Gb18030,
+  ShiftJis,
+}
+
+/// How a CSV session's rows are read (see `set_csv_dialect`).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct CsvDialect {
+  /// Row 0 names the columns. Off: it is data like any other row, and columns are keyed
+  /// `col_0..col_<n-1>` after the first row's field count.
+  pub has_header: bool,
+  /// Cells that read as numbers, `true` / `false` or `null` (empty cells too) become those JSON
+  /// types in `Record.raw` and JSON exports. Off: every cell is a string.
+  pub infer_types: bool,
+  /// Separates cells: one character like `;`, tab or `|` (see `csv_schema` for a detected one),
+  /// or several, like the `||` some ETL exports write. Literal text; it is also what edits and
+  /// exports write between cells when `delimiter_regex` splits them.
+  pub delimiter: String,
+  /// A regular expression (e.g. `\s*\|\s*`, `[,;]`) splitting cells instead of `delimiter`,
+  /// which it must match. Never matched inside quotes or across a line break.
+  pub delimiter_regex: Option<String>,
+  /// Opens and closes quoted cells (which may hold delimiters and line breaks); doubled inside
+  /// one, it stands for itself. `'` for tools that quote with single quotes.
+  pub quote: char,
+  /// Makes the next character literal (delimiter, quote, line break or itself), inside quotes
+  /// or not, e.g. `\` for `a\,b`. None: only doubled quotes escape.
+  pub escape: Option<char>,
+}
+
+impl Default for CsvDialect {
+  fn default() -> Self {
+    Self {
+      has_header: true,
+      infer_types: false,
+      delimiter: ",".into(),
+      delimiter_regex: None,
+      quote: '"',
+      escape: None,
+    }
+  }
+}
+
+impl CsvDialect {
+  /// Whether both split a file into the same records (quoting decides where line breaks end one,
+  /// and quotes only open a cell right after a delimiter).
+  pub(crate) fn same_records(&self, other: &CsvDialect) -> bool {
+    self.delimiter == other.delimiter
+      && self.delimiter_regex == other.delimiter_regex
+      && self.quote == other.quote
+      && self.escape == other.escape
+  }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SessionInfo {
   pub session_id: String,
   pub path: String,
   pub format: FileFormat,
   pub created_at_ms: i64,
+  /// Background line-offset index build started on open (large JSONL / CSV files only).
+  #[serde(default)]
+  pub index_task: Option<TaskInfo>,
+  /// Multi-file sessions (see `open_files`): every part, in record order. Empty otherwise.
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub parts: Vec<String>,
+  /// Filtered sessions (see `open_search_results`): the scan_all task whose hits are paged.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub filter_task_id: Option<String>,
+  /// Sorted sessions (see `open_sorted_view`): the ordering records are paged in.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub sort: Option<SortSpec>,
+  /// Deduplicated sessions (see `open_dedup_view`): which records are kept.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub dedup: Option<DedupSpec>,
+  /// How JSONL / CSV bytes are decoded for previews, raw and search (other formats: UTF-8).
+  #[serde(default)]
+  pub encoding: TextEncoding,
+  /// Computed fields (see `set_derived_columns`), evaluated into every record's `derived`.
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub derived: Vec<DerivedColumn>,
+  /// Streamed sessions (see `open_stream`): the task copying the input into `path`.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub stream_task: Option<TaskInfo>,
+  /// Remote sessions (`open_file` of an http/https URL): the URL; `path` is the local copy.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub source_url: Option<String>,
+  /// `open_file`: what `save_view_prefs` last stored for this file (already applied: encoding,
+  /// CSV dialect, columns, first page size, lenient JSON).
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub view_prefs: Option<ViewPrefs>,
+  /// `open_file`: where the user left off in this version of the file, if past the start (see
+  /// `page_at_read_position`).
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub read_position: Option<ReadPosition>,
+  /// CSV / Parquet sessions: the only columns pages show (see `set_session_columns`;
+  /// `open_file` starts with the saved `view_prefs.columns` the file still has). Unset: all.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub columns: Option<Vec<String>>,
+  /// Parquet sessions: the conditions rows must meet to be paged, scanned and exported (see
+  /// `set_session_filters`). Unset: every row.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub filters: Option<Vec<ColumnFilter>>,
+  /// CSV sessions: how rows are read (see `set_csv_dialect`).
+  #[serde(default)]
+  pub csv: CsvDialect,
+  /// JSON / JSONL sessions: comments and trailing commas are skipped (see `set_json_lenient`).
+  #[serde(default)]
+  pub json_lenient: bool,
+}
+
+/// First record of the last page served for a file, kept per file version (size + mtime).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ReadPosition {
+  pub record_index: u64,
+  /// JSONL / CSV: where that record starts, so resuming needs no scan up to it.
+  pub byte_offset: Option<u64>,
+  pub updated_at_ms: i64,
+}
+
+/// How the user last viewed a file (see `save_view_prefs`); unset fields keep the defaults.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ViewPrefs {
+  /// CSV: how rows are read (see `set_csv_dialect`), e.g. the delimiter the user picked.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub csv: Option<CsvDialect>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub encoding: Option<TextEncoding>,
+  /// CSV / Parquet columns shown (see `next_page_with_columns`).
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub columns: Option<Vec<String>>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub page_size: Option<u32>,
+  /// JSON / JSONL: open with comments and trailing commas skipped (see `set_json_lenient`).
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub json_lenient: Option<bool>,
+}
+
+/// A tab of the saved workspace (see `save_workspace`): the files to reopen, i.e. one path or
+/// URL, or every part of a multi-file session.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct WorkspaceTab {
+  pub paths: Vec<String>,
+}
+
+/// The tabs open when the workspace was saved, in order.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Workspace {
+  pub tabs: Vec<WorkspaceTab>,
+  /// Index into `tabs`.
+  pub active_tab: Option<usize>,
+}
+
+/// A reopened workspace tab.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RestoredTab {
+  pub session: SessionInfo,
+  pub page: RecordPage,
+}
+
+/// Result of `restore_workspace`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RestoredWorkspace {
+  pub tabs: Vec<RestoredTab>,
+  /// Index into `tabs`; unset if the active tab could not be reopened.
+  pub active_tab: Option<usize>,
+  /// Tabs left out because a file is gone or failed to open.
+  pub missing: Vec<WorkspaceTab>,
+}
+
+/// A named group of related files, e.g. every shard of a dataset plus its docs (see
+/// `save_collection`).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DatasetCollection {
+  pub name: String,
+  pub paths: Vec<String>,
+  pub created_at_ms: i64,
+  pub updated_at_ms: i64,
+}
+
+/// Result of `open_collection`: a session per member.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpenedCollection {
+  pub tabs: Vec<RestoredTab>,
+  /// Members that are gone or failed to open (e.g. docs in a format the viewer does not read).
+  pub skipped: Vec<String>,
+}
+
+/// A computed field, e.g. `{ "name": "text_len", "expr": "len(text)" }` (syntax in `derive.rs`).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DerivedColumn {
+  pub name: String,
+  pub expr: String,
+}
+
+/// Ordering for `open_sorted_view`: `key` is a CSV header, parquet column or JSON key.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SortSpec {
+  pub key: String,
+  #[serde(default)]
+  pub descending: bool,
+}
+
+/// One condition of a parquet session's filters (`set_session_filters`); a row must meet all of
+/// them. `column` is a top-level parquet column.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ColumnFilter {
+  pub column: String,
+  pub op: FilterOp,
+  /// Compared value (string, number or boolean); unused by `is_null` / `not_null`.
+  #[serde(default)]
+  pub value: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterOp {
+  Eq,
+  Ne,
+  Lt,
+  Le,
+  Gt,
+  Ge,
+  /// Case-insensitive substring of the value as text.
+  Contains,
+  StartsWith,
+  IsNull,
+  NotNull,
+}
+
+/// Duplicate filter for `open_dedup_view`: records match when `key`'s value (a CSV header,
+/// parquet column or JSON key) is equal, or when their whole content is if `key` is unset.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DedupSpec {
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub key: Option<String>,
+  /// Show only records that have a duplicate (every copy) instead of hiding the repeats.
+  #[serde(default)]
+  pub only_duplicates: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RecordMeta {
+  /// Parquet: the row index (`byte_offset` / `byte_len` are 0).
   pub line_no: u64,
   pub byte_offset: u64,
   pub byte_len: u64,
+  /// Multi-file sessions: index of the part this meta points into (`line_no` / `byte_offset`
+  /// are local to that file).
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub part: Option<u32>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -31,6 +277,34 @@ pub struct Record {
   pub preview: String,
   pub raw: Option<String>,
   pub meta: Option<RecordMeta>,
+  /// Values of the session's derived columns, by name (absent when it defines none).
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub derived: Option<serde_json::Map<String, serde_json::Value>>,
+  /// CSV: why the row is malformed (field count differs from the header's, or a quoted field
+  /// is never closed). Absent for well-formed rows and other formats.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub warning: Option<String>,
+  /// CSV / Parquet: the row's cells as text, one per column shown (the header's, or the
+  /// selected ones), for a table grid; the CSV header row holds the column names. CSV cells are
+  /// cut like in `raw`. Absent for other formats.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub columns: Option<Vec<String>>,
+}
+
+/// A malformed record met while paging (see `csv_warnings`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordWarning {
+  pub id: u64,
+  pub meta: Option<RecordMeta>,
+  pub warning: String,
+}
+
+/// Malformed CSV rows among those a session paged so far, by record id.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CsvWarnings {
+  pub rows: Vec<RecordWarning>,
+  /// More rows were malformed than are kept; later ones are not listed.
+  pub truncated: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -38,6 +312,62 @@ pub struct RecordPage {
   pub records: Vec<Record>,
   pub next_cursor: Option<String>,
   pub reached_eof: bool,
+  /// Parquet only: rows in the file, from the footer (other formats: see `count_records`).
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub total_records: Option<u64>,
+  /// Record ids are estimates: the page was read from `page_at_position` without exact ids, or
+  /// from a `next_cursor` of such a page (which keeps the mark). Not saved as read positions.
+  #[serde(default)]
+  pub ids_estimated: bool,
+}
+
+/// Where `page_at_position` starts reading.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum SeekPosition {
+  /// Byte offset into the file, snapped forward to the next record boundary.
+  ByteOffset { offset: u64 },
+  /// Relative position in `[0, 1]`: of the file size for text formats, of the rows for parquet.
+  Fraction { value: f64 },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PositionPage {
+  pub page: RecordPage,
+  /// Byte offset of the first record after snapping (`None` for parquet).
+  pub byte_offset: Option<u64>,
+  /// False when record ids are estimates (a line-format file not indexed that far yet).
+  pub ids_exact: bool,
+}
+
+/// What `refresh_session` found on disk.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FileChange {
+  Unchanged,
+  /// Only appended to: existing cursors and record ids stay valid.
+  Grew,
+  /// Shrunk or modified in place: cached state was dropped; reopen / restart paging.
+  Rewritten,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionRefresh {
+  pub change: FileChange,
+  pub previous_size: u64,
+  pub size: u64,
+  /// Records appended since the last open / refresh (`Grew` on JSONL/CSV only).
+  pub appended_records: Option<u64>,
+}
+
+/// Result of `poll_new_records`: records appended to the file since the previous poll.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewRecords {
+  pub records: Vec<Record>,
+  /// More complete records were appended than `max_records`; poll again right away.
+  pub more_available: bool,
+  /// The file shrank (truncated / rotated): follow restarted from its current end.
+  pub reset: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -45,7 +375,11 @@ pub struct RecordPage {
 pub enum SearchMode {
   CurrentPage,
   ScanAll,
+  /// Like scan_all over JSONL / CSV lines, reading only the parts of the file the full-text
+  /// index (see `build_text_index`) can't rule out. Needs that index.
   Indexed,
+  /// Like scan_all, but only counts matching records (no hits kept; see `search_match_count`).
+  CountOnly,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -53,8 +387,16 @@ pub struct SearchQuery {
   pub text: String,
   pub mode: SearchMode,
   pub case_sensitive: bool,
-  /// For scan_all: max number of hits to keep in memory.
+  /// For scan_all and indexed: max number of hits to keep in memory.
   pub max_hits: u64,
+  /// For scan_all and indexed: stop after this long running (pauses excluded) and keep the hits found so
+  /// far, with `Task.timed_out` set.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub timeout_ms: Option<u64>,
+  /// For scan_all: read the file even if the same query already ran on this file version (the
+  /// cached hits are returned otherwise, see `Task.from_cache`).
+  #[serde(default)]
+  pub force_rescan: bool,
 }
 
 impl Default for SearchQuery {
@@ -64,6 +406,8 @@ impl Default for SearchQuery {
       mode: SearchMode::CurrentPage,
       case_sensitive: false,
       max_hits: 10_000,
+      timeout_ms: None,
+      force_rescan: false,
     }
   }
 }
@@ -82,6 +426,20 @@ pub struct SearchResult {
 pub enum TaskKind {
   SearchScanAll,
   Export,
+  Stats,
+  CountRecords,
+  /// Builds an index stored per file version for reuse: a session's record-offset index (JSONL /
+  /// CSV lines, `.json` root array elements; complete ones only), the structural node index of
+  /// a large JSON record (see `build_json_node_index`) or the full-text index of JSONL / CSV
+  /// lines (see `build_text_index`).
+  #[serde(alias = "line_index")]
+  IndexBuild,
+  Diff,
+  /// A `count_only` search.
+  SearchCount,
+  /// Copies piped input (see `open_stream`) or a remote file (`open_file` of a URL) into the
+  /// session's local file.
+  Spool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -91,15 +449,192 @@ pub struct TaskInfo {
   pub cancellable: bool,
 }
 
+/// Scheduling class of a task. Background tasks (index builds, stats) are started after queued
+/// normal ones and pause while an interactive call (paging, current-page search) runs.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskPriority {
+  #[default]
+  Normal,
+  Background,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Task {
   pub id: String,
   pub kind: TaskKind,
+  #[serde(default)]
+  pub priority: TaskPriority,
   pub started_at_ms: i64,
   pub progress_0_100: u8,
   pub cancellable: bool,
+  /// Waiting for one of the `max_concurrent_tasks` slots; it starts once an earlier task ends.
+  #[serde(default)]
+  pub queued: bool,
+  /// Held by `pause_task` where it is in the file until `resume_task`.
+  #[serde(default)]
+  pub paused: bool,
   pub finished: bool,
-  pub error: Option<String>,
+  /// Why it failed; `cancelled` once a cancelled task has stopped.
+  pub error: Option<TaskError>,
+  /// Stopped by its timeout (see `SearchQuery.timeout_ms`, `start_stats_task_with_timeout`); the
+  /// results are what it got through until then.
+  #[serde(default)]
+  pub timed_out: bool,
+  /// scan_all only: the hits are those of an earlier identical search on the unchanged file,
+  /// returned without reading it (see `SearchQuery.force_rescan`).
+  #[serde(default)]
+  pub from_cache: bool,
+  /// File bytes / records the task got through (bytes stay 0 for Parquet, read in rows).
+  #[serde(default)]
+  pub bytes_processed: u64,
+  #[serde(default)]
+  pub records_processed: u64,
+  /// Searches: matching records found so far, including those past `max_hits` (set for
+  /// scan_all and count_only tasks).
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub hits_found: Option<u64>,
+  /// Average throughput since the task started running (unset before any progress).
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub bytes_per_sec: Option<f64>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub records_per_sec: Option<f64>,
+  /// Time left at the rate `progress_0_100` has moved so far (unset while queued, before any
+  /// progress and once finished).
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub eta_ms: Option<u64>,
+}
+
+/// A finished scan_all search, stats or diff task, or an export, as kept in SQLite across
+/// restarts (see `list_task_history`).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TaskHistoryEntry {
+  /// Row id in the history, increasing with every entry.
+  pub id: i64,
+  pub task_id: String,
+  pub kind: TaskKind,
+  /// Files the task read, to reopen before running it again: every part of a multi-file
+  /// session, the URL of a remote one, the left then right file of a diff.
+  pub paths: Vec<String>,
+  /// Arguments of the call that started it: `{ query }` for a search, `{ align }` for a diff,
+  /// `{ request, format, output_path }` for an export, `{ timeout_ms }` for stats.
+  pub params: serde_json::Value,
+  pub started_at_ms: i64,
+  pub finished_at_ms: i64,
+  /// Time spent running (not queued or paused).
+  pub duration_ms: u64,
+  pub cancelled: bool,
+  /// What it produced, e.g. `12 hits` or `40 records written`; unset when it failed.
+  pub summary: Option<String>,
+  pub error: Option<TaskError>,
+}
+
+/// An export that was still being written when the app last stopped (see
+/// `list_interrupted_exports`). Its temp file is deleted on startup; `destination`, if it exists,
+/// still holds whatever was there before the export.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct InterruptedExport {
+  pub id: i64,
+  pub task_id: String,
+  pub destination: String,
+  pub temp_path: String,
+  pub started_at_ms: i64,
+  /// When startup found it unfinished.
+  pub interrupted_at_ms: i64,
+}
+
+/// What happened to a task, as logged in its event log (see `list_task_events`).
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskEventKind {
+  /// Left the queue (or was answered from the scan cache).
+  Started,
+  /// Got another quarter of the way through.
+  Progress,
+  Cancelled,
+  TimedOut,
+  Finished,
+  Failed,
+}
+
+/// One entry of a background task's event log, with its counters at the time.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TaskEvent {
+  pub task_id: String,
+  pub at_ms: i64,
+  pub event: TaskEventKind,
+  pub progress_0_100: u8,
+  pub records_processed: u64,
+  pub bytes_processed: u64,
+  /// Searches: matches found so far.
+  pub hits_found: Option<u64>,
+  /// Failed: the error; cancelled, timed out and finished: the task's summary (as in the history).
+  pub detail: Option<String>,
+}
+
+/// Why a background task (or an export) failed, e.g. `{ "type": "io", "message": "..." }`, so
+/// the UI can word the message and decide whether offering a retry makes sense.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, thiserror::Error)]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum TaskError {
+  /// Reading or writing a file failed (missing, permissions, disk full); a retry may succeed.
+  #[error("io error: {message}")]
+  Io { message: String },
+  /// Stopped by `cancel_task`; whatever it found so far is kept.
+  #[error("task was cancelled")]
+  Cancelled,
+  /// The file's content is not what its format requires (bad JSON, a `.json` without a root
+  /// array, an unsupported format); retrying the same file fails the same way.
+  #[error("format error: {message}")]
+  Format { message: String },
+  /// A record or result went over a size cap.
+  #[error("limit exceeded: {message}")]
+  LimitExceeded { message: String },
+  /// DuckDB failed to read a Parquet file.
+  #[error("duckdb error: {message}")]
+  Duckdb { message: String },
+}
+
+/// Result of `search_match_count`: matching records of a `count_only` search.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SearchCount {
+  /// Matches so far; the total once `complete`.
+  pub matches: u64,
+  /// False while the task runs, and if it was cancelled or timed out.
+  pub complete: bool,
+}
+
+/// Result of `count_records`: either the total, or the background task computing it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordCount {
+  /// Number of data records (the CSV header row is not counted).
+  pub total: Option<u64>,
+  /// Set while a large file is still being counted; call `count_records` again once it finished.
+  pub task: Option<TaskInfo>,
+}
+
+/// Result of `session_metrics`: what serving the session's pages has cost so far.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct SessionMetrics {
+  /// Pages returned by the open call, paging, jumps and views of this session.
+  pub pages_served: u64,
+  pub records_served: u64,
+  /// File bytes of the records served (JSONL / CSV / JSON; Parquet rows carry no byte ranges).
+  pub bytes_read: u64,
+  /// Jumps the line index already covered and counts answered from the cached total.
+  pub cache_hits: u64,
+  /// Jumps that had to scan the file to grow the line index, and counts that read the file.
+  pub cache_misses: u64,
+  pub avg_page_ms: f64,
+  pub slowest_page_ms: f64,
+}
+
+/// Result of `goto_record`: either the page, or the background scan to wait for.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GotoRecord {
+  pub page: Option<RecordPage>,
+  /// Set while the line index is still being extended; call `goto_record` again once it finished.
+  pub task: Option<TaskInfo>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -121,6 +656,51 @@ pub enum JsonPathSegment {
   Index(u64),
 }
 
+/// A node path written out for "copy path" (see `json_path_strings`).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct JsonPathStrings {
+  /// RFC 6901 JSON Pointer: `/a/b/0` (`""` for the root).
+  pub pointer: String,
+  /// JavaScript accessor: `a.b[0]`, with `["..."]` for keys that aren't identifiers (`""` for
+  /// the root).
+  pub js: String,
+}
+
+impl JsonPathStrings {
+  pub fn from_segments(path: &[JsonPathSegment]) -> Self {
+    let mut pointer = String::new();
+    let mut js = String::new();
+    for seg in path {
+      pointer.push('/');
+      match seg {
+        JsonPathSegment::Key(key) => {
+          pointer.push_str(&key.replace('~', "~0").replace('/', "~1"));
+          let mut chars = key.chars();
+          let identifier = chars
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '$')
+            && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$');
+          if identifier {
+            if !js.is_empty() {
+              js.push('.');
+            }
+            js.push_str(key);
+          } else {
+            js.push('[');
+            js.push_str(&serde_json::Value::from(key.as_str()).to_string());
+            js.push(']');
+          }
+        }
+        JsonPathSegment::Index(index) => {
+          pointer.push_str(&index.to_string());
+          js.push_str(&format!("[{index}]"));
+        }
+      }
+    }
+    Self { pointer, js }
+  }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case", tag = "type")]
 pub enum ExportRequest {
@@ -134,12 +714,36 @@ pub enum ExportRequest {
   /// - `path` selects a subtree within that record (empty means root of that record).
   /// - If `include_root` is true: export the subtree value itself.
   /// - Otherwise: export the selected direct children under the subtree (`children`).
+  /// - With `minify`: whitespace between tokens is dropped, so output is compact even when the
+  ///   source is pretty-printed.
+  /// - CSV output needs the subtree to be an array of flat objects: the header is the union of
+  ///   their keys (first-seen order) and each element is a row; `children` picks elements. It
+  ///   always uses the default dialect (`,`, `"`, `\n` line ends): JSON sessions have none.
   JsonSubtree {
     meta: RecordMeta,
     path: Vec<JsonPathSegment>,
     include_root: bool,
     children: Vec<JsonPathSegment>,
+    #[serde(default)]
+    minify: bool,
+  },
+  /// Export labeled records (only those tagged `tag`, if set) with their labels attached.
+  Labeled {
+    #[serde(default)]
+    tag: Option<String>,
   },
+  /// Export every row of a parquet session that meets its filters (see `set_session_filters`).
+  Filtered,
+}
+
+/// Tags / note attached to a record (see `set_record_label`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordLabel {
+  pub meta: RecordMeta,
+  pub tags: Vec<String>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub note: Option<String>,
+  pub updated_at_ms: i64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -148,6 +752,24 @@ pub struct ExportResult {
   pub records_written: u64,
 }
 
+/// Saved export options, picked by name in `export_with_preset`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ExportPreset {
+  pub name: String,
+  pub format: ExportFormat,
+  /// Fields to keep, in this order (dotted names if `flatten`); `None` keeps every field.
+  #[serde(default)]
+  pub columns: Option<Vec<String>>,
+  /// Nested objects become dotted keys: `{"a":{"b":1}}` -> `{"a.b":1}`.
+  #[serde(default)]
+  pub flatten: bool,
+  /// Output path used when the export names none. `{name}` (source file name without its
+  /// extension), `{format}` (`json` / `jsonl` / `csv`) and `{timestamp}` (Unix seconds) are
+  /// filled in; a relative path is taken from the source file's folder.
+  #[serde(default)]
+  pub destination: Option<String>,
+}
+
 // --- JSON lazy tree (for huge records) ---
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -162,6 +784,19 @@ pub enum JsonNodeKind {
   Unknown,
 }
 
+/// Order of the children listed by `json_list_children` / `json_list_children_at_offset`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum JsonChildSort {
+  /// As written in the file.
+  #[default]
+  Original,
+  /// Object keys alphabetically (case-insensitive); arrays keep their order.
+  Key,
+  /// Largest value first, by bytes as written.
+  Size,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct JsonChildItem {
   /// Key or index under the current node.
@@ -225,9 +860,487 @@ pub struct JsonNodeSummaryOffset {
   pub node_offset: u64,
 }
 
-/// Reserved for M3 (DuckDB stats).
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct StatsResult {
+pub struct JsonTableCell {
+  pub kind: JsonNodeKind,
+  /// Best-effort preview of the member value as written (truncated).
+  pub preview: String,
+  /// Absolute byte offset of the member value, to expand it with `json_list_children_at_offset`.
+  pub value_offset: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsonTableRow {
+  /// Element index in the array.
+  pub index: u64,
+  pub value_offset: u64,
+  pub kind: JsonNodeKind,
+  /// Aligned with `JsonTablePage::columns`; `None` where the element has no such key.
+  pub cells: Vec<Option<JsonTableCell>>,
+  /// Preview of elements that aren't objects (their `cells` are all `None`).
+  pub preview: Option<String>,
+}
+
+/// A page of an array of objects shown as a table (see `json_array_table`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsonTablePage {
+  /// The columns passed in, then keys first seen on this page, in document order.
+  pub columns: Vec<String>,
+  pub rows: Vec<JsonTableRow>,
+  /// Same cursor as `JsonChildrenPageOffset` over the array's elements; `None` means no more.
+  pub next_cursor_offset: Option<u64>,
+  pub next_cursor_index: Option<u64>,
+  pub reached_end: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsonNodeStats {
+  /// Absolute byte offset of the node value.
+  pub node_offset: u64,
+  pub kind: JsonNodeKind,
+  /// Bytes from the node's first byte to its last, as written in the file.
+  pub byte_len: u64,
+  /// Containers nested along the deepest branch, the node included (0 for a scalar).
+  pub max_depth: u64,
+  /// Direct members / elements (containers only).
+  pub child_count: Option<u64>,
+  /// Array elements anywhere in the subtree.
+  pub elements: u64,
+  /// Object keys anywhere in the subtree.
+  pub keys: u64,
+}
+
+/// What a decoded base64 payload holds (see `json_base64_preview`).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Base64Content {
+  /// UTF-8 text without control characters (other than tabs and line breaks).
+  Text,
+  Binary,
+}
+
+/// A JSON string node that looks like base64, decoded (see `json_base64_preview`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Base64Preview {
+  /// Absolute byte offset of the string node.
+  pub node_offset: u64,
+  /// From a `data:` URI prefix, else sniffed from the decoded bytes (PNG, JPEG, GIF, WebP, PDF,
+  /// gzip, zip).
+  pub mime: Option<String>,
+  /// Uses the URL-safe alphabet (`-` / `_`).
+  pub url_safe: bool,
+  /// Size of the whole decoded payload.
+  pub decoded_len: u64,
+  pub content: Base64Content,
+  /// The first decoded bytes: the text itself, or hex pairs separated by spaces.
+  pub preview: String,
+  /// `preview` doesn't cover the whole payload.
+  pub truncated: bool,
+}
+
+/// Where a strict parse of a record stopped (see `json_validate_record`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsonSyntaxError {
+  /// The parser's description, e.g. `trailing comma` or `EOF while parsing an object`.
   pub message: String,
+  /// Absolute byte offset of the error in the session file.
+  pub byte_offset: u64,
+  /// 1-based line within the record.
+  pub line: u64,
+  /// 1-based byte column within `line`.
+  pub column: u64,
+  /// Up to 40 bytes of the record before / from `byte_offset`, as text.
+  pub context_before: String,
+  pub context_after: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsonValidation {
+  pub valid: bool,
+  /// The first error; `None` when `valid`.
+  pub error: Option<JsonSyntaxError>,
+}
+
+/// What `json_find_in_record` looks for.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct JsonFindQuery {
+  pub text: String,
+  pub case_sensitive: bool,
+  /// Match object keys.
+  pub keys: bool,
+  /// Match scalar values (string contents, or numbers / `true` / `false` / `null` as written).
+  pub values: bool,
+  /// Hits to return at most; 0 means 100.
+  pub max_hits: usize,
+}
+
+impl Default for JsonFindQuery {
+  fn default() -> Self {
+    Self {
+      text: String::new(),
+      case_sensitive: false,
+      keys: true,
+      values: true,
+      max_hits: 100,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsonFindHit {
+  /// From the record root down to the matching node (for a key: the member it names).
+  pub path: Vec<JsonPathSegment>,
+  /// Absolute byte offset of each node along `path` (the last one is the hit's), to expand the
+  /// tree down to it with `json_list_children_at_offset`.
+  pub node_offsets: Vec<u64>,
+  /// Absolute byte offset of the hit's value (the record's own for a matching scalar root).
+  pub value_offset: u64,
+  pub kind: JsonNodeKind,
+  /// The key matched, rather than the value.
+  pub key_match: bool,
+  /// Best-effort preview of the value (truncated; containers as `{…}` / `[…]`).
+  pub preview: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsonFindResult {
+  /// In document order.
+  pub hits: Vec<JsonFindHit>,
+  /// `max_hits` was reached before the end of the record.
+  pub truncated: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsonPathMatch {
+  /// From the record root down to the matching node.
+  pub path: Vec<JsonPathSegment>,
+  /// Absolute byte offset of each node along `path` (the last one is the match's), as in
+  /// `JsonFindHit::node_offsets`.
+  pub node_offsets: Vec<u64>,
+  /// Absolute byte offset of the matching value.
+  pub value_offset: u64,
+  pub kind: JsonNodeKind,
+  /// Best-effort preview of the value as written (truncated; containers that hold further
+  /// matches as `{…}` / `[…]`).
+  pub preview: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsonPathResult {
+  /// In document order.
+  pub matches: Vec<JsonPathMatch>,
+  /// `max_matches` was reached before the end of the record.
+  pub truncated: bool,
+}
+
+// --- Stats (M3) ---
+
+/// Quick summary of a CSV file for the folder tree (see `csv_schema`), read without a session.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CsvSchema {
+  /// Header row split by `delimiter` (empty names become `col_<i>`).
+  pub header: Vec<String>,
+  /// The most likely delimiter: `,`, `;`, tab or `|`. Use it as `CsvDialect::delimiter`.
+  pub delimiter: String,
+  /// Types inferred from the first `sampled_records` data rows.
+  pub fields: Vec<SchemaField>,
+  pub sampled_records: u64,
+  /// Data rows (header excluded): exact if the sample reached the end of the file, else
+  /// extrapolated from the sampled rows' average size.
+  pub estimated_records: u64,
+  pub exact: bool,
+}
+
+/// Column names and types of a session (see `get_schema`), for column pickers and typed filters.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionSchema {
+  pub fields: Vec<SchemaField>,
+  /// Records the types were inferred from (0 for parquet, whose schema comes from metadata).
+  pub sampled_records: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SchemaField {
+  /// CSV header, parquet column, or JSON key path (nested object keys joined with `.`; `$` for
+  /// records that are not objects).
+  pub name: String,
+  /// Most common kind among sampled non-null values (`null` if only nulls were seen).
+  pub kind: JsonNodeKind,
+  /// Parquet only: the column type as reported by DuckDB (e.g. `BIGINT`, `VARCHAR[]`).
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub data_type: Option<String>,
+  /// Missing or null in at least one sampled record (parquet: column declared nullable).
+  pub nullable: bool,
+}
+
+/// Footer of a parquet file (see `parquet_metadata`): read without loading any rows.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParquetMetadata {
+  pub row_count: u64,
+  pub format_version: i64,
+  /// Writer, e.g. `parquet-cpp-arrow version 15.0.0`.
+  pub created_by: Option<String>,
+  pub file_size_bytes: u64,
+  pub row_groups: Vec<ParquetRowGroup>,
+  /// Leaf columns in file order.
+  pub columns: Vec<ParquetColumn>,
+  /// File-level key-value metadata (e.g. the `ARROW:schema` / `pandas` entries).
+  pub key_value: Vec<ParquetKeyValue>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParquetRowGroup {
+  pub index: u64,
+  pub row_count: u64,
+  /// Uncompressed size of the group's column data.
+  pub total_bytes: u64,
+  pub compressed_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParquetColumn {
+  /// Dotted path of the leaf (`a.b.list.element` inside nested columns).
+  pub path: String,
+  /// Storage type, e.g. `INT64`, `BYTE_ARRAY`.
+  pub physical_type: Option<String>,
+  /// Logical (or legacy converted) type, e.g. `UTF8`, `TIMESTAMP_MICROS`; `None` for plain values.
+  pub logical_type: Option<String>,
+  /// The type DuckDB reads the column as, e.g. `VARCHAR`.
+  pub duckdb_type: Option<String>,
+  /// `REQUIRED`, `OPTIONAL` or `REPEATED`.
+  pub repetition: Option<String>,
+  /// Codecs used across row groups, e.g. `SNAPPY` (`None` when the file has no row groups).
+  pub compression: Option<String>,
+  pub compressed_bytes: u64,
+  pub uncompressed_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ParquetKeyValue {
+  pub key: String,
+  /// Lossy UTF-8 of the stored bytes.
+  pub value: String,
+}
+
+/// How `start_diff_task` pairs records: by position, or by the value of a key column / JSON key.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum DiffAlign {
+  ByLine,
+  ByKey { key: String },
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffChange {
+  Added,
+  Removed,
+  Changed,
+}
+
+/// One differing record pair; `left` is absent for added records, `right` for removed ones.
+/// Records carry no `raw` (fetch it with `get_record_raw` on the matching session).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiffEntry {
+  pub change: DiffChange,
+  /// By-key diffs: the key value both records share.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub key: Option<String>,
+  pub left: Option<Record>,
+  pub right: Option<Record>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DiffSummary {
+  pub added: u64,
+  pub removed: u64,
+  pub changed: u64,
+  pub unchanged: u64,
+  /// By-key diffs: records without the key, or repeating a key already seen on their side.
+  pub skipped: u64,
+  /// `false` while the task runs or if it was cancelled.
+  pub complete: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiffPage {
+  pub entries: Vec<DiffEntry>,
+  pub next_cursor: Option<String>,
+  pub reached_eof: bool,
+  pub summary: DiffSummary,
+}
+
+/// Per-session profile: the schema (one entry per column / top-level key) plus per-column stats.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatsResult {
+  pub records_scanned: u64,
+  /// Records whose content could not be split into fields (e.g. invalid JSON lines).
+  pub invalid_records: u64,
+  pub columns: Vec<ColumnStats>,
+  /// Present for quick (sample-based) stats; `None` means every record was profiled.
+  pub sample: Option<StatsSampleInfo>,
+  /// False when the scan stopped early (cancelled task) or only a sample was profiled.
+  pub complete: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StatsSampleStrategy {
+  Head,
+  Tail,
+  Random,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatsSampleInfo {
+  /// Strategy actually used (`.json` files always fall back to `head`, CSV files until their
+  /// line index is built).
+  pub strategy: StatsSampleStrategy,
+  pub requested: u64,
+  /// Records actually profiled (valid + invalid).
+  pub sampled: u64,
+  /// Exact when the sample reached EOF; otherwise extrapolated from bytes or file metadata.
+  pub estimated_total_records: Option<u64>,
+}
+
+/// 95% confidence margins (normal approximation) for estimates derived from a sample.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ColumnConfidence {
+  /// Share of sampled records containing the column.
+  pub present_rate: f64,
+  pub present_rate_margin: f64,
+  /// Share of sampled records where the column is null.
+  pub null_rate: f64,
+  pub null_rate_margin: f64,
+  /// Margin for `numeric.mean` (if numeric).
+  pub mean_margin: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ColumnStats {
+  /// CSV header / parquet column name / top-level JSON key (`$` for non-object records).
+  pub name: String,
+  /// Most common kind among non-null values (`null` if the column only held nulls).
+  pub inferred_kind: JsonNodeKind,
+  /// Number of records where the column is present (including explicit nulls).
+  pub present_count: u64,
+  pub null_count: u64,
+  pub kind_counts: Vec<KindCount>,
+  pub distinct_count: u64,
+  /// True if distinct counting stopped at its cap (`distinct_count` is then a lower bound).
+  pub distinct_capped: bool,
+  /// Present when the column holds at least one numeric value.
+  pub numeric: Option<NumericStats>,
+  /// Present when the column holds at least one string value.
+  pub text: Option<TextStats>,
+  /// Present for sample-based stats only.
+  pub confidence: Option<ColumnConfidence>,
+  /// False when the counts above only cover part of the file (see `StatsResult::complete`).
+  pub complete: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KindCount {
+  pub kind: JsonNodeKind,
+  pub count: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NumericStats {
+  pub count: u64,
+  pub min: f64,
+  pub max: f64,
+  pub mean: f64,
+  /// Equal-width bins over `[min, max]`, built from a uniform sample of `sample_size` values.
+  pub histogram: Vec<HistogramBin>,
+  pub sample_size: u64,
+}
+
+/// Length / token profile of string values (lengths are in chars, not bytes).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TextStats {
+  pub count: u64,
+  pub min_chars: u64,
+  pub max_chars: u64,
+  pub mean_chars: f64,
+  /// Equal-width bins over `[min_chars, max_chars]`, built from a uniform sample of `sample_size` values.
+  pub length_histogram: Vec<HistogramBin>,
+  pub sample_size: u64,
+  /// Whitespace-separated words.
+  pub total_words: u64,
+  /// Approximate BPE tokens: ~4 ASCII chars per token, one token per non-ASCII (e.g. CJK) char.
+  pub total_tokens_estimate: u64,
+  pub mean_tokens_estimate: f64,
+  pub max_tokens_estimate: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistogramBin {
+  pub lower: f64,
+  pub upper: f64,
+  pub count: u64,
+}
+
+/// Stats of two sessions side by side (`left` = before, `right` = after).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatsDiff {
+  pub left_records: u64,
+  pub right_records: u64,
+  /// `right_records - left_records`.
+  pub records_delta: i64,
+  /// Columns only present on the right.
+  pub added_columns: Vec<String>,
+  /// Columns only present on the left.
+  pub removed_columns: Vec<String>,
+  /// Columns present on both sides (left column order).
+  pub columns: Vec<ColumnStatsDiff>,
+}
+
+/// All deltas are `right - left`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ColumnStatsDiff {
+  pub name: String,
+  pub left_kind: JsonNodeKind,
+  pub right_kind: JsonNodeKind,
+  pub present_delta: i64,
+  pub null_delta: i64,
+  /// Change of the null share (`null_count / present_count`).
+  pub null_rate_delta: f64,
+  pub distinct_delta: i64,
+  /// Present when both sides are numeric.
+  pub mean_delta: Option<f64>,
+  /// Total variation distance between the two numeric histograms (0 = identical, 1 = disjoint).
+  pub distribution_shift: Option<f64>,
+}
+
+/// Per-column statistics read from a parquet footer's row group statistics; no rows are read,
+/// so it is instant on any file size.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParquetFooterStats {
+  pub row_count: u64,
+  pub row_groups: u64,
+  /// Leaf columns in file order.
+  pub columns: Vec<ParquetColumnStats>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParquetColumnStats {
+  /// Dotted leaf path, as in `ParquetColumn::path`.
+  pub path: String,
+  /// Smallest / largest value over the row groups, as DuckDB prints it.
+  pub min: Option<String>,
+  pub max: Option<String>,
+  /// `None` when a row group doesn't record its null count.
+  pub null_count: Option<u64>,
+  /// False when some row group has no min / max, so `min` / `max` only cover the others.
+  pub complete: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StatsReportFormat {
+  Json,
+  Markdown,
 }
 
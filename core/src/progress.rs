@@ -0,0 +1,21 @@
+/// Where a long scan is, as reported by the loops behind background tasks.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct ScanProgress {
+  /// 0..=99; a task reports 100 once it finished.
+  pub pct: u8,
+  /// Bytes of the file got through (0 for Parquet, which is read in rows).
+  pub bytes: u64,
+  pub records: u64,
+}
+
+impl ScanProgress {
+  /// After `bytes` / `records`, with the percentage taken as `done` of `total`.
+  pub(crate) fn new(done: u64, total: u64, bytes: u64, records: u64) -> Self {
+    let pct = if total == 0 {
+      0
+    } else {
+      ((done as f64 / total as f64) * 100.0).floor().clamp(0.0, 99.0) as u8
+    };
+    Self { pct, bytes, records }
+  }
+}
@@ -0,0 +1,153 @@
+use std::{
+  fs::{File, OpenOptions},
+  path::{Path, PathBuf},
+};
+
+use reqwest::{
+  blocking::{Client, Response},
+  header::{ACCEPT_RANGES, CONTENT_LENGTH, ETAG, LAST_MODIFIED, RANGE},
+  StatusCode,
+};
+use ring::digest;
+use serde::{Deserialize, Serialize};
+
+use crate::engine::CoreError;
+
+/// `open_file` paths starting with these are fetched over HTTP(S).
+pub(crate) fn is_remote(path: &str) -> bool {
+  let lower = path.to_ascii_lowercase();
+  lower.starts_with("http://") || lower.starts_with("https://")
+}
+
+/// What the server says about the remote file; stored next to the cached copy so a reopen can
+/// tell whether the copy (or a partial one) is still the same file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct RemoteVersion {
+  pub url: String,
+  pub len: Option<u64>,
+  pub etag: Option<String>,
+  pub last_modified: Option<String>,
+  #[serde(default)]
+  pub accepts_ranges: bool,
+}
+
+/// A download of the remote file into its cache copy: the body continues the copy's current
+/// bytes (a range request) or replaces them.
+pub(crate) struct Download {
+  pub body: Response,
+  pub out: File,
+  /// Bytes of the copy kept (the range start).
+  pub resumed_from: u64,
+}
+
+/// A remote file and its cache copy under `cache_dir`.
+pub(crate) struct RemoteFile {
+  client: Client,
+  pub version: RemoteVersion,
+  /// Local copy; its name keeps the URL's extension so the format is detected as for local files.
+  pub path: PathBuf,
+}
+
+impl RemoteFile {
+  pub(crate) fn head(url: &str, cache_dir: &Path) -> Result<Self, CoreError> {
+    let client = Client::builder().build().map_err(http_error)?;
+    let resp = client.head(url).send().map_err(http_error)?;
+    if !resp.status().is_success() {
+      return Err(CoreError::Http(format!("HEAD {url}: {}", resp.status())));
+    }
+    let header = |name| resp.headers().get(name).and_then(|v| v.to_str().ok()).map(str::to_string);
+    let version = RemoteVersion {
+      url: url.to_string(),
+      len: header(CONTENT_LENGTH).and_then(|v| v.parse().ok()),
+      etag: header(ETAG),
+      last_modified: header(LAST_MODIFIED),
+      accepts_ranges: header(ACCEPT_RANGES).is_some_and(|v| v.eq_ignore_ascii_case("bytes")),
+    };
+    Ok(Self {
+      client,
+      version,
+      path: cache_path(cache_dir, url),
+    })
+  }
+
+  /// The cache copy is the whole current remote file.
+  pub(crate) fn is_cached(&self) -> bool {
+    self.cached_len().is_some() && self.cached_len() == self.version.len
+  }
+
+  /// Length of a cache copy of this same remote version (complete or not).
+  fn cached_len(&self) -> Option<u64> {
+    let stored: RemoteVersion = serde_json::from_slice(&std::fs::read(version_path(&self.path)).ok()?).ok()?;
+    let unversioned = self.version.etag.is_none() && self.version.last_modified.is_none();
+    if stored != self.version || unversioned {
+      return None;
+    }
+    std::fs::metadata(&self.path).ok().map(|m| m.len())
+  }
+
+  /// Starts fetching what the cache copy lacks: the rest of a partial copy of the same version
+  /// (`Range: bytes=N-`) when the server supports ranges, otherwise the whole file.
+  pub(crate) fn download(&self) -> Result<Download, CoreError> {
+    let resume = match self.cached_len() {
+      Some(len) if self.version.accepts_ranges && len > 0 => len,
+      _ => 0,
+    };
+    let mut req = self.client.get(&self.version.url);
+    if resume > 0 {
+      req = req.header(RANGE, format!("bytes={resume}-"));
+    }
+    let body = req.send().map_err(http_error)?;
+    let resumed_from = match body.status() {
+      StatusCode::PARTIAL_CONTENT if resume > 0 => resume,
+      s if s.is_success() => 0,
+      s => return Err(CoreError::Http(format!("GET {}: {s}", self.version.url))),
+    };
+    if let Some(parent) = self.path.parent() {
+      std::fs::create_dir_all(parent)?;
+    }
+    let out = if resumed_from > 0 {
+      OpenOptions::new().append(true).open(&self.path)?
+    } else {
+      File::create(&self.path)?
+    };
+    let version = serde_json::to_vec(&self.version).map_err(|e| CoreError::InvalidArg(e.to_string()))?;
+    std::fs::write(version_path(&self.path), version)?;
+    Ok(Download {
+      body,
+      out,
+      resumed_from,
+    })
+  }
+}
+
+/// Cache copy of `url`: named by the URL's SHA-256, which stays the same across builds, plus the
+/// extension of its last path segment cut down to ASCII letters and digits, so no URL names a
+/// file outside `cache_dir` or one the OS can't create.
+fn cache_path(cache_dir: &Path, url: &str) -> PathBuf {
+  let hash: String = digest::digest(&digest::SHA256, url.as_bytes())
+    .as_ref()
+    .iter()
+    .map(|b| format!("{b:02x}"))
+    .collect();
+  let ext = url
+    .split(['?', '#'])
+    .next()
+    .and_then(|u| u.rsplit(['/', '\\']).next())
+    .and_then(|name| name.rsplit_once('.'))
+    .map(|(_, ext)| ext.chars().filter(char::is_ascii_alphanumeric).take(16).collect::<String>())
+    .filter(|ext| !ext.is_empty());
+  match ext {
+    Some(ext) => cache_dir.join(format!("{hash}.{ext}")),
+    None => cache_dir.join(hash),
+  }
+}
+
+fn version_path(path: &Path) -> PathBuf {
+  let mut name = path.as_os_str().to_owned();
+  name.push(".version.json");
+  PathBuf::from(name)
+}
+
+fn http_error(e: reqwest::Error) -> CoreError {
+  CoreError::Http(e.to_string())
+}
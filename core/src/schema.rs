@@ -0,0 +1,212 @@
+use std::{collections::HashMap, path::Path};
+
+use serde_json::Value;
+
+use crate::{
+  cursor::Cursor,
+  engine::CoreError,
+  formats,
+  models::{CsvDialect, CsvSchema, FileFormat, JsonNodeKind, SchemaField, SessionSchema, TextEncoding},
+  stats::{csv_cell_to_value, kind_of},
+};
+
+/// Records read from the file start to infer CSV / JSON types.
+const SCHEMA_SAMPLE_RECORDS: u64 = 1000;
+/// Nested objects deeper than this are reported as a single `object` field.
+const MAX_KEY_DEPTH: usize = 8;
+
+/// Key path used for records that are not JSON objects (matches the stats column name).
+const NON_OBJECT_FIELD: &str = "$";
+
+/// Schema of a session's file, with JSONL / CSV text decoded from `encoding` and CSV rows read
+/// as `dialect` says.
+pub(crate) fn read_schema(
+  path: &Path,
+  format: FileFormat,
+  encoding: TextEncoding,
+  dialect: &CsvDialect,
+) -> Result<SessionSchema, CoreError> {
+  if format == FileFormat::Parquet {
+    let fields = formats::read_parquet_columns(path)?
+      .into_iter()
+      .map(|(name, data_type, nullable)| SchemaField {
+        name,
+        kind: duckdb_type_kind(&data_type),
+        data_type: Some(data_type),
+        nullable,
+      })
+      .collect();
+    return Ok(SessionSchema {
+      fields,
+      sampled_records: 0,
+    });
+  }
+
+  let csv = format == FileFormat::Csv;
+  let mut acc = SchemaAccumulator::default();
+  formats::for_each_record_decoded(path, format, encoding, dialect, Cursor { offset: 0, line: 0 }, |r| {
+    let value = r
+      .raw
+      .as_deref()
+      .and_then(|raw| serde_json::from_str::<Value>(raw).ok());
+    acc.add(value.as_ref(), csv);
+    acc.records < SCHEMA_SAMPLE_RECORDS
+  })?;
+  Ok(acc.finish())
+}
+
+/// Header, delimiter, types and row estimate of a CSV file from its first records (see
+/// `CoreEngine::csv_schema`).
+pub(crate) fn read_csv_schema(path: &Path) -> Result<CsvSchema, CoreError> {
+  let dialect = CsvDialect {
+    delimiter: formats::sniff_csv_delimiter(path)?.to_string(),
+    ..CsvDialect::default()
+  };
+  let header = formats::read_csv_header(path, TextEncoding::Utf8, &dialect)?;
+  let file_len = std::fs::metadata(path)?.len();
+  let mut acc = SchemaAccumulator::default();
+  // Data rows sampled, as (start of the first, end of the last).
+  let mut span: Option<(u64, u64)> = None;
+  formats::for_each_record_with(path, FileFormat::Csv, &dialect, Cursor { offset: 0, line: 0 }, |r| {
+    let value = r
+      .raw
+      .as_deref()
+      .and_then(|raw| serde_json::from_str::<Value>(raw).ok());
+    acc.add(value.as_ref(), true);
+    if let Some(m) = &r.meta {
+      let start = span.map_or(m.byte_offset, |(start, _)| start);
+      span = Some((start, m.byte_offset + m.byte_len));
+    }
+    acc.records < SCHEMA_SAMPLE_RECORDS
+  })?;
+  let sampled = acc.records;
+  let (estimated_records, exact) = match span {
+    Some((start, end)) if end < file_len && end > start => {
+      let per_record = (end - start) as f64 / sampled as f64;
+      (((file_len - start) as f64 / per_record).round() as u64, false)
+    }
+    _ => (sampled, true),
+  };
+  let fields = acc.finish().fields;
+  Ok(CsvSchema {
+    header,
+    delimiter: dialect.delimiter,
+    fields,
+    sampled_records: sampled,
+    estimated_records,
+    exact,
+  })
+}
+
+#[derive(Default)]
+struct SchemaAccumulator {
+  records: u64,
+  /// Field paths in first-seen order.
+  order: Vec<String>,
+  fields: HashMap<String, FieldAcc>,
+}
+
+#[derive(Default)]
+struct FieldAcc {
+  present: u64,
+  nulls: u64,
+  kinds: Vec<(JsonNodeKind, u64)>,
+}
+
+impl SchemaAccumulator {
+  fn add(&mut self, value: Option<&Value>, csv: bool) {
+    self.records += 1;
+    match value {
+      Some(Value::Object(obj)) => self.add_object("", obj, csv, 0),
+      Some(other) => self.add_value(NON_OBJECT_FIELD.to_string(), other, csv),
+      // Unparseable lines show up as a string `$` field.
+      None => self.add_value(NON_OBJECT_FIELD.to_string(), &Value::String(String::new()), false),
+    }
+  }
+
+  fn add_object(&mut self, prefix: &str, obj: &serde_json::Map<String, Value>, csv: bool, depth: usize) {
+    for (k, v) in obj {
+      let name = if prefix.is_empty() {
+        k.clone()
+      } else {
+        format!("{prefix}.{k}")
+      };
+      match v {
+        Value::Object(inner) if !csv && depth + 1 < MAX_KEY_DEPTH && !inner.is_empty() => {
+          self.add_object(&name, inner, csv, depth + 1)
+        }
+        _ => self.add_value(name, v, csv),
+      }
+    }
+  }
+
+  fn add_value(&mut self, name: String, v: &Value, csv: bool) {
+    let typed;
+    let v = match v {
+      Value::String(s) if csv => {
+        typed = csv_cell_to_value(s);
+        &typed
+      }
+      other => other,
+    };
+    let field = match self.fields.get_mut(&name) {
+      Some(f) => f,
+      None => {
+        self.order.push(name.clone());
+        self.fields.entry(name).or_default()
+      }
+    };
+    field.present += 1;
+    let kind = kind_of(v);
+    if kind == JsonNodeKind::Null {
+      field.nulls += 1;
+      return;
+    }
+    match field.kinds.iter_mut().find(|(k, _)| *k == kind) {
+      Some((_, n)) => *n += 1,
+      None => field.kinds.push((kind, 1)),
+    }
+  }
+
+  fn finish(mut self) -> SessionSchema {
+    let records = self.records;
+    let fields = self
+      .order
+      .into_iter()
+      .map(|name| {
+        let f = self.fields.remove(&name).unwrap_or_default();
+        // Ties keep the first-seen kind.
+        let kind = f
+          .kinds
+          .iter()
+          .fold(None::<&(JsonNodeKind, u64)>, |best, cur| match best {
+            Some(b) if b.1 >= cur.1 => Some(b),
+            _ => Some(cur),
+          })
+          .map(|(k, _)| k.clone())
+          .unwrap_or(JsonNodeKind::Null);
+        SchemaField {
+          name,
+          kind,
+          data_type: None,
+          nullable: f.nulls > 0 || f.present < records,
+        }
+      })
+      .collect();
+    SessionSchema {
+      fields,
+      sampled_records: records,
+    }
+  }
+}
+
+/// Kind a DuckDB column type takes in record JSON: `duckdb_value_to_json` keeps ints, floats and
+/// booleans, everything else (decimals, dates, lists, structs ...) is stringified.
+fn duckdb_type_kind(data_type: &str) -> JsonNodeKind {
+  match data_type.trim().to_ascii_uppercase().as_str() {
+    "TINYINT" | "SMALLINT" | "INTEGER" | "BIGINT" | "UTINYINT" | "USMALLINT" | "UINTEGER" | "UBIGINT"
+    | "FLOAT" | "DOUBLE" => JsonNodeKind::Number,
+    "BOOLEAN" => JsonNodeKind::Boolean,
+    _ => JsonNodeKind::String,
+  }
+}
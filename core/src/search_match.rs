@@ -79,5 +79,13 @@ impl PreparedSearch {
     }
     hay.contains(&self.q) || hay.contains(&self.q_quoted)
   }
+
+  /// What `matches_in_hay` looks for: a hay matches if it contains one needle of every group.
+  pub(crate) fn needle_groups(&self) -> Vec<[&str; 2]> {
+    match &self.kv {
+      Some(kv) => vec![[&kv.key, &kv.key_quoted], [&kv.value, &kv.value_quoted]],
+      None => vec![[&self.q, &self.q_quoted]],
+    }
+  }
 }
 
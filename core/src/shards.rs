@@ -0,0 +1,96 @@
+use std::path::{Path, PathBuf};
+
+use crate::{engine::CoreError, formats, models::FileFormat};
+
+/// Parts of a multi-file (sharded) session, e.g. `part-00000.jsonl … part-00099.jsonl`.
+///
+/// Record ids run continuously across parts. Part `i` occupies `spans[i]` ids: its record count,
+/// plus the header row for the first CSV part (the header stays id 0, as in single-file CSV
+/// sessions; later parts' headers are hidden). Spans are learned lazily — by paging past a
+/// part's end or by counting it when an id lookup needs it.
+#[derive(Debug)]
+pub(crate) struct ShardSet {
+  format: FileFormat,
+  parts: Vec<PathBuf>,
+  spans: Vec<Option<u64>>,
+}
+
+impl ShardSet {
+  pub(crate) fn new(format: FileFormat, parts: Vec<PathBuf>) -> Self {
+    let spans = vec![None; parts.len()];
+    Self { format, parts, spans }
+  }
+
+  pub(crate) fn format(&self) -> FileFormat {
+    self.format.clone()
+  }
+
+  pub(crate) fn parts(&self) -> &[PathBuf] {
+    &self.parts
+  }
+
+  pub(crate) fn part(&self, index: usize) -> Option<&Path> {
+    self.parts.get(index).map(|p| p.as_path())
+  }
+
+  pub(crate) fn spans(&self) -> Vec<Option<u64>> {
+    self.spans.clone()
+  }
+
+  pub(crate) fn set_span(&mut self, index: usize, span: u64) {
+    if let Some(s) = self.spans.get_mut(index) {
+      *s = Some(span);
+    }
+  }
+
+  /// Ids occupied by part `index`, counting the part if needed.
+  pub(crate) fn span(&mut self, index: usize) -> Result<u64, CoreError> {
+    if let Some(span) = self.spans[index] {
+      return Ok(span);
+    }
+    let span = count_span(&self.parts[index], self.format.clone(), index, || false)?.unwrap_or(0);
+    self.spans[index] = Some(span);
+    Ok(span)
+  }
+
+  /// Records across all parts (CSV header excluded, like `count_records`).
+  pub(crate) fn total_records(&mut self) -> Result<u64, CoreError> {
+    let mut total = 0u64;
+    for i in 0..self.parts.len() {
+      total += self.span(i)?;
+    }
+    if self.format == FileFormat::Csv {
+      total = total.saturating_sub(1);
+    }
+    Ok(total)
+  }
+
+  /// Map a session-wide id to `(part, local id)`; `None` past the last part.
+  pub(crate) fn locate(&mut self, id: u64) -> Result<Option<(usize, u64)>, CoreError> {
+    let mut base = 0u64;
+    for i in 0..self.parts.len() {
+      let span = self.span(i)?;
+      if id < base + span {
+        return Ok(Some((i, id - base + first_local_id(&self.format, i))));
+      }
+      base += span;
+    }
+    Ok(None)
+  }
+}
+
+/// First local id of part `index` that maps to a session id (1 skips a later CSV part's header).
+pub(crate) fn first_local_id(format: &FileFormat, index: usize) -> u64 {
+  u64::from(*format == FileFormat::Csv && index > 0)
+}
+
+/// Count the ids part `index` occupies (see `ShardSet`). `None` if `should_stop` fired.
+pub(crate) fn count_span(
+  path: &Path,
+  format: FileFormat,
+  index: usize,
+  should_stop: impl Fn() -> bool,
+) -> Result<Option<u64>, CoreError> {
+  let header = u64::from(format == FileFormat::Csv && index == 0);
+  Ok(formats::count_records(path, format, should_stop, |_| {})?.map(|n| n + header))
+}
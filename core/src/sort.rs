@@ -0,0 +1,114 @@
+use std::{cmp::Ordering, path::Path};
+
+use serde_json::Value;
+
+use crate::{
+  cursor::Cursor,
+  engine::CoreError,
+  formats,
+  models::{CsvDialect, FileFormat, RecordMeta, SortSpec},
+  stats::csv_cell_to_value,
+};
+
+/// Longest string prefix kept per record for ordering (bounds memory on wide text columns).
+const MAX_KEY_CHARS: usize = 256;
+
+/// Sort key of one record. Orders numbers < strings < booleans < other JSON < missing / null.
+#[derive(Debug, Clone, PartialEq)]
+enum SortKey {
+  Number(f64),
+  Text(String),
+  Bool(bool),
+  Other(String),
+  Missing,
+}
+
+impl SortKey {
+  fn from_value(v: Option<&Value>, csv_cells: bool) -> Self {
+    match v {
+      None | Some(Value::Null) => SortKey::Missing,
+      Some(Value::String(s)) if csv_cells => match csv_cell_to_value(s) {
+        Value::String(s) => SortKey::Text(s.chars().take(MAX_KEY_CHARS).collect()),
+        other => SortKey::from_value(Some(&other), false),
+      },
+      Some(Value::Number(n)) => n.as_f64().map(SortKey::Number).unwrap_or(SortKey::Missing),
+      Some(Value::String(s)) => SortKey::Text(s.chars().take(MAX_KEY_CHARS).collect()),
+      Some(Value::Bool(b)) => SortKey::Bool(*b),
+      Some(other) => SortKey::Other(other.to_string().chars().take(MAX_KEY_CHARS).collect()),
+    }
+  }
+
+  fn rank(&self) -> u8 {
+    match self {
+      SortKey::Number(_) => 0,
+      SortKey::Text(_) => 1,
+      SortKey::Bool(_) => 2,
+      SortKey::Other(_) => 3,
+      SortKey::Missing => 4,
+    }
+  }
+
+  fn cmp(&self, other: &Self) -> Ordering {
+    match (self, other) {
+      (SortKey::Number(a), SortKey::Number(b)) => a.total_cmp(b),
+      (SortKey::Text(a), SortKey::Text(b)) | (SortKey::Other(a), SortKey::Other(b)) => a.cmp(b),
+      (SortKey::Bool(a), SortKey::Bool(b)) => a.cmp(b),
+      _ => self.rank().cmp(&other.rank()),
+    }
+  }
+}
+
+/// Read every record once and return their metas ordered by `spec` (stable: ties keep file
+/// order). Records missing the key sort last in both directions.
+///
+/// `spec.key` is a CSV header, parquet column or top-level JSON key; `a.b` descends into nested
+/// objects when no top-level key has that exact name.
+pub(crate) fn sorted_record_order(
+  path: &Path,
+  format: FileFormat,
+  csv: &CsvDialect,
+  spec: &SortSpec,
+) -> Result<Vec<RecordMeta>, CoreError> {
+  if spec.key.is_empty() {
+    return Err(CoreError::InvalidArg("sort key is empty".into()));
+  }
+  let csv_cells = format == FileFormat::Csv;
+  let mut keyed: Vec<(SortKey, RecordMeta)> = Vec::new();
+  formats::for_each_record_with(path, format, csv, Cursor { offset: 0, line: 0 }, |r| {
+    let value = r
+      .raw
+      .as_deref()
+      .and_then(|raw| serde_json::from_str::<Value>(raw).ok());
+    let key = SortKey::from_value(value.as_ref().and_then(|v| lookup(v, &spec.key)), csv_cells);
+    // Parquet rows have no byte offsets; the row index is all a view needs.
+    let meta = r.meta.clone().unwrap_or(RecordMeta {
+      line_no: r.id,
+      byte_offset: 0,
+      byte_len: 0,
+      part: None,
+    });
+    keyed.push((key, meta));
+    true
+  })?;
+
+  keyed.sort_by(|(a, _), (b, _)| match (a, b) {
+    (SortKey::Missing, SortKey::Missing) => Ordering::Equal,
+    (SortKey::Missing, _) => Ordering::Greater,
+    (_, SortKey::Missing) => Ordering::Less,
+    _ if spec.descending => b.cmp(a),
+    _ => a.cmp(b),
+  });
+  Ok(keyed.into_iter().map(|(_, meta)| meta).collect())
+}
+
+pub(crate) fn lookup<'a>(v: &'a Value, key: &str) -> Option<&'a Value> {
+  let obj = v.as_object()?;
+  if let Some(found) = obj.get(key) {
+    return Some(found);
+  }
+  let mut cur = v;
+  for seg in key.split('.') {
+    cur = cur.as_object()?.get(seg)?;
+  }
+  Some(cur)
+}
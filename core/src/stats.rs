@@ -0,0 +1,1038 @@
+use std::{
+  cmp::Ordering,
+  collections::{hash_map::DefaultHasher, HashMap, HashSet},
+  hash::{Hash, Hasher},
+  path::Path,
+};
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::{
+  cursor::Cursor,
+  engine::CoreError,
+  formats::{self, ParquetConn},
+  line_index::LineIndex,
+  models::{
+    ColumnConfidence, ColumnStats, CsvDialect, ColumnStatsDiff, FileFormat, HistogramBin, JsonNodeKind, KindCount,
+    NumericStats, ParquetColumnStats, ParquetFooterStats, Record, StatsDiff, StatsReportFormat, StatsResult,
+    StatsSampleInfo, StatsSampleStrategy, TextEncoding, TextStats,
+  },
+  progress::ScanProgress,
+};
+
+/// Distinct values tracked per column before `distinct_capped` kicks in.
+const MAX_DISTINCT_PER_COLUMN: usize = 10_000;
+/// Reservoir size for numeric / text-length histograms (keeps memory flat on huge files).
+const NUMERIC_SAMPLE_SIZE: usize = 10_000;
+const HISTOGRAM_BINS: usize = 10;
+
+/// Column name used for records that are not JSON objects (arrays, scalars).
+const NON_OBJECT_COLUMN: &str = "$";
+
+/// Profile the whole file (single pass), with text decoded from `encoding` and CSV rows read as
+/// `csv` says.
+pub(crate) fn compute_stats(
+  path: &Path,
+  format: FileFormat,
+  encoding: TextEncoding,
+  csv: &CsvDialect,
+) -> Result<StatsResult, CoreError> {
+  compute_stats_until(path, format, encoding, csv, || false, |_| {})
+}
+
+/// Like `compute_stats`, but polls `should_stop` before every record.
+///
+/// When it fires, the profile of the records seen so far is returned with `complete: false`.
+pub(crate) fn compute_stats_until(
+  path: &Path,
+  format: FileFormat,
+  encoding: TextEncoding,
+  csv: &CsvDialect,
+  should_stop: impl Fn() -> bool,
+  mut on_progress: impl FnMut(ScanProgress),
+) -> Result<StatsResult, CoreError> {
+  // Progress is measured in bytes for text formats and in rows for parquet.
+  let total = match format {
+    FileFormat::Parquet => formats::read_parquet_row_count(path)?,
+    _ => std::fs::metadata(path)?.len(),
+  };
+  let mut acc = StatsAccumulator::new(format == FileFormat::Csv);
+  let mut stopped = false;
+  let mut records = 0u64;
+  formats::for_each_record_decoded(path, format, encoding, csv, Cursor { offset: 0, line: 0 }, |r| {
+    if should_stop() {
+      stopped = true;
+      return false;
+    }
+    acc.add_raw(r.raw.as_deref().unwrap_or(""));
+    records += 1;
+    let (done, bytes) = match &r.meta {
+      Some(m) => (m.byte_offset + m.byte_len, m.byte_offset + m.byte_len),
+      None => (r.id + 1, 0),
+    };
+    on_progress(ScanProgress::new(done, total, bytes, records));
+    true
+  })?;
+  Ok(acc.finish(!stopped))
+}
+
+/// Profile an explicit list of records (filtered sessions), given their full raw text.
+pub(crate) fn compute_stats_for_raws(format: FileFormat, raws: impl IntoIterator<Item = String>) -> StatsResult {
+  let mut acc = StatsAccumulator::new(format == FileFormat::Csv);
+  for raw in raws {
+    acc.add_raw(&raw);
+  }
+  acc.finish(true)
+}
+
+/// Hard cap for quick stats so a typo can't turn a "quick" run into a full scan.
+const MAX_SAMPLE_SIZE: u64 = 1_000_000;
+
+/// Profile a bounded sample of records (head / tail / random) instead of the whole file.
+///
+/// Returns in roughly constant time on huge files; every column carries confidence margins.
+/// CSV tail / random samples are located through `csv_index`, the session's record index once
+/// it reached EOF (a CSV record can't be found from a byte offset); without it they fall back to
+/// head.
+pub(crate) fn compute_quick_stats(
+  path: &Path,
+  format: FileFormat,
+  encoding: TextEncoding,
+  csv: &CsvDialect,
+  csv_index: Option<LineIndex>,
+  strategy: StatsSampleStrategy,
+  sample_size: u64,
+) -> Result<StatsResult, CoreError> {
+  if sample_size == 0 {
+    return Err(CoreError::InvalidArg("sample_size must be > 0".into()));
+  }
+  let requested = sample_size.min(MAX_SAMPLE_SIZE);
+  let source = SampleSource { path, encoding, csv };
+  let sample = sample_records(&source, csv_index, format.clone(), strategy, requested)?;
+
+  let mut acc = StatsAccumulator::new(format == FileFormat::Csv);
+  for raw in &sample.raws {
+    acc.add_raw(raw);
+  }
+  let info = StatsSampleInfo {
+    strategy: sample.strategy,
+    requested,
+    sampled: sample.raws.len() as u64,
+    estimated_total_records: sample.estimated_total_records,
+  };
+  Ok(acc.finish_sampled(info))
+}
+
+/// The file being sampled and how its text is read.
+struct SampleSource<'a> {
+  path: &'a Path,
+  encoding: TextEncoding,
+  csv: &'a CsvDialect,
+}
+
+struct RecordSample {
+  raws: Vec<String>,
+  strategy: StatsSampleStrategy,
+  estimated_total_records: Option<u64>,
+}
+
+fn sample_records(
+  source: &SampleSource<'_>,
+  csv_index: Option<LineIndex>,
+  format: FileFormat,
+  strategy: StatsSampleStrategy,
+  n: u64,
+) -> Result<RecordSample, CoreError> {
+  let path = source.path;
+  let csv_index = csv_index.filter(|index| index.total_records().is_some());
+  match (format, strategy, csv_index) {
+    (FileFormat::Parquet, strategy, _) => {
+      let total = formats::read_parquet_row_count(path)?;
+      let raws = match strategy {
+        StatsSampleStrategy::Random => formats::read_parquet_sample_raw(path, n)?,
+        StatsSampleStrategy::Head | StatsSampleStrategy::Tail => {
+          let start = if strategy == StatsSampleStrategy::Tail {
+            total.saturating_sub(n)
+          } else {
+            0
+          };
+          let mut raws = Vec::new();
+          formats::for_each_record_from(path, FileFormat::Parquet, Cursor { offset: 0, line: start }, |r| {
+            raws.push(r.raw.clone().unwrap_or_default());
+            (raws.len() as u64) < n
+          })?;
+          raws
+        }
+      };
+      Ok(RecordSample {
+        raws,
+        strategy,
+        estimated_total_records: Some(total),
+      })
+    }
+    (FileFormat::Csv, StatsSampleStrategy::Tail, Some(mut index)) => {
+      let (first, end) = csv_data_records(source, &index);
+      let start = end - n.min(end - first);
+      let records = match index.offset_of(path, FileFormat::Csv, start)? {
+        Some(offset) => read_records_at(source, FileFormat::Csv, offset, n)?,
+        _ => Vec::new(),
+      };
+      Ok(RecordSample {
+        raws: into_raws(records),
+        strategy: StatsSampleStrategy::Tail,
+        estimated_total_records: Some(end - first),
+      })
+    }
+    (FileFormat::Csv, StatsSampleStrategy::Random, Some(mut index)) => {
+      let (first, end) = csv_data_records(source, &index);
+      let mut records = Vec::new();
+      for i in sample_indices(end - first, n) {
+        if let Some(offset) = index.offset_of(path, FileFormat::Csv, first + i)? {
+          records.extend(read_records_at(source, FileFormat::Csv, offset, 1)?);
+        }
+      }
+      Ok(RecordSample {
+        raws: into_raws(records),
+        strategy: StatsSampleStrategy::Random,
+        estimated_total_records: Some(end - first),
+      })
+    }
+    (FileFormat::Jsonl, StatsSampleStrategy::Tail, _) => {
+      let start = formats::tail_start_offset(path, n)?;
+      let records = read_records_at(source, FileFormat::Jsonl, start, n)?;
+      let est = estimate_total_from_bytes(source, &FileFormat::Jsonl, &records);
+      Ok(RecordSample {
+        raws: into_raws(records),
+        strategy: StatsSampleStrategy::Tail,
+        estimated_total_records: est,
+      })
+    }
+    (FileFormat::Jsonl, StatsSampleStrategy::Random, _) => {
+      let records = sample_lines_random(source, n)?;
+      let est = estimate_total_from_bytes(source, &FileFormat::Jsonl, &records);
+      Ok(RecordSample {
+        raws: into_raws(records),
+        strategy: StatsSampleStrategy::Random,
+        estimated_total_records: est,
+      })
+    }
+    // Head for everything else. `.json` values can't be located without scanning from the
+    // start, so tail/random fall back to head there (and for CSV until its index is built).
+    (format, _, _) => {
+      let mut records = Vec::new();
+      let mut hit_eof = true;
+      let start = Cursor { offset: 0, line: 0 };
+      formats::for_each_record_decoded(path, format.clone(), source.encoding, source.csv, start, |r| {
+        if records.len() as u64 >= n {
+          hit_eof = false;
+          return false;
+        }
+        records.push(r.clone());
+        true
+      })?;
+      let est = if hit_eof {
+        Some(records.len() as u64)
+      } else {
+        estimate_total_from_bytes(source, &format, &records)
+      };
+      Ok(RecordSample {
+        raws: into_raws(records),
+        strategy: StatsSampleStrategy::Head,
+        estimated_total_records: est,
+      })
+    }
+  }
+}
+
+/// Read up to `n` records starting at a known record boundary.
+fn read_records_at(
+  source: &SampleSource<'_>,
+  format: FileFormat,
+  offset: u64,
+  n: u64,
+) -> Result<Vec<Record>, CoreError> {
+  // For CSV, `line: 0` marks the first row (the header, if there is one); any other value is
+  // treated as data.
+  let line = if offset == 0 { 0 } else { 1 };
+  let mut out = Vec::new();
+  let cursor = Cursor { offset, line };
+  formats::for_each_record_decoded(source.path, format, source.encoding, source.csv, cursor, |r| {
+    out.push(r.clone());
+    (out.len() as u64) < n
+  })?;
+  Ok(out)
+}
+
+/// Pick `n` random byte offsets of a JSONL file, snap each to the next line start and read that
+/// record.
+///
+/// Note: like any offset-based line sampler this slightly favors longer records.
+fn sample_lines_random(source: &SampleSource<'_>, n: u64) -> Result<Vec<Record>, CoreError> {
+  let path = source.path;
+  let file_len = std::fs::metadata(path)?.len();
+  if file_len == 0 {
+    return Ok(vec![]);
+  }
+
+  let mut rng = XorShift64(0x2545_F491_4F6C_DD1D);
+  let mut offsets: Vec<u64> = (0..n).map(|_| rng.next() % file_len).collect();
+  offsets.sort_unstable();
+
+  let mut out = Vec::new();
+  let mut last_start: Option<u64> = None;
+  for off in offsets {
+    let start = formats::next_line_start(path, off)?;
+    if start >= file_len || last_start == Some(start) {
+      continue;
+    }
+    last_start = Some(start);
+    out.extend(read_records_at(source, FileFormat::Jsonl, start, 1)?);
+  }
+  Ok(out)
+}
+
+/// Range of CSV data records in `index` (complete), as `(first, end)`: the header row, if any,
+/// is record 0.
+fn csv_data_records(source: &SampleSource<'_>, index: &LineIndex) -> (u64, u64) {
+  let end = index.total_records().unwrap_or(0);
+  (u64::from(source.csv.has_header).min(end), end)
+}
+
+/// `n` distinct indices below `total` picked uniformly at random (Floyd's algorithm), in order;
+/// all of them if `n >= total`.
+fn sample_indices(total: u64, n: u64) -> Vec<u64> {
+  if n >= total {
+    return (0..total).collect();
+  }
+  let mut rng = XorShift64(0x2545_F491_4F6C_DD1D);
+  let mut picked = HashSet::new();
+  for j in total - n..total {
+    let t = rng.next() % (j + 1);
+    if !picked.insert(t) {
+      picked.insert(j);
+    }
+  }
+  let mut picked: Vec<u64> = picked.into_iter().collect();
+  picked.sort_unstable();
+  picked
+}
+
+fn estimate_total_from_bytes(source: &SampleSource<'_>, format: &FileFormat, records: &[Record]) -> Option<u64> {
+  let bytes: u64 = records.iter().filter_map(|r| r.meta.as_ref()).map(|m| m.byte_len).sum();
+  if bytes == 0 {
+    return None;
+  }
+  let file_len = std::fs::metadata(source.path).ok()?.len();
+  let avg = bytes as f64 / records.len() as f64;
+  let est = (file_len as f64 / avg).round() as u64;
+  // The CSV header row is not a record.
+  Some(if *format == FileFormat::Csv && source.csv.has_header {
+    est.saturating_sub(1)
+  } else {
+    est
+  })
+}
+
+fn into_raws(records: Vec<Record>) -> Vec<String> {
+  records.into_iter().map(|r| r.raw.unwrap_or_default()).collect()
+}
+
+pub(crate) struct StatsAccumulator {
+  /// CSV cells are always strings; infer number/boolean/null from their text.
+  csv_cells: bool,
+  records: u64,
+  invalid: u64,
+  /// Columns in order of first appearance.
+  columns: Vec<ColumnAcc>,
+  index: HashMap<String, usize>,
+  rng: XorShift64,
+}
+
+impl StatsAccumulator {
+  pub(crate) fn new(csv_cells: bool) -> Self {
+    Self {
+      csv_cells,
+      records: 0,
+      invalid: 0,
+      columns: Vec::new(),
+      index: HashMap::new(),
+      rng: XorShift64(0x9E37_79B9_7F4A_7C15),
+    }
+  }
+
+  pub(crate) fn add_raw(&mut self, raw: &str) {
+    match serde_json::from_str::<Value>(raw) {
+      Ok(v) => self.add_value(&v),
+      Err(_) => self.invalid += 1,
+    }
+  }
+
+  pub(crate) fn add_value(&mut self, v: &Value) {
+    self.records += 1;
+    match v {
+      Value::Object(map) => {
+        for (k, v) in map {
+          self.add_cell(k, v);
+        }
+      }
+      other => self.add_cell(NON_OBJECT_COLUMN, other),
+    }
+  }
+
+  fn add_cell(&mut self, name: &str, v: &Value) {
+    let idx = match self.index.get(name) {
+      Some(i) => *i,
+      None => {
+        self.columns.push(ColumnAcc::new(name));
+        self.index.insert(name.to_string(), self.columns.len() - 1);
+        self.columns.len() - 1
+      }
+    };
+    if self.csv_cells {
+      if let Value::String(s) = v {
+        let inferred = csv_cell_to_value(s);
+        self.columns[idx].add(&inferred, &mut self.rng);
+        return;
+      }
+    }
+    self.columns[idx].add(v, &mut self.rng);
+  }
+
+  /// `complete` is false when the caller stopped feeding records before the end of the file.
+  pub(crate) fn finish(self, complete: bool) -> StatsResult {
+    StatsResult {
+      records_scanned: self.records,
+      invalid_records: self.invalid,
+      columns: self.columns.into_iter().map(|c| c.finish(None, complete)).collect(),
+      sample: None,
+      complete,
+    }
+  }
+
+  /// Like `finish`, but annotates every column with confidence margins for the sample size.
+  pub(crate) fn finish_sampled(self, info: StatsSampleInfo) -> StatsResult {
+    let n = self.records;
+    StatsResult {
+      records_scanned: self.records,
+      invalid_records: self.invalid,
+      columns: self.columns.into_iter().map(|c| c.finish(Some(n), false)).collect(),
+      sample: Some(info),
+      complete: false,
+    }
+  }
+}
+
+/// z-score for a two-sided 95% interval.
+const Z_95: f64 = 1.96;
+
+fn rate_margin(p: f64, n: u64) -> f64 {
+  if n == 0 {
+    return 1.0;
+  }
+  Z_95 * (p * (1.0 - p) / n as f64).sqrt()
+}
+
+/// Order used for `kind_counts` (and ties in `inferred_kind`).
+const KIND_ORDER: [JsonNodeKind; 7] = [
+  JsonNodeKind::Number,
+  JsonNodeKind::String,
+  JsonNodeKind::Boolean,
+  JsonNodeKind::Object,
+  JsonNodeKind::Array,
+  JsonNodeKind::Null,
+  JsonNodeKind::Unknown,
+];
+
+struct ColumnAcc {
+  name: String,
+  present: u64,
+  kinds: [u64; 7],
+  distinct: HashSet<u64>,
+  distinct_capped: bool,
+  num_count: u64,
+  num_min: f64,
+  num_max: f64,
+  num_sum: f64,
+  num_sum_sq: f64,
+  num_sample: Vec<f64>,
+  text_count: u64,
+  text_min_chars: u64,
+  text_max_chars: u64,
+  text_sum_chars: u64,
+  text_len_sample: Vec<f64>,
+  text_words: u64,
+  text_tokens: u64,
+  text_max_tokens: u64,
+}
+
+impl ColumnAcc {
+  fn new(name: &str) -> Self {
+    Self {
+      name: name.to_string(),
+      present: 0,
+      kinds: [0; 7],
+      distinct: HashSet::new(),
+      distinct_capped: false,
+      num_count: 0,
+      num_min: f64::INFINITY,
+      num_max: f64::NEG_INFINITY,
+      num_sum: 0.0,
+      num_sum_sq: 0.0,
+      num_sample: Vec::new(),
+      text_count: 0,
+      text_min_chars: u64::MAX,
+      text_max_chars: 0,
+      text_sum_chars: 0,
+      text_len_sample: Vec::new(),
+      text_words: 0,
+      text_tokens: 0,
+      text_max_tokens: 0,
+    }
+  }
+
+  fn add(&mut self, v: &Value, rng: &mut XorShift64) {
+    self.present += 1;
+    let kind = kind_of(v);
+    if let Some(i) = KIND_ORDER.iter().position(|k| *k == kind) {
+      self.kinds[i] += 1;
+    }
+    if kind == JsonNodeKind::Null {
+      return;
+    }
+
+    if !self.distinct_capped {
+      // Hash instead of storing values: object/array cells can be huge.
+      let mut h = DefaultHasher::new();
+      match v {
+        Value::String(s) => s.hash(&mut h),
+        other => other.to_string().hash(&mut h),
+      }
+      self.distinct.insert(h.finish());
+      if self.distinct.len() >= MAX_DISTINCT_PER_COLUMN {
+        self.distinct_capped = true;
+      }
+    }
+
+    if let Some(x) = v.as_f64() {
+      self.num_count += 1;
+      self.num_min = self.num_min.min(x);
+      self.num_max = self.num_max.max(x);
+      self.num_sum += x;
+      self.num_sum_sq += x * x;
+      reservoir_push(&mut self.num_sample, self.num_count, x, rng);
+    }
+
+    if let Value::String(text) = v {
+      let chars = text.chars().count() as u64;
+      let (words, tokens) = word_and_token_counts(text);
+      self.text_count += 1;
+      self.text_min_chars = self.text_min_chars.min(chars);
+      self.text_max_chars = self.text_max_chars.max(chars);
+      self.text_sum_chars += chars;
+      self.text_words += words;
+      self.text_tokens += tokens;
+      self.text_max_tokens = self.text_max_tokens.max(tokens);
+      reservoir_push(&mut self.text_len_sample, self.text_count, chars as f64, rng);
+    }
+  }
+
+  /// `sampled_records` is set for sample-based stats and enables confidence margins.
+  fn finish(self, sampled_records: Option<u64>, complete: bool) -> ColumnStats {
+    let mut inferred_kind = JsonNodeKind::Null;
+    let mut best = 0u64;
+    for (i, k) in KIND_ORDER.iter().enumerate() {
+      if *k != JsonNodeKind::Null && self.kinds[i] > best {
+        best = self.kinds[i];
+        inferred_kind = k.clone();
+      }
+    }
+    let kind_counts = KIND_ORDER
+      .iter()
+      .enumerate()
+      .filter(|(i, _)| self.kinds[*i] > 0)
+      .map(|(i, k)| KindCount {
+        kind: k.clone(),
+        count: self.kinds[i],
+      })
+      .collect();
+
+    let numeric = if self.num_count > 0 {
+      Some(NumericStats {
+        count: self.num_count,
+        min: self.num_min,
+        max: self.num_max,
+        mean: self.num_sum / self.num_count as f64,
+        histogram: histogram(&self.num_sample, self.num_min, self.num_max),
+        sample_size: self.num_sample.len() as u64,
+      })
+    } else {
+      None
+    };
+
+    let text = if self.text_count > 0 {
+      Some(TextStats {
+        count: self.text_count,
+        min_chars: self.text_min_chars,
+        max_chars: self.text_max_chars,
+        mean_chars: self.text_sum_chars as f64 / self.text_count as f64,
+        length_histogram: histogram(
+          &self.text_len_sample,
+          self.text_min_chars as f64,
+          self.text_max_chars as f64,
+        ),
+        sample_size: self.text_len_sample.len() as u64,
+        total_words: self.text_words,
+        total_tokens_estimate: self.text_tokens,
+        mean_tokens_estimate: self.text_tokens as f64 / self.text_count as f64,
+        max_tokens_estimate: self.text_max_tokens,
+      })
+    } else {
+      None
+    };
+
+    let null_count = self.kinds[5];
+    let confidence = sampled_records.map(|n| {
+      let rate = |c: u64| if n == 0 { 0.0 } else { c as f64 / n as f64 };
+      let present_rate = rate(self.present);
+      let null_rate = rate(null_count);
+      let mean_margin = if self.num_count > 1 {
+        let k = self.num_count as f64;
+        let mean = self.num_sum / k;
+        let var = ((self.num_sum_sq - k * mean * mean) / (k - 1.0)).max(0.0);
+        Some(Z_95 * (var / k).sqrt())
+      } else {
+        None
+      };
+      ColumnConfidence {
+        present_rate,
+        present_rate_margin: rate_margin(present_rate, n),
+        null_rate,
+        null_rate_margin: rate_margin(null_rate, n),
+        mean_margin,
+      }
+    });
+
+    ColumnStats {
+      name: self.name,
+      inferred_kind,
+      present_count: self.present,
+      null_count,
+      kind_counts,
+      distinct_count: self.distinct.len() as u64,
+      distinct_capped: self.distinct_capped,
+      numeric,
+      text,
+      confidence,
+      complete,
+    }
+  }
+}
+
+/// Reservoir sampling (Algorithm R); `seen` counts values offered so far, including `x`.
+fn reservoir_push(sample: &mut Vec<f64>, seen: u64, x: f64, rng: &mut XorShift64) {
+  if sample.len() < NUMERIC_SAMPLE_SIZE {
+    sample.push(x);
+  } else {
+    let j = (rng.next() % seen) as usize;
+    if j < NUMERIC_SAMPLE_SIZE {
+      sample[j] = x;
+    }
+  }
+}
+
+/// Whitespace word count plus a BPE-ish token estimate (no tokenizer dependency).
+///
+/// ASCII runs cost ~1 token per 4 chars; every non-ASCII char (CJK etc.) costs one token.
+fn word_and_token_counts(s: &str) -> (u64, u64) {
+  let mut words = 0u64;
+  let mut tokens = 0u64;
+  for word in s.split_whitespace() {
+    words += 1;
+    let mut ascii = 0u64;
+    for c in word.chars() {
+      if c.is_ascii() {
+        ascii += 1;
+      } else {
+        tokens += 1;
+      }
+    }
+    tokens += ascii.div_ceil(4);
+  }
+  (words, tokens)
+}
+
+fn histogram(sample: &[f64], min: f64, max: f64) -> Vec<HistogramBin> {
+  if sample.is_empty() {
+    return vec![];
+  }
+  if min >= max {
+    return vec![HistogramBin {
+      lower: min,
+      upper: max,
+      count: sample.len() as u64,
+    }];
+  }
+  let width = (max - min) / HISTOGRAM_BINS as f64;
+  let mut counts = [0u64; HISTOGRAM_BINS];
+  for x in sample {
+    let i = (((x - min) / width) as usize).min(HISTOGRAM_BINS - 1);
+    counts[i] += 1;
+  }
+  counts
+    .iter()
+    .enumerate()
+    .map(|(i, c)| HistogramBin {
+      lower: min + width * i as f64,
+      upper: if i + 1 == HISTOGRAM_BINS {
+        max
+      } else {
+        min + width * (i + 1) as f64
+      },
+      count: *c,
+    })
+    .collect()
+}
+
+pub(crate) fn kind_of(v: &Value) -> JsonNodeKind {
+  match v {
+    Value::Null => JsonNodeKind::Null,
+    Value::Bool(_) => JsonNodeKind::Boolean,
+    Value::Number(_) => JsonNodeKind::Number,
+    Value::String(_) => JsonNodeKind::String,
+    Value::Array(_) => JsonNodeKind::Array,
+    Value::Object(_) => JsonNodeKind::Object,
+  }
+}
+
+/// Best-effort typing of a CSV cell: empty => null, true/false => boolean, numeric => number.
+pub(crate) fn csv_cell_to_value(s: &str) -> Value {
+  let t = s.trim();
+  if t.is_empty() {
+    return Value::Null;
+  }
+  if t.eq_ignore_ascii_case("true") {
+    return Value::Bool(true);
+  }
+  if t.eq_ignore_ascii_case("false") {
+    return Value::Bool(false);
+  }
+  if let Ok(x) = t.parse::<f64>() {
+    if let Some(n) = serde_json::Number::from_f64(x) {
+      return Value::Number(n);
+    }
+  }
+  Value::String(s.to_string())
+}
+
+/// Tiny deterministic PRNG for reservoir sampling (no extra deps, reproducible reports).
+struct XorShift64(u64);
+
+impl XorShift64 {
+  fn next(&mut self) -> u64 {
+    let mut x = self.0;
+    x ^= x << 13;
+    x ^= x >> 7;
+    x ^= x << 17;
+    self.0 = x;
+    x
+  }
+}
+
+// --- Parquet footer ---
+
+/// Per-column min / max / null count of `conn`'s file, merged from its row group statistics.
+pub(crate) fn parquet_footer_stats(conn: &ParquetConn) -> Result<ParquetFooterStats, CoreError> {
+  let mut columns: Vec<ParquetColumnStats> = Vec::new();
+  let mut by_path: HashMap<String, usize> = HashMap::new();
+  for chunk in conn.chunk_stats()? {
+    let i = *by_path.entry(chunk.path.clone()).or_insert_with(|| {
+      columns.push(ParquetColumnStats {
+        path: chunk.path.clone(),
+        min: None,
+        max: None,
+        null_count: Some(0),
+        complete: true,
+      });
+      columns.len() - 1
+    });
+    let col = &mut columns[i];
+    col.null_count = col.null_count.zip(chunk.null_count).map(|(a, b)| a + b);
+    // Byte arrays are text (or binary); other physical types hold numbers, dates and times.
+    let numeric = chunk.physical_type != "BYTE_ARRAY";
+    match (chunk.min, chunk.max) {
+      (Some(min), Some(max)) => {
+        keep_extreme(&mut col.min, min, Ordering::Less, numeric);
+        keep_extreme(&mut col.max, max, Ordering::Greater, numeric);
+      }
+      // An all-null chunk has nothing to bound.
+      _ if chunk.null_count == Some(chunk.row_count) => {}
+      _ => col.complete = false,
+    }
+  }
+  Ok(ParquetFooterStats {
+    row_count: conn.row_count()?,
+    row_groups: conn.row_group_starts()?.len() as u64,
+    columns,
+  })
+}
+
+/// Replaces `current` with `candidate` if it compares as `wanted` (smaller / larger).
+fn keep_extreme(current: &mut Option<String>, candidate: String, wanted: Ordering, numeric: bool) {
+  let replace = match current {
+    None => true,
+    Some(current) => compare_stat(&candidate, current, numeric) == wanted,
+  };
+  if replace {
+    *current = Some(candidate);
+  }
+}
+
+/// Footer values are text: numbers compare by value, everything else (strings, ISO dates and
+/// timestamps) as text.
+fn compare_stat(a: &str, b: &str, numeric: bool) -> Ordering {
+  if numeric {
+    if let (Ok(x), Ok(y)) = (a.parse::<i128>(), b.parse::<i128>()) {
+      return x.cmp(&y);
+    }
+    if let (Ok(x), Ok(y)) = (a.parse::<f64>(), b.parse::<f64>()) {
+      if let Some(order) = x.partial_cmp(&y) {
+        return order;
+      }
+    }
+  }
+  a.cmp(b)
+}
+
+// --- Diff ---
+
+/// Compare two profiles column by column (matched by name).
+pub(crate) fn diff_stats(left: &StatsResult, right: &StatsResult) -> StatsDiff {
+  let right_by_name: HashMap<&str, &ColumnStats> =
+    right.columns.iter().map(|c| (c.name.as_str(), c)).collect();
+  let left_names: HashSet<&str> = left.columns.iter().map(|c| c.name.as_str()).collect();
+
+  let mut removed_columns = Vec::new();
+  let mut columns = Vec::new();
+  for l in &left.columns {
+    match right_by_name.get(l.name.as_str()) {
+      Some(r) => columns.push(diff_column(l, r)),
+      None => removed_columns.push(l.name.clone()),
+    }
+  }
+  let added_columns = right
+    .columns
+    .iter()
+    .filter(|c| !left_names.contains(c.name.as_str()))
+    .map(|c| c.name.clone())
+    .collect();
+
+  StatsDiff {
+    left_records: left.records_scanned,
+    right_records: right.records_scanned,
+    records_delta: delta(left.records_scanned, right.records_scanned),
+    added_columns,
+    removed_columns,
+    columns,
+  }
+}
+
+fn diff_column(l: &ColumnStats, r: &ColumnStats) -> ColumnStatsDiff {
+  let null_rate = |c: &ColumnStats| {
+    if c.present_count == 0 {
+      0.0
+    } else {
+      c.null_count as f64 / c.present_count as f64
+    }
+  };
+  let (mean_delta, distribution_shift) = match (&l.numeric, &r.numeric) {
+    (Some(ln), Some(rn)) => (
+      Some(rn.mean - ln.mean),
+      Some(histogram_distance(&ln.histogram, &rn.histogram)),
+    ),
+    _ => (None, None),
+  };
+  ColumnStatsDiff {
+    name: l.name.clone(),
+    left_kind: l.inferred_kind.clone(),
+    right_kind: r.inferred_kind.clone(),
+    present_delta: delta(l.present_count, r.present_count),
+    null_delta: delta(l.null_count, r.null_count),
+    null_rate_delta: null_rate(r) - null_rate(l),
+    distinct_delta: delta(l.distinct_count, r.distinct_count),
+    mean_delta,
+    distribution_shift,
+  }
+}
+
+fn delta(left: u64, right: u64) -> i64 {
+  right as i64 - left as i64
+}
+
+/// Total variation distance between two histograms with (possibly) different bin edges.
+///
+/// Both are re-binned onto a shared equal-width grid, assuming values are spread uniformly
+/// inside each source bin.
+fn histogram_distance(a: &[HistogramBin], b: &[HistogramBin]) -> f64 {
+  let (Some(a_total), Some(b_total)) = (histogram_total(a), histogram_total(b)) else {
+    return 0.0;
+  };
+  let lo = a.iter().chain(b).map(|x| x.lower).fold(f64::INFINITY, f64::min);
+  let hi = a.iter().chain(b).map(|x| x.upper).fold(f64::NEG_INFINITY, f64::max);
+  let pa = rebin(a, a_total, lo, hi);
+  let pb = rebin(b, b_total, lo, hi);
+  0.5 * pa.iter().zip(&pb).map(|(x, y)| (x - y).abs()).sum::<f64>()
+}
+
+fn histogram_total(h: &[HistogramBin]) -> Option<f64> {
+  let total: u64 = h.iter().map(|b| b.count).sum();
+  (total > 0).then_some(total as f64)
+}
+
+/// Spread `h` onto `HISTOGRAM_BINS` equal-width bins over `[lo, hi]`, normalized to sum 1.
+fn rebin(h: &[HistogramBin], total: f64, lo: f64, hi: f64) -> [f64; HISTOGRAM_BINS] {
+  let mut out = [0.0; HISTOGRAM_BINS];
+  if hi <= lo {
+    out[0] = 1.0;
+    return out;
+  }
+  let width = (hi - lo) / HISTOGRAM_BINS as f64;
+  let slot = |x: f64| (((x - lo) / width) as usize).min(HISTOGRAM_BINS - 1);
+  for bin in h {
+    let share = bin.count as f64 / total;
+    if bin.upper <= bin.lower {
+      out[slot(bin.lower)] += share;
+      continue;
+    }
+    for (i, o) in out.iter_mut().enumerate() {
+      let s_lo = lo + width * i as f64;
+      let s_hi = s_lo + width;
+      let overlap = bin.upper.min(s_hi) - bin.lower.max(s_lo);
+      if overlap > 0.0 {
+        *o += share * overlap / (bin.upper - bin.lower);
+      }
+    }
+  }
+  out
+}
+
+// --- Reports ---
+
+#[derive(Serialize)]
+struct StatsReport<'a> {
+  path: &'a str,
+  generated_at_ms: i64,
+  stats: &'a StatsResult,
+}
+
+/// Render a stats report (JSON or Markdown) for attaching to dataset release notes.
+pub(crate) fn render_report(
+  source_path: &str,
+  stats: &StatsResult,
+  format: StatsReportFormat,
+  generated_at_ms: i64,
+) -> Result<String, CoreError> {
+  match format {
+    StatsReportFormat::Json => serde_json::to_string_pretty(&StatsReport {
+      path: source_path,
+      generated_at_ms,
+      stats,
+    })
+    .map_err(|e| CoreError::InvalidArg(format!("stats report serialize failed: {e}"))),
+    StatsReportFormat::Markdown => Ok(render_markdown(source_path, stats)),
+  }
+}
+
+fn render_markdown(source_path: &str, stats: &StatsResult) -> String {
+  let mut out = String::new();
+  out.push_str("# Stats report\n\n");
+  out.push_str(&format!("- File: `{source_path}`\n"));
+  out.push_str(&format!("- Records scanned: {}\n", stats.records_scanned));
+  if stats.sample.is_none() && !stats.complete {
+    out.push_str("- Partial result: the scan stopped before the end of the file\n");
+  }
+  out.push_str(&format!("- Invalid records: {}\n\n", stats.invalid_records));
+
+  out.push_str("## Schema\n\n");
+  out.push_str("| Column | Kind | Present | Nulls | Distinct |\n");
+  out.push_str("|---|---|---|---|---|\n");
+  for c in &stats.columns {
+    let distinct = if c.distinct_capped {
+      format!("≥{}", c.distinct_count)
+    } else {
+      c.distinct_count.to_string()
+    };
+    out.push_str(&format!(
+      "| {} | {} | {} | {} | {} |\n",
+      md_cell(&c.name),
+      kind_name(&c.inferred_kind),
+      c.present_count,
+      c.null_count,
+      distinct
+    ));
+  }
+
+  let numeric: Vec<_> = stats
+    .columns
+    .iter()
+    .filter_map(|c| c.numeric.as_ref().map(|n| (c, n)))
+    .collect();
+  if !numeric.is_empty() {
+    out.push_str("\n## Numeric columns\n");
+    for (c, n) in numeric {
+      out.push_str(&format!("\n### {}\n\n", md_cell(&c.name)));
+      out.push_str(&format!(
+        "- Count: {}\n- Min: {}\n- Max: {}\n- Mean: {:.6}\n",
+        n.count, n.min, n.max, n.mean
+      ));
+      if n.sample_size < n.count {
+        out.push_str(&format!("- Histogram sample: {} values\n", n.sample_size));
+      }
+      out.push_str("\n| Bin | Count |\n|---|---|\n");
+      for b in &n.histogram {
+        out.push_str(&format!("| [{}, {}] | {} |\n", b.lower, b.upper, b.count));
+      }
+    }
+  }
+
+  let text: Vec<_> = stats
+    .columns
+    .iter()
+    .filter_map(|c| c.text.as_ref().map(|t| (c, t)))
+    .collect();
+  if !text.is_empty() {
+    out.push_str("\n## Text columns\n\n");
+    out.push_str("| Column | Values | Min chars | Max chars | Mean chars | Words | ~Tokens | Mean ~tokens |\n");
+    out.push_str("|---|---|---|---|---|---|---|---|\n");
+    for (c, t) in text {
+      out.push_str(&format!(
+        "| {} | {} | {} | {} | {:.1} | {} | {} | {:.1} |\n",
+        md_cell(&c.name),
+        t.count,
+        t.min_chars,
+        t.max_chars,
+        t.mean_chars,
+        t.total_words,
+        t.total_tokens_estimate,
+        t.mean_tokens_estimate
+      ));
+    }
+  }
+  out
+}
+
+fn md_cell(s: &str) -> String {
+  s.replace('|', "\\|").replace('\n', " ")
+}
+
+fn kind_name(k: &JsonNodeKind) -> &'static str {
+  match k {
+    JsonNodeKind::Object => "object",
+    JsonNodeKind::Array => "array",
+    JsonNodeKind::String => "string",
+    JsonNodeKind::Number => "number",
+    JsonNodeKind::Boolean => "boolean",
+    JsonNodeKind::Null => "null",
+    JsonNodeKind::Unknown => "unknown",
+  }
+}
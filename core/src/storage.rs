@@ -1,29 +1,178 @@
 use std::{
+  collections::BTreeMap,
   fs,
   path::{Path, PathBuf},
-  time::{SystemTime, UNIX_EPOCH},
+  sync::Arc,
+  time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
-use rusqlite::{params, Connection};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use parking_lot::{Mutex, MutexGuard};
+use rusqlite::{params, params_from_iter, types::ValueRef, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 
-#[derive(Debug, Clone)]
+use crate::crypt::{self, FieldCipher};
+use crate::json_index::JsonNodeIndex;
+use crate::models::{
+  DatasetCollection, ExportPreset, InterruptedExport, ReadPosition, TaskEvent, TaskHistoryEntry, TaskKind, TextEncoding, ViewPrefs,
+};
+use crate::text_index::TextIndex;
+
+/// How long a write waits for another process holding the database lock.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Task history rows kept; older ones are dropped as new tasks finish.
+const TASK_HISTORY_MAX: i64 = 1000;
+
+/// Task event log rows kept (a handful per task); the oldest are dropped first.
+const TASK_EVENTS_MAX: i64 = 20_000;
+
+/// Cached scan_all results kept; the least recently saved are dropped first.
+const SCAN_CACHE_MAX: i64 = 200;
+
+/// Default cap on the total size of cached scan_all hits (`StorageLimits`).
+const SCAN_CACHE_MAX_BYTES: u64 = 64 * 1024 * 1024;
+
+/// `format` of a backup file, so restoring some other JSON file fails early.
+const BACKUP_FORMAT: &str = "fluxpeek-storage-backup";
+
+/// Version of the backup file layout.
+const BACKUP_VERSION: u64 = 1;
+
+/// What a backup holds: the tables the user built up, with the columns carried over. Caches
+/// (line indexes, scan results) and the task history / event log are left out.
+const BACKUP_TABLES: &[(&str, &[&str])] = &[
+  ("settings", &["key", "value_json"]),
+  ("recent_files", &["path", "display_name", "last_opened_at", "exists_flag", "pinned"]),
+  ("recent_folders", &["path", "display_name", "last_scanned_at", "exists_flag", "pinned"]),
+  (
+    "record_labels",
+    &[
+      "path",
+      "file_size",
+      "file_mtime_ms",
+      "record_offset",
+      "line_no",
+      "byte_len",
+      "tags_json",
+      "note",
+      "updated_at",
+    ],
+  ),
+  ("view_prefs", &["path", "prefs_json", "updated_at"]),
+  ("collections", &["name", "paths_json", "created_at", "updated_at"]),
+  ("export_presets", &["name", "preset_json", "updated_at"]),
+];
+
+/// Environment variable naming the SQLite file to use (like `--storage <path>`).
+pub const STORAGE_PATH_ENV: &str = "FLUXPEEK_STORAGE";
+
+/// Environment variable that turns on portable mode when set to `1` / `true` (like `--portable`).
+pub const PORTABLE_ENV: &str = "FLUXPEEK_PORTABLE";
+
+/// File next to the executable that turns on portable mode, for copies run from a USB stick etc.
+pub const PORTABLE_MARKER: &str = "portable";
+
+/// Environment variable holding the storage passphrase (`StorageOptions::passphrase`).
+pub const PASSPHRASE_ENV: &str = "FLUXPEEK_STORAGE_KEY";
+
+#[derive(Debug, Clone, Default)]
 pub struct StorageOptions {
   /// Path to SQLite file. If None, defaults to ~/.datasets-helper/storage.sqlite (or %USERPROFILE% on Windows).
   pub sqlite_path: Option<PathBuf>,
+  /// Encrypts file paths, record contents (cached hits, labels) and the other sensitive fields
+  /// (`SEALED_COLUMNS`) with a key derived from this passphrase. Set once, the database can't
+  /// be opened without it; the first open with a passphrase encrypts what is already stored.
+  pub passphrase: Option<String>,
 }
 
-impl Default for StorageOptions {
-  fn default() -> Self {
-    Self { sqlite_path: None }
+impl StorageOptions {
+  /// Where the app keeps its storage, from its command line (`args`, program name excluded)
+  /// and environment, first match wins:
+  ///
+  /// 1. `--storage <path>` / `--storage=<path>`, then `FLUXPEEK_STORAGE`: that SQLite file.
+  /// 2. Portable mode (`--portable`, `FLUXPEEK_PORTABLE=1` or a `portable` file next to the
+  ///    executable): `data/storage.sqlite` next to the executable.
+  /// 3. The default under the home folder.
+  ///
+  /// The passphrase is the first line of the file named by `--storage-key-file <path>` /
+  /// `--storage-key-file=<path>`, else `FLUXPEEK_STORAGE_KEY`; never an argument itself, to keep
+  /// it out of process lists. The desktop app has no passphrase prompt: these are its only
+  /// ways to open encrypted storage.
+  pub fn from_args_and_env(args: &[std::ffi::OsString]) -> Self {
+    let mut flag_path = None;
+    let mut key_file = None;
+    let mut portable = false;
+    let mut it = args.iter();
+    while let Some(arg) = it.next() {
+      let Some(arg) = arg.to_str() else { continue };
+      if arg == "--portable" {
+        portable = true;
+      } else if arg == "--storage" {
+        flag_path = it.next().map(PathBuf::from);
+      } else if let Some(path) = arg.strip_prefix("--storage=") {
+        flag_path = Some(PathBuf::from(path));
+      } else if arg == "--storage-key-file" {
+        key_file = it.next().map(PathBuf::from);
+      } else if let Some(path) = arg.strip_prefix("--storage-key-file=") {
+        key_file = Some(PathBuf::from(path));
+      }
+    }
+    let sqlite_path = flag_path
+      .or_else(|| std::env::var_os(STORAGE_PATH_ENV).filter(|p| !p.is_empty()).map(PathBuf::from))
+      .or_else(|| {
+        let exe_dir = std::env::current_exe().ok()?.parent()?.to_path_buf();
+        let portable = portable
+          || std::env::var(PORTABLE_ENV).is_ok_and(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+          || exe_dir.join(PORTABLE_MARKER).is_file();
+        portable.then(|| exe_dir.join("data").join("storage.sqlite"))
+      });
+    let passphrase = key_file
+      .and_then(|path| std::fs::read_to_string(path).ok())
+      .and_then(|text| text.lines().next().map(str::to_string))
+      .or_else(|| std::env::var(PASSPHRASE_ENV).ok())
+      .filter(|p| !p.is_empty());
+    Self { sqlite_path, passphrase }
   }
 }
 
-#[derive(Clone)]
+/// Clones share one connection: calls from different threads take turns on it rather than each
+/// opening the file.
+#[derive(Debug, Clone)]
 pub struct Storage {
   path: PathBuf,
+  conn: Arc<Mutex<Connection>>,
+  /// Set for encrypted databases.
+  cipher: Option<FieldCipher>,
+}
+
+/// A complete sparse line index as persisted in SQLite (see `line_index.rs`).
+#[derive(Debug, Clone)]
+pub(crate) struct StoredLineIndex {
+  pub stride: u64,
+  pub total_records: u64,
+  pub end_offset: u64,
+  pub checkpoints: Vec<u64>,
+  /// CSV quoting the records were split by (see `line_index::csv_quoting_key`).
+  pub csv_quoting: String,
 }
 
+/// Tags / note attached to one record, as persisted in SQLite.
 #[derive(Debug, Clone)]
+pub(crate) struct StoredRecordLabel {
+  /// Byte offset of the record (Parquet: row index).
+  pub record_offset: u64,
+  pub line_no: u64,
+  pub byte_len: u64,
+  pub tags: Vec<String>,
+  pub note: Option<String>,
+  pub updated_at_ms: i64,
+}
+
+/// A file on the "Recent" list (see `CoreEngine::list_recent`), or a folder on the recent
+/// folders list (`CoreEngine::list_recent_folders`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RecentFile {
   pub path: String,
   pub display_name: String,
@@ -32,6 +181,62 @@ pub struct RecentFile {
   pub pinned: bool,
 }
 
+/// Caps applied by `Storage::maintain`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StorageLimits {
+  /// Task history entries kept, newest first.
+  pub max_task_history: u64,
+  /// Task event log rows kept, newest first.
+  pub max_task_events: u64,
+  /// Total size of cached scan_all hits; the least recently saved results are dropped first.
+  pub max_scan_cache_bytes: u64,
+}
+
+impl Default for StorageLimits {
+  fn default() -> Self {
+    Self {
+      max_task_history: TASK_HISTORY_MAX as u64,
+      max_task_events: TASK_EVENTS_MAX as u64,
+      max_scan_cache_bytes: SCAN_CACHE_MAX_BYTES,
+    }
+  }
+}
+
+/// Size of the storage database and of the tables that grow with use.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct StorageReport {
+  pub path: String,
+  /// Bytes in use by the database file, free pages included.
+  pub size_bytes: u64,
+  /// Bytes in pages that `maintain` would give back.
+  pub free_bytes: u64,
+  pub task_history_rows: u64,
+  pub task_events_rows: u64,
+  pub scan_cache_rows: u64,
+  pub scan_cache_bytes: u64,
+  /// Sensitive fields are encrypted (`StorageOptions::passphrase`).
+  pub encrypted: bool,
+}
+
+/// Result of `Storage::maintain`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StorageMaintenance {
+  pub before: StorageReport,
+  pub after: StorageReport,
+  /// History, event and cache rows dropped to fit the limits.
+  pub rows_removed: u64,
+}
+
+/// Result of `Storage::backup_to` / `Storage::restore_from`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct StorageBackup {
+  /// The backup file.
+  pub path: String,
+  /// Rows written / restored per table.
+  pub rows: BTreeMap<String, u64>,
+}
+
 impl Storage {
   pub fn new(opts: StorageOptions) -> Result<Self, String> {
     let path = opts
@@ -43,18 +248,107 @@ impl Storage {
       fs::create_dir_all(parent).map_err(|e| e.to_string())?;
     }
 
-    let conn = Connection::open(&path).map_err(|e| e.to_string())?;
-    migrate(&conn).map_err(|e| e.to_string())?;
-    Ok(Self { path })
+    let mut conn = Connection::open(&path).map_err(|e| e.to_string())?;
+    // WAL lets another app instance read while this one writes; the busy timeout covers the
+    // writes they still take turns on.
+    conn
+      .pragma_update_and_check(None, "journal_mode", "WAL", |row| row.get::<_, String>(0))
+      .map_err(|e| e.to_string())?;
+    conn
+      .pragma_update(None, "synchronous", "NORMAL")
+      .map_err(|e| e.to_string())?;
+    conn.busy_timeout(BUSY_TIMEOUT).map_err(|e| e.to_string())?;
+    migrate(&mut conn)?;
+    let cipher = setup_encryption(&mut conn, opts.passphrase.as_deref())?;
+    Ok(Self {
+      path,
+      conn: Arc::new(Mutex::new(conn)),
+      cipher,
+    })
+  }
+
+  /// Whether sensitive fields are stored encrypted (see `StorageOptions::passphrase`).
+  pub fn is_encrypted(&self) -> bool {
+    self.cipher.is_some()
+  }
+
+  /// The SQLite file in use.
+  pub fn path(&self) -> &Path {
+    &self.path
+  }
+
+  /// Version of the database schema: the number of `MIGRATIONS` applied to it.
+  pub fn schema_version(&self) -> Result<u32, String> {
+    let conn = self.conn();
+    current_schema_version(&conn).map_err(|e| e.to_string())
+  }
+
+  fn conn(&self) -> MutexGuard<'_, Connection> {
+    self.conn.lock()
+  }
+
+  /// `value` as stored in a sealed column: encrypted if the storage is.
+  fn seal(&self, value: &str) -> Result<String, String> {
+    match &self.cipher {
+      Some(cipher) => cipher.seal(value),
+      None => Ok(value.to_string()),
+    }
+  }
+
+  /// Like `seal`, for columns that are looked up by value (paths, cache keys).
+  fn seal_key(&self, value: &str) -> Result<String, String> {
+    match &self.cipher {
+      Some(cipher) => cipher.seal_key(value),
+      None => Ok(value.to_string()),
+    }
+  }
+
+  fn seal_opt(&self, value: Option<&str>) -> Result<Option<String>, String> {
+    value.map(|v| self.seal(v)).transpose()
+  }
+
+  /// A value read from a sealed column; for use inside row mappers.
+  fn unseal(&self, stored: String) -> rusqlite::Result<String> {
+    let plain = match &self.cipher {
+      Some(cipher) => cipher.open(&stored),
+      None if crypt::is_sealed(&stored) => Err("storage is encrypted; a passphrase is needed".to_string()),
+      None => Ok(stored),
+    };
+    plain.map_err(|e| rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Text, e.into()))
   }
 
-  fn open(&self) -> Result<Connection, String> {
-    Connection::open(&self.path).map_err(|e| e.to_string())
+  fn unseal_opt(&self, stored: Option<String>) -> rusqlite::Result<Option<String>> {
+    stored.map(|s| self.unseal(s)).transpose()
+  }
+
+  /// A `task_history` row; `None` for kinds this build no longer knows, which are left out
+  /// rather than failing the whole list.
+  fn history_row(&self, row: &rusqlite::Row<'_>) -> rusqlite::Result<Option<TaskHistoryEntry>> {
+    let kind: String = row.get(2)?;
+    let Ok(kind) = serde_json::from_str(&kind) else {
+      return Ok(None);
+    };
+    let paths_json = self.unseal(row.get(3)?)?;
+    let params_json = self.unseal(row.get(4)?)?;
+    let error_json = self.unseal_opt(row.get(10)?)?;
+    Ok(Some(TaskHistoryEntry {
+      id: row.get(0)?,
+      task_id: row.get(1)?,
+      kind,
+      paths: serde_json::from_str(&paths_json).unwrap_or_default(),
+      params: serde_json::from_str(&params_json).unwrap_or_default(),
+      started_at_ms: row.get(5)?,
+      finished_at_ms: row.get(6)?,
+      duration_ms: row.get::<_, i64>(7)? as u64,
+      cancelled: row.get::<_, i64>(8)? != 0,
+      summary: self.unseal_opt(row.get(9)?)?,
+      error: error_json.and_then(|e| serde_json::from_str(&e).ok()),
+    }))
   }
 
   /// Add/update a recent file entry.
   pub fn touch_recent(&self, path: &str, pinned: Option<bool>) -> Result<(), String> {
-    let conn = self.open()?;
+    let conn = self.conn();
     let now = now_ms();
     let display_name = Path::new(path)
       .file_name()
@@ -62,6 +356,7 @@ impl Storage {
       .unwrap_or(path)
       .to_string();
     let exists = Path::new(path).exists();
+    let (path, display_name) = (self.seal_key(path)?, self.seal(&display_name)?);
 
     conn
       .execute(
@@ -81,7 +376,7 @@ ON CONFLICT(path) DO UPDATE SET
   }
 
   pub fn list_recent(&self, limit: usize) -> Result<Vec<RecentFile>, String> {
-    let conn = self.open()?;
+    let conn = self.conn();
     let mut stmt = conn
       .prepare(
         r#"
@@ -96,8 +391,8 @@ LIMIT ?1
     let rows = stmt
       .query_map(params![limit as i64], |row| {
         Ok(RecentFile {
-          path: row.get(0)?,
-          display_name: row.get(1)?,
+          path: self.unseal(row.get(0)?)?,
+          display_name: self.unseal(row.get(1)?)?,
           last_opened_at_ms: row.get(2)?,
           exists: row.get::<_, i64>(3)? != 0,
           pinned: row.get::<_, i64>(4)? != 0,
@@ -112,8 +407,539 @@ LIMIT ?1
     Ok(out)
   }
 
+  /// Pin / unpin a recent entry without touching its last-opened time. Returns `false` if the
+  /// path is not on the list.
+  pub fn set_recent_pinned(&self, path: &str, pinned: bool) -> Result<bool, String> {
+    let conn = self.conn();
+    let n = conn
+      .execute(
+        "UPDATE recent_files SET pinned=?2 WHERE path=?1",
+        params![self.seal_key(path)?, pinned as i32],
+      )
+      .map_err(|e| e.to_string())?;
+    Ok(n > 0)
+  }
+
+  pub fn remove_recent(&self, path: &str) -> Result<(), String> {
+    let conn = self.conn();
+    conn
+      .execute("DELETE FROM recent_files WHERE path=?1", params![self.seal_key(path)?])
+      .map_err(|e| e.to_string())?;
+    Ok(())
+  }
+
+  pub fn clear_recent(&self) -> Result<(), String> {
+    let conn = self.conn();
+    conn.execute("DELETE FROM recent_files", []).map_err(|e| e.to_string())?;
+    Ok(())
+  }
+
+  /// Re-checks `exists` for every recent entry and drops the unpinned ones whose file is gone
+  /// (pinned ones stay, flagged as missing). Returns how many were dropped.
+  pub fn prune_recent(&self) -> Result<usize, String> {
+    let mut conn = self.conn();
+    let paths: Vec<String> = {
+      let mut stmt = conn.prepare("SELECT path FROM recent_files").map_err(|e| e.to_string())?;
+      let rows = stmt.query_map([], |row| row.get(0)).map_err(|e| e.to_string())?;
+      rows.collect::<Result<_, _>>().map_err(|e| e.to_string())?
+    };
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    for stored in paths {
+      let path = self.unseal(stored.clone()).map_err(|e| e.to_string())?;
+      tx.execute(
+        "UPDATE recent_files SET exists_flag=?2 WHERE path=?1",
+        params![stored, Path::new(&path).exists() as i32],
+      )
+      .map_err(|e| e.to_string())?;
+    }
+    let removed = tx
+      .execute("DELETE FROM recent_files WHERE exists_flag=0 AND pinned=0", [])
+      .map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(removed)
+  }
+
+  /// Add/update a recent folder entry (a folder scanned for the file picker).
+  pub fn touch_recent_folder(&self, path: &str, pinned: Option<bool>) -> Result<(), String> {
+    let conn = self.conn();
+    let display_name = Path::new(path)
+      .file_name()
+      .and_then(|s| s.to_str())
+      .unwrap_or(path)
+      .to_string();
+    conn
+      .execute(
+        r#"
+INSERT INTO recent_folders(path, display_name, last_scanned_at, exists_flag, pinned)
+VALUES(?1, ?2, ?3, ?4, COALESCE(?5, 0))
+ON CONFLICT(path) DO UPDATE SET
+  display_name=excluded.display_name,
+  last_scanned_at=excluded.last_scanned_at,
+  exists_flag=excluded.exists_flag,
+  pinned=COALESCE(?5, pinned)
+        "#,
+        params![
+          self.seal_key(path)?,
+          self.seal(&display_name)?,
+          now_ms(),
+          Path::new(path).is_dir() as i32,
+          pinned.map(|b| b as i32)
+        ],
+      )
+      .map_err(|e| e.to_string())?;
+    Ok(())
+  }
+
+  /// Pinned first, then most recently scanned first; `last_opened_at_ms` is the last scan.
+  pub fn list_recent_folders(&self, limit: usize) -> Result<Vec<RecentFile>, String> {
+    let conn = self.conn();
+    let mut stmt = conn
+      .prepare(
+        r#"
+SELECT path, display_name, last_scanned_at, exists_flag, pinned
+FROM recent_folders
+ORDER BY pinned DESC, last_scanned_at DESC
+LIMIT ?1
+        "#,
+      )
+      .map_err(|e| e.to_string())?;
+
+    let rows = stmt
+      .query_map(params![limit as i64], |row| {
+        Ok(RecentFile {
+          path: self.unseal(row.get(0)?)?,
+          display_name: self.unseal(row.get(1)?)?,
+          last_opened_at_ms: row.get(2)?,
+          exists: row.get::<_, i64>(3)? != 0,
+          pinned: row.get::<_, i64>(4)? != 0,
+        })
+      })
+      .map_err(|e| e.to_string())?;
+
+    let mut out = Vec::new();
+    for r in rows {
+      out.push(r.map_err(|e| e.to_string())?);
+    }
+    Ok(out)
+  }
+
+  /// Like `set_recent_pinned`, for folders.
+  pub fn set_recent_folder_pinned(&self, path: &str, pinned: bool) -> Result<bool, String> {
+    let conn = self.conn();
+    let n = conn
+      .execute(
+        "UPDATE recent_folders SET pinned=?2 WHERE path=?1",
+        params![self.seal_key(path)?, pinned as i32],
+      )
+      .map_err(|e| e.to_string())?;
+    Ok(n > 0)
+  }
+
+  pub fn remove_recent_folder(&self, path: &str) -> Result<(), String> {
+    let conn = self.conn();
+    conn
+      .execute("DELETE FROM recent_folders WHERE path=?1", params![self.seal_key(path)?])
+      .map_err(|e| e.to_string())?;
+    Ok(())
+  }
+
+  /// Replaces the read position stored for `path`, kept for this file version only.
+  pub(crate) fn save_read_position(
+    &self,
+    path: &str,
+    file_size: u64,
+    file_mtime_ms: i64,
+    record_index: u64,
+    byte_offset: Option<u64>,
+  ) -> Result<(), String> {
+    let conn = self.conn();
+    conn
+      .execute(
+        r#"
+INSERT INTO read_positions(path, file_size, file_mtime_ms, record_index, byte_offset, updated_at)
+VALUES(?1, ?2, ?3, ?4, ?5, ?6)
+ON CONFLICT(path) DO UPDATE SET
+  file_size=excluded.file_size,
+  file_mtime_ms=excluded.file_mtime_ms,
+  record_index=excluded.record_index,
+  byte_offset=excluded.byte_offset,
+  updated_at=excluded.updated_at
+        "#,
+        params![
+          self.seal_key(path)?,
+          file_size as i64,
+          file_mtime_ms,
+          record_index as i64,
+          byte_offset.map(|o| o as i64),
+          now_ms()
+        ],
+      )
+      .map_err(|e| e.to_string())?;
+    Ok(())
+  }
+
+  /// The read position of `path` if stored for this version and past the first record.
+  pub(crate) fn load_read_position(
+    &self,
+    path: &str,
+    file_size: u64,
+    file_mtime_ms: i64,
+  ) -> Result<Option<ReadPosition>, String> {
+    let conn = self.conn();
+    let mut stmt = conn
+      .prepare(
+        r#"
+SELECT record_index, byte_offset, updated_at FROM read_positions
+WHERE path=?1 AND file_size=?2 AND file_mtime_ms=?3 AND record_index > 0
+        "#,
+      )
+      .map_err(|e| e.to_string())?;
+    let mut rows = stmt
+      .query(params![self.seal_key(path)?, file_size as i64, file_mtime_ms])
+      .map_err(|e| e.to_string())?;
+    let Some(row) = rows.next().map_err(|e| e.to_string())? else {
+      return Ok(None);
+    };
+    Ok(Some(ReadPosition {
+      record_index: row.get::<_, i64>(0).map_err(|e| e.to_string())? as u64,
+      byte_offset: row.get::<_, Option<i64>>(1).map_err(|e| e.to_string())?.map(|o| o as u64),
+      updated_at_ms: row.get(2).map_err(|e| e.to_string())?,
+    }))
+  }
+
+  pub(crate) fn save_view_prefs(&self, path: &str, prefs: &ViewPrefs) -> Result<(), String> {
+    let conn = self.conn();
+    let prefs_json = self.seal(&serde_json::to_string(prefs).map_err(|e| e.to_string())?)?;
+    conn
+      .execute(
+        r#"
+INSERT INTO view_prefs(path, prefs_json, updated_at)
+VALUES(?1, ?2, ?3)
+ON CONFLICT(path) DO UPDATE SET prefs_json=excluded.prefs_json, updated_at=excluded.updated_at
+        "#,
+        params![self.seal_key(path)?, prefs_json, now_ms()],
+      )
+      .map_err(|e| e.to_string())?;
+    Ok(())
+  }
+
+  pub(crate) fn load_view_prefs(&self, path: &str) -> Result<Option<ViewPrefs>, String> {
+    let conn = self.conn();
+    let mut stmt = conn
+      .prepare("SELECT prefs_json FROM view_prefs WHERE path=?1")
+      .map_err(|e| e.to_string())?;
+    let mut rows = stmt.query(params![self.seal_key(path)?]).map_err(|e| e.to_string())?;
+    let Some(row) = rows.next().map_err(|e| e.to_string())? else {
+      return Ok(None);
+    };
+    let json = self.unseal(row.get(0).map_err(|e| e.to_string())?).map_err(|e| e.to_string())?;
+    Ok(serde_json::from_str(&json).ok())
+  }
+
+  /// Create or replace a collection; replacing keeps its creation time.
+  pub(crate) fn save_collection(&self, name: &str, paths: &[String]) -> Result<(), String> {
+    let conn = self.conn();
+    let paths_json = self.seal(&serde_json::to_string(paths).map_err(|e| e.to_string())?)?;
+    let now = now_ms();
+    conn
+      .execute(
+        r#"
+INSERT INTO collections(name, paths_json, created_at, updated_at)
+VALUES(?1, ?2, ?3, ?3)
+ON CONFLICT(name) DO UPDATE SET
+  paths_json=excluded.paths_json,
+  updated_at=excluded.updated_at
+        "#,
+        params![name, paths_json, now],
+      )
+      .map_err(|e| e.to_string())?;
+    Ok(())
+  }
+
+  /// Collections by name.
+  pub(crate) fn list_collections(&self) -> Result<Vec<DatasetCollection>, String> {
+    let conn = self.conn();
+    let mut stmt = conn
+      .prepare("SELECT name, paths_json, created_at, updated_at FROM collections ORDER BY name")
+      .map_err(|e| e.to_string())?;
+    let rows = stmt
+      .query_map([], |row| {
+        let paths_json = self.unseal(row.get(1)?)?;
+        Ok(DatasetCollection {
+          name: row.get(0)?,
+          paths: serde_json::from_str(&paths_json).unwrap_or_default(),
+          created_at_ms: row.get(2)?,
+          updated_at_ms: row.get(3)?,
+        })
+      })
+      .map_err(|e| e.to_string())?;
+
+    let mut out = Vec::new();
+    for r in rows {
+      out.push(r.map_err(|e| e.to_string())?);
+    }
+    Ok(out)
+  }
+
+  /// Returns `false` if there was no such collection.
+  pub(crate) fn delete_collection(&self, name: &str) -> Result<bool, String> {
+    let conn = self.conn();
+    let n = conn
+      .execute("DELETE FROM collections WHERE name=?1", params![name])
+      .map_err(|e| e.to_string())?;
+    Ok(n > 0)
+  }
+
+  /// Create or replace the preset named `preset.name`.
+  pub(crate) fn save_export_preset(&self, preset: &ExportPreset) -> Result<(), String> {
+    let conn = self.conn();
+    let json = self.seal(&serde_json::to_string(preset).map_err(|e| e.to_string())?)?;
+    conn
+      .execute(
+        r#"
+INSERT INTO export_presets(name, preset_json, updated_at)
+VALUES(?1, ?2, ?3)
+ON CONFLICT(name) DO UPDATE SET
+  preset_json=excluded.preset_json,
+  updated_at=excluded.updated_at
+        "#,
+        params![preset.name, json, now_ms()],
+      )
+      .map_err(|e| e.to_string())?;
+    Ok(())
+  }
+
+  /// Presets by name.
+  pub(crate) fn list_export_presets(&self) -> Result<Vec<ExportPreset>, String> {
+    let conn = self.conn();
+    let mut stmt = conn
+      .prepare("SELECT preset_json FROM export_presets ORDER BY name")
+      .map_err(|e| e.to_string())?;
+    let rows = stmt
+      .query_map([], |row| self.unseal(row.get(0)?))
+      .map_err(|e| e.to_string())?;
+
+    let mut out = Vec::new();
+    for r in rows {
+      let json = r.map_err(|e| e.to_string())?;
+      if let Ok(preset) = serde_json::from_str(&json) {
+        out.push(preset);
+      }
+    }
+    Ok(out)
+  }
+
+  pub(crate) fn get_export_preset(&self, name: &str) -> Result<Option<ExportPreset>, String> {
+    let conn = self.conn();
+    let mut stmt = conn
+      .prepare("SELECT preset_json FROM export_presets WHERE name=?1")
+      .map_err(|e| e.to_string())?;
+    let mut rows = stmt.query(params![name]).map_err(|e| e.to_string())?;
+    let Some(row) = rows.next().map_err(|e| e.to_string())? else {
+      return Ok(None);
+    };
+    let json = self.unseal(row.get(0).map_err(|e| e.to_string())?).map_err(|e| e.to_string())?;
+    Ok(serde_json::from_str(&json).ok())
+  }
+
+  /// Returns `false` if there was no such preset.
+  pub(crate) fn delete_export_preset(&self, name: &str) -> Result<bool, String> {
+    let conn = self.conn();
+    let n = conn
+      .execute("DELETE FROM export_presets WHERE name=?1", params![name])
+      .map_err(|e| e.to_string())?;
+    Ok(n > 0)
+  }
+
+  pub fn report(&self) -> Result<StorageReport, String> {
+    let conn = self.conn();
+    let pragma = |name: &str| -> Result<u64, String> {
+      conn
+        .query_row(&format!("PRAGMA {name}"), [], |row| row.get::<_, i64>(0))
+        .map(|n| n as u64)
+        .map_err(|e| e.to_string())
+    };
+    let count = |sql: &str| -> Result<u64, String> {
+      conn
+        .query_row(sql, [], |row| row.get::<_, i64>(0))
+        .map(|n| n as u64)
+        .map_err(|e| e.to_string())
+    };
+    let page_size = pragma("page_size")?;
+    Ok(StorageReport {
+      path: self.path.to_string_lossy().to_string(),
+      size_bytes: pragma("page_count")? * page_size,
+      free_bytes: pragma("freelist_count")? * page_size,
+      task_history_rows: count("SELECT COUNT(*) FROM task_history")?,
+      task_events_rows: count("SELECT COUNT(*) FROM task_events")?,
+      scan_cache_rows: count("SELECT COUNT(*) FROM scan_cache")?,
+      scan_cache_bytes: count("SELECT COALESCE(SUM(LENGTH(hits_json)), 0) FROM scan_cache")?,
+      encrypted: self.is_encrypted(),
+    })
+  }
+
+  /// Trims the history, event log and scan cache to `limits`, then vacuums the database so the
+  /// freed space goes back to the file system.
+  pub fn maintain(&self, limits: &StorageLimits) -> Result<StorageMaintenance, String> {
+    let before = self.report()?;
+    let conn = self.conn();
+    let mut rows_removed = 0u64;
+    for (sql, limit) in [
+      (
+        "DELETE FROM task_history WHERE id NOT IN (SELECT id FROM task_history ORDER BY id DESC LIMIT ?1)",
+        limits.max_task_history,
+      ),
+      (
+        "DELETE FROM task_events WHERE id NOT IN (SELECT id FROM task_events ORDER BY id DESC LIMIT ?1)",
+        limits.max_task_events,
+      ),
+      (
+        r#"
+DELETE FROM scan_cache WHERE rowid IN (
+  SELECT rowid FROM (
+    SELECT rowid, SUM(LENGTH(hits_json)) OVER (ORDER BY saved_at DESC, rowid DESC) AS total
+    FROM scan_cache
+  ) WHERE total > ?1
+)
+        "#,
+        limits.max_scan_cache_bytes,
+      ),
+    ] {
+      rows_removed += conn
+        .execute(sql, params![limit.min(i64::MAX as u64) as i64])
+        .map_err(|e| e.to_string())? as u64;
+    }
+    // Checkpointing afterwards moves the vacuumed pages from the WAL into the (now smaller) file.
+    conn
+      .execute_batch("VACUUM; PRAGMA wal_checkpoint(TRUNCATE);")
+      .map_err(|e| e.to_string())?;
+    drop(conn);
+    Ok(StorageMaintenance {
+      before,
+      after: self.report()?,
+      rows_removed,
+    })
+  }
+
+  /// Writes settings, recent files and folders, record labels, view preferences, collections
+  /// and export presets to one JSON file at `path`. Values are written decrypted, so the file is
+  /// plain text even for encrypted storage; `restore_from` seals them again.
+  pub fn backup_to(&self, path: &Path) -> Result<StorageBackup, String> {
+    let conn = self.conn();
+    let mut tables = Map::new();
+    let mut counts = BTreeMap::new();
+    for (table, columns) in BACKUP_TABLES {
+      let mut stmt = conn
+        .prepare(&format!("SELECT {} FROM {table}", columns.join(", ")))
+        .map_err(|e| e.to_string())?;
+      let mut rows = stmt.query([]).map_err(|e| e.to_string())?;
+      let mut out = Vec::new();
+      while let Some(row) = rows.next().map_err(|e| e.to_string())? {
+        let mut obj = Map::new();
+        for (i, column) in columns.iter().enumerate() {
+          let value = match row.get_ref(i).map_err(|e| e.to_string())? {
+            ValueRef::Null | ValueRef::Blob(_) => Value::Null,
+            ValueRef::Integer(n) => Value::from(n),
+            ValueRef::Real(f) => Value::from(f),
+            ValueRef::Text(t) if sealed_column(table, column).is_some() => {
+              Value::from(self.unseal(String::from_utf8_lossy(t).into_owned()).map_err(|e| e.to_string())?)
+            }
+            ValueRef::Text(t) => Value::from(String::from_utf8_lossy(t)),
+          };
+          obj.insert(column.to_string(), value);
+        }
+        out.push(Value::Object(obj));
+      }
+      counts.insert(table.to_string(), out.len() as u64);
+      tables.insert(table.to_string(), Value::Array(out));
+    }
+    let backup = serde_json::json!({
+      "format": BACKUP_FORMAT,
+      "version": BACKUP_VERSION,
+      "schema_version": current_schema_version(&conn).map_err(|e| e.to_string())?,
+      "created_at_ms": now_ms(),
+      "tables": tables,
+    });
+    if let Some(parent) = path.parent() {
+      fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let json = serde_json::to_vec_pretty(&backup).map_err(|e| e.to_string())?;
+    fs::write(path, json).map_err(|e| e.to_string())?;
+    Ok(StorageBackup {
+      path: path.to_string_lossy().to_string(),
+      rows: counts,
+    })
+  }
+
+  /// Merges a `backup_to` file into this database in one transaction: rows with the same key
+  /// (setting key, file path, collection / preset name, ...) are replaced, others are kept.
+  pub fn restore_from(&self, path: &Path) -> Result<StorageBackup, String> {
+    let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let backup: Value = serde_json::from_str(&text).map_err(|e| format!("not a backup file: {e}"))?;
+    if backup.get("format").and_then(Value::as_str) != Some(BACKUP_FORMAT) {
+      return Err("not a backup file".to_string());
+    }
+    let version = backup.get("version").and_then(Value::as_u64).unwrap_or(0);
+    if version > BACKUP_VERSION {
+      return Err(format!("backup version {version} is newer than this app supports ({BACKUP_VERSION})"));
+    }
+    let empty = Map::new();
+    let tables = backup.get("tables").and_then(Value::as_object).unwrap_or(&empty);
+
+    let mut conn = self.conn();
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let mut counts = BTreeMap::new();
+    for (table, columns) in BACKUP_TABLES {
+      let rows = tables.get(*table).and_then(Value::as_array).map(Vec::as_slice).unwrap_or_default();
+      let mut restored = 0u64;
+      for row in rows {
+        let Some(obj) = row.as_object() else { continue };
+        // Only the columns the row has, so a field added to a table later keeps its default.
+        let present: Vec<&str> = columns.iter().copied().filter(|c| obj.contains_key(*c)).collect();
+        if present.is_empty() {
+          continue;
+        }
+        let mut values = Vec::with_capacity(present.len());
+        for c in &present {
+          let value = match &obj[*c] {
+            Value::Null => rusqlite::types::Value::Null,
+            Value::Bool(b) => rusqlite::types::Value::Integer(*b as i64),
+            Value::Number(n) => match n.as_i64() {
+              Some(i) => rusqlite::types::Value::Integer(i),
+              None => rusqlite::types::Value::Real(n.as_f64().unwrap_or_default()),
+            },
+            Value::String(s) => rusqlite::types::Value::Text(s.clone()),
+            other => rusqlite::types::Value::Text(other.to_string()),
+          };
+          values.push(match (value, sealed_column(table, c)) {
+            (rusqlite::types::Value::Text(s), Some(true)) => rusqlite::types::Value::Text(self.seal_key(&s)?),
+            (rusqlite::types::Value::Text(s), Some(false)) => rusqlite::types::Value::Text(self.seal(&s)?),
+            (value, _) => value,
+          });
+        }
+        let placeholders: Vec<String> = (1..=present.len()).map(|i| format!("?{i}")).collect();
+        tx.execute(
+          &format!(
+            "INSERT OR REPLACE INTO {table}({}) VALUES({})",
+            present.join(", "),
+            placeholders.join(", ")
+          ),
+          params_from_iter(values),
+        )
+        .map_err(|e| format!("restoring {table}: {e}"))?;
+        restored += 1;
+      }
+      counts.insert(table.to_string(), restored);
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(StorageBackup {
+      path: path.to_string_lossy().to_string(),
+      rows: counts,
+    })
+  }
+
   pub fn set_setting_json(&self, key: &str, value_json: &str) -> Result<(), String> {
-    let conn = self.open()?;
+    let conn = self.conn();
     conn
       .execute(
         r#"
@@ -121,30 +947,671 @@ INSERT INTO settings(key, value_json)
 VALUES(?1, ?2)
 ON CONFLICT(key) DO UPDATE SET value_json=excluded.value_json
         "#,
-        params![key, value_json],
+        params![key, self.seal(value_json)?],
       )
       .map_err(|e| e.to_string())?;
     Ok(())
   }
 
   pub fn get_setting_json(&self, key: &str) -> Result<Option<String>, String> {
-    let conn = self.open()?;
+    let conn = self.conn();
     let mut stmt = conn
       .prepare("SELECT value_json FROM settings WHERE key=?1")
       .map_err(|e| e.to_string())?;
     let mut rows = stmt.query(params![key]).map_err(|e| e.to_string())?;
     if let Some(row) = rows.next().map_err(|e| e.to_string())? {
-      let v: String = row.get(0).map_err(|e| e.to_string())?;
+      let v = self.unseal(row.get(0).map_err(|e| e.to_string())?).map_err(|e| e.to_string())?;
       Ok(Some(v))
     } else {
       Ok(None)
     }
   }
+
+  /// Persist a line index for `path`; `file_size` + `file_mtime_ms` identify the file version.
+  pub(crate) fn save_line_index(
+    &self,
+    path: &str,
+    file_size: u64,
+    file_mtime_ms: i64,
+    index: &StoredLineIndex,
+  ) -> Result<(), String> {
+    let conn = self.conn();
+    let blob: Vec<u8> = index.checkpoints.iter().flat_map(|o| o.to_le_bytes()).collect();
+    conn
+      .execute(
+        r#"
+INSERT INTO line_index(path, file_size, file_mtime_ms, stride, total_records, end_offset, checkpoints, built_at, csv_quoting)
+VALUES(?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+ON CONFLICT(path, csv_quoting) DO UPDATE SET
+  file_size=excluded.file_size,
+  file_mtime_ms=excluded.file_mtime_ms,
+  stride=excluded.stride,
+  total_records=excluded.total_records,
+  end_offset=excluded.end_offset,
+  checkpoints=excluded.checkpoints,
+  built_at=excluded.built_at
+        "#,
+        params![
+          self.seal_key(path)?,
+          file_size as i64,
+          file_mtime_ms,
+          index.stride as i64,
+          index.total_records as i64,
+          index.end_offset as i64,
+          blob,
+          now_ms(),
+          index.csv_quoting
+        ],
+      )
+      .map_err(|e| e.to_string())?;
+    Ok(())
+  }
+
+  /// Load the line index for `path` if it was built for this exact file version and CSV quoting.
+  pub(crate) fn load_line_index(
+    &self,
+    path: &str,
+    csv_quoting: &str,
+    file_size: u64,
+    file_mtime_ms: i64,
+  ) -> Result<Option<StoredLineIndex>, String> {
+    let conn = self.conn();
+    let mut stmt = conn
+      .prepare(
+        r#"
+SELECT stride, total_records, end_offset, checkpoints, csv_quoting
+FROM line_index
+WHERE path=?1 AND csv_quoting=?2 AND file_size=?3 AND file_mtime_ms=?4
+        "#,
+      )
+      .map_err(|e| e.to_string())?;
+    let mut rows = stmt
+      .query(params![self.seal_key(path)?, csv_quoting, file_size as i64, file_mtime_ms])
+      .map_err(|e| e.to_string())?;
+    let Some(row) = rows.next().map_err(|e| e.to_string())? else {
+      return Ok(None);
+    };
+    let blob: Vec<u8> = row.get(3).map_err(|e| e.to_string())?;
+    let checkpoints = blob
+      .chunks_exact(8)
+      .map(|c| u64::from_le_bytes(c.try_into().unwrap_or_default()))
+      .collect();
+    Ok(Some(StoredLineIndex {
+      stride: row.get::<_, i64>(0).map_err(|e| e.to_string())? as u64,
+      total_records: row.get::<_, i64>(1).map_err(|e| e.to_string())? as u64,
+      end_offset: row.get::<_, i64>(2).map_err(|e| e.to_string())? as u64,
+      checkpoints,
+      csv_quoting: row.get(4).map_err(|e| e.to_string())?,
+    }))
+  }
+
+  /// Persist the node index of the JSON record at `record_offset` in `path` (at version
+  /// `file_size` + `file_mtime_ms`), replacing one built for an earlier version.
+  pub(crate) fn save_json_node_index(
+    &self,
+    path: &str,
+    record_offset: u64,
+    file_size: u64,
+    file_mtime_ms: i64,
+    index: &JsonNodeIndex,
+  ) -> Result<(), String> {
+    let conn = self.conn();
+    conn
+      .execute(
+        r#"
+INSERT INTO json_node_index(path, record_offset, file_size, file_mtime_ms, nodes, built_at)
+VALUES(?1, ?2, ?3, ?4, ?5, ?6)
+ON CONFLICT(path, record_offset) DO UPDATE SET
+  file_size=excluded.file_size,
+  file_mtime_ms=excluded.file_mtime_ms,
+  nodes=excluded.nodes,
+  built_at=excluded.built_at
+        "#,
+        params![
+          self.seal_key(path)?,
+          record_offset as i64,
+          file_size as i64,
+          file_mtime_ms,
+          index.to_blob(),
+          now_ms()
+        ],
+      )
+      .map_err(|e| e.to_string())?;
+    Ok(())
+  }
+
+  /// The node index of the JSON record at `record_offset` in `path`, if built for this exact
+  /// file version.
+  pub(crate) fn load_json_node_index(
+    &self,
+    path: &str,
+    record_offset: u64,
+    file_size: u64,
+    file_mtime_ms: i64,
+  ) -> Result<Option<JsonNodeIndex>, String> {
+    let conn = self.conn();
+    let blob: Option<Vec<u8>> = conn
+      .query_row(
+        r#"
+SELECT nodes
+FROM json_node_index
+WHERE path=?1 AND record_offset=?2 AND file_size=?3 AND file_mtime_ms=?4
+        "#,
+        params![self.seal_key(path)?, record_offset as i64, file_size as i64, file_mtime_ms],
+        |row| row.get(0),
+      )
+      .optional()
+      .map_err(|e| e.to_string())?;
+    Ok(blob.and_then(|b| JsonNodeIndex::from_blob(&b)))
+  }
+
+  /// Persist the full-text index of `path` (at version `file_size` + `file_mtime_ms`, decoded
+  /// with `encoding`), replacing one built for an earlier version. Sealed like record contents:
+  /// the trigrams it lists say what the file holds.
+  pub(crate) fn save_text_index(
+    &self,
+    path: &str,
+    encoding: TextEncoding,
+    file_size: u64,
+    file_mtime_ms: i64,
+    index: &TextIndex,
+  ) -> Result<(), String> {
+    let conn = self.conn();
+    conn
+      .execute(
+        r#"
+INSERT INTO text_index(path, encoding, file_size, file_mtime_ms, index_data, built_at)
+VALUES(?1, ?2, ?3, ?4, ?5, ?6)
+ON CONFLICT(path, encoding) DO UPDATE SET
+  file_size=excluded.file_size,
+  file_mtime_ms=excluded.file_mtime_ms,
+  index_data=excluded.index_data,
+  built_at=excluded.built_at
+        "#,
+        params![
+          self.seal_key(path)?,
+          serde_json::to_string(&encoding).map_err(|e| e.to_string())?,
+          file_size as i64,
+          file_mtime_ms,
+          self.seal(&STANDARD.encode(index.to_blob()))?,
+          now_ms()
+        ],
+      )
+      .map_err(|e| e.to_string())?;
+    Ok(())
+  }
+
+  /// The full-text index of `path` decoded with `encoding`, if built for this exact file
+  /// version.
+  pub(crate) fn load_text_index(
+    &self,
+    path: &str,
+    encoding: TextEncoding,
+    file_size: u64,
+    file_mtime_ms: i64,
+  ) -> Result<Option<TextIndex>, String> {
+    let conn = self.conn();
+    let data: Option<String> = conn
+      .query_row(
+        r#"
+SELECT index_data
+FROM text_index
+WHERE path=?1 AND encoding=?2 AND file_size=?3 AND file_mtime_ms=?4
+        "#,
+        params![
+          self.seal_key(path)?,
+          serde_json::to_string(&encoding).map_err(|e| e.to_string())?,
+          file_size as i64,
+          file_mtime_ms
+        ],
+        |row| self.unseal(row.get(0)?),
+      )
+      .optional()
+      .map_err(|e| e.to_string())?;
+    Ok(data.and_then(|d| STANDARD.decode(d).ok()).and_then(|b| TextIndex::from_blob(&b)))
+  }
+
+  /// Insert / replace the label of one record of `path` (at version `file_size` + `file_mtime_ms`).
+  pub(crate) fn save_record_label(
+    &self,
+    path: &str,
+    file_size: u64,
+    file_mtime_ms: i64,
+    label: &StoredRecordLabel,
+  ) -> Result<(), String> {
+    let conn = self.conn();
+    let tags_json = self.seal(&serde_json::to_string(&label.tags).map_err(|e| e.to_string())?)?;
+    conn
+      .execute(
+        r#"
+INSERT INTO record_labels(path, file_size, file_mtime_ms, record_offset, line_no, byte_len, tags_json, note, updated_at)
+VALUES(?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+ON CONFLICT(path, file_size, file_mtime_ms, record_offset) DO UPDATE SET
+  line_no=excluded.line_no,
+  byte_len=excluded.byte_len,
+  tags_json=excluded.tags_json,
+  note=excluded.note,
+  updated_at=excluded.updated_at
+        "#,
+        params![
+          self.seal_key(path)?,
+          file_size as i64,
+          file_mtime_ms,
+          label.record_offset as i64,
+          label.line_no as i64,
+          label.byte_len as i64,
+          tags_json,
+          self.seal_opt(label.note.as_deref())?,
+          label.updated_at_ms
+        ],
+      )
+      .map_err(|e| e.to_string())?;
+    Ok(())
+  }
+
+  pub(crate) fn delete_record_label(
+    &self,
+    path: &str,
+    file_size: u64,
+    file_mtime_ms: i64,
+    record_offset: u64,
+  ) -> Result<(), String> {
+    let conn = self.conn();
+    conn
+      .execute(
+        "DELETE FROM record_labels WHERE path=?1 AND file_size=?2 AND file_mtime_ms=?3 AND record_offset=?4",
+        params![self.seal_key(path)?, file_size as i64, file_mtime_ms, record_offset as i64],
+      )
+      .map_err(|e| e.to_string())?;
+    Ok(())
+  }
+
+  /// Labels of `path` at this file version, in record order.
+  pub(crate) fn list_record_labels(
+    &self,
+    path: &str,
+    file_size: u64,
+    file_mtime_ms: i64,
+  ) -> Result<Vec<StoredRecordLabel>, String> {
+    let conn = self.conn();
+    let mut stmt = conn
+      .prepare(
+        r#"
+SELECT record_offset, line_no, byte_len, tags_json, note, updated_at
+FROM record_labels
+WHERE path=?1 AND file_size=?2 AND file_mtime_ms=?3
+ORDER BY record_offset
+        "#,
+      )
+      .map_err(|e| e.to_string())?;
+    let rows = stmt
+      .query_map(params![self.seal_key(path)?, file_size as i64, file_mtime_ms], |row| {
+        let tags_json = self.unseal(row.get(3)?)?;
+        Ok(StoredRecordLabel {
+          record_offset: row.get::<_, i64>(0)? as u64,
+          line_no: row.get::<_, i64>(1)? as u64,
+          byte_len: row.get::<_, i64>(2)? as u64,
+          tags: serde_json::from_str(&tags_json).unwrap_or_default(),
+          note: self.unseal_opt(row.get(4)?)?,
+          updated_at_ms: row.get(5)?,
+        })
+      })
+      .map_err(|e| e.to_string())?;
+
+    let mut out = Vec::new();
+    for r in rows {
+      out.push(r.map_err(|e| e.to_string())?);
+    }
+    Ok(out)
+  }
+
+  /// The file only had data appended: existing record offsets stay valid under the new version.
+  pub(crate) fn move_record_labels(
+    &self,
+    path: &str,
+    from: (u64, i64),
+    to: (u64, i64),
+  ) -> Result<(), String> {
+    let conn = self.conn();
+    conn
+      .execute(
+        r#"
+UPDATE OR REPLACE record_labels SET file_size=?4, file_mtime_ms=?5
+WHERE path=?1 AND file_size=?2 AND file_mtime_ms=?3
+        "#,
+        params![self.seal_key(path)?, from.0 as i64, from.1, to.0 as i64, to.1],
+      )
+      .map_err(|e| e.to_string())?;
+    Ok(())
+  }
+  /// Cache the hits (`hits_json`) of a complete scan_all over `path` at this file version,
+  /// replacing an earlier result for the same query.
+  pub(crate) fn save_scan_cache(
+    &self,
+    path: &str,
+    file_size: u64,
+    file_mtime_ms: i64,
+    query_key: &str,
+    hits_json: &str,
+    truncated: bool,
+  ) -> Result<(), String> {
+    let conn = self.conn();
+    conn
+      .execute(
+        r#"
+INSERT INTO scan_cache(path, query_key, file_size, file_mtime_ms, hits_json, truncated, saved_at)
+VALUES(?1, ?2, ?3, ?4, ?5, ?6, ?7)
+ON CONFLICT(path, query_key) DO UPDATE SET
+  file_size=excluded.file_size,
+  file_mtime_ms=excluded.file_mtime_ms,
+  hits_json=excluded.hits_json,
+  truncated=excluded.truncated,
+  saved_at=excluded.saved_at
+        "#,
+        params![
+          self.seal_key(path)?,
+          self.seal_key(query_key)?,
+          file_size as i64,
+          file_mtime_ms,
+          self.seal(hits_json)?,
+          truncated as i32,
+          now_ms()
+        ],
+      )
+      .map_err(|e| e.to_string())?;
+    conn
+      .execute(
+        "DELETE FROM scan_cache WHERE rowid NOT IN (SELECT rowid FROM scan_cache ORDER BY saved_at DESC LIMIT ?1)",
+        params![SCAN_CACHE_MAX],
+      )
+      .map_err(|e| e.to_string())?;
+    Ok(())
+  }
+
+  /// Cached hits + truncated flag of `query_key` over `path`, if saved for this exact file version.
+  pub(crate) fn load_scan_cache(
+    &self,
+    path: &str,
+    file_size: u64,
+    file_mtime_ms: i64,
+    query_key: &str,
+  ) -> Result<Option<(String, bool)>, String> {
+    let conn = self.conn();
+    let mut stmt = conn
+      .prepare(
+        r#"
+SELECT hits_json, truncated
+FROM scan_cache
+WHERE path=?1 AND query_key=?2 AND file_size=?3 AND file_mtime_ms=?4
+        "#,
+      )
+      .map_err(|e| e.to_string())?;
+    let mut rows = stmt
+      .query(params![self.seal_key(path)?, self.seal_key(query_key)?, file_size as i64, file_mtime_ms])
+      .map_err(|e| e.to_string())?;
+    let Some(row) = rows.next().map_err(|e| e.to_string())? else {
+      return Ok(None);
+    };
+    Ok(Some((
+      self.unseal(row.get(0).map_err(|e| e.to_string())?).map_err(|e| e.to_string())?,
+      row.get::<_, i64>(1).map_err(|e| e.to_string())? != 0,
+    )))
+  }
+
+  /// Append a finished task to the history (`entry.id` is ignored: rows are numbered in order).
+  pub(crate) fn save_task_history(&self, entry: &TaskHistoryEntry) -> Result<(), String> {
+    let conn = self.conn();
+    let kind = serde_json::to_string(&entry.kind).map_err(|e| e.to_string())?;
+    let paths_json = serde_json::to_string(&entry.paths).map_err(|e| e.to_string())?;
+    let error_json = match &entry.error {
+      Some(e) => Some(serde_json::to_string(e).map_err(|e| e.to_string())?),
+      None => None,
+    };
+    conn
+      .execute(
+        r#"
+INSERT INTO task_history(task_id, kind, paths_json, params_json, started_at, finished_at, duration_ms, cancelled, summary, error)
+VALUES(?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+        "#,
+        params![
+          entry.task_id,
+          kind,
+          self.seal(&paths_json)?,
+          self.seal(&entry.params.to_string())?,
+          entry.started_at_ms,
+          entry.finished_at_ms,
+          entry.duration_ms as i64,
+          entry.cancelled as i32,
+          self.seal_opt(entry.summary.as_deref())?,
+          self.seal_opt(error_json.as_deref())?
+        ],
+      )
+      .map_err(|e| e.to_string())?;
+    conn
+      .execute(
+        "DELETE FROM task_history WHERE id <= (SELECT MAX(id) FROM task_history) - ?1",
+        params![TASK_HISTORY_MAX],
+      )
+      .map_err(|e| e.to_string())?;
+    Ok(())
+  }
+
+  /// Most recent history entries first; only those of `kind` if set.
+  pub(crate) fn list_task_history(&self, limit: usize, kind: Option<&TaskKind>) -> Result<Vec<TaskHistoryEntry>, String> {
+    let conn = self.conn();
+    let kind = match kind {
+      Some(k) => Some(serde_json::to_string(k).map_err(|e| e.to_string())?),
+      None => None,
+    };
+    let mut stmt = conn
+      .prepare(
+        r#"
+SELECT id, task_id, kind, paths_json, params_json, started_at, finished_at, duration_ms, cancelled, summary, error
+FROM task_history
+WHERE ?2 IS NULL OR kind = ?2
+ORDER BY id DESC
+LIMIT ?1
+        "#,
+      )
+      .map_err(|e| e.to_string())?;
+    let rows = stmt
+      .query_map(params![limit as i64, kind], |row| self.history_row(row))
+      .map_err(|e| e.to_string())?;
+
+    let mut out = Vec::new();
+    for r in rows {
+      out.extend(r.map_err(|e| e.to_string())?);
+    }
+    Ok(out)
+  }
+
+  pub(crate) fn get_task_history(&self, id: i64) -> Result<Option<TaskHistoryEntry>, String> {
+    let conn = self.conn();
+    let mut stmt = conn
+      .prepare(
+        r#"
+SELECT id, task_id, kind, paths_json, params_json, started_at, finished_at, duration_ms, cancelled, summary, error
+FROM task_history
+WHERE id = ?1
+        "#,
+      )
+      .map_err(|e| e.to_string())?;
+    let mut rows = stmt
+      .query_map(params![id], |row| self.history_row(row))
+      .map_err(|e| e.to_string())?;
+    match rows.next() {
+      Some(r) => r.map_err(|e| e.to_string()),
+      None => Ok(None),
+    }
+  }
+
+  /// Clears the event log along with the history.
+  pub(crate) fn clear_task_history(&self) -> Result<(), String> {
+    let conn = self.conn();
+    conn
+      .execute_batch("DELETE FROM task_history; DELETE FROM task_events;")
+      .map_err(|e| e.to_string())?;
+    Ok(())
+  }
+
+  pub(crate) fn save_task_event(&self, event: &TaskEvent) -> Result<(), String> {
+    let conn = self.conn();
+    let kind = serde_json::to_string(&event.event).map_err(|e| e.to_string())?;
+    conn
+      .execute(
+        r#"
+INSERT INTO task_events(task_id, at, event, progress, records, bytes, hits, detail)
+VALUES(?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+        "#,
+        params![
+          event.task_id,
+          event.at_ms,
+          kind,
+          event.progress_0_100 as i64,
+          event.records_processed as i64,
+          event.bytes_processed as i64,
+          event.hits_found.map(|n| n as i64),
+          self.seal_opt(event.detail.as_deref())?
+        ],
+      )
+      .map_err(|e| e.to_string())?;
+    conn
+      .execute(
+        "DELETE FROM task_events WHERE id <= (SELECT MAX(id) FROM task_events) - ?1",
+        params![TASK_EVENTS_MAX],
+      )
+      .map_err(|e| e.to_string())?;
+    Ok(())
+  }
+
+  /// Events of one task, oldest first.
+  pub(crate) fn list_task_events(&self, task_id: &str) -> Result<Vec<TaskEvent>, String> {
+    let conn = self.conn();
+    let mut stmt = conn
+      .prepare(
+        r#"
+SELECT task_id, at, event, progress, records, bytes, hits, detail
+FROM task_events
+WHERE task_id = ?1
+ORDER BY id
+        "#,
+      )
+      .map_err(|e| e.to_string())?;
+    let rows = stmt
+      .query_map(params![task_id], |row| {
+        let kind: String = row.get(2)?;
+        let Ok(event) = serde_json::from_str(&kind) else {
+          return Ok(None);
+        };
+        Ok(Some(TaskEvent {
+          task_id: row.get(0)?,
+          at_ms: row.get(1)?,
+          event,
+          progress_0_100: row.get::<_, i64>(3)? as u8,
+          records_processed: row.get::<_, i64>(4)? as u64,
+          bytes_processed: row.get::<_, i64>(5)? as u64,
+          hits_found: row.get::<_, Option<i64>>(6)?.map(|n| n as u64),
+          detail: self.unseal_opt(row.get(7)?)?,
+        }))
+      })
+      .map_err(|e| e.to_string())?;
+
+    let mut out = Vec::new();
+    for r in rows {
+      out.extend(r.map_err(|e| e.to_string())?);
+    }
+    Ok(out)
+  }
+
+  /// Journals an export about to be written to `temp_path` and moved to `destination`; returns
+  /// the id to pass to `end_export` once it is done either way.
+  pub(crate) fn begin_export(&self, task_id: &str, destination: &str, temp_path: &str) -> Result<i64, String> {
+    let conn = self.conn();
+    conn
+      .execute(
+        "INSERT INTO export_journal(task_id, destination, temp_path, started_at) VALUES(?1, ?2, ?3, ?4)",
+        params![task_id, self.seal(destination)?, self.seal(temp_path)?, now_ms()],
+      )
+      .map_err(|e| e.to_string())?;
+    Ok(conn.last_insert_rowid())
+  }
+
+  pub(crate) fn end_export(&self, id: i64) -> Result<(), String> {
+    let conn = self.conn();
+    conn
+      .execute("DELETE FROM export_journal WHERE id=?1", params![id])
+      .map_err(|e| e.to_string())?;
+    Ok(())
+  }
+
+  /// Flags the exports journaled but never ended, i.e. cut short by a crash, and returns them.
+  pub(crate) fn mark_interrupted_exports(&self) -> Result<Vec<InterruptedExport>, String> {
+    let mut conn = self.conn();
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let now = now_ms();
+    tx.execute(
+      "UPDATE export_journal SET interrupted_at=?1 WHERE interrupted_at IS NULL",
+      params![now],
+    )
+    .map_err(|e| e.to_string())?;
+    let out = self.interrupted_exports(&tx, Some(now))?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(out)
+  }
+
+  /// Every export flagged by `mark_interrupted_exports` and not dismissed, newest first.
+  pub(crate) fn list_interrupted_exports(&self) -> Result<Vec<InterruptedExport>, String> {
+    let conn = self.conn();
+    self.interrupted_exports(&conn, None)
+  }
+
+  pub(crate) fn dismiss_interrupted_export(&self, id: i64) -> Result<bool, String> {
+    let conn = self.conn();
+    let n = conn
+      .execute(
+        "DELETE FROM export_journal WHERE id=?1 AND interrupted_at IS NOT NULL",
+        params![id],
+      )
+      .map_err(|e| e.to_string())?;
+    Ok(n > 0)
+  }
+
+  /// Flagged exports; only those flagged at `at_ms` if set.
+  fn interrupted_exports(&self, conn: &Connection, at_ms: Option<i64>) -> Result<Vec<InterruptedExport>, String> {
+    let mut stmt = conn
+      .prepare(
+        r#"
+SELECT id, task_id, destination, temp_path, started_at, interrupted_at
+FROM export_journal
+WHERE interrupted_at IS NOT NULL AND (?1 IS NULL OR interrupted_at = ?1)
+ORDER BY id DESC
+        "#,
+      )
+      .map_err(|e| e.to_string())?;
+    let rows = stmt
+      .query_map(params![at_ms], |row| {
+        Ok(InterruptedExport {
+          id: row.get(0)?,
+          task_id: row.get(1)?,
+          destination: self.unseal(row.get(2)?)?,
+          temp_path: self.unseal(row.get(3)?)?,
+          started_at_ms: row.get(4)?,
+          interrupted_at_ms: row.get(5)?,
+        })
+      })
+      .map_err(|e| e.to_string())?;
+    rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+  }
 }
 
-fn migrate(conn: &Connection) -> Result<(), rusqlite::Error> {
-  conn.execute_batch(
-    r#"
+/// Schema changes, oldest first: `MIGRATIONS[i]` takes a database from version `i` to `i + 1`.
+///
+/// Only ever append. A change to an existing table (a new column, index or table) goes in a new
+/// entry, so databases created by older versions are brought up to date step by step.
+const MIGRATIONS: &[&str] = &[
+  // 1: the tables as they were before versioning. `IF NOT EXISTS` makes it a no-op on
+  // databases created back then.
+  r#"
 CREATE TABLE IF NOT EXISTS recent_files(
   id INTEGER PRIMARY KEY AUTOINCREMENT,
   path TEXT NOT NULL UNIQUE,
@@ -158,11 +1625,308 @@ CREATE TABLE IF NOT EXISTS settings(
   key TEXT PRIMARY KEY,
   value_json TEXT NOT NULL
 );
-    "#,
-  )?;
+
+CREATE TABLE IF NOT EXISTS line_index(
+  path TEXT PRIMARY KEY,
+  file_size INTEGER NOT NULL,
+  file_mtime_ms INTEGER NOT NULL,
+  stride INTEGER NOT NULL,
+  total_records INTEGER NOT NULL,
+  end_offset INTEGER NOT NULL,
+  checkpoints BLOB NOT NULL,
+  built_at INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS record_labels(
+  path TEXT NOT NULL,
+  file_size INTEGER NOT NULL,
+  file_mtime_ms INTEGER NOT NULL,
+  record_offset INTEGER NOT NULL,
+  line_no INTEGER NOT NULL,
+  byte_len INTEGER NOT NULL,
+  tags_json TEXT NOT NULL,
+  note TEXT,
+  updated_at INTEGER NOT NULL,
+  PRIMARY KEY(path, file_size, file_mtime_ms, record_offset)
+);
+
+CREATE TABLE IF NOT EXISTS scan_cache(
+  path TEXT NOT NULL,
+  query_key TEXT NOT NULL,
+  file_size INTEGER NOT NULL,
+  file_mtime_ms INTEGER NOT NULL,
+  hits_json TEXT NOT NULL,
+  truncated INTEGER NOT NULL,
+  saved_at INTEGER NOT NULL,
+  PRIMARY KEY(path, query_key)
+);
+
+CREATE TABLE IF NOT EXISTS task_history(
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  task_id TEXT NOT NULL,
+  kind TEXT NOT NULL,
+  paths_json TEXT NOT NULL,
+  params_json TEXT NOT NULL,
+  started_at INTEGER NOT NULL,
+  finished_at INTEGER NOT NULL,
+  duration_ms INTEGER NOT NULL,
+  cancelled INTEGER NOT NULL,
+  summary TEXT,
+  error TEXT
+);
+
+CREATE TABLE IF NOT EXISTS view_prefs(
+  path TEXT PRIMARY KEY,
+  prefs_json TEXT NOT NULL,
+  updated_at INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS collections(
+  name TEXT PRIMARY KEY,
+  paths_json TEXT NOT NULL,
+  created_at INTEGER NOT NULL,
+  updated_at INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS export_presets(
+  name TEXT PRIMARY KEY,
+  preset_json TEXT NOT NULL,
+  updated_at INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS task_events(
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  task_id TEXT NOT NULL,
+  at INTEGER NOT NULL,
+  event TEXT NOT NULL,
+  progress INTEGER NOT NULL,
+  records INTEGER NOT NULL,
+  bytes INTEGER NOT NULL,
+  hits INTEGER,
+  detail TEXT
+);
+CREATE INDEX IF NOT EXISTS task_events_task ON task_events(task_id);
+  "#,
+  // 2: recently scanned folders, next to recent files.
+  r#"
+CREATE TABLE recent_folders(
+  path TEXT PRIMARY KEY,
+  display_name TEXT NOT NULL,
+  last_scanned_at INTEGER NOT NULL,
+  exists_flag INTEGER NOT NULL,
+  pinned INTEGER NOT NULL DEFAULT 0
+);
+  "#,
+  // 3: where the user left off in each file.
+  r#"
+CREATE TABLE read_positions(
+  path TEXT PRIMARY KEY,
+  file_size INTEGER NOT NULL,
+  file_mtime_ms INTEGER NOT NULL,
+  record_index INTEGER NOT NULL,
+  byte_offset INTEGER,
+  updated_at INTEGER NOT NULL
+);
+  "#,
+  // 4: key check for encrypted storage; at most one row, none for plain storage.
+  r#"
+CREATE TABLE encryption(
+  id INTEGER PRIMARY KEY CHECK (id = 1),
+  salt TEXT NOT NULL,
+  check_value TEXT NOT NULL
+);
+  "#,
+  // 5: exports being written, to clean up after a crash.
+  r#"
+CREATE TABLE export_journal(
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  task_id TEXT NOT NULL,
+  destination TEXT NOT NULL,
+  temp_path TEXT NOT NULL,
+  started_at INTEGER NOT NULL,
+  interrupted_at INTEGER
+);
+  "#,
+  // 6: one line index per CSV quoting (see `line_index::csv_quoting_key`) instead of per path.
+  r#"
+CREATE TABLE line_index_v6(
+  path TEXT NOT NULL,
+  csv_quoting TEXT NOT NULL,
+  file_size INTEGER NOT NULL,
+  file_mtime_ms INTEGER NOT NULL,
+  stride INTEGER NOT NULL,
+  total_records INTEGER NOT NULL,
+  end_offset INTEGER NOT NULL,
+  checkpoints BLOB NOT NULL,
+  built_at INTEGER NOT NULL,
+  PRIMARY KEY(path, csv_quoting)
+);
+INSERT INTO line_index_v6(path, csv_quoting, file_size, file_mtime_ms, stride, total_records, end_offset, checkpoints, built_at)
+SELECT path, '', file_size, file_mtime_ms, stride, total_records, end_offset, checkpoints, built_at FROM line_index;
+DROP TABLE line_index;
+ALTER TABLE line_index_v6 RENAME TO line_index;
+  "#,
+  // 7: offsets of the large containers of huge JSON records (see `json_index`).
+  r#"
+CREATE TABLE json_node_index(
+  path TEXT NOT NULL,
+  record_offset INTEGER NOT NULL,
+  file_size INTEGER NOT NULL,
+  file_mtime_ms INTEGER NOT NULL,
+  nodes BLOB NOT NULL,
+  built_at INTEGER NOT NULL,
+  PRIMARY KEY(path, record_offset)
+);
+  "#,
+  // 8: full-text indexes of JSONL / CSV lines (see `text_index`), sealed in encrypted storage.
+  r#"
+CREATE TABLE text_index(
+  path TEXT NOT NULL,
+  encoding TEXT NOT NULL,
+  file_size INTEGER NOT NULL,
+  file_mtime_ms INTEGER NOT NULL,
+  index_data TEXT NOT NULL,
+  built_at INTEGER NOT NULL,
+  PRIMARY KEY(path, encoding)
+);
+  "#,
+];
+
+/// Columns encrypted in encrypted storage, per table. `true` marks the ones looked up by value,
+/// which are sealed deterministically (`FieldCipher::seal_key`).
+const SEALED_COLUMNS: &[(&str, &[(&str, bool)])] = &[
+  ("recent_files", &[("path", true), ("display_name", false)]),
+  ("recent_folders", &[("path", true), ("display_name", false)]),
+  ("settings", &[("value_json", false)]),
+  ("line_index", &[("path", true)]),
+  ("json_node_index", &[("path", true)]),
+  ("text_index", &[("path", true), ("index_data", false)]),
+  ("record_labels", &[("path", true), ("tags_json", false), ("note", false)]),
+  ("scan_cache", &[("path", true), ("query_key", true), ("hits_json", false)]),
+  ("task_history", &[("paths_json", false), ("params_json", false), ("summary", false), ("error", false)]),
+  ("task_events", &[("detail", false)]),
+  ("view_prefs", &[("path", true), ("prefs_json", false)]),
+  ("collections", &[("paths_json", false)]),
+  ("export_presets", &[("preset_json", false)]),
+  ("read_positions", &[("path", true)]),
+  ("export_journal", &[("destination", false), ("temp_path", false)]),
+];
+
+/// `Some(key)` if `table.column` is in `SEALED_COLUMNS`.
+fn sealed_column(table: &str, column: &str) -> Option<bool> {
+  SEALED_COLUMNS
+    .iter()
+    .find(|(t, _)| *t == table)
+    .and_then(|(_, columns)| columns.iter().find(|(c, _)| *c == column))
+    .map(|&(_, key)| key)
+}
+
+/// Sealed into `encryption.check_value`, to tell a wrong passphrase from a right one.
+const ENCRYPTION_CHECK: &str = "fluxpeek-storage-key-check";
+
+/// The cipher for `conn`'s database, checking `passphrase` against it.
+///
+/// A plain database given a passphrase is encrypted on the spot: every `SEALED_COLUMNS` value is
+/// sealed in one transaction, then the file is vacuumed so the plain values don't linger in free
+/// pages or the WAL. There is no way back short of a backup and restore into new storage.
+fn setup_encryption(conn: &mut Connection, passphrase: Option<&str>) -> Result<Option<FieldCipher>, String> {
+  let stored: Option<(String, String)> = conn
+    .query_row("SELECT salt, check_value FROM encryption WHERE id=1", [], |row| {
+      Ok((row.get(0)?, row.get(1)?))
+    })
+    .optional()
+    .map_err(|e| e.to_string())?;
+  let passphrase = passphrase.filter(|p| !p.is_empty());
+
+  match (stored, passphrase) {
+    (None, None) => Ok(None),
+    (Some(_), None) => Err(format!(
+      "storage is encrypted; set {PASSPHRASE_ENV} to its passphrase or pass --storage-key-file <path>"
+    )),
+    (Some((salt, check)), Some(passphrase)) => {
+      let salt = STANDARD.decode(salt).map_err(|e| e.to_string())?;
+      let cipher = FieldCipher::derive(passphrase, &salt);
+      match cipher.open(&check) {
+        Ok(check) if check == ENCRYPTION_CHECK => Ok(Some(cipher)),
+        _ => Err("wrong storage passphrase".to_string()),
+      }
+    }
+    (None, Some(passphrase)) => {
+      let salt = FieldCipher::new_salt()?;
+      let cipher = FieldCipher::derive(passphrase, &salt);
+      let tx = conn.transaction().map_err(|e| e.to_string())?;
+      for (table, columns) in SEALED_COLUMNS {
+        for &(column, key) in *columns {
+          let rows: Vec<(i64, String)> = {
+            let mut stmt = tx
+              .prepare(&format!("SELECT rowid, {column} FROM {table} WHERE {column} IS NOT NULL"))
+              .map_err(|e| e.to_string())?;
+            let rows = stmt
+              .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+              .map_err(|e| e.to_string())?;
+            rows.collect::<Result<_, _>>().map_err(|e| e.to_string())?
+          };
+          for (rowid, plain) in rows {
+            let sealed = if key { cipher.seal_key(&plain)? } else { cipher.seal(&plain)? };
+            tx.execute(&format!("UPDATE {table} SET {column}=?2 WHERE rowid=?1"), params![rowid, sealed])
+              .map_err(|e| e.to_string())?;
+          }
+        }
+      }
+      tx.execute(
+        "INSERT INTO encryption(id, salt, check_value) VALUES(1, ?1, ?2)",
+        params![STANDARD.encode(salt), cipher.seal(ENCRYPTION_CHECK)?],
+      )
+      .map_err(|e| e.to_string())?;
+      tx.commit().map_err(|e| e.to_string())?;
+      // The updates left the plain values in freed pages and WAL frames; rewrite the file
+      // without them and empty the WAL.
+      conn
+        .execute_batch("VACUUM; PRAGMA wal_checkpoint(TRUNCATE);")
+        .map_err(|e| e.to_string())?;
+      Ok(Some(cipher))
+    }
+  }
+}
+
+/// Applies the migrations `conn` hasn't had yet, each in its own transaction.
+fn migrate(conn: &mut Connection) -> Result<(), String> {
+  conn
+    .execute_batch(
+      r#"
+CREATE TABLE IF NOT EXISTS schema_version(
+  version INTEGER PRIMARY KEY,
+  applied_at INTEGER NOT NULL
+);
+      "#,
+    )
+    .map_err(|e| e.to_string())?;
+  let current = current_schema_version(conn).map_err(|e| e.to_string())? as usize;
+  if current > MIGRATIONS.len() {
+    return Err(format!(
+      "storage schema version {current} is newer than this app supports ({}); update the app",
+      MIGRATIONS.len()
+    ));
+  }
+  for (i, sql) in MIGRATIONS.iter().enumerate().skip(current) {
+    let version = i + 1;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    tx.execute_batch(sql)
+      .map_err(|e| format!("storage migration {version} failed: {e}"))?;
+    tx.execute(
+      "INSERT INTO schema_version(version, applied_at) VALUES(?1, ?2)",
+      params![version as i64, now_ms()],
+    )
+    .map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+  }
   Ok(())
 }
 
+fn current_schema_version(conn: &Connection) -> Result<u32, rusqlite::Error> {
+  conn.query_row("SELECT COALESCE(MAX(version), 0) FROM schema_version", [], |row| row.get(0))
+}
+
 fn default_sqlite_path() -> PathBuf {
   // Keep it simple & cross-platform without extra deps.
   // - macOS/Linux: $HOME/.datasets-helper/storage.sqlite
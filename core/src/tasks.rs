@@ -1,14 +1,14 @@
 use std::{
-  collections::HashMap,
+  collections::{HashMap, VecDeque},
   fs::File,
-  io::{BufRead, BufReader, Read, Seek, SeekFrom},
-  path::PathBuf,
+  io::{BufRead, BufReader, Read, Seek, SeekFrom, Write},
+  path::{Path, PathBuf},
   sync::{
-    atomic::{AtomicBool, AtomicU8, AtomicUsize, Ordering},
-    Arc,
+    atomic::{AtomicBool, AtomicI64, AtomicU64, AtomicU8, AtomicUsize, Ordering},
+    Arc, OnceLock,
   },
-  thread,
-  time::{SystemTime, UNIX_EPOCH},
+  thread::{self, JoinHandle},
+  time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
 use parking_lot::Mutex;
@@ -16,46 +16,385 @@ use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use crate::{
+  derive::LineDeriver,
+  diff::{self as diff_impl, DiffOutcome, DiffSide},
   engine::CoreError,
-  models::{FileFormat, Record, RecordMeta, RecordPage, SearchQuery, Task, TaskKind},
+  formats::{self, JsonSource},
+  hit_store::{HitStore, SearchHit},
+  json_index::{JsonNodeIndex, JSON_INDEX_MIN_NODE_BYTES},
+  line_index::LineIndex,
+  models::{
+    ColumnFilter, CsvDialect, DiffAlign, DiffPage, FileFormat, Record, RecordMeta, RecordPage, SearchCount, SearchMode, SearchQuery, StatsResult, Task, TaskError,
+    TaskEvent, TaskEventKind, TaskHistoryEntry, TaskKind, TaskPriority, TextEncoding,
+  },
+  progress::ScanProgress,
   search_match::PreparedSearch,
+  stats as stats_impl,
+  storage::Storage,
+  text_index::{TextIndex, TEXT_INDEX_BLOCK_LINES},
 };
 
+/// How often a paused task checks whether it was resumed or cancelled.
+const PAUSE_POLL: Duration = Duration::from_millis(50);
+/// How often a background task checks whether the interactive calls it yields to are done.
+const YIELD_POLL: Duration = Duration::from_millis(5);
+/// Progress is logged each time a task gets this much further (see `TaskEvent`).
+const PROGRESS_MILESTONE_PCT: u8 = 25;
+
+impl From<&std::io::Error> for TaskError {
+  /// Truncated or undecodable content is the file's fault, not the disk's.
+  fn from(e: &std::io::Error) -> Self {
+    match e.kind() {
+      std::io::ErrorKind::InvalidData | std::io::ErrorKind::UnexpectedEof => TaskError::Format { message: e.to_string() },
+      _ => TaskError::Io { message: e.to_string() },
+    }
+  }
+}
+
+impl From<std::io::Error> for TaskError {
+  fn from(e: std::io::Error) -> Self {
+    (&e).into()
+  }
+}
+
 #[derive(Debug, Clone)]
 pub struct TaskManagerOptions {
   pub max_concurrent_tasks: usize,
+  /// Bytes of hits a scan_all task keeps in memory before spilling the rest to disk (0: no cap).
+  pub task_memory_budget_bytes: usize,
+  /// See `CoreOptions::parquet_utc_offset_minutes`; applies to scan_all previews of parquet files.
+  pub parquet_utc_offset_minutes: i32,
 }
 
 #[derive(Clone)]
 pub struct TaskManager {
   opts: TaskManagerOptions,
+  /// `opts.max_concurrent_tasks`, as changed since by `set_max_concurrent_tasks`.
+  max_concurrent: Arc<AtomicUsize>,
   tasks: Arc<Mutex<HashMap<String, Arc<TaskState>>>>,
-  running: Arc<AtomicUsize>,
+  slots: Arc<Mutex<Slots>>,
+  /// Interactive calls in progress; background tasks yield to them.
+  interactive: Arc<AtomicUsize>,
+  /// Exports in progress, the running workers and whether `shutdown` was called.
+  exports: Arc<AtomicUsize>,
+  workers: Arc<Mutex<Vec<JoinHandle<()>>>>,
+  shutting_down: Arc<AtomicBool>,
+  /// Where tasks given a `TaskOrigin` are recorded once they finish, and scan_all hits cached.
+  storage: Storage,
+}
+
+/// What the history keeps about how a task was started (see `TaskHistoryEntry`).
+#[derive(Debug, Clone)]
+pub(crate) struct TaskOrigin {
+  pub paths: Vec<String>,
+  pub params: serde_json::Value,
+}
+
+/// Identifies a scan_all result that can be reused: the file version and the query settings
+/// that change which hits it finds.
+#[derive(Debug, Clone)]
+pub(crate) struct ScanCacheKey {
+  pub path: String,
+  pub file_size: u64,
+  pub file_mtime_ms: i64,
+  pub query_key: String,
+}
+
+/// Result sets with more hits than this are not cached.
+const SCAN_CACHE_MAX_HITS: usize = 100_000;
+
+/// Tasks running against `max_concurrent_tasks`, and the ones waiting for a slot (FIFO within
+/// a priority).
+#[derive(Default)]
+struct Slots {
+  running: usize,
+  queue: VecDeque<QueuedJob>,
+}
+
+impl Slots {
+  /// The oldest queued normal task, else the oldest background one.
+  fn pop_next(&mut self) -> Option<QueuedJob> {
+    let i = self
+      .queue
+      .iter()
+      .position(|q| q.state.priority == TaskPriority::Normal)
+      .unwrap_or(0);
+    self.queue.remove(i)
+  }
+}
+
+/// Counts a call in progress while held: interactive calls (paging, current-page search), which
+/// background tasks wait out in `should_stop`, and exports, which `shutdown` waits for.
+pub(crate) struct CallGuard(Arc<AtomicUsize>);
+
+impl Drop for CallGuard {
+  fn drop(&mut self) {
+    self.0.fetch_sub(1, Ordering::SeqCst);
+  }
+}
+
+struct QueuedJob {
+  state: Arc<TaskState>,
+  job: Box<dyn FnOnce(&TaskState) + Send>,
 }
 
 #[derive(Debug)]
 struct TaskState {
   id: String,
   kind: TaskKind,
+  priority: TaskPriority,
   started_at_ms: i64,
   cancellable: bool,
-
+  /// Background tasks: interactive calls in progress (see `TaskManager::interactive`), set when
+  /// the task is dispatched.
+  yield_to: OnceLock<Arc<AtomicUsize>>,
+
+  /// Waiting for a slot (see `TaskManager::dispatch`).
+  queued: AtomicBool,
+  /// When the task left the queue and when it finished (0 until then).
+  run_started_ms: AtomicI64,
+  finished_at_ms: AtomicI64,
+  /// Set by `pause_task`: the task's loop waits in `should_stop` until resumed or cancelled.
+  paused: AtomicBool,
+  paused_since_ms: AtomicI64,
+  /// Time spent paused before the current pause (left out of rates and ETA).
+  paused_total_ms: AtomicI64,
   progress: AtomicU8,
+  bytes_done: AtomicU64,
+  records_done: AtomicU64,
   finished: AtomicBool,
   cancelled: AtomicBool,
-  error: Mutex<Option<String>>,
+  /// Running time after which `should_stop` ends the task (0: none), and whether it did.
+  timeout_ms: AtomicU64,
+  timed_out: AtomicBool,
+  error: Mutex<Option<TaskError>>,
+  /// Where `log_event` writes, set when the task is started; and the last progress milestone
+  /// logged.
+  event_log: OnceLock<Storage>,
+  progress_milestone: AtomicU8,
+  /// Set by `record_in_history`; the entry is written once both it and `finished` are set.
+  origin: Mutex<Option<TaskOrigin>>,
+  recorded: AtomicBool,
 
   // For search_scan_all
-  search_hits: Mutex<Vec<SearchHit>>,
+  search_hits: Mutex<HitStore>,
   truncated: AtomicBool,
+  /// Matching records so far, kept or not (the result of a count_only search).
+  match_count: AtomicU64,
+  /// Hits were taken from the scan cache instead of reading the file.
+  from_cache: AtomicBool,
+  /// Multi-file scans: the part currently being scanned.
+  scan_part: Mutex<Option<ScanPart>>,
+
+  // For stats
+  stats_result: Mutex<Option<StatsResult>>,
+
+  // For count_records
+  count_result: Mutex<Option<u64>>,
+
+  // For diff
+  diff_result: Mutex<Option<DiffOutcome>>,
 }
 
-#[derive(Debug, Clone)]
-struct SearchHit {
-  line_no: u64,
-  byte_offset: u64,
-  byte_len: u64,
-  preview: String,
+impl TaskState {
+  fn new(id: String, kind: TaskKind) -> Self {
+    Self {
+      id,
+      priority: priority_of(&kind),
+      kind,
+      started_at_ms: now_ms(),
+      cancellable: true,
+      yield_to: OnceLock::new(),
+      queued: AtomicBool::new(false),
+      run_started_ms: AtomicI64::new(0),
+      finished_at_ms: AtomicI64::new(0),
+      paused: AtomicBool::new(false),
+      paused_since_ms: AtomicI64::new(0),
+      paused_total_ms: AtomicI64::new(0),
+      progress: AtomicU8::new(0),
+      bytes_done: AtomicU64::new(0),
+      records_done: AtomicU64::new(0),
+      finished: AtomicBool::new(false),
+      cancelled: AtomicBool::new(false),
+      timeout_ms: AtomicU64::new(0),
+      timed_out: AtomicBool::new(false),
+      error: Mutex::new(None),
+      event_log: OnceLock::new(),
+      progress_milestone: AtomicU8::new(0),
+      origin: Mutex::new(None),
+      recorded: AtomicBool::new(false),
+      search_hits: Mutex::new(HitStore::default()),
+      truncated: AtomicBool::new(false),
+      match_count: AtomicU64::new(0),
+      from_cache: AtomicBool::new(false),
+      scan_part: Mutex::new(None),
+      stats_result: Mutex::new(None),
+      count_result: Mutex::new(None),
+      diff_result: Mutex::new(None),
+    }
+  }
+
+  /// Polled by task loops between records: `true` once cancelled or past its timeout. While
+  /// paused it blocks, so the loop keeps its place in the file and continues from there on resume;
+  /// background tasks likewise wait out interactive calls.
+  fn should_stop(&self) -> bool {
+    while self.paused.load(Ordering::SeqCst) && !self.cancelled.load(Ordering::SeqCst) {
+      thread::sleep(PAUSE_POLL);
+    }
+    if let Some(interactive) = self.yield_to.get() {
+      while interactive.load(Ordering::SeqCst) > 0 && !self.cancelled.load(Ordering::SeqCst) {
+        thread::sleep(YIELD_POLL);
+      }
+    }
+    if self.cancelled.load(Ordering::SeqCst) {
+      return true;
+    }
+    let timeout_ms = self.timeout_ms.load(Ordering::SeqCst);
+    if timeout_ms > 0 && self.run_ms(now_ms()) >= timeout_ms {
+      self.timed_out.store(true, Ordering::SeqCst);
+      return true;
+    }
+    false
+  }
+
+  /// Time paused so far, including a pause still in progress.
+  fn paused_ms(&self, now: i64) -> i64 {
+    let current = if self.paused.load(Ordering::SeqCst) {
+      now - self.paused_since_ms.load(Ordering::SeqCst)
+    } else {
+      0
+    };
+    self.paused_total_ms.load(Ordering::SeqCst) + current
+  }
+
+  /// Time spent running up to `until` (from leaving the queue, less pauses).
+  fn run_ms(&self, until: i64) -> u64 {
+    let run_started = self.run_started_ms.load(Ordering::SeqCst);
+    if run_started > 0 {
+      (until - run_started - self.paused_ms(until)).max(0) as u64
+    } else {
+      0
+    }
+  }
+
+  fn report(&self, p: ScanProgress) {
+    self.progress.store(p.pct, Ordering::SeqCst);
+    self.bytes_done.store(p.bytes, Ordering::SeqCst);
+    self.records_done.store(p.records, Ordering::SeqCst);
+    let milestone = p.pct.min(99) / PROGRESS_MILESTONE_PCT;
+    if self.progress_milestone.fetch_max(milestone, Ordering::SeqCst) < milestone {
+      self.log_event(TaskEventKind::Progress, None);
+    }
+  }
+
+  fn mark_finished(&self) {
+    self.finished_at_ms.store(now_ms(), Ordering::SeqCst);
+    self.finished.store(true, Ordering::SeqCst);
+    self.progress.store(100, Ordering::SeqCst);
+  }
+
+  /// Writes the history entry of a finished task started with a `TaskOrigin` (once).
+  fn record_history(&self, history: &Storage) {
+    let origin = self.origin.lock();
+    let Some(origin) = origin.as_ref() else {
+      return;
+    };
+    if !self.finished.load(Ordering::SeqCst) || self.recorded.swap(true, Ordering::SeqCst) {
+      return;
+    }
+    let error = self.error.lock().clone();
+    let summary = if error.is_some() { None } else { self.summary() };
+    let finished_at_ms = self.finished_at_ms.load(Ordering::SeqCst);
+    let _ = history.save_task_history(&TaskHistoryEntry {
+      id: 0,
+      task_id: self.id.clone(),
+      kind: self.kind.clone(),
+      paths: origin.paths.clone(),
+      params: origin.params.clone(),
+      started_at_ms: self.started_at_ms,
+      finished_at_ms,
+      duration_ms: self.run_ms(finished_at_ms),
+      cancelled: self.cancelled.load(Ordering::SeqCst),
+      summary,
+      error,
+    });
+  }
+
+  /// What the task came up with, for the history and the event log.
+  fn summary(&self) -> Option<String> {
+    let summary = match self.kind {
+      TaskKind::SearchScanAll => {
+        let hits = self.search_hits.lock().len();
+        let truncated = if self.truncated.load(Ordering::SeqCst) { " (truncated)" } else { "" };
+        Some(format!("{hits} hits{truncated}"))
+      }
+      TaskKind::SearchCount => Some(format!("{} matches", self.match_count.load(Ordering::SeqCst))),
+      TaskKind::Stats => self
+        .stats_result
+        .lock()
+        .as_ref()
+        .map(|s| format!("{} records, {} columns", s.records_scanned, s.columns.len())),
+      TaskKind::CountRecords => self.count_result.lock().map(|n| format!("{n} records")),
+      TaskKind::Diff => self.diff_result.lock().as_ref().map(|o| {
+        let s = &o.summary;
+        format!("{} added, {} removed, {} changed", s.added, s.removed, s.changed)
+      }),
+      _ => None,
+    };
+    match summary {
+      Some(s) if self.timed_out.load(Ordering::SeqCst) => Some(format!("{s} (timed out)")),
+      other => other,
+    }
+  }
+
+  /// Appends to the task's event log (see `TaskEvent`), if it has one.
+  fn log_event(&self, event: TaskEventKind, detail: Option<String>) {
+    let Some(storage) = self.event_log.get() else {
+      return;
+    };
+    let _ = storage.save_task_event(&TaskEvent {
+      task_id: self.id.clone(),
+      at_ms: now_ms(),
+      event,
+      progress_0_100: self.progress.load(Ordering::SeqCst),
+      records_processed: self.records_done.load(Ordering::SeqCst),
+      bytes_processed: self.bytes_done.load(Ordering::SeqCst),
+      hits_found: self.hits_found(),
+      detail,
+    });
+  }
+
+  /// How the task ended: failed, cancelled, timed out or finished.
+  fn log_end(&self) {
+    if let Some(e) = self.error.lock().clone() {
+      self.log_event(TaskEventKind::Failed, Some(e.to_string()));
+    } else if self.cancelled.load(Ordering::SeqCst) {
+      self.log_event(TaskEventKind::Cancelled, self.summary());
+    } else if self.timed_out.load(Ordering::SeqCst) {
+      self.log_event(TaskEventKind::TimedOut, self.summary());
+    } else {
+      self.log_event(TaskEventKind::Finished, self.summary());
+    }
+  }
+
+  fn hits_found(&self) -> Option<u64> {
+    matches!(self.kind, TaskKind::SearchScanAll | TaskKind::SearchCount)
+      .then(|| self.match_count.load(Ordering::SeqCst))
+  }
+}
+
+/// Where the part being scanned sits in a multi-file session.
+#[derive(Debug, Clone, Copy)]
+struct ScanPart {
+  index: u32,
+  count: u32,
+  /// Session-wide id of the part's first record.
+  id_base: u64,
+  /// Local ids below this are skipped (the CSV header of every part but the first).
+  first_local: u64,
+  /// Bytes / records of the earlier parts, added to this part's own progress.
+  bytes_base: u64,
+  records_base: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -68,20 +407,49 @@ pub(crate) struct StartedTask {
 }
 
 impl TaskManager {
-  pub fn new(opts: TaskManagerOptions) -> Self {
+  pub fn new(opts: TaskManagerOptions, storage: Storage) -> Self {
     Self {
+      max_concurrent: Arc::new(AtomicUsize::new(opts.max_concurrent_tasks.max(1))),
       opts,
       tasks: Arc::new(Mutex::new(HashMap::new())),
-      running: Arc::new(AtomicUsize::new(0)),
+      slots: Arc::default(),
+      interactive: Arc::default(),
+      exports: Arc::default(),
+      workers: Arc::default(),
+      shutting_down: Arc::default(),
+      storage,
     }
   }
 
+  /// Keeps the task in the task history (see `TaskHistoryEntry`) once it finishes, or right
+  /// away if it already has.
+  pub(crate) fn record_in_history(&self, task_id: &str, origin: TaskOrigin) {
+    let Some(t) = self.tasks.lock().get(task_id).cloned() else {
+      return;
+    };
+    *t.origin.lock() = Some(origin);
+    t.record_history(&self.storage);
+  }
+
+  /// JSONL / CSV lines are decoded as `encoding` before matching, and also match on the values
+  /// of `derived` columns. Parquet scans only visit rows meeting every one of `filters`, and
+  /// read encrypted files with `decryption_key`.
+  ///
+  /// With a `cache` key, a complete earlier result for it is returned as an already finished
+  /// task (unless `query.force_rescan`), and a scan that runs to the end is saved under it.
+  #[allow(clippy::too_many_arguments)]
   pub(crate) fn start_search_scan_all(
     &self,
     path: PathBuf,
     format: FileFormat,
+    encoding: TextEncoding,
+    json_lenient: bool,
     query: SearchQuery,
     preview_max_chars: usize,
+    derived: Option<LineDeriver>,
+    filters: Option<Vec<ColumnFilter>>,
+    decryption_key: Option<String>,
+    cache: Option<ScanCacheKey>,
   ) -> Result<StartedTask, CoreError> {
     match format {
       FileFormat::Jsonl | FileFormat::Csv | FileFormat::Json | FileFormat::Parquet => {}
@@ -91,50 +459,689 @@ impl TaskManager {
       return Err(CoreError::InvalidArg("query.text is empty".into()));
     }
 
-    // Concurrency limit
-    let now_running = self.running.load(Ordering::SeqCst);
-    if now_running >= self.opts.max_concurrent_tasks {
-      return Err(CoreError::Task(format!(
-        "too many concurrent tasks (max {})",
-        self.opts.max_concurrent_tasks
-      )));
+    let id = Uuid::new_v4().to_string();
+    let state = Arc::new(TaskState::new(id.clone(), search_kind(&query)));
+    *state.search_hits.lock() = HitStore::new(self.opts.task_memory_budget_bytes);
+    state.timeout_ms.store(query.timeout_ms.unwrap_or(0), Ordering::SeqCst);
+    self.tasks.lock().insert(id.clone(), state.clone());
+
+    if let Some(key) = cache.as_ref().filter(|_| !query.force_rescan) {
+      if let Some((hits_json, truncated)) = self
+        .storage
+        .load_scan_cache(&key.path, key.file_size, key.file_mtime_ms, &key.query_key)
+        .map_err(CoreError::Storage)? {
+        if let Ok(hits) = serde_json::from_str::<Vec<SearchHit>>(&hits_json) {
+          return self.finish_from_cache(state, hits, truncated);
+        }
+      }
+    }
+
+    let storage = self.storage.clone();
+    let utc_offset_minutes = self.opts.parquet_utc_offset_minutes;
+    self.dispatch(state, move |state| {
+      let res = run_search_scan_all(
+        state,
+        path,
+        format,
+        encoding,
+        json_lenient,
+        query,
+        preview_max_chars,
+        derived.as_ref(),
+        filters.as_deref(),
+        decryption_key.as_deref(),
+        utc_offset_minutes,
+      );
+      match res {
+        Err(e) => *state.error.lock() = Some(e),
+        Ok(()) => {
+          if let Some(key) = cache {
+            save_scan_cache(state, &storage, &key);
+          }
+        }
+      }
+    });
+
+    Ok(StartedTask { id })
+  }
+
+  /// scan_all over the JSONL / CSV lines of `path`, reading only the blocks `index` can't rule
+  /// out (see `SearchMode::Indexed`). Finds the same hits as `start_search_scan_all`.
+  pub(crate) fn start_search_indexed(
+    &self,
+    path: PathBuf,
+    encoding: TextEncoding,
+    query: SearchQuery,
+    preview_max_chars: usize,
+    index: Arc<TextIndex>,
+  ) -> Result<StartedTask, CoreError> {
+    let Some(prepared) = PreparedSearch::new(&query) else {
+      return Err(CoreError::InvalidArg("query.text is empty".into()));
+    };
+    let id = Uuid::new_v4().to_string();
+    let state = Arc::new(TaskState::new(id.clone(), search_kind(&query)));
+    *state.search_hits.lock() = HitStore::new(self.opts.task_memory_budget_bytes);
+    state.timeout_ms.store(query.timeout_ms.unwrap_or(0), Ordering::SeqCst);
+    self.tasks.lock().insert(id.clone(), state.clone());
+
+    self.dispatch(state, move |state| {
+      if let Err(e) = run_search_indexed(state, &path, encoding, &query, &prepared, preview_max_chars, &index) {
+        *state.error.lock() = Some(e);
+      }
+    });
+
+    Ok(StartedTask { id })
+  }
+
+  /// A scan_all task that is already finished with `hits` (see `ScanCacheKey`).
+  fn finish_from_cache(
+    &self,
+    state: Arc<TaskState>,
+    hits: Vec<SearchHit>,
+    truncated: bool,
+  ) -> Result<StartedTask, CoreError> {
+    {
+      let mut store = state.search_hits.lock();
+      for hit in hits {
+        store.push(hit).map_err(|e| CoreError::Task(e.to_string()))?;
+      }
+      state.match_count.store(store.len() as u64, Ordering::SeqCst);
     }
-    self.running.fetch_add(1, Ordering::SeqCst);
+    let now = now_ms();
+    state.truncated.store(truncated, Ordering::SeqCst);
+    state.from_cache.store(true, Ordering::SeqCst);
+    state.run_started_ms.store(now, Ordering::SeqCst);
+    let _ = state.event_log.set(self.storage.clone());
+    state.log_event(TaskEventKind::Started, Some("from scan cache".into()));
+    state.mark_finished();
+    state.log_end();
+    Ok(StartedTask { id: state.id.clone() })
+  }
+
+  /// scan_all over every part of a multi-file session; hit ids continue across parts.
+  ///
+  /// `spans[i]` is the number of session ids part `i` occupies, if already known; missing spans
+  /// are counted before the next part is scanned.
+  pub(crate) fn start_search_scan_all_parts(
+    &self,
+    parts: Vec<PathBuf>,
+    spans: Vec<Option<u64>>,
+    format: FileFormat,
+    query: SearchQuery,
+    preview_max_chars: usize,
+  ) -> Result<StartedTask, CoreError> {
+    match format {
+      FileFormat::Jsonl | FileFormat::Csv | FileFormat::Parquet => {}
+      other => return Err(CoreError::UnsupportedFormat(other)),
+    }
+    if query.text.is_empty() {
+      return Err(CoreError::InvalidArg("query.text is empty".into()));
+    }
+    let id = Uuid::new_v4().to_string();
+    let state = Arc::new(TaskState::new(id.clone(), search_kind(&query)));
+    *state.search_hits.lock() = HitStore::new(self.opts.task_memory_budget_bytes);
+    state.timeout_ms.store(query.timeout_ms.unwrap_or(0), Ordering::SeqCst);
+    self.tasks.lock().insert(id.clone(), state.clone());
+
+    let utc_offset_minutes = self.opts.parquet_utc_offset_minutes;
+    self.dispatch(state, move |state| {
+      let count = parts.len() as u32;
+      let mut id_base = 0u64;
+      for (i, path) in parts.into_iter().enumerate() {
+        if state.should_stop() || state.truncated.load(Ordering::SeqCst) {
+          break;
+        }
+        let first_local = crate::shards::first_local_id(&format, i);
+        *state.scan_part.lock() = Some(ScanPart {
+          index: i as u32,
+          count,
+          id_base,
+          first_local,
+          bytes_base: state.bytes_done.load(Ordering::SeqCst),
+          records_base: state.records_done.load(Ordering::SeqCst),
+        });
+        let res = run_search_scan_all(
+          state,
+          path.clone(),
+          format.clone(),
+          TextEncoding::Utf8,
+          false,
+          query.clone(),
+          preview_max_chars,
+          None,
+          None,
+          None,
+          utc_offset_minutes,
+        )
+          .and_then(|_| match spans.get(i).copied().flatten() {
+            Some(span) => Ok(Some(span)),
+            None => crate::shards::count_span(&path, format.clone(), i, || state.should_stop())
+              .map_err(|e| TaskError::from(&e)),
+          });
+        match res {
+          Ok(Some(span)) => id_base += span,
+          Ok(None) => break,
+          Err(e) => {
+            *state.error.lock() = Some(e);
+            break;
+          }
+        }
+      }
+    });
+
+    Ok(StartedTask { id })
+  }
+
+  /// Whole-file stats in the background; cancelling or running past `timeout_ms` keeps the
+  /// partial profile.
+  pub(crate) fn start_stats(
+    &self,
+    path: PathBuf,
+    format: FileFormat,
+    encoding: TextEncoding,
+    csv: CsvDialect,
+    timeout_ms: Option<u64>,
+  ) -> Result<StartedTask, CoreError> {
+    match format {
+      FileFormat::Jsonl | FileFormat::Csv | FileFormat::Json | FileFormat::Parquet => {}
+      other => return Err(CoreError::UnsupportedFormat(other)),
+    }
+    let id = Uuid::new_v4().to_string();
+    let state = Arc::new(TaskState::new(id.clone(), TaskKind::Stats));
+    state.timeout_ms.store(timeout_ms.unwrap_or(0), Ordering::SeqCst);
+    self.tasks.lock().insert(id.clone(), state.clone());
+
+    self.dispatch(state, move |state| {
+      let res = stats_impl::compute_stats_until(
+        &path,
+        format,
+        encoding,
+        &csv,
+        || state.should_stop(),
+        |p| state.report(p),
+      );
+      match res {
+        Ok(stats) => *state.stats_result.lock() = Some(stats),
+        Err(e) => *state.error.lock() = Some(TaskError::from(&e)),
+      }
+    });
+
+    Ok(StartedTask { id })
+  }
+
+  /// Result of a finished stats task (`complete: false` if it was cancelled).
+  pub fn stats_task_result(&self, task_id: &str) -> Result<StatsResult, String> {
+    let t = self
+      .tasks
+      .lock()
+      .get(task_id)
+      .cloned()
+      .ok_or_else(|| "unknown task".to_string())?;
+    if t.kind != TaskKind::Stats {
+      return Err("task is not stats".into());
+    }
+    if !t.finished.load(Ordering::SeqCst) {
+      return Err("task still running".into());
+    }
+    if let Some(e) = t.error.lock().clone() {
+      return Err(e.to_string());
+    }
+    let result = t.stats_result.lock().clone();
+    result.ok_or_else(|| "task has no result".to_string())
+  }
+
+  /// Exact record count in the background (see `formats::count_records_with`).
+  pub(crate) fn start_count_records(
+    &self,
+    path: PathBuf,
+    format: FileFormat,
+    csv: CsvDialect,
+    json_lenient: bool,
+  ) -> Result<StartedTask, CoreError> {
+    match format {
+      FileFormat::Jsonl | FileFormat::Csv | FileFormat::Json | FileFormat::Parquet => {}
+      other => return Err(CoreError::UnsupportedFormat(other)),
+    }
+    let id = Uuid::new_v4().to_string();
+    let state = Arc::new(TaskState::new(id.clone(), TaskKind::CountRecords));
+    self.tasks.lock().insert(id.clone(), state.clone());
+
+    self.dispatch(state, move |state| {
+      let res = crate::formats::count_records_with(
+        &path,
+        format,
+        &csv,
+        json_lenient,
+        || state.should_stop(),
+        |p| state.report(p),
+      );
+      match res {
+        Ok(count) => *state.count_result.lock() = count,
+        Err(e) => *state.error.lock() = Some(TaskError::from(&e)),
+      }
+    });
+
+    Ok(StartedTask { id })
+  }
+
+  /// Record-by-record diff of two files in the background (see `diff::diff_records`).
+  pub(crate) fn start_diff(
+    &self,
+    left: (PathBuf, FileFormat),
+    right: (PathBuf, FileFormat),
+    align: DiffAlign,
+    preview_max_chars: usize,
+  ) -> Result<StartedTask, CoreError> {
+    for (_, format) in [&left, &right] {
+      match format {
+        FileFormat::Jsonl | FileFormat::Csv | FileFormat::Json | FileFormat::Parquet => {}
+        other => return Err(CoreError::UnsupportedFormat(other.clone())),
+      }
+    }
+    if matches!(&align, DiffAlign::ByKey { key } if key.is_empty()) {
+      return Err(CoreError::InvalidArg("diff key is empty".into()));
+    }
+    let id = Uuid::new_v4().to_string();
+    let state = Arc::new(TaskState::new(id.clone(), TaskKind::Diff));
+    self.tasks.lock().insert(id.clone(), state.clone());
+
+    self.dispatch(state, move |state| {
+      let res = diff_impl::diff_records(
+        DiffSide { path: &left.0, format: &left.1 },
+        DiffSide { path: &right.0, format: &right.1 },
+        &align,
+        preview_max_chars,
+        || state.should_stop(),
+        |p| state.report(p),
+      );
+      match res {
+        Ok(outcome) => *state.diff_result.lock() = Some(outcome),
+        Err(e) => *state.error.lock() = Some(TaskError::from(&e)),
+      }
+    });
+
+    Ok(StartedTask { id })
+  }
+
+  /// A page of a finished diff task's entries (a cancelled task pages what it found).
+  pub fn diff_task_page(&self, task_id: &str, cursor: Option<&str>, page_size: usize) -> Result<DiffPage, String> {
+    let t = self
+      .tasks
+      .lock()
+      .get(task_id)
+      .cloned()
+      .ok_or_else(|| "unknown task".to_string())?;
+    if t.kind != TaskKind::Diff {
+      return Err("task is not diff".into());
+    }
+    if !t.finished.load(Ordering::SeqCst) {
+      return Err("task still running".into());
+    }
+    if let Some(e) = t.error.lock().clone() {
+      return Err(e.to_string());
+    }
+
+    let idx = decode_index_cursor(cursor).map_err(|e| e.to_string())?.idx as usize;
+    let page_size = if page_size == 0 { 50 } else { page_size };
+
+    let result = t.diff_result.lock();
+    let outcome = result.as_ref().ok_or_else(|| "task has no result".to_string())?;
+    let entries: Vec<_> = outcome.entries.iter().skip(idx).take(page_size).cloned().collect();
+    let next_idx = idx + entries.len();
+    let reached_eof = next_idx >= outcome.entries.len();
+    let next_cursor = if reached_eof {
+      None
+    } else {
+      Some(encode_index_cursor(IndexCursor {
+        idx: next_idx as u64,
+      }))
+    };
+    Ok(DiffPage {
+      entries,
+      next_cursor,
+      reached_eof,
+      summary: outcome.summary.clone(),
+    })
+  }
+
+  /// Build a full line index in the background; `on_done` receives it unless cancelled.
+  pub(crate) fn start_line_index(
+    &self,
+    path: PathBuf,
+    format: FileFormat,
+    csv: CsvDialect,
+    on_done: Box<dyn FnOnce(LineIndex) + Send>,
+  ) -> Result<StartedTask, CoreError> {
+    match format {
+      FileFormat::Jsonl | FileFormat::Csv | FileFormat::Json => {}
+      other => return Err(CoreError::UnsupportedFormat(other)),
+    }
+    let id = Uuid::new_v4().to_string();
+    let state = Arc::new(TaskState::new(id.clone(), TaskKind::IndexBuild));
+    self.tasks.lock().insert(id.clone(), state.clone());
+
+    self.dispatch(state, move |state| {
+      let res = LineIndex::build(
+        &path,
+        format,
+        &csv,
+        || state.should_stop(),
+        |p| state.report(p),
+      );
+      match res {
+        Ok(Some(index)) => on_done(index),
+        Ok(None) => {}
+        Err(e) => *state.error.lock() = Some(TaskError::from(&e)),
+      }
+    });
+
+    Ok(StartedTask { id })
+  }
+
+  /// Index the containers of the JSON record at `record_offset` (`record_len` bytes) in the
+  /// background; `on_done` receives the index unless cancelled.
+  pub(crate) fn start_json_node_index(
+    &self,
+    path: PathBuf,
+    lenient: bool,
+    record_offset: u64,
+    record_len: u64,
+    on_done: Box<dyn FnOnce(JsonNodeIndex) + Send>,
+  ) -> Result<StartedTask, CoreError> {
+    let id = Uuid::new_v4().to_string();
+    let state = Arc::new(TaskState::new(id.clone(), TaskKind::IndexBuild));
+    self.tasks.lock().insert(id.clone(), state.clone());
+
+    self.dispatch(state, move |state| {
+      let res = crate::formats::index_json_record(
+        &path,
+        lenient,
+        record_offset,
+        record_len,
+        JSON_INDEX_MIN_NODE_BYTES,
+        || state.should_stop(),
+        |p| state.report(p),
+      );
+      match res {
+        Ok(Some(nodes)) => on_done(JsonNodeIndex::from_nodes(nodes)),
+        Ok(None) => {}
+        Err(e) => *state.error.lock() = Some(TaskError::from(&e)),
+      }
+    });
 
+    Ok(StartedTask { id })
+  }
+
+  /// Build the full-text index of `path` (see `TextIndex`) in the background; `on_done` receives
+  /// it unless cancelled.
+  pub(crate) fn start_text_index(
+    &self,
+    path: PathBuf,
+    encoding: TextEncoding,
+    on_done: Box<dyn FnOnce(TextIndex) + Send>,
+  ) -> Result<StartedTask, CoreError> {
+    let id = Uuid::new_v4().to_string();
+    let state = Arc::new(TaskState::new(id.clone(), TaskKind::IndexBuild));
+    self.tasks.lock().insert(id.clone(), state.clone());
+
+    self.dispatch(state, move |state| {
+      let res = TextIndex::build(&path, encoding, || state.should_stop(), |p| state.report(p));
+      match res {
+        Ok(Some(index)) => on_done(index),
+        Ok(None) => {}
+        Err(e) => *state.error.lock() = Some(TaskError::from(&e)),
+      }
+    });
+
+    Ok(StartedTask { id })
+  }
+
+  /// Extend `index` up to record `target` in the background (see `goto_record`); `on_done`
+  /// receives it unless cancelled.
+  pub(crate) fn start_line_index_extend(
+    &self,
+    path: PathBuf,
+    format: FileFormat,
+    mut index: LineIndex,
+    target: u64,
+    on_done: Box<dyn FnOnce(LineIndex) + Send>,
+  ) -> Result<StartedTask, CoreError> {
+    match format {
+      FileFormat::Jsonl | FileFormat::Csv | FileFormat::Json => {}
+      other => return Err(CoreError::UnsupportedFormat(other)),
+    }
     let id = Uuid::new_v4().to_string();
+    // The user is waiting on the jump, unlike the build started on open.
     let state = Arc::new(TaskState {
-      id: id.clone(),
-      kind: TaskKind::SearchScanAll,
-      started_at_ms: now_ms(),
-      cancellable: true,
-      progress: AtomicU8::new(0),
-      finished: AtomicBool::new(false),
-      cancelled: AtomicBool::new(false),
-      error: Mutex::new(None),
-      search_hits: Mutex::new(Vec::new()),
-      truncated: AtomicBool::new(false),
+      priority: TaskPriority::Normal,
+      ..TaskState::new(id.clone(), TaskKind::IndexBuild)
     });
     self.tasks.lock().insert(id.clone(), state.clone());
 
-    let tasks_map = self.tasks.clone();
-    let running = self.running.clone();
+    self.dispatch(state, move |state| {
+      let res = index.extend_to(
+        &path,
+        format,
+        target,
+        || state.should_stop(),
+        |p| state.report(p),
+      );
+      match res {
+        Ok(true) => on_done(index),
+        Ok(false) => {}
+        Err(e) => *state.error.lock() = Some(TaskError::from(&e)),
+      }
+    });
+
+    Ok(StartedTask { id })
+  }
+
+  /// Copy `input` (stdin, a pipe, a download) into `out` until EOF; `on_chunk` sees every chunk
+  /// once it is written. Not counted against `max_concurrent_tasks` since it runs as long as the
+  /// producer does. Cancelling takes effect after the read in progress returns.
+  pub(crate) fn start_spool(
+    &self,
+    mut input: impl Read + Send + 'static,
+    mut out: File,
+    mut on_chunk: impl FnMut(&[u8]) + Send + 'static,
+  ) -> Result<StartedTask, CoreError> {
+    let id = Uuid::new_v4().to_string();
+    let state = Arc::new(TaskState::new(id.clone(), TaskKind::Spool));
+    let _ = state.event_log.set(self.storage.clone());
+    self.tasks.lock().insert(id.clone(), state.clone());
 
     thread::spawn(move || {
-      let res = run_search_scan_all(&state, path, format, query, preview_max_chars);
-      if let Err(e) = res {
-        *state.error.lock() = Some(e);
+      state.run_started_ms.store(now_ms(), Ordering::SeqCst);
+      state.log_event(TaskEventKind::Started, None);
+      let mut buf = vec![0u8; 256 * 1024];
+      loop {
+        if state.should_stop() {
+          break;
+        }
+        let n = match input.read(&mut buf) {
+          Ok(0) => break,
+          Ok(n) => n,
+          Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+          Err(e) => {
+            *state.error.lock() = Some(e.into());
+            break;
+          }
+        };
+        if let Err(e) = out.write_all(&buf[..n]).and_then(|_| out.flush()) {
+          *state.error.lock() = Some(e.into());
+          break;
+        }
+        state.bytes_done.fetch_add(n as u64, Ordering::SeqCst);
+        on_chunk(&buf[..n]);
       }
-      state.finished.store(true, Ordering::SeqCst);
-      state.progress.store(100, Ordering::SeqCst);
-      running.fetch_sub(1, Ordering::SeqCst);
-
-      // Best-effort: drop finished tasks with no error & no hits? keep for now.
-      let _ = tasks_map;
+      state.mark_finished();
+      state.log_end();
     });
 
     Ok(StartedTask { id })
   }
 
+  /// `Ok(None)` while running or if the task was cancelled.
+  pub(crate) fn count_task_result(&self, task_id: &str) -> Result<Option<u64>, String> {
+    let t = self
+      .tasks
+      .lock()
+      .get(task_id)
+      .cloned()
+      .ok_or_else(|| "unknown task".to_string())?;
+    if t.kind != TaskKind::CountRecords {
+      return Err("task is not count_records".into());
+    }
+    if let Some(e) = t.error.lock().clone() {
+      return Err(e.to_string());
+    }
+    let result = *t.count_result.lock();
+    Ok(result)
+  }
+
+  pub(crate) fn is_task_finished(&self, task_id: &str) -> bool {
+    self
+      .tasks
+      .lock()
+      .get(task_id)
+      .map(|t| t.finished.load(Ordering::SeqCst))
+      .unwrap_or(true)
+  }
+
+  /// Runs `job` on a worker thread, then marks the task finished. While
+  /// `max_concurrent_tasks` tasks (of any kind) are running, the task is queued instead
+  /// (`Task.queued`) and started by the worker that finishes next: normal tasks first, each
+  /// priority in FIFO order.
+  fn dispatch(&self, state: Arc<TaskState>, job: impl FnOnce(&TaskState) + Send + 'static) {
+    if state.priority == TaskPriority::Background {
+      let _ = state.yield_to.set(self.interactive.clone());
+    }
+    let _ = state.event_log.set(self.storage.clone());
+    if self.shutting_down.load(Ordering::SeqCst) {
+      self.end_cancelled(&state);
+      return;
+    }
+    let first = QueuedJob {
+      state,
+      job: Box::new(job),
+    };
+    {
+      let mut slots = self.slots.lock();
+      if slots.running >= self.max_concurrent.load(Ordering::SeqCst) {
+        first.state.queued.store(true, Ordering::SeqCst);
+        slots.queue.push_back(first);
+        return;
+      }
+      slots.running += 1;
+    }
+    self.spawn_worker(first);
+  }
+
+  /// A worker holding one of the running slots: runs `first`, then queued jobs until the queue
+  /// is empty or the limit was lowered below the tasks running.
+  fn spawn_worker(&self, first: QueuedJob) {
+    let slots = self.slots.clone();
+    let max_concurrent = self.max_concurrent.clone();
+    let history = self.storage.clone();
+    let worker = thread::spawn(move || {
+      let mut next = Some(first);
+      while let Some(QueuedJob { state, job }) = next.take() {
+        state.run_started_ms.store(now_ms(), Ordering::SeqCst);
+        state.log_event(TaskEventKind::Started, None);
+        job(&state);
+        state.mark_finished();
+        state.log_end();
+        state.record_history(&history);
+
+        let mut slots = slots.lock();
+        if slots.running <= max_concurrent.load(Ordering::SeqCst) {
+          next = slots.pop_next();
+        }
+        match &next {
+          Some(queued) => queued.state.queued.store(false, Ordering::SeqCst),
+          None => slots.running -= 1,
+        }
+      }
+    });
+    let mut workers = self.workers.lock();
+    workers.retain(|w| !w.is_finished());
+    workers.push(worker);
+  }
+
+  /// Marks an interactive call in progress until the guard is dropped.
+  pub(crate) fn interactive(&self) -> CallGuard {
+    self.interactive.fetch_add(1, Ordering::SeqCst);
+    CallGuard(self.interactive.clone())
+  }
+
+  /// Marks an export in progress until the guard is dropped; fails once shut down.
+  pub(crate) fn export_started(&self) -> Result<CallGuard, String> {
+    self.exports.fetch_add(1, Ordering::SeqCst);
+    let guard = CallGuard(self.exports.clone());
+    if self.shutting_down.load(Ordering::SeqCst) {
+      return Err("shutting down".into());
+    }
+    Ok(guard)
+  }
+
+  pub(crate) fn shutdown_flag(&self) -> Arc<AtomicBool> {
+    self.shutting_down.clone()
+  }
+
+  /// Cancels every task (queued ones never start), waits for the workers to stop and for
+  /// exports in progress to give up. Tasks started afterwards end cancelled right away.
+  pub(crate) fn shutdown(&self) {
+    self.shutting_down.store(true, Ordering::SeqCst);
+    let queued: Vec<QueuedJob> = self.slots.lock().queue.drain(..).collect();
+    for QueuedJob { state, .. } in queued {
+      state.queued.store(false, Ordering::SeqCst);
+      self.end_cancelled(&state);
+    }
+    for t in self.tasks.lock().values() {
+      t.cancelled.store(true, Ordering::SeqCst);
+    }
+    let workers = std::mem::take(&mut *self.workers.lock());
+    for worker in workers {
+      let _ = worker.join();
+    }
+    while self.exports.load(Ordering::SeqCst) > 0 {
+      thread::sleep(YIELD_POLL);
+    }
+  }
+
+  fn end_cancelled(&self, state: &TaskState) {
+    state.cancelled.store(true, Ordering::SeqCst);
+    state.mark_finished();
+    state.log_end();
+    state.record_history(&self.storage);
+  }
+
+  pub(crate) fn max_concurrent_tasks(&self) -> usize {
+    self.max_concurrent.load(Ordering::SeqCst)
+  }
+
+  /// Raising the limit starts queued tasks right away; lowering it lets running tasks finish
+  /// and holds the queue until fewer than `max` are running.
+  pub(crate) fn set_max_concurrent_tasks(&self, max: usize) {
+    let max = max.max(1);
+    self.max_concurrent.store(max, Ordering::SeqCst);
+    loop {
+      let job = {
+        let mut slots = self.slots.lock();
+        if slots.running >= max {
+          return;
+        }
+        let Some(job) = slots.pop_next() else {
+          return;
+        };
+        job.state.queued.store(false, Ordering::SeqCst);
+        slots.running += 1;
+        job
+      };
+      self.spawn_worker(job);
+    }
+  }
+
   pub fn get_task(&self, task_id: &str) -> Result<Task, String> {
     let t = self
       .tasks
@@ -142,15 +1149,38 @@ impl TaskManager {
       .get(task_id)
       .cloned()
       .ok_or_else(|| "unknown task".to_string())?;
-    let err = t.error.lock().clone();
+    let finished = t.finished.load(Ordering::SeqCst);
+    let cancelled = finished && t.cancelled.load(Ordering::SeqCst);
+    let err = t.error.lock().clone().or(cancelled.then_some(TaskError::Cancelled));
+    let progress = t.progress.load(Ordering::SeqCst);
+    let bytes = t.bytes_done.load(Ordering::SeqCst);
+    let records = t.records_done.load(Ordering::SeqCst);
+    // Rates cover the time since the task left the queue (up to when it finished), less pauses.
+    let run_ended = if finished { t.finished_at_ms.load(Ordering::SeqCst) } else { now_ms() };
+    let elapsed_ms = t.run_ms(run_ended);
+    let paused = t.paused.load(Ordering::SeqCst);
+    let per_sec = |n: u64| (elapsed_ms > 0 && n > 0).then(|| n as f64 * 1000.0 / elapsed_ms as f64);
+    let eta_ms = (!finished && !paused && progress > 0 && elapsed_ms > 0)
+      .then(|| elapsed_ms * (100 - progress.min(100) as u64) / progress as u64);
     Ok(Task {
       id: t.id.clone(),
       kind: t.kind.clone(),
+      priority: t.priority,
       started_at_ms: t.started_at_ms,
       progress_0_100: t.progress.load(Ordering::SeqCst),
       cancellable: t.cancellable,
-      finished: t.finished.load(Ordering::SeqCst),
+      queued: t.queued.load(Ordering::SeqCst),
+      paused: paused && !finished,
+      finished,
       error: err,
+      timed_out: t.timed_out.load(Ordering::SeqCst),
+      from_cache: t.from_cache.load(Ordering::SeqCst),
+      bytes_processed: bytes,
+      records_processed: records,
+      hits_found: t.hits_found(),
+      bytes_per_sec: per_sec(bytes),
+      records_per_sec: per_sec(records),
+      eta_ms,
     })
   }
 
@@ -165,9 +1195,55 @@ impl TaskManager {
       return Err("task not cancellable".into());
     }
     t.cancelled.store(true, Ordering::SeqCst);
+    // A queued task gives up its place and finishes right away; its job never runs (it may do
+    // real work before its first `should_stop` check, and this is the caller's thread).
+    let queued = {
+      let mut slots = self.slots.lock();
+      let pos = slots.queue.iter().position(|q| q.state.id == t.id);
+      pos.and_then(|pos| slots.queue.remove(pos))
+    };
+    if let Some(QueuedJob { state, .. }) = queued {
+      state.queued.store(false, Ordering::SeqCst);
+      self.end_cancelled(&state);
+    }
+    Ok(())
+  }
+
+  /// Stops a running or queued task where it is until `resume_task`; it keeps its slot.
+  pub fn pause_task(&self, task_id: &str) -> Result<(), String> {
+    let t = self.pausable_task(task_id)?;
+    if !t.paused.swap(true, Ordering::SeqCst) {
+      t.paused_since_ms.store(now_ms(), Ordering::SeqCst);
+    }
     Ok(())
   }
 
+  pub fn resume_task(&self, task_id: &str) -> Result<(), String> {
+    let t = self.pausable_task(task_id)?;
+    if t.paused.load(Ordering::SeqCst) {
+      let since = t.paused_since_ms.load(Ordering::SeqCst);
+      t.paused_total_ms.fetch_add(now_ms() - since, Ordering::SeqCst);
+      t.paused.store(false, Ordering::SeqCst);
+    }
+    Ok(())
+  }
+
+  fn pausable_task(&self, task_id: &str) -> Result<Arc<TaskState>, String> {
+    let t = self
+      .tasks
+      .lock()
+      .get(task_id)
+      .cloned()
+      .ok_or_else(|| "unknown task".to_string())?;
+    if !t.cancellable {
+      return Err("task not pausable".into());
+    }
+    if t.finished.load(Ordering::SeqCst) {
+      return Err("task already finished".into());
+    }
+    Ok(t)
+  }
+
   pub fn search_task_hits_page(
     &self,
     task_id: &str,
@@ -187,25 +1263,30 @@ impl TaskManager {
     let idx = decode_index_cursor(cursor).map_err(|e| e.to_string())?.idx as usize;
     let page_size = if page_size == 0 { 50 } else { page_size };
 
-    let hits = t.search_hits.lock();
-    let slice = hits.iter().skip(idx).take(page_size);
+    let mut hits = t.search_hits.lock();
+    let total = hits.len();
+    let page = hits.page(idx, page_size).map_err(|e| e.to_string())?;
 
     let mut records = Vec::new();
-    for h in slice {
+    for h in page {
       records.push(Record {
-        id: h.line_no,
-        preview: h.preview.clone(),
+        id: h.id,
+        preview: h.preview,
         raw: None,
         meta: Some(RecordMeta {
           line_no: h.line_no,
           byte_offset: h.byte_offset,
           byte_len: h.byte_len,
+          part: h.part,
         }),
+        derived: None,
+        warning: None,
+        columns: None,
       });
     }
 
     let next_idx = idx + records.len();
-    let reached_eof = next_idx >= hits.len();
+    let reached_eof = next_idx >= total;
     let next_cursor = if reached_eof {
       None
     } else {
@@ -218,9 +1299,62 @@ impl TaskManager {
       records,
       next_cursor,
       reached_eof,
+      total_records: None,
+      ids_estimated: false,
     })
   }
 
+  pub(crate) fn search_match_count(&self, task_id: &str) -> Result<SearchCount, String> {
+    let t = self
+      .tasks
+      .lock()
+      .get(task_id)
+      .cloned()
+      .ok_or_else(|| "unknown task".to_string())?;
+    if t.kind != TaskKind::SearchCount {
+      return Err("task is not search_count".into());
+    }
+    if let Some(e) = t.error.lock().clone() {
+      return Err(e.to_string());
+    }
+    let complete = t.finished.load(Ordering::SeqCst)
+      && !t.cancelled.load(Ordering::SeqCst)
+      && !t.timed_out.load(Ordering::SeqCst);
+    Ok(SearchCount {
+      matches: t.match_count.load(Ordering::SeqCst),
+      complete,
+    })
+  }
+
+  /// Hits of a finished scan_all task as record metas (for filtered sessions).
+  pub(crate) fn search_task_hit_metas(&self, task_id: &str) -> Result<Vec<RecordMeta>, String> {
+    let t = self
+      .tasks
+      .lock()
+      .get(task_id)
+      .cloned()
+      .ok_or_else(|| "unknown task".to_string())?;
+    if t.kind != TaskKind::SearchScanAll {
+      return Err("task is not search_scan_all".into());
+    }
+    if !t.finished.load(Ordering::SeqCst) {
+      return Err("task is still running".into());
+    }
+    let mut metas = Vec::new();
+    t.search_hits
+      .lock()
+      .for_each(|h| {
+        metas.push(RecordMeta {
+          line_no: h.line_no,
+          byte_offset: h.byte_offset,
+          byte_len: h.byte_len,
+          part: h.part,
+        })
+      })
+      .map_err(|e| e.to_string())?;
+    Ok(metas)
+  }
+
   pub(crate) fn get_search_task_hit_ids(&self, task_id: &str) -> Result<Vec<u64>, String> {
     let t = self
       .tasks
@@ -231,150 +1365,258 @@ impl TaskManager {
     if t.kind != TaskKind::SearchScanAll {
       return Err("task is not search_scan_all".into());
     }
-    let hits = t.search_hits.lock();
-    Ok(hits.iter().map(|h| h.line_no).collect())
+    let mut ids = Vec::new();
+    t.search_hits.lock().for_each(|h| ids.push(h.id)).map_err(|e| e.to_string())?;
+    Ok(ids)
   }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn run_search_scan_all(
   state: &TaskState,
   path: PathBuf,
   format: FileFormat,
+  encoding: TextEncoding,
+  json_lenient: bool,
   query: SearchQuery,
   preview_max_chars: usize,
-) -> Result<(), String> {
+  derived: Option<&LineDeriver>,
+  filters: Option<&[ColumnFilter]>,
+  decryption_key: Option<&str>,
+  utc_offset_minutes: i32,
+) -> Result<(), TaskError> {
   match format {
-    FileFormat::Jsonl | FileFormat::Csv => run_search_scan_all_lines(state, path, query, preview_max_chars),
-    FileFormat::Json => run_search_scan_all_json_root_array(state, path, query, preview_max_chars),
-    FileFormat::Parquet => run_search_scan_all_parquet(state, path, query, preview_max_chars),
-    other => Err(format!("unsupported format for scan_all: {other:?}")),
+    FileFormat::Jsonl | FileFormat::Csv => {
+      run_search_scan_all_lines(state, path, encoding, query, preview_max_chars, derived)
+    }
+    FileFormat::Json => run_search_scan_all_json(state, path, json_lenient, query, preview_max_chars),
+    FileFormat::Parquet => {
+      run_search_scan_all_parquet(state, path, query, preview_max_chars, filters, decryption_key, utc_offset_minutes)
+    }
+    other => Err(TaskError::Format {
+      message: format!("unsupported format for scan_all: {other:?}"),
+    }),
   }
 }
 
 fn run_search_scan_all_lines(
   state: &TaskState,
   path: PathBuf,
+  encoding: TextEncoding,
   query: SearchQuery,
   preview_max_chars: usize,
-) -> Result<(), String> {
-  let mut file = File::open(&path).map_err(|e| e.to_string())?;
+  derived: Option<&LineDeriver>,
+) -> Result<(), TaskError> {
+  let mut file = File::open(&path)?;
   let file_len = file.metadata().ok().map(|m| m.len()).unwrap_or(0);
-  file.seek(SeekFrom::Start(0)).map_err(|e| e.to_string())?;
+  file.seek(SeekFrom::Start(0))?;
   let mut reader = BufReader::new(file);
 
-  let prepared = PreparedSearch::new(&query).ok_or_else(|| "query.text is empty".to_string())?;
+  let prepared = PreparedSearch::new(&query).ok_or_else(|| TaskError::Format {
+    message: "query.text is empty".into(),
+  })?;
 
   let mut offset = 0u64;
   let mut line_no = 0u64;
   loop {
-    if state.cancelled.load(Ordering::SeqCst) {
-      state.finished.store(true, Ordering::SeqCst);
+    if state.should_stop() {
       return Ok(());
     }
 
     let start_offset = offset;
-    let mut buf = Vec::new();
-    let n = reader.read_until(b'\n', &mut buf).map_err(|e| e.to_string())?;
-    if n == 0 {
+    let Some((n, line)) = formats::read_search_line(&mut reader, encoding).map_err(|e| TaskError::from(&e))? else {
       break;
-    }
-    offset += n as u64;
+    };
+    offset += n;
 
-    if buf.ends_with(b"\n") {
-      buf.pop();
-      if buf.ends_with(b"\r") {
-        buf.pop();
-      }
-    }
-    let line = String::from_utf8_lossy(&buf).to_string();
-    let hay = if query.case_sensitive {
-      line.clone()
-    } else {
-      line.to_lowercase()
+    let text = match derived {
+      Some(derived) => format!("{line}\n{}", derived.search_text(line_no, &line)),
+      None => line.clone(),
     };
+    let hay = if query.case_sensitive { text } else { text.to_lowercase() };
 
     if prepared.matches_in_hay(&hay) {
       push_hit(state, &query, SearchHit {
+        id: line_no,
         line_no,
+        part: None,
         byte_offset: start_offset,
-        byte_len: n as u64,
+        byte_len: n,
         preview: truncate_chars(&line, preview_max_chars),
-      });
+      })?;
     }
 
     line_no += 1;
-    if file_len > 0 {
-      let p = ((offset as f64 / file_len as f64) * 100.0).floor() as i32;
-      let p = p.clamp(0, 99) as u8;
-      state.progress.store(p, Ordering::SeqCst);
+    set_scan_progress(state, ScanProgress::new(offset, file_len, offset, line_no));
+  }
+  Ok(())
+}
+
+/// Reads the candidate blocks of `index` in file order and matches their lines like
+/// `run_search_scan_all_lines` (without derived columns).
+fn run_search_indexed(
+  state: &TaskState,
+  path: &Path,
+  encoding: TextEncoding,
+  query: &SearchQuery,
+  prepared: &PreparedSearch,
+  preview_max_chars: usize,
+  index: &TextIndex,
+) -> Result<(), TaskError> {
+  let blocks = index.candidate_blocks(prepared);
+  let mut reader = BufReader::new(File::open(path)?);
+  let mut lines_read = 0u64;
+  for (i, &block) in blocks.iter().enumerate() {
+    if state.should_stop() {
+      return Ok(());
     }
+    let (mut offset, mut line_no) = index.block_start(block);
+    reader.seek(SeekFrom::Start(offset))?;
+    for _ in 0..TEXT_INDEX_BLOCK_LINES {
+      let Some((n, line)) = formats::read_search_line(&mut reader, encoding).map_err(|e| TaskError::from(&e))? else {
+        break;
+      };
+      let hay = if query.case_sensitive { line.clone() } else { line.to_lowercase() };
+      if prepared.matches_in_hay(&hay) {
+        push_hit(state, query, SearchHit {
+          id: line_no,
+          line_no,
+          part: None,
+          byte_offset: offset,
+          byte_len: n,
+          preview: truncate_chars(&line, preview_max_chars),
+        })?;
+      }
+      offset += n;
+      line_no += 1;
+      lines_read += 1;
+    }
+    set_scan_progress(state, ScanProgress::new(i as u64 + 1, blocks.len() as u64, offset, lines_read));
   }
   Ok(())
 }
 
-fn push_hit(state: &TaskState, query: &SearchQuery, hit: SearchHit) {
+/// Index builds and stats run in the background: they can wait on everything else.
+fn priority_of(kind: &TaskKind) -> TaskPriority {
+  match kind {
+    TaskKind::IndexBuild | TaskKind::Stats => TaskPriority::Background,
+    _ => TaskPriority::Normal,
+  }
+}
+
+fn search_kind(query: &SearchQuery) -> TaskKind {
+  match query.mode {
+    SearchMode::CountOnly => TaskKind::SearchCount,
+    _ => TaskKind::SearchScanAll,
+  }
+}
+
+/// Saves the hits of a scan that got to the end of the file (not cancelled or timed out).
+fn save_scan_cache(state: &TaskState, storage: &Storage, key: &ScanCacheKey) {
+  if state.cancelled.load(Ordering::SeqCst) || state.timed_out.load(Ordering::SeqCst) {
+    return;
+  }
+  let mut hits = Vec::new();
+  {
+    let mut store = state.search_hits.lock();
+    if store.len() > SCAN_CACHE_MAX_HITS || store.for_each(|h| hits.push(h.clone())).is_err() {
+      return;
+    }
+  }
+  if let Ok(hits_json) = serde_json::to_string(&hits) {
+    let truncated = state.truncated.load(Ordering::SeqCst);
+    let _ = storage.save_scan_cache(&key.path, key.file_size, key.file_mtime_ms, &key.query_key, &hits_json, truncated);
+  }
+}
+
+fn push_hit(state: &TaskState, query: &SearchQuery, mut hit: SearchHit) -> Result<(), TaskError> {
+  if let Some(part) = *state.scan_part.lock() {
+    if hit.line_no < part.first_local {
+      return Ok(());
+    }
+    hit.id = part.id_base + hit.line_no - part.first_local;
+    hit.part = Some(part.index);
+  }
+  state.match_count.fetch_add(1, Ordering::SeqCst);
+  if state.kind == TaskKind::SearchCount {
+    return Ok(());
+  }
   let mut hits = state.search_hits.lock();
   if (hits.len() as u64) < query.max_hits {
-    hits.push(hit);
+    hits.push(hit)?;
   } else {
     state.truncated.store(true, Ordering::SeqCst);
   }
+  Ok(())
 }
 
-fn run_search_scan_all_json_root_array(
+/// Per-file progress, scaled to the whole scan for multi-file sessions.
+fn set_scan_progress(state: &TaskState, p: ScanProgress) {
+  let p = match *state.scan_part.lock() {
+    Some(part) => ScanProgress {
+      pct: ((part.index as u64 * 100 + p.pct as u64) / part.count.max(1) as u64).min(99) as u8,
+      bytes: part.bytes_base + p.bytes,
+      records: part.records_base + p.records,
+    },
+    None => p,
+  };
+  state.report(p);
+}
+
+/// Records as paging reads them: the elements of a root array, otherwise each top-level value
+/// (a single root object, or whitespace-separated values) in turn. Values are read leniently
+/// with `lenient` (see `formats::JsonSource`).
+fn run_search_scan_all_json(
   state: &TaskState,
   path: PathBuf,
+  lenient: bool,
   query: SearchQuery,
   preview_max_chars: usize,
-) -> Result<(), String> {
+) -> Result<(), TaskError> {
   const MAX_JSON_VALUE_BYTES: usize = 50 * 1024 * 1024; // keep consistent with get_record_raw safety cap
 
-  let mut file = File::open(&path).map_err(|e| e.to_string())?;
+  let mut file = File::open(&path)?;
   let file_len = file.metadata().ok().map(|m| m.len()).unwrap_or(0);
-  file.seek(SeekFrom::Start(0)).map_err(|e| e.to_string())?;
-  let mut reader = BufReader::with_capacity(1024 * 1024, file);
+  file.seek(SeekFrom::Start(0))?;
+  let mut reader = BufReader::with_capacity(1024 * 1024, JsonSource::new(file, lenient)?);
 
-  let prepared = PreparedSearch::new(&query).ok_or_else(|| "query.text is empty".to_string())?;
+  let prepared = PreparedSearch::new(&query).ok_or_else(|| TaskError::Format {
+    message: "query.text is empty".into(),
+  })?;
 
   let mut abs: u64 = 0;
   // Skip BOM + whitespace
-  skip_bom_and_ws(&mut reader, &mut abs).map_err(|e| e.to_string())?;
-  // Enforce root array
-  match peek_byte(&mut reader).map_err(|e| e.to_string())? {
-    Some(b'[') => {
-      consume_one(&mut reader, &mut abs).map_err(|e| e.to_string())?;
-    }
-    _ => {
-      return Err("scan_all for .json only supports root array: file must start with '[' (after BOM/whitespace)".into());
-    }
+  skip_bom_and_ws(&mut reader, &mut abs)?;
+  // Optional root array; without one the top-level values are the records.
+  if peek_byte(&mut reader)? == Some(b'[') {
+    consume_one(&mut reader, &mut abs)?;
   }
-  skip_ws_and_nul(&mut reader, &mut abs).map_err(|e| e.to_string())?;
+  skip_ws_and_nul(&mut reader, &mut abs)?;
 
   // Empty array => done
-  if peek_byte(&mut reader).map_err(|e| e.to_string())? == Some(b']') {
+  if peek_byte(&mut reader)? == Some(b']') {
     return Ok(());
   }
 
   let mut idx: u64 = 0;
   loop {
-    if state.cancelled.load(Ordering::SeqCst) {
-      state.finished.store(true, Ordering::SeqCst);
+    if state.should_stop() {
       return Ok(());
     }
 
-    skip_ws_and_nul(&mut reader, &mut abs).map_err(|e| e.to_string())?;
-    if peek_byte(&mut reader).map_err(|e| e.to_string())? == Some(b',') {
-      consume_one(&mut reader, &mut abs).map_err(|e| e.to_string())?;
+    skip_ws_and_nul(&mut reader, &mut abs)?;
+    if peek_byte(&mut reader)? == Some(b',') {
+      consume_one(&mut reader, &mut abs)?;
       continue;
     }
-    match peek_byte(&mut reader).map_err(|e| e.to_string())? {
+    match peek_byte(&mut reader)? {
       Some(b']') | None => break,
       _ => {}
     }
 
     let start_offset = abs;
     let (value_bytes, value_len) =
-      scan_one_json_value_full(&mut reader, &mut abs, MAX_JSON_VALUE_BYTES).map_err(|e| e.to_string())?;
+      scan_one_json_value_full(&mut reader, &mut abs, MAX_JSON_VALUE_BYTES)?;
 
     let text = String::from_utf8_lossy(&value_bytes).to_string();
     let hay = if query.case_sensitive {
@@ -387,28 +1629,26 @@ fn run_search_scan_all_json_root_array(
         state,
         &query,
         SearchHit {
+          id: idx,
           line_no: idx,
+          part: None,
           byte_offset: start_offset,
           byte_len: value_len as u64,
           preview: truncate_chars(&text, preview_max_chars),
         },
-      );
+      )?;
     }
 
     idx += 1;
 
     // Progress by bytes read (best-effort)
-    if file_len > 0 {
-      let p = ((abs as f64 / file_len as f64) * 100.0).floor() as i32;
-      let p = p.clamp(0, 99) as u8;
-      state.progress.store(p, Ordering::SeqCst);
-    }
+    set_scan_progress(state, ScanProgress::new(abs, file_len, abs, idx));
 
     // After value: whitespace, comma or closing bracket.
-    skip_ws_and_nul(&mut reader, &mut abs).map_err(|e| e.to_string())?;
-    match peek_byte(&mut reader).map_err(|e| e.to_string())? {
+    skip_ws_and_nul(&mut reader, &mut abs)?;
+    match peek_byte(&mut reader)? {
       Some(b',') => {
-        consume_one(&mut reader, &mut abs).map_err(|e| e.to_string())?;
+        consume_one(&mut reader, &mut abs)?;
       }
       Some(b']') => break,
       None => break,
@@ -424,110 +1664,59 @@ fn run_search_scan_all_parquet(
   path: PathBuf,
   query: SearchQuery,
   preview_max_chars: usize,
-) -> Result<(), String> {
-  let prepared = PreparedSearch::new(&query).ok_or_else(|| "query.text is empty".to_string())?;
-
-  let path_str = path
-    .to_str()
-    .ok_or_else(|| "invalid path encoding".to_string())?
-    .to_string();
-
-  let conn = duckdb::Connection::open_in_memory()
-    .map_err(|e| format!("DuckDB 初始化失败：{e}"))?;
-  let _ = conn.execute_batch("LOAD parquet;");
-
-  // Best-effort total row count for progress.
-  let total_rows: u64 = conn
-    .query_row(
-      "SELECT count(*) FROM read_parquet(?)",
-      duckdb::params![path_str.as_str()],
-      |r| r.get::<usize, i64>(0),
-    )
-    .map(|n| n.max(0) as u64)
-    .unwrap_or(0);
-
-  const CHUNK: u64 = 2048;
-  let mut offset: u64 = 0;
-
-  loop {
-    if state.cancelled.load(Ordering::SeqCst) {
-      state.finished.store(true, Ordering::SeqCst);
-      return Ok(());
-    }
-    if state.truncated.load(Ordering::SeqCst) {
-      break;
-    }
-
-    let limit_i64 = i64::try_from(CHUNK).map_err(|_| "invalid parquet chunk size".to_string())?;
-    let offset_i64 = i64::try_from(offset).map_err(|_| "invalid parquet offset".to_string())?;
-
-    let mut stmt = conn
-      .prepare("SELECT * FROM read_parquet(?) LIMIT ? OFFSET ?")
-      .map_err(|e| format!("DuckDB 准备语句失败：{e}"))?;
-
-    let mut rows = stmt
-      .query(duckdb::params![path_str.as_str(), limit_i64, offset_i64])
-      .map_err(|e| format!("Parquet 读取失败：{e}"))?;
-
-    let mut got_any = false;
-    let mut row_idx = offset;
-    while let Some(row) = rows.next().map_err(|e| format!("Parquet 读取失败：{e}"))? {
-      got_any = true;
-      if state.cancelled.load(Ordering::SeqCst) {
-        state.finished.store(true, Ordering::SeqCst);
-        return Ok(());
-      }
-
-      let col_count = row.as_ref().column_count();
-      let mut cols = Vec::with_capacity(col_count);
-      for i in 0..col_count {
-        let v: duckdb::types::Value = row
-          .get(i)
-          .map_err(|e| format!("Parquet 读取失败：{e}"))?;
-        cols.push(sanitize_cell(&value_to_string(&v)));
+  filters: Option<&[ColumnFilter]>,
+  decryption_key: Option<&str>,
+  utc_offset_minutes: i32,
+) -> Result<(), TaskError> {
+  let prepared = PreparedSearch::new(&query).ok_or_else(|| TaskError::Format {
+    message: "query.text is empty".into(),
+  })?;
+
+  let mut conn = crate::formats::ParquetConn::open(&path)
+    .map_err(|e| TaskError::from(&e))?
+    .with_utc_offset(utc_offset_minutes);
+  if let Some(key) = decryption_key {
+    conn = conn.with_decryption_key(key).map_err(|e| TaskError::from(&e))?;
+  }
+  // Best-effort total row count for progress (row ids still run to it when filtered).
+  let total_rows = conn.row_count().unwrap_or(0);
+
+  let mut result = Ok(());
+  conn
+    .for_each_line(0, filters, |row_idx, line| {
+      if state.should_stop() {
+        return false;
       }
-      let line = cols.join("\t");
       let hay = if query.case_sensitive {
         line.clone()
       } else {
         line.to_lowercase()
       };
       if prepared.matches_in_hay(&hay) {
-        push_hit(
-          state,
-          &query,
-          SearchHit {
-            line_no: row_idx,
-            byte_offset: row_idx, // not a real byte offset; kept for backwards-compat meta shape
-            byte_len: 0,
-            preview: truncate_chars(&line, preview_max_chars),
-          },
-        );
-      }
-
-      row_idx += 1;
-      if total_rows > 0 {
-        let p = (((row_idx.min(total_rows)) as f64 / total_rows as f64) * 100.0).floor() as i32;
-        let p = p.clamp(0, 99) as u8;
-        state.progress.store(p, Ordering::SeqCst);
-      }
-      if state.truncated.load(Ordering::SeqCst) {
-        break;
+        let hit = SearchHit {
+          id: row_idx,
+          line_no: row_idx,
+          part: None,
+          byte_offset: row_idx, // not a real byte offset; kept for backwards-compat meta shape
+          byte_len: 0,
+          preview: truncate_chars(&line, preview_max_chars),
+        };
+        if let Err(e) = push_hit(state, &query, hit) {
+          result = Err(e);
+          return false;
+        }
       }
-    }
-
-    if !got_any {
-      break;
-    }
-    offset += CHUNK;
-  }
-
-  Ok(())
+      let done = row_idx + 1;
+      set_scan_progress(state, ScanProgress::new(done.min(total_rows), total_rows, 0, done));
+      !state.truncated.load(Ordering::SeqCst)
+    })
+    .map_err(|e| TaskError::from(&e))?;
+  result
 }
 
 // ---------------- JSON scanning helpers (root-array only) ----------------
 
-fn peek_byte(reader: &mut BufReader<File>) -> Result<Option<u8>, std::io::Error> {
+fn peek_byte(reader: &mut BufReader<JsonSource>) -> Result<Option<u8>, std::io::Error> {
   let buf = reader.fill_buf()?;
   if buf.is_empty() {
     Ok(None)
@@ -536,7 +1725,7 @@ fn peek_byte(reader: &mut BufReader<File>) -> Result<Option<u8>, std::io::Error>
   }
 }
 
-fn consume_one(reader: &mut BufReader<File>, abs: &mut u64) -> Result<u8, std::io::Error> {
+fn consume_one(reader: &mut BufReader<JsonSource>, abs: &mut u64) -> Result<u8, std::io::Error> {
   let mut buf = [0u8; 1];
   let n = reader.read(&mut buf)?;
   if n == 0 {
@@ -546,7 +1735,7 @@ fn consume_one(reader: &mut BufReader<File>, abs: &mut u64) -> Result<u8, std::i
   Ok(buf[0])
 }
 
-fn skip_bom_and_ws(reader: &mut BufReader<File>, abs: &mut u64) -> Result<(), std::io::Error> {
+fn skip_bom_and_ws(reader: &mut BufReader<JsonSource>, abs: &mut u64) -> Result<(), std::io::Error> {
   // UTF-8 BOM: EF BB BF
   let buf = reader.fill_buf()?;
   if buf.len() >= 3 && buf[0] == 0xEF && buf[1] == 0xBB && buf[2] == 0xBF {
@@ -557,7 +1746,7 @@ fn skip_bom_and_ws(reader: &mut BufReader<File>, abs: &mut u64) -> Result<(), st
   Ok(())
 }
 
-fn skip_ws_and_nul(reader: &mut BufReader<File>, abs: &mut u64) -> Result<(), std::io::Error> {
+fn skip_ws_and_nul(reader: &mut BufReader<JsonSource>, abs: &mut u64) -> Result<(), std::io::Error> {
   loop {
     match peek_byte(reader)? {
       Some(b) if b == 0 || b.is_ascii_whitespace() => {
@@ -570,10 +1759,10 @@ fn skip_ws_and_nul(reader: &mut BufReader<File>, abs: &mut u64) -> Result<(), st
 }
 
 fn scan_one_json_value_full(
-  reader: &mut BufReader<File>,
+  reader: &mut BufReader<JsonSource>,
   abs: &mut u64,
   max_bytes: usize,
-) -> Result<(Vec<u8>, usize), std::io::Error> {
+) -> Result<(Vec<u8>, usize), TaskError> {
   let mut out: Vec<u8> = Vec::new();
   let mut total_len: usize = 0;
 
@@ -586,7 +1775,14 @@ fn scan_one_json_value_full(
     let b = match peek_byte(reader)? {
       None => {
         if !started {
-          return Err(std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "EOF before value"));
+          return Err(TaskError::Format {
+            message: "EOF before value".into(),
+          });
+        }
+        if in_string || depth > 0 {
+          return Err(TaskError::Format {
+            message: format!("EOF inside json value at byte {}", *abs),
+          });
         }
         break;
       }
@@ -594,10 +1790,9 @@ fn scan_one_json_value_full(
     };
     total_len += 1;
     if total_len > max_bytes {
-      return Err(std::io::Error::new(
-        std::io::ErrorKind::InvalidData,
-        format!("json value too large: {} bytes (max {})", total_len, max_bytes),
-      ));
+      return Err(TaskError::LimitExceeded {
+        message: format!("json value too large: {} bytes (max {})", total_len, max_bytes),
+      });
     }
     out.push(b);
 
@@ -629,20 +1824,16 @@ fn scan_one_json_value_full(
     match b {
       b'"' => in_string = true,
       b'{' | b'[' => depth += 1,
-      b'}' | b']' => {
-        if depth > 0 {
-          depth -= 1;
-        }
+      b'}' | b']' if depth > 0 => {
+        depth -= 1;
       }
-      b',' => {
-        if depth == 0 {
-          // delimiter ends value; unread by stepping back one byte
-          reader.seek(SeekFrom::Current(-1))?;
-          *abs -= 1;
-          out.pop();
-          total_len -= 1;
-          break;
-        }
+      b',' if depth == 0 => {
+        // delimiter ends value; unread by stepping back one byte
+        reader.seek(SeekFrom::Current(-1))?;
+        *abs -= 1;
+        out.pop();
+        total_len -= 1;
+        break;
       }
       _ => {}
     }
@@ -662,62 +1853,6 @@ fn scan_one_json_value_full(
   Ok((out, total_len))
 }
 
-fn sanitize_cell(s: &str) -> String {
-  s.replace(&['\n', '\r', '\t'][..], " ")
-}
-
-fn value_to_string(v: &duckdb::types::Value) -> String {
-  use duckdb::types::Value;
-  match v {
-    Value::Null => "null".into(),
-    Value::Boolean(b) => b.to_string(),
-    Value::TinyInt(x) => x.to_string(),
-    Value::SmallInt(x) => x.to_string(),
-    Value::Int(x) => x.to_string(),
-    Value::BigInt(x) => x.to_string(),
-    Value::HugeInt(x) => x.to_string(),
-    Value::UTinyInt(x) => x.to_string(),
-    Value::USmallInt(x) => x.to_string(),
-    Value::UInt(x) => x.to_string(),
-    Value::UBigInt(x) => x.to_string(),
-    Value::Float(x) => x.to_string(),
-    Value::Double(x) => x.to_string(),
-    Value::Decimal(d) => d.to_string(),
-    Value::Timestamp(unit, v) => format!("timestamp({unit:?},{v})"),
-    Value::Text(s) => s.clone(),
-    Value::Blob(b) => {
-      use base64::Engine as _;
-      let encoded = base64::engine::general_purpose::STANDARD.encode(b);
-      format!("blob(base64:{encoded})")
-    }
-    Value::Date32(days) => format!("date32({days})"),
-    Value::Time64(unit, v) => format!("time64({unit:?},{v})"),
-    Value::Interval { months, days, nanos } => format!("interval({months}m,{days}d,{nanos}n)"),
-    Value::List(xs) | Value::Array(xs) => {
-      let inner = xs.iter().map(value_to_string).collect::<Vec<_>>().join(", ");
-      format!("[{inner}]")
-    }
-    Value::Enum(s) => s.clone(),
-    Value::Struct(map) => {
-      let inner = map
-        .iter()
-        .map(|(k, v)| format!("{k}: {}", value_to_string(v)))
-        .collect::<Vec<_>>()
-        .join(", ");
-      format!("{{{inner}}}")
-    }
-    Value::Map(map) => {
-      let inner = map
-        .iter()
-        .map(|(k, v)| format!("{}: {}", value_to_string(k), value_to_string(v)))
-        .collect::<Vec<_>>()
-        .join(", ");
-      format!("{{{inner}}}")
-    }
-    Value::Union(v) => value_to_string(v),
-  }
-}
-
 fn truncate_chars(s: &str, max: usize) -> String {
   if max == 0 {
     return String::new();
@@ -725,7 +1860,7 @@ fn truncate_chars(s: &str, max: usize) -> String {
   let mut out = String::new();
   for (i, ch) in s.chars().enumerate() {
     if i >= max {
-      out.push_str("…");
+      out.push('…');
       break;
     }
     out.push(ch);
@@ -749,7 +1884,7 @@ fn encode_index_cursor(c: IndexCursor) -> String {
 fn decode_index_cursor(token: Option<&str>) -> Result<IndexCursor, CoreError> {
   match token {
     None => Ok(IndexCursor { idx: 0 }),
-    Some(t) if t.is_empty() => Ok(IndexCursor { idx: 0 }),
+    Some("") => Ok(IndexCursor { idx: 0 }),
     Some(t) => {
       use base64::Engine as _;
       let bytes = base64::engine::general_purpose::URL_SAFE_NO_PAD
@@ -0,0 +1,219 @@
+use std::{
+  collections::{HashMap, HashSet},
+  fs::File,
+  io::BufReader,
+  path::Path,
+};
+
+use crate::{engine::CoreError, formats, models::TextEncoding, progress::ScanProgress, search_match::PreparedSearch};
+
+/// Lines per block: the unit the index points to, and what an indexed search reads per candidate.
+pub(crate) const TEXT_INDEX_BLOCK_LINES: u64 = 64;
+
+const FNV_OFFSET: u32 = 0x811c_9dc5;
+const FNV_PRIME: u32 = 0x0100_0193;
+
+/// Full-text index of JSONL / CSV lines, for `SearchMode::Indexed`.
+///
+/// Lines are grouped in blocks of `TEXT_INDEX_BLOCK_LINES`; for every trigram (3 chars of the
+/// case-folded line) the index lists the blocks it occurs in. A search reads only the blocks that
+/// hold every trigram of what it looks for and matches their lines as scan_all does, so both find
+/// the same lines. Built by `build`; persisted per file version (see `Storage::save_text_index`).
+#[derive(Debug, Clone, Default)]
+pub(crate) struct TextIndex {
+  /// `blocks[i]` = byte offset of line `i * TEXT_INDEX_BLOCK_LINES`.
+  blocks: Vec<u64>,
+  /// Blocks each trigram (by `trigram_hash`) occurs in, ascending.
+  postings: HashMap<u32, Vec<u32>>,
+  /// Trigrams in more than half of the blocks: left out of `postings`, as they rule little out.
+  common: HashSet<u32>,
+}
+
+/// Chars a line is indexed (and a needle looked up) by: lowercased one by one, with the final
+/// sigma folded into `σ` so that it doesn't depend on the char after it.
+fn folded(text: &str) -> Vec<char> {
+  text
+    .chars()
+    .flat_map(char::to_lowercase)
+    .map(|c| if c == 'ς' { 'σ' } else { c })
+    .collect()
+}
+
+/// FNV-1a of three chars, so it is stable across runs and builds.
+fn trigram_hash(gram: &[char]) -> u32 {
+  let mut hash = FNV_OFFSET;
+  for c in gram {
+    for b in (*c as u32).to_le_bytes() {
+      hash ^= b as u32;
+      hash = hash.wrapping_mul(FNV_PRIME);
+    }
+  }
+  hash
+}
+
+fn trigrams(text: &str) -> Vec<u32> {
+  folded(text).windows(3).map(trigram_hash).collect()
+}
+
+/// Ids in both ascending lists.
+fn intersect(a: &[u32], b: &[u32]) -> Vec<u32> {
+  let (mut i, mut j, mut out) = (0, 0, Vec::new());
+  while i < a.len() && j < b.len() {
+    match a[i].cmp(&b[j]) {
+      std::cmp::Ordering::Less => i += 1,
+      std::cmp::Ordering::Greater => j += 1,
+      std::cmp::Ordering::Equal => {
+        out.push(a[i]);
+        i += 1;
+        j += 1;
+      }
+    }
+  }
+  out
+}
+
+impl TextIndex {
+  /// Index every line of `path` (decoded with `encoding`). Returns `None` if `should_stop` fired
+  /// before EOF.
+  pub(crate) fn build(
+    path: &Path,
+    encoding: TextEncoding,
+    should_stop: impl Fn() -> bool,
+    mut on_progress: impl FnMut(ScanProgress),
+  ) -> Result<Option<Self>, CoreError> {
+    let file = File::open(path)?;
+    let file_len = file.metadata()?.len();
+    let mut reader = BufReader::new(file);
+    let mut index = Self::default();
+    let mut block = HashSet::new();
+    let mut offset = 0u64;
+    let mut lines = 0u64;
+    while let Some((n, line)) = formats::read_search_line(&mut reader, encoding)? {
+      if lines.is_multiple_of(TEXT_INDEX_BLOCK_LINES) {
+        if should_stop() {
+          return Ok(None);
+        }
+        index.close_block(&mut block);
+        index.blocks.push(offset);
+        on_progress(ScanProgress::new(offset, file_len, offset, lines));
+      }
+      block.extend(trigrams(&line));
+      offset += n;
+      lines += 1;
+    }
+    index.close_block(&mut block);
+    let half = index.blocks.len() / 2;
+    let common: Vec<u32> = index.postings.iter().filter(|(_, b)| b.len() > half).map(|(g, _)| *g).collect();
+    for gram in common {
+      index.postings.remove(&gram);
+      index.common.insert(gram);
+    }
+    Ok(Some(index))
+  }
+
+  /// Adds the trigrams of the last block in `blocks` to `postings`.
+  fn close_block(&mut self, grams: &mut HashSet<u32>) {
+    let Some(block) = self.blocks.len().checked_sub(1) else {
+      return;
+    };
+    for gram in grams.drain() {
+      self.postings.entry(gram).or_default().push(block as u32);
+    }
+  }
+
+  /// Byte offset and line number of the first line of `block`.
+  pub(crate) fn block_start(&self, block: u32) -> (u64, u64) {
+    (self.blocks[block as usize], block as u64 * TEXT_INDEX_BLOCK_LINES)
+  }
+
+  /// Blocks that may hold a line matching `search` (ascending); the others can't.
+  pub(crate) fn candidate_blocks(&self, search: &PreparedSearch) -> Vec<u32> {
+    let mut candidates: Option<Vec<u32>> = None;
+    for group in search.needle_groups() {
+      // A group rules out the blocks none of its needles can be in.
+      let mut any: Option<Vec<u32>> = Some(Vec::new());
+      for needle in group {
+        any = match (any, self.blocks_with(needle)) {
+          (Some(a), Some(b)) => {
+            let mut both: Vec<u32> = a.into_iter().chain(b).collect();
+            both.sort_unstable();
+            both.dedup();
+            Some(both)
+          }
+          _ => None,
+        };
+      }
+      candidates = match (candidates, any) {
+        (Some(a), Some(b)) => Some(intersect(&a, &b)),
+        (a, b) => a.or(b),
+      };
+    }
+    candidates.unwrap_or_else(|| (0..self.blocks.len() as u32).collect())
+  }
+
+  /// Blocks holding every listed trigram of `needle`; `None` (any block) if it has none.
+  fn blocks_with(&self, needle: &str) -> Option<Vec<u32>> {
+    let mut blocks: Option<Vec<u32>> = None;
+    for gram in trigrams(needle) {
+      if self.common.contains(&gram) {
+        continue;
+      }
+      let listed = self.postings.get(&gram).map(Vec::as_slice).unwrap_or_default();
+      blocks = Some(match blocks {
+        Some(b) => intersect(&b, listed),
+        None => listed.to_vec(),
+      });
+    }
+    blocks
+  }
+
+  /// Block offsets, common trigrams, then each listed trigram with its blocks; all little-endian.
+  pub(crate) fn to_blob(&self) -> Vec<u8> {
+    let mut blob = Vec::new();
+    blob.extend_from_slice(&(self.blocks.len() as u64).to_le_bytes());
+    for offset in &self.blocks {
+      blob.extend_from_slice(&offset.to_le_bytes());
+    }
+    blob.extend_from_slice(&(self.common.len() as u32).to_le_bytes());
+    for gram in &self.common {
+      blob.extend_from_slice(&gram.to_le_bytes());
+    }
+    for (gram, blocks) in &self.postings {
+      blob.extend_from_slice(&gram.to_le_bytes());
+      blob.extend_from_slice(&(blocks.len() as u32).to_le_bytes());
+      for block in blocks {
+        blob.extend_from_slice(&block.to_le_bytes());
+      }
+    }
+    blob
+  }
+
+  /// `None` if `blob` isn't one produced by `to_blob`.
+  pub(crate) fn from_blob(mut blob: &[u8]) -> Option<Self> {
+    let u64_at = |b: &[u8]| u64::from_le_bytes(b.try_into().unwrap_or_default());
+    let u32_at = |b: &[u8]| u32::from_le_bytes(b.try_into().unwrap_or_default());
+
+    let mut index = Self::default();
+    let block_count = u64_at(take(&mut blob, 8)?) as usize;
+    index.blocks = take(&mut blob, block_count.checked_mul(8)?)?.chunks_exact(8).map(u64_at).collect();
+    let common_count = u32_at(take(&mut blob, 4)?) as usize;
+    index.common = take(&mut blob, common_count.checked_mul(4)?)?.chunks_exact(4).map(u32_at).collect();
+    while !blob.is_empty() {
+      let gram = u32_at(take(&mut blob, 4)?);
+      let count = u32_at(take(&mut blob, 4)?) as usize;
+      let blocks: Vec<u32> = take(&mut blob, count.checked_mul(4)?)?.chunks_exact(4).map(u32_at).collect();
+      if blocks.iter().any(|&b| b as usize >= block_count) {
+        return None;
+      }
+      index.postings.insert(gram, blocks);
+    }
+    Some(index)
+  }
+}
+
+/// The first `n` bytes of `blob`, which is moved past them.
+fn take<'a>(blob: &mut &'a [u8], n: usize) -> Option<&'a [u8]> {
+  let (head, rest) = blob.split_at_checked(n)?;
+  *blob = rest;
+  Some(head)
+}
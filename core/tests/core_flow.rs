@@ -1,8 +1,9 @@
 use std::{path::PathBuf, thread, time::Duration};
 
 use dh_core::{
-  CoreEngine, CoreOptions, ExportFormat, ExportRequest, JsonPathSegment, SearchMode, SearchQuery,
-  StorageOptions,
+  CoreEngine, CoreOptions, DedupSpec, DerivedColumn, DiffAlign, DiffChange, ExportFormat, ExportPreset, ExportRequest, JsonNodeKind, JsonPathSegment, SearchCount, SearchMode, SearchQuery,
+  SortSpec, Storage, StorageLimits, StorageOptions, TaskError, TaskEventKind, TaskKind, TaskPriority, ViewPrefs, WorkspaceTab, TextEncoding, FileChange, FileFormat,
+  CoreError, ParquetKeyValue, ParquetColumnStats, ColumnFilter, FilterOp, CsvDialect, JsonChildSort, JsonFindQuery, Base64Content,
 };
 
 fn engine_with_sqlite(sqlite_path: PathBuf) -> CoreEngine {
@@ -13,7 +14,9 @@ fn engine_with_sqlite(sqlite_path: PathBuf) -> CoreEngine {
     max_concurrent_tasks: 2,
     storage: StorageOptions {
       sqlite_path: Some(sqlite_path),
+      ..StorageOptions::default()
     },
+    ..CoreOptions::default()
   })
   .unwrap()
 }
@@ -81,6 +84,8 @@ fn search_current_page_works() {
         mode: SearchMode::CurrentPage,
         case_sensitive: false,
         max_hits: 100,
+        timeout_ms: None,
+        force_rescan: false,
       },
     )
     .unwrap();
@@ -106,6 +111,8 @@ fn scan_all_search_and_export_selection() {
         mode: SearchMode::ScanAll,
         case_sensitive: true,
         max_hits: 100,
+        timeout_ms: None,
+        force_rescan: false,
       },
     )
     .unwrap();
@@ -277,6 +284,7 @@ fn export_json_subtree_root_and_children() {
         path: vec![JsonPathSegment::Key("a".into()), JsonPathSegment::Key("b".into())],
         include_root: true,
         children: vec![],
+        minify: false,
       },
       ExportFormat::Jsonl,
       &out1,
@@ -296,6 +304,7 @@ fn export_json_subtree_root_and_children() {
         path: vec![JsonPathSegment::Key("a".into()), JsonPathSegment::Key("b".into())],
         include_root: false,
         children: vec![JsonPathSegment::Index(1)],
+        minify: false,
       },
       ExportFormat::Jsonl,
       &out2,
@@ -324,6 +333,8 @@ fn scan_all_search_json_root_array_works() {
         mode: SearchMode::ScanAll,
         case_sensitive: true,
         max_hits: 100,
+        timeout_ms: None,
+        force_rescan: false,
       },
     )
     .unwrap();
@@ -381,6 +392,8 @@ fn scan_all_search_parquet_works() {
         mode: SearchMode::ScanAll,
         case_sensitive: true,
         max_hits: 100,
+        timeout_ms: None,
+        force_rescan: false,
       },
     )
     .unwrap();
@@ -490,3 +503,5127 @@ fn json_trailing_nul_bytes_are_ignored() {
   assert_eq!(p1.records.len(), 2);
   assert!(p1.reached_eof);
 }
+
+#[test]
+fn stats_report_exports_json_and_markdown() {
+  let dir = tempfile::tempdir().unwrap();
+  let sqlite = dir.path().join("t.sqlite");
+  let file = dir.path().join("a.csv");
+  std::fs::write(&file, "id,name,score\n1,Alice,98\n2,Bob,\n3,Alice,87.5\n").unwrap();
+
+  let eng = engine_with_sqlite(sqlite);
+  let (session, _p1) = eng.open_file(&file).unwrap();
+
+  let stats = eng.get_stats(&session.session_id).unwrap();
+  assert_eq!(stats.records_scanned, 3);
+  let score = stats.columns.iter().find(|c| c.name == "score").unwrap();
+  assert_eq!(score.inferred_kind, dh_core::JsonNodeKind::Number);
+  assert_eq!(score.null_count, 1);
+  let n = score.numeric.as_ref().unwrap();
+  assert_eq!(n.count, 2);
+  assert_eq!(n.min, 87.5);
+  assert_eq!(n.max, 98.0);
+  let name = stats.columns.iter().find(|c| c.name == "name").unwrap();
+  assert_eq!(name.distinct_count, 2);
+
+  let out_md = dir.path().join("stats.md");
+  let ex = eng
+    .export_stats_report(&session.session_id, dh_core::StatsReportFormat::Markdown, &out_md)
+    .unwrap();
+  assert_eq!(ex.records_written, 3);
+  let md = std::fs::read_to_string(out_md).unwrap();
+  assert!(md.contains("| score | number | 3 | 1 | 2 |"));
+
+  let out_json = dir.path().join("stats.json");
+  eng
+    .export_stats_report(&session.session_id, dh_core::StatsReportFormat::Json, &out_json)
+    .unwrap();
+  let v: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(out_json).unwrap()).unwrap();
+  assert_eq!(v["stats"]["records_scanned"], 3);
+}
+
+#[test]
+fn quick_stats_samples_head_tail_and_random() {
+  let dir = tempfile::tempdir().unwrap();
+  let sqlite = dir.path().join("t.sqlite");
+  let file = dir.path().join("a.csv");
+  let mut s = String::from("id,v\n");
+  for i in 0..1000 {
+    s.push_str(&format!("{i},{}\n", i % 10));
+  }
+  std::fs::write(&file, s).unwrap();
+
+  let eng = engine_with_sqlite(sqlite);
+  let (session, _p1) = eng.open_file(&file).unwrap();
+
+  let head = eng
+    .quick_stats(&session.session_id, dh_core::StatsSampleStrategy::Head, 10)
+    .unwrap();
+  assert_eq!(head.records_scanned, 10);
+  let id = head.columns.iter().find(|c| c.name == "id").unwrap();
+  assert_eq!(id.numeric.as_ref().unwrap().max, 9.0);
+  assert!(id.confidence.is_some());
+
+  let tail = eng
+    .quick_stats(&session.session_id, dh_core::StatsSampleStrategy::Tail, 10)
+    .unwrap();
+  assert_eq!(tail.records_scanned, 10);
+  let id = tail.columns.iter().find(|c| c.name == "id").unwrap();
+  assert_eq!(id.numeric.as_ref().unwrap().min, 990.0);
+  assert_eq!(id.numeric.as_ref().unwrap().max, 999.0);
+
+  let random = eng
+    .quick_stats(&session.session_id, dh_core::StatsSampleStrategy::Random, 50)
+    .unwrap();
+  let info = random.sample.as_ref().unwrap();
+  assert_eq!(info.strategy, dh_core::StatsSampleStrategy::Random);
+  assert!(info.sampled > 0 && info.sampled <= 50);
+  // The header row must never be sampled as data.
+  assert_eq!(random.invalid_records, 0);
+  let v = random.columns.iter().find(|c| c.name == "v").unwrap();
+  assert_eq!(v.inferred_kind, dh_core::JsonNodeKind::Number);
+  let est = info.estimated_total_records.unwrap();
+  assert!((800..1200).contains(&est), "estimate {est}");
+}
+
+#[test]
+fn quick_stats_csv_tail_and_random_samples_keep_quoted_line_breaks() {
+  let dir = tempfile::tempdir().unwrap();
+  let file = dir.path().join("notes.csv");
+  let mut s = String::from("id,note\n");
+  for i in 0..200 {
+    s.push_str(&format!("{i},\"first line\nsecond, {i}\"\n"));
+  }
+  std::fs::write(&file, s).unwrap();
+  let eng = engine_with_sqlite(dir.path().join("t.sqlite"));
+  let (session, _p) = eng.open_file(&file).unwrap();
+
+  for (strategy, n) in [(dh_core::StatsSampleStrategy::Tail, 5), (dh_core::StatsSampleStrategy::Random, 20)] {
+    let stats = eng.quick_stats(&session.session_id, strategy, n).unwrap();
+    let info = stats.sample.as_ref().unwrap();
+    assert_eq!((info.sampled, info.estimated_total_records), (n, Some(200)));
+    // Starting inside a quoted cell would turn "second, <i>\"" into an id.
+    let id = stats.columns.iter().find(|c| c.name == "id").unwrap();
+    assert_eq!(id.inferred_kind, dh_core::JsonNodeKind::Number);
+    assert_eq!(stats.columns.len(), 2);
+    if strategy == dh_core::StatsSampleStrategy::Tail {
+      assert_eq!(id.numeric.as_ref().unwrap().min, 195.0);
+    }
+  }
+
+  // Without a header, row 0 is a record like the others.
+  let headerless = dir.path().join("rows.csv");
+  std::fs::write(&headerless, "1,a\n2,b\n3,c\n").unwrap();
+  let (session, _p) = eng.open_file(&headerless).unwrap();
+  eng
+    .set_csv_dialect(&session.session_id, CsvDialect { has_header: false, ..CsvDialect::default() })
+    .unwrap();
+  for strategy in [dh_core::StatsSampleStrategy::Tail, dh_core::StatsSampleStrategy::Random] {
+    let stats = eng.quick_stats(&session.session_id, strategy, 10).unwrap();
+    let info = stats.sample.as_ref().unwrap();
+    assert_eq!((info.strategy, info.sampled, info.estimated_total_records), (strategy, 3, Some(3)));
+    let first = stats.columns.iter().find(|c| c.name == "col_0").unwrap();
+    assert_eq!(first.numeric.as_ref().unwrap().min, 1.0);
+  }
+}
+
+#[test]
+fn quick_stats_csv_tail_reads_through_the_line_index_built_on_open() {
+  let dir = tempfile::tempdir().unwrap();
+  let file = dir.path().join("big.csv");
+  let mut s = String::from("id,note\n");
+  for i in 0..5000 {
+    s.push_str(&format!("{i},\"row\n{i}\"\n"));
+  }
+  std::fs::write(&file, s).unwrap();
+  let eng = CoreEngine::new(CoreOptions {
+    line_index_min_bytes: 0,
+    storage: StorageOptions {
+      sqlite_path: Some(dir.path().join("t.sqlite")),
+      ..StorageOptions::default()
+    },
+    ..CoreOptions::default()
+  })
+  .unwrap();
+  let (session, _p) = eng.open_file(&file).unwrap();
+  let task = session.index_task.expect("index built on open");
+  for _ in 0..500 {
+    if eng.get_task(&task.id).unwrap().finished {
+      break;
+    }
+    thread::sleep(Duration::from_millis(10));
+  }
+
+  let stats = eng
+    .quick_stats(&session.session_id, dh_core::StatsSampleStrategy::Tail, 10)
+    .unwrap();
+  let info = stats.sample.as_ref().unwrap();
+  assert_eq!(
+    (info.strategy, info.sampled, info.estimated_total_records),
+    (dh_core::StatsSampleStrategy::Tail, 10, Some(5000))
+  );
+  let id = stats.columns.iter().find(|c| c.name == "id").unwrap();
+  assert_eq!(id.numeric.as_ref().unwrap().min, 4990.0);
+}
+
+#[test]
+fn compare_stats_reports_column_and_distribution_changes() {
+  let dir = tempfile::tempdir().unwrap();
+  let sqlite = dir.path().join("t.sqlite");
+  let before = dir.path().join("before.csv");
+  let after = dir.path().join("after.csv");
+  std::fs::write(&before, "id,score,old\n1,1,x\n2,2,y\n3,3,z\n").unwrap();
+  std::fs::write(&after, "id,score,new\n1,100,a\n2,200,b\n3,300,c\n4,400,d\n").unwrap();
+
+  let eng = engine_with_sqlite(sqlite);
+  let (left, _p1) = eng.open_file(&before).unwrap();
+  let (right, _p2) = eng.open_file(&after).unwrap();
+
+  let diff = eng.compare_stats(&left.session_id, &right.session_id).unwrap();
+  assert_eq!(diff.records_delta, 1);
+  assert_eq!(diff.added_columns, vec!["new".to_string()]);
+  assert_eq!(diff.removed_columns, vec!["old".to_string()]);
+
+  let score = diff.columns.iter().find(|c| c.name == "score").unwrap();
+  assert_eq!(score.present_delta, 1);
+  assert_eq!(score.mean_delta, Some(248.0));
+  // Disjoint value ranges => maximal shift.
+  assert!((score.distribution_shift.unwrap() - 1.0).abs() < 1e-9);
+
+  let same = eng.compare_stats(&left.session_id, &left.session_id).unwrap();
+  let score = same.columns.iter().find(|c| c.name == "score").unwrap();
+  assert!(score.distribution_shift.unwrap().abs() < 1e-9);
+}
+
+#[test]
+fn stats_report_text_length_and_token_estimates() {
+  let dir = tempfile::tempdir().unwrap();
+  let sqlite = dir.path().join("t.sqlite");
+  let file = dir.path().join("a.jsonl");
+  std::fs::write(
+    &file,
+    "{\"text\":\"hello world\",\"n\":1}\n{\"text\":\"你好\",\"n\":2}\n{\"text\":\"\",\"n\":3}\n",
+  )
+  .unwrap();
+
+  let eng = engine_with_sqlite(sqlite);
+  let (session, _p1) = eng.open_file(&file).unwrap();
+  let stats = eng.get_stats(&session.session_id).unwrap();
+
+  let text = stats.columns.iter().find(|c| c.name == "text").unwrap();
+  let t = text.text.as_ref().unwrap();
+  assert_eq!(t.count, 3);
+  assert_eq!(t.min_chars, 0);
+  assert_eq!(t.max_chars, 11);
+  assert_eq!(t.total_words, 3);
+  // "hello" + "world" => 2 + 2, "你好" => 2
+  assert_eq!(t.total_tokens_estimate, 6);
+  assert_eq!(t.max_tokens_estimate, 4);
+
+  let n = stats.columns.iter().find(|c| c.name == "n").unwrap();
+  assert!(n.text.is_none());
+}
+
+#[test]
+fn stats_task_completes_and_cancel_keeps_partial_result() {
+  let dir = tempfile::tempdir().unwrap();
+  let sqlite = dir.path().join("t.sqlite");
+  let small = dir.path().join("small.jsonl");
+  std::fs::write(&small, "{\"a\":1}\n{\"a\":2}\n").unwrap();
+  let big = dir.path().join("big.jsonl");
+  let mut s = String::new();
+  for i in 0..200_000 {
+    s.push_str(&format!("{{\"a\":{i},\"t\":\"row {i}\"}}\n"));
+  }
+  std::fs::write(&big, s).unwrap();
+
+  let eng = engine_with_sqlite(sqlite);
+  let wait = |task_id: &str| {
+    for _ in 0..1000 {
+      if eng.get_task(task_id).unwrap().finished {
+        return;
+      }
+      thread::sleep(Duration::from_millis(10));
+    }
+    panic!("stats task did not finish");
+  };
+
+  let (session, _p1) = eng.open_file(&small).unwrap();
+  let task = eng.start_stats_task(&session.session_id).unwrap();
+  assert_eq!(task.kind, dh_core::TaskKind::Stats);
+  wait(&task.id);
+  let full = eng.stats_task_result(&task.id).unwrap();
+  assert!(full.complete);
+  assert_eq!(full.records_scanned, 2);
+  assert!(full.columns.iter().all(|c| c.complete));
+
+  let (session, _p2) = eng.open_file(&big).unwrap();
+  let task = eng.start_stats_task(&session.session_id).unwrap();
+  eng.cancel_task(&task.id).unwrap();
+  wait(&task.id);
+  let partial = eng.stats_task_result(&task.id).unwrap();
+  assert!(!partial.complete);
+  assert!(partial.records_scanned < 200_000);
+  assert!(partial.columns.iter().all(|c| !c.complete));
+}
+
+#[test]
+fn count_records_per_format() {
+  let dir = tempfile::tempdir().unwrap();
+  let sqlite = dir.path().join("t.sqlite");
+  let eng = engine_with_sqlite(sqlite);
+
+  let jsonl = dir.path().join("a.jsonl");
+  std::fs::write(&jsonl, "{\"a\":1}\n{\"a\":2}\n{\"a\":3}").unwrap();
+  let (s, _p) = eng.open_file(&jsonl).unwrap();
+  let c = eng.count_records(&s.session_id).unwrap();
+  assert_eq!(c.total, Some(3));
+  assert!(c.task.is_none());
+
+  // Header excluded; the quoted newline does not start a new record.
+  let csv = dir.path().join("a.csv");
+  std::fs::write(&csv, "id,text\n1,\"a\nb\"\n2,c\n").unwrap();
+  let (s, _p) = eng.open_file(&csv).unwrap();
+  assert_eq!(eng.count_records(&s.session_id).unwrap().total, Some(2));
+
+  let json = dir.path().join("a.json");
+  std::fs::write(&json, "[{\"a\":1},{\"a\":2}]").unwrap();
+  let (s, _p) = eng.open_file(&json).unwrap();
+  assert_eq!(eng.count_records(&s.session_id).unwrap().total, Some(2));
+
+  let parquet = dir.path().join("a.parquet");
+  let conn = duckdb::Connection::open_in_memory().unwrap();
+  conn
+    .execute(
+      "COPY (SELECT range AS x FROM range(5)) TO ? (FORMAT PARQUET);",
+      duckdb::params![parquet.to_string_lossy().to_string()],
+    )
+    .unwrap();
+  let (s, _p) = eng.open_file(&parquet).unwrap();
+  assert_eq!(eng.count_records(&s.session_id).unwrap().total, Some(5));
+}
+
+#[test]
+fn page_at_jumps_to_record_per_format() {
+  let dir = tempfile::tempdir().unwrap();
+  let sqlite = dir.path().join("t.sqlite");
+  let eng = engine_with_sqlite(sqlite);
+
+  let jsonl = dir.path().join("a.jsonl");
+  let mut s = String::new();
+  for i in 0..5000 {
+    s.push_str(&format!("{{\"i\":{i}}}\n"));
+  }
+  std::fs::write(&jsonl, s).unwrap();
+  let (session, _p) = eng.open_file(&jsonl).unwrap();
+  // Deep jump first (builds the index), then one behind it (served from a checkpoint).
+  for idx in [4321u64, 1500, 0, 4999] {
+    let page = eng.page_at(&session.session_id, idx, 3).unwrap();
+    assert_eq!(page.records[0].id, idx);
+    assert_eq!(page.records[0].preview, format!("{{\"i\":{idx}}}"));
+  }
+  // Paging continues seamlessly from a jumped-to page.
+  let page = eng.page_at(&session.session_id, 2047, 2).unwrap();
+  let next = eng
+    .next_page(&session.session_id, page.next_cursor.as_deref(), 1)
+    .unwrap();
+  assert_eq!(next.records[0].id, 2049);
+  assert!(eng.page_at(&session.session_id, 5000, 3).is_err());
+  // The failed jump scanned to EOF, so the index now knows the total.
+  assert_eq!(eng.count_records(&session.session_id).unwrap().total, Some(5000));
+
+  let csv = dir.path().join("a.csv");
+  std::fs::write(&csv, "id,text\n1,\"multi\nline\"\n2,b\n3,c\n").unwrap();
+  let (session, _p) = eng.open_file(&csv).unwrap();
+  let page = eng.page_at(&session.session_id, 2, 10).unwrap();
+  assert_eq!(page.records.len(), 2);
+  assert_eq!(page.records[0].id, 2);
+  assert!(page.records[0].raw.as_deref().unwrap().contains("\"id\":\"2\""));
+
+  let parquet = dir.path().join("a.parquet");
+  let conn = duckdb::Connection::open_in_memory().unwrap();
+  conn
+    .execute(
+      "COPY (SELECT range AS x FROM range(100)) TO ? (FORMAT PARQUET);",
+      duckdb::params![parquet.to_string_lossy().to_string()],
+    )
+    .unwrap();
+  let (session, _p) = eng.open_file(&parquet).unwrap();
+  let page = eng.page_at(&session.session_id, 42, 1).unwrap();
+  assert_eq!(page.records[0].id, 42);
+  assert!(eng.page_at(&session.session_id, 100, 1).is_err());
+}
+
+#[test]
+fn page_at_position_snaps_to_record_boundaries() {
+  let dir = tempfile::tempdir().unwrap();
+  let sqlite = dir.path().join("t.sqlite");
+  let eng = engine_with_sqlite(sqlite);
+
+  // Fixed-width lines make the id estimate exact.
+  let jsonl = dir.path().join("a.jsonl");
+  let mut s = String::new();
+  for i in 0..1000 {
+    s.push_str(&format!("{{\"i\":{i:04}}}\n"));
+  }
+  std::fs::write(&jsonl, &s).unwrap();
+  let (session, _p) = eng.open_file(&jsonl).unwrap();
+  let line_len = 11u64;
+  // Exact pages are saved as the read position.
+  eng.page_at(&session.session_id, 2, 2).unwrap();
+  let (opened, _p) = eng.open_file(&jsonl).unwrap();
+  let stored = opened.read_position.unwrap();
+  assert_eq!((stored.record_index, stored.byte_offset), (2, Some(2 * line_len)));
+  let p = eng
+    .page_at_position(&session.session_id, dh_core::SeekPosition::Fraction { value: 0.5 }, 2)
+    .unwrap();
+  assert_eq!(p.byte_offset, Some(500 * line_len));
+  assert_eq!(p.page.records[0].preview, "{\"i\":0500}");
+  assert_eq!(p.page.records[0].id, 500);
+  assert!(!p.ids_exact);
+  assert!(p.page.ids_estimated);
+  // Pages read on from it are estimates too, and none is saved as a read position.
+  let next = eng.next_page(&session.session_id, p.page.next_cursor.as_deref(), 2).unwrap();
+  assert_eq!(next.records[0].id, 502);
+  assert!(next.ids_estimated);
+  let (reopened, _p) = eng.open_file(&jsonl).unwrap();
+  assert_eq!(reopened.read_position, Some(stored));
+
+  // Mid-line offsets snap forward; once indexed, ids are exact.
+  eng.page_at(&session.session_id, 999, 1).unwrap();
+  let p = eng
+    .page_at_position(
+      &session.session_id,
+      dh_core::SeekPosition::ByteOffset { offset: 700 * line_len + 3 },
+      1,
+    )
+    .unwrap();
+  assert_eq!(p.page.records[0].id, 701);
+  assert!(p.ids_exact);
+  assert!(!p.page.ids_estimated);
+  // The snapped page is the one saved, at the start of its record.
+  let stored = eng.open_file(&jsonl).unwrap().0.read_position.unwrap();
+  assert_eq!((stored.record_index, stored.byte_offset), (701, Some(701 * line_len)));
+
+  let json = dir.path().join("a.json");
+  std::fs::write(&json, "[{\"a\":\"x,{]\"},{\"a\":[1,{\"b\":2}]},{\"a\":3}]").unwrap();
+  let (session, _p) = eng.open_file(&json).unwrap();
+  let p = eng
+    .page_at_position(&session.session_id, dh_core::SeekPosition::ByteOffset { offset: 5 }, 5)
+    .unwrap();
+  assert!(p.ids_exact);
+  assert_eq!(p.page.records.len(), 2);
+  assert_eq!(p.page.records[0].id, 1);
+  assert_eq!(p.page.records[0].preview, "{\"a\":[1,{\"b\":2}]}");
+
+  let parquet = dir.path().join("a.parquet");
+  let conn = duckdb::Connection::open_in_memory().unwrap();
+  conn
+    .execute(
+      "COPY (SELECT range AS x FROM range(10)) TO ? (FORMAT PARQUET);",
+      duckdb::params![parquet.to_string_lossy().to_string()],
+    )
+    .unwrap();
+  let (session, _p) = eng.open_file(&parquet).unwrap();
+  let p = eng
+    .page_at_position(&session.session_id, dh_core::SeekPosition::Fraction { value: 1.0 }, 5)
+    .unwrap();
+  assert_eq!(p.page.records[0].id, 9);
+}
+
+#[test]
+fn line_index_is_built_on_open_and_reused_from_sqlite() {
+  let dir = tempfile::tempdir().unwrap();
+  let sqlite = dir.path().join("t.sqlite");
+  let indexing_engine = |sqlite_path: PathBuf| {
+    CoreEngine::new(CoreOptions {
+      default_page_size: 2,
+      line_index_min_bytes: 0,
+      storage: StorageOptions {
+        sqlite_path: Some(sqlite_path),
+        ..StorageOptions::default()
+      },
+      ..CoreOptions::default()
+    })
+    .unwrap()
+  };
+
+  let jsonl = dir.path().join("a.jsonl");
+  let mut s = String::new();
+  for i in 0..3000 {
+    s.push_str(&format!("{{\"i\":{i}}}\n"));
+  }
+  std::fs::write(&jsonl, s).unwrap();
+
+  let eng = indexing_engine(sqlite.clone());
+  let (session, _p) = eng.open_file(&jsonl).unwrap();
+  let task = session.index_task.expect("index task started on open");
+  assert_eq!(task.kind, TaskKind::IndexBuild);
+  for _ in 0..200 {
+    if eng.get_task(&task.id).unwrap().finished {
+      break;
+    }
+    thread::sleep(Duration::from_millis(10));
+  }
+  let c = eng.count_records(&session.session_id).unwrap();
+  assert_eq!(c.total, Some(3000));
+  assert!(c.task.is_none());
+  assert_eq!(eng.page_at(&session.session_id, 2500, 1).unwrap().records[0].id, 2500);
+
+  // Same file, fresh engine: the index comes from SQLite, no rebuild.
+  let eng = indexing_engine(sqlite);
+  let (session, _p) = eng.open_file(&jsonl).unwrap();
+  assert!(session.index_task.is_none());
+  assert_eq!(eng.count_records(&session.session_id).unwrap().total, Some(3000));
+  let page = eng.page_at(&session.session_id, 2999, 1).unwrap();
+  assert_eq!(page.records[0].preview, "{\"i\":2999}");
+
+  // `.json` root arrays are indexed by element (brackets and commas inside strings are data).
+  let json = dir.path().join("a.json");
+  let elements: Vec<String> = (0..3000).map(|i| format!("{{\"i\":{i},\"s\":\"],[{{\\\"\"}}")).collect();
+  std::fs::write(&json, format!(" [\n{}\n]\n", elements.join(",\n  "))).unwrap();
+  let (session, _p) = eng.open_file(&json).unwrap();
+  let task = session.index_task.expect("index task started on open");
+  assert_eq!(task.kind, TaskKind::IndexBuild);
+  for _ in 0..200 {
+    if eng.get_task(&task.id).unwrap().finished {
+      break;
+    }
+    thread::sleep(Duration::from_millis(10));
+  }
+  assert_eq!(eng.count_records(&session.session_id).unwrap().total, Some(3000));
+  let page = eng.page_at(&session.session_id, 2049, 2).unwrap();
+  assert_eq!(page.records.iter().map(|r| r.id).collect::<Vec<_>>(), vec![2049, 2050]);
+  assert_eq!(page.records[0].preview, elements[2049]);
+  assert_eq!(eng.page_at(&session.session_id, 0, 1).unwrap().records[0].preview, elements[0]);
+  assert!(eng.page_at(&session.session_id, 3000, 1).is_err());
+  let eng = indexing_engine(dir.path().join("t.sqlite"));
+  let (session, _p) = eng.open_file(&json).unwrap();
+  assert!(session.index_task.is_none());
+  assert_eq!(eng.page_at(&session.session_id, 2999, 1).unwrap().records[0].preview, elements[2999]);
+
+  // Default threshold: small files are not indexed eagerly.
+  let eng = engine_with_sqlite(dir.path().join("other.sqlite"));
+  let (session, _p) = eng.open_file(&jsonl).unwrap();
+  assert!(session.index_task.is_none());
+}
+
+#[test]
+fn list_and_close_sessions_with_idle_eviction() {
+  let dir = tempfile::tempdir().unwrap();
+  let sqlite = dir.path().join("t.sqlite");
+  let eng = engine_with_sqlite(sqlite.clone());
+
+  let jsonl = dir.path().join("a.jsonl");
+  std::fs::write(&jsonl, "{\"a\":1}\n{\"a\":2}\n").unwrap();
+  let (a, _p) = eng.open_file(&jsonl).unwrap();
+  let (b, _p) = eng.open_file(&jsonl).unwrap();
+  let ids: Vec<String> = eng.list_sessions().into_iter().map(|s| s.session_id).collect();
+  assert_eq!(ids.len(), 2);
+  assert!(ids.contains(&a.session_id) && ids.contains(&b.session_id));
+
+  eng.close_session(&a.session_id).unwrap();
+  assert!(eng.next_page(&a.session_id, None, 1).is_err());
+  assert!(eng.close_session(&a.session_id).is_err());
+  assert_eq!(eng.list_sessions().len(), 1);
+
+  let eng = CoreEngine::new(CoreOptions {
+    session_idle_timeout_ms: Some(50),
+    storage: StorageOptions {
+      sqlite_path: Some(sqlite),
+      ..StorageOptions::default()
+    },
+    ..CoreOptions::default()
+  })
+  .unwrap();
+  let (idle, _p) = eng.open_file(&jsonl).unwrap();
+  let (busy, _p) = eng.open_file(&jsonl).unwrap();
+  for _ in 0..4 {
+    thread::sleep(Duration::from_millis(20));
+    eng.next_page(&busy.session_id, None, 1).unwrap();
+  }
+  // Paging the busy session already closed the idle one.
+  assert!(eng.next_page(&idle.session_id, None, 1).is_err());
+  let ids: Vec<String> = eng.list_sessions().into_iter().map(|s| s.session_id).collect();
+  assert_eq!(ids, vec![busy.session_id]);
+}
+
+#[test]
+fn multi_file_session_pages_searches_and_exports_across_parts() {
+  let dir = tempfile::tempdir().unwrap();
+  let sqlite = dir.path().join("t.sqlite");
+  let eng = engine_with_sqlite(sqlite);
+
+  let parts: Vec<PathBuf> = ["{\"i\":0}\n{\"i\":1}\n{\"i\":2}\n", "{\"i\":3}\n{\"i\":4}\n", "{\"i\":5}\n"]
+    .iter()
+    .enumerate()
+    .map(|(n, body)| {
+      let p = dir.path().join(format!("part-{n:05}.jsonl"));
+      std::fs::write(&p, body).unwrap();
+      p
+    })
+    .collect();
+  let (session, first) = eng.open_files(&parts).unwrap();
+  assert_eq!(session.parts.len(), 3);
+  assert_eq!(first.records.iter().map(|r| r.id).collect::<Vec<_>>(), vec![0, 1]);
+
+  // Pages cross part boundaries with continuous ids.
+  let page = eng.next_page(&session.session_id, first.next_cursor.as_deref(), 3).unwrap();
+  assert_eq!(page.records.iter().map(|r| r.id).collect::<Vec<_>>(), vec![2, 3, 4]);
+  assert_eq!(page.records[1].meta.as_ref().unwrap().part, Some(1));
+  let last = eng.next_page(&session.session_id, page.next_cursor.as_deref(), 3).unwrap();
+  assert_eq!(last.records.len(), 1);
+  assert_eq!(last.records[0].preview, "{\"i\":5}");
+  assert!(last.reached_eof);
+  assert!(last.next_cursor.is_none());
+
+  let raw = eng
+    .get_record_raw(&session.session_id, page.records[2].meta.clone().unwrap())
+    .unwrap();
+  assert_eq!(raw, "{\"i\":4}");
+  assert_eq!(eng.count_records(&session.session_id).unwrap().total, Some(6));
+  assert!(eng.page_at(&session.session_id, 3, 1).is_err());
+
+  let r = eng
+    .search(
+      &session.session_id,
+      SearchQuery {
+        text: "\"i\":".into(),
+        mode: SearchMode::ScanAll,
+        case_sensitive: true,
+        max_hits: 100,
+        timeout_ms: None,
+        force_rescan: false,
+      },
+    )
+    .unwrap();
+  let task_id = r.task.unwrap().id;
+  for _ in 0..200 {
+    if eng.get_task(&task_id).unwrap().finished {
+      break;
+    }
+    thread::sleep(Duration::from_millis(10));
+  }
+  let hits = eng.search_task_hits_page(&task_id, None, 10).unwrap();
+  assert_eq!(hits.records.iter().map(|r| r.id).collect::<Vec<_>>(), vec![0, 1, 2, 3, 4, 5]);
+
+  let out = dir.path().join("out.json");
+  let res = eng
+    .export(
+      &session.session_id,
+      ExportRequest::Selection { record_ids: vec![5, 1, 3] },
+      ExportFormat::Json,
+      &out,
+    )
+    .unwrap();
+  assert_eq!(res.records_written, 3);
+  let v: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&out).unwrap()).unwrap();
+  assert_eq!(v, serde_json::json!([{"i":1},{"i":3},{"i":5}]));
+
+  // CSV parts: the first header stays record 0, later headers are hidden.
+  let a = dir.path().join("a.csv");
+  let b = dir.path().join("b.csv");
+  std::fs::write(&a, "id,name\n1,x\n").unwrap();
+  std::fs::write(&b, "id,name\n2,y\n3,z\n").unwrap();
+  let (session, first) = eng.open_files(&[a, b]).unwrap();
+  let page = eng.next_page(&session.session_id, first.next_cursor.as_deref(), 10).unwrap();
+  let all: Vec<_> = first.records.iter().chain(page.records.iter()).collect();
+  assert_eq!(all.iter().map(|r| r.id).collect::<Vec<_>>(), vec![0, 1, 2, 3]);
+  assert!(all[2].preview.contains('y'));
+  assert_eq!(eng.count_records(&session.session_id).unwrap().total, Some(3));
+  let out = dir.path().join("out.csv");
+  eng
+    .export(
+      &session.session_id,
+      ExportRequest::Selection { record_ids: vec![0, 1, 3] },
+      ExportFormat::Csv,
+      &out,
+    )
+    .unwrap();
+  assert_eq!(std::fs::read_to_string(&out).unwrap(), "id,name\n1,x\n3,z\n");
+
+  let mixed = eng.open_files(&[parts[0].clone(), dir.path().join("a.csv")]);
+  assert!(mixed.is_err());
+}
+
+#[test]
+fn concatenated_session_attributes_every_record_to_its_file() {
+  let dir = tempfile::tempdir().unwrap();
+  let sqlite = dir.path().join("t.sqlite");
+  let eng = engine_with_sqlite(sqlite);
+
+  let conn = duckdb::Connection::open_in_memory().unwrap();
+  let _ = conn.execute_batch("LOAD parquet;");
+  let parts: Vec<PathBuf> = [(0, 2), (2, 3)]
+    .iter()
+    .enumerate()
+    .map(|(n, (from, to))| {
+      let p = dir.path().join(format!("log-{n}.parquet"));
+      conn
+        .execute(
+          &format!("COPY (SELECT range AS i FROM range({from}, {to})) TO ? (FORMAT PARQUET);"),
+          duckdb::params![p.to_string_lossy().to_string()],
+        )
+        .unwrap();
+      p
+    })
+    .collect();
+  // Chosen order, not name order.
+  let (session, first) = eng.open_files(&[parts[1].clone(), parts[0].clone()]).unwrap();
+  let page = eng.next_page(&session.session_id, first.next_cursor.as_deref(), 10).unwrap();
+  let all: Vec<_> = first.records.iter().chain(page.records.iter()).collect();
+  assert_eq!(all.iter().map(|r| r.id).collect::<Vec<_>>(), vec![0, 1, 2]);
+  let metas: Vec<_> = all.iter().map(|r| r.meta.clone().unwrap()).collect();
+  assert_eq!(
+    metas.iter().map(|m| (m.part, m.line_no)).collect::<Vec<_>>(),
+    vec![(Some(0), 0), (Some(1), 0), (Some(1), 1)]
+  );
+  assert_eq!(session.parts[metas[0].part.unwrap() as usize], parts[1].to_string_lossy());
+  let raw = eng.get_record_raw(&session.session_id, metas[2].clone()).unwrap();
+  assert_eq!(serde_json::from_str::<serde_json::Value>(&raw).unwrap(), serde_json::json!({"i": 1}));
+
+  let a = dir.path().join("a.csv");
+  let b = dir.path().join("b.csv");
+  std::fs::write(&a, "id,name\n1,x\n").unwrap();
+  std::fs::write(&b, "id,label\n2,y\n").unwrap();
+  let err = eng.open_files(&[a, b]).unwrap_err();
+  assert!(err.to_string().contains("different CSV header"));
+}
+
+#[test]
+fn poll_new_records_follows_appends() {
+  use std::io::Write;
+
+  let dir = tempfile::tempdir().unwrap();
+  let sqlite = dir.path().join("t.sqlite");
+  let eng = engine_with_sqlite(sqlite);
+
+  let file = dir.path().join("log.jsonl");
+  std::fs::write(&file, "{\"n\":0}\n{\"n\":1}\n").unwrap();
+  let (session, _p) = eng.open_file(&file).unwrap();
+  assert_eq!(eng.count_records(&session.session_id).unwrap().total, Some(2));
+  assert!(eng.poll_new_records(&session.session_id, 10).unwrap().records.is_empty());
+
+  let append = |s: &str| {
+    let mut f = std::fs::OpenOptions::new().append(true).open(&file).unwrap();
+    f.write_all(s.as_bytes()).unwrap();
+  };
+  // The unterminated tail is held back until its newline arrives.
+  append("{\"n\":2}\n{\"n\":3}\n{\"n\"");
+  let got = eng.poll_new_records(&session.session_id, 10).unwrap();
+  assert_eq!(got.records.iter().map(|r| r.id).collect::<Vec<_>>(), vec![2, 3]);
+  assert!(!got.more_available && !got.reset);
+  append(":4}\n{\"n\":5}\n");
+  let got = eng.poll_new_records(&session.session_id, 1).unwrap();
+  assert_eq!(got.records[0].preview, "{\"n\":4}");
+  assert!(got.more_available);
+  let got = eng.poll_new_records(&session.session_id, 1).unwrap();
+  assert_eq!(got.records[0].id, 5);
+  assert!(!got.more_available);
+  assert_eq!(eng.count_records(&session.session_id).unwrap().total, Some(6));
+
+  // Truncation / rotation restarts from the new end.
+  std::fs::write(&file, "{\"n\":0}\n").unwrap();
+  assert!(eng.poll_new_records(&session.session_id, 10).unwrap().reset);
+  append("{\"n\":1}\n");
+  let got = eng.poll_new_records(&session.session_id, 10).unwrap();
+  assert_eq!(got.records.iter().map(|r| r.id).collect::<Vec<_>>(), vec![1]);
+
+  let json = dir.path().join("a.json");
+  std::fs::write(&json, "[1]").unwrap();
+  let (session, _p) = eng.open_file(&json).unwrap();
+  assert!(eng.poll_new_records(&session.session_id, 10).is_err());
+}
+
+#[test]
+fn refresh_session_detects_appends_and_rewrites() {
+  use std::io::Write;
+
+  let dir = tempfile::tempdir().unwrap();
+  let sqlite = dir.path().join("t.sqlite");
+  let eng = engine_with_sqlite(sqlite);
+
+  let file = dir.path().join("a.jsonl");
+  std::fs::write(&file, "{\"n\":0}\n{\"n\":1}\n{\"n\":2}\n").unwrap();
+  let (session, first) = eng.open_file(&file).unwrap();
+  assert_eq!(eng.refresh_session(&session.session_id).unwrap().change, FileChange::Unchanged);
+
+  let mut f = std::fs::OpenOptions::new().append(true).open(&file).unwrap();
+  f.write_all(b"{\"n\":3}\n{\"n\":4}\n").unwrap();
+  drop(f);
+  let r = eng.refresh_session(&session.session_id).unwrap();
+  assert_eq!(r.change, FileChange::Grew);
+  assert_eq!(r.appended_records, Some(2));
+  assert!(r.size > r.previous_size);
+  // The cursor handed out before the append still continues where it left off.
+  let page = eng.next_page(&session.session_id, first.next_cursor.as_deref(), 10).unwrap();
+  assert_eq!(page.records.iter().map(|r| r.id).collect::<Vec<_>>(), vec![2, 3, 4]);
+  assert_eq!(eng.count_records(&session.session_id).unwrap().total, Some(5));
+
+  std::fs::write(&file, "{\"n\":9}\n").unwrap();
+  let r = eng.refresh_session(&session.session_id).unwrap();
+  assert_eq!(r.change, FileChange::Rewritten);
+  assert_eq!(r.appended_records, None);
+  assert_eq!(eng.count_records(&session.session_id).unwrap().total, Some(1));
+}
+
+#[test]
+fn search_results_open_as_filtered_session() {
+  let dir = tempfile::tempdir().unwrap();
+  let sqlite = dir.path().join("t.sqlite");
+  let eng = engine_with_sqlite(sqlite);
+
+  let file = dir.path().join("a.jsonl");
+  let mut s = String::new();
+  for i in 0..10 {
+    let level = if i % 3 == 0 { "error" } else { "info" };
+    s.push_str(&format!("{{\"i\":{i},\"level\":\"{level}\"}}\n"));
+  }
+  std::fs::write(&file, s).unwrap();
+  let (base, _p) = eng.open_file(&file).unwrap();
+
+  let r = eng
+    .search(
+      &base.session_id,
+      SearchQuery {
+        text: "error".into(),
+        mode: SearchMode::ScanAll,
+        case_sensitive: true,
+        max_hits: 100,
+        timeout_ms: None,
+        force_rescan: false,
+      },
+    )
+    .unwrap();
+  let task_id = r.task.unwrap().id;
+  for _ in 0..200 {
+    if eng.get_task(&task_id).unwrap().finished {
+      break;
+    }
+    thread::sleep(Duration::from_millis(10));
+  }
+
+  let (view, first) = eng.open_search_results(&base.session_id, &task_id).unwrap();
+  assert_eq!(view.filter_task_id.as_deref(), Some(task_id.as_str()));
+  // Original ids are kept; default page size is 2.
+  assert_eq!(first.records.iter().map(|r| r.id).collect::<Vec<_>>(), vec![0, 3]);
+  let next = eng.next_page(&view.session_id, first.next_cursor.as_deref(), 10).unwrap();
+  assert_eq!(next.records.iter().map(|r| r.id).collect::<Vec<_>>(), vec![6, 9]);
+  assert!(next.reached_eof);
+  assert_eq!(eng.count_records(&view.session_id).unwrap().total, Some(4));
+  assert_eq!(eng.page_at(&view.session_id, 2, 1).unwrap().records[0].id, 6);
+
+  let stats = eng.get_stats(&view.session_id).unwrap();
+  assert_eq!(stats.records_scanned, 4);
+  let level = stats.columns.iter().find(|c| c.name == "level").unwrap();
+  assert_eq!(level.distinct_count, 1);
+
+  let out = dir.path().join("errors.jsonl");
+  let res = eng
+    .export(
+      &view.session_id,
+      ExportRequest::Selection {
+        record_ids: next.records.iter().map(|r| r.id).collect(),
+      },
+      ExportFormat::Jsonl,
+      &out,
+    )
+    .unwrap();
+  assert_eq!(res.records_written, 2);
+  assert!(std::fs::read_to_string(&out).unwrap().starts_with("{\"i\":6,"));
+
+  let whole = SearchQuery {
+    text: "info".into(),
+    mode: SearchMode::ScanAll,
+    case_sensitive: true,
+    max_hits: 100,
+    timeout_ms: None,
+    force_rescan: false,
+  };
+  assert!(eng.search(&view.session_id, whole).is_err());
+}
+
+#[test]
+fn sorted_view_orders_csv_jsonl_and_parquet_records() {
+  let dir = tempfile::tempdir().unwrap();
+  let sqlite = dir.path().join("t.sqlite");
+  let eng = engine_with_sqlite(sqlite);
+
+  // CSV cells are typed, so 10 sorts after 9 (numerically, not as text).
+  let csv = dir.path().join("a.csv");
+  std::fs::write(&csv, "name,score\na,9\nb,10\nc,2\nd,\n").unwrap();
+  let (base, _p) = eng.open_file(&csv).unwrap();
+  let asc = SortSpec { key: "score".into(), descending: false };
+  let (view, first) = eng.open_sorted_view(&base.session_id, asc.clone()).unwrap();
+  assert_eq!(view.sort.as_ref(), Some(&asc));
+  assert_eq!(first.records.iter().map(|r| r.id).collect::<Vec<_>>(), vec![3, 1]);
+  let next = eng.next_page(&view.session_id, first.next_cursor.as_deref(), 10).unwrap();
+  // The empty cell sorts last.
+  assert_eq!(next.records.iter().map(|r| r.id).collect::<Vec<_>>(), vec![2, 4]);
+  assert!(next.reached_eof);
+  assert_eq!(eng.count_records(&view.session_id).unwrap().total, Some(4));
+  assert!(eng.open_sorted_view(&view.session_id, asc).is_err());
+
+  // Missing keys stay last when descending too.
+  let jsonl = dir.path().join("a.jsonl");
+  std::fs::write(&jsonl, "{\"t\":1}\n{\"x\":0}\n{\"t\":3}\n{\"t\":2}\n").unwrap();
+  let (base, _p) = eng.open_file(&jsonl).unwrap();
+  let (view, _first) = eng
+    .open_sorted_view(&base.session_id, SortSpec { key: "t".into(), descending: true })
+    .unwrap();
+  let all = eng.next_page(&view.session_id, None, 10).unwrap();
+  assert_eq!(all.records.iter().map(|r| r.id).collect::<Vec<_>>(), vec![2, 3, 0, 1]);
+  assert_eq!(eng.page_at(&view.session_id, 2, 1).unwrap().records[0].id, 0);
+
+  let parquet = dir.path().join("a.parquet");
+  let conn = duckdb::Connection::open_in_memory().unwrap();
+  let _ = conn.execute_batch("LOAD parquet;");
+  conn
+    .execute(
+      "COPY (SELECT * FROM (VALUES (1, 'c'), (2, 'a'), (3, 'b')) t(id, name)) TO ? (FORMAT PARQUET);",
+      duckdb::params![parquet.to_string_lossy().to_string()],
+    )
+    .unwrap();
+  let (base, _p) = eng.open_file(&parquet).unwrap();
+  let (view, _first) = eng
+    .open_sorted_view(&base.session_id, SortSpec { key: "name".into(), descending: false })
+    .unwrap();
+  let all = eng.next_page(&view.session_id, None, 10).unwrap();
+  assert_eq!(all.records.iter().map(|r| r.id).collect::<Vec<_>>(), vec![1, 2, 0]);
+}
+
+#[test]
+fn dedup_view_hides_or_shows_only_duplicate_records() {
+  let dir = tempfile::tempdir().unwrap();
+  let sqlite = dir.path().join("t.sqlite");
+  let eng = engine_with_sqlite(sqlite);
+
+  // Key order and spacing do not make records different.
+  let jsonl = dir.path().join("a.jsonl");
+  std::fs::write(
+    &jsonl,
+    "{\"a\":1,\"b\":2}\n{\"a\":2}\n{\"b\": 2, \"a\": 1}\n{\"a\":2,\"c\":0}\n{\"z\":1}\n",
+  )
+  .unwrap();
+  let (base, _p) = eng.open_file(&jsonl).unwrap();
+  let content = DedupSpec { key: None, only_duplicates: false };
+  let (view, first) = eng.open_dedup_view(&base.session_id, content.clone()).unwrap();
+  assert_eq!(view.dedup.as_ref(), Some(&content));
+  assert_eq!(first.records.iter().map(|r| r.id).collect::<Vec<_>>(), vec![0, 1]);
+  let next = eng.next_page(&view.session_id, first.next_cursor.as_deref(), 10).unwrap();
+  assert_eq!(next.records.iter().map(|r| r.id).collect::<Vec<_>>(), vec![3, 4]);
+  assert_eq!(eng.count_records(&view.session_id).unwrap().total, Some(4));
+  assert!(eng.open_dedup_view(&view.session_id, content).is_err());
+
+  let (dups, _first) = eng
+    .open_dedup_view(&base.session_id, DedupSpec { key: None, only_duplicates: true })
+    .unwrap();
+  let all = eng.next_page(&dups.session_id, None, 10).unwrap();
+  assert_eq!(all.records.iter().map(|r| r.id).collect::<Vec<_>>(), vec![0, 2]);
+
+  // By key: records missing it are kept and never count as duplicates.
+  let (by_a, _first) = eng
+    .open_dedup_view(&base.session_id, DedupSpec { key: Some("a".into()), only_duplicates: false })
+    .unwrap();
+  let all = eng.next_page(&by_a.session_id, None, 10).unwrap();
+  assert_eq!(all.records.iter().map(|r| r.id).collect::<Vec<_>>(), vec![0, 1, 4]);
+  let (dup_a, _first) = eng
+    .open_dedup_view(&base.session_id, DedupSpec { key: Some("a".into()), only_duplicates: true })
+    .unwrap();
+  let all = eng.next_page(&dup_a.session_id, None, 10).unwrap();
+  assert_eq!(all.records.iter().map(|r| r.id).collect::<Vec<_>>(), vec![0, 1, 2, 3]);
+
+  let csv = dir.path().join("a.csv");
+  std::fs::write(&csv, "name,city\nann,x\nbob,y\nann,x\ncid,y\n").unwrap();
+  let (base, _p) = eng.open_file(&csv).unwrap();
+  let (view, first) = eng
+    .open_dedup_view(&base.session_id, DedupSpec { key: Some("city".into()), only_duplicates: false })
+    .unwrap();
+  assert_eq!(first.records.iter().map(|r| r.id).collect::<Vec<_>>(), vec![1, 2]);
+  assert_eq!(eng.count_records(&view.session_id).unwrap().total, Some(2));
+  let (view, _first) = eng
+    .open_dedup_view(&base.session_id, DedupSpec { key: None, only_duplicates: false })
+    .unwrap();
+  let all = eng.next_page(&view.session_id, None, 10).unwrap();
+  assert_eq!(all.records.iter().map(|r| r.id).collect::<Vec<_>>(), vec![1, 2, 4]);
+}
+
+#[test]
+fn session_metrics_count_pages_bytes_and_cache_use() {
+  let dir = tempfile::tempdir().unwrap();
+  let sqlite = dir.path().join("t.sqlite");
+  let eng = engine_with_sqlite(sqlite);
+  let jsonl = dir.path().join("a.jsonl");
+  std::fs::write(&jsonl, "{\"a\":1}\n{\"a\":2}\n{\"a\":3}\n{\"a\":4}\n{\"a\":5}\n").unwrap();
+
+  let (session, first) = eng.open_file(&jsonl).unwrap();
+  let m = eng.session_metrics(&session.session_id).unwrap();
+  assert_eq!((m.pages_served, m.records_served, m.bytes_read), (1, 2, 16));
+  let _next = eng.next_page(&session.session_id, first.next_cursor.as_deref(), 2).unwrap();
+
+  // The first count reads the file; later ones are cached.
+  assert_eq!(eng.count_records(&session.session_id).unwrap().total, Some(5));
+  assert_eq!(eng.count_records(&session.session_id).unwrap().total, Some(5));
+  let _page = eng.page_at(&session.session_id, 4, 2).unwrap();
+  let _page = eng.page_at(&session.session_id, 1, 2).unwrap();
+
+  let m = eng.session_metrics(&session.session_id).unwrap();
+  assert_eq!(m.pages_served, 4);
+  assert_eq!(m.records_served, 2 + 2 + 1 + 2);
+  assert_eq!(m.bytes_read, 8 * 7);
+  assert_eq!((m.cache_hits, m.cache_misses), (2, 2));
+  assert!(m.avg_page_ms >= 0.0 && m.slowest_page_ms >= m.avg_page_ms);
+  assert!(eng.session_metrics("nope").is_err());
+}
+
+#[test]
+fn tasks_over_the_concurrency_cap_are_queued_in_order() {
+  let dir = tempfile::tempdir().unwrap();
+  let eng = CoreEngine::new(CoreOptions {
+    max_concurrent_tasks: 1,
+    storage: StorageOptions {
+      sqlite_path: Some(dir.path().join("t.sqlite")),
+      ..StorageOptions::default()
+    },
+    ..CoreOptions::default()
+  })
+  .unwrap();
+  // Big enough that scanning it is still running while the next tasks are started.
+  let big = dir.path().join("big.jsonl");
+  std::fs::write(&big, "{\"msg\":\"nothing to see here\"}\n".repeat(400_000)).unwrap();
+  let small = dir.path().join("small.jsonl");
+  std::fs::write(&small, "aa\nbb\naa\n").unwrap();
+  let (big_session, _p) = eng.open_file(&big).unwrap();
+  let (small_session, _p) = eng.open_file(&small).unwrap();
+  let scan = |session_id: &str, text: &str| {
+    let query = SearchQuery {
+      text: text.into(),
+      mode: SearchMode::ScanAll,
+      case_sensitive: true,
+      max_hits: 100,
+      timeout_ms: None,
+      force_rescan: false,
+    };
+    eng.search(session_id, query).unwrap().task.unwrap().id
+  };
+
+  let running = scan(&big_session.session_id, "zzz");
+  let dropped = scan(&small_session.session_id, "aa");
+  let waiting = scan(&small_session.session_id, "aa");
+  assert!(!eng.get_task(&running).unwrap().queued);
+  for id in [&dropped, &waiting] {
+    let t = eng.get_task(id).unwrap();
+    assert!(t.queued && !t.finished);
+  }
+
+  // Cancelling a queued task finishes it without running; the next one waits its turn.
+  eng.cancel_task(&dropped).unwrap();
+  let t = eng.get_task(&dropped).unwrap();
+  assert!(t.finished && !t.queued);
+  assert_eq!((t.bytes_processed, t.records_processed), (0, 0));
+  let events = eng.list_task_events(&dropped).unwrap();
+  assert_eq!(events.last().map(|e| e.event), Some(TaskEventKind::Cancelled));
+  assert!(eng.search_task_hits_page(&dropped, None, 10).unwrap().records.is_empty());
+  assert!(eng.get_task(&waiting).unwrap().queued);
+
+  eng.cancel_task(&running).unwrap();
+  for _ in 0..500 {
+    if eng.get_task(&waiting).unwrap().finished {
+      break;
+    }
+    thread::sleep(Duration::from_millis(10));
+  }
+  let t = eng.get_task(&waiting).unwrap();
+  assert!(t.finished && !t.queued);
+  assert_eq!(eng.search_task_hits_page(&waiting, None, 10).unwrap().records.len(), 2);
+}
+
+#[test]
+fn finished_tasks_report_bytes_records_and_throughput() {
+  let dir = tempfile::tempdir().unwrap();
+  let sqlite = dir.path().join("t.sqlite");
+  let eng = engine_with_sqlite(sqlite);
+  let file = dir.path().join("a.jsonl");
+  let body = "{\"msg\":\"row\"}\n".repeat(50_000);
+  std::fs::write(&file, &body).unwrap();
+  let (session, _p) = eng.open_file(&file).unwrap();
+
+  let query = SearchQuery {
+    text: "zzz".into(),
+    mode: SearchMode::ScanAll,
+    case_sensitive: true,
+    max_hits: 100,
+    timeout_ms: None,
+    force_rescan: false,
+  };
+  let task_id = eng.search(&session.session_id, query).unwrap().task.unwrap().id;
+  for _ in 0..500 {
+    if eng.get_task(&task_id).unwrap().finished {
+      break;
+    }
+    thread::sleep(Duration::from_millis(10));
+  }
+  let t = eng.get_task(&task_id).unwrap();
+  assert!(t.finished);
+  assert_eq!(t.bytes_processed, body.len() as u64);
+  assert_eq!(t.records_processed, 50_000);
+  assert!(t.eta_ms.is_none());
+  if let Some(rate) = t.records_per_sec {
+    assert!(rate > 0.0 && t.bytes_per_sec.unwrap() > rate);
+  }
+}
+
+#[test]
+fn paused_task_holds_its_place_until_resumed() {
+  let dir = tempfile::tempdir().unwrap();
+  let sqlite = dir.path().join("t.sqlite");
+  let eng = engine_with_sqlite(sqlite);
+  let big = dir.path().join("big.jsonl");
+  std::fs::write(&big, "{\"msg\":\"nothing to see here\"}\n".repeat(400_000)).unwrap();
+  let (session, _p) = eng.open_file(&big).unwrap();
+  let query = SearchQuery {
+    text: "zzz".into(),
+    mode: SearchMode::ScanAll,
+    case_sensitive: true,
+    max_hits: 100,
+    timeout_ms: None,
+    force_rescan: false,
+  };
+  let task_id = eng.search(&session.session_id, query).unwrap().task.unwrap().id;
+  eng.pause_task(&task_id).unwrap();
+
+  thread::sleep(Duration::from_millis(100));
+  let held = eng.get_task(&task_id).unwrap();
+  assert!(held.paused && !held.finished);
+  assert!(held.eta_ms.is_none());
+  thread::sleep(Duration::from_millis(150));
+  assert_eq!(eng.get_task(&task_id).unwrap().records_processed, held.records_processed);
+
+  eng.resume_task(&task_id).unwrap();
+  for _ in 0..1000 {
+    if eng.get_task(&task_id).unwrap().finished {
+      break;
+    }
+    thread::sleep(Duration::from_millis(10));
+  }
+  let done = eng.get_task(&task_id).unwrap();
+  assert!(done.finished && !done.paused);
+  assert_eq!(done.records_processed, 400_000);
+  assert!(eng.pause_task(&task_id).is_err());
+  assert!(eng.resume_task("nope").is_err());
+}
+
+#[test]
+fn next_page_projects_csv_and_parquet_columns() {
+  let dir = tempfile::tempdir().unwrap();
+  let sqlite = dir.path().join("t.sqlite");
+  let eng = engine_with_sqlite(sqlite);
+  let cols = vec!["c".to_string(), "a".to_string()];
+
+  let csv = dir.path().join("a.csv");
+  std::fs::write(&csv, "a,b,c\n1,2,\"x,y\"\n4,5,6\n").unwrap();
+  let (session, _p) = eng.open_file(&csv).unwrap();
+  let p = eng
+    .next_page_with_columns(&session.session_id, None, 3, Some(&cols))
+    .unwrap();
+  assert_eq!(p.records[0].preview, "c,a");
+  assert_eq!(p.records[1].preview, "\"x,y\",1");
+  let raw: serde_json::Value = serde_json::from_str(p.records[1].raw.as_deref().unwrap()).unwrap();
+  assert_eq!(raw, serde_json::json!({"c": "x,y", "a": "1"}));
+  // Cursors are shared with unprojected paging.
+  let rest = eng.next_page(&session.session_id, p.next_cursor.as_deref(), 3).unwrap();
+  assert!(rest.records.is_empty() || rest.reached_eof);
+  assert!(eng
+    .next_page_with_columns(&session.session_id, None, 3, Some(&["nope".to_string()]))
+    .is_err());
+
+  let parquet = dir.path().join("a.parquet");
+  let conn = duckdb::Connection::open_in_memory().unwrap();
+  let _ = conn.execute_batch("LOAD parquet;");
+  conn
+    .execute(
+      "COPY (SELECT 1 AS a, 2 AS b, 'z' AS c) TO ? (FORMAT PARQUET);",
+      duckdb::params![parquet.to_string_lossy().to_string()],
+    )
+    .unwrap();
+  let (session, _p) = eng.open_file(&parquet).unwrap();
+  let p = eng
+    .next_page_with_columns(&session.session_id, None, 10, Some(&cols))
+    .unwrap();
+  let raw: serde_json::Value = serde_json::from_str(p.records[0].raw.as_deref().unwrap()).unwrap();
+  assert_eq!(raw, serde_json::json!({"c": "z", "a": 1}));
+  assert!(!p.records[0].preview.contains('2'));
+
+  let jsonl = dir.path().join("a.jsonl");
+  std::fs::write(&jsonl, "{\"a\":1}\n").unwrap();
+  let (session, _p) = eng.open_file(&jsonl).unwrap();
+  assert!(eng
+    .next_page_with_columns(&session.session_id, None, 10, Some(&cols))
+    .is_err());
+}
+
+#[test]
+fn parquet_files_with_their_own_file_row_number_column_page_and_filter() {
+  let dir = tempfile::tempdir().unwrap();
+  let sqlite = dir.path().join("t.sqlite");
+  let eng = engine_with_sqlite(sqlite);
+  let parquet = dir.path().join("a.parquet");
+  let conn = duckdb::Connection::open_in_memory().unwrap();
+  let _ = conn.execute_batch("LOAD parquet;");
+  conn
+    .execute(
+      "COPY (SELECT 'own-' || i AS file_row_number, i AS x FROM range(3) t(i)) TO ? (FORMAT PARQUET);",
+      duckdb::params![parquet.to_string_lossy().to_string()],
+    )
+    .unwrap();
+  let (session, _p) = eng.open_file(&parquet).unwrap();
+  let p = eng.next_page(&session.session_id, None, 10).unwrap();
+  assert_eq!(p.records.len(), 3);
+  assert_eq!(p.records[1].id, 1);
+  assert_eq!(p.records[1].preview, "own-1\t1");
+  let raw: serde_json::Value = serde_json::from_str(p.records[1].raw.as_deref().unwrap()).unwrap();
+  assert_eq!(raw, serde_json::json!({"file_row_number": "own-1", "x": 1}));
+  let raw = eng
+    .get_record_raw(&session.session_id, p.records[2].meta.clone().unwrap())
+    .unwrap();
+  assert_eq!(
+    serde_json::from_str::<serde_json::Value>(&raw).unwrap(),
+    serde_json::json!({"file_row_number": "own-2", "x": 2})
+  );
+
+  let p = eng
+    .set_session_filters(
+      &session.session_id,
+      Some(vec![ColumnFilter {
+        column: "file_row_number".into(),
+        op: FilterOp::Ne,
+        value: serde_json::json!("own-0"),
+      }]),
+    )
+    .unwrap();
+  assert_eq!(p.records.iter().map(|r| r.id).collect::<Vec<_>>(), vec![1, 2]);
+}
+
+#[test]
+fn record_labels_persist_filter_and_export() {
+  let dir = tempfile::tempdir().unwrap();
+  let sqlite = dir.path().join("t.sqlite");
+  let file = dir.path().join("a.jsonl");
+  std::fs::write(&file, "{\"i\":0}\n{\"i\":1}\n[2]\n").unwrap();
+
+  let eng = engine_with_sqlite(sqlite.clone());
+  let (session, _p) = eng.open_file(&file).unwrap();
+  let page = eng.next_page(&session.session_id, None, 10).unwrap();
+  let meta = |i: usize| page.records[i].meta.clone().unwrap();
+
+  let l = eng
+    .set_record_label(&session.session_id, meta(1), vec![" bad ".into(), "bad".into(), "x".into()], None)
+    .unwrap()
+    .unwrap();
+  assert_eq!(l.tags, vec!["bad", "x"]);
+  eng
+    .set_record_label(&session.session_id, meta(2), vec!["bad".into()], Some("not an object".into()))
+    .unwrap();
+  eng
+    .set_record_label(&session.session_id, meta(0), vec!["ok".into()], None)
+    .unwrap();
+  // Clearing tags and note removes the label.
+  assert!(eng
+    .set_record_label(&session.session_id, meta(0), vec![], None)
+    .unwrap()
+    .is_none());
+
+  // Labels live in SQLite, so a fresh engine sees them.
+  let eng = engine_with_sqlite(sqlite);
+  let (session, _p) = eng.open_file(&file).unwrap();
+  let bad = eng.list_record_labels(&session.session_id, Some("bad")).unwrap();
+  assert_eq!(bad.iter().map(|l| l.meta.line_no).collect::<Vec<_>>(), vec![1, 2]);
+  assert!(eng.list_record_labels(&session.session_id, Some("ok")).unwrap().is_empty());
+
+  let out = dir.path().join("labeled.jsonl");
+  let res = eng
+    .export(
+      &session.session_id,
+      ExportRequest::Labeled { tag: None },
+      ExportFormat::Jsonl,
+      &out,
+    )
+    .unwrap();
+  assert_eq!(res.records_written, 2);
+  let lines: Vec<serde_json::Value> = std::fs::read_to_string(&out)
+    .unwrap()
+    .lines()
+    .map(|l| serde_json::from_str(l).unwrap())
+    .collect();
+  assert_eq!(lines[0], serde_json::json!({"i": 1, "_labels": {"tags": ["bad", "x"]}}));
+  assert_eq!(
+    lines[1],
+    serde_json::json!({"record": [2], "_labels": {"tags": ["bad"], "note": "not an object"}})
+  );
+
+  // Appends keep labels once the session is refreshed.
+  let mut f = std::fs::OpenOptions::new().append(true).open(&file).unwrap();
+  std::io::Write::write_all(&mut f, b"{\"i\":3}\n").unwrap();
+  drop(f);
+  eng.refresh_session(&session.session_id).unwrap();
+  assert_eq!(eng.list_record_labels(&session.session_id, None).unwrap().len(), 2);
+}
+
+#[test]
+fn goto_record_waits_for_line_index_on_large_files() {
+  let dir = tempfile::tempdir().unwrap();
+  let jsonl = dir.path().join("a.jsonl");
+  let mut s = String::new();
+  for i in 0..3000 {
+    s.push_str(&format!("{{\"i\":{i}}}\n"));
+  }
+  std::fs::write(&jsonl, &s).unwrap();
+
+  // Below line_index_min_bytes: answered right away.
+  let eng = engine_with_sqlite(dir.path().join("small.sqlite"));
+  let (session, _p) = eng.open_file(&jsonl).unwrap();
+  let g = eng.goto_record(&session.session_id, 2500, 1).unwrap();
+  assert!(g.task.is_none());
+  assert_eq!(g.page.unwrap().records[0].id, 2500);
+
+  let eng = CoreEngine::new(CoreOptions {
+    default_page_size: 2,
+    line_index_min_bytes: 0,
+    storage: StorageOptions {
+      sqlite_path: Some(dir.path().join("t.sqlite")),
+      ..StorageOptions::default()
+    },
+    ..CoreOptions::default()
+  })
+  .unwrap();
+  let wait = |eng: &CoreEngine, task_id: &str| {
+    for _ in 0..200 {
+      if eng.get_task(task_id).unwrap().finished {
+        break;
+      }
+      thread::sleep(Duration::from_millis(10));
+    }
+  };
+  let goto = |eng: &CoreEngine, session_id: &str, index: u64| {
+    let mut g = eng.goto_record(session_id, index, 1).unwrap();
+    if let Some(task) = g.task {
+      assert_eq!(task.kind, TaskKind::IndexBuild);
+      wait(eng, &task.id);
+      g = eng.goto_record(session_id, index, 1).unwrap();
+    }
+    assert!(g.task.is_none());
+    g.page.unwrap().records[0].id
+  };
+
+  let (session, _p) = eng.open_file(&jsonl).unwrap();
+  assert_eq!(goto(&eng, &session.session_id, 2999), 2999);
+
+  // Appended records are past the (now incomplete) index: a scan task extends it.
+  let mut more = String::new();
+  for i in 3000..5000 {
+    more.push_str(&format!("{{\"i\":{i}}}\n"));
+  }
+  let mut f = std::fs::OpenOptions::new().append(true).open(&jsonl).unwrap();
+  std::io::Write::write_all(&mut f, more.as_bytes()).unwrap();
+  drop(f);
+  eng.refresh_session(&session.session_id).unwrap();
+  let g = eng.goto_record(&session.session_id, 4500, 1).unwrap();
+  assert!(g.task.is_some());
+  assert_eq!(goto(&eng, &session.session_id, 4500), 4500);
+  // Past the end: the scan reaches EOF, then the retry reports it.
+  if let Some(task) = eng.goto_record(&session.session_id, 5000, 1).unwrap().task {
+    wait(&eng, &task.id);
+  }
+  assert!(eng.goto_record(&session.session_id, 5000, 1).is_err());
+}
+
+#[test]
+fn get_schema_reports_columns_and_key_paths() {
+  let dir = tempfile::tempdir().unwrap();
+  let sqlite = dir.path().join("t.sqlite");
+  let eng = engine_with_sqlite(sqlite);
+  let kinds = |schema: &dh_core::SessionSchema| {
+    schema
+      .fields
+      .iter()
+      .map(|f| (f.name.clone(), f.kind.clone(), f.nullable))
+      .collect::<Vec<_>>()
+  };
+
+  let csv = dir.path().join("a.csv");
+  std::fs::write(&csv, "id,name,ok\n1,a,true\n2,,false\n").unwrap();
+  let (session, _p) = eng.open_file(&csv).unwrap();
+  let schema = eng.get_schema(&session.session_id).unwrap();
+  assert_eq!(schema.sampled_records, 2);
+  assert_eq!(
+    kinds(&schema),
+    vec![
+      ("id".into(), JsonNodeKind::Number, false),
+      ("name".into(), JsonNodeKind::String, true),
+      ("ok".into(), JsonNodeKind::Boolean, false),
+    ]
+  );
+
+  let jsonl = dir.path().join("a.jsonl");
+  std::fs::write(&jsonl, "{\"a\":1,\"u\":{\"n\":\"x\",\"t\":[1]}}\n{\"a\":2.5}\n").unwrap();
+  let (session, _p) = eng.open_file(&jsonl).unwrap();
+  let schema = eng.get_schema(&session.session_id).unwrap();
+  assert_eq!(
+    kinds(&schema),
+    vec![
+      ("a".into(), JsonNodeKind::Number, false),
+      ("u.n".into(), JsonNodeKind::String, true),
+      ("u.t".into(), JsonNodeKind::Array, true),
+    ]
+  );
+
+  let parquet = dir.path().join("a.parquet");
+  let conn = duckdb::Connection::open_in_memory().unwrap();
+  let _ = conn.execute_batch("LOAD parquet;");
+  conn
+    .execute(
+      "COPY (SELECT 1::BIGINT AS id, 'x' AS name) TO ? (FORMAT PARQUET);",
+      duckdb::params![parquet.to_string_lossy().to_string()],
+    )
+    .unwrap();
+  let (session, _p) = eng.open_file(&parquet).unwrap();
+  let schema = eng.get_schema(&session.session_id).unwrap();
+  assert_eq!(schema.sampled_records, 0);
+  assert_eq!(schema.fields[0].name, "id");
+  assert_eq!(schema.fields[0].kind, JsonNodeKind::Number);
+  assert_eq!(schema.fields[0].data_type.as_deref(), Some("BIGINT"));
+  assert_eq!(schema.fields[1].kind, JsonNodeKind::String);
+}
+
+#[test]
+fn non_utf8_files_are_detected_and_transcoded() {
+  let dir = tempfile::tempdir().unwrap();
+  let sqlite = dir.path().join("t.sqlite");
+  let eng = engine_with_sqlite(sqlite);
+
+  let csv = dir.path().join("latin1.csv");
+  std::fs::write(&csv, b"name,city\ncaf\xe9,M\xfcnchen\n").unwrap();
+  let (session, page) = eng.open_file(&csv).unwrap();
+  assert_eq!(session.encoding, TextEncoding::Latin1);
+  assert_eq!(page.records[1].preview, "caf\u{e9},M\u{fc}nchen");
+  let raw = eng.get_record_raw(&session.session_id, page.records[1].meta.clone().unwrap()).unwrap();
+  assert_eq!(raw, "caf\u{e9},M\u{fc}nchen");
+
+  let r = eng
+    .search(
+      &session.session_id,
+      SearchQuery {
+        text: "M\u{fc}nchen".into(),
+        mode: SearchMode::ScanAll,
+        case_sensitive: true,
+        max_hits: 10,
+        timeout_ms: None,
+        force_rescan: false,
+      },
+    )
+    .unwrap();
+  let task_id = r.task.unwrap().id;
+  for _ in 0..100 {
+    if eng.get_task(&task_id).unwrap().finished {
+      break;
+    }
+    thread::sleep(Duration::from_millis(10));
+  }
+  let hits = eng.search_task_hits_page(&task_id, None, 10).unwrap();
+  assert_eq!(hits.records.len(), 1);
+  assert_eq!(hits.records[0].id, 1);
+
+  // Forcing UTF-8 brings the replacement characters back.
+  let page = eng.set_session_encoding(&session.session_id, TextEncoding::Utf8).unwrap();
+  assert_eq!(page.records[1].preview, "caf\u{fffd},M\u{fffd}nchen");
+  let info = eng.list_sessions().into_iter().find(|s| s.session_id == session.session_id).unwrap();
+  assert_eq!(info.encoding, TextEncoding::Utf8);
+
+  // GBK "中文" / Shift-JIS "テスト" are told apart by their byte structure.
+  let gbk = dir.path().join("gbk.jsonl");
+  std::fs::write(&gbk, b"{\"t\":\"\xd6\xd0\xce\xc4\"}\n").unwrap();
+  let (gbk_session, gbk_page) = eng.open_file(&gbk).unwrap();
+  assert_eq!(gbk_session.encoding, TextEncoding::Gb18030);
+  assert_eq!(gbk_page.records[0].preview, "{\"t\":\"中文\"}");
+  let sjis = dir.path().join("sjis.jsonl");
+  std::fs::write(&sjis, b"{\"t\":\"\x83\x65\x83\x58\x83\x67\"}\n").unwrap();
+  let (sjis_session, sjis_page) = eng.open_file(&sjis).unwrap();
+  assert_eq!(sjis_session.encoding, TextEncoding::ShiftJis);
+  assert_eq!(sjis_page.records[0].preview, "{\"t\":\"テスト\"}");
+
+  // Edits are written back in the file's encoding.
+  let out = dir.path().join("gbk_edit.jsonl");
+  let meta = gbk_page.records[0].meta.clone().unwrap();
+  eng.save_record_edit(&gbk_session.session_id, meta, "{\"t\":\"文字\"}", &out).unwrap();
+  assert_eq!(std::fs::read(&out).unwrap(), b"{\"t\":\"\xce\xc4\xd7\xd6\"}\n");
+  let out = dir.path().join("sjis_edit.jsonl");
+  let meta = sjis_page.records[0].meta.clone().unwrap();
+  eng.save_record_edit(&sjis_session.session_id, meta.clone(), "{\"t\":\"テキスト\"}", &out).unwrap();
+  assert_eq!(std::fs::read(&out).unwrap(), b"{\"t\":\"\x83\x65\x83\x4c\x83\x58\x83\x67\"}\n");
+  let err = eng.save_record_edit(&sjis_session.session_id, meta, "{\"t\":\"\u{1f600}\"}", &out).unwrap_err();
+  assert!(matches!(err, CoreError::InvalidArg(_)));
+
+  let utf8 = dir.path().join("utf8.jsonl");
+  std::fs::write(&utf8, "{\"t\":\"caf\u{e9}\"}\n").unwrap();
+  assert_eq!(eng.open_file(&utf8).unwrap().0.encoding, TextEncoding::Utf8);
+}
+
+#[test]
+fn diff_task_reports_added_removed_and_changed_records() {
+  let dir = tempfile::tempdir().unwrap();
+  let sqlite = dir.path().join("t.sqlite");
+  let eng = engine_with_sqlite(sqlite);
+  let wait = |task_id: &str| {
+    for _ in 0..200 {
+      if eng.get_task(task_id).unwrap().finished {
+        return;
+      }
+      thread::sleep(Duration::from_millis(10));
+    }
+    panic!("diff task did not finish");
+  };
+
+  let before = dir.path().join("before.jsonl");
+  let after = dir.path().join("after.jsonl");
+  std::fs::write(&before, "{\"id\":1,\"v\":\"a\"}\n{\"id\":2,\"v\":\"b\"}\n{\"id\":3,\"v\":\"c\"}\n").unwrap();
+  // Same record 1 with another key order / spacing; 2 changed; 3 dropped; 4 added.
+  std::fs::write(&after, "{\"v\": \"a\", \"id\": 1}\n{\"id\":4,\"v\":\"d\"}\n{\"id\":2,\"v\":\"B\"}\n").unwrap();
+  let (left, _p) = eng.open_file(&before).unwrap();
+  let (right, _p) = eng.open_file(&after).unwrap();
+
+  let task = eng
+    .start_diff_task(&left.session_id, &right.session_id, DiffAlign::ByKey { key: "id".into() })
+    .unwrap();
+  assert_eq!(task.kind, TaskKind::Diff);
+  wait(&task.id);
+  let page = eng.diff_task_page(&task.id, None, 2).unwrap();
+  assert!(page.summary.complete);
+  assert_eq!((page.summary.added, page.summary.removed, page.summary.changed, page.summary.unchanged), (1, 1, 1, 1));
+  assert_eq!(page.entries.len(), 2);
+  assert_eq!(page.entries[0].change, DiffChange::Added);
+  assert_eq!(page.entries[0].key.as_deref(), Some("4"));
+  assert!(page.entries[0].left.is_none());
+  assert_eq!(page.entries[1].change, DiffChange::Changed);
+  assert_eq!(page.entries[1].left.as_ref().unwrap().id, 1);
+  assert_eq!(page.entries[1].right.as_ref().unwrap().id, 2);
+  let rest = eng.diff_task_page(&task.id, page.next_cursor.as_deref(), 2).unwrap();
+  assert!(rest.reached_eof);
+  assert_eq!(rest.entries.len(), 1);
+  assert_eq!(rest.entries[0].change, DiffChange::Removed);
+  let removed = rest.entries[0].left.clone().unwrap();
+  let raw = eng.get_record_raw(&left.session_id, removed.meta.unwrap()).unwrap();
+  assert_eq!(raw, "{\"id\":3,\"v\":\"c\"}");
+
+  // By line, every position past the first differs.
+  let task = eng.start_diff_task(&left.session_id, &right.session_id, DiffAlign::ByLine).unwrap();
+  wait(&task.id);
+  let page = eng.diff_task_page(&task.id, None, 10).unwrap();
+  assert_eq!((page.summary.added, page.summary.removed, page.summary.changed, page.summary.unchanged), (0, 0, 2, 1));
+  assert!(page.entries.iter().all(|e| e.key.is_none()));
+
+  // CSV keys are cell text; a row without the key column is skipped.
+  let a = dir.path().join("a.csv");
+  let b = dir.path().join("b.csv");
+  std::fs::write(&a, "id,n\n1,x\n2,y\n").unwrap();
+  std::fs::write(&b, "id,n\n2,y\n1,z\n,w\n").unwrap();
+  let (a, _p) = eng.open_file(&a).unwrap();
+  let (b, _p) = eng.open_file(&b).unwrap();
+  let task = eng
+    .start_diff_task(&a.session_id, &b.session_id, DiffAlign::ByKey { key: "id".into() })
+    .unwrap();
+  wait(&task.id);
+  let page = eng.diff_task_page(&task.id, None, 10).unwrap();
+  assert_eq!((page.summary.changed, page.summary.unchanged, page.summary.added), (1, 1, 0));
+  assert_eq!(page.summary.skipped, 1);
+  assert_eq!(page.entries[0].key.as_deref(), Some("1"));
+
+  assert!(eng
+    .start_diff_task(&a.session_id, &b.session_id, DiffAlign::ByKey { key: String::new() })
+    .is_err());
+}
+
+#[test]
+fn derived_columns_apply_to_pages_search_and_export() {
+  let dir = tempfile::tempdir().unwrap();
+  let sqlite = dir.path().join("t.sqlite");
+  let eng = engine_with_sqlite(sqlite);
+  let column = |name: &str, expr: &str| DerivedColumn {
+    name: name.into(),
+    expr: expr.into(),
+  };
+
+  let file = dir.path().join("a.jsonl");
+  std::fs::write(
+    &file,
+    "{\"text\":\"hello\",\"meta\":{\"lang\":\"en\"}}\n{\"text\":\"hi\",\"meta\":{\"lang\":\"fr\"}}\n",
+  )
+  .unwrap();
+  let (session, _p) = eng.open_file(&file).unwrap();
+  let page = eng
+    .set_derived_columns(
+      &session.session_id,
+      vec![column("n", "len(text)"), column("lang", "upper(json_extract(raw, '$.meta.lang'))")],
+    )
+    .unwrap();
+  let derived = page.records[0].derived.as_ref().unwrap();
+  assert_eq!(derived["n"], serde_json::json!(5));
+  assert_eq!(derived["lang"], serde_json::json!("EN"));
+  assert_eq!(page.records[1].derived.as_ref().unwrap()["n"], serde_json::json!(2));
+  let again = eng.next_page(&session.session_id, None, 2).unwrap();
+  assert_eq!(again.records[1].derived.as_ref().unwrap()["lang"], serde_json::json!("FR"));
+
+  // Derived values are searchable, on the page and over the whole file.
+  let query = |mode| SearchQuery {
+    text: "FR".into(),
+    mode,
+    case_sensitive: true,
+    max_hits: 100,
+    timeout_ms: None,
+    force_rescan: false,
+  };
+  let res = eng.search(&session.session_id, query(SearchMode::CurrentPage)).unwrap();
+  assert_eq!(res.hits.iter().map(|r| r.id).collect::<Vec<_>>(), vec![1]);
+  let task = eng.search(&session.session_id, query(SearchMode::ScanAll)).unwrap().task.unwrap();
+  for _ in 0..200 {
+    if eng.get_task(&task.id).unwrap().finished {
+      break;
+    }
+    thread::sleep(Duration::from_millis(10));
+  }
+  let hits = eng.search_task_hits_page(&task.id, None, 10).unwrap();
+  assert_eq!(hits.records.iter().map(|r| r.id).collect::<Vec<_>>(), vec![1]);
+
+  let out = dir.path().join("out.jsonl");
+  eng
+    .export(
+      &session.session_id,
+      ExportRequest::Selection { record_ids: vec![0] },
+      ExportFormat::Jsonl,
+      &out,
+    )
+    .unwrap();
+  let line: serde_json::Value = serde_json::from_str(std::fs::read_to_string(&out).unwrap().trim()).unwrap();
+  assert_eq!(line["n"], serde_json::json!(5));
+  assert_eq!(line["text"], serde_json::json!("hello"));
+
+  // CSV: fields are cells; CSV exports append the columns.
+  let csv = dir.path().join("a.csv");
+  std::fs::write(&csv, "name,city\nalice,paris\nbob,rome\n").unwrap();
+  let (csv_session, _p) = eng.open_file(&csv).unwrap();
+  let page = eng
+    .set_derived_columns(&csv_session.session_id, vec![column("name_len", "len(name)")])
+    .unwrap();
+  assert!(page.records[0].derived.is_none());
+  assert_eq!(page.records[1].derived.as_ref().unwrap()["name_len"], serde_json::json!(5));
+  let out = dir.path().join("out.csv");
+  eng
+    .export(
+      &csv_session.session_id,
+      ExportRequest::Selection { record_ids: vec![0, 2] },
+      ExportFormat::Csv,
+      &out,
+    )
+    .unwrap();
+  assert_eq!(std::fs::read_to_string(&out).unwrap(), "name,city,name_len\nbob,rome,3\n");
+
+  assert!(eng
+    .set_derived_columns(&csv_session.session_id, vec![column("bad", "len(name")])
+    .is_err());
+  assert!(eng
+    .set_derived_columns(&csv_session.session_id, vec![column("x", "nope(name)")])
+    .is_err());
+  // Clearing them drops the values.
+  let page = eng.set_derived_columns(&csv_session.session_id, vec![]).unwrap();
+  assert!(page.records.iter().all(|r| r.derived.is_none()));
+}
+
+#[test]
+fn save_record_edit_writes_a_copy_with_the_record_replaced() {
+  let dir = tempfile::tempdir().unwrap();
+  let sqlite = dir.path().join("t.sqlite");
+  let eng = engine_with_sqlite(sqlite);
+
+  let file = dir.path().join("a.jsonl");
+  std::fs::write(&file, "{\"a\":1}\r\n{\"a\":2}\r\n{\"a\":3}").unwrap();
+  let (session, page) = eng.open_file(&file).unwrap();
+  let meta = page.records[1].meta.clone().unwrap();
+  let out = dir.path().join("fixed.jsonl");
+  let res = eng
+    .save_record_edit(&session.session_id, meta.clone(), "{\n  \"a\": 20\n}", &out)
+    .unwrap();
+  assert_eq!(res.records_written, 1);
+  assert_eq!(std::fs::read_to_string(&out).unwrap(), "{\"a\":1}\r\n{\"a\":20}\r\n{\"a\":3}");
+  assert_eq!(std::fs::read_to_string(&file).unwrap(), "{\"a\":1}\r\n{\"a\":2}\r\n{\"a\":3}");
+
+  assert!(eng.save_record_edit(&session.session_id, meta.clone(), "{oops", &out).is_err());
+  assert!(eng.save_record_edit(&session.session_id, meta, "{\"a\":0}", &file).is_err());
+
+  // CSV: the `raw` object form is written back as a row in header order.
+  let csv = dir.path().join("a.csv");
+  std::fs::write(&csv, "name,city\nalice,paris\nbob,rome\n").unwrap();
+  let (csv_session, _p) = eng.open_file(&csv).unwrap();
+  let page = eng.next_page(&csv_session.session_id, None, 3).unwrap();
+  let out = dir.path().join("fixed.csv");
+  eng
+    .save_record_edit(
+      &csv_session.session_id,
+      page.records[2].meta.clone().unwrap(),
+      "{\"city\":\"rome, italy\",\"name\":\"bob\"}",
+      &out,
+    )
+    .unwrap();
+  assert_eq!(std::fs::read_to_string(&out).unwrap(), "name,city\nalice,paris\nbob,\"rome, italy\"\n");
+  assert!(eng
+    .save_record_edit(
+      &csv_session.session_id,
+      page.records[1].meta.clone().unwrap(),
+      "{\"nope\":\"x\"}",
+      &out,
+    )
+    .is_err());
+}
+
+#[test]
+fn reload_session_returns_a_fresh_page_and_rejects_stale_cursors() {
+  let dir = tempfile::tempdir().unwrap();
+  let sqlite = dir.path().join("t.sqlite");
+  let eng = engine_with_sqlite(sqlite);
+  let file = dir.path().join("a.jsonl");
+  std::fs::write(&file, "{\"v\":1}\n{\"v\":2}\n{\"v\":3}\n").unwrap();
+  let (session, p1) = eng.open_file(&file).unwrap();
+  let old_cursor = p1.next_cursor.clone().unwrap();
+
+  // Unchanged file: cursors stay valid.
+  let (_info, same) = eng.reload_session(&session.session_id).unwrap();
+  assert_eq!(same.records[0].raw.as_deref(), Some("{\"v\":1}"));
+  assert!(eng.next_page(&session.session_id, Some(&old_cursor), 2).is_ok());
+
+  std::fs::write(&file, "{\"w\":10}\n{\"w\":20}\n{\"w\":30}\n{\"w\":40}\n").unwrap();
+  let (info, fresh) = eng.reload_session(&session.session_id).unwrap();
+  assert_eq!(info.session_id, session.session_id);
+  assert_eq!(fresh.records[0].raw.as_deref(), Some("{\"w\":10}"));
+  assert!(eng.next_page(&session.session_id, Some(&old_cursor), 2).is_err());
+  let p2 = eng.next_page(&session.session_id, fresh.next_cursor.as_deref(), 2).unwrap();
+  assert_eq!(p2.records[0].raw.as_deref(), Some("{\"w\":30}"));
+  assert_eq!(eng.count_records(&session.session_id).unwrap().total, Some(4));
+}
+
+#[test]
+fn open_stream_spools_piped_input_and_follows_it() {
+  use std::io::Write;
+
+  let dir = tempfile::tempdir().unwrap();
+  let spool = dir.path().join("spool");
+  let eng = CoreEngine::new(CoreOptions {
+    default_page_size: 2,
+    spool_dir: Some(spool.clone()),
+    storage: StorageOptions {
+      sqlite_path: Some(dir.path().join("t.sqlite")),
+      ..StorageOptions::default()
+    },
+    ..CoreOptions::default()
+  })
+  .unwrap();
+
+  let (reader, mut writer) = std::io::pipe().unwrap();
+  writer.write_all(b"{\"n\":0}\n{\"n\":1}\n{\"n\":2}\n").unwrap();
+  let (session, first) = eng.open_stream(reader, FileFormat::Jsonl).unwrap();
+  assert_eq!(first.records.iter().map(|r| r.id).collect::<Vec<_>>(), vec![0, 1]);
+  let stream_task = session.stream_task.clone().unwrap();
+  assert_eq!(stream_task.kind, TaskKind::Spool);
+  assert!(session.path.starts_with(spool.to_str().unwrap()));
+  let count = eng.count_records(&session.session_id).unwrap();
+  assert_eq!((count.total, count.task.map(|t| t.id)), (None, Some(stream_task.id.clone())));
+
+  writer.write_all(b"{\"n\":3}\n").unwrap();
+  let mut ids = Vec::new();
+  for _ in 0..200 {
+    ids.extend(eng.poll_new_records(&session.session_id, 10).unwrap().records.iter().map(|r| r.id));
+    if ids.len() >= 2 {
+      break;
+    }
+    thread::sleep(Duration::from_millis(10));
+  }
+  assert_eq!(ids, vec![2, 3]);
+
+  drop(writer);
+  for _ in 0..200 {
+    if eng.get_task(&stream_task.id).unwrap().finished {
+      break;
+    }
+    thread::sleep(Duration::from_millis(10));
+  }
+  assert_eq!(eng.count_records(&session.session_id).unwrap().total, Some(4));
+  assert_eq!(eng.page_at(&session.session_id, 3, 2).unwrap().records[0].preview, "{\"n\":3}");
+  // Spooling shows in the task event log like any other task.
+  let mut kinds = Vec::new();
+  for _ in 0..200 {
+    kinds = eng.list_task_events(&stream_task.id).unwrap().iter().map(|e| e.event).collect();
+    if kinds.len() >= 2 {
+      break;
+    }
+    thread::sleep(Duration::from_millis(10));
+  }
+  assert_eq!(kinds, vec![TaskEventKind::Started, TaskEventKind::Finished]);
+
+  eng.close_session(&session.session_id).unwrap();
+  assert!(!std::path::Path::new(&session.path).exists());
+  let (reader, _writer) = std::io::pipe().unwrap();
+  assert!(eng.open_stream(reader, FileFormat::Parquet).is_err());
+}
+
+#[test]
+fn open_file_fetches_http_urls_into_a_reused_cache() {
+  use std::io::{BufRead, BufReader, Write};
+  use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+  };
+
+  // Minimal HTTP/1.1 server: HEAD, GET and `Range: bytes=N-` over a fixed body.
+  let body: &'static [u8] = b"{\"n\":0}\n{\"n\":1}\n{\"n\":2}\n";
+  let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+  let url = format!("http://{}/data/events.jsonl", listener.local_addr().unwrap());
+  let gets = Arc::new(AtomicUsize::new(0));
+  let served = gets.clone();
+  thread::spawn(move || {
+    for stream in listener.incoming() {
+      let mut stream = stream.unwrap();
+      let mut reader = BufReader::new(stream.try_clone().unwrap());
+      let mut request = String::new();
+      reader.read_line(&mut request).unwrap();
+      let mut range_start = None;
+      loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        if line.trim().is_empty() {
+          break;
+        }
+        if let Some(v) = line.to_ascii_lowercase().strip_prefix("range: bytes=") {
+          range_start = v.trim().trim_end_matches('-').parse::<usize>().ok();
+        }
+      }
+      let head = request.starts_with("HEAD");
+      if !head {
+        served.fetch_add(1, Ordering::SeqCst);
+      }
+      let (status, part) = match range_start {
+        Some(start) => ("206 Partial Content", &body[start..]),
+        None => ("200 OK", body),
+      };
+      let headers = format!(
+        "HTTP/1.1 {status}\r\nContent-Length: {}\r\nETag: \"v1\"\r\nAccept-Ranges: bytes\r\nConnection: close\r\n\r\n",
+        if head { body.len() } else { part.len() }
+      );
+      stream.write_all(headers.as_bytes()).unwrap();
+      if !head {
+        stream.write_all(part).unwrap();
+      }
+    }
+  });
+
+  let dir = tempfile::tempdir().unwrap();
+  let cache = dir.path().join("cache");
+  let eng = CoreEngine::new(CoreOptions {
+    default_page_size: 2,
+    remote_cache_dir: Some(cache.clone()),
+    storage: StorageOptions {
+      sqlite_path: Some(dir.path().join("t.sqlite")),
+      ..StorageOptions::default()
+    },
+    ..CoreOptions::default()
+  })
+  .unwrap();
+
+  let (session, first) = eng.open_file(&url).unwrap();
+  assert_eq!(session.source_url.as_deref(), Some(url.as_str()));
+  assert_eq!(session.format, FileFormat::Jsonl);
+  assert!(session.path.starts_with(cache.to_str().unwrap()));
+  assert_eq!(first.records[0].preview, "{\"n\":0}");
+  let task = session.stream_task.clone().unwrap();
+  for _ in 0..200 {
+    if eng.get_task(&task.id).unwrap().finished {
+      break;
+    }
+    thread::sleep(Duration::from_millis(10));
+  }
+  assert_eq!(eng.count_records(&session.session_id).unwrap().total, Some(3));
+  assert_eq!(gets.load(Ordering::SeqCst), 1);
+
+  // The complete copy is reused; a truncated one is resumed with a range request.
+  let (again, _p) = eng.open_file(&url).unwrap();
+  assert!(again.stream_task.is_none());
+  assert_eq!(gets.load(Ordering::SeqCst), 1);
+  eng.close_session(&again.session_id).unwrap();
+  eng.close_session(&session.session_id).unwrap();
+  let copy = std::fs::OpenOptions::new().write(true).open(&session.path).unwrap();
+  copy.set_len(8).unwrap();
+  let (resumed, _p) = eng.open_file(&url).unwrap();
+  let task = resumed.stream_task.clone().unwrap();
+  for _ in 0..200 {
+    if eng.get_task(&task.id).unwrap().finished {
+      break;
+    }
+    thread::sleep(Duration::from_millis(10));
+  }
+  assert_eq!(gets.load(Ordering::SeqCst), 2);
+  assert_eq!(std::fs::read(&session.path).unwrap(), body);
+  eng.close_session(&resumed.session_id).unwrap();
+
+  // A copy cut inside a record is followed from the end of its last whole line.
+  let copy = std::fs::OpenOptions::new().write(true).open(&session.path).unwrap();
+  copy.set_len(11).unwrap();
+  let (resumed, _p) = eng.open_file(&url).unwrap();
+  let task = resumed.stream_task.clone().unwrap();
+  for _ in 0..200 {
+    if eng.get_task(&task.id).unwrap().finished {
+      break;
+    }
+    thread::sleep(Duration::from_millis(10));
+  }
+  let polled = eng.poll_new_records(&resumed.session_id, 10).unwrap();
+  assert!(!polled.records.is_empty());
+  for r in &polled.records {
+    assert!(serde_json::from_str::<serde_json::Value>(&r.preview).is_ok(), "{}", r.preview);
+  }
+  assert_eq!(polled.records.last().unwrap().preview, "{\"n\":2}");
+
+  // Cache names keep only the URL's extension, cut down to letters and digits.
+  let odd = url.replace("/data/events.jsonl", "/data/..\\..\\c:evil.js:onl");
+  let (odd, _p) = eng.open_file(&odd).unwrap();
+  let odd = std::path::Path::new(&odd.path);
+  assert_eq!(odd.parent().unwrap(), cache.as_path());
+  assert!(odd.file_name().unwrap().to_str().unwrap().ends_with(".jsonl"));
+  assert!(!odd.file_name().unwrap().to_str().unwrap().contains([':', '\\']));
+}
+
+#[test]
+fn finished_tasks_and_exports_are_kept_in_history_across_restarts() {
+  let dir = tempfile::tempdir().unwrap();
+  let sqlite = dir.path().join("t.sqlite");
+  let file = dir.path().join("a.jsonl");
+  std::fs::write(&file, "aa\nbb\naa\n").unwrap();
+
+  let eng = engine_with_sqlite(sqlite.clone());
+  let (session, _p) = eng.open_file(&file).unwrap();
+  let query = SearchQuery {
+    text: "aa".into(),
+    mode: SearchMode::ScanAll,
+    case_sensitive: true,
+    max_hits: 100,
+    timeout_ms: None,
+    force_rescan: false,
+  };
+  let task_id = eng.search(&session.session_id, query).unwrap().task.unwrap().id;
+  for _ in 0..500 {
+    if eng.get_task(&task_id).unwrap().finished {
+      break;
+    }
+    thread::sleep(Duration::from_millis(10));
+  }
+  let out = dir.path().join("out.jsonl");
+  eng
+    .export(
+      &session.session_id,
+      ExportRequest::Selection { record_ids: vec![1] },
+      ExportFormat::Jsonl,
+      &out,
+    )
+    .unwrap();
+  drop(eng);
+
+  // A new engine on the same SQLite file sees both, newest first.
+  let eng = engine_with_sqlite(sqlite);
+  let history = eng.list_task_history(10).unwrap();
+  assert_eq!(history.len(), 2);
+  assert_eq!(history[0].kind, TaskKind::Export);
+  assert_eq!(history[0].summary.as_deref(), Some("1 records written"));
+  assert_eq!(history[0].params["output_path"], out.to_string_lossy().as_ref());
+  assert_eq!(history[1].kind, TaskKind::SearchScanAll);
+  assert_eq!(history[1].task_id, task_id);
+  assert_eq!(history[1].paths, vec![file.to_string_lossy().to_string()]);
+  assert_eq!(history[1].params["query"]["text"], "aa");
+  assert_eq!(history[1].summary.as_deref(), Some("2 hits"));
+  assert!(history[1].error.is_none() && !history[1].cancelled);
+  assert_eq!(eng.list_task_history(1).unwrap().len(), 1);
+
+  eng.clear_task_history().unwrap();
+  assert!(eng.list_task_history(10).unwrap().is_empty());
+}
+
+#[test]
+fn failed_and_cancelled_tasks_report_typed_errors() {
+  let dir = tempfile::tempdir().unwrap();
+  let sqlite = dir.path().join("t.sqlite");
+  let eng = engine_with_sqlite(sqlite);
+  let wait = |task_id: &str| {
+    for _ in 0..500 {
+      if eng.get_task(task_id).unwrap().finished {
+        break;
+      }
+      thread::sleep(Duration::from_millis(10));
+    }
+    eng.get_task(task_id).unwrap()
+  };
+  let query = SearchQuery {
+    text: "x".into(),
+    mode: SearchMode::ScanAll,
+    case_sensitive: true,
+    max_hits: 100,
+    timeout_ms: None,
+    force_rescan: false,
+  };
+
+  // A truncated .json opens, but scan_all reads values whole.
+  let object = dir.path().join("object.json");
+  std::fs::write(&object, "{\"x\": [1, 2").unwrap();
+  let (session, _p) = eng.open_file(&object).unwrap();
+  let task_id = eng.search(&session.session_id, query.clone()).unwrap().task.unwrap().id;
+  let t = wait(&task_id);
+  assert!(matches!(t.error, Some(TaskError::Format { .. })), "{:?}", t.error);
+  let json = serde_json::to_value(&t.error).unwrap();
+  assert_eq!(json["type"], "format");
+
+  let big = dir.path().join("big.jsonl");
+  std::fs::write(&big, "{\"msg\":\"nothing to see here\"}\n".repeat(400_000)).unwrap();
+  let (session, _p) = eng.open_file(&big).unwrap();
+  let task_id = eng.search(&session.session_id, query).unwrap().task.unwrap().id;
+  eng.cancel_task(&task_id).unwrap();
+  assert_eq!(wait(&task_id).error, Some(TaskError::Cancelled));
+}
+
+#[test]
+fn scan_all_hits_over_the_memory_budget_spill_to_disk() {
+  let dir = tempfile::tempdir().unwrap();
+  let eng = CoreEngine::new(CoreOptions {
+    // Room for a handful of hits; the rest go to a temp file.
+    task_memory_budget_bytes: 500,
+    storage: StorageOptions {
+      sqlite_path: Some(dir.path().join("t.sqlite")),
+      ..StorageOptions::default()
+    },
+    ..CoreOptions::default()
+  })
+  .unwrap();
+  let file = dir.path().join("a.jsonl");
+  let body: String = (0..300).map(|i| format!("{{\"n\":{i},\"tag\":\"hit\"}}\n")).collect();
+  std::fs::write(&file, body).unwrap();
+  let (session, _p) = eng.open_file(&file).unwrap();
+  let query = SearchQuery {
+    text: "hit".into(),
+    mode: SearchMode::ScanAll,
+    case_sensitive: true,
+    max_hits: 1000,
+    timeout_ms: None,
+    force_rescan: false,
+  };
+  let task_id = eng.search(&session.session_id, query).unwrap().task.unwrap().id;
+  for _ in 0..500 {
+    if eng.get_task(&task_id).unwrap().finished {
+      break;
+    }
+    thread::sleep(Duration::from_millis(10));
+  }
+  assert!(eng.get_task(&task_id).unwrap().error.is_none());
+
+  let mut ids = Vec::new();
+  let mut cursor: Option<String> = None;
+  loop {
+    let page = eng.search_task_hits_page(&task_id, cursor.as_deref(), 40).unwrap();
+    for r in &page.records {
+      assert_eq!(r.preview, format!("{{\"n\":{},\"tag\":\"hit\"}}", r.id));
+    }
+    ids.extend(page.records.iter().map(|r| r.id));
+    if page.reached_eof {
+      break;
+    }
+    cursor = page.next_cursor;
+  }
+  assert_eq!(ids, (0..300).collect::<Vec<u64>>());
+
+  // Spilled hits also back filtered sessions.
+  let (filtered, first) = eng.open_search_results(&session.session_id, &task_id).unwrap();
+  assert_eq!(first.records.len(), 10);
+  assert_eq!(eng.count_records(&filtered.session_id).unwrap().total, Some(300));
+}
+
+#[test]
+fn tasks_past_their_timeout_finish_with_partial_results() {
+  let dir = tempfile::tempdir().unwrap();
+  let sqlite = dir.path().join("t.sqlite");
+  let eng = engine_with_sqlite(sqlite);
+  let big = dir.path().join("big.jsonl");
+  std::fs::write(&big, "{\"msg\":\"needle\"}\n".repeat(400_000)).unwrap();
+  let (session, _p) = eng.open_file(&big).unwrap();
+  let wait = |task_id: &str| {
+    for _ in 0..500 {
+      if eng.get_task(task_id).unwrap().finished {
+        break;
+      }
+      thread::sleep(Duration::from_millis(10));
+    }
+    eng.get_task(task_id).unwrap()
+  };
+
+  let query = SearchQuery {
+    text: "needle".into(),
+    mode: SearchMode::ScanAll,
+    case_sensitive: true,
+    max_hits: 1_000_000,
+    timeout_ms: Some(20),
+    force_rescan: false,
+  };
+  let task_id = eng.search(&session.session_id, query).unwrap().task.unwrap().id;
+  let t = wait(&task_id);
+  assert!(t.finished && t.timed_out);
+  assert!(t.error.is_none());
+  assert!(t.records_processed < 400_000);
+
+  let stats = eng.start_stats_task_with_timeout(&session.session_id, Some(20)).unwrap();
+  let t = wait(&stats.id);
+  assert!(t.finished && t.timed_out);
+  assert!(!eng.stats_task_result(&stats.id).unwrap().complete);
+
+  let untimed = eng.start_stats_task(&session.session_id).unwrap();
+  assert!(!wait(&untimed.id).timed_out);
+}
+
+#[test]
+fn repeated_scan_all_on_an_unchanged_file_returns_cached_hits() {
+  let dir = tempfile::tempdir().unwrap();
+  let sqlite = dir.path().join("t.sqlite");
+  let file = dir.path().join("a.jsonl");
+  std::fs::write(&file, "aa\nbb\naa\n").unwrap();
+  let eng = engine_with_sqlite(sqlite.clone());
+  let (session, _p) = eng.open_file(&file).unwrap();
+  let query = SearchQuery {
+    text: "aa".into(),
+    mode: SearchMode::ScanAll,
+    case_sensitive: true,
+    max_hits: 100,
+    timeout_ms: None,
+    force_rescan: false,
+  };
+  let run = |eng: &CoreEngine, session_id: &str, query: SearchQuery| {
+    let task_id = eng.search(session_id, query).unwrap().task.unwrap().id;
+    for _ in 0..500 {
+      if eng.get_task(&task_id).unwrap().finished {
+        break;
+      }
+      thread::sleep(Duration::from_millis(10));
+    }
+    let hits: Vec<u64> = eng
+      .search_task_hits_page(&task_id, None, 10)
+      .unwrap()
+      .records
+      .iter()
+      .map(|r| r.id)
+      .collect();
+    (eng.get_task(&task_id).unwrap(), hits)
+  };
+
+  let (t, hits) = run(&eng, &session.session_id, query.clone());
+  assert!(!t.from_cache);
+  assert_eq!(hits, vec![0, 2]);
+
+  // Kept across restarts, keyed by the file version.
+  drop(eng);
+  let eng = engine_with_sqlite(sqlite);
+  let (session, _p) = eng.open_file(&file).unwrap();
+  let (t, hits) = run(&eng, &session.session_id, query.clone());
+  assert!(t.finished && t.from_cache);
+  assert_eq!(hits, vec![0, 2]);
+
+  let forced = SearchQuery {
+    force_rescan: true,
+    ..query.clone()
+  };
+  assert!(!run(&eng, &session.session_id, forced).0.from_cache);
+
+  thread::sleep(Duration::from_millis(20));
+  std::fs::write(&file, "aa\naa\naa\n").unwrap();
+  let (session, _p) = eng.open_file(&file).unwrap();
+  let (t, hits) = run(&eng, &session.session_id, query);
+  assert!(!t.from_cache);
+  assert_eq!(hits, vec![0, 1, 2]);
+}
+
+#[test]
+fn raising_max_concurrent_tasks_starts_queued_tasks_and_is_kept() {
+  let dir = tempfile::tempdir().unwrap();
+  let options = CoreOptions {
+    max_concurrent_tasks: 1,
+    storage: StorageOptions {
+      sqlite_path: Some(dir.path().join("t.sqlite")),
+      ..StorageOptions::default()
+    },
+    ..CoreOptions::default()
+  };
+  let eng = CoreEngine::new(options.clone()).unwrap();
+  let big = dir.path().join("big.jsonl");
+  std::fs::write(&big, "{\"msg\":\"nothing to see here\"}\n".repeat(400_000)).unwrap();
+  let small = dir.path().join("small.jsonl");
+  std::fs::write(&small, "aa\nbb\naa\n").unwrap();
+  let (big_session, _p) = eng.open_file(&big).unwrap();
+  let (small_session, _p) = eng.open_file(&small).unwrap();
+  let scan = |session_id: &str, text: &str| {
+    let query = SearchQuery {
+      text: text.into(),
+      mode: SearchMode::ScanAll,
+      case_sensitive: true,
+      max_hits: 100,
+      timeout_ms: None,
+      force_rescan: false,
+    };
+    eng.search(session_id, query).unwrap().task.unwrap().id
+  };
+
+  let running = scan(&big_session.session_id, "zzz");
+  eng.pause_task(&running).unwrap();
+  let waiting = scan(&small_session.session_id, "aa");
+  assert!(eng.get_task(&waiting).unwrap().queued);
+
+  assert!(eng.set_max_concurrent_tasks(0).is_err());
+  eng.set_max_concurrent_tasks(2).unwrap();
+  assert_eq!(eng.max_concurrent_tasks(), 2);
+  for _ in 0..500 {
+    if eng.get_task(&waiting).unwrap().finished {
+      break;
+    }
+    thread::sleep(Duration::from_millis(10));
+  }
+  assert!(eng.get_task(&waiting).unwrap().finished);
+  assert!(!eng.get_task(&running).unwrap().finished);
+  eng.cancel_task(&running).unwrap();
+
+  drop(eng);
+  assert_eq!(CoreEngine::new(options).unwrap().max_concurrent_tasks(), 2);
+}
+
+#[test]
+fn count_only_search_counts_matches_without_keeping_hits() {
+  let dir = tempfile::tempdir().unwrap();
+  let file = dir.path().join("a.jsonl");
+  let text: String = (0..1000).map(|i| format!("{{\"n\":{i},\"tag\":\"{}\"}}\n", if i % 3 == 0 { "hit" } else { "miss" })).collect();
+  std::fs::write(&file, text).unwrap();
+  let eng = engine_with_sqlite(dir.path().join("t.sqlite"));
+  let (session, _p) = eng.open_file(&file).unwrap();
+  let res = eng
+    .search(
+      &session.session_id,
+      SearchQuery {
+        text: "\"hit\"".into(),
+        mode: SearchMode::CountOnly,
+        case_sensitive: true,
+        max_hits: 10,
+        timeout_ms: None,
+        force_rescan: false,
+      },
+    )
+    .unwrap();
+  assert_eq!(res.mode, SearchMode::CountOnly);
+  let task = res.task.unwrap();
+  assert_eq!(task.kind, TaskKind::SearchCount);
+  for _ in 0..500 {
+    if eng.get_task(&task.id).unwrap().finished {
+      break;
+    }
+    thread::sleep(Duration::from_millis(10));
+  }
+  // Not capped by max_hits, and no hits to page through.
+  let count = eng.search_match_count(&task.id).unwrap();
+  assert_eq!(count, SearchCount { matches: 334, complete: true });
+  assert!(eng.search_task_hits_page(&task.id, None, 10).is_err());
+}
+
+#[test]
+fn scan_all_tasks_report_records_scanned_and_hits_found() {
+  let dir = tempfile::tempdir().unwrap();
+  let file = dir.path().join("a.jsonl");
+  let text: String = (0..100).map(|i| format!("{{\"n\":{i},\"even\":{}}}\n", i % 2 == 0)).collect();
+  std::fs::write(&file, &text).unwrap();
+  let eng = engine_with_sqlite(dir.path().join("t.sqlite"));
+  let (session, _p) = eng.open_file(&file).unwrap();
+  let task_id = eng
+    .search(
+      &session.session_id,
+      SearchQuery {
+        text: "true".into(),
+        mode: SearchMode::ScanAll,
+        case_sensitive: true,
+        max_hits: 5,
+        timeout_ms: None,
+        force_rescan: false,
+      },
+    )
+    .unwrap()
+    .task
+    .unwrap()
+    .id;
+  let mut t = eng.get_task(&task_id).unwrap();
+  for _ in 0..500 {
+    if t.finished {
+      break;
+    }
+    thread::sleep(Duration::from_millis(10));
+    t = eng.get_task(&task_id).unwrap();
+  }
+  // Counts every match, not just the max_hits kept.
+  assert_eq!(t.hits_found, Some(50));
+  assert_eq!(t.records_processed, 100);
+  assert_eq!(t.bytes_processed, text.len() as u64);
+  assert_eq!(eng.search_task_hits_page(&task_id, None, 10).unwrap().records.len(), 5);
+
+  let stats_task = eng.start_stats_task(&session.session_id).unwrap();
+  assert_eq!(eng.get_task(&stats_task.id).unwrap().hits_found, None);
+}
+
+#[test]
+fn queued_background_tasks_start_after_normal_ones() {
+  let dir = tempfile::tempdir().unwrap();
+  let eng = CoreEngine::new(CoreOptions {
+    max_concurrent_tasks: 1,
+    storage: StorageOptions {
+      sqlite_path: Some(dir.path().join("t.sqlite")),
+      ..StorageOptions::default()
+    },
+    ..CoreOptions::default()
+  })
+  .unwrap();
+  let big = dir.path().join("big.jsonl");
+  std::fs::write(&big, "{\"msg\":\"nothing to see here\"}\n".repeat(400_000)).unwrap();
+  let small = dir.path().join("small.jsonl");
+  std::fs::write(&small, "aa\nbb\naa\n").unwrap();
+  let (big_session, _p) = eng.open_file(&big).unwrap();
+  let (small_session, _p) = eng.open_file(&small).unwrap();
+  let scan = |session_id: &str, text: &str| {
+    let query = SearchQuery {
+      text: text.into(),
+      mode: SearchMode::ScanAll,
+      case_sensitive: true,
+      max_hits: 100,
+      timeout_ms: None,
+      force_rescan: false,
+    };
+    eng.search(session_id, query).unwrap().task.unwrap().id
+  };
+
+  let running = scan(&big_session.session_id, "zzz");
+  eng.pause_task(&running).unwrap();
+  let stats = eng.start_stats_task(&small_session.session_id).unwrap().id;
+  let search = scan(&small_session.session_id, "aa");
+  assert_eq!(eng.get_task(&stats).unwrap().priority, TaskPriority::Background);
+  assert_eq!(eng.get_task(&search).unwrap().priority, TaskPriority::Normal);
+  // Holds the search once it starts, so the stats task stays queued behind it.
+  eng.pause_task(&search).unwrap();
+
+  eng.cancel_task(&running).unwrap();
+  for _ in 0..500 {
+    if !eng.get_task(&search).unwrap().queued {
+      break;
+    }
+    thread::sleep(Duration::from_millis(10));
+  }
+  assert!(!eng.get_task(&search).unwrap().queued);
+  assert!(eng.get_task(&stats).unwrap().queued);
+
+  // Paging still works while background work waits.
+  assert!(!eng.next_page(&small_session.session_id, None, 10).unwrap().records.is_empty());
+  eng.resume_task(&search).unwrap();
+  for _ in 0..500 {
+    if eng.get_task(&stats).unwrap().finished {
+      break;
+    }
+    thread::sleep(Duration::from_millis(10));
+  }
+  assert!(eng.get_task(&search).unwrap().finished);
+  assert!(eng.get_task(&stats).unwrap().finished);
+}
+
+#[test]
+fn task_events_log_start_progress_and_how_tasks_ended() {
+  let dir = tempfile::tempdir().unwrap();
+  let file = dir.path().join("a.jsonl");
+  let text: String = (0..20_000)
+    .map(|i| format!("{{\"n\":{i},\"tag\":\"{}\"}}\n", if i % 4 == 0 { "hit" } else { "miss" }))
+    .collect();
+  std::fs::write(&file, text).unwrap();
+  let eng = engine_with_sqlite(dir.path().join("t.sqlite"));
+  let (session, _p) = eng.open_file(&file).unwrap();
+  let scan = |text: &str| {
+    let query = SearchQuery {
+      text: text.into(),
+      mode: SearchMode::ScanAll,
+      case_sensitive: true,
+      max_hits: 3,
+      timeout_ms: None,
+      force_rescan: false,
+    };
+    let task_id = eng.search(&session.session_id, query).unwrap().task.unwrap().id;
+    for _ in 0..500 {
+      if eng.get_task(&task_id).unwrap().finished {
+        break;
+      }
+      thread::sleep(Duration::from_millis(10));
+    }
+    task_id
+  };
+
+  let done = scan("\"hit\"");
+  let events = eng.list_task_events(&done).unwrap();
+  let kinds: Vec<TaskEventKind> = events.iter().map(|e| e.event).collect();
+  assert_eq!(kinds.first(), Some(&TaskEventKind::Started));
+  assert!(kinds.contains(&TaskEventKind::Progress));
+  let end = events.last().unwrap();
+  assert_eq!(end.event, TaskEventKind::Finished);
+  assert_eq!(end.records_processed, 20_000);
+  // Shows that far more matched than the 3 hits kept.
+  assert_eq!(end.hits_found, Some(5_000));
+  assert_eq!(end.detail.as_deref(), Some("3 hits (truncated)"));
+
+  eng.clear_task_history().unwrap();
+  assert!(eng.list_task_events(&done).unwrap().is_empty());
+}
+
+#[test]
+fn shutdown_cancels_tasks_and_refuses_new_work() {
+  let dir = tempfile::tempdir().unwrap();
+  let eng = CoreEngine::new(CoreOptions {
+    max_concurrent_tasks: 1,
+    storage: StorageOptions {
+      sqlite_path: Some(dir.path().join("t.sqlite")),
+      ..StorageOptions::default()
+    },
+    ..CoreOptions::default()
+  })
+  .unwrap();
+  let big = dir.path().join("big.jsonl");
+  std::fs::write(&big, "{\"msg\":\"nothing to see here\"}\n".repeat(400_000)).unwrap();
+  let (session, _p) = eng.open_file(&big).unwrap();
+  let scan = || {
+    let query = SearchQuery {
+      text: "zzz".into(),
+      mode: SearchMode::ScanAll,
+      case_sensitive: true,
+      max_hits: 100,
+      timeout_ms: None,
+      force_rescan: false,
+    };
+    eng.search(&session.session_id, query).unwrap().task.unwrap().id
+  };
+  let running = scan();
+  eng.pause_task(&running).unwrap();
+  let queued = scan();
+  assert!(eng.get_task(&queued).unwrap().queued);
+
+  eng.shutdown();
+  for id in [&running, &queued] {
+    let t = eng.get_task(id).unwrap();
+    assert!(t.finished);
+    assert_eq!(t.error, Some(TaskError::Cancelled));
+  }
+  let after = eng.get_task(&scan()).unwrap();
+  assert!(after.finished && after.error == Some(TaskError::Cancelled));
+
+  let out = dir.path().join("out.jsonl");
+  let res = eng.export(
+    &session.session_id,
+    ExportRequest::Selection { record_ids: vec![0, 1] },
+    ExportFormat::Jsonl,
+    &out,
+  );
+  assert!(res.is_err());
+  assert!(!out.exists());
+  assert!(!dir.path().join("out.jsonl.partial").exists());
+}
+
+#[test]
+fn recent_files_can_be_listed_pinned_and_removed() {
+  let dir = tempfile::tempdir().unwrap();
+  let eng = engine_with_sqlite(dir.path().join("t.sqlite"));
+  let a = dir.path().join("a.jsonl");
+  let b = dir.path().join("b.jsonl");
+  std::fs::write(&a, "{\"a\":1}\n").unwrap();
+  std::fs::write(&b, "{\"b\":1}\n").unwrap();
+  eng.open_file(&a).unwrap();
+  thread::sleep(Duration::from_millis(5));
+  eng.open_file(&b).unwrap();
+  let a = a.to_string_lossy().to_string();
+  let b = b.to_string_lossy().to_string();
+
+  let paths = |eng: &CoreEngine| eng.list_recent(10).unwrap().into_iter().map(|r| r.path).collect::<Vec<_>>();
+  assert_eq!(paths(&eng), vec![b.clone(), a.clone()]);
+
+  eng.pin_recent(&a, true).unwrap();
+  let recent = eng.list_recent(10).unwrap();
+  assert_eq!(recent[0].path, a);
+  assert!(recent[0].pinned && recent[0].exists);
+  assert_eq!(recent[0].display_name, "a.jsonl");
+
+  eng.remove_recent(&a).unwrap();
+  assert_eq!(paths(&eng), vec![b]);
+}
+
+#[test]
+fn prune_recent_drops_missing_unpinned_files_and_clear_empties_the_list() {
+  let dir = tempfile::tempdir().unwrap();
+  let eng = engine_with_sqlite(dir.path().join("t.sqlite"));
+  let keep = dir.path().join("keep.jsonl");
+  let gone = dir.path().join("gone.jsonl");
+  let pinned = dir.path().join("pinned.jsonl");
+  for p in [&keep, &gone, &pinned] {
+    std::fs::write(p, "{\"a\":1}\n").unwrap();
+    eng.open_file(p).unwrap();
+  }
+  eng.pin_recent(&pinned.to_string_lossy(), true).unwrap();
+  std::fs::remove_file(&gone).unwrap();
+  std::fs::remove_file(&pinned).unwrap();
+
+  assert_eq!(eng.prune_recent().unwrap(), 1);
+  let recent = eng.list_recent(10).unwrap();
+  assert_eq!(recent.len(), 2);
+  assert_eq!(recent[0].path, pinned.to_string_lossy());
+  assert!(!recent[0].exists);
+  assert!(recent[1].exists);
+
+  eng.clear_recent().unwrap();
+  assert!(eng.list_recent(10).unwrap().is_empty());
+}
+
+#[test]
+fn saved_workspace_reopens_its_tabs_after_restart_and_skips_missing_files() {
+  let dir = tempfile::tempdir().unwrap();
+  let sqlite = dir.path().join("t.sqlite");
+  let a = dir.path().join("a.jsonl");
+  let b = dir.path().join("b.jsonl");
+  let c = dir.path().join("c.jsonl");
+  for p in [&a, &b, &c] {
+    std::fs::write(p, "{\"x\":1}\n{\"x\":2}\n").unwrap();
+  }
+  let eng = engine_with_sqlite(sqlite.clone());
+  let (sa, _) = eng.open_file(&a).unwrap();
+  let (sb, _) = eng.open_file(&b).unwrap();
+  let (sc, _) = eng.open_file(&c).unwrap();
+  let saved = eng
+    .save_workspace(
+      &[sa.session_id.clone(), sb.session_id.clone(), sc.session_id.clone()],
+      Some(&sc.session_id),
+    )
+    .unwrap();
+  assert_eq!(saved.tabs.len(), 3);
+  assert_eq!(saved.active_tab, Some(2));
+  drop(eng);
+
+  std::fs::remove_file(&b).unwrap();
+  let eng = engine_with_sqlite(sqlite);
+  let restored = eng.restore_workspace().unwrap();
+  let paths: Vec<&str> = restored.tabs.iter().map(|t| t.session.path.as_str()).collect();
+  assert_eq!(paths, vec![a.to_str().unwrap(), c.to_str().unwrap()]);
+  assert_eq!(restored.tabs[1].page.records.len(), 2);
+  assert_eq!(restored.active_tab, Some(1));
+  assert_eq!(restored.missing, vec![WorkspaceTab { paths: vec![b.to_string_lossy().to_string()] }]);
+}
+
+#[test]
+fn collections_are_saved_listed_and_opened_together() {
+  let dir = tempfile::tempdir().unwrap();
+  let eng = engine_with_sqlite(dir.path().join("t.sqlite"));
+  let shard0 = dir.path().join("train-0.jsonl");
+  let shard1 = dir.path().join("train-1.jsonl");
+  let docs = dir.path().join("README.md");
+  std::fs::write(&shard0, "{\"x\":1}\n").unwrap();
+  std::fs::write(&shard1, "{\"x\":2}\n").unwrap();
+  std::fs::write(&docs, "# train\n").unwrap();
+  let members: Vec<String> = [&shard0, &shard1, &docs].iter().map(|p| p.to_string_lossy().to_string()).collect();
+
+  assert!(eng.save_collection("  ", members.clone()).is_err());
+  let saved = eng.save_collection("train", members.clone()).unwrap();
+  assert_eq!(saved.paths, members);
+  eng.save_collection("other", vec![]).unwrap();
+  let names: Vec<String> = eng.list_collections().unwrap().into_iter().map(|c| c.name).collect();
+  assert_eq!(names, vec!["other", "train"]);
+
+  std::fs::remove_file(&shard1).unwrap();
+  let opened = eng.open_collection("train").unwrap();
+  // Docs are kept with the dataset but are not something the viewer opens.
+  assert_eq!(opened.tabs.len(), 1);
+  assert_eq!(opened.tabs[0].session.path, members[0]);
+  assert_eq!(opened.tabs[0].page.records.len(), 1);
+  assert_eq!(opened.skipped, vec![members[1].clone(), members[2].clone()]);
+
+  eng.delete_collection("train").unwrap();
+  assert!(eng.open_collection("train").is_err());
+  assert!(eng.delete_collection("train").is_err());
+}
+
+#[test]
+fn view_prefs_are_applied_and_returned_when_the_file_is_reopened() {
+  let dir = tempfile::tempdir().unwrap();
+  let sqlite = dir.path().join("t.sqlite");
+  let file = dir.path().join("a.csv");
+  let text: String = std::iter::once("id;name\n".to_string())
+    .chain((0..30).map(|i| format!("{i};n{i}\n")))
+    .collect();
+  std::fs::write(&file, text).unwrap();
+  let eng = engine_with_sqlite(sqlite.clone());
+  let (session, _p) = eng.open_file(&file).unwrap();
+  assert_eq!(session.view_prefs, None);
+  let csv = CsvDialect {
+    delimiter: ";".into(),
+    ..CsvDialect::default()
+  };
+  let prefs = ViewPrefs {
+    csv: Some(csv.clone()),
+    encoding: Some(TextEncoding::Latin1),
+    columns: Some(vec!["name".into()]),
+    page_size: Some(5),
+    json_lenient: None,
+  };
+  eng.save_view_prefs(&session.session_id, prefs.clone()).unwrap();
+  drop(eng);
+
+  let eng = engine_with_sqlite(sqlite.clone());
+  let (session, page) = eng.open_file(&file).unwrap();
+  assert_eq!(session.view_prefs, Some(prefs));
+  assert_eq!(session.encoding, TextEncoding::Latin1);
+  // The saved dialect splits the header, so the saved column is found and shown.
+  assert_eq!(session.csv, csv);
+  assert_eq!(session.columns, Some(vec!["name".to_string()]));
+  assert_eq!(page.records.len(), 5);
+  assert_eq!(page.records[1].raw.as_deref(), Some(r#"{"name":"n0"}"#));
+
+  // A dialect that can't be used any more is left out.
+  let bad = ViewPrefs {
+    csv: Some(CsvDialect {
+      delimiter: String::new(),
+      ..CsvDialect::default()
+    }),
+    ..ViewPrefs::default()
+  };
+  eng.save_view_prefs(&session.session_id, bad).unwrap();
+  drop(eng);
+  let eng = engine_with_sqlite(sqlite);
+  let (session, _page) = eng.open_file(&file).unwrap();
+  assert_eq!(session.csv, CsvDialect::default());
+}
+
+#[test]
+fn exports_from_the_history_can_be_run_again() {
+  let dir = tempfile::tempdir().unwrap();
+  let file = dir.path().join("a.jsonl");
+  std::fs::write(&file, "{\"v\":0}\n{\"v\":1}\n{\"v\":2}\n").unwrap();
+  let eng = engine_with_sqlite(dir.path().join("t.sqlite"));
+  let (session, _p) = eng.open_file(&file).unwrap();
+  let out = dir.path().join("out.jsonl");
+  eng
+    .export(
+      &session.session_id,
+      ExportRequest::Selection { record_ids: vec![0, 2] },
+      ExportFormat::Jsonl,
+      &out,
+    )
+    .unwrap();
+  eng.start_stats_task(&session.session_id).unwrap();
+
+  let exports = eng.list_export_history(10).unwrap();
+  assert_eq!(exports.len(), 1);
+  assert_eq!(exports[0].kind, TaskKind::Export);
+
+  // The source changed since; the re-run picks up the current records.
+  std::fs::write(&file, "{\"v\":10}\n{\"v\":11}\n{\"v\":12}\n").unwrap();
+  let res = eng.rerun_export(exports[0].id).unwrap();
+  assert_eq!(res.records_written, 2);
+  assert_eq!(std::fs::read_to_string(&out).unwrap(), "{\"v\":10}\n{\"v\":12}\n");
+  assert_eq!(eng.list_export_history(10).unwrap().len(), 2);
+
+  assert!(eng.rerun_export(-1).is_err());
+}
+
+#[test]
+fn export_presets_project_and_flatten_fields() {
+  let dir = tempfile::tempdir().unwrap();
+  let file = dir.path().join("events.jsonl");
+  std::fs::write(
+    &file,
+    "{\"id\":1,\"user\":{\"name\":\"a,b\",\"age\":3},\"x\":true}\n{\"id\":2,\"user\":{\"name\":\"c\"}}\n",
+  )
+  .unwrap();
+  let eng = engine_with_sqlite(dir.path().join("t.sqlite"));
+  let (session, _p) = eng.open_file(&file).unwrap();
+
+  let preset = eng
+    .save_export_preset(ExportPreset {
+      name: " users ".into(),
+      format: ExportFormat::Csv,
+      columns: Some(vec!["id".into(), "user.name".into(), "user.age".into()]),
+      flatten: true,
+      destination: Some("out/{name}-users.{format}".into()),
+    })
+    .unwrap();
+  assert_eq!(preset.name, "users");
+  assert_eq!(eng.list_export_presets().unwrap(), vec![preset]);
+
+  let res = eng
+    .export_with_preset(
+      &session.session_id,
+      ExportRequest::Selection { record_ids: vec![0, 1] },
+      "users",
+      None,
+    )
+    .unwrap();
+  let out = dir.path().join("out").join("events-users.csv");
+  assert_eq!(PathBuf::from(&res.output_path), out);
+  assert_eq!(res.records_written, 2);
+  assert_eq!(
+    std::fs::read_to_string(&out).unwrap(),
+    "id,user.name,user.age\n1,\"a,b\",3\n2,c,\n"
+  );
+
+  // The history keeps the preset, so deleting it doesn't stop a re-run.
+  eng.delete_export_preset("users").unwrap();
+  assert!(eng.delete_export_preset("users").is_err());
+  std::fs::remove_file(&out).unwrap();
+  let entry = &eng.list_export_history(10).unwrap()[0];
+  eng.rerun_export(entry.id).unwrap();
+  assert!(std::fs::read_to_string(&out).unwrap().starts_with("id,user.name,user.age\n"));
+
+  assert!(eng
+    .export_with_preset(&session.session_id, ExportRequest::Selection { record_ids: vec![0] }, "users", None)
+    .is_err());
+}
+
+#[test]
+fn storage_migrates_databases_from_before_schema_versioning() {
+  let dir = tempfile::tempdir().unwrap();
+  let db = dir.path().join("old.sqlite");
+  {
+    let conn = rusqlite::Connection::open(&db).unwrap();
+    conn
+      .execute_batch(
+        "CREATE TABLE recent_files(id INTEGER PRIMARY KEY AUTOINCREMENT, path TEXT NOT NULL UNIQUE, \
+         display_name TEXT NOT NULL, last_opened_at INTEGER NOT NULL, exists_flag INTEGER NOT NULL, \
+         pinned INTEGER NOT NULL DEFAULT 0);
+         INSERT INTO recent_files(path, display_name, last_opened_at, exists_flag, pinned)
+         VALUES('/data/a.jsonl', 'a.jsonl', 1, 0, 1);",
+      )
+      .unwrap();
+  }
+
+  let storage = Storage::new(StorageOptions {
+    sqlite_path: Some(db.clone()),
+    ..StorageOptions::default()
+  })
+  .unwrap();
+  let version = storage.schema_version().unwrap();
+  assert!(version >= 1);
+  let recent = storage.list_recent(10).unwrap();
+  assert_eq!(recent.len(), 1);
+  assert!(recent[0].pinned);
+  // Tables added later exist too.
+  storage.set_setting_json("k", "1").unwrap();
+
+  // Reopening applies nothing twice.
+  let again = Storage::new(StorageOptions {
+    sqlite_path: Some(db.clone()),
+    ..StorageOptions::default()
+  })
+  .unwrap();
+  assert_eq!(again.schema_version().unwrap(), version);
+
+  // A database from a newer app is refused rather than half-used.
+  rusqlite::Connection::open(&db)
+    .unwrap()
+    .execute("INSERT INTO schema_version(version, applied_at) VALUES(999, 0)", [])
+    .unwrap();
+  assert!(Storage::new(StorageOptions {
+    sqlite_path: Some(db),
+    ..StorageOptions::default()
+  })
+  .is_err());
+}
+
+#[test]
+fn storage_maintenance_trims_history_and_reports_size() {
+  let dir = tempfile::tempdir().unwrap();
+  let file = dir.path().join("a.jsonl");
+  std::fs::write(&file, "{\"v\":0}\n{\"v\":1}\n").unwrap();
+  let eng = engine_with_sqlite(dir.path().join("t.sqlite"));
+  let (session, _p) = eng.open_file(&file).unwrap();
+  for i in 0..3 {
+    eng
+      .export(
+        &session.session_id,
+        ExportRequest::Selection { record_ids: vec![0] },
+        ExportFormat::Jsonl,
+        dir.path().join(format!("out{i}.jsonl")),
+      )
+      .unwrap();
+  }
+
+  let report = eng.storage_report().unwrap();
+  assert_eq!(report.task_history_rows, 3);
+  assert!(report.size_bytes > 0);
+
+  let done = eng
+    .maintain_storage(Some(StorageLimits {
+      max_task_history: 1,
+      ..StorageLimits::default()
+    }))
+    .unwrap();
+  assert_eq!(done.before, report);
+  assert_eq!(done.rows_removed, 2);
+  assert_eq!(done.after.task_history_rows, 1);
+  assert_eq!(done.after.free_bytes, 0);
+  let kept = &eng.list_export_history(10).unwrap()[0];
+  assert_eq!(kept.params["output_path"], dir.path().join("out2.jsonl").to_string_lossy().as_ref());
+}
+
+#[test]
+fn storage_location_comes_from_flags() {
+  let args = |list: &[&str]| list.iter().map(std::ffi::OsString::from).collect::<Vec<_>>();
+  let opts = StorageOptions::from_args_and_env(&args(&["a.jsonl", "--storage", "/tmp/x.sqlite"]));
+  assert_eq!(opts.sqlite_path, Some(PathBuf::from("/tmp/x.sqlite")));
+  let opts = StorageOptions::from_args_and_env(&args(&["--storage=/tmp/y.sqlite", "--portable"]));
+  assert_eq!(opts.sqlite_path, Some(PathBuf::from("/tmp/y.sqlite")));
+
+  let exe_dir = std::env::current_exe().unwrap().parent().unwrap().to_path_buf();
+  let opts = StorageOptions::from_args_and_env(&args(&["--portable"]));
+  assert_eq!(opts.sqlite_path, Some(exe_dir.join("data").join("storage.sqlite")));
+
+  let dir = tempfile::tempdir().unwrap();
+  let db = dir.path().join("nested").join("s.sqlite");
+  let storage = Storage::new(StorageOptions::from_args_and_env(&args(&[
+    "--storage",
+    db.to_str().unwrap(),
+  ])))
+  .unwrap();
+  assert_eq!(storage.path(), db);
+  assert!(db.is_file());
+
+  // The passphrase is read from a key file, not taken from the command line.
+  let key = dir.path().join("key.txt");
+  std::fs::write(&key, "s3cret\n").unwrap();
+  let opts = StorageOptions::from_args_and_env(&args(&["--storage-key-file", key.to_str().unwrap()]));
+  assert_eq!(opts.passphrase.as_deref(), Some("s3cret"));
+  let flag = format!("--storage-key-file={}", key.display());
+  assert_eq!(StorageOptions::from_args_and_env(&args(&[&flag])).passphrase.as_deref(), Some("s3cret"));
+}
+
+#[test]
+fn storage_backup_restores_setup_on_another_database() {
+  let dir = tempfile::tempdir().unwrap();
+  let old = engine_with_sqlite(dir.path().join("old.sqlite"));
+  old.pin_recent("/data/a.jsonl", true).unwrap();
+  old.save_collection("shards", vec!["/data/a.jsonl".into(), "/data/b.jsonl".into()]).unwrap();
+  old
+    .save_export_preset(ExportPreset {
+      name: "csv".into(),
+      format: ExportFormat::Csv,
+      columns: None,
+      flatten: true,
+      destination: None,
+    })
+    .unwrap();
+  old.storage().set_setting_json("theme", "\"dark\"").unwrap();
+
+  let backup_path = dir.path().join("backup").join("setup.json");
+  let backup = old.backup_storage(&backup_path).unwrap();
+  assert_eq!(backup.rows["recent_files"], 1);
+  assert_eq!(backup.rows["collections"], 1);
+
+  let new = engine_with_sqlite(dir.path().join("new.sqlite"));
+  new.save_collection("local", vec!["/x.csv".into()]).unwrap();
+  let restored = new.restore_storage(&backup_path).unwrap();
+  assert_eq!(restored.rows, backup.rows);
+
+  let names: Vec<String> = new.list_collections().unwrap().into_iter().map(|c| c.name).collect();
+  assert_eq!(names, ["local", "shards"]);
+  assert_eq!(new.list_export_presets().unwrap(), old.list_export_presets().unwrap());
+  let recent = new.list_recent(10).unwrap();
+  assert_eq!(recent.len(), 1);
+  assert!(recent[0].pinned);
+  assert_eq!(new.storage().get_setting_json("theme").unwrap().as_deref(), Some("\"dark\""));
+
+  let not_backup = dir.path().join("other.json");
+  std::fs::write(&not_backup, "{\"tables\":{}}").unwrap();
+  assert!(new.restore_storage(&not_backup).is_err());
+}
+
+#[test]
+fn recent_folders_are_listed_apart_from_files() {
+  let dir = tempfile::tempdir().unwrap();
+  let eng = engine_with_sqlite(dir.path().join("t.sqlite"));
+  let a = dir.path().join("a");
+  let b = dir.path().join("b");
+  std::fs::create_dir(&a).unwrap();
+  std::fs::create_dir(&b).unwrap();
+
+  eng.touch_recent_folder(a.to_str().unwrap()).unwrap();
+  thread::sleep(Duration::from_millis(2));
+  eng.touch_recent_folder(b.to_str().unwrap()).unwrap();
+  eng.pin_recent_folder(a.to_str().unwrap(), true).unwrap();
+
+  let folders = eng.list_recent_folders(10).unwrap();
+  let names: Vec<&str> = folders.iter().map(|f| f.display_name.as_str()).collect();
+  assert_eq!(names, ["a", "b"]);
+  assert!(folders[0].pinned && folders[0].exists);
+  assert!(eng.list_recent(10).unwrap().is_empty());
+
+  eng.remove_recent_folder(a.to_str().unwrap()).unwrap();
+  assert_eq!(eng.list_recent_folders(10).unwrap().len(), 1);
+  assert!(eng.storage().schema_version().unwrap() >= 2);
+}
+
+#[test]
+fn reopening_a_file_can_resume_at_the_last_read_position() {
+  let dir = tempfile::tempdir().unwrap();
+  let file = dir.path().join("a.jsonl");
+  let lines: String = (0..10).map(|i| format!("{{\"n\":{i}}}\n")).collect();
+  std::fs::write(&file, &lines).unwrap();
+  let eng = engine_with_sqlite(dir.path().join("t.sqlite"));
+
+  let (session, _p) = eng.open_file(&file).unwrap();
+  assert_eq!(session.read_position, None);
+  assert!(eng.page_at_read_position(&session.session_id, 2).is_err());
+  eng.page_at(&session.session_id, 6, 2).unwrap();
+  eng.close_session(&session.session_id).unwrap();
+
+  let (session, first) = eng.open_file(&file).unwrap();
+  assert_eq!(first.records[0].id, 0);
+  let position = session.read_position.clone().unwrap();
+  assert_eq!(position.record_index, 6);
+  assert_eq!(position.byte_offset, Some(lines.find("{\"n\":6}").unwrap() as u64));
+  let page = eng.page_at_read_position(&session.session_id, 2).unwrap();
+  assert_eq!(page.records[0].id, 6);
+  assert!(page.records[0].preview.contains("\"n\":6"));
+  eng.close_session(&session.session_id).unwrap();
+
+  // A changed file starts over.
+  std::fs::write(&file, format!("{lines}{{\"n\":10}}\n")).unwrap();
+  let (session, _p) = eng.open_file(&file).unwrap();
+  assert_eq!(session.read_position, None);
+}
+
+#[test]
+fn storage_with_a_passphrase_keeps_sensitive_fields_encrypted() {
+  let dir = tempfile::tempdir().unwrap();
+  let db = dir.path().join("t.sqlite");
+  let options = |passphrase: Option<&str>| StorageOptions {
+    sqlite_path: Some(db.clone()),
+    passphrase: passphrase.map(str::to_string),
+  };
+
+  // Data stored before encryption is turned on is kept, and sealed.
+  let plain = Storage::new(options(None)).unwrap();
+  plain.touch_recent("/secret/plain.jsonl", Some(true)).unwrap();
+  assert!(!plain.is_encrypted());
+  drop(plain);
+
+  let storage = Storage::new(options(Some("hunter2"))).unwrap();
+  assert!(storage.is_encrypted() && storage.report().unwrap().encrypted);
+  // Nothing of the plain value is left on disk, in the database or its WAL.
+  for file in [db.clone(), dir.path().join("t.sqlite-wal")] {
+    let bytes = std::fs::read(&file).unwrap_or_default();
+    assert!(!bytes.windows(b"/secret/plain".len()).any(|w| w == b"/secret/plain"), "{}", file.display());
+  }
+  storage.touch_recent("/secret/later.jsonl", None).unwrap();
+  storage.set_setting_json("theme", "\"dark\"").unwrap();
+  let recent = storage.list_recent(10).unwrap();
+  let paths: Vec<&str> = recent.iter().map(|r| r.path.as_str()).collect();
+  assert_eq!(paths, ["/secret/plain.jsonl", "/secret/later.jsonl"]);
+  storage.set_recent_pinned("/secret/later.jsonl", true).unwrap();
+  assert!(storage.list_recent(10).unwrap().iter().all(|r| r.pinned));
+
+  {
+    let conn = rusqlite::Connection::open(&db).unwrap();
+    let raw: Vec<String> = conn
+      .prepare("SELECT path || display_name FROM recent_files UNION ALL SELECT value_json FROM settings")
+      .unwrap()
+      .query_map([], |row| row.get(0))
+      .unwrap()
+      .map(Result::unwrap)
+      .collect();
+    assert_eq!(raw.len(), 3);
+    assert!(raw.iter().all(|v| !v.contains("secret") && !v.contains("dark")));
+  }
+
+  let again = Storage::new(options(Some("hunter2"))).unwrap();
+  assert_eq!(again.get_setting_json("theme").unwrap().as_deref(), Some("\"dark\""));
+  assert!(Storage::new(options(None)).is_err());
+  assert!(Storage::new(options(Some("wrong"))).is_err());
+
+  // Backups are plain and restore into plain storage.
+  let backup = dir.path().join("backup.json");
+  again.backup_to(&backup).unwrap();
+  let other = Storage::new(StorageOptions {
+    sqlite_path: Some(dir.path().join("other.sqlite")),
+    ..StorageOptions::default()
+  })
+  .unwrap();
+  other.restore_from(&backup).unwrap();
+  assert_eq!(other.list_recent(10).unwrap().len(), 2);
+}
+
+#[test]
+fn storage_clones_share_one_wal_connection_across_threads() {
+  let dir = tempfile::tempdir().unwrap();
+  let db = dir.path().join("t.sqlite");
+  let storage = Storage::new(StorageOptions {
+    sqlite_path: Some(db.clone()),
+    ..StorageOptions::default()
+  })
+  .unwrap();
+
+  let workers: Vec<_> = (0..8)
+    .map(|i| {
+      let storage = storage.clone();
+      thread::spawn(move || {
+        for j in 0..20 {
+          storage.touch_recent(&format!("/data/{i}-{j}.jsonl"), None).unwrap();
+          storage.set_setting_json(&format!("k{i}"), &j.to_string()).unwrap();
+        }
+      })
+    })
+    .collect();
+  for w in workers {
+    w.join().unwrap();
+  }
+  assert_eq!(storage.list_recent(500).unwrap().len(), 160);
+  assert_eq!(storage.get_setting_json("k3").unwrap().as_deref(), Some("19"));
+
+  let mode: String = rusqlite::Connection::open(&db)
+    .unwrap()
+    .query_row("PRAGMA journal_mode", [], |row| row.get(0))
+    .unwrap();
+  assert_eq!(mode, "wal");
+}
+
+#[test]
+fn exports_cut_short_by_a_crash_are_cleaned_up_and_listed_on_startup() {
+  let dir = tempfile::tempdir().unwrap();
+  let db = dir.path().join("t.sqlite");
+  let file = dir.path().join("a.jsonl");
+  std::fs::write(&file, "{\"n\":1}\n{\"n\":2}\n").unwrap();
+
+  let eng = engine_with_sqlite(db.clone());
+  let (session, _p) = eng.open_file(&file).unwrap();
+  let out = dir.path().join("out.jsonl");
+  eng
+    .export(
+      &session.session_id,
+      ExportRequest::Selection { record_ids: vec![0, 1] },
+      ExportFormat::Jsonl,
+      &out,
+    )
+    .unwrap();
+  assert!(eng.list_interrupted_exports().unwrap().is_empty());
+  drop(eng);
+
+  // What a crash mid-export leaves: its temp files and its journal entry.
+  let partial = dir.path().join("big.csv.partial");
+  let stage = dir.path().join("big.csv.stage.jsonl");
+  std::fs::write(&partial, "n\n1\n").unwrap();
+  std::fs::write(&stage, "{\"n\":1}\n").unwrap();
+  rusqlite::Connection::open(&db)
+    .unwrap()
+    .execute(
+      "INSERT INTO export_journal(task_id, destination, temp_path, started_at) VALUES('t1', ?1, ?2, 5)",
+      rusqlite::params![
+        dir.path().join("big.csv").to_string_lossy(),
+        partial.to_string_lossy()
+      ],
+    )
+    .unwrap();
+
+  let eng = engine_with_sqlite(db.clone());
+  assert!(!partial.exists() && !stage.exists());
+  assert!(out.exists());
+  let interrupted = eng.list_interrupted_exports().unwrap();
+  assert_eq!(interrupted.len(), 1);
+  assert_eq!(interrupted[0].task_id, "t1");
+  assert!(interrupted[0].destination.ends_with("big.csv"));
+  assert!(interrupted[0].interrupted_at_ms >= interrupted[0].started_at_ms);
+
+  // Still listed after another restart, until dismissed.
+  drop(eng);
+  let eng = engine_with_sqlite(db);
+  assert_eq!(eng.list_interrupted_exports().unwrap(), interrupted);
+  eng.dismiss_interrupted_export(interrupted[0].id).unwrap();
+  assert!(eng.list_interrupted_exports().unwrap().is_empty());
+  assert!(eng.dismiss_interrupted_export(interrupted[0].id).is_err());
+}
+
+#[test]
+fn parquet_session_reads_stay_consistent_on_its_kept_connection() {
+  let dir = tempfile::tempdir().unwrap();
+  let file = dir.path().join("a.parquet");
+  let write = |rows: u32| {
+    duckdb::Connection::open_in_memory()
+      .unwrap()
+      .execute(
+        &format!("COPY (SELECT range AS x, 'v' || range AS y FROM range({rows})) TO ? (FORMAT PARQUET);"),
+        duckdb::params![file.to_string_lossy().to_string()],
+      )
+      .unwrap();
+  };
+  write(30);
+  let eng = engine_with_sqlite(dir.path().join("t.sqlite"));
+  let (session, first) = eng.open_file(&file).unwrap();
+  let sid = &session.session_id;
+  assert_eq!(first.records[0].id, 0);
+
+  let mut cursor = first.next_cursor.clone();
+  let mut seen = first.records.len();
+  while let Some(c) = cursor {
+    let page = eng.next_page(sid, Some(&c), 7).unwrap();
+    assert!(page.records.iter().enumerate().all(|(i, r)| r.id == (seen + i) as u64));
+    seen += page.records.len();
+    cursor = page.next_cursor;
+  }
+  assert_eq!(seen, 30);
+  assert_eq!(eng.page_at(sid, 25, 2).unwrap().records[0].id, 25);
+  let meta = dh_core::RecordMeta {
+    line_no: 12,
+    byte_offset: 0,
+    byte_len: 0,
+    part: None,
+  };
+  assert!(eng.get_record_raw(sid, meta).unwrap().contains("\"v12\""));
+  let out = dir.path().join("out.jsonl");
+  let ex = eng
+    .export(sid, ExportRequest::Selection { record_ids: vec![3, 4] }, ExportFormat::Jsonl, &out)
+    .unwrap();
+  assert_eq!(ex.records_written, 2);
+  assert!(std::fs::read_to_string(&out).unwrap().contains("\"v4\""));
+
+  // A rewritten file is read afresh after a reload, not from the old connection.
+  write(3);
+  eng.reload_session(sid).unwrap();
+  assert_eq!(eng.page_at(sid, 0, 10).unwrap().records.len(), 3);
+  assert!(eng.page_at(sid, 25, 2).is_err());
+}
+
+#[test]
+fn parquet_pages_deep_in_a_file_read_the_requested_rows() {
+  let dir = tempfile::tempdir().unwrap();
+  let file = dir.path().join("a.parquet");
+  duckdb::Connection::open_in_memory()
+    .unwrap()
+    .execute(
+      "COPY (SELECT range AS x, 'v' || range AS y, range % 2 = 0 AS even, DATE '2024-01-01' + range::INTEGER AS d, \
+       CASE WHEN range % 3 = 0 THEN NULL ELSE range * 1.5 END::DOUBLE AS z FROM range(5000)) \
+       TO ? (FORMAT PARQUET, ROW_GROUP_SIZE 1000);",
+      duckdb::params![file.to_string_lossy().to_string()],
+    )
+    .unwrap();
+  let eng = engine_with_sqlite(dir.path().join("t.sqlite"));
+  let (session, _) = eng.open_file(&file).unwrap();
+  let sid = &session.session_id;
+
+  let page = eng.page_at(sid, 4321, 3).unwrap();
+  assert_eq!(page.records.iter().map(|r| r.id).collect::<Vec<_>>(), vec![4321, 4322, 4323]);
+  assert_eq!(page.records[0].preview, "4321\tv4321\tfalse\t2035-10-31\t6481.5");
+  assert!(page.records[2].preview.ends_with("\tnull"));
+  let raw: serde_json::Value = serde_json::from_str(page.records[1].raw.as_deref().unwrap()).unwrap();
+  assert_eq!(raw["y"], "v4322");
+  assert_eq!(raw["even"], true);
+  assert_eq!(raw["z"], 6483.0);
+
+  // A projected page keeps the requested column order.
+  let cols = vec!["y".to_string(), "x".to_string()];
+  let cursor = page.next_cursor.unwrap();
+  let next = eng.next_page_with_columns(sid, Some(&cursor), 2, Some(&cols)).unwrap();
+  assert_eq!(next.records[0].id, 4324);
+  assert_eq!(next.records[0].preview, "v4324\t4324");
+
+  // The last page is short and ends the file.
+  let tail = eng.page_at(sid, 4998, 5).unwrap();
+  assert_eq!(tail.records.len(), 2);
+  assert!(tail.next_cursor.is_none());
+}
+
+#[test]
+fn parquet_metadata_describes_the_footer() {
+  let dir = tempfile::tempdir().unwrap();
+  let file = dir.path().join("a.parquet");
+  duckdb::Connection::open_in_memory()
+    .unwrap()
+    .execute(
+      "COPY (SELECT range AS x, 'v' || range AS y, {'a': range, 'b': [1, 2]} AS s FROM range(3000)) \
+       TO ? (FORMAT PARQUET, ROW_GROUP_SIZE 1000, COMPRESSION ZSTD, KV_METADATA {owner: 'team'});",
+      duckdb::params![file.to_string_lossy().to_string()],
+    )
+    .unwrap();
+  let eng = engine_with_sqlite(dir.path().join("t.sqlite"));
+  let (session, _) = eng.open_file(&file).unwrap();
+  let meta = eng.parquet_metadata(&session.session_id).unwrap();
+
+  assert_eq!(meta.row_count, 3000);
+  assert!(meta.row_groups.len() > 1);
+  assert_eq!(meta.row_groups.iter().map(|g| g.row_count).sum::<u64>(), 3000);
+  let paths = meta.columns.iter().map(|c| c.path.as_str()).collect::<Vec<_>>();
+  assert_eq!(paths, vec!["x", "y", "s.a", "s.b.list.element"]);
+  let y = &meta.columns[1];
+  assert_eq!(y.physical_type.as_deref(), Some("BYTE_ARRAY"));
+  assert_eq!(y.logical_type.as_deref(), Some("UTF8"));
+  assert_eq!(y.duckdb_type.as_deref(), Some("VARCHAR"));
+  assert_eq!(y.compression.as_deref(), Some("ZSTD"));
+  assert!(y.compressed_bytes > 0);
+  assert!(meta
+    .key_value
+    .contains(&ParquetKeyValue { key: "owner".into(), value: "team".into() }));
+
+  // Only parquet sessions have a footer.
+  let csv = dir.path().join("a.csv");
+  std::fs::write(&csv, "a\n1\n").unwrap();
+  let (csv_session, _) = eng.open_file(&csv).unwrap();
+  assert!(matches!(
+    eng.parquet_metadata(&csv_session.session_id),
+    Err(CoreError::UnsupportedFormat(FileFormat::Csv))
+  ));
+}
+
+#[test]
+fn parquet_pages_know_the_row_total_and_end_exactly_at_the_last_row() {
+  let dir = tempfile::tempdir().unwrap();
+  let file = dir.path().join("a.parquet");
+  duckdb::Connection::open_in_memory()
+    .unwrap()
+    .execute(
+      "COPY (SELECT range AS x FROM range(6)) TO ? (FORMAT PARQUET);",
+      duckdb::params![file.to_string_lossy().to_string()],
+    )
+    .unwrap();
+  let eng = engine_with_sqlite(dir.path().join("t.sqlite"));
+  let (session, first) = eng.open_file(&file).unwrap();
+  assert_eq!(first.total_records, Some(6));
+  assert!(!first.reached_eof);
+
+  // 6 rows in pages of 3: the second page is full and already the last one.
+  let sid = &session.session_id;
+  let page = eng.page_at(sid, 0, 3).unwrap();
+  let last = eng.next_page(sid, page.next_cursor.as_deref(), 3).unwrap();
+  assert_eq!(last.records.len(), 3);
+  assert!(last.reached_eof);
+  assert!(last.next_cursor.is_none());
+  assert_eq!(last.total_records, Some(6));
+
+  // Other formats leave the total to `count_records`.
+  let jsonl = dir.path().join("a.jsonl");
+  std::fs::write(&jsonl, "{}\n{}\n").unwrap();
+  assert_eq!(eng.open_file(&jsonl).unwrap().1.total_records, None);
+}
+
+#[test]
+fn parquet_sessions_page_only_their_selected_columns() {
+  let dir = tempfile::tempdir().unwrap();
+  let sqlite = dir.path().join("t.sqlite");
+  let file = dir.path().join("a.parquet");
+  let write = |sql: &str| {
+    duckdb::Connection::open_in_memory()
+      .unwrap()
+      .execute(
+        &format!("COPY ({sql}) TO ? (FORMAT PARQUET);"),
+        duckdb::params![file.to_string_lossy().to_string()],
+      )
+      .unwrap();
+  };
+  write("SELECT range AS x, 'v' || range AS y, range * 2 AS z FROM range(10)");
+  let eng = engine_with_sqlite(sqlite.clone());
+  let (session, _) = eng.open_file(&file).unwrap();
+  let sid = &session.session_id;
+
+  let page = eng.set_session_columns(sid, Some(vec!["z".into(), "x".into()])).unwrap();
+  assert_eq!(page.records[0].preview, "0\t0");
+  assert_eq!(eng.page_at(sid, 3, 2).unwrap().records[0].preview, "6\t3");
+  let next = eng.next_page(sid, page.next_cursor.as_deref(), 2).unwrap();
+  assert_eq!(next.records[0].raw.as_deref(), Some(r#"{"x":2,"z":4}"#));
+  assert!(matches!(
+    eng.set_session_columns(sid, Some(vec!["nope".into()])),
+    Err(CoreError::InvalidArg(_))
+  ));
+  assert_eq!(eng.set_session_columns(sid, None).unwrap().records[0].preview, "0\tv0\t0");
+
+  // Saved prefs select the columns again on open, minus those the file no longer has.
+  eng
+    .save_view_prefs(
+      sid,
+      ViewPrefs {
+        columns: Some(vec!["y".into(), "z".into()]),
+        ..ViewPrefs::default()
+      },
+    )
+    .unwrap();
+  drop(eng);
+  write("SELECT range AS x, 'v' || range AS y FROM range(10)");
+  let eng = engine_with_sqlite(sqlite);
+  let (session, first) = eng.open_file(&file).unwrap();
+  assert_eq!(session.columns, Some(vec!["y".to_string()]));
+  assert_eq!(first.records[1].preview, "v1");
+}
+
+#[test]
+fn parquet_paging_crosses_row_group_boundaries_from_any_start() {
+  let dir = tempfile::tempdir().unwrap();
+  let file = dir.path().join("a.parquet");
+  duckdb::Connection::open_in_memory()
+    .unwrap()
+    .execute(
+      "COPY (SELECT range AS x FROM range(7000)) TO ? (FORMAT PARQUET, ROW_GROUP_SIZE 2048);",
+      duckdb::params![file.to_string_lossy().to_string()],
+    )
+    .unwrap();
+  let eng = engine_with_sqlite(dir.path().join("t.sqlite"));
+  let (session, _) = eng.open_file(&file).unwrap();
+  let sid = &session.session_id;
+  assert!(eng.parquet_metadata(sid).unwrap().row_groups.len() >= 3);
+
+  for start in [0, 1900, 4095, 6990] {
+    let mut page = eng.page_at(sid, start, 700).unwrap();
+    let mut next_id = start;
+    loop {
+      for r in &page.records {
+        assert_eq!(r.id, next_id);
+        assert_eq!(r.preview, next_id.to_string());
+        next_id += 1;
+      }
+      match page.next_cursor {
+        Some(c) => page = eng.next_page(sid, Some(&c), 700).unwrap(),
+        None => break,
+      }
+    }
+    assert_eq!(next_id, 7000);
+  }
+}
+
+#[test]
+fn parquet_pages_read_only_the_row_groups_they_span() {
+  use std::io::{Seek, SeekFrom, Write};
+
+  let dir = tempfile::tempdir().unwrap();
+  let file = dir.path().join("a.parquet");
+  let path = file.to_string_lossy().to_string();
+  let conn = duckdb::Connection::open_in_memory().unwrap();
+  conn
+    .execute(
+      "COPY (SELECT range AS x FROM range(6144)) TO ? (FORMAT PARQUET, ROW_GROUP_SIZE 2048);",
+      duckdb::params![path],
+    )
+    .unwrap();
+  // Overwrite the data of the middle row group (rows 2048..4096); the footer stays intact.
+  let chunks: Vec<(u64, usize)> = conn
+    .prepare("SELECT data_page_offset, total_compressed_size FROM parquet_metadata(?) WHERE row_group_id = 1")
+    .unwrap()
+    .query_map(duckdb::params![path], |r| Ok((r.get::<_, i64>(0)? as u64, r.get::<_, i64>(1)? as usize)))
+    .unwrap()
+    .map(Result::unwrap)
+    .collect();
+  let mut f = std::fs::OpenOptions::new().write(true).open(&file).unwrap();
+  for (offset, len) in chunks {
+    f.seek(SeekFrom::Start(offset)).unwrap();
+    f.write_all(&vec![0xab; len]).unwrap();
+  }
+  drop(f);
+
+  let eng = engine_with_sqlite(dir.path().join("t.sqlite"));
+  let (session, _) = eng.open_file(&file).unwrap();
+  let sid = &session.session_id;
+  // Pages before and after the damaged group never read it, so they load.
+  assert_eq!(eng.page_at(sid, 2000, 48).unwrap().records.last().unwrap().id, 2047);
+  let page = eng.page_at(sid, 6000, 10).unwrap();
+  assert_eq!(page.records[0].preview, "6000");
+  let next = eng.next_page(sid, page.next_cursor.as_deref(), 10).unwrap();
+  assert_eq!(next.records[0].preview, "6010");
+  // So do filtered pages whose filter the damaged group's footer stats rule out.
+  let filtered = eng
+    .set_session_filters(
+      sid,
+      Some(vec![ColumnFilter {
+        column: "x".into(),
+        op: FilterOp::Ge,
+        value: serde_json::json!(4100),
+      }]),
+    )
+    .unwrap();
+  assert_eq!(filtered.records[0].id, 4100);
+  assert_eq!(eng.page_at(sid, 5000, 2).unwrap().records[0].id, 5000);
+  eng.set_session_filters(sid, None).unwrap();
+  // A page inside it does.
+  assert!(eng.page_at(sid, 3000, 10).is_err());
+}
+
+#[test]
+fn nested_parquet_columns_come_back_as_json_values() {
+  let dir = tempfile::tempdir().unwrap();
+  let file = dir.path().join("a.parquet");
+  duckdb::Connection::open_in_memory()
+    .unwrap()
+    .execute(
+      "COPY (SELECT range AS id, {'city': 'c' || range, 'zip': [range, range + 1]} AS addr, \
+       MAP {'k': range} AS tags FROM range(3)) TO ? (FORMAT PARQUET);",
+      duckdb::params![file.to_string_lossy().to_string()],
+    )
+    .unwrap();
+  let eng = engine_with_sqlite(dir.path().join("t.sqlite"));
+  let (session, first) = eng.open_file(&file).unwrap();
+  let sid = &session.session_id;
+  let expected = serde_json::json!({ "id": 1, "addr": { "city": "c1", "zip": [1, 2] }, "tags": { "k": 1 } });
+
+  let raw: serde_json::Value = serde_json::from_str(first.records[1].raw.as_deref().unwrap()).unwrap();
+  assert_eq!(raw, expected);
+  let meta = dh_core::RecordMeta {
+    line_no: 1,
+    byte_offset: 0,
+    byte_len: 0,
+    part: None,
+  };
+  let raw: serde_json::Value = serde_json::from_str(&eng.get_record_raw(sid, meta).unwrap()).unwrap();
+  assert_eq!(raw, expected);
+
+  let hits = eng
+    .search(
+      sid,
+      SearchQuery {
+        text: "city:c1".into(),
+        mode: SearchMode::CurrentPage,
+        case_sensitive: false,
+        max_hits: 100,
+        timeout_ms: None,
+        force_rescan: false,
+      },
+    )
+    .unwrap()
+    .hits;
+  assert_eq!(hits.iter().map(|r| r.id).collect::<Vec<_>>(), vec![1]);
+
+  let out = dir.path().join("out.jsonl");
+  eng
+    .export(sid, ExportRequest::Selection { record_ids: vec![1] }, ExportFormat::Jsonl, &out)
+    .unwrap();
+  let exported: serde_json::Value = serde_json::from_str(std::fs::read_to_string(&out).unwrap().trim()).unwrap();
+  assert_eq!(exported, expected);
+}
+
+#[test]
+fn parquet_blobs_are_previewed_by_size_and_saved_whole() {
+  let dir = tempfile::tempdir().unwrap();
+  let file = dir.path().join("a.parquet");
+  duckdb::Connection::open_in_memory()
+    .unwrap()
+    .execute(
+      "COPY (SELECT range AS id, CASE WHEN range = 0 THEN NULL ELSE repeat('\\x01\\xAB', 5000)::BLOB END AS data \
+       FROM range(2)) TO ? (FORMAT PARQUET);",
+      duckdb::params![file.to_string_lossy().to_string()],
+    )
+    .unwrap();
+  let eng = engine_with_sqlite(dir.path().join("t.sqlite"));
+  let (session, first) = eng.open_file(&file).unwrap();
+  let sid = &session.session_id;
+
+  let preview = "blob(10000 bytes, hex:01ab01ab01ab01ab01ab01ab01ab01ab…)";
+  assert!(first.records[1].preview.starts_with("1\tblob(10000 bytes, hex:01ab"));
+  let raw: serde_json::Value = serde_json::from_str(first.records[1].raw.as_deref().unwrap()).unwrap();
+  assert_eq!(raw["data"], preview);
+
+  let out = dir.path().join("cell.bin");
+  eng.save_parquet_blob(sid, 1, "data", &out).unwrap();
+  assert_eq!(std::fs::read(&out).unwrap(), [0x01, 0xAB].repeat(5000));
+  eng.save_parquet_blob(sid, 0, "data", &out).unwrap();
+  assert!(std::fs::read(&out).unwrap().is_empty());
+  assert!(matches!(eng.save_parquet_blob(sid, 1, "id", &out), Err(CoreError::InvalidArg(_))));
+  assert!(matches!(eng.save_parquet_blob(sid, 5, "data", &out), Err(CoreError::InvalidArg(_))));
+}
+
+#[test]
+fn parquet_dates_and_times_render_as_iso_8601() {
+  let dir = tempfile::tempdir().unwrap();
+  let file = dir.path().join("a.parquet");
+  duckdb::Connection::open_in_memory()
+    .unwrap()
+    .execute(
+      "COPY (SELECT DATE '1969-12-31' AS d, TIME '13:45:06.5' AS t, TIMESTAMP '2024-02-29 23:30:00' AS ts, \
+       TIMESTAMPTZ '2024-02-29 23:30:00+00' AS tsz) TO ? (FORMAT PARQUET);",
+      duckdb::params![file.to_string_lossy().to_string()],
+    )
+    .unwrap();
+
+  // TIMESTAMPTZ values are shown in UTC by default; plain ones as stored.
+  let eng = engine_with_sqlite(dir.path().join("t.sqlite"));
+  let (session, first) = eng.open_file(&file).unwrap();
+  assert_eq!(first.records[0].preview, "1969-12-31\t13:45:06.5\t2024-02-29T23:30:00\t2024-02-…");
+  let raw: serde_json::Value = serde_json::from_str(first.records[0].raw.as_deref().unwrap()).unwrap();
+  assert_eq!(raw["tsz"], "2024-02-29T23:30:00Z");
+  eng.close_session(&session.session_id).unwrap();
+
+  let eng = CoreEngine::new(CoreOptions {
+    parquet_utc_offset_minutes: 90,
+    storage: StorageOptions {
+      sqlite_path: Some(dir.path().join("t2.sqlite")),
+      ..StorageOptions::default()
+    },
+    ..CoreOptions::default()
+  })
+  .unwrap();
+  let (session, first) = eng.open_file(&file).unwrap();
+  let sid = &session.session_id;
+  let expected = serde_json::json!({
+    "d": "1969-12-31",
+    "t": "13:45:06.5",
+    "ts": "2024-02-29T23:30:00",
+    "tsz": "2024-03-01T01:00:00+01:30",
+  });
+  let raw: serde_json::Value = serde_json::from_str(first.records[0].raw.as_deref().unwrap()).unwrap();
+  assert_eq!(raw, expected);
+
+  let out = dir.path().join("out.jsonl");
+  eng
+    .export(sid, ExportRequest::Selection { record_ids: vec![0] }, ExportFormat::Jsonl, &out)
+    .unwrap();
+  let exported: serde_json::Value = serde_json::from_str(std::fs::read_to_string(&out).unwrap().trim()).unwrap();
+  assert_eq!(exported, expected);
+
+  let task_id = eng
+    .search(
+      sid,
+      SearchQuery {
+        text: "01:00:00+01:30".into(),
+        mode: SearchMode::ScanAll,
+        case_sensitive: true,
+        max_hits: 100,
+        timeout_ms: None,
+        force_rescan: false,
+      },
+    )
+    .unwrap()
+    .task
+    .unwrap()
+    .id;
+  for _ in 0..200 {
+    if eng.get_task(&task_id).unwrap().finished {
+      break;
+    }
+    thread::sleep(Duration::from_millis(10));
+  }
+  let hits = eng.search_task_hits_page(&task_id, None, 10).unwrap();
+  assert_eq!(hits.records.len(), 1);
+  assert!(hits.records[0].preview.ends_with("\t2024-03-01T01:00:00+01:30"));
+}
+
+#[test]
+fn parquet_decimals_keep_every_digit() {
+  let dir = tempfile::tempdir().unwrap();
+  let file = dir.path().join("a.parquet");
+  duckdb::Connection::open_in_memory()
+    .unwrap()
+    .execute(
+      "COPY (SELECT 12345678901234567890123456789.123456789::DECIMAL(38,9) AS amount, \
+       (-99999999999999999999999999999999999999)::DECIMAL(38,0) AS big, (-0.05)::DECIMAL(4,2) AS fee) \
+       TO ? (FORMAT PARQUET);",
+      duckdb::params![file.to_string_lossy().to_string()],
+    )
+    .unwrap();
+  let eng = engine_with_sqlite(dir.path().join("t.sqlite"));
+  let (session, first) = eng.open_file(&file).unwrap();
+  let sid = &session.session_id;
+
+  let expected = serde_json::json!({
+    "amount": "12345678901234567890123456789.123456789",
+    "big": "-99999999999999999999999999999999999999",
+    "fee": "-0.05",
+  });
+  let raw: serde_json::Value = serde_json::from_str(first.records[0].raw.as_deref().unwrap()).unwrap();
+  assert_eq!(raw, expected);
+
+  let out = dir.path().join("out.jsonl");
+  eng
+    .export(sid, ExportRequest::Selection { record_ids: vec![0] }, ExportFormat::Jsonl, &out)
+    .unwrap();
+  let exported: serde_json::Value = serde_json::from_str(std::fs::read_to_string(&out).unwrap().trim()).unwrap();
+  assert_eq!(exported, expected);
+
+  let task_id = eng
+    .search(
+      sid,
+      SearchQuery {
+        text: "3456789.123456789".into(),
+        mode: SearchMode::ScanAll,
+        case_sensitive: true,
+        max_hits: 100,
+        timeout_ms: None,
+        force_rescan: false,
+      },
+    )
+    .unwrap()
+    .task
+    .unwrap()
+    .id;
+  for _ in 0..200 {
+    if eng.get_task(&task_id).unwrap().finished {
+      break;
+    }
+    thread::sleep(Duration::from_millis(10));
+  }
+  let t = eng.get_task(&task_id).unwrap();
+  assert!(t.error.is_none(), "{:?}", t.error);
+  assert_eq!(eng.search_task_hits_page(&task_id, None, 10).unwrap().records.len(), 1);
+}
+
+#[test]
+fn parquet_footer_stats_merge_row_groups_without_reading_rows() {
+  let dir = tempfile::tempdir().unwrap();
+  let file = dir.path().join("a.parquet");
+  duckdb::Connection::open_in_memory()
+    .unwrap()
+    .execute(
+      "COPY (SELECT range AS id, 'v' || range AS s, CASE WHEN range % 3 = 0 THEN NULL ELSE range * 1.5 END::DOUBLE AS z, \
+       {'d': DATE '2024-01-01' + range::INTEGER} AS st, NULL::INTEGER AS n FROM range(6000)) \
+       TO ? (FORMAT PARQUET, ROW_GROUP_SIZE 2048);",
+      duckdb::params![file.to_string_lossy().to_string()],
+    )
+    .unwrap();
+  let eng = engine_with_sqlite(dir.path().join("t.sqlite"));
+  let (session, _) = eng.open_file(&file).unwrap();
+
+  let stats = eng.parquet_footer_stats(&session.session_id).unwrap();
+  assert_eq!((stats.row_count, stats.row_groups), (6000, 3));
+  let summary = |c: &ParquetColumnStats| (c.path.clone(), c.min.clone(), c.max.clone(), c.null_count, c.complete);
+  let owned = |s: &str| Some(s.to_string());
+  assert_eq!(
+    stats.columns.iter().map(summary).collect::<Vec<_>>(),
+    vec![
+      ("id".into(), owned("0"), owned("5999"), Some(0), true),
+      ("s".into(), owned("v0"), owned("v999"), Some(0), true),
+      ("z".into(), owned("1.5"), owned("8998.5"), Some(2000), true),
+      ("st.d".into(), owned("2024-01-01"), owned("2040-06-04"), Some(0), true),
+      ("n".into(), None, None, Some(6000), true),
+    ]
+  );
+
+  let jsonl = dir.path().join("a.jsonl");
+  std::fs::write(&jsonl, "{}\n").unwrap();
+  let (other, _) = eng.open_file(&jsonl).unwrap();
+  assert!(matches!(eng.parquet_footer_stats(&other.session_id), Err(CoreError::UnsupportedFormat(_))));
+}
+
+#[test]
+fn parquet_filters_slice_pages_scans_and_exports() {
+  let dir = tempfile::tempdir().unwrap();
+  let file = dir.path().join("a.parquet");
+  duckdb::Connection::open_in_memory()
+    .unwrap()
+    .execute(
+      "COPY (SELECT range AS id, 'v' || range AS s FROM range(6000)) TO ? (FORMAT PARQUET, ROW_GROUP_SIZE 2048);",
+      duckdb::params![file.to_string_lossy().to_string()],
+    )
+    .unwrap();
+  let eng = engine_with_sqlite(dir.path().join("t.sqlite"));
+  let (session, _) = eng.open_file(&file).unwrap();
+  let sid = session.session_id.clone();
+  let filter = |column: &str, op, value| ColumnFilter { column: column.into(), op, value };
+
+  let page = eng
+    .set_session_filters(
+      &sid,
+      Some(vec![
+        filter("id", FilterOp::Ge, serde_json::json!(1950)),
+        filter("s", FilterOp::Contains, serde_json::json!("V19")),
+      ]),
+    )
+    .unwrap();
+  let ids = |p: &dh_core::RecordPage| p.records.iter().map(|r| r.id).collect::<Vec<_>>();
+  assert_eq!(ids(&page), vec![1950, 1951]);
+  assert_eq!(page.total_records, None);
+  let next = eng.next_page(&sid, page.next_cursor.as_deref(), 2).unwrap();
+  assert_eq!(ids(&next), vec![1952, 1953]);
+  let info = eng.list_sessions().into_iter().find(|s| s.session_id == sid).unwrap();
+  assert_eq!(info.filters.map(|f| f.len()), Some(2));
+
+  let r = eng
+    .search(
+      &sid,
+      SearchQuery {
+        text: "v199".into(),
+        mode: SearchMode::ScanAll,
+        case_sensitive: true,
+        max_hits: 100,
+        timeout_ms: None,
+        force_rescan: false,
+      },
+    )
+    .unwrap();
+  let task_id = r.task.unwrap().id;
+  for _ in 0..200 {
+    if eng.get_task(&task_id).unwrap().finished {
+      break;
+    }
+    thread::sleep(Duration::from_millis(10));
+  }
+  let hits = eng.search_task_hits_page(&task_id, None, 100).unwrap();
+  assert_eq!(ids(&hits), (1990..2000).collect::<Vec<u64>>());
+
+  let out = dir.path().join("out.jsonl");
+  let res = eng.export(&sid, ExportRequest::Filtered, ExportFormat::Jsonl, &out).unwrap();
+  assert_eq!(res.records_written, 50);
+  let first = std::fs::read_to_string(&out).unwrap().lines().next().unwrap().to_string();
+  assert!(first.contains("v1950"), "{first}");
+
+  let sort = SortSpec { key: "id".into(), descending: false };
+  assert!(matches!(eng.open_sorted_view(&sid, sort), Err(CoreError::InvalidArg(_))));
+  assert!(matches!(
+    eng.set_session_filters(&sid, Some(vec![filter("nope", FilterOp::IsNull, serde_json::Value::Null)])),
+    Err(CoreError::InvalidArg(_))
+  ));
+  assert!(eng
+    .set_session_filters(&sid, Some(vec![filter("id", FilterOp::Gt, serde_json::json!("abc"))]))
+    .is_err());
+  assert_eq!(ids(&eng.next_page(&sid, None, 2).unwrap()), vec![1950, 1951]);
+
+  let all = eng.set_session_filters(&sid, None).unwrap();
+  assert_eq!(ids(&all), vec![0, 1]);
+  assert_eq!(all.total_records, Some(6000));
+}
+
+#[test]
+fn parquet_page_records_carry_row_index_meta() {
+  let dir = tempfile::tempdir().unwrap();
+  let file = dir.path().join("a.parquet");
+  duckdb::Connection::open_in_memory()
+    .unwrap()
+    .execute(
+      "COPY (SELECT range AS id, 'v' || range AS s FROM range(5000)) TO ? (FORMAT PARQUET, ROW_GROUP_SIZE 2048);",
+      duckdb::params![file.to_string_lossy().to_string()],
+    )
+    .unwrap();
+  let eng = engine_with_sqlite(dir.path().join("t.sqlite"));
+  let (session, _) = eng.open_file(&file).unwrap();
+  let sid = session.session_id.clone();
+
+  let page = eng.page_at(&sid, 4097, 2).unwrap();
+  let metas: Vec<_> = page.records.iter().map(|r| r.meta.clone().unwrap()).collect();
+  assert_eq!(metas.iter().map(|m| m.line_no).collect::<Vec<_>>(), vec![4097, 4098]);
+  assert_eq!(eng.get_record_raw(&sid, metas[1].clone()).unwrap(), r#"{"id":4098,"s":"v4098"}"#);
+
+  let out = dir.path().join("out.jsonl");
+  let record_ids = page.records.iter().map(|r| r.id).collect();
+  let res = eng
+    .export(&sid, ExportRequest::Selection { record_ids }, ExportFormat::Jsonl, &out)
+    .unwrap();
+  assert_eq!(res.records_written, 2);
+  assert!(std::fs::read_to_string(&out).unwrap().contains("v4097"));
+}
+
+#[test]
+fn encrypted_parquet_needs_a_key_and_plain_files_ignore_it() {
+  let dir = tempfile::tempdir().unwrap();
+  let plain = dir.path().join("plain.parquet");
+  duckdb::Connection::open_in_memory()
+    .unwrap()
+    .execute(
+      "COPY (SELECT range AS id FROM range(10)) TO ? (FORMAT PARQUET);",
+      duckdb::params![plain.to_string_lossy().to_string()],
+    )
+    .unwrap();
+  let eng = engine_with_sqlite(dir.path().join("t.sqlite"));
+
+  // A key for a file that isn't encrypted is not used.
+  let (session, page) = eng.open_file_with_key(&plain, "0123456789112345", |_| {}).unwrap();
+  assert_eq!(page.records[0].raw.as_deref(), Some(r#"{"id":0}"#));
+  assert_eq!(eng.parquet_metadata(&session.session_id).unwrap().row_count, 10);
+
+  // The `PARE` magic marks an encrypted footer.
+  let mut bytes = std::fs::read(&plain).unwrap();
+  let n = bytes.len();
+  bytes[..4].copy_from_slice(b"PARE");
+  bytes[n - 4..].copy_from_slice(b"PARE");
+  let encrypted = dir.path().join("enc.parquet");
+  std::fs::write(&encrypted, bytes).unwrap();
+  match eng.open_file(&encrypted) {
+    Err(CoreError::InvalidArg(msg)) => assert!(msg.contains("decryption key"), "{msg}"),
+    other => panic!("unexpected: {other:?}"),
+  }
+  match eng.open_file_with_key(&encrypted, "short", |_| {}) {
+    Err(CoreError::InvalidArg(msg)) => assert!(msg.contains("invalid parquet decryption key"), "{msg}"),
+    other => panic!("unexpected: {other:?}"),
+  }
+  // The footer isn't really encrypted, so DuckDB can't decrypt it with any key.
+  assert!(eng.open_file_with_key(&encrypted, "0123456789112345", |_| {}).is_err());
+}
+
+#[test]
+fn parquet_sessions_serve_recent_pages_from_memory() {
+  let dir = tempfile::tempdir().unwrap();
+  let file = dir.path().join("a.parquet");
+  duckdb::Connection::open_in_memory()
+    .unwrap()
+    .execute(
+      "COPY (SELECT range AS id FROM range(5000)) TO ? (FORMAT PARQUET);",
+      duckdb::params![file.to_string_lossy().to_string()],
+    )
+    .unwrap();
+  let eng = engine_with_sqlite(dir.path().join("t.sqlite"));
+  let uncached = CoreEngine::new(CoreOptions {
+    parquet_page_cache_pages: 0,
+    storage: StorageOptions {
+      sqlite_path: Some(dir.path().join("u.sqlite")),
+      ..StorageOptions::default()
+    },
+    ..CoreOptions::default()
+  })
+  .unwrap();
+  let (session, first) = eng.open_file(&file).unwrap();
+  let sid = session.session_id.clone();
+  let second = eng.next_page(&sid, first.next_cursor.as_deref(), 2).unwrap();
+  let (other, _) = uncached.open_file(&file).unwrap();
+
+  // Without the file, only the pages read before can still be served.
+  std::fs::remove_file(&file).unwrap();
+  let back = eng.page_at(&sid, 0, 2).unwrap();
+  assert_eq!(back.records.iter().map(|r| r.id).collect::<Vec<_>>(), vec![0, 1]);
+  let again = eng.next_page(&sid, first.next_cursor.as_deref(), 2).unwrap();
+  assert_eq!(again.records[0].raw, second.records[0].raw);
+  assert!(eng.page_at(&sid, 4000, 2).is_err());
+  assert!(uncached.page_at(&other.session_id, 0, 10).is_err());
+}
+
+#[test]
+fn headerless_csv_treats_line_0_as_data() {
+  let dir = tempfile::tempdir().unwrap();
+  let file = dir.path().join("gen.csv");
+  std::fs::write(&file, "1,Alice,98\n2,Bob,87\n3,Carol,75\n").unwrap();
+
+  let eng = engine_with_sqlite(dir.path().join("t.sqlite"));
+  let (session, first) = eng.open_file(&file).unwrap();
+  assert!(session.csv.has_header);
+  assert_eq!(first.records[0].raw.as_deref(), Some("1,Alice,98"));
+  assert_eq!(eng.count_records(&session.session_id).unwrap().total, Some(2));
+
+  let page = eng
+    .set_csv_dialect(&session.session_id, CsvDialect { has_header: false, ..CsvDialect::default() })
+    .unwrap();
+  assert_eq!(
+    page.records[0].raw.as_deref(),
+    Some(r#"{"col_0":"1","col_1":"Alice","col_2":"98"}"#)
+  );
+  assert_eq!(eng.count_records(&session.session_id).unwrap().total, Some(3));
+  let info = eng.list_sessions().into_iter().find(|s| s.session_id == session.session_id).unwrap();
+  assert!(!info.csv.has_header);
+
+  let out = dir.path().join("out.jsonl");
+  let ex = eng
+    .export(
+      &session.session_id,
+      ExportRequest::Selection { record_ids: vec![0, 2] },
+      ExportFormat::Jsonl,
+      &out,
+    )
+    .unwrap();
+  assert_eq!(ex.records_written, 2);
+  let text = std::fs::read_to_string(out).unwrap();
+  assert!(text.starts_with(r#"{"col_0":"1","col_1":"Alice","col_2":"98"}"#));
+  assert!(text.contains(r#""col_1":"Carol""#));
+
+  let jsonl = dir.path().join("a.jsonl");
+  std::fs::write(&jsonl, "{\"a\":1}\n").unwrap();
+  let (other, _) = eng.open_file(&jsonl).unwrap();
+  assert!(matches!(
+    eng.set_csv_dialect(&other.session_id, CsvDialect { has_header: false, ..CsvDialect::default() }),
+    Err(CoreError::UnsupportedFormat(_))
+  ));
+}
+
+#[test]
+fn headerless_csv_stats_and_schema_follow_the_dialect() {
+  let dir = tempfile::tempdir().unwrap();
+  let file = dir.path().join("gen.csv");
+  std::fs::write(&file, "1,Alice,98\n2,Bob,87\n3,Carol,75\n").unwrap();
+
+  let eng = engine_with_sqlite(dir.path().join("t.sqlite"));
+  let (session, _) = eng.open_file(&file).unwrap();
+  let sid = session.session_id;
+  eng
+    .set_csv_dialect(&sid, CsvDialect { has_header: false, ..CsvDialect::default() })
+    .unwrap();
+
+  let names = |columns: &[dh_core::ColumnStats]| columns.iter().map(|c| c.name.clone()).collect::<Vec<_>>();
+  let stats = eng.get_stats(&sid).unwrap();
+  assert_eq!(stats.records_scanned, 3);
+  assert_eq!(names(&stats.columns), vec!["col_0", "col_1", "col_2"]);
+
+  let quick = eng.quick_stats(&sid, dh_core::StatsSampleStrategy::Head, 10).unwrap();
+  assert_eq!(quick.records_scanned, 3);
+  assert_eq!(quick.sample.unwrap().estimated_total_records, Some(3));
+  assert_eq!(names(&quick.columns), vec!["col_0", "col_1", "col_2"]);
+
+  let task = eng.start_stats_task(&sid).unwrap();
+  for _ in 0..1000 {
+    if eng.get_task(&task.id).unwrap().finished {
+      break;
+    }
+    std::thread::sleep(std::time::Duration::from_millis(10));
+  }
+  assert_eq!(eng.stats_task_result(&task.id).unwrap().records_scanned, 3);
+
+  let schema = eng.get_schema(&sid).unwrap();
+  assert_eq!(schema.sampled_records, 3);
+  let fields: Vec<_> = schema.fields.iter().map(|f| (f.name.as_str(), f.kind.clone())).collect();
+  assert_eq!(
+    fields,
+    vec![
+      ("col_0", dh_core::JsonNodeKind::Number),
+      ("col_1", dh_core::JsonNodeKind::String),
+      ("col_2", dh_core::JsonNodeKind::Number),
+    ]
+  );
+}
+
+#[test]
+fn csv_type_inference_types_raw_and_exported_cells() {
+  let dir = tempfile::tempdir().unwrap();
+  let file = dir.path().join("scores.csv");
+  std::fs::write(&file, "id,score,ok,note,zip\n1,0.93,true,,007\n2,-4e2,FALSE,null, 12\n").unwrap();
+
+  let eng = engine_with_sqlite(dir.path().join("t.sqlite"));
+  let (session, first) = eng.open_file(&file).unwrap();
+  assert!(first.records[1].raw.as_deref().unwrap().contains(r#""score":"0.93""#));
+
+  let dialect = CsvDialect {
+    infer_types: true,
+    ..CsvDialect::default()
+  };
+  let page = eng.set_csv_dialect(&session.session_id, dialect).unwrap();
+  assert_eq!(page.records[0].raw.as_deref(), Some("id,score,ok,note,zip"));
+  let raw: serde_json::Value = serde_json::from_str(page.records[1].raw.as_deref().unwrap()).unwrap();
+  assert_eq!(raw, serde_json::json!({"id": 1, "score": 0.93, "ok": true, "note": null, "zip": "007"}));
+
+  let out = dir.path().join("out.jsonl");
+  eng
+    .export(
+      &session.session_id,
+      ExportRequest::Selection { record_ids: vec![1, 2] },
+      ExportFormat::Jsonl,
+      &out,
+    )
+    .unwrap();
+  let lines: Vec<serde_json::Value> = std::fs::read_to_string(out)
+    .unwrap()
+    .lines()
+    .map(|l| serde_json::from_str(l).unwrap())
+    .collect();
+  assert_eq!(lines[0]["score"], serde_json::json!(0.93));
+  assert_eq!(lines[1]["score"], serde_json::json!(-400.0));
+  assert_eq!(lines[1]["ok"], serde_json::json!(false));
+  assert_eq!(lines[1]["note"], serde_json::Value::Null);
+  assert_eq!(lines[1]["zip"], serde_json::json!(" 12"));
+}
+
+#[test]
+fn malformed_csv_rows_carry_warnings_and_a_session_summary() {
+  let dir = tempfile::tempdir().unwrap();
+  let file = dir.path().join("bad.csv");
+  std::fs::write(&file, "a,b,c\n1,2,3\n4,5\n6,7,8,9\n10,\"open,11\n12,13,14\n").unwrap();
+
+  let eng = engine_with_sqlite(dir.path().join("t.sqlite"));
+  let (session, first) = eng.open_file(&file).unwrap();
+  assert!(first.records.iter().all(|r| r.warning.is_none()));
+  assert!(eng.csv_warnings(&session.session_id).unwrap().rows.is_empty());
+
+  let page = eng.page_at(&session.session_id, 2, 10).unwrap();
+  let warnings: Vec<_> = page.records.iter().map(|r| (r.id, r.warning.clone())).collect();
+  assert_eq!(warnings[0], (2, Some("expected 3 fields, found 2".to_string())));
+  assert_eq!(warnings[1], (3, Some("expected 3 fields, found 4".to_string())));
+  assert_eq!(warnings.len(), 3);
+  assert!(warnings[2].1.as_deref().unwrap().starts_with("unterminated quoted field"));
+  assert!(page.records[1].raw.as_deref().unwrap().contains(r#""__extra__":["9"]"#));
+
+  // Served again: still one entry per row.
+  eng.page_at(&session.session_id, 3, 1).unwrap();
+  let summary = eng.csv_warnings(&session.session_id).unwrap();
+  assert_eq!(summary.rows.iter().map(|w| w.id).collect::<Vec<_>>(), vec![2, 3, 4]);
+  assert!(!summary.truncated);
+  assert_eq!(summary.rows[0].meta.as_ref().unwrap().line_no, 2);
+}
+
+#[test]
+fn csv_custom_quote_and_escape_split_cells_and_records() {
+  let dir = tempfile::tempdir().unwrap();
+  let file = dir.path().join("legacy.csv");
+  std::fs::write(&file, "name,note\n'Smith, J','it''s\ntwo lines'\nO\\,Brien,say \\'hi\\'\n").unwrap();
+
+  let eng = engine_with_sqlite(dir.path().join("t.sqlite"));
+  let (session, _) = eng.open_file(&file).unwrap();
+  assert_eq!(eng.count_records(&session.session_id).unwrap().total, Some(3));
+
+  let dialect = CsvDialect {
+    quote: '\'',
+    escape: Some('\\'),
+    ..CsvDialect::default()
+  };
+  let page = eng.set_csv_dialect(&session.session_id, dialect).unwrap();
+  assert_eq!(page.records.len(), 2);
+  let raw = |r: &dh_core::Record| serde_json::from_str::<serde_json::Value>(r.raw.as_deref().unwrap()).unwrap();
+  assert_eq!(raw(&page.records[1]), serde_json::json!({"name": "Smith, J", "note": "it's\ntwo lines"}));
+  assert!(page.records[1].warning.is_none());
+  assert_eq!(eng.count_records(&session.session_id).unwrap().total, Some(2));
+
+  let page = eng.page_at(&session.session_id, 2, 10).unwrap();
+  assert_eq!(page.records.len(), 1);
+  assert_eq!(raw(&page.records[0]), serde_json::json!({"name": "O,Brien", "note": "say 'hi'"}));
+
+  let stats = eng.get_stats(&session.session_id).unwrap();
+  assert_eq!(stats.records_scanned, 2);
+  assert_eq!(stats.invalid_records, 0);
+  assert_eq!(stats.columns.iter().map(|c| c.name.as_str()).collect::<Vec<_>>(), vec!["name", "note"]);
+  for strategy in [
+    dh_core::StatsSampleStrategy::Head,
+    dh_core::StatsSampleStrategy::Tail,
+    dh_core::StatsSampleStrategy::Random,
+  ] {
+    let quick = eng.quick_stats(&session.session_id, strategy, 10).unwrap();
+    assert_eq!(quick.records_scanned, 2, "{strategy:?}");
+    assert_eq!(quick.columns.len(), 2, "{strategy:?}");
+    assert_eq!(quick.sample.unwrap().estimated_total_records, Some(2), "{strategy:?}");
+  }
+  let schema = eng.get_schema(&session.session_id).unwrap();
+  assert_eq!(schema.sampled_records, 2);
+  assert_eq!(schema.fields.iter().map(|f| f.name.as_str()).collect::<Vec<_>>(), vec!["name", "note"]);
+
+  assert!(matches!(
+    eng.set_csv_dialect(&session.session_id, CsvDialect { escape: Some('"'), ..CsvDialect::default() }),
+    Err(CoreError::InvalidArg(_))
+  ));
+}
+
+#[test]
+fn csv_record_index_follows_quoting_and_is_reused_from_sqlite() {
+  let dir = tempfile::tempdir().unwrap();
+  let sqlite = dir.path().join("t.sqlite");
+  let indexing_engine = |sqlite_path: PathBuf| {
+    CoreEngine::new(CoreOptions {
+      default_page_size: 2,
+      line_index_min_bytes: 0,
+      storage: StorageOptions {
+        sqlite_path: Some(sqlite_path),
+        ..StorageOptions::default()
+      },
+      ..CoreOptions::default()
+    })
+    .unwrap()
+  };
+  let file = dir.path().join("notes.csv");
+  let mut s = String::from("id,note\n");
+  for i in 0..3000 {
+    s.push_str(&format!("{i},'line one\nline two of {i}'\n"));
+  }
+  std::fs::write(&file, s).unwrap();
+  let single_quotes = CsvDialect {
+    quote: '\'',
+    ..CsvDialect::default()
+  };
+  let wait = |eng: &CoreEngine, task_id: &str| {
+    for _ in 0..200 {
+      if eng.get_task(task_id).unwrap().finished {
+        break;
+      }
+      thread::sleep(Duration::from_millis(10));
+    }
+  };
+
+  let eng = indexing_engine(sqlite.clone());
+  let (session, _) = eng.open_file(&file).unwrap();
+  wait(&eng, &session.index_task.unwrap().id);
+  assert_eq!(eng.count_records(&session.session_id).unwrap().total, Some(6000));
+
+  eng.set_csv_dialect(&session.session_id, single_quotes.clone()).unwrap();
+  let info = eng.list_sessions().into_iter().find(|s| s.session_id == session.session_id).unwrap();
+  let task = info.index_task.expect("index rebuilt for the new quoting");
+  assert_eq!(task.kind, TaskKind::IndexBuild);
+  wait(&eng, &task.id);
+  assert_eq!(eng.count_records(&session.session_id).unwrap().total, Some(3000));
+  let page = eng.page_at(&session.session_id, 2501, 1).unwrap();
+  assert!(page.records[0].raw.as_deref().unwrap().contains("line two of 2500"));
+  let page = eng.page_at(&session.session_id, 11, 1).unwrap();
+  assert!(page.records[0].raw.as_deref().unwrap().contains("line two of 10"));
+
+  // Fresh engine: both indexes come from SQLite.
+  let eng = indexing_engine(sqlite);
+  let (session, _) = eng.open_file(&file).unwrap();
+  assert!(session.index_task.is_none());
+  eng.set_csv_dialect(&session.session_id, single_quotes).unwrap();
+  let info = eng.list_sessions().into_iter().find(|s| s.session_id == session.session_id).unwrap();
+  assert!(info.index_task.is_none());
+  assert_eq!(eng.count_records(&session.session_id).unwrap().total, Some(3000));
+  let page = eng.page_at(&session.session_id, 3000, 1).unwrap();
+  assert!(page.records[0].raw.as_deref().unwrap().contains("line two of 2999"));
+}
+
+#[test]
+fn csv_schema_sniffs_delimiter_types_and_row_estimate() {
+  let dir = tempfile::tempdir().unwrap();
+  let small = dir.path().join("eu.csv");
+  std::fs::write(&small, "id;name;price\n1;\"Smith; J\";2.5\n2;Bob;3\n3;Carol;\n").unwrap();
+  let big = dir.path().join("big.csv");
+  let mut s = String::from("a,b\n");
+  for i in 0..5000 {
+    s.push_str(&format!("{i:04},x\n"));
+  }
+  std::fs::write(&big, s).unwrap();
+
+  let eng = engine_with_sqlite(dir.path().join("t.sqlite"));
+  let schema = eng.csv_schema(&small).unwrap();
+  assert_eq!(schema.delimiter, ";");
+  assert_eq!(schema.header, vec!["id", "name", "price"]);
+  let kinds: Vec<_> = schema.fields.iter().map(|f| (f.name.as_str(), f.kind.clone(), f.nullable)).collect();
+  assert_eq!(
+    kinds,
+    vec![
+      ("id", JsonNodeKind::Number, false),
+      ("name", JsonNodeKind::String, false),
+      ("price", JsonNodeKind::Number, true)
+    ]
+  );
+  assert_eq!((schema.estimated_records, schema.exact), (3, true));
+
+  let schema = eng.csv_schema(&big).unwrap();
+  assert_eq!(schema.delimiter, ",");
+  assert_eq!(schema.sampled_records, 1000);
+  assert!(!schema.exact);
+  assert!((4900..=5100).contains(&schema.estimated_records), "{}", schema.estimated_records);
+
+  // The sniffed delimiter reads the file in a session.
+  let (session, _) = eng.open_file(&small).unwrap();
+  let dialect = CsvDialect {
+    delimiter: ";".into(),
+    ..CsvDialect::default()
+  };
+  let page = eng.set_csv_dialect(&session.session_id, dialect).unwrap();
+  assert_eq!(page.records[1].raw.as_deref(), Some(r#"{"id":"1","name":"Smith; J","price":"2.5"}"#));
+  assert!(matches!(eng.csv_schema(dir.path().join("t.sqlite")), Err(CoreError::UnsupportedFormat(_))));
+}
+
+#[test]
+fn csv_session_columns_project_every_page_and_restore_from_prefs() {
+  let dir = tempfile::tempdir().unwrap();
+  let file = dir.path().join("wide.csv");
+  let mut s = String::from("a,b,c,d\n");
+  for i in 0..50 {
+    s.push_str(&format!("{i},b{i},c{i},d{i}\n"));
+  }
+  std::fs::write(&file, s).unwrap();
+  let sqlite = dir.path().join("t.sqlite");
+  let eng = engine_with_sqlite(sqlite.clone());
+  let (session, _) = eng.open_file(&file).unwrap();
+  let sid = &session.session_id;
+
+  let page = eng.set_session_columns(sid, Some(vec!["d".into(), "a".into()])).unwrap();
+  assert_eq!(page.records[0].preview, "d,a");
+  assert_eq!(page.records[1].preview, "d0,0");
+  assert_eq!(page.records[1].raw.as_deref(), Some(r#"{"a":"0","d":"d0"}"#));
+  let next = eng.next_page(sid, page.next_cursor.as_deref(), 5).unwrap();
+  assert!(next.records.iter().all(|r| r.preview.split(',').count() == 2));
+  let at = eng.page_at(sid, 30, 1).unwrap();
+  assert_eq!(at.records[0].preview, "d29,29");
+  let positioned = eng.page_at_position(sid, dh_core::SeekPosition::Fraction { value: 0.5 }, 1).unwrap();
+  assert!(!positioned.page.records[0].raw.as_deref().unwrap().contains("\"b\""));
+  assert!(matches!(
+    eng.set_session_columns(sid, Some(vec!["nope".into()])),
+    Err(CoreError::InvalidArg(_))
+  ));
+
+  // A dialect that splits the header differently drops the columns it no longer has.
+  let dialect = CsvDialect {
+    has_header: false,
+    ..CsvDialect::default()
+  };
+  let page = eng.set_csv_dialect(sid, dialect).unwrap();
+  assert_eq!(page.records[0].preview, "a,b,c,d");
+  let info = eng.list_sessions().into_iter().find(|s| s.session_id == *sid).unwrap();
+  assert_eq!(info.columns, None);
+  eng.set_csv_dialect(sid, CsvDialect::default()).unwrap();
+  assert_eq!(eng.set_session_columns(sid, None).unwrap().records[1].preview, "0,b0,c0,d0");
+
+  // Saved prefs select the columns again on open.
+  eng
+    .save_view_prefs(
+      sid,
+      ViewPrefs {
+        columns: Some(vec!["c".into(), "gone".into()]),
+        ..ViewPrefs::default()
+      },
+    )
+    .unwrap();
+  drop(eng);
+  let eng = engine_with_sqlite(sqlite.clone());
+  let (session, first) = eng.open_file(&file).unwrap();
+  assert_eq!(session.columns, Some(vec!["c".to_string()]));
+  assert_eq!(first.records[1].preview, "c0");
+}
+
+#[test]
+fn csv_multi_character_delimiters_split_and_export_cells() {
+  let dir = tempfile::tempdir().unwrap();
+  let file = dir.path().join("etl.csv");
+  std::fs::write(&file, "id||name||note\n1||a|b||\"x || y\"\n2||carol||\n").unwrap();
+  let eng = engine_with_sqlite(dir.path().join("t.sqlite"));
+  let (session, _) = eng.open_file(&file).unwrap();
+  let sid = &session.session_id;
+
+  let dialect = CsvDialect {
+    delimiter: "||".into(),
+    ..CsvDialect::default()
+  };
+  let page = eng.set_csv_dialect(sid, dialect).unwrap();
+  let raw = |r: &dh_core::Record| serde_json::from_str::<serde_json::Value>(r.raw.as_deref().unwrap()).unwrap();
+  assert_eq!(raw(&page.records[1]), serde_json::json!({"id": "1", "name": "a|b", "note": "x || y"}));
+  assert!(page.records[1].warning.is_none());
+  let page = eng.page_at(sid, 2, 1).unwrap();
+  assert_eq!(raw(&page.records[0]), serde_json::json!({"id": "2", "name": "carol", "note": ""}));
+  let page = eng.set_session_columns(sid, Some(vec!["note".into(), "name".into()])).unwrap();
+  assert_eq!(page.records[1].preview, "\"x || y\"||a|b");
+
+  eng
+    .set_derived_columns(
+      sid,
+      vec![DerivedColumn {
+        name: "tag".into(),
+        expr: "upper(name)".into(),
+      }],
+    )
+    .unwrap();
+  let out = dir.path().join("out.csv");
+  eng
+    .export(sid, ExportRequest::Selection { record_ids: vec![0, 1] }, ExportFormat::Csv, &out)
+    .unwrap();
+  assert_eq!(
+    std::fs::read_to_string(&out).unwrap(),
+    "id||name||note||tag\n1||a|b||\"x || y\"||A|B\n"
+  );
+
+  let bad = |delimiter: &str| CsvDialect {
+    delimiter: delimiter.into(),
+    ..CsvDialect::default()
+  };
+  assert!(matches!(eng.set_csv_dialect(sid, bad("")), Err(CoreError::InvalidArg(_))));
+  assert!(matches!(eng.set_csv_dialect(sid, bad("|\"|")), Err(CoreError::InvalidArg(_))));
+  assert!(eng.set_csv_dialect(sid, bad("\t|\t")).is_ok());
+}
+
+#[test]
+fn csv_regex_delimiters_split_cells_in_paging_scans_edits_and_exports() {
+  let dir = tempfile::tempdir().unwrap();
+  let file = dir.path().join("etl.csv");
+  std::fs::write(&file, "id ; name;note\n1;  Ann ;\"x ; y\"\n2 ;bob; z\n").unwrap();
+  let eng = engine_with_sqlite(dir.path().join("t.sqlite"));
+  let (session, _) = eng.open_file(&file).unwrap();
+  let sid = &session.session_id;
+
+  let dialect = |pattern: &str| CsvDialect {
+    delimiter: ";".into(),
+    delimiter_regex: Some(pattern.into()),
+    ..CsvDialect::default()
+  };
+  let page = eng.set_csv_dialect(sid, dialect(r"\s*;\s*")).unwrap();
+  let raw = |r: &dh_core::Record| serde_json::from_str::<serde_json::Value>(r.raw.as_deref().unwrap()).unwrap();
+  assert_eq!(raw(&page.records[1]), serde_json::json!({"id": "1", "name": "Ann", "note": "x ; y"}));
+  assert!(page.records[1].warning.is_none());
+  let page = eng.page_at(sid, 2, 1).unwrap();
+  assert_eq!(raw(&page.records[0]), serde_json::json!({"id": "2", "name": "bob", "note": "z"}));
+  let meta = page.records[0].meta.clone().unwrap();
+  assert_eq!(eng.get_cell_raw(sid, meta.clone(), "note").unwrap(), "z");
+  assert_eq!(eng.count_records(sid).unwrap().total, Some(2));
+  let stats = eng.get_stats(sid).unwrap();
+  assert_eq!(stats.columns.iter().map(|c| c.name.as_str()).collect::<Vec<_>>(), ["id", "name", "note"]);
+
+  let task = eng
+    .search(
+      sid,
+      SearchQuery {
+        text: "bob".into(),
+        mode: SearchMode::ScanAll,
+        ..SearchQuery::default()
+      },
+    )
+    .unwrap()
+    .task
+    .unwrap();
+  for _ in 0..100 {
+    if eng.get_task(&task.id).unwrap().finished {
+      break;
+    }
+    thread::sleep(Duration::from_millis(10));
+  }
+  let hits = eng.search_task_hits_page(&task.id, None, 10).unwrap();
+  assert_eq!(hits.records.iter().map(|r| r.id).collect::<Vec<_>>(), [2]);
+
+  // Edits write the literal delimiter, quoting cells the pattern would split.
+  let edited = dir.path().join("edited.csv");
+  eng
+    .save_record_edit(sid, meta, r#"{"id":"2","name":"b ; c","note":"w"}"#, &edited)
+    .unwrap();
+  assert!(std::fs::read_to_string(&edited).unwrap().ends_with("2;\"b ; c\";w\n"));
+
+  eng
+    .set_derived_columns(
+      sid,
+      vec![DerivedColumn {
+        name: "tag".into(),
+        expr: "upper(name)".into(),
+      }],
+    )
+    .unwrap();
+  let out = dir.path().join("out.csv");
+  eng
+    .export(sid, ExportRequest::Selection { record_ids: vec![0, 2] }, ExportFormat::Csv, &out)
+    .unwrap();
+  assert_eq!(std::fs::read_to_string(&out).unwrap(), "id ; name;note;tag\n2 ;bob; z;BOB\n");
+
+  for bad in [r"(", r"\s*", r"\s+", r";|\n", "[;\"]"] {
+    assert!(matches!(eng.set_csv_dialect(sid, dialect(bad)), Err(CoreError::InvalidArg(_))), "{bad}");
+  }
+}
+
+#[test]
+fn oversized_csv_cells_are_flagged_in_raw_and_fetched_whole() {
+  let dir = tempfile::tempdir().unwrap();
+  let file = dir.path().join("blobs.csv");
+  let blob = format!("{{\"\"items\"\":[{}]}}", vec!["1"; 500].join(","));
+  let whole = blob.replace("\"\"", "\"");
+  std::fs::write(&file, format!("id,blob,note\r\n1,\"{blob}\",n1\r\n2,small,n2\r\n")).unwrap();
+  let eng = engine_with_sqlite(dir.path().join("t.sqlite"));
+  let (session, page) = eng.open_file(&file).unwrap();
+  let sid = &session.session_id;
+
+  let raw: serde_json::Value = serde_json::from_str(page.records[1].raw.as_deref().unwrap()).unwrap();
+  assert_eq!(raw["__truncated__"], serde_json::json!(["blob"]));
+  assert_eq!(raw["id"], serde_json::json!("1"));
+  assert!(raw["blob"].as_str().unwrap().ends_with('…'));
+  assert_eq!(raw["blob"].as_str().unwrap().chars().count(), 201);
+  let meta = page.records[1].meta.clone().unwrap();
+  assert_eq!(eng.get_cell_raw(sid, meta.clone(), "blob").unwrap(), whole);
+  assert_eq!(eng.get_cell_raw(sid, meta.clone(), "note").unwrap(), "n1");
+  assert!(matches!(eng.get_cell_raw(sid, meta, "nope"), Err(CoreError::InvalidArg(_))));
+
+  let next = eng.next_page(sid, page.next_cursor.as_deref(), 1).unwrap();
+  assert_eq!(next.records[0].raw.as_deref(), Some(r#"{"blob":"small","id":"2","note":"n2"}"#));
+
+  // Derived columns see the whole cell.
+  let page = eng
+    .set_derived_columns(
+      sid,
+      vec![DerivedColumn {
+        name: "n".into(),
+        expr: "len(blob)".into(),
+      }],
+    )
+    .unwrap();
+  assert_eq!(page.records[1].derived.as_ref().unwrap()["n"], serde_json::json!(whole.chars().count()));
+}
+
+#[test]
+fn csv_and_parquet_records_carry_cells_aligned_with_columns() {
+  let dir = tempfile::tempdir().unwrap();
+  let eng = engine_with_sqlite(dir.path().join("t.sqlite"));
+  let cells = |r: &dh_core::Record| r.columns.clone().unwrap();
+
+  let csv = dir.path().join("a.csv");
+  std::fs::write(&csv, "a,,c\n1,\"x,y\"\n4,5,6,7\n").unwrap();
+  let (session, page) = eng.open_file(&csv).unwrap();
+  assert_eq!(cells(&page.records[0]), vec!["a", "col_1", "c"]);
+  // Missing cells are empty; extra ones stay in `raw.__extra__`.
+  assert_eq!(cells(&page.records[1]), vec!["1", "x,y", ""]);
+  let page = eng.page_at(&session.session_id, 2, 1).unwrap();
+  assert_eq!(cells(&page.records[0]), vec!["4", "5", "6"]);
+  let page = eng
+    .set_session_columns(&session.session_id, Some(vec!["c".into(), "a".into()]))
+    .unwrap();
+  assert_eq!(cells(&page.records[0]), vec!["c", "a"]);
+  assert_eq!(cells(&page.records[1]), vec!["", "1"]);
+
+  let parquet = dir.path().join("a.parquet");
+  duckdb::Connection::open_in_memory()
+    .unwrap()
+    .execute_batch(&format!(
+      "COPY (SELECT 1 AS a, 'two' AS b, NULL::INTEGER AS c) TO '{}' (FORMAT PARQUET);",
+      parquet.display()
+    ))
+    .unwrap();
+  let (_session, page) = eng.open_file(&parquet).unwrap();
+  assert_eq!(page.records[0].columns.as_ref().unwrap().len(), 3);
+  assert_eq!(page.records[0].columns.as_ref().unwrap()[..2], ["1", "two"]);
+
+  let jsonl = dir.path().join("a.jsonl");
+  std::fs::write(&jsonl, "{\"a\":1}\n").unwrap();
+  let (_session, page) = eng.open_file(&jsonl).unwrap();
+  assert!(page.records[0].columns.is_none());
+}
+
+#[test]
+fn json_find_in_record_locates_deep_fields_by_node_offsets() {
+  let dir = tempfile::tempdir().unwrap();
+  let file = dir.path().join("deep.jsonl");
+  let mut deep = String::from("{\"Needle\": [1, \"hay\", null]}");
+  for i in (0..12).rev() {
+    deep = format!("{{\"level{i}\": {deep}, \"n{i}\": {i}}}");
+  }
+  std::fs::write(&file, format!("{{\"a\":1}}\n{deep}\n")).unwrap();
+  let eng = engine_with_sqlite(dir.path().join("t.sqlite"));
+  let (session, page) = eng.open_file(&file).unwrap();
+  let sid = &session.session_id;
+  let meta = page.records[1].meta.clone().unwrap();
+
+  let query = |text: &str| JsonFindQuery {
+    text: text.into(),
+    ..JsonFindQuery::default()
+  };
+  let found = eng.json_find_in_record(sid, meta.clone(), query("needle")).unwrap();
+  assert_eq!(found.hits.len(), 1);
+  let hit = &found.hits[0];
+  assert!(hit.key_match);
+  assert_eq!(hit.kind, JsonNodeKind::Array);
+  assert_eq!(hit.path.len(), 13);
+  assert_eq!(hit.path[12], JsonPathSegment::Key("Needle".into()));
+  assert_eq!(hit.node_offsets.last(), Some(&hit.value_offset));
+  // Each offset is a child of the previous node as the tree lists it.
+  let mut parent = meta.byte_offset;
+  for (seg, &offset) in hit.path.iter().zip(&hit.node_offsets) {
+    let children = eng.json_list_children_at_offset(sid, meta.clone(), parent, None, None, 10, JsonChildSort::Original).unwrap();
+    let child = children.items.iter().find(|c| c.seg == *seg).unwrap();
+    assert_eq!(child.value_offset, offset);
+    parent = offset;
+  }
+
+  // Values: strings, numbers and literals; keys can be left out.
+  let found = eng.json_find_in_record(sid, meta.clone(), query("hay")).unwrap();
+  assert_eq!(found.hits[0].path.last(), Some(&JsonPathSegment::Index(1)));
+  assert_eq!(found.hits[0].preview, "hay");
+  let found = eng
+    .json_find_in_record(
+      sid,
+      meta.clone(),
+      JsonFindQuery {
+        text: "n1".into(),
+        keys: false,
+        ..JsonFindQuery::default()
+      },
+    )
+    .unwrap();
+  assert!(found.hits.is_empty());
+  let found = eng
+    .json_find_in_record(
+      sid,
+      meta.clone(),
+      JsonFindQuery {
+        text: "1".into(),
+        values: false,
+        max_hits: 2,
+        ..JsonFindQuery::default()
+      },
+    )
+    .unwrap();
+  assert_eq!(found.hits.len(), 2);
+  assert!(found.truncated);
+  assert!(found.hits.iter().all(|h| h.key_match));
+  assert!(matches!(eng.json_find_in_record(sid, meta, query("")), Err(CoreError::InvalidArg(_))));
+}
+
+#[test]
+fn json_eval_path_selects_nodes_of_one_record() {
+  let dir = tempfile::tempdir().unwrap();
+  let file = dir.path().join("chat.jsonl");
+  let chat = r#"{"id":7,"messages":[{"role":"system","content":"be brief"},{"role":"user","content":{"role":"nested"}},{"content":"no role"}],"meta":{"role":"x"}}"#;
+  std::fs::write(&file, format!("{{\"a\":1}}\n{chat}\n")).unwrap();
+  let eng = engine_with_sqlite(dir.path().join("t.sqlite"));
+  let (session, page) = eng.open_file(&file).unwrap();
+  let sid = &session.session_id;
+  let meta = page.records[1].meta.clone().unwrap();
+
+  let found = eng.json_eval_path(sid, meta.clone(), "$.messages[*].role", None).unwrap();
+  let previews: Vec<&str> = found.matches.iter().map(|m| m.preview.as_str()).collect();
+  assert_eq!(previews, ["\"system\"", "\"user\""]);
+  assert!(!found.truncated);
+  let first = &found.matches[0];
+  assert_eq!(
+    first.path,
+    [
+      JsonPathSegment::Key("messages".into()),
+      JsonPathSegment::Index(0),
+      JsonPathSegment::Key("role".into())
+    ]
+  );
+  assert_eq!(first.node_offsets.last(), Some(&first.value_offset));
+  let children = eng
+    .json_list_children_at_offset(sid, meta.clone(), first.node_offsets[1], None, None, 10, JsonChildSort::Original)
+    .unwrap();
+  assert_eq!(children.items[0].value_offset, first.value_offset);
+
+  // Recursive descent, bracketed names and indexes; containers that are matched in full preview as written.
+  let found = eng.json_eval_path(sid, meta.clone(), "$..role", None).unwrap();
+  assert_eq!(found.matches.len(), 4);
+  assert_eq!(found.matches[2].preview, "\"nested\"");
+  let found = eng.json_eval_path(sid, meta.clone(), "$['messages'][1].content", None).unwrap();
+  assert_eq!(found.matches[0].kind, JsonNodeKind::Object);
+  assert_eq!(found.matches[0].preview, r#"{"role":"nested"}"#);
+  let found = eng.json_eval_path(sid, meta.clone(), "$.messages.*", Some(2)).unwrap();
+  assert_eq!(found.matches.len(), 2);
+  assert!(found.truncated);
+  assert!(eng.json_eval_path(sid, meta.clone(), "$.nope[3]", None).unwrap().matches.is_empty());
+  assert!(matches!(eng.json_eval_path(sid, meta, "messages[?(@.role)]", None), Err(CoreError::InvalidArg(_))));
+}
+
+#[test]
+fn json_node_stats_measures_subtrees() {
+  let dir = tempfile::tempdir().unwrap();
+  let file = dir.path().join("n.jsonl");
+  let doc = r#"{"a": [1, "x,]", {"b": [[], {}]}], "c": {"d": null}, "e": "}"}"#;
+  std::fs::write(&file, format!("{doc}\n")).unwrap();
+  let eng = engine_with_sqlite(dir.path().join("t.sqlite"));
+  let (session, page) = eng.open_file(&file).unwrap();
+  let sid = &session.session_id;
+  let meta = page.records[0].meta.clone().unwrap();
+
+  let root = eng.json_node_stats(sid, meta.byte_offset).unwrap();
+  assert_eq!(root.kind, JsonNodeKind::Object);
+  assert_eq!(root.byte_len, doc.len() as u64);
+  assert_eq!(root.max_depth, 5);
+  assert_eq!(root.child_count, Some(3));
+  assert_eq!(root.keys, 5);
+  assert_eq!(root.elements, 5);
+
+  let children = eng.json_list_children_at_offset(sid, meta.clone(), meta.byte_offset, None, None, 10, JsonChildSort::Original).unwrap();
+  let a = eng.json_node_stats(sid, children.items[0].value_offset).unwrap();
+  assert_eq!(a.kind, JsonNodeKind::Array);
+  assert_eq!(a.byte_len, r#"[1, "x,]", {"b": [[], {}]}]"#.len() as u64);
+  assert_eq!((a.max_depth, a.child_count, a.elements, a.keys), (4, Some(3), 5, 1));
+  let e = eng.json_node_stats(sid, children.items[2].value_offset).unwrap();
+  assert_eq!((e.kind, e.byte_len, e.max_depth, e.child_count), (JsonNodeKind::String, 3, 0, None));
+}
+
+#[test]
+fn json_path_strings_render_pointer_and_js_paths() {
+  let dir = tempfile::tempdir().unwrap();
+  let eng = engine_with_sqlite(dir.path().join("t.sqlite"));
+  let key = |k: &str| JsonPathSegment::Key(k.into());
+
+  let plain = eng.json_path_strings(&[key("a"), key("b"), JsonPathSegment::Index(0)]);
+  assert_eq!(plain.pointer, "/a/b/0");
+  assert_eq!(plain.js, "a.b[0]");
+  let odd = eng.json_path_strings(&[JsonPathSegment::Index(2), key("a/b~c"), key("say \"hi\""), key("$ok_1"), key("")]);
+  assert_eq!(odd.pointer, "/2/a~1b~0c/say \"hi\"/$ok_1/");
+  assert_eq!(odd.js, r#"[2]["a/b~c"]["say \"hi\""].$ok_1[""]"#);
+  let root = eng.json_path_strings(&[]);
+  assert_eq!((root.pointer.as_str(), root.js.as_str()), ("", ""));
+}
+
+#[test]
+fn save_json_node_edit_replaces_one_node_in_a_copy() {
+  let dir = tempfile::tempdir().unwrap();
+  let file = dir.path().join("r.jsonl");
+  std::fs::write(&file, "{\"a\": 1}\r\n{\"m\": [{\"x\": \"old\"}, 2],  \"n\": true}\r\n{\"z\":0}\r\n").unwrap();
+  let eng = engine_with_sqlite(dir.path().join("t.sqlite"));
+  let (session, page) = eng.open_file(&file).unwrap();
+  let sid = &session.session_id;
+  let meta = page.records[1].meta.clone().unwrap();
+  let out = dir.path().join("out.jsonl");
+
+  let path = [JsonPathSegment::Key("m".into()), JsonPathSegment::Index(0), JsonPathSegment::Key("x".into())];
+  let saved = eng
+    .save_json_node_edit(sid, meta.clone(), None, &path, "{\n \"fixed\": [1, 2]\n}", &out)
+    .unwrap();
+  assert_eq!(saved.records_written, 1);
+  assert_eq!(
+    std::fs::read_to_string(&out).unwrap(),
+    "{\"a\": 1}\r\n{\"m\": [{\"x\": {\"fixed\":[1,2]}}, 2],  \"n\": true}\r\n{\"z\":0}\r\n"
+  );
+
+  // By offset, as the offset tree lists it; whole containers too.
+  let children = eng.json_list_children_at_offset(sid, meta.clone(), meta.byte_offset, None, None, 10, JsonChildSort::Original).unwrap();
+  eng
+    .save_json_node_edit(sid, meta.clone(), Some(children.items[0].value_offset), &[], "null", &out)
+    .unwrap();
+  assert_eq!(
+    std::fs::read_to_string(&out).unwrap(),
+    "{\"a\": 1}\r\n{\"m\": null,  \"n\": true}\r\n{\"z\":0}\r\n"
+  );
+
+  assert!(matches!(
+    eng.save_json_node_edit(sid, meta.clone(), None, &path, "{oops", &out),
+    Err(CoreError::InvalidArg(_))
+  ));
+  assert!(matches!(
+    eng.save_json_node_edit(sid, meta.clone(), Some(0), &[], "1", &out),
+    Err(CoreError::InvalidArg(_))
+  ));
+  assert!(matches!(
+    eng.save_json_node_edit(sid, meta, None, &path, "1", &file),
+    Err(CoreError::InvalidArg(_))
+  ));
+}
+
+#[test]
+fn json_array_table_pages_objects_as_rows() {
+  let dir = tempfile::tempdir().unwrap();
+  let file = dir.path().join("t.json");
+  let doc = r#"{"rows": [{"id": 1, "name": "a"}, {"name": "b", "tags": [1, 2]}, 7, {"id": 4, "extra": null}]}"#;
+  std::fs::write(&file, doc).unwrap();
+  let eng = engine_with_sqlite(dir.path().join("t.sqlite"));
+  let (session, page) = eng.open_file(&file).unwrap();
+  let sid = &session.session_id;
+  let meta = page.records[0].meta.clone().unwrap();
+  let children = eng.json_list_children_at_offset(sid, meta.clone(), meta.byte_offset, None, None, 10, JsonChildSort::Original).unwrap();
+  let rows_offset = children.items[0].value_offset;
+
+  let first = eng.json_array_table(sid, meta.clone(), rows_offset, vec![], None, None, 2).unwrap();
+  assert_eq!(first.columns, ["id", "name", "tags"]);
+  assert_eq!(first.rows.len(), 2);
+  let cell = |row: &dh_core::JsonTableRow, col: usize| row.cells[col].as_ref().map(|c| c.preview.clone());
+  assert_eq!(cell(&first.rows[0], 0).as_deref(), Some("1"));
+  assert_eq!(cell(&first.rows[0], 2), None);
+  assert_eq!(cell(&first.rows[1], 1).as_deref(), Some("\"b\""));
+  let tags = first.rows[1].cells[2].as_ref().unwrap();
+  assert_eq!(tags.kind, JsonNodeKind::Array);
+  let nested = eng.json_list_children_at_offset(sid, meta.clone(), tags.value_offset, None, None, 10, JsonChildSort::Original).unwrap();
+  assert_eq!(nested.items.len(), 2);
+  assert!(!first.reached_end);
+
+  // Columns carried over keep their order; new keys are appended; non-objects get a preview.
+  let next = eng
+    .json_array_table(
+      sid,
+      meta.clone(),
+      rows_offset,
+      first.columns.clone(),
+      first.next_cursor_offset,
+      first.next_cursor_index,
+      2,
+    )
+    .unwrap();
+  assert!(next.reached_end);
+  assert_eq!(next.columns, ["id", "name", "tags", "extra"]);
+  assert_eq!((next.rows[0].index, next.rows[0].preview.as_deref()), (2, Some("7")));
+  assert!(next.rows[0].cells.iter().all(Option::is_none));
+  assert_eq!(next.rows[1].cells.len(), 4);
+  assert_eq!(cell(&next.rows[1], 3).as_deref(), Some("null"));
+
+  assert!(matches!(
+    eng.json_array_table(sid, meta.clone(), meta.byte_offset, vec![], None, None, 10),
+    Err(CoreError::InvalidArg(_))
+  ));
+}
+
+#[test]
+fn json_children_list_sorted_by_key_or_size() {
+  let dir = tempfile::tempdir().unwrap();
+  let file = dir.path().join("s.json");
+  std::fs::write(&file, r#"{"b": "xx", "C": [1, 2, 3], "a": {"k": "a long value here"}, "d": 0}"#).unwrap();
+  let eng = engine_with_sqlite(dir.path().join("t.sqlite"));
+  let (session, page) = eng.open_file(&file).unwrap();
+  let sid = &session.session_id;
+  let meta = page.records[0].meta.clone().unwrap();
+  let keys = |items: Vec<JsonPathSegment>| {
+    items
+      .into_iter()
+      .map(|s| match s {
+        JsonPathSegment::Key(k) => k,
+        JsonPathSegment::Index(i) => i.to_string(),
+      })
+      .collect::<Vec<_>>()
+  };
+
+  let original = eng
+    .json_list_children_at_offset(sid, meta.clone(), meta.byte_offset, None, None, 10, JsonChildSort::Original)
+    .unwrap();
+  assert_eq!(keys(original.items.into_iter().map(|c| c.seg).collect()), ["b", "C", "a", "d"]);
+
+  // Sorted pages go by position; the offsets still expand the children.
+  let first = eng
+    .json_list_children_at_offset(sid, meta.clone(), meta.byte_offset, None, None, 3, JsonChildSort::Key)
+    .unwrap();
+  assert_eq!(keys(first.items.iter().map(|c| c.seg.clone()).collect()), ["a", "b", "C"]);
+  assert_eq!(first.next_cursor_index, Some(3));
+  let c = eng
+    .json_list_children_at_offset(sid, meta.clone(), first.items[2].value_offset, None, None, 10, JsonChildSort::Original)
+    .unwrap();
+  assert_eq!(c.items.len(), 3);
+  let rest = eng
+    .json_list_children_at_offset(
+      sid,
+      meta.clone(),
+      meta.byte_offset,
+      first.next_cursor_offset,
+      first.next_cursor_index,
+      3,
+      JsonChildSort::Key,
+    )
+    .unwrap();
+  assert!(rest.reached_end);
+  assert_eq!(keys(rest.items.into_iter().map(|c| c.seg).collect()), ["d"]);
+
+  let by_size = eng.json_list_children(sid, meta.clone(), vec![], None, 10, JsonChildSort::Size).unwrap();
+  assert_eq!(keys(by_size.items.into_iter().map(|c| c.seg).collect()), ["a", "C", "b", "d"]);
+  let nested = eng
+    .json_list_children(sid, meta, vec![JsonPathSegment::Key("C".into())], Some(1), 1, JsonChildSort::Size)
+    .unwrap();
+  assert_eq!(keys(nested.items.into_iter().map(|c| c.seg).collect()), ["1"]);
+  assert_eq!(nested.next_cursor, Some(2));
+}
+
+#[test]
+fn json_node_index_serves_deep_nodes_and_persists() {
+  let dir = tempfile::tempdir().unwrap();
+  let file = dir.path().join("big.json");
+  let items: Vec<String> = (0..2000).map(|i| format!(r#"{{"id": {i}, "name": "item number {i}", "tags": ["x", "y"]}}"#)).collect();
+  std::fs::write(&file, format!(r#"{{"head": 1, "deep": {{"a": [{}]}}, "tail": true}}"#, items.join(", "))).unwrap();
+  let sqlite = dir.path().join("t.sqlite");
+  let path = |segs: &[&str]| {
+    segs
+      .iter()
+      .map(|s| match s.parse::<u64>() {
+        Ok(i) => JsonPathSegment::Index(i),
+        Err(_) => JsonPathSegment::Key(s.to_string()),
+      })
+      .collect::<Vec<_>>()
+  };
+
+  let eng = engine_with_sqlite(sqlite.clone());
+  let (session, page) = eng.open_file(&file).unwrap();
+  let sid = &session.session_id;
+  let meta = page.records[0].meta.clone().unwrap();
+  let task = eng.build_json_node_index(sid, meta.clone()).unwrap().expect("index task");
+  assert_eq!(task.kind, TaskKind::IndexBuild);
+  for _ in 0..200 {
+    if eng.get_task(&task.id).unwrap().finished {
+      break;
+    }
+    thread::sleep(Duration::from_millis(10));
+  }
+  assert!(eng.build_json_node_index(sid, meta.clone()).unwrap().is_none());
+
+  // Indexed nodes are summarized from the index: complete even with no scan budget.
+  let summary = eng.json_node_summary(sid, meta.clone(), path(&["deep", "a"]), Some(1), Some(1)).unwrap();
+  assert!(summary.complete);
+  assert_eq!(summary.child_count, Some(2000));
+  let root = eng
+    .json_list_children_at_offset(sid, meta.clone(), meta.byte_offset, None, None, 10, JsonChildSort::Original)
+    .unwrap();
+  let deep = eng
+    .json_list_children_at_offset(sid, meta.clone(), root.items[1].value_offset, None, None, 10, JsonChildSort::Original)
+    .unwrap();
+  let a = eng
+    .json_node_summary_at_offset(sid, meta.clone(), deep.items[0].value_offset, Some(1), Some(1))
+    .unwrap();
+  assert!(a.complete);
+  assert_eq!(a.child_count, Some(2000));
+
+  // Paths below an indexed node are read from there.
+  let item = eng
+    .json_list_children(sid, meta.clone(), path(&["deep", "a", "1500"]), None, 10, JsonChildSort::Original)
+    .unwrap();
+  assert_eq!(item.items.len(), 3);
+  assert_eq!(item.items[1].preview, "\"item number 1500\"");
+  let tags = eng.json_node_summary(sid, meta.clone(), path(&["deep", "a", "1999", "tags"]), None, None).unwrap();
+  assert_eq!(tags.child_count, Some(2));
+
+  // A fresh engine on the same store loads the index instead of rebuilding it.
+  let eng = engine_with_sqlite(sqlite);
+  let (session, _p) = eng.open_file(&file).unwrap();
+  assert!(eng.build_json_node_index(&session.session_id, meta.clone()).unwrap().is_none());
+  let summary = eng
+    .json_node_summary(&session.session_id, meta, path(&["deep", "a"]), Some(1), Some(1))
+    .unwrap();
+  assert!(summary.complete);
+}
+
+#[test]
+fn indexed_search_reads_only_the_blocks_its_text_index_keeps_and_finds_what_scan_all_does() {
+  let dir = tempfile::tempdir().unwrap();
+  let file = dir.path().join("a.jsonl");
+  let lines: String = (0..1000)
+    .map(|i| match i {
+      10 => "{\"i\":10,\"name\":\"a needle here\"}\n".to_string(),
+      700 => "{\"i\":700,\"name\":\"NEEDLE\"}\r\n".to_string(),
+      _ => format!("{{\"i\":{i},\"name\":\"item {i}\"}}\n"),
+    })
+    .collect();
+  std::fs::write(&file, &lines).unwrap();
+  let sqlite = dir.path().join("t.sqlite");
+  let query = |text: &str, mode: SearchMode, case_sensitive: bool| SearchQuery {
+    text: text.into(),
+    mode,
+    case_sensitive,
+    max_hits: 100,
+    timeout_ms: None,
+    force_rescan: false,
+  };
+  let run = |eng: &CoreEngine, sid: &str, q: SearchQuery| {
+    let task = eng.search(sid, q).unwrap().task.unwrap();
+    for _ in 0..200 {
+      if eng.get_task(&task.id).unwrap().finished {
+        break;
+      }
+      thread::sleep(Duration::from_millis(10));
+    }
+    let hits = eng.search_task_hits_page(&task.id, None, 100).unwrap();
+    let ids: Vec<u64> = hits.records.iter().map(|r| r.id).collect();
+    (ids, eng.get_task(&task.id).unwrap())
+  };
+
+  let eng = engine_with_sqlite(sqlite.clone());
+  let (session, _p) = eng.open_file(&file).unwrap();
+  let sid = &session.session_id;
+  assert!(eng.search(sid, query("needle", SearchMode::Indexed, false)).is_err());
+  let task = eng.build_text_index(sid).unwrap().expect("index task");
+  assert_eq!(task.kind, TaskKind::IndexBuild);
+  for _ in 0..200 {
+    if eng.get_task(&task.id).unwrap().finished {
+      break;
+    }
+    thread::sleep(Duration::from_millis(10));
+  }
+  assert!(eng.build_text_index(sid).unwrap().is_none());
+
+  for (text, case_sensitive) in [("needle", false), ("NEEDLE", true), ("name:needle", false), ("item 99", false), ("m", false)] {
+    let (scanned, _) = run(&eng, sid, query(text, SearchMode::ScanAll, case_sensitive));
+    let (indexed, task) = run(&eng, sid, query(text, SearchMode::Indexed, case_sensitive));
+    assert_eq!(indexed, scanned, "{text}");
+    assert!(task.error.is_none());
+  }
+  // Two blocks of 64 lines hold the needle; the other lines aren't read.
+  let (ids, task) = run(&eng, sid, query("needle", SearchMode::Indexed, false));
+  assert_eq!(ids, [10, 700]);
+  assert_eq!(task.records_processed, 128);
+  let hits = eng.search_task_hits_page(&task.id, None, 10).unwrap();
+  assert_eq!(hits.records[1].preview, "{\"i\":700,\"name\":\"NEEDLE\"}");
+  assert_eq!(
+    hits.records[1].meta.as_ref().unwrap().byte_offset,
+    lines.find("{\"i\":700").unwrap() as u64
+  );
+
+  // The index is stored: a fresh engine uses it without a build, until the file changes.
+  let eng = engine_with_sqlite(sqlite);
+  let (session, _p) = eng.open_file(&file).unwrap();
+  assert!(eng.build_text_index(&session.session_id).unwrap().is_none());
+  assert_eq!(run(&eng, &session.session_id, query("needle", SearchMode::Indexed, false)).0, [10, 700]);
+  std::fs::write(&file, format!("{lines}{{\"name\":\"needle\"}}\n")).unwrap();
+  let (session, _p) = eng.open_file(&file).unwrap();
+  assert!(eng.search(&session.session_id, query("needle", SearchMode::Indexed, false)).is_err());
+
+  let parquet = dir.path().join("a.parquet");
+  duckdb::Connection::open_in_memory()
+    .unwrap()
+    .execute(
+      "COPY (SELECT 1 AS x) TO ? (FORMAT PARQUET);",
+      duckdb::params![parquet.to_string_lossy().to_string()],
+    )
+    .unwrap();
+  let (session, _p) = eng.open_file(&parquet).unwrap();
+  assert!(matches!(
+    eng.build_text_index(&session.session_id),
+    Err(CoreError::UnsupportedFormat(FileFormat::Parquet))
+  ));
+}
+
+#[test]
+fn scan_all_search_json_top_level_values() {
+  let dir = tempfile::tempdir().unwrap();
+  let file = dir.path().join("a.json");
+  std::fs::write(&file, "{\"x\":\"hello\"}\n{\"x\":\"world\"}\n\n {\"x\":[\"world\"]}\r\n\"world\"\n").unwrap();
+  let eng = engine_with_sqlite(dir.path().join("t.sqlite"));
+  let (session, _p) = eng.open_file(&file).unwrap();
+  let query = SearchQuery {
+    text: "world".into(),
+    mode: SearchMode::ScanAll,
+    case_sensitive: true,
+    max_hits: 100,
+    timeout_ms: None,
+    force_rescan: false,
+  };
+  let task_id = eng.search(&session.session_id, query).unwrap().task.unwrap().id;
+  for _ in 0..100 {
+    if eng.get_task(&task_id).unwrap().finished {
+      break;
+    }
+    thread::sleep(Duration::from_millis(10));
+  }
+  assert!(eng.get_task(&task_id).unwrap().error.is_none());
+
+  // Ids match paging, which reads each top-level value as a record.
+  let hits = eng.search_task_hits_page(&task_id, None, 10).unwrap();
+  assert_eq!(hits.records.iter().map(|r| r.id).collect::<Vec<_>>(), vec![1, 2, 3]);
+  let page = eng.page_at(&session.session_id, 2, 1).unwrap();
+  assert_eq!(page.records[0].preview, hits.records[1].preview);
+  let raw = eng.get_record_raw(&session.session_id, hits.records[1].meta.clone().unwrap()).unwrap();
+  assert_eq!(raw, "{\"x\":[\"world\"]}");
+
+  // A single root object is one record.
+  let object = dir.path().join("object.json");
+  std::fs::write(&object, "{\"a\": {\"x\": \"world\"}}").unwrap();
+  let (session, _p) = eng.open_file(&object).unwrap();
+  let task_id = eng
+    .search(
+      &session.session_id,
+      SearchQuery {
+        text: "world".into(),
+        mode: SearchMode::ScanAll,
+        case_sensitive: true,
+        max_hits: 100,
+        timeout_ms: None,
+        force_rescan: false,
+      },
+    )
+    .unwrap()
+    .task
+    .unwrap()
+    .id;
+  for _ in 0..100 {
+    if eng.get_task(&task_id).unwrap().finished {
+      break;
+    }
+    thread::sleep(Duration::from_millis(10));
+  }
+  let hits = eng.search_task_hits_page(&task_id, None, 10).unwrap();
+  assert_eq!(hits.records.len(), 1);
+  assert_eq!(hits.records[0].id, 0);
+}
+
+#[test]
+fn lenient_json_skips_comments_and_trailing_commas() {
+  let dir = tempfile::tempdir().unwrap();
+  let file = dir.path().join("config.json");
+  std::fs::write(
+    &file,
+    "// settings\n[\n  {\"name\": \"a\", /* \"]\" is no close */ \"tags\": [\"x\", \"y\",],},\n  \
+     {\"name\": \"b // kept\", \"n\": 2,}, // trailing\n  {\"name\": \"c\", \"deep\": {\"hit\": \"needle\",},},\n]\n",
+  )
+  .unwrap();
+  let eng = engine_with_sqlite(dir.path().join("t.sqlite"));
+  let (session, strict) = eng.open_file(&file).unwrap();
+  let sid = &session.session_id;
+  assert_eq!(strict.records[0].raw.as_deref(), Some("//"));
+
+  let page = eng.set_json_lenient(sid, true).unwrap();
+  assert_eq!(page.records.len(), 2);
+  let meta = page.records[0].meta.clone().unwrap();
+  let raw: serde_json::Value = serde_json::from_str(page.records[1].raw.as_deref().unwrap()).unwrap();
+  assert_eq!(raw, serde_json::json!({"name": "b // kept", "n": 2}));
+  let tags = eng
+    .json_list_children(sid, meta, vec![JsonPathSegment::Key("tags".into())], None, 10, JsonChildSort::Original)
+    .unwrap();
+  assert_eq!(tags.items.iter().map(|c| c.preview.as_str()).collect::<Vec<_>>(), vec!["\"x\"", "\"y\""]);
+  assert!(tags.reached_end);
+  let next = eng.next_page(sid, page.next_cursor.as_deref(), 2).unwrap();
+  assert_eq!(next.records.len(), 1);
+  assert!(next.reached_eof);
+  assert_eq!(eng.page_at(sid, 2, 1).unwrap().records[0].raw, next.records[0].raw);
+  assert_eq!(eng.count_records(sid).unwrap().total, Some(3));
+
+  let query = SearchQuery {
+    text: "needle".into(),
+    mode: SearchMode::ScanAll,
+    case_sensitive: true,
+    max_hits: 100,
+    timeout_ms: None,
+    force_rescan: false,
+  };
+  let task_id = eng.search(sid, query).unwrap().task.unwrap().id;
+  for _ in 0..100 {
+    if eng.get_task(&task_id).unwrap().finished {
+      break;
+    }
+    thread::sleep(Duration::from_millis(10));
+  }
+  assert!(eng.get_task(&task_id).unwrap().error.is_none());
+  let hits = eng.search_task_hits_page(&task_id, None, 10).unwrap();
+  assert_eq!(hits.records.iter().map(|r| r.id).collect::<Vec<_>>(), vec![2]);
+
+  // Strict again: the comment is a record once more.
+  let page = eng.set_json_lenient(sid, false).unwrap();
+  assert_eq!(page.records[0].raw.as_deref(), Some("//"));
+}
+
+#[test]
+fn write_pretty_record_streams_to_temp_file() {
+  let dir = tempfile::tempdir().unwrap();
+  let file = dir.path().join("a.json");
+  let record = serde_json::json!({
+    "name": "a, \"b\": [c]",
+    "empty": {},
+    "none": [],
+    "rows": [{"x": 1, "y": [true, null]}, -2.5e3, "z"],
+  });
+  std::fs::write(&file, format!("[ 7,\n{} ]", serde_json::to_string(&record).unwrap())).unwrap();
+  let eng = CoreEngine::new(CoreOptions {
+    spool_dir: Some(dir.path().join("spool")),
+    storage: StorageOptions {
+      sqlite_path: Some(dir.path().join("t.sqlite")),
+      ..StorageOptions::default()
+    },
+    ..CoreOptions::default()
+  })
+  .unwrap();
+  let (session, page) = eng.open_file(&file).unwrap();
+  let sid = &session.session_id;
+
+  let out = eng.write_pretty_record(sid, page.records[1].meta.clone().unwrap()).unwrap();
+  assert!(out.output_path.starts_with(dir.path().join("spool").to_str().unwrap()));
+  let pretty = std::fs::read_to_string(&out.output_path).unwrap();
+  assert_eq!(pretty, format!("{}\n", serde_json::to_string_pretty(&record).unwrap()));
+  let scalar = eng.write_pretty_record(sid, page.records[0].meta.clone().unwrap()).unwrap();
+  assert_eq!(std::fs::read_to_string(&scalar.output_path).unwrap(), "7\n");
+
+  eng.close_session(sid).unwrap();
+  assert!(!PathBuf::from(&out.output_path).exists());
+  assert!(!PathBuf::from(&scalar.output_path).exists());
+}
+
+#[test]
+fn export_json_subtree_minified() {
+  let dir = tempfile::tempdir().unwrap();
+  let file = dir.path().join("a.json");
+  std::fs::write(&file, "{\n  \"a\": {\n    \"s\": \"keep  these\\n spaces\",\n    \"b\": [ 1,\n      { \"c\" : null } ]\n  }\n}\n")
+    .unwrap();
+  let eng = engine_with_sqlite(dir.path().join("t.sqlite"));
+  let (session, p1) = eng.open_file(&file).unwrap();
+  let meta = p1.records[0].meta.clone().unwrap();
+  let export = |path: Vec<JsonPathSegment>, include_root: bool, children: Vec<JsonPathSegment>, format, name: &str| {
+    let out = dir.path().join(name);
+    eng
+      .export(
+        &session.session_id,
+        ExportRequest::JsonSubtree {
+          meta: meta.clone(),
+          path,
+          include_root,
+          children,
+          minify: true,
+        },
+        format,
+        &out,
+      )
+      .unwrap();
+    std::fs::read_to_string(out).unwrap()
+  };
+
+  let root = export(vec![], true, vec![], ExportFormat::Json, "root.json");
+  assert_eq!(root, r#"{"a":{"s":"keep  these\n spaces","b":[1,{"c":null}]}}"#);
+  let a = vec![JsonPathSegment::Key("a".into())];
+  let lines = export(a.clone(), false, vec![JsonPathSegment::Key("b".into())], ExportFormat::Jsonl, "b.jsonl");
+  assert_eq!(lines, "[1,{\"c\":null}]\n");
+  let children = vec![JsonPathSegment::Key("s".into()), JsonPathSegment::Key("b".into())];
+  let wrapped = export(a, false, children, ExportFormat::Json, "children.json");
+  assert_eq!(wrapped, r#"["keep  these\n spaces",[1,{"c":null}]]"#);
+}
+
+#[test]
+fn json_validate_record_reports_error_positions() {
+  let dir = tempfile::tempdir().unwrap();
+  let file = dir.path().join("a.jsonl");
+  std::fs::write(&file, "{\"a\": [1, 2]}\n{\"a\": 1,}\n{\"a\":1} x\n").unwrap();
+  let eng = engine_with_sqlite(dir.path().join("t.sqlite"));
+  let (session, p1) = eng.open_file(&file).unwrap();
+  let sid = &session.session_id;
+  let metas: Vec<_> = p1.records.iter().map(|r| r.meta.clone().unwrap()).collect();
+
+  let ok = eng.json_validate_record(sid, metas[0].clone()).unwrap();
+  assert!(ok.valid && ok.error.is_none());
+  let comma = eng.json_validate_record(sid, metas[1].clone()).unwrap().error.unwrap();
+  assert_eq!(comma.message, "key must be a string");
+  assert_eq!((comma.line, comma.column), (1, 9));
+  assert_eq!(comma.byte_offset, metas[1].byte_offset + 8);
+  // Context stays within the record.
+  assert_eq!((comma.context_before.as_str(), comma.context_after.as_str()), ("{\"a\": 1,", "}\n"));
+
+  // Content after the value within the record.
+  let next = eng.next_page(sid, p1.next_cursor.as_deref(), 2).unwrap();
+  let extra = eng.json_validate_record(sid, next.records[0].meta.clone().unwrap()).unwrap().error.unwrap();
+  assert_eq!(extra.message, "trailing characters");
+  assert_eq!(extra.context_after, "x\n");
+
+  // Errors on later lines of a multi-line record.
+  let json = dir.path().join("b.json");
+  std::fs::write(&json, "[\n{\"ok\": true},\n{\n  \"a\": [1,\n    2\n  }\n}\n]").unwrap();
+  let (session, page) = eng.open_file(&json).unwrap();
+  assert!(eng.json_validate_record(&session.session_id, page.records[0].meta.clone().unwrap()).unwrap().valid);
+  let meta = page.records[1].meta.clone().unwrap();
+  let err = eng.json_validate_record(&session.session_id, meta.clone()).unwrap().error.unwrap();
+  assert_eq!((err.line, err.column), (4, 3));
+  assert_eq!(std::fs::read(&json).unwrap()[err.byte_offset as usize], b'}');
+  assert!(err.context_before.ends_with("2\n  "));
+}
+
+#[test]
+fn json_base64_preview_decodes_payloads() {
+  use base64::Engine as _;
+  let dir = tempfile::tempdir().unwrap();
+  let png: Vec<u8> = b"\x89PNG\r\n\x1a\n\x00\x00\x00\rIHDR".iter().copied().chain(0..=255u8).collect();
+  let png_b64 = base64::engine::general_purpose::STANDARD.encode(&png);
+  let text_b64 = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode("héllo, wörld? ~~~ base64 text");
+  let body = format!(
+    "{{\"img\": \"{png_b64}\", \"text\": \"{text_b64}\", \"uri\": \"data:text/plain;base64,aGk=\", \"id\": \"ProductCategoryName\"}}\n"
+  );
+  let file = dir.path().join("a.jsonl");
+  std::fs::write(&file, &body).unwrap();
+  let eng = engine_with_sqlite(dir.path().join("t.sqlite"));
+  let (session, _) = eng.open_file(&file).unwrap();
+  let sid = &session.session_id;
+  let at = |key: &str| (body.find(&format!("\"{key}\": ")).unwrap() + key.len() + 4) as u64;
+
+  let img = eng.json_base64_preview(sid, at("img"), Some(8)).unwrap().unwrap();
+  assert_eq!(img.content, Base64Content::Binary);
+  assert_eq!(img.mime.as_deref(), Some("image/png"));
+  assert_eq!(img.decoded_len, png.len() as u64);
+  assert_eq!(img.preview, "89 50 4e 47 0d 0a 1a 0a");
+  assert!(img.truncated && !img.url_safe);
+
+  let text = eng.json_base64_preview(sid, at("text"), None).unwrap().unwrap();
+  assert_eq!(text.content, Base64Content::Text);
+  assert_eq!(text.preview, "héllo, wörld? ~~~ base64 text");
+  assert!(text.url_safe && !text.truncated && text.mime.is_none());
+
+  let uri = eng.json_base64_preview(sid, at("uri"), None).unwrap().unwrap();
+  assert_eq!((uri.mime.as_deref(), uri.preview.as_str(), uri.decoded_len), (Some("text/plain"), "hi", 2));
+  assert!(eng.json_base64_preview(sid, at("id"), None).unwrap().is_none());
+  assert!(eng.json_base64_preview(sid, 0, None).unwrap().is_none());
+
+  let out = dir.path().join("img.png");
+  let saved = eng.save_json_base64(sid, at("img"), &out).unwrap();
+  assert_eq!(saved.records_written, 1);
+  assert_eq!(std::fs::read(&out).unwrap(), png);
+  assert!(eng.save_json_base64(sid, at("id"), dir.path().join("id.bin")).is_err());
+  assert!(!dir.path().join("id.bin").exists());
+}
+
+#[test]
+fn export_json_subtree_array_of_objects_to_csv() {
+  let dir = tempfile::tempdir().unwrap();
+  let file = dir.path().join("a.json");
+  std::fs::write(
+    &file,
+    "{\"rows\": [\n  {\"id\": 1, \"name\": \"a, b\"},\n  {\"name\": \"say \\\"hi\\\"\", \"ok\": true},\n  {\"id\": 3, \"ok\": null}\n], \"bad\": [{\"x\": {\"y\": 1}}], \"broken\": [{\"id\": 1 \"x\": 2}]}",
+  )
+  .unwrap();
+  let eng = engine_with_sqlite(dir.path().join("t.sqlite"));
+  let (session, page) = eng.open_file(&file).unwrap();
+  let meta = page.records[0].meta.clone().unwrap();
+  let export = |key: &str, children: Vec<JsonPathSegment>, name: &str| {
+    let out = dir.path().join(name);
+    let request = ExportRequest::JsonSubtree {
+      meta: meta.clone(),
+      path: vec![JsonPathSegment::Key(key.into())],
+      include_root: children.is_empty(),
+      children,
+      minify: false,
+    };
+    eng.export(&session.session_id, request, ExportFormat::Csv, &out)
+      .map(|res| (res.records_written, std::fs::read_to_string(out).unwrap()))
+  };
+
+  let (rows, csv) = export("rows", vec![], "all.csv").unwrap();
+  assert_eq!(rows, 3);
+  assert_eq!(csv, "id,name,ok\n1,\"a, b\",\n,\"say \"\"hi\"\"\",true\n3,,\n");
+  let picked = vec![JsonPathSegment::Index(0), JsonPathSegment::Index(2)];
+  let (rows, csv) = export("rows", picked, "picked.csv").unwrap();
+  assert_eq!(rows, 2);
+  assert_eq!(csv, "id,name,ok\n1,\"a, b\",\n3,,\n");
+
+  assert!(matches!(export("bad", vec![], "bad.csv"), Err(CoreError::InvalidArg(_))));
+  assert!(matches!(export("broken", vec![], "broken.csv"), Err(CoreError::InvalidArg(_))));
+}